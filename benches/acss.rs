@@ -1,4 +1,7 @@
-use acss::acss::{DoubleSecret, Keypair};
+use acss::{
+	acss::{DealingMode, DoubleSecret, Keypair},
+	params::ThresholdParams,
+};
 use ark_ff::UniformRand;
 use criterion::{
 	black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
@@ -16,11 +19,15 @@ fn acss_reshare_with_single_threaded_recovery_tinybls377(
 	committee_keys: &[KeypairVT<TinyBLS377>],
 	t: u8,
 ) {
+	let params = ThresholdParams::for_committee(committee_public, t).unwrap();
 	let resharing =
-		double_secret.reshare(committee_public, t, &mut OsRng).unwrap();
-	committee_keys.iter().enumerate().for_each(|(idx, kp)| {
+		double_secret.reshare(committee_public, params, OsRng).unwrap();
+	// each member recovers from the single dealer
+	let single_dealer = ThresholdParams::new(1, 1).unwrap();
+	committee_keys.iter().for_each(|kp| {
+		let pok = resharing.for_recipient(&kp.public).unwrap();
 		let sk = Keypair(kp.clone());
-		sk.recover(resharing[idx].1.clone(), t).unwrap();
+		sk.recover(core::slice::from_ref(pok), single_dealer).unwrap();
 	});
 }
 
@@ -45,9 +52,9 @@ fn acss(c: &mut Criterion) {
 			|b, &size| {
 				b.iter(|| {
 					acss_reshare_with_single_threaded_recovery_tinybls377(
-						black_box(DoubleSecret(s1, s2)),
-						black_box(&initial_committee_public_keys.clone()),
-						black_box(&keys.clone()),
+						black_box(DoubleSecret::new(s1, s2)),
+						black_box(&initial_committee_public_keys),
+						black_box(&keys),
 						black_box(size),
 					)
 				});
@@ -57,5 +64,39 @@ fn acss(c: &mut Criterion) {
 	group.finish();
 }
 
-criterion_group!(benches, acss);
+/// compares the dealer's cost in each dealing mode
+fn dealing(c: &mut Criterion) {
+	let mut group = c.benchmark_group("dealing");
+	for size in [10, 50, 100].iter() {
+		let committee: Vec<PublicKey<TinyBLS377>> = (0..*size)
+			.map(|_| KeypairVT::<TinyBLS377>::generate(&mut OsRng).public)
+			.collect();
+		let s1 = <TinyBLS377 as EngineBLS>::Scalar::rand(&mut OsRng);
+		let s2 = <TinyBLS377 as EngineBLS>::Scalar::rand(&mut OsRng);
+
+		for mode in [DealingMode::PerRecipient, DealingMode::SharedEphemeral] {
+			group.bench_with_input(
+				BenchmarkId::new(format!("{:?}", mode), size),
+				size,
+				|b, &size| {
+					b.iter(|| {
+						DoubleSecret::new(s1, s2)
+							.reshare_with_mode(
+								black_box(&committee),
+								black_box(
+									ThresholdParams::new(size, size).unwrap(),
+								),
+								mode,
+								OsRng,
+							)
+							.unwrap()
+					});
+				},
+			);
+		}
+	}
+	group.finish();
+}
+
+criterion_group!(benches, acss, dealing);
 criterion_main!(benches);
@@ -0,0 +1,262 @@
+use acss::{
+	acss::{DoubleSecret, Keypair},
+	keys::PublicKey as EncryptionKey,
+	params::ThresholdParams,
+	proofs::{
+		hashed_el_gamal::{Ciphertext, HashedElGamal},
+		hashed_el_gamal_sigma::BatchPoK,
+		ipa::{AggregateProof, VerificationEquation},
+	},
+};
+use ark_ff::UniformRand;
+use ark_serialize::CanonicalSerialize;
+use criterion::{
+	black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+	Throughput,
+};
+use rand_core::OsRng;
+use w3f_bls::{EngineBLS, KeypairVT, TinyBLS377};
+
+type E = TinyBLS377;
+type G = <E as EngineBLS>::PublicKeyGroup;
+type Scalar = <E as EngineBLS>::Scalar;
+
+/// the committee sizes each phase is measured at
+const SIZES: [u8; 4] = [10, 50, 100, 255];
+
+/// the encryption keys of a committee of `size` members
+fn committee(size: u8) -> Vec<EncryptionKey<G>> {
+	(0..size)
+		.map(|_| EncryptionKey(KeypairVT::<E>::generate(&mut OsRng).public.0))
+		.collect()
+}
+
+/// a share and blinding share for each of `size` recipients, encoded as
+/// messages
+fn messages(size: u8) -> Vec<([Scalar; 2], [[u8; 32]; 2])> {
+	(0..size)
+		.map(|_| {
+			let scalars = [Scalar::rand(&mut OsRng), Scalar::rand(&mut OsRng)];
+			let mut bytes = [[0u8; 32]; 2];
+			for (scalar, out) in scalars.iter().zip(bytes.iter_mut()) {
+				scalar.serialize_compressed(&mut out[..]).unwrap();
+			}
+			(scalars, bytes)
+		})
+		.collect()
+}
+
+/// one proof per recipient, as a dealer produces for a committee
+fn poks(
+	committee: &[EncryptionKey<G>],
+	messages: &[([Scalar; 2], [[u8; 32]; 2])],
+) -> Vec<BatchPoK<G>> {
+	committee
+		.iter()
+		.zip(messages)
+		.map(|(pk, (scalars, _))| BatchPoK::prove(scalars, *pk, OsRng).unwrap())
+		.collect()
+}
+
+/// encrypting the share and blinding share of every recipient
+fn encryption(c: &mut Criterion) {
+	let mut group = c.benchmark_group("phases/encryption");
+	for size in SIZES {
+		let committee = committee(size);
+		let messages = messages(size);
+		group.throughput(Throughput::Elements(size.into()));
+		group.bench_with_input(
+			BenchmarkId::from_parameter(size),
+			&(committee, messages),
+			|b, (committee, messages)| {
+				b.iter(|| {
+					for (pk, (_, bytes)) in committee.iter().zip(messages) {
+						for message in bytes {
+							black_box(
+								HashedElGamal::encrypt(*message, *pk, OsRng)
+									.unwrap(),
+							);
+						}
+					}
+				})
+			},
+		);
+	}
+	group.finish();
+}
+
+/// proving knowledge of the plaintexts of every recipient
+fn proof_generation(c: &mut Criterion) {
+	let mut group = c.benchmark_group("phases/prove");
+	for size in SIZES {
+		let committee = committee(size);
+		let messages = messages(size);
+		group.throughput(Throughput::Elements(size.into()));
+		group.bench_with_input(
+			BenchmarkId::from_parameter(size),
+			&(committee, messages),
+			|b, (committee, messages)| {
+				b.iter(|| poks(black_box(committee), black_box(messages)))
+			},
+		);
+	}
+	group.finish();
+}
+
+/// verifying the proof of every recipient, one at a time and in a batch
+fn verification(c: &mut Criterion) {
+	let mut group = c.benchmark_group("phases/verify");
+	for size in SIZES {
+		let committee = committee(size);
+		let poks = poks(&committee, &messages(size));
+		let proofs: Vec<(EncryptionKey<G>, &BatchPoK<G>)> =
+			committee.iter().copied().zip(&poks).collect();
+		group.throughput(Throughput::Elements(size.into()));
+		group.bench_with_input(
+			BenchmarkId::new("single", size),
+			&proofs,
+			|b, proofs| {
+				b.iter(|| {
+					proofs.iter().all(|(pk, pok)| black_box(pok).verify(*pk))
+				})
+			},
+		);
+		group.bench_with_input(
+			BenchmarkId::new("batch", size),
+			&proofs,
+			|b, proofs| {
+				b.iter(|| BatchPoK::verify_batch(black_box(proofs), OsRng))
+			},
+		);
+	}
+	group.finish();
+}
+
+/// summing the ciphertexts one recipient receives from every dealer
+fn aggregation(c: &mut Criterion) {
+	let mut group = c.benchmark_group("phases/aggregate");
+	for size in SIZES {
+		let pk = committee(1)[0];
+		let ciphertexts: Vec<Ciphertext<G>> = (0..size)
+			.map(|i| HashedElGamal::encrypt([i; 32], pk, OsRng).unwrap())
+			.collect();
+		group.throughput(Throughput::Elements(size.into()));
+		group.bench_with_input(
+			BenchmarkId::from_parameter(size),
+			&ciphertexts,
+			|b, ciphertexts| {
+				b.iter(|| Ciphertext::aggregate(black_box(ciphertexts).iter()))
+			},
+		);
+	}
+	group.finish();
+}
+
+/// recovering a share from one dealing per dealer
+fn recovery(c: &mut Criterion) {
+	let mut group = c.benchmark_group("phases/recover");
+	for size in SIZES {
+		let member = Keypair(KeypairVT::<E>::generate(&mut OsRng));
+		// only the member's own PoK is read, so each dealer deals to it alone
+		let alone = ThresholdParams::new(1, 1).unwrap();
+		let poks: Vec<BatchPoK<G>> = (0..size)
+			.map(|_| {
+				let secret = DoubleSecret::<E>::new(
+					Scalar::rand(&mut OsRng),
+					Scalar::rand(&mut OsRng),
+				);
+				let resharing =
+					secret.reshare(&[member.0.public], alone, OsRng).unwrap();
+				resharing.for_recipient(&member.0.public).unwrap().clone()
+			})
+			.collect();
+		let params = ThresholdParams::new(size, size).unwrap();
+		group.throughput(Throughput::Elements(size.into()));
+		group.bench_with_input(
+			BenchmarkId::from_parameter(size),
+			&poks,
+			|b, poks| {
+				b.iter(|| member.recover(black_box(poks), params).unwrap())
+			},
+		);
+	}
+	group.finish();
+}
+
+/// verifying n proofs with the linear aggregate (`verify_batch`, which needs
+/// every response) and with an `AggregateProof` (which replaces them)
+fn aggregate_proofs(c: &mut Criterion) {
+	let mut group = c.benchmark_group("phases/aggregate-proof");
+	group.sample_size(10);
+	for size in [255usize, 1000] {
+		// n is not bounded by the committee size here, proofs are drawn to
+		// independent keys
+		let committee: Vec<EncryptionKey<G>> = (0..size)
+			.map(|_| {
+				EncryptionKey(KeypairVT::<E>::generate(&mut OsRng).public.0)
+			})
+			.collect();
+		let poks: Vec<BatchPoK<G>> = committee
+			.iter()
+			.map(|pk| {
+				let scalars =
+					[Scalar::rand(&mut OsRng), Scalar::rand(&mut OsRng)];
+				BatchPoK::prove(&scalars, *pk, OsRng).unwrap()
+			})
+			.collect();
+		let proofs: Vec<(EncryptionKey<G>, &BatchPoK<G>)> =
+			committee.iter().copied().zip(&poks).collect();
+		let (equations, proof) = BatchPoK::aggregate(&proofs).unwrap();
+		println!(
+			"phases/aggregate-proof/{}: responses {} bytes, ipa {} bytes",
+			size,
+			size * Scalar::from(0u8).compressed_size(),
+			proof.compressed_size(),
+		);
+
+		group.throughput(Throughput::Elements(size as u64));
+		group.bench_with_input(
+			BenchmarkId::new("linear", size),
+			&proofs,
+			|b, proofs| {
+				b.iter(|| BatchPoK::verify_batch(black_box(proofs), OsRng))
+			},
+		);
+		group.bench_with_input(
+			BenchmarkId::new("ipa", size),
+			&(proofs.clone(), proof),
+			|b, (proofs, proof)| {
+				b.iter(|| {
+					let equations: Option<Vec<_>> = proofs
+						.iter()
+						.map(|(pk, pok)| VerificationEquation::of(*pk, pok))
+						.collect();
+					proof.verify(&equations.unwrap())
+				})
+			},
+		);
+		group.bench_with_input(
+			BenchmarkId::new("ipa-prove", size),
+			&equations,
+			|b, equations| {
+				let responses: Vec<Scalar> =
+					poks.iter().map(|pok| pok.z).collect();
+				b.iter(|| {
+					AggregateProof::prove(black_box(equations), &responses)
+				})
+			},
+		);
+	}
+	group.finish();
+}
+
+criterion_group!(
+	benches,
+	encryption,
+	proof_generation,
+	verification,
+	aggregation,
+	aggregate_proofs,
+	recovery
+);
+criterion_main!(benches);
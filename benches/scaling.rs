@@ -0,0 +1,108 @@
+use acss::{
+	acss::{DoubleSecret, Keypair},
+	params::ThresholdParams,
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+};
+use ark_ff::UniformRand;
+use criterion::{
+	black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+	Throughput,
+};
+use rand_core::OsRng;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use w3f_bls::{EngineBLS, KeypairVT, PublicKey, TinyBLS377};
+
+type E = TinyBLS377;
+type G = <E as EngineBLS>::PublicKeyGroup;
+type Scalar = <E as EngineBLS>::Scalar;
+
+/// the committee sizes to scale across, up to the largest committee a share
+/// index can address
+const SIZES: [u8; 3] = [100, 175, 255];
+
+/// a pool for each power of two number of threads up to the available
+/// parallelism, the first running on one thread as the sequential baseline
+fn pools() -> Vec<(usize, ThreadPool)> {
+	let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+	core::iter::successors(Some(1), |threads| Some(threads * 2))
+		.take_while(|threads| *threads <= available)
+		.map(|threads| {
+			let pool =
+				ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+			(threads, pool)
+		})
+		.collect()
+}
+
+fn secret() -> DoubleSecret<E> {
+	DoubleSecret::new(Scalar::rand(&mut OsRng), Scalar::rand(&mut OsRng))
+}
+
+/// a dealer resharing to a committee of n, per thread count, in shares/sec
+fn dealing(c: &mut Criterion) {
+	let pools = pools();
+	let mut group = c.benchmark_group("scaling/dealing");
+	group.sample_size(10);
+	for size in SIZES {
+		let committee: Vec<PublicKey<E>> = (0..size)
+			.map(|_| KeypairVT::<E>::generate(&mut OsRng).public)
+			.collect();
+		let params = ThresholdParams::new(size, size).unwrap();
+		group.throughput(Throughput::Elements(size.into()));
+		for (threads, pool) in &pools {
+			group.bench_with_input(
+				BenchmarkId::new(format!("{threads}-threads"), size),
+				&committee,
+				|b, committee| {
+					b.iter(|| {
+						pool.install(|| {
+							secret()
+								.reshare(black_box(committee), params, OsRng)
+								.unwrap()
+						})
+					})
+				},
+			);
+		}
+	}
+	group.finish();
+}
+
+/// a member recovering its share from n dealers, per thread count, in
+/// shares/sec
+fn recovery(c: &mut Criterion) {
+	let pools = pools();
+	let mut group = c.benchmark_group("scaling/recovery");
+	group.sample_size(10);
+	for size in SIZES {
+		let member = Keypair(KeypairVT::<E>::generate(&mut OsRng));
+		// only the member's own PoK is read, so each dealer deals to it alone
+		let alone = ThresholdParams::new(1, 1).unwrap();
+		let poks: Vec<BatchPoK<G>> = (0..size)
+			.map(|_| {
+				let resharing =
+					secret().reshare(&[member.0.public], alone, OsRng).unwrap();
+				resharing.for_recipient(&member.0.public).unwrap().clone()
+			})
+			.collect();
+		let params = ThresholdParams::new(size, size).unwrap();
+		group.throughput(Throughput::Elements(size.into()));
+		for (threads, pool) in &pools {
+			group.bench_with_input(
+				BenchmarkId::new(format!("{threads}-threads"), size),
+				&poks,
+				|b, poks| {
+					b.iter(|| {
+						pool.install(|| {
+							member.recover(black_box(poks), params).unwrap()
+						})
+					})
+				},
+			);
+		}
+	}
+	group.finish();
+}
+
+criterion_group!(benches, dealing, recovery);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+use acss::{
+	acss::DoubleSecret,
+	params::ThresholdParams,
+	transcript::{decode_compact, encode_compact},
+};
+use ark_ff::UniformRand;
+use ark_serialize::CanonicalSerialize;
+use criterion::{
+	black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+	Throughput,
+};
+use rand_core::OsRng;
+use w3f_bls::{EngineBLS, KeypairVT, PublicKey, TinyBLS377};
+
+/// measures encoding and decoding of the compact transcript encoding
+/// the throughput is set to the encoded size so that criterion reports it, and
+/// the bytes-per-member of the canonical and compact encodings are printed
+fn transcript(c: &mut Criterion) {
+	let mut group = c.benchmark_group("transcript");
+	for size in [3, 10, 50, 100, 255].iter() {
+		let committee: Vec<PublicKey<TinyBLS377>> = (0..*size)
+			.map(|_| KeypairVT::<TinyBLS377>::generate(&mut OsRng).public)
+			.collect();
+		let s1 = <TinyBLS377 as EngineBLS>::Scalar::rand(&mut OsRng);
+		let s2 = <TinyBLS377 as EngineBLS>::Scalar::rand(&mut OsRng);
+		let resharing = DoubleSecret::<TinyBLS377>::new(s1, s2)
+			.reshare(
+				&committee,
+				ThresholdParams::new(*size, *size).unwrap(),
+				OsRng,
+			)
+			.unwrap();
+
+		let mut canonical = Vec::new();
+		for (_, pk, pok) in &resharing {
+			pk.serialize_compressed(&mut canonical).unwrap();
+			pok.serialize_compressed(&mut canonical).unwrap();
+		}
+		let compact =
+			encode_compact::<TinyBLS377>(resharing.dealings()).unwrap();
+		println!(
+			"transcript/{}: canonical {} bytes/member, compact {} bytes/member",
+			size,
+			canonical.len() / *size as usize,
+			compact.len() / *size as usize,
+		);
+
+		group.throughput(Throughput::Bytes(compact.len() as u64));
+		group.bench_with_input(
+			BenchmarkId::new("encode_compact", size),
+			resharing.dealings(),
+			|b, resharing| {
+				b.iter(|| encode_compact::<TinyBLS377>(black_box(resharing)))
+			},
+		);
+		group.bench_with_input(
+			BenchmarkId::new("decode_compact", size),
+			&compact,
+			|b, compact| {
+				b.iter(|| decode_compact::<TinyBLS377>(black_box(compact)))
+			},
+		);
+	}
+	group.finish();
+}
+
+criterion_group!(benches, transcript);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Batched proofs of knowledge are parsed from untrusted peers and then
+//! verified. Neither step may panic, whatever the bytes.
+
+#![no_main]
+
+use acss::{keys::PublicKey, proofs::hashed_el_gamal_sigma::BatchPoK};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use libfuzzer_sys::fuzz_target;
+use w3f_bls::{EngineBLS, TinyBLS377};
+
+type G = <TinyBLS377 as EngineBLS>::PublicKeyGroup;
+
+fuzz_target!(|data: &[u8]| {
+	let mut reader = data;
+	if let Ok(pok) = BatchPoK::<G>::deserialize_compressed(&mut reader) {
+		let mut bytes = Vec::new();
+		pok.serialize_compressed(&mut bytes).unwrap();
+		assert_eq!(bytes, &data[..data.len() - reader.len()]);
+
+		// verify against each of the points carried by the proof
+		for pk in [pok.s, pok.t, pok.commitment] {
+			let _ = pok.verify(PublicKey(pk));
+		}
+	}
+});
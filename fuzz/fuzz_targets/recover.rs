@@ -0,0 +1,56 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Recovery runs on proofs received from untrusted dealers. The input is a
+//! threshold byte followed by a compact transcript, whose proofs are all
+//! recovered by a fixed recipient, in order and with explicit indices.
+
+#![no_main]
+
+use acss::{
+	acss::HighThresholdACSS,
+	keys::{SecretKey, ShareIndex},
+	params::ThresholdParams,
+	transcript::decode_compact,
+};
+use libfuzzer_sys::fuzz_target;
+use w3f_bls::{EngineBLS, TinyBLS377};
+
+type E = TinyBLS377;
+
+fuzz_target!(|data: &[u8]| {
+	let Some((t, transcript)) = data.split_first() else {
+		return;
+	};
+	let Ok(resharing) = decode_compact::<E>(transcript) else {
+		return;
+	};
+	let poks: Vec<_> = resharing.into_iter().map(|(_, pok)| pok).collect();
+	let Ok(params) = ThresholdParams::for_committee(&poks, *t) else {
+		return;
+	};
+
+	let sk = SecretKey::new(<E as EngineBLS>::Scalar::from(7u64));
+	let _ = HighThresholdACSS::<E>::recover(&sk, &poks, params);
+
+	// indices taken from the transcript bytes, so they may repeat or be out
+	// of range
+	let indexed: Vec<_> = poks
+		.into_iter()
+		.zip(transcript.iter().rev())
+		.filter_map(|(p, i)| Some((ShareIndex::new(*i)?, p)))
+		.collect();
+	let _ = HighThresholdACSS::<E>::recover_indexed(&sk, &indexed, params);
+});
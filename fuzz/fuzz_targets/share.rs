@@ -0,0 +1,41 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Shares and the public keys they are dealt to are parsed from untrusted
+//! peers: scalars, share bytes recovered by decryption, and committee keys.
+
+#![no_main]
+
+use acss::{context::VerificationContext, proofs::hashed_el_gamal::decode_scalar};
+use ark_serialize::CanonicalDeserialize;
+use libfuzzer_sys::fuzz_target;
+use w3f_bls::{DoublePublicKey, EngineBLS, PublicKey, TinyBLS377};
+
+type E = TinyBLS377;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = <E as EngineBLS>::Scalar::deserialize_compressed(data);
+	if let Ok(bytes) = <[u8; 32]>::try_from(data) {
+		let _ = decode_scalar::<<E as EngineBLS>::Scalar>(&bytes);
+	}
+	let _ = PublicKey::<E>::deserialize_compressed(data);
+	let _ = DoublePublicKey::<E>::deserialize_compressed(data);
+
+	// a committee of keys, split on a separator byte
+	let keys: Vec<&[u8]> = data.split(|b| *b == 0xff).collect();
+	if let Ok(context) = VerificationContext::<E>::from_bytes(&keys) {
+		assert_eq!(context.len(), keys.len());
+	}
+});
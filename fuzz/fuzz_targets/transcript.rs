@@ -0,0 +1,33 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Resharings are relayed as compact transcripts. Anything that decodes must
+//! re-encode to a transcript that decodes to the same resharing.
+
+#![no_main]
+
+use acss::transcript::{decode_compact, encode_compact};
+use libfuzzer_sys::fuzz_target;
+use w3f_bls::TinyBLS377;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(resharing) = decode_compact::<TinyBLS377>(data) {
+		// the shared c1 flag is recomputed, so the bytes may differ from the
+		// input, but encoding is stable from then on
+		let bytes = encode_compact::<TinyBLS377>(&resharing).unwrap();
+		let decoded = decode_compact::<TinyBLS377>(&bytes).unwrap();
+		assert_eq!(encode_compact::<TinyBLS377>(&decoded).unwrap(), bytes);
+	}
+});
@@ -14,190 +14,1078 @@
  * limitations under the License.
  */
 
-use crate::proofs::{
-	hashed_el_gamal::HashedElGamal, hashed_el_gamal_sigma::BatchPoK,
+#[cfg(all(feature = "mlock", unix))]
+use crate::mlock::Locked;
+#[cfg(feature = "getrandom")]
+use crate::rng::OsRng;
+use crate::{
+	builder::ReshareBuilder,
+	commitment::{CommittedResharing, PolynomialCommitment},
+	error::SerializationError,
+	keys::{
+		committee_keys, CommitteeKey, PublicKey as EncryptionKey, SecretKey,
+		ShareIndex,
+	},
+	progress::Monitor,
+	proofs::{
+		challenge::Binding,
+		hashed_el_gamal::{
+			decode_scalar, DetectionTag, Ephemeral, HashedElGamal,
+		},
+		hashed_el_gamal_sigma::BatchPoK,
+	},
+	resharing::Resharing,
+	rng::SecureRng,
+	trace::{timed_span, trace_event},
+	wipe,
+};
+pub use crate::{
+	error::AcssError,
+	lagrange::{interpolate, lagrange_at_zero},
+	params::ThresholdParams,
 };
-use ark_ec::{CurveGroup, Group};
 use ark_ff::{Field, UniformRand};
 use ark_poly::{
 	polynomial::univariate::DensePolynomial, DenseUVPolynomial, Polynomial,
 };
-use ark_serialize::CanonicalDeserialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{
-	collections::BTreeMap,
-	marker::PhantomData,
-	rand::{CryptoRng, Rng},
-	vec::Vec,
-	One, Zero,
+	borrow::Borrow, collections::BTreeMap, marker::PhantomData, vec::Vec, Zero,
 };
+use core::fmt;
 use w3f_bls::{
 	DoublePublicKey, DoublePublicKeyScheme, EngineBLS, KeypairVT, PublicKey,
 	SecretKeyVT,
 };
+use zeroize::Zeroize;
+#[cfg(feature = "zeroize")]
+use zeroize::ZeroizeOnDrop;
 
-/// errors for the ACSS algorithm
-#[derive(Debug, PartialEq)]
-pub enum ACSSError {
-	/// the committee was invalid (either empty or buffer overflow)
-	InvalidCommittee,
-	/// the ciphertext could not be decrypted
-	InvalidCiphertext,
-	/// the commitment could not be verified
-	InvalidCommitment,
-	/// the proof could not be verified
-	InvalidProof,
-	/// insufficiently many valid proofs of knowledge were provided
-	InsufficientValidPoK,
-	/// a proof of knowledge could not be generated
-	InvalidMessage,
+/// how a dealer samples the ephemeral keys used to encrypt shares
+/// the default is `PerRecipient`, so transcripts are unchanged unless a dealer
+/// opts in to `SharedEphemeral`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DealingMode {
+	/// one ephemeral key for all shares and another for all blinding shares,
+	/// shared across the committee (multi-recipient El Gamal). This halves the
+	/// dealer's encryption work since c1 is only computed once per slot.
+	SharedEphemeral,
+	/// a fresh ephemeral key for every ciphertext
+	#[default]
+	PerRecipient,
 }
 
 /// a double secret holds two elements of the scalar field
-pub struct DoubleSecret<E: EngineBLS>(pub E::Scalar, pub E::Scalar);
+/// with the `zeroize` feature, both elements are wiped when it is dropped
+///
+/// the elements are only reachable through `expose_secret` and
+/// `expose_blinding_secret`, so no caller can move them out, which would not
+/// compile once the `zeroize` feature adds a `Drop` impl
+pub struct DoubleSecret<E: EngineBLS>(
+	pub(crate) E::Scalar,
+	pub(crate) E::Scalar,
+);
+
+impl<E: EngineBLS> Zeroize for DoubleSecret<E> {
+	fn zeroize(&mut self) {
+		self.0.zeroize();
+		self.1.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: EngineBLS> Drop for DoubleSecret<E> {
+	fn drop(&mut self) {
+		self.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: EngineBLS> ZeroizeOnDrop for DoubleSecret<E> {}
+
+impl<E: EngineBLS> fmt::Debug for DoubleSecret<E> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("DoubleSecret(<redacted>)")
+	}
+}
 
 impl<E: EngineBLS> DoubleSecret<E> {
+	/// a double secret from a secret and its blinding secret
+	pub fn new(secret: E::Scalar, blinding_secret: E::Scalar) -> Self {
+		Self(secret, blinding_secret)
+	}
+
+	/// the secret
+	pub fn expose_secret(&self) -> &E::Scalar {
+		&self.0
+	}
+
+	/// the blinding secret
+	pub fn expose_blinding_secret(&self) -> &E::Scalar {
+		&self.1
+	}
+
+	/// move the secret into page-locked memory, wiping this copy
+	#[cfg(all(feature = "mlock", unix))]
+	pub fn into_locked(mut self) -> Result<Locked<Self>, AcssError> {
+		let locked = Locked::new(Self(self.0, self.1));
+		self.zeroize();
+		locked
+	}
+
+	/// a blinded copy of the double secret, both elements multiplied by `r`
+	/// the copy can be handed to a co-signer or escrow service without
+	/// revealing the secret, and `unblind` with the same factor recovers it.
+	/// Its commitment is `blind_commitment` of the commitment of the secret.
+	///
+	/// fails with `InvalidBlindingFactor` if `r` is zero
+	///
+	/// * `r`: The blinding factor
+	pub fn blind(&self, r: &E::Scalar) -> Result<Self, AcssError> {
+		if r.is_zero() {
+			return Err(AcssError::InvalidBlindingFactor);
+		}
+		Ok(Self(self.0 * r, self.1 * r))
+	}
+
+	/// remove the blinding factor `r` from a blinded double secret
+	///
+	/// fails with `InvalidBlindingFactor` if `r` is zero
+	///
+	/// * `r`: The blinding factor the secret was blinded with
+	pub fn unblind(&self, r: &E::Scalar) -> Result<Self, AcssError> {
+		let mut inverse =
+			r.inverse().ok_or(AcssError::InvalidBlindingFactor)?;
+		let unblinded = Self(self.0 * inverse, self.1 * inverse);
+		wipe(&mut inverse);
+		Ok(unblinded)
+	}
+
+	/// start building a resharing of the double secret
+	pub fn builder(&self) -> ReshareBuilder<'_, E> {
+		ReshareBuilder::new(self)
+	}
+
 	/// create a resharing of a double secret with a committee
 	///
 	/// * `committee`: The committee to reshare to
-	/// * `t`: the threshold (1 < t < committee_size)
+	/// * `params`: the threshold parameters, where n is the committee size
 	/// * `rng`: a CSPRNG
-	pub fn reshare<R: Rng + CryptoRng>(
+	pub fn reshare<R: SecureRng, K: CommitteeKey<E>>(
 		&self,
-		committee: &[PublicKey<E>],
-		t: u8,
+		committee: &[K],
+		params: ThresholdParams,
+		mut rng: R,
+	) -> Result<Resharing<E>, AcssError> {
+		HighThresholdACSS::<E>::reshare(
+			self.0, self.1, committee, params, &mut rng,
+		)
+	}
+
+	/// create a resharing of a double secret with a committee, drawing
+	/// randomness from the operating system
+	///
+	/// * `committee`: The committee to reshare to
+	/// * `params`: the threshold parameters, where n is the committee size
+	#[cfg(feature = "getrandom")]
+	pub fn reshare_with_os_rng<K: CommitteeKey<E>>(
+		&self,
+		committee: &[K],
+		params: ThresholdParams,
+	) -> Result<Resharing<E>, AcssError> {
+		self.reshare(committee, params, OsRng)
+	}
+
+	/// create a resharing of a double secret with a committee, sampling the
+	/// ephemeral keys according to `mode`
+	///
+	/// * `committee`: The committee to reshare to
+	/// * `params`: the threshold parameters, where n is the committee size
+	/// * `mode`: how to sample ephemeral keys
+	/// * `rng`: a CSPRNG
+	pub fn reshare_with_mode<R: SecureRng, K: CommitteeKey<E>>(
+		&self,
+		committee: &[K],
+		params: ThresholdParams,
+		mode: DealingMode,
+		mut rng: R,
+	) -> Result<Resharing<E>, AcssError> {
+		HighThresholdACSS::<E>::reshare_with_mode(
+			self.0, self.1, committee, params, mode, &mut rng,
+		)
+	}
+
+	/// create a resharing of a double secret with a committee, handing each
+	/// recipient's entry to `emit` as it is dealt (see
+	/// `HighThresholdACSS::reshare_streaming`)
+	///
+	/// * `committee`: The committee to reshare to
+	/// * `params`: the threshold parameters, where n is the committee size
+	/// * `mode`: how to sample ephemeral keys
+	/// * `rng`: a CSPRNG
+	/// * `emit`: Called with the entry of each recipient in turn
+	pub fn reshare_streaming<R, K, F>(
+		&self,
+		committee: &[K],
+		params: ThresholdParams,
+		mode: DealingMode,
 		mut rng: R,
-	) -> Result<Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>, ACSSError>
+		emit: F,
+	) -> Result<(), AcssError>
+	where
+		R: SecureRng,
+		K: CommitteeKey<E>,
+		F: FnMut(DealtShare<E>) -> Result<(), AcssError>,
 	{
-		HighThresholdACSS::<E>::reshare(self.0, self.1, committee, t, &mut rng)
+		HighThresholdACSS::<E>::reshare_streaming(
+			self.0, self.1, committee, params, mode, &mut rng, emit,
+		)
 	}
 }
 
+/// the commitment of a share blinded by `r` (see `DoubleSecret::blind`),
+/// given the commitment of the share
+///
+/// fails with `InvalidBlindingFactor` if `r` is zero
+///
+/// * `commitment`: The commitment of the share
+/// * `r`: The blinding factor
+pub fn blind_commitment<E: EngineBLS>(
+	commitment: &DoublePublicKey<E>,
+	r: &E::Scalar,
+) -> Result<DoublePublicKey<E>, AcssError> {
+	if r.is_zero() {
+		return Err(AcssError::InvalidBlindingFactor);
+	}
+	Ok(DoublePublicKey(commitment.0 * r, commitment.1 * r))
+}
+
+/// the commitment of a share, given the commitment of its copy blinded by `r`
+///
+/// fails with `InvalidBlindingFactor` if `r` is zero
+///
+/// * `commitment`: The commitment of the blinded share
+/// * `r`: The blinding factor
+pub fn unblind_commitment<E: EngineBLS>(
+	commitment: &DoublePublicKey<E>,
+	r: &E::Scalar,
+) -> Result<DoublePublicKey<E>, AcssError> {
+	let inverse = r.inverse().ok_or(AcssError::InvalidBlindingFactor)?;
+	Ok(DoublePublicKey(commitment.0 * inverse, commitment.1 * inverse))
+}
+
 /// a wrapper around a keypair vartime...
 /// could get confusing with w3f-bls keypair, maybe add conversion?
+/// with the `serde-secret` feature, it is (de)serialized as the compressed
+/// encoding of its secret key, as with `to_bytes`
 pub struct Keypair<E: EngineBLS>(pub KeypairVT<E>);
 
+impl<E: EngineBLS> fmt::Debug for Keypair<E> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Keypair")
+			.field("public", &self.0.public.0)
+			.field("secret", &format_args!("<redacted>"))
+			.finish()
+	}
+}
+
+#[cfg(feature = "serde-secret")]
+impl<E: EngineBLS> serde::Serialize for Keypair<E> {
+	fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+		let mut bytes =
+			self.to_bytes().map_err(<S::Error as serde::ser::Error>::custom)?;
+		let result = s.serialize_bytes(&bytes);
+		wipe(&mut bytes);
+		result
+	}
+}
+
+#[cfg(feature = "serde-secret")]
+impl<'de, E: EngineBLS> serde::Deserialize<'de> for Keypair<E> {
+	/// rejects anything `from_bytes` rejects
+	fn deserialize<D: serde::Deserializer<'de>>(
+		d: D,
+	) -> Result<Self, D::Error> {
+		let mut bytes: Vec<u8> = serde::Deserialize::deserialize(d)?;
+		let keypair = Self::from_bytes(&bytes);
+		wipe(&mut bytes);
+		keypair.map_err(serde::de::Error::custom)
+	}
+}
+
 impl<E: EngineBLS> Keypair<E> {
+	/// derive a keypair from a 32 byte seed
+	/// the seed must be secret, anyone who learns it learns the secret key
+	///
+	/// * `seed`: The seed, wiped once the key is derived
+	pub fn from_seed(mut seed: [u8; 32]) -> Self {
+		let secret = SecretKeyVT::<E>::from_seed(&seed);
+		wipe(&mut seed);
+		let public = secret.into_public();
+		Self(KeypairVT { secret, public })
+	}
+
+	/// the compressed encoding of the secret key
+	/// the caller is responsible for wiping the bytes once they are stored
+	/// fails with `Encoding` if the key cannot be encoded
+	pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+		let secret = &self.0.secret.0;
+		let mut bytes = Vec::with_capacity(secret.compressed_size());
+		if secret.serialize_compressed(&mut bytes).is_err() {
+			wipe(&mut bytes);
+			return Err(SerializationError::Encoding);
+		}
+		Ok(bytes)
+	}
+
+	/// decode a keypair from the encoding of its secret key, as written by
+	/// `to_bytes`, deriving the public key
+	/// fails with `Decoding` if the bytes are not exactly the canonical
+	/// encoding of a non-zero scalar
+	///
+	/// * `bytes`: The compressed encoding of the secret key
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+		let mut reader = bytes;
+		let mut sk = E::Scalar::deserialize_compressed(&mut reader)
+			.map_err(|_| SerializationError::Decoding)?;
+		if !reader.is_empty() || sk.is_zero() {
+			wipe(&mut sk);
+			return Err(SerializationError::Decoding);
+		}
+		let secret = SecretKeyVT::<E>(sk);
+		wipe(&mut sk);
+		let public = secret.into_public();
+		Ok(Self(KeypairVT { secret, public }))
+	}
+
+	/// the public key
+	pub fn public(&self) -> &PublicKey<E> {
+		&self.0.public
+	}
+
+	/// the public key as an encryption key, i.e. the same point in the
+	/// newtype dealers encrypt shares to
+	pub fn encryption_key(&self) -> EncryptionKey<E::PublicKeyGroup> {
+		EncryptionKey(self.0.public.0)
+	}
+
 	/// try to recover a double secret key from a resharing
-	/// returns none if ACSS recovery fails
+	/// returns an error if ACSS recovery fails
+	///
+	/// * `poks`: The batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers, where n is the
+	///   number of dealers and t the number of valid PoKs required
 	///
-	/// * `pok`: A batch pok
-	/// * `threshold`: A minimum number of valid proofs of knowledge requires
 	/// note to self: 'pok' is difficult to pluralize, poks doesn't really work
 	/// since it's proofs of knowledge, but psok seems even stranger. What if I
 	/// said 'knowlegde proofs'? pluralized as 'kps'
 	pub fn recover(
 		&self,
-		pok: BatchPoK<E::PublicKeyGroup>,
-		threshold: u8,
-	) -> Result<DoubleSecret<E>, ACSSError> {
-		let secret = self.0.secret.0;
-		HighThresholdACSS::<E>::recover(secret, vec![pok], threshold)
+		poks: &[BatchPoK<E::PublicKeyGroup>],
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		let secret = SecretKey::from(&self.0.secret);
+		HighThresholdACSS::<E>::recover(&secret, poks, params)
+	}
+	/// try to recover a double secret key from a resharing, as with
+	/// `recover`, keeping it in page-locked memory
+	///
+	/// * `poks`: The batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers
+	#[cfg(all(feature = "mlock", unix))]
+	pub fn recover_locked(
+		&self,
+		poks: &[BatchPoK<E::PublicKeyGroup>],
+		params: ThresholdParams,
+	) -> Result<Locked<DoubleSecret<E>>, AcssError> {
+		self.recover(poks, params)?.into_locked()
+	}
+
+	/// try to recover a double secret key from a stream of batched PoKs,
+	/// stopping as soon as t valid ones have been processed
+	///
+	/// * `poks`: A stream of batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers
+	pub fn recover_streaming<I>(
+		&self,
+		poks: I,
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError>
+	where
+		I: IntoIterator,
+		I::Item: Borrow<BatchPoK<E::PublicKeyGroup>>,
+	{
+		let secret = SecretKey::from(&self.0.secret);
+		HighThresholdACSS::<E>::recover_streaming(&secret, poks, params)
+	}
+
+	/// try to recover a double secret key from batched PoKs labelled with the
+	/// index of their dealer, e.g. when they arrive out of order
+	///
+	/// * `poks`: (dealer index, batched PoK) pairs, indices start at 1
+	/// * `params`: The threshold parameters of the dealers
+	pub fn recover_indexed(
+		&self,
+		poks: &[(ShareIndex, BatchPoK<E::PublicKeyGroup>)],
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		let secret = SecretKey::from(&self.0.secret);
+		HighThresholdACSS::<E>::recover_indexed(&secret, poks, params)
 	}
 }
-/// the high threshold asynchronous complete secret sharing struct
-pub struct HighThresholdACSS<E: EngineBLS> {
-	_curve_group: PhantomData<E>,
+
+/// a resharing in progress, dealt one recipient at a time so that callers
+/// can interleave other work (see [`crate::nonblocking`])
+///
+/// the shares are wiped when the dealing is dropped, finished or not
+pub(crate) struct Dealing<'a, E: EngineBLS> {
+	committee: &'a [PublicKey<E>],
+	// (share, blinding share) for each recipient, in committee order
+	shares: Vec<(E::Scalar, E::Scalar)>,
+	ephemerals: Option<[Ephemeral<E::PublicKeyGroup>; 2]>,
+	// the number of recipients dealt to
+	dealt: usize,
+	// the entries dealt so far, unless they are handed out by `deal`
+	poks: Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>,
+	// the detection tag of each recipient dealt to, if requested
+	detection_tags: Option<Vec<DetectionTag>>,
+	// the associated data every proof is bound to, if any
+	binding: Option<Binding>,
 }
 
-impl<E: EngineBLS> HighThresholdACSS<E> {
-	/// Construct a resharing for a committee identified by their public keys
-	///
-	/// `msk`: the master secret key
-	/// `msk_hat`: the blinding secret key
-	/// `committee`: The next committee to generate shares for
-	/// `t`: The threshold (1 <= t <= n) (note: if t = 0, this function returns
-	/// an empty vec) `rng`: A CSPRNG
-	pub fn reshare<R: CryptoRng + Rng>(
+/// the optional extras of a dealing, as the builder collects them
+#[derive(Default)]
+pub(crate) struct DealingOptions {
+	/// tag each recipient's entry, see `Dealing::with_detection_tags`
+	pub(crate) detection_tags: bool,
+	/// bind each proof to associated data, see `Dealing::with_binding`
+	pub(crate) binding: Option<Binding>,
+}
+
+impl<'a, E: EngineBLS> Dealing<'a, E> {
+	/// sample the polynomials (and shared ephemerals) of a dealing
+	/// fails with `InvalidParams` if the committee does not match `params` and
+	/// with `InvalidCommittee` if it holds the identity
+	pub(crate) fn new<R: SecureRng>(
 		msk: E::Scalar,
 		msk_hat: E::Scalar,
-		committee: &[PublicKey<E>],
-		t: u8,
+		committee: &'a [PublicKey<E>],
+		params: ThresholdParams,
+		mode: DealingMode,
 		mut rng: R,
-	) -> Result<Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>, ACSSError>
-	{
-		if committee.is_empty() {
-			return Err(ACSSError::InvalidCommittee);
+	) -> Result<Self, AcssError> {
+		params.check_len(committee.len())?;
+		if committee.iter().any(|pk| pk.0.is_zero()) {
+			return Err(AcssError::InvalidCommittee);
 		}
 
 		// f(x) -> [f(0), {(1, f(1)), ..., (n, f(n))}]
-		let evals: BTreeMap<E::Scalar, E::Scalar> =
-			generate_shares_checked::<E, R>(
+		let mut evals: BTreeMap<E::Scalar, E::Scalar> =
+			generate_shares_checked::<E, _>(
 				msk,
-				committee.len() as u8,
-				t,
+				params.n(),
+				params.t(),
 				&mut rng,
 			);
 		// f_hat(x) (blinding polynomial) -> [f'(0), {(1, f'(1)), ...(n,
 		// f'(n))}]
-		let evals_hat: BTreeMap<E::Scalar, E::Scalar> =
-			generate_shares_checked::<E, R>(
+		let mut evals_hat: BTreeMap<E::Scalar, E::Scalar> =
+			generate_shares_checked::<E, _>(
 				msk_hat,
-				committee.len() as u8,
-				t,
+				params.n(),
+				params.t(),
 				&mut rng,
 			);
+		let shares =
+			evals.values().copied().zip(evals_hat.values().copied()).collect();
+		evals.values_mut().chain(evals_hat.values_mut()).for_each(wipe);
+		Ok(Self::from_shares(committee, shares, mode, rng))
+	}
+
+	/// start a dealing of given shares, sampling the shared ephemerals (if
+	/// any); the committee must already have been checked
+	fn from_shares<R: SecureRng>(
+		committee: &'a [PublicKey<E>],
+		shares: Vec<(E::Scalar, E::Scalar)>,
+		mode: DealingMode,
+		mut rng: R,
+	) -> Self {
+		// one ephemeral for the shares and one for the blinding shares
+		let ephemerals = match mode {
+			DealingMode::SharedEphemeral =>
+				Some([Ephemeral::new(&mut rng), Ephemeral::new(&mut rng)]),
+			DealingMode::PerRecipient => None,
+		};
+
+		Self {
+			committee,
+			shares,
+			ephemerals,
+			dealt: 0,
+			poks: Vec::new(),
+			detection_tags: None,
+			binding: None,
+		}
+	}
+
+	/// tag each recipient's entry so that it can find it with
+	/// [`crate::transcript::detect_entries`]
+	pub(crate) fn with_detection_tags(mut self) -> Self {
+		self.detection_tags = Some(Vec::with_capacity(self.committee.len()));
+		self
+	}
+
+	/// bind every proof to `binding`, so that it only verifies with
+	/// `BatchPoK::verify_bound` and the same binding
+	pub(crate) fn with_binding(mut self, binding: Binding) -> Self {
+		self.binding = Some(binding);
+		self
+	}
 
-		let mut poks: Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)> =
-			Vec::new();
-		for (pk, (u, u_hat)) in
-			committee.iter().zip(evals.iter().zip(evals_hat.iter()))
+	/// deal to the next recipient, keeping its entry for `finish`
+	/// outputs true once every recipient has been dealt to
+	pub(crate) fn deal_next<R: SecureRng>(
+		&mut self,
+		rng: R,
+	) -> Result<bool, AcssError> {
+		if let Some((_, commitment, pok)) = self.deal(rng)? {
+			self.poks.reserve_exact(self.committee.len() - self.poks.len());
+			self.poks.push((commitment, pok));
+		}
+		Ok(self.dealt == self.committee.len())
+	}
+
+	/// deal to the next recipient, handing out its entry rather than keeping
+	/// it
+	/// outputs the recipient's position in the committee, the public key of
+	/// its share and its PoK, or none once every recipient has been dealt to
+	pub(crate) fn deal<R: SecureRng>(
+		&mut self,
+		mut rng: R,
+	) -> Result<
+		Option<(usize, DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>,
+		AcssError,
+	> {
+		let next = self.dealt;
+		let (Some(pk), Some((u, u_hat))) =
+			(self.committee.get(next), self.shares.get(next))
+		else {
+			return Ok(None);
+		};
+
+		let mut messages = [*u, *u_hat];
+		let fresh;
+		// sampled as `BatchPoK::prove` would, so tagging or binding does not
+		// change the dealing
+		let needs_ephemerals =
+			self.detection_tags.is_some() || self.binding.is_some();
+		let ephemerals = match &self.ephemerals {
+			Some(ephemerals) => Some(ephemerals),
+			None if needs_ephemerals => {
+				fresh = [Ephemeral::new(&mut rng), Ephemeral::new(&mut rng)];
+				Some(&fresh)
+			},
+			None => None,
+		};
+		let pok = match (ephemerals, &self.binding) {
+			(Some(ephemerals), Some(binding)) => BatchPoK::prove_bound(
+				&messages,
+				EncryptionKey(pk.0),
+				ephemerals,
+				binding,
+				&mut rng,
+			),
+			(Some(ephemerals), None) => BatchPoK::prove_with_ephemerals(
+				&messages,
+				EncryptionKey(pk.0),
+				ephemerals,
+				&mut rng,
+			),
+			(None, _) =>
+				BatchPoK::prove(&messages, EncryptionKey(pk.0), &mut rng),
+		};
+		wipe(&mut messages);
+		if let (Some(tags), Some(ephemerals)) =
+			(&mut self.detection_tags, ephemerals)
 		{
-			if let Ok(pok) = BatchPoK::prove(&[*u.1, *u_hat.1], pk.0, &mut rng)
-			{
-				// lets get a public key while we're at it...
-				let etf_pk = SecretKeyVT::<E>(*u.1).into_double_public_key();
-				poks.push((etf_pk, pok));
-			} else {
-				return Err(ACSSError::InvalidMessage);
-			}
+			let tag = HashedElGamal::detection_tag(
+				EncryptionKey(pk.0),
+				&ephemerals[0],
+			);
+			tags.push(tag.map_err(AcssError::Proof)?);
+		}
+		// lets get a public key while we're at it...
+		let etf_pk = SecretKeyVT::<E>(*u).into_double_public_key();
+		let pok = pok.map_err(AcssError::Proof)?;
+		self.dealt += 1;
+		trace_event!(recipient = next + 1, "dealt share");
+		Ok(Some((next, etf_pk, pok)))
+	}
+
+	/// the resharing, once every recipient has been dealt to
+	pub(crate) fn finish(mut self) -> Result<Resharing<E>, AcssError> {
+		let poks = core::mem::take(&mut self.poks);
+		let mut resharing = Resharing::from_dealings(self.committee, poks)?;
+		if let Some(tags) = self.detection_tags.take() {
+			resharing.set_detection_tags(tags);
+		}
+		Ok(resharing)
+	}
+}
+
+impl<E: EngineBLS> Drop for Dealing<'_, E> {
+	fn drop(&mut self) {
+		for (u, u_hat) in self.shares.iter_mut() {
+			wipe(u);
+			wipe(u_hat);
+		}
+	}
+}
+
+/// one recipient's entry of a resharing, as `reshare_streaming` hands it out
+#[derive(Clone)]
+pub struct DealtShare<E: EngineBLS> {
+	/// the index of the recipient's share
+	pub index: ShareIndex,
+	/// the recipient's public key
+	pub recipient: PublicKey<E>,
+	/// the public key of the recipient's share
+	pub commitment: DoublePublicKey<E>,
+	/// the recipient's encrypted share and blinding share, and their PoK
+	pub pok: BatchPoK<E::PublicKeyGroup>,
+}
+
+/// the high threshold asynchronous complete secret sharing struct
+pub struct HighThresholdACSS<E: EngineBLS> {
+	_curve_group: PhantomData<E>,
+}
+
+impl<E: EngineBLS> HighThresholdACSS<E> {
+	/// Construct a resharing for a committee identified by their public keys
+	///
+	/// `msk`: the master secret key
+	/// `msk_hat`: the blinding secret key
+	/// `committee`: The next committee to generate shares for
+	/// `params`: The threshold parameters, where n is the committee size
+	/// `rng`: A CSPRNG
+	pub fn reshare<R: SecureRng, K: CommitteeKey<E>>(
+		msk: E::Scalar,
+		msk_hat: E::Scalar,
+		committee: &[K],
+		params: ThresholdParams,
+		rng: R,
+	) -> Result<Resharing<E>, AcssError> {
+		Self::reshare_with_mode(
+			msk,
+			msk_hat,
+			committee,
+			params,
+			DealingMode::default(),
+			rng,
+		)
+	}
+
+	/// Construct a resharing for a committee, drawing randomness from the
+	/// operating system
+	///
+	/// `msk`: the master secret key
+	/// `msk_hat`: the blinding secret key
+	/// `committee`: The next committee to generate shares for
+	/// `params`: The threshold parameters, where n is the committee size
+	#[cfg(feature = "getrandom")]
+	pub fn reshare_with_os_rng<K: CommitteeKey<E>>(
+		msk: E::Scalar,
+		msk_hat: E::Scalar,
+		committee: &[K],
+		params: ThresholdParams,
+	) -> Result<Resharing<E>, AcssError> {
+		Self::reshare(msk, msk_hat, committee, params, OsRng)
+	}
+
+	/// Construct a resharing for a committee, sampling the ephemeral keys used
+	/// to encrypt shares according to `mode`
+	///
+	/// `msk`: the master secret key
+	/// `msk_hat`: the blinding secret key
+	/// `committee`: The next committee to generate shares for
+	/// `params`: The threshold parameters, where n is the committee size
+	/// `mode`: how to sample ephemeral keys
+	/// `rng`: A CSPRNG
+	pub fn reshare_with_mode<R: SecureRng, K: CommitteeKey<E>>(
+		msk: E::Scalar,
+		msk_hat: E::Scalar,
+		committee: &[K],
+		params: ThresholdParams,
+		mode: DealingMode,
+		rng: R,
+	) -> Result<Resharing<E>, AcssError> {
+		Self::reshare_monitored(
+			&DoubleSecret(msk, msk_hat),
+			&committee_keys(committee),
+			params,
+			mode,
+			DealingOptions::default(),
+			rng,
+			Monitor::new(),
+		)
+	}
+
+	/// Construct a resharing for a committee as with `reshare_with_mode`, but
+	/// hand each recipient's entry to `emit` as soon as it is dealt rather
+	/// than collecting them into a `Resharing`
+	///
+	/// Only the n (share, blinding share) pairs are held while dealing, so a
+	/// dealer with little memory can send each entry to the network as it is
+	/// produced. The entries are those `reshare_with_mode` outputs for the
+	/// same randomness, in committee order. Fails as `reshare_with_mode` does,
+	/// or with the first error `emit` returns, e.g. `SendFailed`, in which
+	/// case no further entries are dealt.
+	///
+	/// `msk`: the master secret key
+	/// `msk_hat`: the blinding secret key
+	/// `committee`: The next committee to generate shares for
+	/// `params`: The threshold parameters, where n is the committee size
+	/// `mode`: how to sample ephemeral keys
+	/// `rng`: A CSPRNG
+	/// `emit`: Called with the entry of each recipient in turn
+	pub fn reshare_streaming<R, K, F>(
+		msk: E::Scalar,
+		msk_hat: E::Scalar,
+		committee: &[K],
+		params: ThresholdParams,
+		mode: DealingMode,
+		mut rng: R,
+		mut emit: F,
+	) -> Result<(), AcssError>
+	where
+		R: SecureRng,
+		K: CommitteeKey<E>,
+		F: FnMut(DealtShare<E>) -> Result<(), AcssError>,
+	{
+		timed_span!(
+			"acss::deal_streaming",
+			n = params.n(),
+			t = params.t(),
+			?mode
+		);
+		let committee = &committee_keys(committee)[..];
+		let mut dealing =
+			Dealing::new(msk, msk_hat, committee, params, mode, &mut rng)?;
+		while let Some((position, commitment, pok)) = dealing.deal(&mut rng)? {
+			let index = ShareIndex::from_position(position)
+				.ok_or(AcssError::InvalidCommittee)?;
+			emit(DealtShare {
+				index,
+				recipient: committee[position],
+				commitment,
+				pok,
+			})?;
+		}
+		Ok(())
+	}
+
+	/// construct a resharing as with `reshare_with_mode`, reporting the
+	/// number of recipients dealt to and checking for cancellation between
+	/// recipients, and tagging or binding each entry as `options` asks
+	pub(crate) fn reshare_monitored<R: SecureRng>(
+		secret: &DoubleSecret<E>,
+		committee: &[PublicKey<E>],
+		params: ThresholdParams,
+		mode: DealingMode,
+		options: DealingOptions,
+		mut rng: R,
+		mut monitor: Monitor<'_>,
+	) -> Result<Resharing<E>, AcssError> {
+		timed_span!("acss::deal", n = params.n(), t = params.t(), ?mode);
+		let DoubleSecret(msk, msk_hat) = secret;
+		let mut dealing =
+			Dealing::new(*msk, *msk_hat, committee, params, mode, &mut rng)?;
+		if options.detection_tags {
+			dealing = dealing.with_detection_tags();
+		}
+		if let Some(binding) = options.binding {
+			dealing = dealing.with_binding(binding);
 		}
+		for done in 1..=committee.len() {
+			monitor.check()?;
+			dealing.deal_next(&mut rng)?;
+			monitor.report(done, committee.len());
+		}
+		dealing.finish()
+	}
 
-		Ok(poks)
+	/// construct a resharing as with `reshare_with_mode`, together with a
+	/// commitment `P` to the dealt polynomials and the opening of each share
+	/// fails as `reshare_with_mode` does
+	///
+	/// `msk`: the master secret key
+	/// `msk_hat`: the blinding secret key
+	/// `committee`: The next committee to generate shares for
+	/// `params`: The threshold parameters, where n is the committee size
+	/// `mode`: how to sample ephemeral keys
+	/// `rng`: A CSPRNG
+	pub fn reshare_committed<P, R, K>(
+		msk: E::Scalar,
+		msk_hat: E::Scalar,
+		committee: &[K],
+		params: ThresholdParams,
+		mode: DealingMode,
+		mut rng: R,
+	) -> Result<CommittedResharing<E, P>, AcssError>
+	where
+		P: PolynomialCommitment<E::PublicKeyGroup>,
+		R: SecureRng,
+		K: CommitteeKey<E>,
+	{
+		timed_span!("acss::deal_committed", n = params.n(), t = params.t());
+		let committee = &committee_keys(committee)[..];
+		params.check_len(committee.len())?;
+		if committee.iter().any(|pk| pk.0.is_zero()) {
+			return Err(AcssError::InvalidCommittee);
+		}
+
+		let mut sample = |s: E::Scalar| {
+			let mut coeffs: Vec<E::Scalar> =
+				(0..params.t()).map(|_| E::Scalar::rand(&mut rng)).collect();
+			coeffs[0] = s;
+			DensePolynomial::from_coefficients_vec(coeffs)
+		};
+		let mut f = sample(msk);
+		let mut f_hat = sample(msk_hat);
+		let commitment = P::commit(&f.coeffs, &f_hat.coeffs);
+		let (shares, openings) = (1..=params.n())
+			.map(|i| {
+				let x = E::Scalar::from(i);
+				(
+					(f.evaluate(&x), f_hat.evaluate(&x)),
+					P::open(&f.coeffs, &f_hat.coeffs, x),
+				)
+			})
+			.unzip();
+		wipe(&mut f.coeffs);
+		wipe(&mut f_hat.coeffs);
+
+		let mut dealing =
+			Dealing::from_shares(committee, shares, mode, &mut rng);
+		while !dealing.deal_next(&mut rng)? {}
+		Ok(CommittedResharing {
+			resharing: dealing.finish()?,
+			commitment,
+			openings,
+		})
 	}
 
 	/// decrypt shares + authenticate from a collection of batched PoKs
 	/// outputs the new share and its blinding share
 	/// assumes default generator is used
-	//
+	///
+	/// the poks are borrowed, so a single transcript can be shared by every
+	/// member of the committee without being copied
+	///
+	/// invalid poks are skipped, and recovery fails unless at least t of them
+	/// are valid
+	///
+	/// * `sk`: the secret key of the recipient
+	/// * `poks`: The batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers, the number of poks
+	///   must equal n
 	pub fn recover(
-		sk: E::Scalar,
-		poks: Vec<BatchPoK<E::PublicKeyGroup>>,
-		threshold: u8,
-	) -> Result<DoubleSecret<E>, ACSSError> {
-		let q = E::PublicKeyGroup::generator() * sk;
-
-		let mut secrets = Vec::new();
-		let mut blinding_secrets = Vec::new();
-
-		let mut invalid_poks = Vec::new();
-
-		for (idx, pok) in poks.iter().enumerate() {
-			if !pok.verify(q) {
-				invalid_poks.push(pok);
-				if poks.len() - invalid_poks.len() > threshold as usize {
-					return Err(ACSSError::InsufficientValidPoK);
-				};
-			}
+		sk: &SecretKey<E::PublicKeyGroup>,
+		poks: &[BatchPoK<E::PublicKeyGroup>],
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		params.check_len(poks.len())?;
+		Self::recover_streaming(sk, poks, params)
+	}
+
+	/// decrypt shares + authenticate lazily from a stream of batched PoKs
+	/// outputs the new share and its blinding share
+	///
+	/// the i-th item of the stream is the pok from dealer i + 1. Each pok is
+	/// verified as it arrives and invalid ones are skipped. The stream is not
+	/// consumed any further once t valid poks have been processed, and at most
+	/// n items are ever read from it.
+	///
+	/// * `sk`: the secret key of the recipient
+	/// * `poks`: A stream of (owned or borrowed) batched PoKs
+	/// * `params`: The threshold parameters of the dealers
+	pub fn recover_streaming<I>(
+		sk: &SecretKey<E::PublicKeyGroup>,
+		poks: I,
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError>
+	where
+		I: IntoIterator,
+		I::Item: Borrow<BatchPoK<E::PublicKeyGroup>>,
+	{
+		let shares = decrypt_valid_poks::<E, I>(sk, poks, params)?;
+		Ok(interpolate_shares::<E>(shares))
+	}
 
-			let f = E::Scalar::from(idx as u8 + 1);
+	/// decrypt shares + authenticate from batched PoKs labelled with the
+	/// index of their dealer, so they need not be ordered or complete
+	/// outputs the new share and its blinding share
+	///
+	/// fails with `ShareIndexOutOfRange` if an index is not in 1..=n and with
+	/// `DuplicateShareIndex` if an index is repeated, since either would give
+	/// a wrong interpolation
+	///
+	/// * `sk`: the secret key of the recipient
+	/// * `poks`: (dealer index, batched PoK) pairs, indices start at 1
+	/// * `params`: The threshold parameters of the dealers
+	pub fn recover_indexed(
+		sk: &SecretKey<E::PublicKeyGroup>,
+		poks: &[(ShareIndex, BatchPoK<E::PublicKeyGroup>)],
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		params.check_indices(poks.iter().map(|(index, _)| *index))?;
+		let shares = decrypt_indexed_poks::<E, _, _>(
+			sk,
+			poks.iter().map(|(index, pok)| (*index, pok)),
+			params.t(),
+			None,
+		)?;
+		Ok(interpolate_shares::<E>(shares))
+	}
 
-			let r_bytes =
-				HashedElGamal::decrypt(sk, pok.ciphertexts[0].clone()).unwrap();
-			let r = E::Scalar::deserialize_compressed(&r_bytes[..])
-				.map_err(|_| ACSSError::InvalidCiphertext)?;
-			secrets.push((f, r));
+	/// decrypt shares + authenticate from batched PoKs bound to associated
+	/// data, as a builder with a session or associated data deals them
+	/// outputs the new share and its blinding share
+	///
+	/// PoKs bound to other data (or to none) are skipped like invalid ones
+	///
+	/// * `sk`: the secret key of the recipient
+	/// * `poks`: The batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers, the number of poks
+	///   must equal n
+	/// * `binding`: The binding the dealers used, see `Binding::session`
+	pub fn recover_bound(
+		sk: &SecretKey<E::PublicKeyGroup>,
+		poks: &[BatchPoK<E::PublicKeyGroup>],
+		params: ThresholdParams,
+		binding: &Binding,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		params.check_len(poks.len())?;
+		let shares = decrypt_indexed_poks::<E, _, _>(
+			sk,
+			params.indices().zip(poks),
+			params.t(),
+			Some(binding),
+		)?;
+		Ok(interpolate_shares::<E>(shares))
+	}
+}
+
+/// interpolate the share and blinding share from (x, share, blinding share)
+/// triples, wiping the shares afterwards
+fn interpolate_shares<E: EngineBLS>(
+	shares: Vec<(E::Scalar, E::Scalar, E::Scalar)>,
+) -> DoubleSecret<E> {
+	let (mut secrets, mut blinding_secrets): (Vec<_>, Vec<_>) =
+		shares.into_iter().map(|(x, r, r_hat)| ((x, r), (x, r_hat))).unzip();
 
-			let r_prime_bytes =
-				HashedElGamal::decrypt(sk, pok.ciphertexts[1].clone()).unwrap();
-			let r_prime = E::Scalar::deserialize_compressed(&r_prime_bytes[..])
-				.map_err(|_| ACSSError::InvalidCiphertext)?;
-			blinding_secrets.push((f, r_prime));
+	let s = interpolate::<E::SignatureGroup>(&secrets);
+	let s_prime = interpolate::<E::SignatureGroup>(&blinding_secrets);
+	secrets
+		.iter_mut()
+		.chain(blinding_secrets.iter_mut())
+		.for_each(|(_, y)| wipe(y));
+	DoubleSecret::<E>(s, s_prime)
+}
+
+/// verify and decrypt the first n batched PoKs of a stream until t valid ones
+/// have been processed, skipping invalid ones
+/// outputs (x, share, blinding share) triples where x = i + 1 for the i-th pok
+pub(crate) fn decrypt_valid_poks<E: EngineBLS, I>(
+	sk: &SecretKey<E::PublicKeyGroup>,
+	poks: I,
+	params: ThresholdParams,
+) -> Result<Vec<(E::Scalar, E::Scalar, E::Scalar)>, AcssError>
+where
+	I: IntoIterator,
+	I::Item: Borrow<BatchPoK<E::PublicKeyGroup>>,
+{
+	let indexed = params.indices().zip(poks);
+	decrypt_indexed_poks::<E, _, _>(sk, indexed, params.t(), None)
+}
+
+/// verify and decrypt (index, batched PoK) pairs until `threshold` valid ones
+/// have been processed, skipping invalid ones
+/// the indices are assumed to be distinct and in range, and the poks are
+/// verified against `binding` if given
+/// outputs (x, share, blinding share) triples where x is the index of the pok
+pub(crate) fn decrypt_indexed_poks<E: EngineBLS, I, P>(
+	sk: &SecretKey<E::PublicKeyGroup>,
+	poks: I,
+	threshold: u8,
+	binding: Option<&Binding>,
+) -> Result<Vec<(E::Scalar, E::Scalar, E::Scalar)>, AcssError>
+where
+	I: IntoIterator<Item = (ShareIndex, P)>,
+	P: Borrow<BatchPoK<E::PublicKeyGroup>>,
+{
+	timed_span!("acss::recover", t = threshold);
+	let threshold = threshold as usize;
+	let q = sk.public_key();
+	let mut shares = Vec::with_capacity(threshold);
+
+	for (index, pok) in poks {
+		let pok = pok.borrow();
+		let valid = match binding {
+			Some(binding) => pok.verify_bound(q, binding),
+			None => pok.verify(q),
+		};
+		trace_event!(dealer = index.get(), valid, "verified pok");
+		if !valid {
+			continue;
+		}
+
+		// a pok can verify and still not decrypt to a pair of scalars, which
+		// only its dealer is to blame for
+		let Ok((r, r_prime)) = decrypt_pok::<E>(sk, &q, pok) else {
+			trace_event!(dealer = index.get(), "skipped undecryptable pok");
+			continue;
+		};
+		shares.push((index.to_scalar(), r, r_prime));
+
+		if shares.len() == threshold {
+			return Ok(shares);
 		}
+	}
+
+	Err(AcssError::InsufficientValidPoK)
+}
+
+/// decrypt the share and blinding share carried by a batched PoK
+pub(crate) fn decrypt_pok<E: EngineBLS>(
+	sk: &SecretKey<E::PublicKeyGroup>,
+	pk: &EncryptionKey<E::PublicKeyGroup>,
+	pok: &BatchPoK<E::PublicKeyGroup>,
+) -> Result<(E::Scalar, E::Scalar), AcssError> {
+	let [ct, ct_hat] = &pok.ciphertexts[..] else {
+		return Err(AcssError::InvalidCiphertext);
+	};
+
+	// c1 and c2 are bound by the (verified) proof, whereas the tags are not,
+	// so checking them would only let a relay reject an honest dealing
+	let mut r_bytes =
+		HashedElGamal::decrypt_unauthenticated_with_pk(sk, pk, ct)
+			.map_err(|_| AcssError::InvalidCiphertext)?;
+	let mut r_prime_bytes =
+		HashedElGamal::decrypt_unauthenticated_with_pk(sk, pk, ct_hat)
+			.map_err(|_| AcssError::InvalidCiphertext)?;
 
-		let s = interpolate::<E::SignatureGroup>(secrets);
-		let s_prime = interpolate::<E::SignatureGroup>(blinding_secrets);
-		Ok(DoubleSecret::<E>(s, s_prime))
+	// decode both shares before checking either, so that the only branch is
+	// on whether the pok as a whole decrypted to valid shares
+	let (r, r_ok) = decode_scalar::<E::Scalar>(&r_bytes);
+	let (r_prime, r_prime_ok) = decode_scalar::<E::Scalar>(&r_prime_bytes);
+	wipe(&mut r_bytes);
+	wipe(&mut r_prime_bytes);
+
+	if !bool::from(r_ok & r_prime_ok) {
+		return Err(AcssError::InvalidCiphertext);
 	}
+	Ok((r, r_prime))
 }
 
 /// randomly sample coefficients for a degree t polynomial `f(x)` such that
@@ -207,7 +1095,7 @@ impl<E: EngineBLS> HighThresholdACSS<E> {
 /// `n`: The number of shares to generate
 /// `t`: The threshold (degree of the polynomial)
 /// `rng`: A cryptographically secure rng
-pub fn generate_shares_checked<E: EngineBLS, R: Rng + Sized>(
+pub fn generate_shares_checked<E: EngineBLS, R: SecureRng>(
 	s: E::Scalar,
 	n: u8,
 	t: u8,
@@ -228,79 +1116,44 @@ pub fn generate_shares_checked<E: EngineBLS, R: Rng + Sized>(
 		(0..t).map(|_| E::Scalar::rand(rng)).collect();
 	coeffs[0] = s;
 	//
-	let f = DensePolynomial::<E::Scalar>::from_coefficients_vec(coeffs);
+	let mut f = DensePolynomial::<E::Scalar>::from_coefficients_vec(coeffs);
 
-	(1..n + 1).for_each(|i| {
+	(1..=n).for_each(|i| {
 		let idx = E::Scalar::from(i);
 		let eval = f.evaluate(&idx);
 		out.insert(idx, eval);
 	});
+	wipe(&mut f.coeffs);
 	out
 }
 
-/// interpolate a polynomial from the input and evaluate it at 0
-/// P(X) = sum_{i = 0} ^n y_i * (\prod_{j=0}^n [j != i] (x-xj/xi - xj))hegs
-///
-/// * `evalulation`: a vec of (x, f(x)) pairs
-pub fn interpolate<C: CurveGroup>(
-	points: Vec<(C::ScalarField, C::ScalarField)>,
-) -> C::ScalarField {
-	let n = points.len();
-	// Calculate the Lagrange basis polynomials evaluated at 0
-	let mut lagrange_at_zero: Vec<C::ScalarField> = Vec::with_capacity(n);
-	for i in 0..n {
-		// build \prod_{j=0}^n [j != i] (x-xj/xi - xj)
-		let mut basis_value = C::ScalarField::one();
-		for j in 0..n {
-			if j != i {
-				let denominator = points[i].0 - points[j].0;
-				// Check if the denominator is zero before taking the inverse
-				if denominator.is_zero() {
-					// Handle the case when the denominator is zero (or very
-					// close to zero)
-					return C::ScalarField::zero();
-				}
-				let numerator = C::ScalarField::zero() - points[j].0;
-				// Use the precomputed inverse
-				basis_value *= numerator * denominator.inverse().unwrap();
-			}
-		}
-		lagrange_at_zero.push(basis_value);
-	}
-
-	// Interpolate the value at 0
-	// compute  sum_{i = 0} ^n (y_i * sum... )
-	let mut interpolated_value = C::ScalarField::zero();
-	for i in 0..n {
-		interpolated_value += points[i].1 * lagrange_at_zero[i];
-	}
-
-	interpolated_value
-}
-
 #[cfg(test)]
 pub mod tests {
 
 	use super::*;
-	use ark_ec::Group;
+	use crate::{
+		proofs::{
+			challenge::{ChallengeHash, DefaultChallenge},
+			hashed_el_gamal::Ciphertext,
+		},
+		rng::test_rng,
+	};
+	use ark_ec::{CurveGroup, Group};
 	use ark_serialize::CanonicalSerialize;
-	use ark_std::{rand::SeedableRng, test_rng, vec::Vec};
+	use ark_std::{rand::SeedableRng, vec::Vec};
 
 	use ark_ff::One;
 	use rand_chacha::ChaCha20Rng;
 
-	use w3f_bls::{Keypair, TinyBLS377};
+	use w3f_bls::{Keypair, KeypairVT, TinyBLS377};
 
 	#[derive(Debug, PartialEq)]
 	enum TestStatusReport {
-		ReshareSoftFail {
-			size: u8,
-		},
 		ReshareError {
-			error: ACSSError,
+			error: AcssError,
 		},
 		RecoverError {
-			error: ACSSError,
+			error: AcssError,
 		},
 		Completed {
 			// recovered secret
@@ -319,9 +1172,8 @@ pub mod tests {
 		t: u8,
 		num_actual_signers: u8,
 		num_valid_pok: u8,
-		do_fail_bad_recover: bool,
-		handler: &dyn Fn(TestStatusReport) -> (),
-	) -> () {
+		handler: &dyn Fn(TestStatusReport),
+	) {
 		let mut rng = ChaCha20Rng::seed_from_u64(0);
 
 		let msk = E::Scalar::rand(&mut rng);
@@ -336,46 +1188,44 @@ pub mod tests {
 			keys.iter().map(|kp| kp.public).collect::<Vec<_>>();
 
 		let mock_bad_resharing = BatchPoK::prove(
-			&vec![E::Scalar::one(), E::Scalar::one()],
-			E::PublicKeyGroup::generator(),
+			&[E::Scalar::one(), E::Scalar::one()],
+			EncryptionKey(E::PublicKeyGroup::generator()),
 			test_rng(),
 		)
 		.unwrap();
 		// );
 
-		match double_secret.reshare(
-			initial_committee_public_keys.as_slice(),
-			t,
-			&mut rng,
-		) {
+		let resharing =
+			ThresholdParams::for_committee(&initial_committee_public_keys, t)
+				.and_then(|params| {
+					double_secret.reshare(
+						initial_committee_public_keys.as_slice(),
+						params,
+						&mut rng,
+					)
+				});
+		match resharing {
 			Ok(resharing) => {
 				let mut poks: Vec<BatchPoK<E::PublicKeyGroup>> =
-					resharing.iter().map(|r| r.1.clone()).collect();
-				if resharing.is_empty() {
-					handler(TestStatusReport::ReshareSoftFail {
-						size: resharing.len() as u8,
-					});
-
-					if !do_fail_bad_recover {
-						return ();
-					}
-				} else {
-					// only the first `num_valid_pok` are valid, the rest are
-					// invalid
-					poks = poks[0..num_valid_pok as usize].to_vec();
-					(num_valid_pok..num_actual_signers)
-						.for_each(|_| poks.push(mock_bad_resharing.clone()));
-				}
+					resharing.poks().cloned().collect();
+				// only the first `num_valid_pok` are valid, the rest are
+				// invalid
+				poks = poks[0..num_valid_pok as usize].to_vec();
+				(num_valid_pok..num_actual_signers)
+					.for_each(|_| poks.push(mock_bad_resharing.clone()));
 
 				let mut recovered_shares: Vec<DoubleSecret<E>> = Vec::new();
 				// we only need to take 'num_actual_signers' elements from keys
 				keys = keys[0..num_actual_signers as usize].to_vec();
-				// then each member of the committee recovers a share
+				// then each member of the committee recovers a share from the
+				// single dealer
+				let single_dealer = ThresholdParams::new(1, 1).unwrap();
 				keys.iter().enumerate().for_each(|(idx, kp)| {
 					let w = Keypair(kp.into_vartime());
-					let r = poks[idx].clone();
-
-					match w.recover(r, t) {
+					match w.recover(
+						core::slice::from_ref(&poks[idx]),
+						single_dealer,
+					) {
 						Ok(recovered_share) => {
 							recovered_shares.push(recovered_share);
 						},
@@ -383,7 +1233,6 @@ pub mod tests {
 							handler(TestStatusReport::RecoverError {
 								error: e,
 							});
-							return ();
 						},
 					}
 				});
@@ -396,7 +1245,7 @@ pub mod tests {
 					})
 					.collect();
 				let recovered_msk =
-					interpolate::<E::SignatureGroup>(msk_shares);
+					interpolate::<E::SignatureGroup>(&msk_shares);
 
 				let msk_hat_shares: Vec<(E::Scalar, E::Scalar)> =
 					recovered_shares
@@ -407,7 +1256,7 @@ pub mod tests {
 						})
 						.collect();
 				let recovered_msk_hat =
-					interpolate::<E::SignatureGroup>(msk_hat_shares);
+					interpolate::<E::SignatureGroup>(&msk_hat_shares);
 
 				let a =
 					convert_to_bytes::<E::Scalar, 32>(recovered_msk).to_vec();
@@ -419,8 +1268,6 @@ pub mod tests {
 			},
 			Err(e) => handler(TestStatusReport::ReshareError { error: e }),
 		}
-
-		()
 	}
 
 	// should it be an error instead?
@@ -445,7 +1292,6 @@ pub mod tests {
 			1,
 			1,
 			1,
-			false,
 			&|status: TestStatusReport| match status {
 				TestStatusReport::Completed { a, b, c, d } => {
 					assert_eq!(a, c);
@@ -469,7 +1315,6 @@ pub mod tests {
 			3,
 			3,
 			3,
-			false,
 			&|status: TestStatusReport| match status {
 				TestStatusReport::Completed { a, b, c, d } => {
 					assert_eq!(a, c);
@@ -493,7 +1338,6 @@ pub mod tests {
 			2,
 			2,
 			2,
-			false,
 			&|status: TestStatusReport| match status {
 				TestStatusReport::Completed { a, b, c, d } => {
 					assert_eq!(a, c);
@@ -517,7 +1361,6 @@ pub mod tests {
 			3,
 			1,
 			1,
-			false,
 			&|status: TestStatusReport| match status {
 				TestStatusReport::Completed { a, b, c, d } => {
 					assert!(a != c);
@@ -541,10 +1384,9 @@ pub mod tests {
 			0,
 			0,
 			0,
-			false,
 			&|status: TestStatusReport| match status {
 				TestStatusReport::ReshareError { error } => {
-					assert_eq!(error, ACSSError::InvalidCommittee);
+					assert_eq!(error, AcssError::InvalidCommittee);
 				},
 				_ => {
 					panic!("The resharing should fail");
@@ -564,10 +1406,9 @@ pub mod tests {
 			0,
 			3,
 			3,
-			false,
 			&|status: TestStatusReport| match status {
-				TestStatusReport::ReshareSoftFail { size } => {
-					assert_eq!(size, 0)
+				TestStatusReport::ReshareError { error } => {
+					assert_eq!(error, AcssError::InvalidParams);
 				},
 				_ => {
 					panic!("All other conditions are invalid");
@@ -588,10 +1429,9 @@ pub mod tests {
 			2,
 			2,
 			1,
-			false,
 			&|status: TestStatusReport| match status {
 				TestStatusReport::RecoverError { error } => {
-					assert_eq!(error, ACSSError::InvalidCiphertext);
+					assert_eq!(error, AcssError::InsufficientValidPoK);
 				},
 				TestStatusReport::Completed { a, b, c, d } => {
 					assert!(a != c);
@@ -604,6 +1444,508 @@ pub mod tests {
 		);
 	}
 
+	#[cfg(feature = "getrandom")]
+	#[test]
+	pub fn reshare_with_os_rng_produces_valid_poks() {
+		type E = TinyBLS377;
+		let mut rng = test_rng();
+		let committee: Vec<PublicKey<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng).public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+
+		let resharing = secret
+			.reshare_with_os_rng(
+				&committee,
+				ThresholdParams::new(3, 2).unwrap(),
+			)
+			.unwrap();
+		assert_eq!(resharing.len(), committee.len());
+		for (pk, _, pok) in &resharing {
+			assert!(pok.verify(EncryptionKey(pk.0)));
+		}
+	}
+
+	#[test]
+	pub fn reshare_streaming_emits_the_entries_of_a_resharing() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..4).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|keys| keys.public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let params = ThresholdParams::new(4, 3).unwrap();
+
+		for mode in [DealingMode::SharedEphemeral, DealingMode::PerRecipient] {
+			let resharing = secret
+				.reshare_with_mode(
+					&committee,
+					params,
+					mode,
+					ChaCha20Rng::seed_from_u64(1),
+				)
+				.unwrap();
+			let mut streamed = Vec::new();
+			secret
+				.reshare_streaming(
+					&committee,
+					params,
+					mode,
+					ChaCha20Rng::seed_from_u64(1),
+					|entry| {
+						streamed.push(entry);
+						Ok(())
+					},
+				)
+				.unwrap();
+
+			assert_eq!(streamed.len(), resharing.len());
+			for (i, (entry, (pk, commitment, pok))) in
+				streamed.iter().zip(&resharing).enumerate()
+			{
+				assert_eq!(entry.index, ShareIndex::from_position(i).unwrap());
+				assert_eq!(entry.recipient.0, pk.0);
+				assert_eq!(
+					(entry.commitment.0, entry.commitment.1),
+					(commitment.0, commitment.1)
+				);
+				assert_eq!(entry.pok, *pok);
+				assert!(entry.pok.verify(EncryptionKey(entry.recipient.0)));
+			}
+		}
+
+		// an error from `emit` stops the dealing
+		let mut emitted = 0;
+		let result = secret.reshare_streaming(
+			&committee,
+			params,
+			DealingMode::default(),
+			&mut rng,
+			|_| {
+				emitted += 1;
+				if emitted == 2 {
+					return Err(AcssError::SendFailed);
+				}
+				Ok(())
+			},
+		);
+		assert_eq!(result, Err(AcssError::SendFailed));
+		assert_eq!(emitted, 2);
+	}
+
+	#[test]
+	pub fn reshare_rejects_identity_public_keys() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let committee = vec![
+			KeypairVT::<E>::generate(&mut rng).public,
+			PublicKey::<E>(<E as EngineBLS>::PublicKeyGroup::zero()),
+		];
+		let result = HighThresholdACSS::<E>::reshare(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			&committee,
+			ThresholdParams::new(2, 1).unwrap(),
+			&mut rng,
+		);
+		assert_eq!(result.err(), Some(AcssError::InvalidCommittee));
+	}
+
+	#[test]
+	pub fn double_secret_zeroize_clears_both_elements() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let mut secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		secret.zeroize();
+		assert!(secret.0.is_zero());
+		assert!(secret.1.is_zero());
+	}
+
+	#[test]
+	pub fn acss_works_in_every_dealing_mode() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|kp| kp.public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let single_dealer = ThresholdParams::new(1, 1).unwrap();
+		for mode in [DealingMode::SharedEphemeral, DealingMode::PerRecipient] {
+			let resharing = secret
+				.reshare_with_mode(&committee, params, mode, &mut rng)
+				.unwrap();
+			let c1s: Vec<_> =
+				resharing.poks().map(|pok| pok.ciphertexts[0].c1).collect();
+			assert_eq!(
+				c1s.windows(2).all(|w| w[0] == w[1]),
+				mode == DealingMode::SharedEphemeral
+			);
+
+			let shares: Vec<(
+				<E as EngineBLS>::Scalar,
+				<E as EngineBLS>::Scalar,
+			)> = keys
+				.iter()
+				.zip(resharing.iter())
+				.enumerate()
+				.take(2)
+				.map(|(idx, (kp, (_, _, pok)))| {
+					let share = Keypair(kp.clone())
+						.recover(core::slice::from_ref(pok), single_dealer)
+						.unwrap();
+					(<E as EngineBLS>::Scalar::from(idx as u8 + 1), share.0)
+				})
+				.collect();
+			assert_eq!(
+				interpolate::<<E as EngineBLS>::SignatureGroup>(&shares),
+				secret.0
+			);
+		}
+	}
+
+	#[test]
+	pub fn recover_streaming_stops_after_threshold_valid_poks() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let kp = KeypairVT::<E>::generate(&mut rng);
+		let pk = EncryptionKey(kp.public.0);
+
+		let s = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let s_hat = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let evals = generate_shares_checked::<E, _>(s, 3, 2, &mut rng);
+		let evals_hat = generate_shares_checked::<E, _>(s_hat, 3, 2, &mut rng);
+
+		// the first dealer misbehaves, the other two are honest
+		let bad_pok = BatchPoK::prove(
+			&[<E as EngineBLS>::Scalar::one(); 2],
+			EncryptionKey(<E as EngineBLS>::PublicKeyGroup::generator()),
+			&mut rng,
+		)
+		.unwrap();
+		let mut poks = vec![bad_pok];
+		for (u, u_hat) in evals.values().zip(evals_hat.values()).skip(1) {
+			poks.push(BatchPoK::prove(&[*u, *u_hat], pk, &mut rng).unwrap());
+		}
+
+		// anything past the threshold must never be pulled from the stream
+		let stream = poks
+			.iter()
+			.chain(core::iter::from_fn(|| panic!("stream was over-consumed")));
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let recovered =
+			Keypair(kp.clone()).recover_streaming(stream, params).unwrap();
+		assert_eq!(recovered.0, s);
+		assert_eq!(recovered.1, s_hat);
+
+		// a single valid pok is not enough for a threshold of 2
+		assert_eq!(
+			Keypair(kp).recover_streaming(&poks[..2], params).err(),
+			Some(AcssError::InsufficientValidPoK)
+		);
+	}
+
+	/// a pok that verifies for `pk` over `ciphertexts`, whatever they decrypt
+	/// to, as a malicious dealer can produce
+	fn forge_pok<C: CurveGroup, R: SecureRng>(
+		pk: EncryptionKey<C>,
+		ciphertexts: Vec<Ciphertext<C>>,
+		mut rng: R,
+	) -> BatchPoK<C> {
+		let g = C::generator();
+		let m = C::ScalarField::rand(&mut rng);
+		let k = C::ScalarField::rand(&mut rng);
+		let (s, t) = (g * k, pk.0 * k);
+		let aggregate = Ciphertext::aggregate(ciphertexts.iter());
+		let e = DefaultChallenge::challenge(&s, &t, &aggregate).unwrap();
+		BatchPoK { s, t, z: k + e * m, commitment: (g + pk.0) * m, ciphertexts }
+	}
+
+	#[test]
+	pub fn recover_skips_poks_that_decrypt_to_non_canonical_shares() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let kp = KeypairVT::<E>::generate(&mut rng);
+		let pk = EncryptionKey(kp.public.0);
+
+		let s = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let s_hat = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let evals = generate_shares_checked::<E, _>(s, 3, 2, &mut rng);
+		let evals_hat = generate_shares_checked::<E, _>(s_hat, 3, 2, &mut rng);
+
+		// the first dealer's pok verifies, but its plaintexts are not the
+		// encodings of scalars
+		let ciphertexts = (0..2)
+			.map(|_| HashedElGamal::encrypt([0xff; 32], pk, &mut rng).unwrap())
+			.collect();
+		let forged = forge_pok(pk, ciphertexts, &mut rng);
+		assert!(forged.verify(pk));
+		let mut poks = vec![forged];
+		for (u, u_hat) in evals.values().zip(evals_hat.values()).skip(1) {
+			poks.push(BatchPoK::prove(&[*u, *u_hat], pk, &mut rng).unwrap());
+		}
+
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let recovered = Keypair(kp).recover(&poks, params).unwrap();
+		assert_eq!(recovered.0, s);
+		assert_eq!(recovered.1, s_hat);
+	}
+
+	#[test]
+	pub fn recover_rejects_params_that_do_not_match_the_dealing() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|kp| kp.public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+
+		// n must match the size of the committee
+		let params = ThresholdParams::new(4, 2).unwrap();
+		assert_eq!(
+			secret.reshare(&committee, params, &mut rng).err(),
+			Some(AcssError::InvalidParams)
+		);
+
+		// and the number of dealers when recovering
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut rng).unwrap();
+		let poks: Vec<_> = resharing.poks().cloned().collect();
+		let kp = Keypair(keys[0].clone());
+		assert_eq!(
+			kp.recover(&poks[..2], params).err(),
+			Some(AcssError::InvalidParams)
+		);
+
+		// a threshold that cannot be met by the valid poks is an error rather
+		// than a wrong interpolation
+		assert_eq!(
+			kp.recover(&poks, params).err(),
+			Some(AcssError::InsufficientValidPoK)
+		);
+	}
+
+	#[test]
+	pub fn recover_indexed_rejects_duplicate_and_out_of_range_indices() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let kp = KeypairVT::<E>::generate(&mut rng);
+		let pk = EncryptionKey(kp.public.0);
+		let params = ThresholdParams::new(3, 2).unwrap();
+
+		let s = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let s_hat = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let evals = generate_shares_checked::<E, _>(s, 3, 2, &mut rng);
+		let evals_hat = generate_shares_checked::<E, _>(s_hat, 3, 2, &mut rng);
+		let poks: Vec<BatchPoK<_>> = evals
+			.values()
+			.zip(evals_hat.values())
+			.map(|(u, u_hat)| {
+				BatchPoK::prove(&[*u, *u_hat], pk, &mut rng).unwrap()
+			})
+			.collect();
+
+		// out of order and incomplete is fine
+		let index = |i| ShareIndex::new(i).unwrap();
+		let kp = Keypair(kp);
+		let recovered = kp
+			.recover_indexed(
+				&[(index(3), poks[2].clone()), (index(1), poks[0].clone())],
+				params,
+			)
+			.unwrap();
+		assert_eq!(recovered.0, s);
+		assert_eq!(recovered.1, s_hat);
+
+		assert_eq!(
+			kp.recover_indexed(
+				&[(index(1), poks[0].clone()), (index(1), poks[0].clone())],
+				params
+			)
+			.err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+		// index 0 cannot be constructed, so only n + 1 is left to check
+		assert_eq!(
+			kp.recover_indexed(
+				&[(index(1), poks[0].clone()), (index(4), poks[1].clone())],
+				params
+			)
+			.err(),
+			Some(AcssError::ShareIndexOutOfRange)
+		);
+	}
+
+	#[cfg(all(feature = "mlock", unix))]
+	#[test]
+	pub fn recovered_secrets_can_be_locked() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let kp = KeypairVT::<E>::generate(&mut rng);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let (s, s_hat) = (secret.0, secret.1);
+		let params = ThresholdParams::new(1, 1).unwrap();
+		let resharing = secret.reshare(&[kp.public], params, &mut rng).unwrap();
+
+		let pok = resharing.for_recipient(&kp.public).unwrap();
+		let locked = Keypair(kp)
+			.recover_locked(core::slice::from_ref(pok), params)
+			.unwrap();
+		// a single member's share of a single member committee is the secret
+		assert_eq!((locked.0, locked.1), (s, s_hat));
+		assert_eq!(format!("{locked:?}"), "Locked(<redacted>)");
+	}
+
+	#[test]
+	pub fn secrets_are_redacted_in_debug_output() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let s = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let s_hat = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let leaks = |debug: &str| {
+			[s, s_hat].iter().any(|x| debug.contains(&format!("{x:?}")))
+		};
+
+		let secret = DoubleSecret::<E>(s, s_hat);
+		assert_eq!(format!("{secret:?}"), "DoubleSecret(<redacted>)");
+		assert_eq!(
+			(secret.expose_secret(), secret.expose_blinding_secret()),
+			(&s, &s_hat)
+		);
+
+		let kp = KeypairVT::<E>::generate(&mut rng);
+		let sk = format!("{:?}", kp.secret.0);
+		let debug = format!("{:?}", Keypair(kp));
+		assert!(debug.contains("secret: <redacted>"));
+		assert!(!debug.contains(&sk));
+
+		let ephemeral =
+			Ephemeral::<<E as EngineBLS>::PublicKeyGroup>::new(&mut rng);
+		let debug = format!("{ephemeral:?}");
+		assert!(debug.contains("r: <redacted>"));
+		assert!(!leaks(&debug));
+	}
+
+	#[test]
+	pub fn blinded_shares_match_blinded_commitments() {
+		type E = TinyBLS377;
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let share = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let commitment = SecretKeyVT::<E>(share.0).into_double_public_key();
+		let r = <E as EngineBLS>::Scalar::rand(&mut rng);
+
+		let blinded = share.blind(&r).unwrap();
+		assert_ne!(blinded.0, share.0);
+		assert_ne!(blinded.1, share.1);
+		let blinded_commitment = blind_commitment(&commitment, &r).unwrap();
+		let expected = SecretKeyVT::<E>(blinded.0).into_double_public_key();
+		assert_eq!(
+			(expected.0, expected.1),
+			(blinded_commitment.0, blinded_commitment.1)
+		);
+
+		let unblinded = blinded.unblind(&r).unwrap();
+		assert_eq!((unblinded.0, unblinded.1), (share.0, share.1));
+		let unblinded_commitment =
+			unblind_commitment(&blinded_commitment, &r).unwrap();
+		assert_eq!(
+			(unblinded_commitment.0, unblinded_commitment.1),
+			(commitment.0, commitment.1)
+		);
+
+		let zero = <E as EngineBLS>::Scalar::zero();
+		assert_eq!(
+			share.blind(&zero).err(),
+			Some(AcssError::InvalidBlindingFactor)
+		);
+		assert_eq!(
+			blinded.unblind(&zero).err(),
+			Some(AcssError::InvalidBlindingFactor)
+		);
+		assert_eq!(
+			blind_commitment(&commitment, &zero).err(),
+			Some(AcssError::InvalidBlindingFactor)
+		);
+	}
+
+	#[test]
+	pub fn keypairs_round_trip_through_bytes_and_seeds() {
+		type E = TinyBLS377;
+		let kp = super::Keypair::<E>::from_seed([7u8; 32]);
+		assert_eq!(
+			super::Keypair::<E>::from_seed([7u8; 32]).public().0,
+			kp.public().0
+		);
+		assert_ne!(
+			super::Keypair::<E>::from_seed([8u8; 32]).public().0,
+			kp.public().0
+		);
+		assert_eq!(kp.public().0, kp.0.secret.into_public().0);
+
+		let bytes = kp.to_bytes().unwrap();
+		let decoded = super::Keypair::<E>::from_bytes(&bytes).unwrap();
+		assert_eq!(decoded.public().0, kp.public().0);
+		assert_eq!(decoded.0.secret.0, kp.0.secret.0);
+
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		let zero = vec![0u8; bytes.len()];
+		for bad in [&bytes[1..], &trailing[..], &zero[..]] {
+			assert_eq!(
+				super::Keypair::<E>::from_bytes(bad).err(),
+				Some(SerializationError::Decoding)
+			);
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "serde-secret")]
+	pub fn keypairs_round_trip_through_serde() {
+		type E = TinyBLS377;
+		let kp = super::Keypair::<E>::from_seed([7u8; 32]);
+		let json = serde_json::to_vec(&kp).unwrap();
+		let decoded: super::Keypair<E> = serde_json::from_slice(&json).unwrap();
+		assert_eq!(decoded.public().0, kp.public().0);
+		assert_eq!(decoded.0.secret.0, kp.0.secret.0);
+
+		let zero = serde_json::to_vec(&vec![0u8; kp.to_bytes().unwrap().len()])
+			.unwrap();
+		assert!(serde_json::from_slice::<super::Keypair<E>>(&zero).is_err());
+
+		// committee identities persist without the secret
+		let pk = kp.encryption_key();
+		let json = serde_json::to_vec(&pk).unwrap();
+		assert_eq!(
+			serde_json::from_slice::<EncryptionKey<_>>(&json).unwrap(),
+			pk
+		);
+		assert_eq!(pk.0, kp.public().0);
+	}
+
 	pub fn test_generate_shares_checked<E: EngineBLS>(
 		n: u8,
 		t: u8,
@@ -625,5 +1967,6 @@ pub mod tests {
 		test_generate_shares_checked::<TinyBLS377>(3, 0, 0);
 		test_generate_shares_checked::<TinyBLS377>(0, 0, 0);
 		test_generate_shares_checked::<TinyBLS377>(21, 100, 0);
+		test_generate_shares_checked::<TinyBLS377>(255, 3, 255);
 	}
 }
@@ -0,0 +1,350 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Randomness Beacon
+//!
+//! The committee of a resharing runs a chained, publicly verifiable
+//! randomness beacon with its shares. In every round, each member signs the
+//! round number and the previous round's signature with its share
+//! ([`partial_sign`]), and any t partial signatures interpolate to the
+//! threshold BLS signature of the committee key ([`combine`]). The signature
+//! is unique, so nobody can bias the round once the previous one is fixed,
+//! and its hash is the round's randomness.
+//!
+//! A [`Beacon`] follows the chain from the committee key alone, checking every
+//! round against the key and the round before it.
+
+#[cfg(feature = "participant")]
+use crate::acss::DoubleSecret;
+use crate::{
+	error::AcssError,
+	keys::ShareIndex,
+	params::ThresholdParams,
+	proofs::ser::HashWriter,
+	resharing::Resharing,
+	threshold::{group_key, interpolate_in_exponent},
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{collections::BTreeSet, vec::Vec};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "participant")]
+use w3f_bls::SecretKeyVT;
+use w3f_bls::{DoublePublicKey, EngineBLS, Message, PublicKey, Signature};
+
+/// the signing context of beacon rounds
+pub const BEACON_CONTEXT: &[u8] = b"acss/beacon";
+/// the domain separation label of beacon randomness
+pub const RANDOMNESS_LABEL: &[u8] = b"acss/beacon/randomness";
+
+/// a round of the beacon
+pub struct BeaconRound<E: EngineBLS> {
+	/// the round number, starting from zero
+	pub round: u64,
+	/// the committee's signature over the round number and the previous
+	/// round's signature
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for BeaconRound<E> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<E: EngineBLS> Copy for BeaconRound<E> {}
+
+impl<E: EngineBLS> BeaconRound<E> {
+	/// the randomness of the round, the hash of its signature
+	pub fn randomness(&self) -> [u8; 32] {
+		let mut hasher = Sha256::new().chain_update(RANDOMNESS_LABEL);
+		// a valid signature always encodes
+		let _ = self.signature.0.serialize_compressed(HashWriter(&mut hasher));
+		hasher.finalize().into()
+	}
+
+	/// true if the round follows `previous` (none for round zero) and is
+	/// signed by the committee with key `group_pk`
+	///
+	/// * `group_pk`: The public key of the committee
+	/// * `previous`: The previous round, if any
+	pub fn verify(
+		&self,
+		group_pk: &PublicKey<E>,
+		previous: Option<&BeaconRound<E>>,
+	) -> bool {
+		let expected = previous.map_or(Some(0), |p| p.round.checked_add(1));
+		expected == Some(self.round) &&
+			round_message(self.round, previous).is_ok_and(|message| {
+				self.signature.verify(&message, group_pk)
+			})
+	}
+}
+
+/// a member's signature of a round with its share
+pub struct PartialSignature<E: EngineBLS> {
+	/// the index of the member's share
+	pub index: ShareIndex,
+	/// the signature with the share
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for PartialSignature<E> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<E: EngineBLS> Copy for PartialSignature<E> {}
+
+impl<E: EngineBLS> PartialSignature<E> {
+	/// true if the partial signature of `round` was made with the share
+	/// committed to by `share_pk`
+	///
+	/// * `share_pk`: The double public key of the share at `self.index`
+	/// * `round`: The round number
+	/// * `previous`: The previous round, if any
+	pub fn verify(
+		&self,
+		share_pk: &DoublePublicKey<E>,
+		round: u64,
+		previous: Option<&BeaconRound<E>>,
+	) -> bool {
+		round_message(round, previous).is_ok_and(|message| {
+			self.signature.verify(&message, &PublicKey(share_pk.1))
+		})
+	}
+}
+
+/// sign a round of the beacon with a member's share
+/// fails with `InvalidSignature` if the previous round cannot be encoded
+///
+/// * `share`: The member's share
+/// * `index`: The index of the member's share
+/// * `round`: The round number
+/// * `previous`: The previous round, if any
+#[cfg(feature = "participant")]
+pub fn partial_sign<E: EngineBLS>(
+	share: &DoubleSecret<E>,
+	index: ShareIndex,
+	round: u64,
+	previous: Option<&BeaconRound<E>>,
+) -> Result<PartialSignature<E>, AcssError> {
+	let message = round_message(round, previous)?;
+	let signature = SecretKeyVT::<E>(*share.expose_secret()).sign(&message);
+	Ok(PartialSignature { index, signature })
+}
+
+/// combine the partial signatures of at least t members into a round
+/// fails with `ShareIndexOutOfRange` if a partial signature is not of the
+/// resharing, with `DuplicateShareIndex` if a share signed twice, with
+/// `InvalidSignature` if a partial signature does not verify, and with
+/// `InsufficientValidPoK` if there are fewer than t partial signatures
+///
+/// * `resharing`: The resharing that dealt the committee's shares
+/// * `params`: The threshold parameters of the resharing
+/// * `round`: The round number
+/// * `previous`: The previous round, if any
+/// * `partials`: The partial signatures
+pub fn combine<E: EngineBLS>(
+	resharing: &Resharing<E>,
+	params: ThresholdParams,
+	round: u64,
+	previous: Option<&BeaconRound<E>>,
+	partials: &[PartialSignature<E>],
+) -> Result<BeaconRound<E>, AcssError> {
+	params.check_len(resharing.len())?;
+	let mut indices = BTreeSet::new();
+	for partial in partials {
+		let share_pk = resharing
+			.share_public_key(partial.index)
+			.ok_or(AcssError::ShareIndexOutOfRange)?;
+		if !indices.insert(partial.index) {
+			return Err(AcssError::DuplicateShareIndex);
+		}
+		if !partial.verify(share_pk, round, previous) {
+			return Err(AcssError::InvalidSignature);
+		}
+	}
+	let t = params.t() as usize;
+	if partials.len() < t {
+		return Err(AcssError::InsufficientValidPoK);
+	}
+
+	let points: Vec<E::SignatureGroup> =
+		partials[..t].iter().map(|p| p.signature.0).collect();
+	let xs: Vec<E::Scalar> =
+		partials[..t].iter().map(|p| p.index.to_scalar()).collect();
+	let signature = Signature(interpolate_in_exponent(&points, &xs)?);
+	Ok(BeaconRound { round, signature })
+}
+
+/// a follower of the beacon of a committee
+pub struct Beacon<E: EngineBLS> {
+	group_pk: PublicKey<E>,
+	latest: Option<BeaconRound<E>>,
+}
+
+impl<E: EngineBLS> Beacon<E> {
+	/// follow the beacon of the committee with public key `group_pk` from
+	/// round zero
+	pub fn new(group_pk: PublicKey<E>) -> Self {
+		Self { group_pk, latest: None }
+	}
+
+	/// follow the beacon of the committee a resharing was dealt to
+	/// fails as `threshold::group_key` does
+	///
+	/// * `resharing`: The resharing that dealt the committee's shares
+	/// * `params`: The threshold parameters of the resharing
+	pub fn for_resharing(
+		resharing: &Resharing<E>,
+		params: ThresholdParams,
+	) -> Result<Self, AcssError> {
+		Ok(Self::new(PublicKey(group_key(resharing, params)?.0)))
+	}
+
+	/// the public key of the committee
+	pub fn group_pk(&self) -> &PublicKey<E> {
+		&self.group_pk
+	}
+
+	/// the latest verified round, if any
+	pub fn latest(&self) -> Option<&BeaconRound<E>> {
+		self.latest.as_ref()
+	}
+
+	/// the number of the next round
+	pub fn next_round(&self) -> u64 {
+		self.latest.map_or(0, |latest| latest.round.saturating_add(1))
+	}
+
+	/// verify the next round and move the beacon to it, outputting its
+	/// randomness
+	/// fails with `InvalidSignature` unless the round follows the latest one
+	/// and is signed by the committee
+	///
+	/// * `round`: The next round
+	pub fn advance(
+		&mut self,
+		round: BeaconRound<E>,
+	) -> Result<[u8; 32], AcssError> {
+		if !round.verify(&self.group_pk, self.latest.as_ref()) {
+			return Err(AcssError::InvalidSignature);
+		}
+		self.latest = Some(round);
+		Ok(round.randomness())
+	}
+}
+
+/// the message signed in a round, the round number followed by the previous
+/// signature
+fn round_message<E: EngineBLS>(
+	round: u64,
+	previous: Option<&BeaconRound<E>>,
+) -> Result<Message, AcssError> {
+	let mut bytes = round.to_be_bytes().to_vec();
+	if let Some(previous) = previous {
+		previous
+			.signature
+			.0
+			.serialize_compressed(&mut bytes)
+			.map_err(|_| AcssError::InvalidSignature)?;
+	}
+	Ok(Message::new(BEACON_CONTEXT, &bytes))
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::acss::Keypair;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	#[test]
+	fn committees_run_a_verifiable_chained_beacon() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<Keypair<E>> = (0..4)
+			.map(|_| Keypair(KeypairVT::<E>::generate(&mut rng)))
+			.collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|m| *m.public()).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 3).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut rng).unwrap();
+		let single_dealer = ThresholdParams::new(1, 1).unwrap();
+		let shares: Vec<DoubleSecret<E>> = members
+			.iter()
+			.zip(resharing.poks())
+			.map(|(m, pok)| {
+				m.recover(core::slice::from_ref(pok), single_dealer).unwrap()
+			})
+			.collect();
+		let sign = |round: u64, previous: Option<&BeaconRound<E>>| {
+			shares
+				.iter()
+				.enumerate()
+				.map(|(position, share)| {
+					let index = ShareIndex::from_position(position).unwrap();
+					partial_sign(share, index, round, previous).unwrap()
+				})
+				.collect::<Vec<_>>()
+		};
+
+		let mut beacon = Beacon::for_resharing(&resharing, params).unwrap();
+		let partials = sign(0, None);
+		let genesis =
+			combine(&resharing, params, 0, None, &partials[1..]).unwrap();
+		// any t members produce the same round
+		let other =
+			combine(&resharing, params, 0, None, &partials[..3]).unwrap();
+		assert_eq!(genesis.signature.0, other.signature.0);
+		assert_eq!(beacon.advance(genesis), Ok(genesis.randomness()));
+
+		let partials = sign(1, Some(&genesis));
+		let next =
+			combine(&resharing, params, 1, Some(&genesis), &partials).unwrap();
+		assert_ne!(next.randomness(), genesis.randomness());
+		// rounds are checked against the chain
+		assert_eq!(beacon.advance(genesis), Err(AcssError::InvalidSignature));
+		let mut skipped = next;
+		skipped.round = 2;
+		assert_eq!(beacon.advance(skipped), Err(AcssError::InvalidSignature));
+		assert!(beacon.advance(next).is_ok());
+		assert_eq!(beacon.next_round(), 2);
+
+		assert_eq!(
+			combine(&resharing, params, 1, Some(&genesis), &partials[..2])
+				.err(),
+			Some(AcssError::InsufficientValidPoK)
+		);
+		let stale = sign(1, None);
+		assert_eq!(
+			combine(&resharing, params, 1, Some(&genesis), &stale).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		let repeated = [partials[0], partials[0], partials[1]];
+		assert_eq!(
+			combine(&resharing, params, 1, Some(&genesis), &repeated).err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+	}
+}
@@ -0,0 +1,326 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Dealing Builder
+//!
+//! [`ReshareBuilder`] collects the options of a dealing one at a time, so new
+//! options do not grow the positional arguments of `reshare`. A builder is
+//! started from the secret with [`DoubleSecret::builder`].
+//!
+//! Long dealings can report their progress and be cancelled by attaching a
+//! [`Monitor`].
+//!
+//! A dealing can be bound to the session it is made for and to associated
+//! data (e.g. the block headers it answers), so that its proofs cannot be
+//! replayed in another session. Bound proofs only verify with
+//! `BatchPoK::verify_bound`, so recipients recover them with
+//! [`HighThresholdACSS::recover_bound`] and the binding of
+//! [`Binding::session`].
+
+use crate::{
+	acss::{DealingMode, DealingOptions, DoubleSecret, HighThresholdACSS},
+	error::AcssError,
+	keys::{committee_keys, CommitteeKey},
+	params::ThresholdParams,
+	progress::Monitor,
+	proofs::challenge::{Binding, TranscriptBinder},
+	resharing::Resharing,
+	rng::SecureRng,
+};
+use ark_std::{rand::SeedableRng, vec::Vec};
+use rand_chacha::ChaCha20Rng;
+use w3f_bls::{EngineBLS, PublicKey};
+
+/// builds a resharing of a double secret
+pub struct ReshareBuilder<'a, E: EngineBLS> {
+	secret: &'a DoubleSecret<E>,
+	committee: Option<Vec<PublicKey<E>>>,
+	threshold: Option<u8>,
+	mode: DealingMode,
+	detection_tags: bool,
+	session: Option<[u8; 32]>,
+	aad: Option<TranscriptBinder>,
+	seed: Option<[u8; 32]>,
+	monitor: Monitor<'a>,
+}
+
+impl<'a, E: EngineBLS> ReshareBuilder<'a, E> {
+	/// start building a resharing of `secret`
+	pub fn new(secret: &'a DoubleSecret<E>) -> Self {
+		Self {
+			secret,
+			committee: None,
+			threshold: None,
+			mode: DealingMode::default(),
+			detection_tags: false,
+			session: None,
+			aad: None,
+			seed: None,
+			monitor: Monitor::new(),
+		}
+	}
+
+	/// the committee to reshare to (required)
+	pub fn committee<K: CommitteeKey<E>>(mut self, committee: &[K]) -> Self {
+		self.committee = Some(committee_keys(committee));
+		self
+	}
+
+	/// the number of shares required to recover (required)
+	pub fn threshold(mut self, t: u8) -> Self {
+		self.threshold = Some(t);
+		self
+	}
+
+	/// how to sample the ephemeral keys, `PerRecipient` by default
+	pub fn mode(mut self, mode: DealingMode) -> Self {
+		self.mode = mode;
+		self
+	}
+
+	/// tag each recipient's entry so that recipients can find theirs in the
+	/// compact transcript without trial decryption, see
+	/// [`crate::transcript::detect_entries`]
+	pub fn detection_tags(mut self) -> Self {
+		self.detection_tags = true;
+		self
+	}
+
+	/// bind the dealing to the session it is made for
+	///
+	/// * `id`: The identifier of the session
+	pub fn session(mut self, id: [u8; 32]) -> Self {
+		self.session = Some(id);
+		self
+	}
+
+	/// bind the dealing to associated data, which can be given in pieces
+	/// by calling this repeatedly, the pieces being concatenated
+	///
+	/// * `bytes`: The next piece of the associated data
+	pub fn aad(mut self, bytes: &[u8]) -> Self {
+		self.aad.get_or_insert_with(TranscriptBinder::new).absorb(bytes);
+		self
+	}
+
+	/// draw all randomness from ChaCha20 seeded with `seed` instead of the rng
+	/// passed to `build`, so that the dealing can be reproduced
+	///
+	/// the seed must be secret and never reused, anyone who learns it learns
+	/// every share
+	pub fn deterministic(mut self, seed: [u8; 32]) -> Self {
+		self.seed = Some(seed);
+		self
+	}
+
+	/// report the number of recipients dealt to, and stop dealing once the
+	/// monitor's token is cancelled
+	pub fn monitor(mut self, monitor: Monitor<'a>) -> Self {
+		self.monitor = monitor;
+		self
+	}
+
+	/// deal the secret, binding every proof to the session and associated
+	/// data if either was set
+	/// fails with `InvalidCommittee` if no committee was set, with
+	/// `InvalidParams` if no threshold was set or it does not fit the
+	/// committee, and with `Cancelled` if the monitor was cancelled
+	///
+	/// * `rng`: A CSPRNG, unused if a seed was set
+	pub fn build<R: SecureRng>(
+		self,
+		rng: R,
+	) -> Result<Resharing<E>, AcssError> {
+		let committee = self.committee.ok_or(AcssError::InvalidCommittee)?;
+		let t = self.threshold.ok_or(AcssError::InvalidParams)?;
+		let params = ThresholdParams::for_committee(&committee, t)?;
+		// unset parts bind to no session and to empty data
+		let binding = match (self.session, self.aad) {
+			(None, None) => None,
+			(session, aad) => Some(Binding::session(
+				&session.unwrap_or_default(),
+				&aad.unwrap_or_default().finalize(),
+			)),
+		};
+		let options =
+			DealingOptions { detection_tags: self.detection_tags, binding };
+
+		match self.seed {
+			Some(seed) => HighThresholdACSS::<E>::reshare_monitored(
+				self.secret,
+				&committee,
+				params,
+				self.mode,
+				options,
+				ChaCha20Rng::from_seed(seed),
+				self.monitor,
+			),
+			None => HighThresholdACSS::<E>::reshare_monitored(
+				self.secret,
+				&committee,
+				params,
+				self.mode,
+				options,
+				rng,
+				self.monitor,
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::keys::{PublicKey as EncryptionKey, SecretKey};
+	use ark_ff::UniformRand;
+	use ark_std::vec::Vec;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	fn setup() -> (DoubleSecret<E>, Vec<PublicKey<E>>) {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let committee =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng).public).collect();
+		(secret, committee)
+	}
+
+	#[test]
+	fn builder_matches_positional_reshare() {
+		let (secret, committee) = setup();
+		let seed = [7u8; 32];
+
+		for mode in [DealingMode::SharedEphemeral, DealingMode::PerRecipient] {
+			let built = secret
+				.builder()
+				.committee(&committee)
+				.threshold(2)
+				.mode(mode)
+				.deterministic(seed)
+				.build(ChaCha20Rng::seed_from_u64(1))
+				.unwrap();
+			let expected = secret
+				.reshare_with_mode(
+					&committee,
+					ThresholdParams::new(3, 2).unwrap(),
+					mode,
+					ChaCha20Rng::from_seed(seed),
+				)
+				.unwrap();
+
+			assert_eq!(built.len(), expected.len());
+			for (a, b) in built.iter().zip(expected.iter()) {
+				assert_eq!(a.0 .0, b.0 .0);
+				assert_eq!((a.1 .0, a.1 .1), (b.1 .0, b.1 .1));
+				assert_eq!(a.2, b.2);
+			}
+		}
+	}
+
+	#[test]
+	fn session_and_aad_bind_every_proof() {
+		let mut rng = ChaCha20Rng::seed_from_u64(2);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let keys: Vec<_> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<_> = keys.iter().map(|kp| kp.public).collect();
+		let session = [5u8; 32];
+
+		for mode in [DealingMode::SharedEphemeral, DealingMode::PerRecipient] {
+			let resharing = secret
+				.builder()
+				.committee(&committee)
+				.threshold(2)
+				.mode(mode)
+				.session(session)
+				.aad(b"headers ")
+				.aad(b"1..=1000")
+				.build(&mut rng)
+				.unwrap();
+			let binding =
+				Binding::session(&session, &Binding::of(b"headers 1..=1000"));
+			let other_session =
+				Binding::session(&[6u8; 32], &Binding::of(b"headers 1..=1000"));
+			let other_aad =
+				Binding::session(&session, &Binding::of(b"headers 1..=999"));
+
+			for ((_, _, pok), pk) in resharing.iter().zip(&committee) {
+				let pk = EncryptionKey(pk.0);
+				assert!(pok.verify_bound(pk, &binding));
+				assert!(!pok.verify(pk));
+				assert!(!pok.verify_bound(pk, &other_session));
+				assert!(!pok.verify_bound(pk, &other_aad));
+			}
+
+			let single_dealer = ThresholdParams::new(1, 1).unwrap();
+			let sk = SecretKey::from(&keys[0].secret);
+			let pok = core::slice::from_ref(
+				resharing.for_recipient(&committee[0]).unwrap(),
+			);
+			assert!(HighThresholdACSS::<E>::recover_bound(
+				&sk,
+				pok,
+				single_dealer,
+				&binding
+			)
+			.is_ok());
+			assert_eq!(
+				HighThresholdACSS::<E>::recover_bound(
+					&sk,
+					pok,
+					single_dealer,
+					&other_aad
+				)
+				.err(),
+				Some(AcssError::InsufficientValidPoK)
+			);
+			assert_eq!(
+				HighThresholdACSS::<E>::recover(&sk, pok, single_dealer).err(),
+				Some(AcssError::InsufficientValidPoK)
+			);
+		}
+	}
+
+	#[test]
+	fn builder_requires_a_committee_and_threshold() {
+		let (secret, committee) = setup();
+		let rng = || ChaCha20Rng::seed_from_u64(0);
+
+		assert_eq!(
+			secret.builder().threshold(2).build(rng()).err(),
+			Some(AcssError::InvalidCommittee)
+		);
+		assert_eq!(
+			secret.builder().committee(&committee).build(rng()).err(),
+			Some(AcssError::InvalidParams)
+		);
+		assert_eq!(
+			secret
+				.builder()
+				.committee(&committee)
+				.threshold(4)
+				.build(rng())
+				.err(),
+			Some(AcssError::InvalidParams)
+		);
+	}
+}
@@ -0,0 +1,254 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Acknowledgments and Output Certificates
+//!
+//! Once a member has verified the share a dealing carries for it, it signs a
+//! [`ReadyAck`] of the dealing's digest. [`aggregate`] sums the
+//! acknowledgments of a dealing into a single BLS signature, which an
+//! [`OutputCertificate`] carries once a quorum of 2f + 1 members signed,
+//! where f is the number of Byzantine members the committee tolerates.
+//! Anyone who knows the committee can verify it, so downstream consumers
+//! (e.g. a chain finalizing the dealing) learn that the sharing completed and
+//! was agreed on without replaying it.
+//!
+//! Acknowledgments are [`Vote`]s under [`ACK_SIGNATURE_CONTEXT`] and an
+//! output certificate is a [`QuorumCert`] of them, see [`crate::quorum`].
+
+use crate::{
+	error::AcssError,
+	keys::{CommitteeKey, ShareIndex},
+	params::max_byzantine_faults,
+	quorum::{verify_aggregate, QuorumCert, Vote},
+};
+use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "participant")]
+use w3f_bls::KeypairVT;
+use w3f_bls::{EngineBLS, Signature};
+
+/// the domain separation label of dealing digests
+pub const DIGEST_LABEL: &[u8] = b"acss/dealing-digest";
+/// the signing context of acknowledgments
+pub const ACK_SIGNATURE_CONTEXT: &[u8] = b"acss/ack";
+
+/// the digest of the compact transcript of a dealing
+pub fn dealing_digest(transcript: &[u8]) -> [u8; 32] {
+	Sha256::new()
+		.chain_update(DIGEST_LABEL)
+		.chain_update(transcript)
+		.finalize()
+		.into()
+}
+
+/// the number of acknowledgments a certificate needs in a committee of `n`,
+/// 2f + 1 for the f Byzantine members it tolerates
+pub fn quorum(n: u8) -> u8 {
+	2 * max_byzantine_faults(n) + 1
+}
+
+/// a member's acknowledgment that a dealing carried a valid share for it
+pub struct ReadyAck<E: EngineBLS> {
+	/// the digest of the dealing
+	pub digest: [u8; 32],
+	/// the index of the acknowledging member in the committee
+	pub member: ShareIndex,
+	/// the member's signature over the digest
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for ReadyAck<E> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<E: EngineBLS> Copy for ReadyAck<E> {}
+
+impl<E: EngineBLS> From<Vote<E>> for ReadyAck<E> {
+	fn from(vote: Vote<E>) -> Self {
+		Self {
+			digest: vote.digest,
+			member: vote.member,
+			signature: vote.signature,
+		}
+	}
+}
+
+impl<E: EngineBLS> From<ReadyAck<E>> for Vote<E> {
+	fn from(ack: ReadyAck<E>) -> Self {
+		Self {
+			digest: ack.digest,
+			member: ack.member,
+			signature: ack.signature,
+		}
+	}
+}
+
+impl<E: EngineBLS> ReadyAck<E> {
+	/// acknowledge the dealing with digest `digest`, as the member at index
+	/// `member`
+	///
+	/// * `digest`: The digest of the dealing
+	/// * `member`: The index of the acknowledging member
+	/// * `keypair`: The acknowledging member's keypair
+	#[cfg(feature = "participant")]
+	pub fn sign(
+		digest: [u8; 32],
+		member: ShareIndex,
+		keypair: &KeypairVT<E>,
+	) -> Self {
+		Vote::sign(ACK_SIGNATURE_CONTEXT, digest, member, keypair).into()
+	}
+
+	/// true if the acknowledgment is signed by the member of `committee` at
+	/// its index
+	///
+	/// * `committee`: The public keys of the committee, in share order
+	pub fn verify<K: CommitteeKey<E>>(&self, committee: &[K]) -> bool {
+		Vote::from(*self).verify(ACK_SIGNATURE_CONTEXT, committee)
+	}
+}
+
+/// aggregate acknowledgments of the same dealing into one signature
+/// outputs the indices of the signers, in increasing order, and the sum of
+/// their signatures. The acknowledgments are not checked, see
+/// `OutputCertificate::verify`.
+///
+/// fails with `InvalidCommittee` if there are no acknowledgments, with
+/// `InvalidSignature` if they acknowledge different dealings, and with
+/// `DuplicateShareIndex` if a member acknowledged twice
+///
+/// * `acks`: The acknowledgments
+pub fn aggregate<E: EngineBLS>(
+	acks: &[ReadyAck<E>],
+) -> Result<(Vec<ShareIndex>, Signature<E>), AcssError> {
+	let votes: Vec<Vote<E>> = acks.iter().copied().map(Vote::from).collect();
+	let certificate = QuorumCert::aggregate(&votes)?;
+	Ok((certificate.signers, certificate.signature))
+}
+
+/// a quorum's aggregated acknowledgment of a dealing
+pub struct OutputCertificate<E: EngineBLS> {
+	/// the digest of the dealing
+	pub digest: [u8; 32],
+	/// the indices of the acknowledging members, in increasing order
+	pub signers: Vec<ShareIndex>,
+	/// the sum of their acknowledgments
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> OutputCertificate<E> {
+	/// certify a dealing from acknowledgments of it
+	/// fails as `aggregate` does
+	///
+	/// * `acks`: The acknowledgments, which must all be of the same dealing
+	pub fn from_acks(acks: &[ReadyAck<E>]) -> Result<Self, AcssError> {
+		let (signers, signature) = aggregate(acks)?;
+		Ok(Self { digest: acks[0].digest, signers, signature })
+	}
+
+	/// the certificate of a quorum of acknowledgments
+	///
+	/// * `certificate`: The aggregated acknowledgments
+	pub fn from_quorum(certificate: QuorumCert<E>) -> Self {
+		let QuorumCert { digest, signers, signature } = certificate;
+		Self { digest, signers, signature }
+	}
+
+	/// true if a quorum of distinct members of `committee` acknowledged the
+	/// dealing
+	///
+	/// * `committee`: The public keys of the committee, in share order
+	pub fn verify<K: CommitteeKey<E>>(&self, committee: &[K]) -> bool {
+		let Ok(n) = u8::try_from(committee.len()) else {
+			return false;
+		};
+		verify_aggregate(
+			ACK_SIGNATURE_CONTEXT,
+			&self.digest,
+			&self.signers,
+			&self.signature,
+			committee,
+			quorum(n) as usize,
+		)
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{PublicKey, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	#[test]
+	fn certificates_require_a_quorum_of_valid_acks() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<KeypairVT<E>> =
+			(0..4).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let digest = dealing_digest(b"transcript");
+		let acks: Vec<ReadyAck<E>> = members
+			.iter()
+			.enumerate()
+			.map(|(position, kp)| {
+				let index = ShareIndex::from_position(position).unwrap();
+				ReadyAck::sign(digest, index, kp)
+			})
+			.collect();
+		assert!(acks.iter().all(|ack| ack.verify(&committee)));
+		assert!(!acks[3].verify(&committee[..3]));
+		assert_eq!(quorum(4), 3);
+
+		let reversed: Vec<ReadyAck<E>> =
+			acks[1..].iter().rev().copied().collect();
+		let certificate = OutputCertificate::from_acks(&reversed).unwrap();
+		assert_eq!(
+			certificate.signers,
+			[2, 3, 4].map(|i| ShareIndex::new(i).unwrap())
+		);
+		assert!(certificate.verify(&committee));
+		assert!(!certificate.verify(&committee[..3]));
+
+		let too_few = OutputCertificate::from_acks(&acks[..2]).unwrap();
+		assert!(!too_few.verify(&committee));
+
+		let mut forged = OutputCertificate::from_acks(&acks[..3]).unwrap();
+		forged.signature = acks[0].signature;
+		assert!(!forged.verify(&committee));
+
+		let other = ReadyAck::sign(
+			dealing_digest(b"other transcript"),
+			acks[2].member,
+			&members[2],
+		);
+		assert_eq!(
+			aggregate(&[acks[0], acks[1], other]).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		assert_eq!(
+			aggregate(&[acks[0], acks[0], acks[1]]).err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+		assert_eq!(
+			aggregate::<E>(&[]).err(),
+			Some(AcssError::InvalidCommittee)
+		);
+	}
+}
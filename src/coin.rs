@@ -0,0 +1,359 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Common Coins
+//!
+//! A commit-reveal coin flip for protocol layers that need common randomness.
+//! Every member samples a value, broadcasts a Pedersen commitment to it
+//! ([`CoinShare::commit`]), and opens the commitment once the commitments are
+//! in ([`CoinShare::reveal`]). The coin of a round is the hash of the sum of
+//! the revealed values, so it is uniform as long as one member is honest.
+//!
+//! Each commitment carries a proof of knowledge of its opening, bound to the
+//! round and the member. Without it, a member could commit to the negated sum
+//! of the others' commitments and fix the coin once they reveal.
+//!
+//! [`CoinFlip`] collects the messages of a round. Commitments close with the
+//! first accepted reveal, and the coin is only output once every committed
+//! member revealed: a member withholding its reveal can stall the round, and
+//! is named by [`CoinFlip::missing`], but cannot pick between outcomes.
+
+use crate::{
+	error::AcssError,
+	keys::ShareIndex,
+	proofs::{nums_generator, ser::HashWriter},
+	rng::SecureRng,
+	wipe,
+};
+use ark_ec::CurveGroup;
+use ark_ff::{PrimeField, UniformRand};
+use ark_serialize::CanonicalSerialize;
+use ark_std::collections::BTreeMap;
+use core::fmt;
+use sha2::{Digest, Sha256};
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+use zeroize::Zeroize;
+#[cfg(feature = "zeroize")]
+use zeroize::ZeroizeOnDrop;
+
+/// the label the commitment generator H is hashed from
+pub const GENERATOR_LABEL: &[u8] = b"acss/coin/generator";
+/// the domain separation label of opening proof challenges
+pub const CHALLENGE_LABEL: &[u8] = b"acss/coin/challenge";
+/// the domain separation label of coins
+pub const COIN_LABEL: &[u8] = b"acss/coin";
+
+/// a member's secret contribution to a coin
+/// with the `zeroize` feature, it is wiped when it is dropped
+pub struct CoinShare<C: CurveGroup> {
+	value: C::ScalarField,
+	blinding: C::ScalarField,
+}
+
+impl<C: CurveGroup> Zeroize for CoinShare<C> {
+	fn zeroize(&mut self) {
+		self.value.zeroize();
+		self.blinding.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveGroup> Drop for CoinShare<C> {
+	fn drop(&mut self) {
+		self.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveGroup> ZeroizeOnDrop for CoinShare<C> {}
+
+impl<C: CurveGroup> fmt::Debug for CoinShare<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("CoinShare(<redacted>)")
+	}
+}
+
+/// a commitment to a coin share with a proof of knowledge of its opening
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoinCommitment<C: CurveGroup> {
+	/// the Pedersen commitment vG + rH
+	pub commitment: C,
+	/// the proof commitment aG + bH
+	pub a: C,
+	/// the responses (a + ev, b + er)
+	pub z: [C::ScalarField; 2],
+}
+
+/// the opening of a coin commitment
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoinReveal<C: CurveGroup> {
+	/// the committed value
+	pub value: C::ScalarField,
+	/// the blinding factor of the commitment
+	pub blinding: C::ScalarField,
+}
+
+impl<C: CurveGroup> CoinShare<C> {
+	/// sample a fresh contribution
+	pub fn new<R: SecureRng>(mut rng: R) -> Self {
+		Self {
+			value: C::ScalarField::rand(&mut rng),
+			blinding: C::ScalarField::rand(&mut rng),
+		}
+	}
+
+	/// commit to the contribution for `round`, as the member at `member`
+	///
+	/// * `round`: The round of the coin
+	/// * `member`: The index of the committing member
+	/// * `rng`: A CSPRNG
+	pub fn commit<R: SecureRng>(
+		&self,
+		round: u64,
+		member: ShareIndex,
+		mut rng: R,
+	) -> Result<CoinCommitment<C>, AcssError> {
+		let (g, h) = (C::generator(), nums_generator::<C>(GENERATOR_LABEL));
+		let commitment = g * self.value + h * self.blinding;
+		let mut k =
+			[C::ScalarField::rand(&mut rng), C::ScalarField::rand(&mut rng)];
+		let a = g * k[0] + h * k[1];
+		let e = challenge(round, member, &commitment, &a);
+		let z = e.map(|e| [k[0] + e * self.value, k[1] + e * self.blinding]);
+		wipe(&mut k);
+		Ok(CoinCommitment { commitment, a, z: z? })
+	}
+
+	/// the opening of the commitment, to broadcast once the commitments of
+	/// the round are in
+	pub fn reveal(&self) -> CoinReveal<C> {
+		CoinReveal { value: self.value, blinding: self.blinding }
+	}
+}
+
+/// the commitments and reveals of one round of coin flipping
+pub struct CoinFlip<C: CurveGroup> {
+	round: u64,
+	// the commitment generator, hashed to the curve once per round
+	h: C,
+	commitments: BTreeMap<ShareIndex, C>,
+	reveals: BTreeMap<ShareIndex, C::ScalarField>,
+}
+
+impl<C: CurveGroup> CoinFlip<C> {
+	/// start collecting the messages of `round`
+	pub fn new(round: u64) -> Self {
+		Self {
+			round,
+			h: nums_generator(GENERATOR_LABEL),
+			commitments: BTreeMap::new(),
+			reveals: BTreeMap::new(),
+		}
+	}
+
+	/// the round of the coin
+	pub fn round(&self) -> u64 {
+		self.round
+	}
+
+	/// accept a member's commitment
+	/// fails with `InvalidProof` if the proof of knowledge does not verify,
+	/// and with `ReplayedMessage` if the member already committed or the
+	/// commitments are closed
+	///
+	/// * `member`: The index of the committing member
+	/// * `commitment`: The member's commitment
+	pub fn commit(
+		&mut self,
+		member: ShareIndex,
+		commitment: &CoinCommitment<C>,
+	) -> Result<(), AcssError> {
+		if !self.reveals.is_empty() || self.commitments.contains_key(&member) {
+			return Err(AcssError::ReplayedMessage);
+		}
+		let e = challenge(
+			self.round,
+			member,
+			&commitment.commitment,
+			&commitment.a,
+		)?;
+		if C::generator() * commitment.z[0] + self.h * commitment.z[1] !=
+			commitment.a + commitment.commitment * e
+		{
+			return Err(AcssError::InvalidProof);
+		}
+		self.commitments.insert(member, commitment.commitment);
+		Ok(())
+	}
+
+	/// accept a member's reveal, closing the commitments
+	/// outputs the coin once every committed member revealed
+	///
+	/// fails with `InvalidCommitment` if the member did not commit or the
+	/// reveal does not open its commitment, and with `ReplayedMessage` if the
+	/// member already revealed
+	///
+	/// * `member`: The index of the revealing member
+	/// * `reveal`: The member's opening
+	pub fn reveal(
+		&mut self,
+		member: ShareIndex,
+		reveal: &CoinReveal<C>,
+	) -> Result<Option<[u8; 32]>, AcssError> {
+		let commitment = self
+			.commitments
+			.get(&member)
+			.ok_or(AcssError::InvalidCommitment)?;
+		if self.reveals.contains_key(&member) {
+			return Err(AcssError::ReplayedMessage);
+		}
+		if C::generator() * reveal.value + self.h * reveal.blinding !=
+			*commitment
+		{
+			return Err(AcssError::InvalidCommitment);
+		}
+		self.reveals.insert(member, reveal.value);
+		Ok(self.coin())
+	}
+
+	/// the members that committed but have not revealed yet
+	pub fn missing(&self) -> impl Iterator<Item = ShareIndex> + '_ {
+		self.commitments
+			.keys()
+			.copied()
+			.filter(|member| !self.reveals.contains_key(member))
+	}
+
+	/// the coin, once every committed member revealed
+	pub fn coin(&self) -> Option<[u8; 32]> {
+		if self.commitments.is_empty() ||
+			self.reveals.len() != self.commitments.len()
+		{
+			return None;
+		}
+		let sum: C::ScalarField = self.reveals.values().copied().sum();
+		let mut hasher = Sha256::new()
+			.chain_update(COIN_LABEL)
+			.chain_update(self.round.to_be_bytes());
+		// a scalar always encodes
+		let _ = sum.serialize_compressed(HashWriter(&mut hasher));
+		Some(hasher.finalize().into())
+	}
+}
+
+/// the challenge of an opening proof, bound to the round and the member
+fn challenge<C: CurveGroup>(
+	round: u64,
+	member: ShareIndex,
+	commitment: &C,
+	a: &C,
+) -> Result<C::ScalarField, AcssError> {
+	let mut h = Shake256::default();
+	h.update(CHALLENGE_LABEL);
+	h.update(&round.to_be_bytes());
+	h.update(&[member.get()]);
+	commitment
+		.serialize_compressed(HashWriter(&mut h))
+		.and_then(|_| a.serialize_compressed(HashWriter(&mut h)))
+		.map_err(|_| AcssError::InvalidCommitment)?;
+	let mut bytes = [0u8; 64];
+	h.finalize_xof().read(&mut bytes);
+	Ok(C::ScalarField::from_le_bytes_mod_order(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_std::{rand::SeedableRng, vec::Vec};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{EngineBLS, TinyBLS377};
+
+	type C = <TinyBLS377 as EngineBLS>::SignatureGroup;
+
+	#[test]
+	fn members_flip_a_common_coin() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let shares: Vec<CoinShare<C>> =
+			(0..3).map(|_| CoinShare::new(&mut rng)).collect();
+		let index = |position| ShareIndex::from_position(position).unwrap();
+		let commitments: Vec<CoinCommitment<C>> = shares
+			.iter()
+			.enumerate()
+			.map(|(p, share)| share.commit(7, index(p), &mut rng).unwrap())
+			.collect();
+
+		let mut flips: Vec<CoinFlip<C>> =
+			(0..2).map(|_| CoinFlip::new(7)).collect();
+		for flip in flips.iter_mut() {
+			for (p, commitment) in commitments.iter().enumerate() {
+				flip.commit(index(p), commitment).unwrap();
+			}
+		}
+		let flip = &mut flips[0];
+		assert_eq!(
+			flip.commit(index(0), &commitments[0]),
+			Err(AcssError::ReplayedMessage)
+		);
+		assert_eq!(
+			flip.reveal(index(0), &shares[1].reveal()),
+			Err(AcssError::InvalidCommitment)
+		);
+		assert_eq!(flip.reveal(index(0), &shares[0].reveal()), Ok(None));
+		assert_eq!(flip.missing().collect::<Vec<_>>(), [index(1), index(2)]);
+		assert_eq!(
+			flip.reveal(index(0), &shares[0].reveal()),
+			Err(AcssError::ReplayedMessage)
+		);
+		assert_eq!(flip.reveal(index(1), &shares[1].reveal()), Ok(None));
+		let coin = flip.reveal(index(2), &shares[2].reveal()).unwrap().unwrap();
+
+		// every member computes the same coin, whatever the order of reveals
+		let other = &mut flips[1];
+		for p in [2, 1] {
+			other.reveal(index(p), &shares[p].reveal()).unwrap();
+		}
+		assert_eq!(other.reveal(index(0), &shares[0].reveal()), Ok(Some(coin)));
+		assert_eq!(CoinFlip::<C>::new(8).coin(), None);
+	}
+
+	#[test]
+	fn commitments_are_bound_to_their_member_and_round() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let share = CoinShare::<C>::new(&mut rng);
+		let one = ShareIndex::new(1).unwrap();
+		let two = ShareIndex::new(2).unwrap();
+		let commitment = share.commit(7, one, &mut rng).unwrap();
+
+		assert_eq!(
+			CoinFlip::new(7).commit(two, &commitment),
+			Err(AcssError::InvalidProof)
+		);
+		assert_eq!(
+			CoinFlip::new(8).commit(one, &commitment),
+			Err(AcssError::InvalidProof)
+		);
+
+		// commitments close once the first reveal is in
+		let mut flip = CoinFlip::new(7);
+		flip.commit(one, &commitment).unwrap();
+		flip.reveal(one, &share.reveal()).unwrap();
+		let late =
+			CoinShare::<C>::new(&mut rng).commit(7, two, &mut rng).unwrap();
+		assert_eq!(flip.commit(two, &late), Err(AcssError::ReplayedMessage));
+	}
+}
@@ -0,0 +1,322 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Polynomial Commitments
+//!
+//! A dealing can publish a commitment to the polynomials it evaluated, so
+//! that each recipient can check that its share (and blinding share) lies on
+//! the same polynomials as everyone else's. The scheme is a type parameter of
+//! [`HighThresholdACSS::reshare_committed`], trading the size of the
+//! commitment against its assumptions:
+//!    1) [`Feldman`] commits to the coefficients of the secret polynomial, t
+//!       points, revealing `f(x) * G` for every x
+//!    2) [`Pedersen`] commits to both polynomials at once, t points, hiding the
+//!       secret polynomial behind the blinding one
+//!
+//! Neither needs a trusted setup. A constant size scheme (e.g. KZG, one point
+//! and an opening per share, at the cost of a trusted setup) implements the
+//! same trait.
+//!
+//! [`HighThresholdACSS::reshare_committed`]:
+//! crate::acss::HighThresholdACSS::reshare_committed
+
+#[cfg(feature = "participant")]
+use crate::{
+	acss::{decrypt_pok, Keypair},
+	error::AcssError,
+	keys::{SecretKey, ShareIndex},
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+	wipe,
+};
+use crate::{proofs::nums_generator, resharing::Resharing};
+use ark_ec::CurveGroup;
+use ark_std::vec::Vec;
+use w3f_bls::EngineBLS;
+
+/// the label the Pedersen blinding generator is hashed from
+pub const PEDERSEN_GENERATOR_LABEL: &[u8] = b"acss/commitment/pedersen";
+
+/// a commitment scheme for the (secret, blinding) polynomial pair of a
+/// dealing, over the group C
+pub trait PolynomialCommitment<C: CurveGroup> {
+	/// the commitment to a pair of polynomials
+	type Commitment: Clone;
+	/// the proof that a pair of values are evaluations of the committed
+	/// polynomials at a point
+	type Opening: Clone;
+
+	/// commit to the polynomials with coefficients `f` and `f_hat`, in
+	/// increasing degree
+	///
+	/// * `f`: The coefficients of the secret polynomial
+	/// * `f_hat`: The coefficients of the blinding polynomial
+	fn commit(
+		f: &[C::ScalarField],
+		f_hat: &[C::ScalarField],
+	) -> Self::Commitment;
+
+	/// open the polynomials with coefficients `f` and `f_hat` at `x`
+	///
+	/// * `f`: The coefficients of the secret polynomial
+	/// * `f_hat`: The coefficients of the blinding polynomial
+	/// * `x`: The point to open at
+	fn open(
+		f: &[C::ScalarField],
+		f_hat: &[C::ScalarField],
+		x: C::ScalarField,
+	) -> Self::Opening;
+
+	/// true if `share` are the evaluations at `x` of the polynomials
+	/// committed to
+	///
+	/// * `commitment`: The commitment to the polynomials
+	/// * `x`: The point
+	/// * `share`: The claimed (secret, blinding) evaluations
+	/// * `opening`: The opening at `x`
+	fn verify_eval(
+		commitment: &Self::Commitment,
+		x: C::ScalarField,
+		share: (C::ScalarField, C::ScalarField),
+		opening: &Self::Opening,
+	) -> bool;
+}
+
+/// Feldman commitments: `a_i * G` for each coefficient `a_i` of the secret
+/// polynomial
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Feldman;
+
+impl<C: CurveGroup> PolynomialCommitment<C> for Feldman {
+	type Commitment = Vec<C>;
+	type Opening = ();
+
+	fn commit(f: &[C::ScalarField], _f_hat: &[C::ScalarField]) -> Vec<C> {
+		let g = C::generator();
+		f.iter().map(|a| g * a).collect()
+	}
+
+	fn open(_: &[C::ScalarField], _: &[C::ScalarField], _: C::ScalarField) {}
+
+	fn verify_eval(
+		commitment: &Vec<C>,
+		x: C::ScalarField,
+		share: (C::ScalarField, C::ScalarField),
+		_opening: &(),
+	) -> bool {
+		!commitment.is_empty() &&
+			evaluate_in_exponent(commitment, x) == C::generator() * share.0
+	}
+}
+
+/// Pedersen commitments: `a_i * G + b_i * H` for the coefficients `a_i` of
+/// the secret polynomial and `b_i` of the blinding polynomial, where H has
+/// an unknown discrete log to G
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pedersen;
+
+impl Pedersen {
+	/// the blinding generator H
+	pub fn h<C: CurveGroup>() -> C {
+		nums_generator(PEDERSEN_GENERATOR_LABEL)
+	}
+}
+
+impl<C: CurveGroup> PolynomialCommitment<C> for Pedersen {
+	type Commitment = Vec<C>;
+	type Opening = ();
+
+	fn commit(f: &[C::ScalarField], f_hat: &[C::ScalarField]) -> Vec<C> {
+		let (g, h) = (C::generator(), Self::h::<C>());
+		f.iter().zip(f_hat).map(|(a, b)| g * a + h * b).collect()
+	}
+
+	fn open(_: &[C::ScalarField], _: &[C::ScalarField], _: C::ScalarField) {}
+
+	fn verify_eval(
+		commitment: &Vec<C>,
+		x: C::ScalarField,
+		share: (C::ScalarField, C::ScalarField),
+		_opening: &(),
+	) -> bool {
+		!commitment.is_empty() &&
+			evaluate_in_exponent(commitment, x) ==
+				C::generator() * share.0 + Self::h::<C>() * share.1
+	}
+}
+
+/// the sum of `x^i * C_i`
+fn evaluate_in_exponent<C: CurveGroup>(
+	coefficients: &[C],
+	x: C::ScalarField,
+) -> C {
+	coefficients.iter().rev().fold(C::zero(), |acc, c| acc * x + c)
+}
+
+/// a resharing with a commitment to its polynomials
+pub struct CommittedResharing<E: EngineBLS, P>
+where
+	P: PolynomialCommitment<E::PublicKeyGroup>,
+{
+	/// the resharing
+	pub resharing: Resharing<E>,
+	/// the commitment to the polynomials of the resharing
+	pub commitment: P::Commitment,
+	/// the opening of each share, in committee order
+	pub openings: Vec<P::Opening>,
+}
+
+/// check that the share dealt to `keypair` in `pok` lies on the committed
+/// polynomials
+/// fails with `InvalidProof` if the pok is not for `keypair`, with
+/// `InvalidCiphertext` if it cannot be decrypted and with
+/// `InvalidCommitment` if the share does not match the commitment
+///
+/// * `keypair`: The recipient's keypair
+/// * `index`: The index of the recipient's share
+/// * `pok`: The batched PoK dealt to the recipient
+/// * `commitment`: The commitment to the polynomials of the dealing
+/// * `opening`: The opening of the recipient's share
+#[cfg(feature = "participant")]
+pub fn verify_dealt_share<E: EngineBLS, P>(
+	keypair: &Keypair<E>,
+	index: ShareIndex,
+	pok: &BatchPoK<E::PublicKeyGroup>,
+	commitment: &P::Commitment,
+	opening: &P::Opening,
+) -> Result<(), AcssError>
+where
+	P: PolynomialCommitment<E::PublicKeyGroup>,
+{
+	let sk = SecretKey::from(&keypair.0.secret);
+	let pk = sk.public_key();
+	if !pok.verify(pk) {
+		return Err(AcssError::InvalidProof);
+	}
+	let (mut r, mut r_hat) = decrypt_pok::<E>(&sk, &pk, pok)?;
+	let valid =
+		P::verify_eval(commitment, index.to_scalar(), (r, r_hat), opening);
+	wipe(&mut r);
+	wipe(&mut r_hat);
+	valid.then_some(()).ok_or(AcssError::InvalidCommitment)
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::{DealingMode, HighThresholdACSS},
+		params::ThresholdParams,
+	};
+	use ark_ec::Group;
+	use ark_ff::{UniformRand, Zero};
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, PublicKey, TinyBLS377};
+
+	type E = TinyBLS377;
+	type G = <E as EngineBLS>::PublicKeyGroup;
+	type F = <E as EngineBLS>::Scalar;
+
+	fn check_scheme<P: PolynomialCommitment<G>>() -> P::Commitment {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<Keypair<E>> =
+			(0..4).map(|_| Keypair(KeypairVT::generate(&mut rng))).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|m| *m.public()).collect();
+		let dealt = HighThresholdACSS::<E>::reshare_committed::<P, _, _>(
+			F::rand(&mut rng),
+			F::rand(&mut rng),
+			&committee,
+			ThresholdParams::new(4, 3).unwrap(),
+			DealingMode::default(),
+			&mut rng,
+		)
+		.unwrap();
+
+		for (position, (member, pok)) in
+			members.iter().zip(dealt.resharing.poks()).enumerate()
+		{
+			let index = ShareIndex::from_position(position).unwrap();
+			assert_eq!(
+				verify_dealt_share::<E, P>(
+					member,
+					index,
+					pok,
+					&dealt.commitment,
+					&dealt.openings[position]
+				),
+				Ok(())
+			);
+			// the share does not open the commitment at another index
+			let other = ShareIndex::from_position((position + 1) % 4).unwrap();
+			assert_eq!(
+				verify_dealt_share::<E, P>(
+					member,
+					other,
+					pok,
+					&dealt.commitment,
+					&dealt.openings[position]
+				),
+				Err(AcssError::InvalidCommitment)
+			);
+		}
+		assert_eq!(
+			verify_dealt_share::<E, P>(
+				&members[0],
+				ShareIndex::new(2).unwrap(),
+				dealt.resharing.poks().nth(1).unwrap(),
+				&dealt.commitment,
+				&dealt.openings[1]
+			),
+			Err(AcssError::InvalidProof)
+		);
+		dealt.commitment
+	}
+
+	#[test]
+	fn feldman_commitments_bind_shares() {
+		let commitment = check_scheme::<Feldman>();
+		assert_eq!(commitment.len(), 3);
+		assert!(!Feldman::verify_eval(
+			&commitment,
+			F::from(1u64),
+			(F::from(1u64), F::zero()),
+			&()
+		));
+	}
+
+	#[test]
+	fn pedersen_commitments_bind_both_polynomials() {
+		let commitment = check_scheme::<Pedersen>();
+		assert_eq!(commitment.len(), 3);
+		assert_ne!(Pedersen::h::<G>(), G::generator());
+
+		let f = [F::from(3u64), F::from(5u64)];
+		let f_hat = [F::from(7u64), F::from(11u64)];
+		let commitment =
+			<Pedersen as PolynomialCommitment<G>>::commit(&f, &f_hat);
+		let x = F::from(2u64);
+		let share = (F::from(13u64), F::from(29u64));
+		assert!(Pedersen::verify_eval(&commitment, x, share, &()));
+		// the blinding share is bound as well
+		assert!(!Pedersen::verify_eval(
+			&commitment,
+			x,
+			(share.0, share.1 + F::from(1u64)),
+			&()
+		));
+		assert!(!Pedersen::verify_eval(&Vec::<G>::new(), x, share, &()));
+	}
+}
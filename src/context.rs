@@ -0,0 +1,476 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Verification Context
+//!
+//! Nodes typically process many dealings against the same committee within an
+//! epoch. The context does the per-committee work once (deserializing and
+//! subgroup checking member keys, and checking that both halves of a double
+//! public key agree) and memoizes the Lagrange coefficients used
+//! during recovery, so repeated `verify`/`recover` calls only pay for the
+//! per-dealing work.
+
+#[cfg(feature = "participant")]
+use crate::{
+	acss::{decrypt_indexed_poks, decrypt_valid_poks, DoubleSecret},
+	keys::SecretKey,
+	params::ThresholdParams,
+	wipe,
+};
+use crate::{
+	error::AcssError,
+	keys::{CommitteeKey, PublicKey as EncryptionKey, ShareIndex},
+	lagrange::lagrange_at_zero,
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use ark_std::{borrow::Borrow, vec::Vec, Zero};
+use w3f_bls::{EngineBLS, PublicKey};
+
+/// the number of sets of interpolation points a context keeps the Lagrange
+/// coefficients of, evicting the least recently used set first
+pub const LAGRANGE_CACHE_SIZE: usize = 8;
+
+/// a reusable context for verifying and recovering dealings made to a fixed
+/// committee
+pub struct VerificationContext<E: EngineBLS> {
+	/// the committee keys, none if a key failed the subgroup or consistency
+	/// check
+	committee: Vec<Option<EncryptionKey<E::PublicKeyGroup>>>,
+	/// Lagrange coefficients at 0 with their interpolation points, the most
+	/// recently used last
+	lagrange: Vec<(Vec<E::Scalar>, Vec<E::Scalar>)>,
+}
+
+impl<E: EngineBLS> VerificationContext<E> {
+	/// build a context for a committee, checking each key once
+	///
+	/// * `committee`: The committee public keys, or double public keys
+	pub fn new<K: CommitteeKey<E>>(committee: &[K]) -> Self {
+		let committee = committee
+			.iter()
+			.map(|member| {
+				let pk = member.public_key();
+				// [r]P = 0 iff P is in the prime order subgroup
+				let affine = pk.0.into_affine();
+				let order = <E::Scalar as PrimeField>::MODULUS;
+				(!affine.is_zero() &&
+					affine.mul_bigint(order).is_zero() &&
+					member.is_consistent())
+				.then_some(EncryptionKey(pk.0))
+			})
+			.collect();
+		Self { committee, lagrange: Vec::new() }
+	}
+
+	/// build a context from serialized (compressed) committee keys
+	/// fails if any key cannot be deserialized
+	///
+	/// * `committee`: The serialized committee public keys
+	pub fn from_bytes<B: AsRef<[u8]>>(
+		committee: &[B],
+	) -> Result<Self, AcssError> {
+		let keys = committee
+			.iter()
+			.map(|bytes| {
+				PublicKey::<E>::deserialize_compressed(bytes.as_ref())
+					.map_err(|_| AcssError::InvalidCommittee)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Self::new(&keys))
+	}
+
+	/// the number of members in the committee
+	pub fn len(&self) -> usize {
+		self.committee.len()
+	}
+
+	/// true if the committee is empty
+	pub fn is_empty(&self) -> bool {
+		self.committee.is_empty()
+	}
+
+	/// the public key of the member holding the share at `index`, if it
+	/// passed validation
+	pub fn member(
+		&self,
+		index: ShareIndex,
+	) -> Option<&EncryptionKey<E::PublicKeyGroup>> {
+		self.committee.get(index.position()).and_then(|pk| pk.as_ref())
+	}
+
+	/// verify the pok dealt to the member holding the share at `index`
+	/// outputs false if the member is unknown or has an invalid key
+	pub fn verify(
+		&self,
+		index: ShareIndex,
+		pok: &BatchPoK<E::PublicKeyGroup>,
+	) -> bool {
+		self.member(index).is_some_and(|pk| pok.verify(*pk))
+	}
+
+	/// verify a full resharing, where the i-th pok is dealt to the i-th member
+	/// outputs false if the sizes do not match or any pok is invalid
+	pub fn verify_all<I>(&self, poks: I) -> bool
+	where
+		I: IntoIterator,
+		I::Item: Borrow<BatchPoK<E::PublicKeyGroup>>,
+	{
+		let mut count = 0;
+		for (position, pok) in poks.into_iter().enumerate() {
+			let Some(index) = ShareIndex::from_position(position) else {
+				return false;
+			};
+			if !self.verify(index, pok.borrow()) {
+				return false;
+			}
+			count += 1;
+		}
+		count == self.len()
+	}
+
+	/// the Lagrange coefficients at 0 for the points `xs`
+	/// these are cached for the last `LAGRANGE_CACHE_SIZE` distinct sets of
+	/// points used
+	/// outputs none if the points are not distinct
+	pub fn lagrange_at_zero(
+		&mut self,
+		xs: &[E::Scalar],
+	) -> Option<&[E::Scalar]> {
+		match self.lagrange.iter().position(|(points, _)| points == xs) {
+			Some(i) => {
+				let entry = self.lagrange.remove(i);
+				self.lagrange.push(entry);
+			},
+			None => {
+				let coeffs = lagrange_at_zero::<E::Scalar>(xs)?;
+				if self.lagrange.len() == LAGRANGE_CACHE_SIZE {
+					self.lagrange.remove(0);
+				}
+				self.lagrange.push((xs.to_vec(), coeffs));
+			},
+		}
+		self.lagrange.last().map(|(_, coeffs)| coeffs.as_slice())
+	}
+
+	/// interpolate a polynomial from the points and evaluate it at 0, using
+	/// the cached Lagrange coefficients
+	pub fn interpolate(
+		&mut self,
+		points: &[(E::Scalar, E::Scalar)],
+	) -> E::Scalar {
+		let xs: Vec<E::Scalar> = points.iter().map(|p| p.0).collect();
+		let Some(coeffs) = self.lagrange_at_zero(&xs) else {
+			return E::Scalar::zero();
+		};
+		points
+			.iter()
+			.zip(coeffs.iter())
+			.fold(E::Scalar::zero(), |acc, (p, c)| acc + p.1 * c)
+	}
+
+	/// decrypt shares + authenticate from a stream of batched PoKs, as with
+	/// `HighThresholdACSS::recover_streaming`, using the cached Lagrange
+	/// coefficients
+	///
+	/// * `sk`: the secret key of the recipient
+	/// * `poks`: A stream of batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers
+	#[cfg(feature = "participant")]
+	pub fn recover<I>(
+		&mut self,
+		sk: &SecretKey<E::PublicKeyGroup>,
+		poks: I,
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError>
+	where
+		I: IntoIterator,
+		I::Item: Borrow<BatchPoK<E::PublicKeyGroup>>,
+	{
+		let shares = decrypt_valid_poks::<E, I>(sk, poks, params)?;
+		self.interpolate_shares(shares)
+	}
+
+	/// decrypt shares + authenticate from batched PoKs labelled with the
+	/// index of their dealer, as with `HighThresholdACSS::recover_indexed`,
+	/// using the cached Lagrange coefficients
+	/// fails if an index is repeated or not in 1..=n
+	///
+	/// * `sk`: the secret key of the recipient
+	/// * `poks`: (dealer index, batched PoK) pairs, indices start at 1
+	/// * `params`: The threshold parameters of the dealers
+	#[cfg(feature = "participant")]
+	pub fn recover_indexed(
+		&mut self,
+		sk: &SecretKey<E::PublicKeyGroup>,
+		poks: &[(ShareIndex, BatchPoK<E::PublicKeyGroup>)],
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		params.check_indices(poks.iter().map(|(index, _)| *index))?;
+		let shares = decrypt_indexed_poks::<E, _, _>(
+			sk,
+			poks.iter().map(|(index, pok)| (*index, pok)),
+			params.t(),
+			None,
+		)?;
+		self.interpolate_shares(shares)
+	}
+
+	/// interpolate the share and blinding share from (x, share, blinding
+	/// share) triples, wiping the shares afterwards
+	/// fails with `DuplicateShareIndex` if an x-coordinate is repeated
+	#[cfg(feature = "participant")]
+	fn interpolate_shares(
+		&mut self,
+		mut shares: Vec<(E::Scalar, E::Scalar, E::Scalar)>,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		let xs: Vec<E::Scalar> = shares.iter().map(|s| s.0).collect();
+		let recovered = self.lagrange_at_zero(&xs).map(|coeffs| {
+			shares.iter().zip(coeffs.iter()).fold(
+				(E::Scalar::zero(), E::Scalar::zero()),
+				|(s, s_prime), ((_, r, r_hat), c)| {
+					(s + *r * c, s_prime + *r_hat * c)
+				},
+			)
+		});
+		shares.iter_mut().for_each(|(_, r, r_hat)| {
+			wipe(r);
+			wipe(r_hat);
+		});
+
+		let (s, s_prime) = recovered.ok_or(AcssError::DuplicateShareIndex)?;
+		Ok(DoubleSecret::<E>(s, s_prime))
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::acss::{
+		generate_shares_checked, interpolate, HighThresholdACSS,
+	};
+	use ark_ec::Group;
+	use ark_ff::UniformRand;
+	use ark_serialize::CanonicalSerialize;
+	use ark_std::{rand::SeedableRng, One};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{
+		DoublePublicKey, DoublePublicKeyScheme, KeypairVT, TinyBLS377,
+	};
+
+	type E = TinyBLS377;
+	type Scalar = <E as EngineBLS>::Scalar;
+
+	fn index(i: u8) -> ShareIndex {
+		ShareIndex::new(i).unwrap()
+	}
+
+	#[test]
+	fn context_verifies_a_resharing() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|kp| kp.public).collect();
+
+		let resharing = HighThresholdACSS::<E>::reshare(
+			Scalar::rand(&mut rng),
+			Scalar::rand(&mut rng),
+			&committee,
+			ThresholdParams::for_committee(&committee, 2).unwrap(),
+			&mut rng,
+		)
+		.unwrap();
+		let poks: Vec<_> = resharing.poks().collect();
+
+		let bytes: Vec<Vec<u8>> = committee
+			.iter()
+			.map(|pk| {
+				let mut out = Vec::new();
+				pk.serialize_compressed(&mut out).unwrap();
+				out
+			})
+			.collect();
+		let context = VerificationContext::<E>::from_bytes(&bytes).unwrap();
+
+		assert_eq!(context.len(), 3);
+		assert!(context.verify_all(poks.iter().copied()));
+		assert!(!context.verify(index(2), poks[0]));
+		assert!(!context.verify(index(4), poks[0]));
+		assert!(!context.verify_all(poks.iter().take(2).copied()));
+	}
+
+	#[test]
+	fn context_accepts_double_public_keys() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<DoublePublicKey<E>> =
+			keys.iter().map(|kp| kp.into_double_public_key()).collect();
+
+		let resharing = HighThresholdACSS::<E>::reshare(
+			Scalar::rand(&mut rng),
+			Scalar::rand(&mut rng),
+			&committee,
+			ThresholdParams::for_committee(&committee, 2).unwrap(),
+			&mut rng,
+		)
+		.unwrap();
+		assert_eq!(resharing.recipients()[0].0, keys[0].public.0);
+		let context = VerificationContext::<E>::new(&committee);
+		assert!(context.verify_all(resharing.poks()));
+
+		// the halves of a double public key must share a secret key
+		let mut mismatched: Vec<DoublePublicKey<E>> =
+			keys.iter().map(|kp| kp.into_double_public_key()).collect();
+		mismatched[1].0 = committee[2].0;
+		let context = VerificationContext::<E>::new(&mismatched);
+		assert!(context.member(index(1)).is_some());
+		assert!(context.member(index(2)).is_none());
+	}
+
+	#[test]
+	fn context_rejects_identity_keys() {
+		let committee = vec![
+			PublicKey::<E>(<E as EngineBLS>::PublicKeyGroup::zero()),
+			PublicKey::<E>(<E as EngineBLS>::PublicKeyGroup::generator()),
+		];
+		let context = VerificationContext::<E>::new(&committee);
+		assert!(context.member(index(1)).is_none());
+		assert!(context.member(index(2)).is_some());
+	}
+
+	#[test]
+	fn context_caches_lagrange_coefficients() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let mut context = VerificationContext::<E>::new::<PublicKey<E>>(&[]);
+
+		let points: Vec<(Scalar, Scalar)> = (1..4u64)
+			.map(|i| (Scalar::from(i), Scalar::rand(&mut rng)))
+			.collect();
+		let expected = interpolate::<<E as EngineBLS>::SignatureGroup>(&points);
+
+		assert_eq!(context.interpolate(&points), expected);
+		assert_eq!(context.interpolate(&points), expected);
+		assert_eq!(context.lagrange.len(), 1);
+
+		let repeated = [Scalar::one(), Scalar::one()];
+		assert!(context.lagrange_at_zero(&repeated).is_none());
+	}
+
+	#[test]
+	fn context_rejects_repeated_share_indices() {
+		let mut context = VerificationContext::<E>::new::<PublicKey<E>>(&[]);
+		let shares = vec![
+			(Scalar::one(), Scalar::from(2u64), Scalar::from(3u64)),
+			(Scalar::one(), Scalar::from(4u64), Scalar::from(5u64)),
+		];
+		assert_eq!(
+			context.interpolate_shares(shares).err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+	}
+
+	#[test]
+	fn context_bounds_the_lagrange_cache() {
+		let mut context = VerificationContext::<E>::new::<PublicKey<E>>(&[]);
+		let points =
+			|first: u64| [Scalar::from(first), Scalar::from(first + 1)];
+
+		for first in 1..=LAGRANGE_CACHE_SIZE as u64 {
+			assert!(context.lagrange_at_zero(&points(first)).is_some());
+		}
+		// using the first set again keeps it over the second
+		assert!(context.lagrange_at_zero(&points(1)).is_some());
+		let next = LAGRANGE_CACHE_SIZE as u64 + 1;
+		assert!(context.lagrange_at_zero(&points(next)).is_some());
+
+		assert_eq!(context.lagrange.len(), LAGRANGE_CACHE_SIZE);
+		let cached = |first: u64| {
+			context.lagrange.iter().any(|(xs, _)| xs[..] == points(first)[..])
+		};
+		assert!(cached(1));
+		assert!(!cached(2));
+		assert!(cached(next));
+	}
+
+	#[test]
+	fn context_recovery_matches_streaming_recovery() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let kp = KeypairVT::<E>::generate(&mut rng);
+		let mut context = VerificationContext::<E>::new(&[kp.public]);
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let sk = SecretKey::from(&kp.secret);
+		let pk = sk.public_key();
+
+		for _ in 0..2 {
+			let s = Scalar::rand(&mut rng);
+			let s_hat = Scalar::rand(&mut rng);
+			let evals = generate_shares_checked::<E, _>(s, 3, 2, &mut rng);
+			let evals_hat =
+				generate_shares_checked::<E, _>(s_hat, 3, 2, &mut rng);
+			let poks: Vec<BatchPoK<_>> = evals
+				.values()
+				.zip(evals_hat.values())
+				.map(|(u, u_hat)| {
+					BatchPoK::prove(&[*u, *u_hat], pk, &mut rng).unwrap()
+				})
+				.collect();
+
+			let recovered = context.recover(&sk, poks.iter(), params).unwrap();
+			assert_eq!(recovered.0, s);
+			assert_eq!(recovered.1, s_hat);
+		}
+		// both dealings were interpolated over the same points
+		assert_eq!(context.lagrange.len(), 1);
+	}
+
+	#[test]
+	fn context_indexed_recovery_validates_indices() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let kp = KeypairVT::<E>::generate(&mut rng);
+		let mut context = VerificationContext::<E>::new(&[kp.public]);
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let sk = SecretKey::from(&kp.secret);
+		let pk = sk.public_key();
+
+		let s = Scalar::rand(&mut rng);
+		let evals = generate_shares_checked::<E, _>(s, 3, 2, &mut rng);
+		let poks: Vec<(ShareIndex, BatchPoK<_>)> = evals
+			.values()
+			.zip(1..=3)
+			.map(|(u, i)| {
+				(index(i), BatchPoK::prove(&[*u, *u], pk, &mut rng).unwrap())
+			})
+			.rev()
+			.collect();
+
+		let recovered = context.recover_indexed(&sk, &poks, params).unwrap();
+		assert_eq!(recovered.0, s);
+
+		let duplicated = [poks[0].clone(), poks[0].clone()];
+		assert_eq!(
+			context.recover_indexed(&sk, &duplicated, params).err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+		let out_of_range = [(index(4), poks[0].1.clone())];
+		assert_eq!(
+			context.recover_indexed(&sk, &out_of_range, params).err(),
+			Some(AcssError::ShareIndexOutOfRange)
+		);
+	}
+}
@@ -0,0 +1,416 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Device Custody
+//!
+//! One logical member may keep its share across several devices, e.g. a
+//! laptop, an HSM and a phone, so that no single device holds it.
+//! [`Share::split_across`] splits the share into one [`DevicePiece`] per
+//! device, any t of which restore it, and wraps each piece to its device's
+//! encryption key with hashed El Gamal. Every piece names its [`Custodian`]
+//! and carries the metadata of the share, so recovery tooling knows which
+//! device to ask for which piece. Each device unwraps its own piece with
+//! [`DevicePiece::unwrap`], and [`Share::from_pieces`] restores the share.
+//!
+//! A share kept whole on one device can name it too, see
+//! [`Share::with_custodian`].
+//!
+//! Layout of an encoded piece:
+//! `kind || len || id || piece || n || t || index || epoch || expires ||
+//! ciphertext`
+
+use crate::{
+	acss::DoubleSecret,
+	error::{AcssError, ProofError, SerializationError},
+	keys::{PublicKey as EncryptionKey, SecretKey, ShareIndex},
+	params::ThresholdParams,
+	proofs::hashed_el_gamal::{Ciphertext, HashedElGamal},
+	rng::SecureRng,
+	shamir::{reconstruct, split, ShamirShare},
+	share::Share,
+	transcript::{read, read_array, write},
+	wipe,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::vec::Vec;
+use w3f_bls::EngineBLS;
+
+/// the length of a wrapped piece: the piece of the share and of its
+/// blinding share
+const PIECE_LEN: usize = 64;
+
+/// the kind of device holding a share or a piece of one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+	/// a general purpose computer
+	Laptop,
+	/// a mobile phone
+	Phone,
+	/// a hardware security module
+	Hsm,
+	/// any other device
+	Other,
+}
+
+impl DeviceKind {
+	/// the encoding of the kind
+	fn to_byte(self) -> u8 {
+		match self {
+			Self::Laptop => 0,
+			Self::Phone => 1,
+			Self::Hsm => 2,
+			Self::Other => 3,
+		}
+	}
+
+	/// the kind encoded as `byte`
+	fn from_byte(byte: u8) -> Option<Self> {
+		Some(match byte {
+			0 => Self::Laptop,
+			1 => Self::Phone,
+			2 => Self::Hsm,
+			3 => Self::Other,
+			_ => return None,
+		})
+	}
+}
+
+/// the device holding a share or a piece of one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Custodian {
+	/// the kind of device
+	pub kind: DeviceKind,
+	/// how recovery tooling addresses the device, e.g. a serial number or
+	/// key slot, at most 255 bytes
+	pub id: Vec<u8>,
+}
+
+impl Custodian {
+	/// a custodian of kind `kind` addressed as `id`
+	///
+	/// * `kind`: The kind of device
+	/// * `id`: How recovery tooling addresses the device
+	pub fn new(kind: DeviceKind, id: &[u8]) -> Self {
+		Self { kind, id: id.to_vec() }
+	}
+}
+
+/// a piece of a share, wrapped to the device that holds it
+pub struct DevicePiece<E: EngineBLS> {
+	/// the device holding the piece
+	pub custodian: Custodian,
+	/// the index of the piece among the pieces of the share
+	pub piece: ShareIndex,
+	/// the number of pieces, and how many restore the share
+	pub params: ThresholdParams,
+	/// the index of the share in its committee
+	pub index: ShareIndex,
+	/// the epoch the share was dealt in
+	pub epoch: u64,
+	/// the first epoch in which the share is expired
+	pub expires: u64,
+	/// the piece, encrypted to the device's key
+	pub ciphertext: Ciphertext<E::PublicKeyGroup, PIECE_LEN>,
+}
+
+/// a piece of a share, unwrapped by its device
+pub struct UnwrappedPiece<E: EngineBLS> {
+	piece: ShareIndex,
+	params: ThresholdParams,
+	index: ShareIndex,
+	epoch: u64,
+	expires: u64,
+	value: DoubleSecret<E>,
+}
+
+impl<E: EngineBLS> Share<E> {
+	/// split the share into one piece per device, any `threshold` of which
+	/// restore it, each wrapped to its device's key
+	/// fails with `ExpiredShare` if the share has expired by epoch `now`,
+	/// with `InvalidParams` unless 0 < `threshold` <= the number of devices
+	/// (at most 255), and with `Proof(InvalidPublicKey)` if a device key is
+	/// the identity
+	///
+	/// * `now`: The current epoch
+	/// * `devices`: Each device and its encryption key
+	/// * `threshold`: The number of pieces that restore the share
+	/// * `rng`: A CSPRNG
+	pub fn split_across<R: SecureRng>(
+		&self,
+		now: u64,
+		devices: &[(Custodian, EncryptionKey<E::PublicKeyGroup>)],
+		threshold: u8,
+		mut rng: R,
+	) -> Result<Vec<DevicePiece<E>>, AcssError> {
+		let secret = self.expose(now)?;
+		let n = u8::try_from(devices.len())
+			.map_err(|_| AcssError::InvalidParams)?;
+		let params = ThresholdParams::new(n, threshold)?;
+		let mut shares = split(secret.0, params, &mut rng);
+		let mut blindings = split(secret.1, params, &mut rng);
+		let pieces: Result<Vec<_>, AcssError> = devices
+			.iter()
+			.zip(shares.iter().zip(&blindings))
+			.map(|((custodian, pk), (share, blinding))| {
+				let mut message = [0u8; PIECE_LEN];
+				let (first, second) = message.split_at_mut(PIECE_LEN / 2);
+				let ciphertext = write_scalar::<E>(&share.value, first)
+					.and_then(|_| write_scalar::<E>(&blinding.value, second))
+					.and_then(|_| {
+						HashedElGamal::encrypt(message, *pk, &mut rng)
+					});
+				wipe(&mut message);
+				Ok(DevicePiece {
+					custodian: custodian.clone(),
+					piece: share.index,
+					params,
+					index: self.index,
+					epoch: self.epoch,
+					expires: self.expires,
+					ciphertext: ciphertext?,
+				})
+			})
+			.collect();
+		wipe_pieces(&mut shares);
+		wipe_pieces(&mut blindings);
+		pieces
+	}
+
+	/// restore a share from at least t of its pieces, unwrapped by their
+	/// devices
+	/// fails with `InvalidParams` if the pieces belong to different shares,
+	/// with `InsufficientValidPoK` if there are fewer than t, and with
+	/// `DuplicateShareIndex` if a piece appears twice
+	///
+	/// * `pieces`: The unwrapped pieces, in any order
+	pub fn from_pieces(
+		pieces: &[UnwrappedPiece<E>],
+	) -> Result<Self, AcssError> {
+		let first = pieces.first().ok_or(AcssError::InsufficientValidPoK)?;
+		if pieces.iter().any(|piece| {
+			(piece.params, piece.index, piece.epoch, piece.expires) !=
+				(first.params, first.index, first.epoch, first.expires)
+		}) {
+			return Err(AcssError::InvalidParams);
+		}
+		let restore = |value: fn(&DoubleSecret<E>) -> E::Scalar| {
+			let mut shares: Vec<ShamirShare<E::Scalar>> = pieces
+				.iter()
+				.map(|piece| ShamirShare {
+					index: piece.piece,
+					value: value(&piece.value),
+				})
+				.collect();
+			let secret = reconstruct(&shares, first.params);
+			wipe_pieces(&mut shares);
+			secret
+		};
+		let secret = DoubleSecret(restore(|s| s.0)?, restore(|s| s.1)?);
+		Ok(Share::new(secret, first.index, first.epoch)
+			.with_expiry(first.expires))
+	}
+}
+
+impl<E: EngineBLS> DevicePiece<E> {
+	/// unwrap the piece with the device's secret key
+	/// fails with `InvalidCiphertext` if the piece is not wrapped to the key
+	/// or was modified
+	///
+	/// * `sk`: The device's secret key
+	pub fn unwrap(
+		&self,
+		sk: &SecretKey<E::PublicKeyGroup>,
+	) -> Result<UnwrappedPiece<E>, AcssError> {
+		let mut message = HashedElGamal::decrypt(sk, &self.ciphertext)
+			.map_err(|_| AcssError::InvalidCiphertext)?;
+		let (first, second) = message.split_at(PIECE_LEN / 2);
+		let value = read_scalar::<E>(first).and_then(|share| {
+			Ok(DoubleSecret(share, read_scalar::<E>(second)?))
+		});
+		wipe(&mut message);
+		Ok(UnwrappedPiece {
+			piece: self.piece,
+			params: self.params,
+			index: self.index,
+			epoch: self.epoch,
+			expires: self.expires,
+			value: value?,
+		})
+	}
+
+	/// encode the piece for storage on its device
+	/// fails with `InvalidParams` if the custodian id is longer than 255
+	/// bytes
+	pub fn encode(&self) -> Result<Vec<u8>, AcssError> {
+		let len = u8::try_from(self.custodian.id.len())
+			.map_err(|_| AcssError::InvalidParams)?;
+		let mut out = vec![self.custodian.kind.to_byte(), len];
+		out.extend_from_slice(&self.custodian.id);
+		out.extend_from_slice(&[
+			self.piece.get(),
+			self.params.n(),
+			self.params.t(),
+			self.index.get(),
+		]);
+		write(&self.epoch, &mut out)?;
+		write(&self.expires, &mut out)?;
+		write(&self.ciphertext, &mut out)?;
+		Ok(out)
+	}
+
+	/// decode a piece, rejecting trailing bytes
+	/// fails with `InvalidTranscript` if the bytes cannot be decoded
+	///
+	/// * `bytes`: The encoded piece
+	pub fn decode(mut bytes: &[u8]) -> Result<Self, AcssError> {
+		let reader = &mut bytes;
+		let [kind, len] = read_array::<2>(reader)?;
+		let kind =
+			DeviceKind::from_byte(kind).ok_or(AcssError::InvalidTranscript)?;
+		if reader.len() < len as usize {
+			return Err(AcssError::InvalidTranscript);
+		}
+		let (id, rest) = reader.split_at(len as usize);
+		*reader = rest;
+		let [piece, n, t, index] = read_array::<4>(reader)?;
+		let share_index =
+			|i| ShareIndex::new(i).ok_or(AcssError::InvalidTranscript);
+		let params = ThresholdParams::new(n, t)
+			.map_err(|_| AcssError::InvalidTranscript)?;
+		let piece = share_index(piece)?;
+		if piece.get() > params.n() {
+			return Err(AcssError::InvalidTranscript);
+		}
+		let decoded = Self {
+			custodian: Custodian::new(kind, id),
+			piece,
+			params,
+			index: share_index(index)?,
+			epoch: read(reader)?,
+			expires: read(reader)?,
+			ciphertext: read(reader)?,
+		};
+		if !reader.is_empty() {
+			return Err(AcssError::InvalidTranscript);
+		}
+		Ok(decoded)
+	}
+}
+
+/// write a scalar into `out`
+/// fails with `InvalidBufferSize` if the scalar does not fit
+fn write_scalar<E: EngineBLS>(
+	scalar: &E::Scalar,
+	out: &mut [u8],
+) -> Result<(), ProofError> {
+	scalar
+		.serialize_compressed(out)
+		.map_err(|_| SerializationError::InvalidBufferSize.into())
+}
+
+/// read a scalar written by `write_scalar`
+fn read_scalar<E: EngineBLS>(mut bytes: &[u8]) -> Result<E::Scalar, AcssError> {
+	E::Scalar::deserialize_compressed(&mut bytes)
+		.map_err(|_| AcssError::InvalidCiphertext)
+}
+
+/// wipe the values of Shamir shares
+fn wipe_pieces<F: ark_ff::PrimeField>(shares: &mut [ShamirShare<F>]) {
+	for share in shares {
+		wipe(&mut share.value);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_ec::Group;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+	type G = <E as EngineBLS>::PublicKeyGroup;
+
+	#[test]
+	fn shares_split_across_devices_are_restored_by_any_t() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let expected = (secret.0, secret.1);
+		let share = Share::new(secret, ShareIndex::new(3).unwrap(), 5)
+			.with_expiry(7)
+			.with_custodian(Custodian::new(DeviceKind::Laptop, b"laptop"));
+		let keys: Vec<SecretKey<G>> = (0..3)
+			.map(|_| SecretKey::new(<G as Group>::ScalarField::rand(&mut rng)))
+			.collect();
+		let devices: Vec<(Custodian, EncryptionKey<G>)> = [
+			(DeviceKind::Laptop, &b"laptop"[..]),
+			(DeviceKind::Hsm, b"slot-1"),
+			(DeviceKind::Phone, b"phone"),
+		]
+		.into_iter()
+		.zip(&keys)
+		.map(|((kind, id), sk)| (Custodian::new(kind, id), sk.public_key()))
+		.collect();
+
+		let pieces = share.split_across(5, &devices, 2, &mut rng).unwrap();
+		assert_eq!(pieces.len(), 3);
+		let pieces: Vec<DevicePiece<E>> = pieces
+			.iter()
+			.map(|piece| DevicePiece::decode(&piece.encode().unwrap()).unwrap())
+			.collect();
+		assert_eq!(pieces[1].custodian, devices[1].0);
+
+		// any two devices restore the share and its metadata
+		for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+			let unwrapped = [
+				pieces[a].unwrap(&keys[a]).unwrap(),
+				pieces[b].unwrap(&keys[b]).unwrap(),
+			];
+			let restored = Share::from_pieces(&unwrapped).unwrap();
+			let secret = restored.expose(6).unwrap();
+			assert_eq!((secret.0, secret.1), expected);
+			assert_eq!((restored.index, restored.epoch), (share.index, 5));
+			assert!(restored.is_expired(7));
+		}
+		let one = [pieces[0].unwrap(&keys[0]).unwrap()];
+		assert_eq!(
+			Share::from_pieces(&one).err(),
+			Some(AcssError::InsufficientValidPoK)
+		);
+		// a piece only unwraps with its device's key
+		assert_eq!(
+			pieces[0].unwrap(&keys[1]).err(),
+			Some(AcssError::InvalidCiphertext)
+		);
+
+		assert_eq!(
+			share.split_across(7, &devices, 2, &mut rng).err(),
+			Some(AcssError::ExpiredShare)
+		);
+		assert_eq!(
+			share.split_across(5, &devices, 4, &mut rng).err(),
+			Some(AcssError::InvalidParams)
+		);
+		let mut encoded = pieces[0].encode().unwrap();
+		encoded.push(0);
+		assert!(DevicePiece::<E>::decode(&encoded).is_err());
+		assert!(DevicePiece::<E>::decode(&encoded[..20]).is_err());
+	}
+}
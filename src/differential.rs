@@ -0,0 +1,266 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Differential Testing
+//!
+//! Cross-checks the optimized share math and hashed El Gamal paths against a
+//! naive reference implementation on random inputs. The reference works over
+//! big integers modulo the field order, multiplies points by double-and-add
+//! and derives pads from fully serialized buffers, so it shares no code with
+//! the paths it checks beyond the group law and SHA256.
+//!
+//! Only built with the `differential` feature.
+
+use crate::{
+	acss::{generate_shares_checked, interpolate, lagrange_at_zero},
+	keys::SecretKey,
+	proofs::hashed_el_gamal::{HashedElGamal, Message},
+	rng::SecureRng,
+};
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_serialize::CanonicalSerialize;
+use ark_std::vec::Vec;
+use num_bigint::BigUint;
+use sha2::Digest;
+use w3f_bls::EngineBLS;
+
+/// the optimized path that disagreed with the reference implementation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Divergence {
+	/// the shares do not lie on a single polynomial of degree t - 1
+	Shares,
+	/// the Lagrange basis at zero differs
+	Lagrange,
+	/// the interpolated secret differs
+	Interpolation,
+	/// a ciphertext does not decrypt to its message under the reference pad
+	Encryption,
+	/// decryption (single or batched) differs from the reference
+	Decryption,
+}
+
+/// deal a random secret to `n` shares with threshold `t`, then check the
+/// shares and their interpolation against the reference implementation
+///
+/// * `n`: The number of shares
+/// * `t`: The threshold, 0 < t <= n
+/// * `rng`: A cryptographically secure rng
+pub fn check_shares<E: EngineBLS, R: SecureRng>(
+	n: u8,
+	t: u8,
+	rng: &mut R,
+) -> Result<(), Divergence> {
+	let p = modulus::<E::Scalar>();
+	let s = E::Scalar::rand(rng);
+	let shares: Vec<(E::Scalar, E::Scalar)> =
+		generate_shares_checked::<E, R>(s, n, t, rng).into_iter().collect();
+	if shares.len() != n as usize {
+		return Err(Divergence::Shares);
+	}
+
+	let big: Vec<(BigUint, BigUint)> =
+		shares.iter().map(|(x, y)| (to_big(x), to_big(y))).collect();
+	let (head, tail) = big.split_at(t as usize);
+	// any t shares determine the rest
+	for (x, y) in tail {
+		if reference::interpolate_at(&p, head, x) != Some(y.clone()) {
+			return Err(Divergence::Shares);
+		}
+	}
+
+	for window in [&shares[..t as usize], &shares[(n - t) as usize..]] {
+		let xs: Vec<E::Scalar> = window.iter().map(|(x, _)| *x).collect();
+		let big_xs: Vec<BigUint> = xs.iter().map(to_big).collect();
+		let expected = reference::lagrange_at_zero(&p, &big_xs);
+		let actual = lagrange_at_zero(&xs)
+			.map(|basis| basis.iter().map(to_big).collect::<Vec<_>>());
+		if actual != expected {
+			return Err(Divergence::Lagrange);
+		}
+
+		let points: Vec<(BigUint, BigUint)> =
+			window.iter().map(|(x, y)| (to_big(x), to_big(y))).collect();
+		let secret = interpolate::<E::SignatureGroup>(window);
+		if Some(to_big(&secret)) !=
+			reference::interpolate_at(&p, &points, &BigUint::from(0u8)) ||
+			secret != s
+		{
+			return Err(Divergence::Interpolation);
+		}
+	}
+	Ok(())
+}
+
+/// encrypt `count` random messages to a random key, then check the
+/// ciphertexts and their (single and batched) decryption against the
+/// reference implementation
+///
+/// * `count`: The number of messages
+/// * `rng`: A cryptographically secure rng
+pub fn check_encryption<C: CurveGroup, R: SecureRng>(
+	count: usize,
+	rng: &mut R,
+) -> Result<(), Divergence> {
+	let sk = SecretKey::<C>::new(C::ScalarField::rand(rng));
+	let pk = sk.public_key();
+	if reference::mul(C::generator(), &to_big(sk.expose_secret())) != pk.0 {
+		return Err(Divergence::Encryption);
+	}
+
+	let mut messages: Vec<Message> = Vec::with_capacity(count);
+	let mut ciphertexts = Vec::with_capacity(count);
+	for _ in 0..count {
+		let mut message = [0u8; 32];
+		rng.fill_bytes(&mut message);
+		let ct = HashedElGamal::encrypt(message, pk, &mut *rng)
+			.map_err(|_| Divergence::Encryption)?;
+
+		let shared = reference::mul(ct.c1, &to_big(sk.expose_secret()));
+		let pad = reference::pad(shared, ct.c1, pk.0)
+			.ok_or(Divergence::Encryption)?;
+		let opened: Vec<u8> =
+			ct.c2.iter().zip(pad.iter()).map(|(c, p)| c ^ p).collect();
+		if opened != message {
+			return Err(Divergence::Encryption);
+		}
+		if HashedElGamal::decrypt(&sk, &ct) != Ok(message) {
+			return Err(Divergence::Decryption);
+		}
+		messages.push(message);
+		ciphertexts.push(ct);
+	}
+
+	if HashedElGamal::decrypt_batch(&sk, &ciphertexts) != Ok(messages) {
+		return Err(Divergence::Decryption);
+	}
+	Ok(())
+}
+
+fn modulus<F: PrimeField>() -> BigUint {
+	BigUint::from_bytes_le(&F::MODULUS.to_bytes_le())
+}
+
+fn to_big<F: PrimeField>(x: &F) -> BigUint {
+	BigUint::from_bytes_le(&x.into_bigint().to_bytes_le())
+}
+
+/// the naive reference implementation
+mod reference {
+	use super::*;
+
+	/// the Lagrange basis at zero for distinct `xs`, none otherwise
+	pub fn lagrange_at_zero(
+		p: &BigUint,
+		xs: &[BigUint],
+	) -> Option<Vec<BigUint>> {
+		let zero = BigUint::from(0u8);
+		xs.iter()
+			.enumerate()
+			.map(|(i, xi)| {
+				let mut numerator = BigUint::from(1u8);
+				let mut denominator = BigUint::from(1u8);
+				for (j, xj) in xs.iter().enumerate() {
+					if i != j {
+						numerator = numerator * (p - xj) % p;
+						denominator = denominator * ((p + xi - xj) % p) % p;
+					}
+				}
+				if denominator == zero {
+					return None;
+				}
+				Some(numerator * inverse(p, &denominator) % p)
+			})
+			.collect()
+	}
+
+	/// evaluate the polynomial through `points` at `x`
+	pub fn interpolate_at(
+		p: &BigUint,
+		points: &[(BigUint, BigUint)],
+		x: &BigUint,
+	) -> Option<BigUint> {
+		// shift the points so that `x` is at zero
+		let xs: Vec<BigUint> =
+			points.iter().map(|(xi, _)| (p + xi - x) % p).collect();
+		let basis = lagrange_at_zero(p, &xs)?;
+		Some(
+			points
+				.iter()
+				.zip(basis)
+				.fold(BigUint::from(0u8), |acc, ((_, y), b)| (acc + y * b) % p),
+		)
+	}
+
+	/// x^-1 mod p by Fermat's little theorem
+	fn inverse(p: &BigUint, x: &BigUint) -> BigUint {
+		x.modpow(&(p - 2u8), p)
+	}
+
+	/// k * g by double-and-add over the bits of k, most significant first
+	pub fn mul<C: CurveGroup>(g: C, k: &BigUint) -> C {
+		(0..k.bits()).rev().fold(C::zero(), |acc, bit| {
+			let acc = acc.double();
+			if k.bit(bit) {
+				acc + g
+			} else {
+				acc
+			}
+		})
+	}
+
+	/// the hashed El Gamal pad, hashed from a single serialized buffer
+	/// none if a point cannot be serialized
+	pub fn pad<C: CurveGroup>(shared: C, c1: C, pk: C) -> Option<[u8; 32]> {
+		let mut buffer = crate::proofs::hashed_el_gamal::PAD_LABEL.to_vec();
+		for point in [shared, c1, pk] {
+			point.into_affine().serialize_compressed(&mut buffer).ok()?;
+		}
+		Some(sha2::Sha256::digest(&buffer).into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_bls12_381::G1Projective as G1;
+	use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{TinyBLS377, ZBLS};
+
+	#[test]
+	fn share_math_matches_the_reference() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		for (n, t) in [(1, 1), (3, 2), (5, 5), (16, 11)] {
+			assert_eq!(check_shares::<TinyBLS377, _>(n, t, &mut rng), Ok(()));
+			assert_eq!(check_shares::<ZBLS, _>(n, t, &mut rng), Ok(()));
+		}
+	}
+
+	#[test]
+	fn encryption_matches_the_reference() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		assert_eq!(check_encryption::<G1, _>(9, &mut rng), Ok(()));
+		assert_eq!(check_encryption::<JubJub, _>(9, &mut rng), Ok(()));
+	}
+
+	#[test]
+	fn the_reference_rejects_repeated_points() {
+		let p = modulus::<ark_bls12_381::Fr>();
+		let xs = [BigUint::from(1u8), BigUint::from(1u8)];
+		assert_eq!(reference::lagrange_at_zero(&p, &xs), None);
+	}
+}
@@ -0,0 +1,350 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Errors
+//!
+//! - [`SerializationError`]: encoding and decoding elements
+//! - [`ProofError`]: encryption and proofs of knowledge, wraps
+//!   [`SerializationError`]
+//! - [`AcssError`]: dealing and recovery, wraps [`ProofError`]
+//!
+//! No public API panics on untrusted input, every failure is reported through
+//! one of these types.
+//!
+//! Every variant has a numeric code, stable across releases, for consumers
+//! that cannot match on Rust enums (FFI, WASM, logs): 1xx for serialization,
+//! 2xx for proofs and 3xx for ACSS errors. A wrapped error reports the code of
+//! its cause. Codes are never reassigned; new variants take new codes.
+
+use core::fmt;
+
+/// errors encountered while encoding or decoding elements
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerializationError {
+	/// an element could not be encoded
+	Encoding,
+	/// an element does not fit the expected buffer size
+	InvalidBufferSize,
+	/// an element could not be decoded (e.g. malformed hex, an invalid point,
+	/// or trailing bytes)
+	Decoding,
+}
+
+impl SerializationError {
+	/// the stable numeric code of this error
+	pub fn code(&self) -> u16 {
+		match self {
+			Self::Encoding => 100,
+			Self::InvalidBufferSize => 101,
+			Self::Decoding => 102,
+		}
+	}
+}
+
+impl fmt::Display for SerializationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Encoding => write!(f, "an element could not be encoded"),
+			Self::InvalidBufferSize =>
+				write!(f, "an element does not fit the expected buffer size"),
+			Self::Decoding => write!(f, "an element could not be decoded"),
+		}
+	}
+}
+
+/// errors for the hashed el gamal scheme and its proofs of knowledge
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofError {
+	/// an element could not be serialized
+	Serialization(SerializationError),
+	/// the public key is the identity
+	InvalidPublicKey,
+	/// the messages could not be encrypted (e.g. the number of ephemeral keys
+	/// does not match the number of messages)
+	EncryptionFailed,
+	/// the ciphertext tag does not match, so it was modified or the wrong key
+	/// was used
+	AuthenticationFailed,
+	/// the hash has no standard instance over the scalar field of the curve
+	UnsupportedField,
+}
+
+impl ProofError {
+	/// the stable numeric code of this error, that of the cause for
+	/// serialization errors
+	pub fn code(&self) -> u16 {
+		match self {
+			Self::Serialization(e) => e.code(),
+			Self::InvalidPublicKey => 200,
+			Self::EncryptionFailed => 201,
+			Self::AuthenticationFailed => 202,
+			Self::UnsupportedField => 203,
+		}
+	}
+}
+
+impl fmt::Display for ProofError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Serialization(e) => write!(f, "serialization failed: {e}"),
+			Self::InvalidPublicKey =>
+				write!(f, "the public key is the identity"),
+			Self::EncryptionFailed =>
+				write!(f, "the messages could not be encrypted"),
+			Self::AuthenticationFailed =>
+				write!(f, "the ciphertext could not be authenticated"),
+			Self::UnsupportedField =>
+				write!(f, "the hash is not defined over this field"),
+		}
+	}
+}
+
+impl From<SerializationError> for ProofError {
+	fn from(e: SerializationError) -> Self {
+		Self::Serialization(e)
+	}
+}
+
+/// errors for the ACSS algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcssError {
+	/// the committee was invalid (empty, buffer overflow, or contains the
+	/// identity as a public key)
+	InvalidCommittee,
+	/// the ciphertext could not be decrypted
+	InvalidCiphertext,
+	/// the commitment could not be verified
+	InvalidCommitment,
+	/// the proof could not be verified
+	InvalidProof,
+	/// insufficiently many valid proofs of knowledge were provided
+	InsufficientValidPoK,
+	/// a proof of knowledge could not be generated
+	Proof(ProofError),
+	/// the transcript could not be decoded
+	InvalidTranscript,
+	/// the threshold parameters are invalid (t = 0 or t > n) or do not match
+	/// the committee or dealing they are used with
+	InvalidParams,
+	/// a share index appears more than once
+	DuplicateShareIndex,
+	/// a share index is outside the range 1..=n of the dealing
+	ShareIndexOutOfRange,
+	/// the dealer signature is missing or does not verify
+	InvalidSignature,
+	/// a message was delivered more than once, or too late to tell
+	ReplayedMessage,
+	/// memory could not be locked (see `RLIMIT_MEMLOCK`)
+	MemoryLockFailed,
+	/// the operation was cancelled through its cancellation token
+	Cancelled,
+	/// a blinding factor is zero
+	InvalidBlindingFactor,
+	/// the share expired in an earlier epoch
+	ExpiredShare,
+	/// a message could not be handed to the network
+	SendFailed,
+	/// the outgoing queue has no room for the message
+	QueueFull,
+	/// the message belongs to another session, epoch or round
+	HeaderMismatch,
+	/// the export label is empty or the key length is out of range
+	InvalidExport,
+	/// a known-answer self test did not give the expected answer
+	SelfTestFailed,
+}
+
+impl AcssError {
+	/// the stable numeric code of this error, that of the cause for proof
+	/// errors
+	pub fn code(&self) -> u16 {
+		match self {
+			Self::InvalidCommittee => 300,
+			Self::InvalidCiphertext => 301,
+			Self::InvalidCommitment => 302,
+			Self::InvalidProof => 303,
+			Self::InsufficientValidPoK => 304,
+			Self::Proof(e) => e.code(),
+			Self::InvalidTranscript => 305,
+			Self::InvalidParams => 306,
+			Self::DuplicateShareIndex => 307,
+			Self::ShareIndexOutOfRange => 308,
+			Self::InvalidSignature => 309,
+			Self::ReplayedMessage => 310,
+			Self::MemoryLockFailed => 311,
+			Self::Cancelled => 312,
+			Self::InvalidBlindingFactor => 313,
+			Self::ExpiredShare => 314,
+			Self::SendFailed => 315,
+			Self::QueueFull => 316,
+			Self::HeaderMismatch => 317,
+			Self::InvalidExport => 318,
+			Self::SelfTestFailed => 319,
+		}
+	}
+}
+
+impl fmt::Display for AcssError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::InvalidCommittee => write!(f, "the committee is invalid"),
+			Self::InvalidCiphertext =>
+				write!(f, "the ciphertext could not be decrypted"),
+			Self::InvalidCommitment =>
+				write!(f, "the commitment could not be verified"),
+			Self::InvalidProof => write!(f, "the proof could not be verified"),
+			Self::InsufficientValidPoK =>
+				write!(f, "too few valid proofs of knowledge"),
+			Self::Proof(e) =>
+				write!(f, "a proof of knowledge could not be generated: {e}"),
+			Self::InvalidTranscript =>
+				write!(f, "the transcript could not be decoded"),
+			Self::InvalidParams =>
+				write!(f, "the threshold parameters are invalid"),
+			Self::DuplicateShareIndex =>
+				write!(f, "a share index appears more than once"),
+			Self::ShareIndexOutOfRange =>
+				write!(f, "a share index is outside the range of the dealing"),
+			Self::InvalidSignature =>
+				write!(f, "the dealer signature could not be verified"),
+			Self::ReplayedMessage =>
+				write!(f, "the message was already received"),
+			Self::MemoryLockFailed => write!(f, "memory could not be locked"),
+			Self::Cancelled => write!(f, "the operation was cancelled"),
+			Self::InvalidBlindingFactor =>
+				write!(f, "the blinding factor is zero"),
+			Self::ExpiredShare => write!(f, "the share has expired"),
+			Self::SendFailed => write!(f, "the message could not be sent"),
+			Self::QueueFull => write!(f, "the outgoing queue is full"),
+			Self::HeaderMismatch =>
+				write!(f, "the message belongs to another session"),
+			Self::InvalidExport =>
+				write!(f, "the export label or key length is invalid"),
+			Self::SelfTestFailed => write!(f, "a self test failed"),
+		}
+	}
+}
+
+impl From<ProofError> for AcssError {
+	fn from(e: ProofError) -> Self {
+		Self::Proof(e)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SerializationError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProofError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Serialization(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AcssError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Proof(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloc::string::ToString;
+
+	#[test]
+	fn errors_display_their_cause() {
+		let e: AcssError =
+			ProofError::from(SerializationError::InvalidBufferSize).into();
+		assert_eq!(
+			e.to_string(),
+			"a proof of knowledge could not be generated: serialization \
+			 failed: an element does not fit the expected buffer size"
+		);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn errors_chain_their_sources() {
+		use std::error::Error;
+
+		let e = AcssError::Proof(ProofError::Serialization(
+			SerializationError::Encoding,
+		));
+		let source = e.source().and_then(|e| e.source()).unwrap();
+		assert_eq!(source.to_string(), "an element could not be encoded");
+		assert!(AcssError::InvalidCommittee.source().is_none());
+	}
+
+	#[test]
+	fn error_codes_are_stable_and_distinct() {
+		use ark_std::{collections::BTreeSet, vec::Vec};
+
+		let serialization = [
+			SerializationError::Encoding,
+			SerializationError::InvalidBufferSize,
+			SerializationError::Decoding,
+		];
+		let proof = [
+			ProofError::InvalidPublicKey,
+			ProofError::EncryptionFailed,
+			ProofError::AuthenticationFailed,
+			ProofError::UnsupportedField,
+		];
+		let acss = [
+			AcssError::InvalidCommittee,
+			AcssError::InvalidCiphertext,
+			AcssError::InvalidCommitment,
+			AcssError::InvalidProof,
+			AcssError::InsufficientValidPoK,
+			AcssError::InvalidTranscript,
+			AcssError::InvalidParams,
+			AcssError::DuplicateShareIndex,
+			AcssError::ShareIndexOutOfRange,
+			AcssError::InvalidSignature,
+			AcssError::ReplayedMessage,
+			AcssError::MemoryLockFailed,
+			AcssError::Cancelled,
+			AcssError::InvalidBlindingFactor,
+			AcssError::ExpiredShare,
+			AcssError::SendFailed,
+			AcssError::QueueFull,
+			AcssError::HeaderMismatch,
+			AcssError::InvalidExport,
+			AcssError::SelfTestFailed,
+		];
+		let codes: Vec<u16> = serialization
+			.iter()
+			.map(SerializationError::code)
+			.chain(proof.iter().map(ProofError::code))
+			.chain(acss.iter().map(AcssError::code))
+			.collect();
+		assert_eq!(codes.iter().collect::<BTreeSet<_>>().len(), codes.len());
+		assert_eq!(codes[..3], [100, 101, 102]);
+		assert_eq!(codes[3..7], [200, 201, 202, 203]);
+		assert_eq!(codes[7..], (300..=319).collect::<Vec<_>>()[..]);
+
+		let wrapped: AcssError =
+			ProofError::from(SerializationError::Decoding).into();
+		assert_eq!(wrapped.code(), 102);
+	}
+}
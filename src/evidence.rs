@@ -0,0 +1,518 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Misbehavior Evidence
+//!
+//! Evidence that a dealer misbehaved, carrying exactly the public data a third
+//! party (e.g. a chain) needs to re-check the fault. Every kind of evidence is
+//! built from dealings signed by the accused dealer (see [`SignedDealing`]),
+//! so a fault cannot be pinned on a dealer that did not produce it.
+//!
+//! [`MisbehaviorEvidence::verify`] only checks the evidence against the keys
+//! it carries. The verifier must still check that `dealer` (and `recipient`
+//! or `n`, where present) match the committee it knows of.
+//!
+//! A complaint is resolved by the committee rather than by each member: a
+//! member that checked the evidence votes for its [`digest`] under
+//! [`COMPLAINT_SIGNATURE_CONTEXT`], and a [`QuorumCert`] of f + 1 votes
+//! (so at least one honest member checked it) upholds the complaint (see
+//! [`MisbehaviorEvidence::is_upheld`]).
+//!
+//! [`digest`]: MisbehaviorEvidence::digest
+//!
+//! Layout: `version || kind || dealer || body` where the body is
+//! - `index || recipient || dealing` for an invalid share
+//! - `dealing || dealing` for an equivocation
+//! - `n || dealing` for an invalid justification
+//!
+//! and each dealing is `header || signature || len || transcript`, with
+//! `header` the session, epoch and round the dealing was signed for and `len`
+//! the length of the compact transcript as a big endian u32.
+//!
+//! Dealers deal once per round, so an equivocation is two dealings signed for
+//! the same session, epoch and round with different transcripts. Dealings of
+//! different rounds, e.g. an honest dealer's dealings in consecutive epochs,
+//! are not evidence.
+
+use crate::{
+	error::AcssError,
+	keys::{CommitteeKey, PublicKey as EncryptionKey, ShareIndex},
+	messages::MessageHeader,
+	params::max_byzantine_faults,
+	quorum::QuorumCert,
+	transcript::{
+		decode_compact, read, read_array, read_point, write, SignedDealing,
+	},
+};
+use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+use w3f_bls::{EngineBLS, PublicKey, Signature};
+
+/// the version of the evidence encoding
+pub const EVIDENCE_VERSION: u8 = 2;
+/// the domain separation label of evidence digests
+pub const EVIDENCE_DIGEST_LABEL: &[u8] = b"acss/evidence-digest";
+/// the signing context of votes upholding a complaint
+pub const COMPLAINT_SIGNATURE_CONTEXT: &[u8] = b"acss/complaint";
+
+const KIND_INVALID_SHARE: u8 = 0;
+const KIND_EQUIVOCATION: u8 = 1;
+const KIND_INVALID_JUSTIFICATION: u8 = 2;
+
+/// evidence that a dealer misbehaved
+pub enum MisbehaviorEvidence<E: EngineBLS> {
+	/// the proof of knowledge dealt to `recipient` does not verify
+	InvalidShare {
+		/// the accused dealer
+		dealer: PublicKey<E>,
+		/// the dealing, signed by the dealer
+		dealing: SignedDealing<E>,
+		/// the index of the recipient's share in the dealing
+		index: ShareIndex,
+		/// the recipient's public key
+		recipient: PublicKey<E>,
+	},
+	/// the dealer signed two different dealings for the same round
+	Equivocation {
+		/// the accused dealer
+		dealer: PublicKey<E>,
+		/// the first dealing, signed by the dealer
+		first: SignedDealing<E>,
+		/// the second dealing, signed by the dealer
+		second: SignedDealing<E>,
+	},
+	/// the proofs of a dealing do not justify a dealing to a committee of
+	/// size `n`: the transcript does not decode, or deals the wrong number of
+	/// shares
+	InvalidJustification {
+		/// the accused dealer
+		dealer: PublicKey<E>,
+		/// the dealing, signed by the dealer
+		dealing: SignedDealing<E>,
+		/// the size of the committee
+		n: u8,
+	},
+}
+
+impl<E: EngineBLS> MisbehaviorEvidence<E> {
+	/// the accused dealer
+	pub fn dealer(&self) -> &PublicKey<E> {
+		match self {
+			Self::InvalidShare { dealer, .. } |
+			Self::Equivocation { dealer, .. } |
+			Self::InvalidJustification { dealer, .. } => dealer,
+		}
+	}
+
+	/// true if the evidence proves that `dealer` misbehaved
+	pub fn verify(&self) -> bool {
+		match self {
+			Self::InvalidShare { dealer, dealing, index, recipient } => {
+				if !dealing.verify(dealer) {
+					return false;
+				}
+				// an undecodable transcript is an invalid justification
+				let Ok(resharing) = decode_compact::<E>(&dealing.transcript)
+				else {
+					return false;
+				};
+				resharing.get(index.position()).is_some_and(|(_, pok)| {
+					!pok.verify(EncryptionKey(recipient.0))
+				})
+			},
+			Self::Equivocation { dealer, first, second } =>
+				first.header.same_round(&second.header) &&
+					first.transcript != second.transcript &&
+					first.verify(dealer) &&
+					second.verify(dealer),
+			Self::InvalidJustification { dealer, dealing, n } =>
+				dealing.verify(dealer) &&
+					decode_compact::<E>(&dealing.transcript)
+						.map_or(true, |resharing| {
+							resharing.len() != *n as usize
+						}),
+		}
+	}
+
+	/// the digest of the encoded evidence, which members vote on to uphold
+	/// the complaint
+	/// fails as `encode` does
+	pub fn digest(&self) -> Result<[u8; 32], AcssError> {
+		Ok(Sha256::new()
+			.chain_update(EVIDENCE_DIGEST_LABEL)
+			.chain_update(self.encode()?)
+			.finalize()
+			.into())
+	}
+
+	/// true if `certificate` holds the votes of at least f + 1 members of
+	/// `committee` for this evidence, where f is the number of Byzantine
+	/// members the committee tolerates
+	///
+	/// * `certificate`: The aggregated votes upholding the complaint
+	/// * `committee`: The public keys of the committee, in share order
+	pub fn is_upheld<K: CommitteeKey<E>>(
+		&self,
+		certificate: &QuorumCert<E>,
+		committee: &[K],
+	) -> bool {
+		let Ok(n) = u8::try_from(committee.len()) else {
+			return false;
+		};
+		self.digest().is_ok_and(|digest| digest == certificate.digest) &&
+			certificate.verify(
+				COMPLAINT_SIGNATURE_CONTEXT,
+				committee,
+				max_byzantine_faults(n) as usize + 1,
+			)
+	}
+
+	/// encode the evidence for submission
+	/// fails with `InvalidSignature` if a dealing is unsigned
+	pub fn encode(&self) -> Result<Vec<u8>, AcssError> {
+		let mut out = vec![EVIDENCE_VERSION];
+		match self {
+			Self::InvalidShare { dealer, dealing, index, recipient } => {
+				out.push(KIND_INVALID_SHARE);
+				write(&dealer.0, &mut out)?;
+				out.push(index.get());
+				write(&recipient.0, &mut out)?;
+				write_dealing(dealing, &mut out)?;
+			},
+			Self::Equivocation { dealer, first, second } => {
+				out.push(KIND_EQUIVOCATION);
+				write(&dealer.0, &mut out)?;
+				write_dealing(first, &mut out)?;
+				write_dealing(second, &mut out)?;
+			},
+			Self::InvalidJustification { dealer, dealing, n } => {
+				out.push(KIND_INVALID_JUSTIFICATION);
+				write(&dealer.0, &mut out)?;
+				out.push(*n);
+				write_dealing(dealing, &mut out)?;
+			},
+		}
+		Ok(out)
+	}
+
+	/// decode evidence, rejecting trailing bytes
+	/// the fault itself is not checked, see [`Self::verify`]
+	///
+	/// * `bytes`: The encoded evidence
+	pub fn decode(mut bytes: &[u8]) -> Result<Self, AcssError> {
+		let reader = &mut bytes;
+		let [version, kind] = read_array::<2>(reader)?;
+		if version != EVIDENCE_VERSION {
+			return Err(AcssError::InvalidTranscript);
+		}
+		let dealer = PublicKey(read_point(reader)?);
+		let evidence = match kind {
+			KIND_INVALID_SHARE => {
+				let [index] = read_array::<1>(reader)?;
+				let index = ShareIndex::new(index)
+					.ok_or(AcssError::InvalidTranscript)?;
+				let recipient = PublicKey(read_point(reader)?);
+				let dealing = read_dealing(reader)?;
+				Self::InvalidShare { dealer, dealing, index, recipient }
+			},
+			KIND_EQUIVOCATION => {
+				let first = read_dealing(reader)?;
+				let second = read_dealing(reader)?;
+				Self::Equivocation { dealer, first, second }
+			},
+			KIND_INVALID_JUSTIFICATION => {
+				let [n] = read_array::<1>(reader)?;
+				let dealing = read_dealing(reader)?;
+				Self::InvalidJustification { dealer, dealing, n }
+			},
+			_ => return Err(AcssError::InvalidTranscript),
+		};
+
+		if !reader.is_empty() {
+			return Err(AcssError::InvalidTranscript);
+		}
+		Ok(evidence)
+	}
+}
+
+/// write a signed dealing as `header || signature || len || transcript`
+pub(crate) fn write_dealing<E: EngineBLS>(
+	dealing: &SignedDealing<E>,
+	out: &mut Vec<u8>,
+) -> Result<(), AcssError> {
+	let signature =
+		dealing.signature.as_ref().ok_or(AcssError::InvalidSignature)?;
+	dealing.header.write(out);
+	write(&signature.0, out)?;
+	let len: u32 = dealing
+		.transcript
+		.len()
+		.try_into()
+		.map_err(|_| AcssError::InvalidTranscript)?;
+	out.extend_from_slice(&len.to_be_bytes());
+	out.extend_from_slice(&dealing.transcript);
+	Ok(())
+}
+
+/// read a signed dealing written by `write_dealing`
+pub(crate) fn read_dealing<E: EngineBLS>(
+	reader: &mut &[u8],
+) -> Result<SignedDealing<E>, AcssError> {
+	let header = MessageHeader::read(reader)?;
+	let signature = Signature(read(reader)?);
+	let len = u32::from_be_bytes(read_array::<4>(reader)?) as usize;
+	if reader.len() < len {
+		return Err(AcssError::InvalidTranscript);
+	}
+	let (transcript, tail) = reader.split_at(len);
+	*reader = tail;
+	Ok(SignedDealing {
+		header,
+		transcript: transcript.to_vec(),
+		signature: Some(signature),
+	})
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::HighThresholdACSS, params::ThresholdParams,
+		proofs::hashed_el_gamal_sigma::BatchPoK, quorum::Vote,
+	};
+	use ark_ec::Group;
+	use ark_ff::{One, UniformRand};
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{DoublePublicKey, KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	const HEADER: MessageHeader =
+		MessageHeader { session: [7; 32], epoch: 3, round: 1, sequence: 0 };
+
+	fn committee_and_resharing(
+		rng: &mut ChaCha20Rng,
+	) -> (
+		Vec<PublicKey<E>>,
+		Vec<(DoublePublicKey<E>, BatchPoK<<E as EngineBLS>::PublicKeyGroup>)>,
+	) {
+		let committee: Vec<PublicKey<E>> = (0..3)
+			.map(|_| KeypairVT::<E>::generate(&mut *rng).public)
+			.collect();
+		let resharing = HighThresholdACSS::<E>::reshare(
+			<E as EngineBLS>::Scalar::rand(rng),
+			<E as EngineBLS>::Scalar::rand(rng),
+			&committee,
+			ThresholdParams::for_committee(&committee, 2).unwrap(),
+			rng,
+		)
+		.unwrap();
+		(committee, resharing.into_dealings())
+	}
+
+	#[test]
+	fn complaints_are_upheld_by_a_quorum_of_votes() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let (_, resharing) = committee_and_resharing(&mut rng);
+		let evidence = MisbehaviorEvidence::Equivocation {
+			dealer: dealer.public,
+			first: SignedDealing::sign(HEADER, &resharing[..2], &dealer)
+				.unwrap(),
+			second: SignedDealing::sign(HEADER, &resharing, &dealer).unwrap(),
+		};
+		let digest = evidence.digest().unwrap();
+		assert_eq!(round_trip(&evidence).digest().unwrap(), digest);
+
+		let members: Vec<KeypairVT<E>> =
+			(0..4).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let votes: Vec<Vote<E>> = members
+			.iter()
+			.enumerate()
+			.map(|(position, kp)| {
+				let index = ShareIndex::from_position(position).unwrap();
+				Vote::sign(COMPLAINT_SIGNATURE_CONTEXT, digest, index, kp)
+			})
+			.collect();
+
+		// f = 1 in a committee of 4
+		let certificate = QuorumCert::aggregate(&votes[2..]).unwrap();
+		assert!(evidence.is_upheld(&certificate, &committee));
+		let too_few = QuorumCert::aggregate(&votes[..1]).unwrap();
+		assert!(!evidence.is_upheld(&too_few, &committee));
+
+		let other = MisbehaviorEvidence::Equivocation {
+			dealer: dealer.public,
+			first: SignedDealing::sign(HEADER, &resharing[..1], &dealer)
+				.unwrap(),
+			second: SignedDealing::sign(HEADER, &resharing, &dealer).unwrap(),
+		};
+		assert!(!other.is_upheld(&certificate, &committee));
+	}
+
+	fn round_trip(evidence: &MisbehaviorEvidence<E>) -> MisbehaviorEvidence<E> {
+		let bytes = evidence.encode().unwrap();
+		let decoded = MisbehaviorEvidence::<E>::decode(&bytes).unwrap();
+		assert_eq!(decoded.encode().unwrap(), bytes);
+		decoded
+	}
+
+	#[test]
+	fn invalid_shares_are_attributable() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let (committee, mut resharing) = committee_and_resharing(&mut rng);
+
+		// the proof for the second member is made for another key
+		resharing[1].1 = BatchPoK::prove(
+			&[<E as EngineBLS>::Scalar::one(); 2],
+			EncryptionKey(<E as EngineBLS>::PublicKeyGroup::generator()),
+			&mut rng,
+		)
+		.unwrap();
+		let evidence = |index: u8| MisbehaviorEvidence::InvalidShare {
+			dealer: dealer.public,
+			dealing: SignedDealing::sign(HEADER, &resharing, &dealer).unwrap(),
+			index: ShareIndex::new(index).unwrap(),
+			recipient: committee[index as usize - 1],
+		};
+
+		let accusation = round_trip(&evidence(2));
+		assert!(accusation.verify());
+		assert_eq!(accusation.dealer().0, dealer.public.0);
+		// the honest share cannot be used to accuse the dealer
+		assert!(!round_trip(&evidence(1)).verify());
+
+		// and neither can a dealing the dealer did not sign
+		let other = KeypairVT::<E>::generate(&mut rng);
+		let forged = MisbehaviorEvidence::InvalidShare {
+			dealer: dealer.public,
+			dealing: SignedDealing::sign(HEADER, &resharing, &other).unwrap(),
+			index: ShareIndex::new(2).unwrap(),
+			recipient: committee[1],
+		};
+		assert!(!forged.verify());
+	}
+
+	#[test]
+	fn equivocations_and_invalid_justifications_are_attributable() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let (_, first) = committee_and_resharing(&mut rng);
+		let (_, second) = committee_and_resharing(&mut rng);
+		let sign = |resharing: &[_]| {
+			SignedDealing::<E>::sign(HEADER, resharing, &dealer).unwrap()
+		};
+
+		let equivocation = MisbehaviorEvidence::Equivocation {
+			dealer: dealer.public,
+			first: sign(&first),
+			second: sign(&second),
+		};
+		assert!(round_trip(&equivocation).verify());
+		let repeated = MisbehaviorEvidence::Equivocation {
+			dealer: dealer.public,
+			first: sign(&first),
+			second: sign(&first),
+		};
+		assert!(!repeated.verify());
+
+		let justification = |n| MisbehaviorEvidence::InvalidJustification {
+			dealer: dealer.public,
+			dealing: sign(&first[..2]),
+			n,
+		};
+		assert!(round_trip(&justification(3)).verify());
+		assert!(!justification(2).verify());
+	}
+
+	#[test]
+	fn dealings_of_different_rounds_are_not_equivocations() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let (_, first) = committee_and_resharing(&mut rng);
+		let (_, second) = committee_and_resharing(&mut rng);
+
+		// an honest dealer deals again in the next epoch, or the next round
+		for next in [
+			MessageHeader { epoch: HEADER.epoch + 1, ..HEADER },
+			MessageHeader { round: HEADER.round + 1, ..HEADER },
+			MessageHeader { session: [8; 32], ..HEADER },
+		] {
+			let evidence = MisbehaviorEvidence::Equivocation {
+				dealer: dealer.public,
+				first: SignedDealing::sign(HEADER, &first, &dealer).unwrap(),
+				second: SignedDealing::sign(next, &second, &dealer).unwrap(),
+			};
+			assert!(!round_trip(&evidence).verify());
+		}
+
+		// nor can an accuser relabel one of the dealings so the rounds match
+		let next = MessageHeader { epoch: HEADER.epoch + 1, ..HEADER };
+		let mut relabeled =
+			SignedDealing::sign(next, &second, &dealer).unwrap();
+		relabeled.header = HEADER;
+		let forged = MisbehaviorEvidence::Equivocation {
+			dealer: dealer.public,
+			first: SignedDealing::sign(HEADER, &first, &dealer).unwrap(),
+			second: relabeled,
+		};
+		assert!(!round_trip(&forged).verify());
+
+		// sequence numbers do not tell dealings of the same round apart
+		let renumbered = MisbehaviorEvidence::Equivocation {
+			dealer: dealer.public,
+			first: SignedDealing::sign(HEADER, &first, &dealer).unwrap(),
+			second: SignedDealing::sign(HEADER.next(), &second, &dealer)
+				.unwrap(),
+		};
+		assert!(renumbered.verify());
+	}
+
+	#[test]
+	fn evidence_decoding_rejects_malformed_input() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let (_, resharing) = committee_and_resharing(&mut rng);
+
+		let unsigned = MisbehaviorEvidence::InvalidJustification {
+			dealer: dealer.public,
+			dealing: SignedDealing::unsigned(HEADER, &resharing).unwrap(),
+			n: 3,
+		};
+		assert_eq!(unsigned.encode().err(), Some(AcssError::InvalidSignature));
+
+		let bytes = MisbehaviorEvidence::InvalidJustification {
+			dealer: dealer.public,
+			dealing: SignedDealing::sign(HEADER, &resharing, &dealer).unwrap(),
+			n: 3,
+		}
+		.encode()
+		.unwrap();
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		let mut bad_kind = bytes.clone();
+		bad_kind[1] = 3;
+		for bytes in [&bytes[..bytes.len() - 1], &trailing, &bad_kind] {
+			assert_eq!(
+				MisbehaviorEvidence::<E>::decode(bytes).err(),
+				Some(AcssError::InvalidTranscript)
+			);
+		}
+	}
+}
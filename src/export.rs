@@ -0,0 +1,232 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Key Export
+//!
+//! A recovered secret is a scalar of the BLS scalar field, and using its bytes
+//! directly as a key for other purposes ties those purposes together: a key
+//! leaked by one application is the key of all of them.
+//! [`DoubleSecret::export_key`] derives independent application keys from it
+//! instead, with HKDF-SHA256 (RFC 5869) keyed by the secret and expanded under
+//! a label naming the purpose. Labels are mandatory, so every exported key is
+//! bound to one.
+
+use crate::{
+	acss::DoubleSecret,
+	error::{AcssError, SerializationError},
+	wipe,
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{vec, vec::Vec};
+use core::fmt;
+use sha2::{Digest, Sha256};
+use w3f_bls::EngineBLS;
+use zeroize::Zeroize;
+#[cfg(feature = "zeroize")]
+use zeroize::ZeroizeOnDrop;
+
+/// the HKDF salt of exported keys
+pub const EXPORT_SALT: &[u8] = b"acss/export";
+/// the longest key HKDF-SHA256 can output, in bytes
+pub const MAX_EXPORT_LEN: usize = 255 * 32;
+
+const BLOCK_LEN: usize = 64;
+
+/// an application key derived from a recovered secret
+/// with the `zeroize` feature, it is wiped when it is dropped
+#[derive(Clone, PartialEq, Eq)]
+pub struct ExportedKey(Vec<u8>);
+
+impl ExportedKey {
+	/// the bytes of the key
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl Zeroize for ExportedKey {
+	fn zeroize(&mut self) {
+		self.0.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ExportedKey {
+	fn drop(&mut self) {
+		self.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for ExportedKey {}
+
+impl fmt::Debug for ExportedKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("ExportedKey(<redacted>)")
+	}
+}
+
+impl<E: EngineBLS> DoubleSecret<E> {
+	/// derive a `len` byte key for the purpose named by `label` from the
+	/// secret, so that keys exported under different labels are independent
+	/// fails with `InvalidExport` if the label is empty or `len` is not in
+	/// 1..=`MAX_EXPORT_LEN`, and with `Proof(Serialization(Encoding))` if the
+	/// secret cannot be encoded
+	///
+	/// * `label`: The purpose of the key, e.g. `b"myapp/tls"`
+	/// * `len`: The length of the key in bytes
+	pub fn export_key(
+		&self,
+		label: &[u8],
+		len: usize,
+	) -> Result<ExportedKey, AcssError> {
+		if label.is_empty() || !(1..=MAX_EXPORT_LEN).contains(&len) {
+			return Err(AcssError::InvalidExport);
+		}
+		let mut ikm = Vec::new();
+		if self.0.serialize_compressed(&mut ikm).is_err() {
+			wipe(&mut ikm);
+			return Err(AcssError::Proof(SerializationError::Encoding.into()));
+		}
+		let mut okm = vec![0; len];
+		hkdf_sha256(EXPORT_SALT, &ikm, label, &mut okm);
+		wipe(&mut ikm);
+		Ok(ExportedKey(okm))
+	}
+}
+
+/// HMAC-SHA256 of the concatenation of `message` under `key`, at most one
+/// block long
+fn hmac_sha256(key: &[u8], message: &[&[u8]]) -> [u8; 32] {
+	let mut block = [0u8; BLOCK_LEN];
+	if key.len() > BLOCK_LEN {
+		block[..32].copy_from_slice(&Sha256::digest(key));
+	} else {
+		block[..key.len()].copy_from_slice(key);
+	}
+	let mut inner = Sha256::new();
+	inner.update(block.map(|b| b ^ 0x36));
+	for part in message {
+		inner.update(part);
+	}
+	let mut inner = inner.finalize();
+	let mut outer = Sha256::new();
+	outer.update(block.map(|b| b ^ 0x5c));
+	outer.update(inner);
+	wipe(&mut block);
+	wipe(&mut inner[..]);
+	outer.finalize().into()
+}
+
+/// fill `okm` with HKDF-SHA256 of `ikm`
+///
+/// * `salt`: The extraction salt
+/// * `ikm`: The input keying material
+/// * `info`: The context of the output
+/// * `okm`: The output keying material, at most `MAX_EXPORT_LEN` bytes
+pub(crate) fn hkdf_sha256(
+	salt: &[u8],
+	ikm: &[u8],
+	info: &[u8],
+	okm: &mut [u8],
+) {
+	let mut prk = hmac_sha256(salt, &[ikm]);
+	let mut block = [0u8; 32];
+	for (counter, chunk) in okm.chunks_mut(32).enumerate() {
+		let previous: &[u8] = if counter == 0 { &[] } else { &block };
+		block = hmac_sha256(&prk, &[previous, info, &[counter as u8 + 1]]);
+		chunk.copy_from_slice(&block[..chunk.len()]);
+	}
+	wipe(&mut prk);
+	wipe(&mut block);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloc::string::String;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	fn hex(bytes: &[u8]) -> String {
+		bytes.iter().map(|b| format!("{b:02x}")).collect()
+	}
+
+	#[test]
+	fn hkdf_matches_rfc_5869() {
+		// test case 1
+		let mut okm = [0u8; 42];
+		hkdf_sha256(
+			&(0..=12).collect::<Vec<u8>>(),
+			&[0x0b; 22],
+			&(0xf0..=0xf9).collect::<Vec<u8>>(),
+			&mut okm,
+		);
+		assert_eq!(
+			hex(&okm),
+			"3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+			 34007208d5b887185865"
+		);
+		// test case 3, with an empty salt and info
+		hkdf_sha256(&[], &[0x0b; 22], &[], &mut okm);
+		assert_eq!(
+			hex(&okm),
+			"8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d\
+			 9d201395faa4b61a96c8"
+		);
+	}
+
+	#[test]
+	fn exported_keys_depend_on_the_label_and_secret() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let scalar = <E as EngineBLS>::Scalar::rand(&mut rng);
+		let secret = DoubleSecret::<E>(scalar, scalar);
+		let key = secret.export_key(b"app/tls", 32).unwrap();
+		assert_eq!(key.as_bytes().len(), 32);
+		// the blinding share is not part of the secret
+		let reblinded =
+			DoubleSecret::<E>(scalar, <E as EngineBLS>::Scalar::rand(&mut rng));
+		assert_eq!(reblinded.export_key(b"app/tls", 32).unwrap(), key);
+		// a longer key extends a shorter one under the same label
+		let long = secret.export_key(b"app/tls", 100).unwrap();
+		assert_eq!(&long.as_bytes()[..32], key.as_bytes());
+		assert_ne!(secret.export_key(b"app/ssh", 32).unwrap(), key);
+		let other =
+			DoubleSecret::<E>(<E as EngineBLS>::Scalar::rand(&mut rng), scalar);
+		assert_ne!(other.export_key(b"app/tls", 32).unwrap(), key);
+		assert_eq!(format!("{key:?}"), "ExportedKey(<redacted>)");
+
+		for (label, len) in
+			[(&b""[..], 32), (b"app/tls", 0), (b"app/tls", 8161)]
+		{
+			assert_eq!(
+				secret.export_key(label, len).err(),
+				Some(AcssError::InvalidExport)
+			);
+		}
+		assert_eq!(
+			secret
+				.export_key(b"app/tls", MAX_EXPORT_LEN)
+				.unwrap()
+				.as_bytes()
+				.len(),
+			MAX_EXPORT_LEN
+		);
+	}
+}
@@ -0,0 +1,283 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Foreign Key Formats
+//!
+//! A committee-held secret can back subsystems that do not speak BLS, such as
+//! TLS or SSH host keys. [`DoubleSecret::ed25519_keypair`] and
+//! [`DoubleSecret::x25519_keypair`] derive a 32 byte seed from the secret as
+//! [`DoubleSecret::export_key`] does, under a label naming the algorithm and
+//! the purpose, and expand it into a keypair as RFC 8032 (Ed25519) and RFC
+//! 7748 (X25519) do: hashing and clamping the seed into a scalar, and
+//! multiplying the base point of edwards25519 by it. The same secret and
+//! label always give the same keypair.
+//!
+//! The curve arithmetic is that of `curve25519-dalek`, whose base point
+//! multiplication runs in constant time. The module is only built with the
+//! `foreign` feature.
+//!
+//! The private keys are output in the formats other libraries import: the
+//! Ed25519 seed and the X25519 scalar bytes before clamping.
+
+use crate::{acss::DoubleSecret, error::AcssError, wipe};
+use ark_std::vec::Vec;
+use core::fmt;
+use curve25519_dalek::EdwardsPoint;
+use sha2::{Digest, Sha512};
+use subtle::{Choice, ConstantTimeEq};
+use w3f_bls::EngineBLS;
+use zeroize::Zeroize;
+#[cfg(feature = "zeroize")]
+use zeroize::ZeroizeOnDrop;
+
+/// the label prefix of Ed25519 seeds
+pub const ED25519_LABEL: &[u8] = b"acss/ed25519/";
+/// the label prefix of X25519 secrets
+pub const X25519_LABEL: &[u8] = b"acss/x25519/";
+
+/// an Ed25519 keypair
+/// with the `zeroize` feature, the seed is wiped when it is dropped
+#[derive(Clone)]
+pub struct Ed25519Keypair {
+	seed: [u8; 32],
+	public: [u8; 32],
+}
+
+impl Ed25519Keypair {
+	/// the keypair of an RFC 8032 seed
+	///
+	/// * `seed`: The private key
+	pub fn from_seed(seed: [u8; 32]) -> Self {
+		let mut digest: [u8; 64] = Sha512::digest(seed).into();
+		let mut scalar = [0u8; 32];
+		scalar.copy_from_slice(&digest[..32]);
+		wipe(&mut digest);
+		let public = base_mul(&mut scalar).compress().to_bytes();
+		Self { seed, public }
+	}
+
+	/// the private key, as the seed RFC 8032 expands
+	pub fn seed(&self) -> &[u8; 32] {
+		&self.seed
+	}
+
+	/// the compressed public key
+	pub fn public_key(&self) -> [u8; 32] {
+		self.public
+	}
+}
+
+/// an X25519 keypair
+/// with the `zeroize` feature, the secret is wiped when it is dropped
+#[derive(Clone)]
+pub struct X25519Keypair {
+	secret: [u8; 32],
+	public: [u8; 32],
+}
+
+impl X25519Keypair {
+	/// the keypair of an RFC 7748 secret, clamped when it is used
+	///
+	/// * `secret`: The private key
+	pub fn from_secret(secret: [u8; 32]) -> Self {
+		let mut scalar = secret;
+		// the u-coordinate of the birationally equivalent curve25519 point
+		let public = base_mul(&mut scalar).to_montgomery().to_bytes();
+		Self { secret, public }
+	}
+
+	/// the private key, before clamping
+	pub fn secret(&self) -> &[u8; 32] {
+		&self.secret
+	}
+
+	/// the public key, the u-coordinate of the scalar times 9
+	pub fn public_key(&self) -> [u8; 32] {
+		self.public
+	}
+}
+
+macro_rules! secret_keypair {
+	($name:ident, $secret:ident) => {
+		impl Zeroize for $name {
+			fn zeroize(&mut self) {
+				self.$secret.zeroize();
+			}
+		}
+
+		#[cfg(feature = "zeroize")]
+		impl Drop for $name {
+			fn drop(&mut self) {
+				self.zeroize();
+			}
+		}
+
+		#[cfg(feature = "zeroize")]
+		impl ZeroizeOnDrop for $name {}
+
+		impl ConstantTimeEq for $name {
+			/// compare the private keys without short-circuiting, the public
+			/// keys being derived from them
+			fn ct_eq(&self, other: &Self) -> Choice {
+				self.$secret.ct_eq(&other.$secret)
+			}
+		}
+
+		impl PartialEq for $name {
+			fn eq(&self, other: &Self) -> bool {
+				self.ct_eq(other).into()
+			}
+		}
+
+		impl Eq for $name {}
+
+		impl fmt::Debug for $name {
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				f.debug_struct(stringify!($name))
+					.field("public", &self.public)
+					.finish_non_exhaustive()
+			}
+		}
+	};
+}
+
+secret_keypair!(Ed25519Keypair, seed);
+secret_keypair!(X25519Keypair, secret);
+
+impl<E: EngineBLS> DoubleSecret<E> {
+	/// derive the Ed25519 keypair for the purpose named by `label`
+	/// fails with `InvalidExport` if the label is empty
+	///
+	/// * `label`: The purpose of the key, e.g. `b"myapp/ssh-host"`
+	pub fn ed25519_keypair(
+		&self,
+		label: &[u8],
+	) -> Result<Ed25519Keypair, AcssError> {
+		Ok(Ed25519Keypair::from_seed(self.seed(ED25519_LABEL, label)?))
+	}
+
+	/// derive the X25519 keypair for the purpose named by `label`
+	/// fails with `InvalidExport` if the label is empty
+	///
+	/// * `label`: The purpose of the key, e.g. `b"myapp/tls"`
+	pub fn x25519_keypair(
+		&self,
+		label: &[u8],
+	) -> Result<X25519Keypair, AcssError> {
+		Ok(X25519Keypair::from_secret(self.seed(X25519_LABEL, label)?))
+	}
+
+	/// the 32 byte key exported under `prefix || label`
+	fn seed(&self, prefix: &[u8], label: &[u8]) -> Result<[u8; 32], AcssError> {
+		if label.is_empty() {
+			return Err(AcssError::InvalidExport);
+		}
+		let label: Vec<u8> = prefix.iter().chain(label).copied().collect();
+		let key = self.export_key(&label, 32)?;
+		let mut seed = [0u8; 32];
+		seed.copy_from_slice(key.as_bytes());
+		Ok(seed)
+	}
+}
+
+/// clamp `scalar` as RFC 7748 does and multiply the base point by it in
+/// constant time, wiping the scalar
+fn base_mul(scalar: &mut [u8; 32]) -> EdwardsPoint {
+	let point = EdwardsPoint::mul_base_clamped(*scalar);
+	wipe(scalar);
+	point
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	fn bytes(encoded: &str) -> [u8; 32] {
+		hex::decode(encoded).unwrap().try_into().unwrap()
+	}
+
+	#[test]
+	fn keypairs_match_the_rfc_test_vectors() {
+		// RFC 8032, section 7.1, tests 1 to 3
+		for (seed, public) in [
+			(
+				"9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+				"d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+			),
+			(
+				"4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+				"3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+			),
+			(
+				"c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+				"fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+			),
+		] {
+			let keypair = Ed25519Keypair::from_seed(bytes(seed));
+			assert_eq!(hex::encode(keypair.public_key()), public);
+		}
+		// RFC 7748, section 6.1
+		for (secret, public) in [
+			(
+				"77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+				"8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a",
+			),
+			(
+				"5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb",
+				"de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f",
+			),
+		] {
+			let keypair = X25519Keypair::from_secret(bytes(secret));
+			assert_eq!(hex::encode(keypair.public_key()), public);
+			assert_eq!(hex::encode(keypair.secret()), secret);
+		}
+	}
+
+	#[test]
+	fn keypairs_are_derived_deterministically_per_label() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let ssh = secret.ed25519_keypair(b"app/ssh").unwrap();
+		assert_eq!(secret.ed25519_keypair(b"app/ssh").unwrap(), ssh);
+		assert_eq!(Ed25519Keypair::from_seed(*ssh.seed()), ssh);
+		assert_ne!(secret.ed25519_keypair(b"app/git").unwrap(), ssh);
+		let tls = secret.x25519_keypair(b"app/ssh").unwrap();
+		assert_eq!(secret.x25519_keypair(b"app/ssh").unwrap(), tls);
+		// the algorithm is part of the label
+		assert_ne!(tls.secret(), ssh.seed());
+		assert_ne!(
+			&tls.secret()[..],
+			secret.export_key(b"app/ssh", 32).unwrap().as_bytes()
+		);
+		assert_eq!(
+			secret.ed25519_keypair(b"").err(),
+			Some(AcssError::InvalidExport)
+		);
+		assert_eq!(
+			secret.x25519_keypair(b"").err(),
+			Some(AcssError::InvalidExport)
+		);
+		assert!(!format!("{ssh:?}").contains(&format!("{:?}", ssh.seed())));
+	}
+}
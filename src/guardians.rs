@@ -0,0 +1,478 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Social Recovery
+//!
+//! An owner protects a key by dealing it to a set of guardians, any t of
+//! which can later restore it ([`protect`]). Each guardian checks and keeps
+//! its share with [`accept`].
+//!
+//! To recover, e.g. after losing the device holding the key, the owner
+//! generates a fresh keypair and sends the guardians a [`RecoveryRequest`]
+//! for it. Each guardian that approves deals its share to the fresh key and
+//! signs the request ([`approve`]). The owner collects the approvals in a
+//! [`Recovery`], which checks each against the guardian's public key and the
+//! public key of its share, and reconstructs the key once t guardians have
+//! approved.
+
+use crate::{
+	acss::{decrypt_pok, DoubleSecret, Keypair},
+	error::AcssError,
+	keys::{PublicKey as EncryptionKey, SecretKey, ShareIndex},
+	lagrange::interpolate,
+	merkle::merkle_root,
+	params::ThresholdParams,
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+	resharing::Resharing,
+	rng::SecureRng,
+	transcript::write,
+	wipe,
+};
+use ark_std::{collections::BTreeMap, slice, vec::Vec};
+use sha2::{Digest, Sha256};
+use w3f_bls::{
+	DoublePublicKeyScheme, EngineBLS, Message, PublicKey, SecretKeyVT,
+	Signature,
+};
+
+/// the signing context of guardian approvals
+pub const APPROVAL_CONTEXT: &[u8] = b"acss/social-recovery/approval";
+
+/// deal `secret` to `guardians`, any `threshold` of which can restore it
+/// fails with `InvalidParams` unless 0 < `threshold` <= the number of
+/// guardians, and with `InvalidCommittee` if there are no guardians or one
+/// of their keys is the identity
+///
+/// * `secret`: The key to protect
+/// * `guardians`: The guardians' public keys
+/// * `threshold`: The number of guardians needed to restore the key
+/// * `rng`: A CSPRNG
+pub fn protect<E: EngineBLS, R: SecureRng>(
+	secret: &DoubleSecret<E>,
+	guardians: &[PublicKey<E>],
+	threshold: u8,
+	rng: R,
+) -> Result<Resharing<E>, AcssError> {
+	let params = ThresholdParams::for_committee(guardians, threshold)?;
+	secret.reshare(guardians, params, rng)
+}
+
+/// decrypt and check a guardian's share of a protected key
+/// outputs the index of the share and the share
+/// fails with `InvalidCommittee` if `keypair` is not a guardian, with
+/// `InsufficientValidPoK` if its PoK does not verify and with
+/// `InvalidCommitment` if the share does not match its public key
+///
+/// * `keypair`: The guardian's keypair
+/// * `guardianship`: The dealing output by `protect`
+pub fn accept<E: EngineBLS>(
+	keypair: &Keypair<E>,
+	guardianship: &Resharing<E>,
+) -> Result<(ShareIndex, DoubleSecret<E>), AcssError> {
+	let position = guardianship
+		.recipients()
+		.iter()
+		.position(|pk| pk.0 == keypair.public().0)
+		.ok_or(AcssError::InvalidCommittee)?;
+	let index = ShareIndex::from_position(position)
+		.ok_or(AcssError::InvalidCommittee)?;
+	let pok =
+		guardianship.by_index(index).ok_or(AcssError::InvalidCommittee)?;
+	let share =
+		keypair.recover(slice::from_ref(pok), ThresholdParams::new(1, 1)?)?;
+	if !matches_share(guardianship, index, share.expose_secret()) {
+		return Err(AcssError::InvalidCommitment);
+	}
+	Ok((index, share))
+}
+
+/// true if `share` is the secret behind the public key of the share at
+/// `index` of `guardianship`
+fn matches_share<E: EngineBLS>(
+	guardianship: &Resharing<E>,
+	index: ShareIndex,
+	share: &E::Scalar,
+) -> bool {
+	guardianship.share_public_key(index).is_some_and(|expected| {
+		let pk = SecretKeyVT::<E>(*share).into_double_public_key();
+		pk.0 == expected.0 && pk.1 == expected.1
+	})
+}
+
+/// a request to restore a protected key to a fresh key
+pub struct RecoveryRequest<E: EngineBLS> {
+	/// the Merkle root of the dealing to the guardians
+	pub guardianship: [u8; 32],
+	/// the fresh key to restore the protected key to
+	pub key: PublicKey<E>,
+}
+
+impl<E: EngineBLS> Clone for RecoveryRequest<E> {
+	fn clone(&self) -> Self {
+		Self { guardianship: self.guardianship, key: PublicKey(self.key.0) }
+	}
+}
+
+impl<E: EngineBLS> RecoveryRequest<E> {
+	/// a request to restore the key protected by `guardianship` to `key`
+	/// fails with `InvalidTranscript` if the dealing cannot be encoded
+	///
+	/// * `guardianship`: The dealing output by `protect`
+	/// * `key`: The fresh key
+	pub fn new(
+		guardianship: &Resharing<E>,
+		key: PublicKey<E>,
+	) -> Result<Self, AcssError> {
+		Ok(Self { guardianship: merkle_root(guardianship)?, key })
+	}
+
+	/// the message a guardian at `index` signs to approve the request
+	/// fails with `InvalidTranscript` if the fresh key cannot be encoded
+	fn message(&self, index: ShareIndex) -> Result<Message, AcssError> {
+		let mut h = Sha256::new();
+		h.update(self.guardianship);
+		let mut key = Vec::new();
+		write(&self.key.0, &mut key)?;
+		h.update(key);
+		h.update([index.get()]);
+		Ok(Message::new(APPROVAL_CONTEXT, &h.finalize()))
+	}
+}
+
+/// a guardian's approval of a recovery request, its share dealt to the fresh
+/// key
+pub struct GuardianApproval<E: EngineBLS> {
+	/// the index of the guardian's share
+	pub index: ShareIndex,
+	/// the guardian's share, encrypted to the fresh key
+	pub pok: BatchPoK<E::PublicKeyGroup>,
+	/// the guardian's signature on the request
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for GuardianApproval<E> {
+	fn clone(&self) -> Self {
+		Self {
+			index: self.index,
+			pok: self.pok.clone(),
+			signature: Signature(self.signature.0),
+		}
+	}
+}
+
+/// approve a recovery request as the guardian holding the share at `index`
+/// fails with `Proof(InvalidPublicKey)` if the fresh key is the identity,
+/// and with `InvalidTranscript` if it cannot be encoded
+///
+/// the guardian should confirm out of band that the request comes from the
+/// owner before approving it
+///
+/// * `keypair`: The guardian's keypair
+/// * `share`: The guardian's share, as output by `accept`
+/// * `index`: The index of the guardian's share
+/// * `request`: The request to approve
+/// * `rng`: A CSPRNG
+pub fn approve<E: EngineBLS, R: SecureRng>(
+	keypair: &Keypair<E>,
+	share: &DoubleSecret<E>,
+	index: ShareIndex,
+	request: &RecoveryRequest<E>,
+	mut rng: R,
+) -> Result<GuardianApproval<E>, AcssError> {
+	let mut messages =
+		[*share.expose_secret(), *share.expose_blinding_secret()];
+	let pok =
+		BatchPoK::prove(&messages, EncryptionKey(request.key.0), &mut rng);
+	wipe(&mut messages);
+	let signature = keypair.0.sign(&request.message(index)?);
+	Ok(GuardianApproval { index, pok: pok?, signature })
+}
+
+/// the approvals of a recovery request collected by the owner
+pub struct Recovery<'a, E: EngineBLS> {
+	guardianship: &'a Resharing<E>,
+	keypair: &'a Keypair<E>,
+	request: RecoveryRequest<E>,
+	threshold: u8,
+	shares: BTreeMap<ShareIndex, DoubleSecret<E>>,
+}
+
+impl<'a, E: EngineBLS> Recovery<'a, E> {
+	/// collect approvals for restoring the key protected by `guardianship`
+	/// to `keypair`
+	/// fails with `InvalidParams` unless 0 < `threshold` <= the number of
+	/// guardians, and with `InvalidTranscript` if the dealing cannot be
+	/// encoded
+	///
+	/// * `guardianship`: The dealing output by `protect`
+	/// * `threshold`: The number of guardians needed to restore the key
+	/// * `keypair`: The fresh keypair
+	pub fn new(
+		guardianship: &'a Resharing<E>,
+		threshold: u8,
+		keypair: &'a Keypair<E>,
+	) -> Result<Self, AcssError> {
+		ThresholdParams::for_committee(guardianship.recipients(), threshold)?;
+		let request = RecoveryRequest::new(guardianship, *keypair.public())?;
+		Ok(Self {
+			guardianship,
+			keypair,
+			request,
+			threshold,
+			shares: BTreeMap::new(),
+		})
+	}
+
+	/// the request to send to the guardians
+	pub fn request(&self) -> &RecoveryRequest<E> {
+		&self.request
+	}
+
+	/// check and record a guardian's approval
+	/// fails with `ShareIndexOutOfRange` if the index is not a guardian's,
+	/// with `DuplicateShareIndex` if the guardian already approved, with
+	/// `InvalidSignature` if the approval is not signed by the guardian, with
+	/// `InvalidProof` if its PoK does not verify, and with `InvalidCommitment`
+	/// if the share does not match its public key
+	///
+	/// * `approval`: The approval
+	pub fn on_approval(
+		&mut self,
+		approval: &GuardianApproval<E>,
+	) -> Result<(), AcssError> {
+		let index = approval.index;
+		let guardian = self
+			.guardianship
+			.recipients()
+			.get(index.position())
+			.ok_or(AcssError::ShareIndexOutOfRange)?;
+		if self.shares.contains_key(&index) {
+			return Err(AcssError::DuplicateShareIndex);
+		}
+		if !approval.signature.verify(&self.request.message(index)?, guardian) {
+			return Err(AcssError::InvalidSignature);
+		}
+
+		let sk = SecretKey::from(&self.keypair.0.secret);
+		let pk = sk.public_key();
+		if !approval.pok.verify(pk) {
+			return Err(AcssError::InvalidProof);
+		}
+		let (mut r, mut r_hat) = decrypt_pok::<E>(&sk, &pk, &approval.pok)?;
+		let share = DoubleSecret(r, r_hat);
+		wipe(&mut r);
+		wipe(&mut r_hat);
+		if !matches_share(self.guardianship, index, share.expose_secret()) {
+			return Err(AcssError::InvalidCommitment);
+		}
+		self.shares.insert(index, share);
+		Ok(())
+	}
+
+	/// the indices of the guardians that approved, in increasing order
+	pub fn approved(&self) -> Vec<ShareIndex> {
+		self.shares.keys().copied().collect()
+	}
+
+	/// the indices of the guardians that have not approved yet
+	pub fn missing(&self) -> Vec<ShareIndex> {
+		(0..self.guardianship.len())
+			.filter_map(ShareIndex::from_position)
+			.filter(|index| !self.shares.contains_key(index))
+			.collect()
+	}
+
+	/// true once enough guardians approved to restore the key
+	pub fn is_ready(&self) -> bool {
+		self.shares.len() >= self.threshold as usize
+	}
+
+	/// restore the protected key from the approvals of the first t guardians
+	/// fails with `InsufficientValidPoK` if fewer than t guardians approved
+	pub fn reconstruct(&self) -> Result<DoubleSecret<E>, AcssError> {
+		if !self.is_ready() {
+			return Err(AcssError::InsufficientValidPoK);
+		}
+		let (mut secrets, mut blinding_secrets): (Vec<_>, Vec<_>) = self
+			.shares
+			.iter()
+			.take(self.threshold as usize)
+			.map(|(index, share)| {
+				let x = index.to_scalar::<E::Scalar>();
+				(
+					(x, *share.expose_secret()),
+					(x, *share.expose_blinding_secret()),
+				)
+			})
+			.unzip();
+		let secret = DoubleSecret(
+			interpolate::<E::SignatureGroup>(&secrets),
+			interpolate::<E::SignatureGroup>(&blinding_secrets),
+		);
+		secrets
+			.iter_mut()
+			.chain(blinding_secrets.iter_mut())
+			.for_each(|(_, y)| wipe(y));
+		Ok(secret)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	struct Fixture {
+		secret: DoubleSecret<E>,
+		guardians: Vec<Keypair<E>>,
+		guardianship: Resharing<E>,
+		owner: Keypair<E>,
+	}
+
+	fn fixture(rng: &mut ChaCha20Rng) -> Fixture {
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(rng),
+			<E as EngineBLS>::Scalar::rand(rng),
+		);
+		let guardians: Vec<Keypair<E>> =
+			(0..5).map(|_| Keypair(KeypairVT::generate(&mut *rng))).collect();
+		let keys: Vec<PublicKey<E>> =
+			guardians.iter().map(|g| *g.public()).collect();
+		let guardianship = protect(&secret, &keys, 3, &mut *rng).unwrap();
+		let owner = Keypair(KeypairVT::generate(&mut *rng));
+		Fixture { secret, guardians, guardianship, owner }
+	}
+
+	fn approval(
+		f: &Fixture,
+		guardian: usize,
+		request: &RecoveryRequest<E>,
+		rng: &mut ChaCha20Rng,
+	) -> GuardianApproval<E> {
+		let keypair = &f.guardians[guardian];
+		let (index, share) = accept(keypair, &f.guardianship).unwrap();
+		approve(keypair, &share, index, request, rng).unwrap()
+	}
+
+	#[test]
+	fn a_quorum_of_guardians_restores_the_key() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let f = fixture(&mut rng);
+		let mut recovery = Recovery::new(&f.guardianship, 3, &f.owner).unwrap();
+		let request = recovery.request().clone();
+
+		for guardian in [4, 1] {
+			let approval = approval(&f, guardian, &request, &mut rng);
+			recovery.on_approval(&approval).unwrap();
+			assert!(!recovery.is_ready());
+		}
+		assert_eq!(
+			recovery.reconstruct().err(),
+			Some(AcssError::InsufficientValidPoK)
+		);
+		let approval = approval(&f, 2, &request, &mut rng);
+		recovery.on_approval(&approval).unwrap();
+		assert!(recovery.is_ready());
+
+		let indices = |is: &[u8]| -> Vec<ShareIndex> {
+			is.iter().map(|i| ShareIndex::new(*i).unwrap()).collect()
+		};
+		assert_eq!(recovery.approved(), indices(&[2, 3, 5]));
+		assert_eq!(recovery.missing(), indices(&[1, 4]));
+
+		let restored = recovery.reconstruct().unwrap();
+		assert_eq!(restored.expose_secret(), f.secret.expose_secret());
+		assert_eq!(
+			restored.expose_blinding_secret(),
+			f.secret.expose_blinding_secret()
+		);
+	}
+
+	#[test]
+	fn guardians_outside_the_dealing_are_rejected() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let f = fixture(&mut rng);
+		assert_eq!(
+			accept(&f.owner, &f.guardianship).err(),
+			Some(AcssError::InvalidCommittee)
+		);
+		assert!(protect(&f.secret, &[], 1, &mut rng).is_err());
+		assert!(Recovery::new(&f.guardianship, 6, &f.owner).is_err());
+	}
+
+	#[test]
+	fn invalid_approvals_are_rejected() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let f = fixture(&mut rng);
+		let mut recovery = Recovery::new(&f.guardianship, 3, &f.owner).unwrap();
+		let request = recovery.request().clone();
+		let valid = approval(&f, 0, &request, &mut rng);
+
+		// signed for another index
+		let mut relabelled = valid.clone();
+		relabelled.index = ShareIndex::new(2).unwrap();
+		assert_eq!(
+			recovery.on_approval(&relabelled),
+			Err(AcssError::InvalidSignature)
+		);
+
+		// a request for another key
+		let other = Keypair::<E>(KeypairVT::generate(&mut rng));
+		let stolen = RecoveryRequest::new(&f.guardianship, *other.public());
+		let stolen = approval(&f, 1, &stolen.unwrap(), &mut rng);
+		assert_eq!(
+			recovery.on_approval(&stolen),
+			Err(AcssError::InvalidSignature)
+		);
+
+		// a signed approval carrying the wrong share
+		let keypair = &f.guardians[1];
+		let (index, _) = accept(keypair, &f.guardianship).unwrap();
+		let wrong = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let forged =
+			approve(keypair, &wrong, index, &request, &mut rng).unwrap();
+		assert_eq!(
+			recovery.on_approval(&forged),
+			Err(AcssError::InvalidCommitment)
+		);
+
+		let mut tampered = valid.clone();
+		tampered.pok.z += <E as EngineBLS>::Scalar::from(1u64);
+		assert_eq!(
+			recovery.on_approval(&tampered),
+			Err(AcssError::InvalidProof)
+		);
+
+		recovery.on_approval(&valid).unwrap();
+		assert_eq!(
+			recovery.on_approval(&valid),
+			Err(AcssError::DuplicateShareIndex)
+		);
+		let mut outside = valid.clone();
+		outside.index = ShareIndex::new(6).unwrap();
+		assert_eq!(
+			recovery.on_approval(&outside),
+			Err(AcssError::ShareIndexOutOfRange)
+		);
+	}
+}
@@ -0,0 +1,317 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Host Functions
+//!
+//! Stateless entry points for callers that only deal in byte slices, such as
+//! runtime host functions and FFI layers. Each call deserializes, validates
+//! and verifies its inputs in one go and only outputs whether they verify, so
+//! no type of this crate crosses the boundary.
+//!
+//! Transcripts are in the compact encoding (see [`crate::transcript`]),
+//! headers are `session || epoch || round || sequence` with little endian
+//! integers (see
+//! [`crate::messages`]), and keys and signatures are compressed points.
+
+use crate::{
+	context::VerificationContext,
+	error::AcssError,
+	keys::ShareIndex,
+	messages::MessageHeader,
+	params::ThresholdParams,
+	resharing::Resharing,
+	threshold::group_key,
+	transcript::{decode_compact, verify_recipient_share, SignedDealing},
+};
+use ark_serialize::CanonicalDeserialize;
+use ark_std::vec::Vec;
+use w3f_bls::{EngineBLS, PublicKey, Signature};
+
+/// verify a transcript dealt to a committee: every PoK must verify against
+/// its recipient's key and the shares must lie on a polynomial of degree
+/// t - 1
+/// fails with `InvalidTranscript` if the transcript cannot be decoded, with
+/// `InvalidCommittee` if a key cannot be decoded or the committee size does
+/// not match the transcript, with `InvalidParams` if it does not match
+/// `params`, with `InvalidProof` if a PoK does not verify and with
+/// `InvalidCommitment` if the shares do not fit the threshold
+///
+/// * `transcript`: The compact encoding of the resharing
+/// * `committee`: The compressed committee public keys, in share order
+/// * `params`: The threshold parameters of the resharing
+pub fn verify_transcript_bytes<E: EngineBLS, B: AsRef<[u8]>>(
+	transcript: &[u8],
+	committee: &[B],
+	params: ThresholdParams,
+) -> Result<(), AcssError> {
+	let dealings = decode_compact::<E>(transcript)?;
+	params.check_len(dealings.len())?;
+	if committee.len() != dealings.len() {
+		return Err(AcssError::InvalidCommittee);
+	}
+	let recipients = committee
+		.iter()
+		.map(|bytes| decode::<PublicKey<E>>(bytes.as_ref()))
+		.collect::<Result<Vec<_>, _>>()?;
+	let context = VerificationContext::new(&recipients);
+	if !context.verify_all(dealings.iter().map(|(_, pok)| pok)) {
+		return Err(AcssError::InvalidProof);
+	}
+	group_key(&Resharing::from_dealings(&recipients, dealings)?, params)
+		.map(|_| ())
+}
+
+/// verify a transcript as `verify_transcript_bytes` does, after checking
+/// that it was signed by `dealer` for `header`
+/// fails with `InvalidTranscript` if the header cannot be decoded, with
+/// `InvalidCommittee` if the dealer's key cannot be decoded, with
+/// `InvalidSignature` if the signature cannot be decoded or does not verify,
+/// and otherwise as `verify_transcript_bytes` does
+///
+/// * `header`: The encoded session, epoch and round of the dealing
+/// * `transcript`: The compact encoding of the resharing
+/// * `signature`: The compressed signature of the dealer over the transcript
+/// * `dealer`: The compressed public key of the dealer
+/// * `committee`: The compressed committee public keys, in share order
+/// * `params`: The threshold parameters of the resharing
+pub fn verify_signed_transcript_bytes<E: EngineBLS, B: AsRef<[u8]>>(
+	mut header: &[u8],
+	transcript: &[u8],
+	signature: &[u8],
+	dealer: &[u8],
+	committee: &[B],
+	params: ThresholdParams,
+) -> Result<(), AcssError> {
+	let reader = &mut header;
+	let header = MessageHeader::read(reader)?;
+	if !reader.is_empty() {
+		return Err(AcssError::InvalidTranscript);
+	}
+	let dealer = decode::<PublicKey<E>>(dealer)?;
+	let signature = Signature::<E>::deserialize_compressed(signature)
+		.map_err(|_| AcssError::InvalidSignature)?;
+	let dealing = SignedDealing {
+		header,
+		transcript: transcript.to_vec(),
+		signature: Some(signature),
+	};
+	if !dealing.verify(&dealer) {
+		return Err(AcssError::InvalidSignature);
+	}
+	verify_transcript_bytes::<E, B>(transcript, committee, params)
+}
+
+/// verify the entry of a transcript dealt to a single recipient, without
+/// decoding the rest of the transcript
+/// fails with `InvalidCommittee` if the recipient's key cannot be decoded,
+/// with `ShareIndexOutOfRange` if `index` is zero or past the end of the
+/// transcript, and otherwise as `verify_recipient_share` does
+///
+/// * `transcript`: The compact encoding of the resharing
+/// * `recipient`: The compressed public key of the recipient
+/// * `index`: The (1-based) index of the recipient's share
+pub fn verify_share_bytes<E: EngineBLS>(
+	transcript: &[u8],
+	recipient: &[u8],
+	index: u8,
+) -> Result<(), AcssError> {
+	let recipient = decode::<PublicKey<E>>(recipient)?;
+	let index =
+		ShareIndex::new(index).ok_or(AcssError::ShareIndexOutOfRange)?;
+	verify_recipient_share::<E>(transcript, &recipient, index).map(|_| ())
+}
+
+/// decode a compressed committee member key
+fn decode<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, AcssError> {
+	T::deserialize_compressed(bytes).map_err(|_| AcssError::InvalidCommittee)
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::{DoubleSecret, Keypair},
+		transcript::encode_compact,
+	};
+	use ark_ff::UniformRand;
+	use ark_serialize::CanonicalSerialize;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	fn compressed<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+		let mut out = Vec::new();
+		value.serialize_compressed(&mut out).unwrap();
+		out
+	}
+
+	/// a dealer keypair, the compressed committee and a compact transcript
+	fn dealing(
+		rng: &mut ChaCha20Rng,
+	) -> (KeypairVT<E>, Vec<Vec<u8>>, Vec<u8>, ThresholdParams) {
+		let dealer = KeypairVT::<E>::generate(&mut *rng);
+		let committee: Vec<PublicKey<E>> = (0..4)
+			.map(|_| *Keypair(KeypairVT::<E>::generate(&mut *rng)).public())
+			.collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(rng),
+			<E as EngineBLS>::Scalar::rand(rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 3).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut *rng).unwrap();
+		let transcript = encode_compact(resharing.dealings()).unwrap();
+		(dealer, committee.iter().map(compressed).collect(), transcript, params)
+	}
+
+	#[test]
+	fn valid_transcripts_verify() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (dealer, committee, transcript, params) = dealing(&mut rng);
+		assert_eq!(
+			verify_transcript_bytes::<E, _>(&transcript, &committee, params),
+			Ok(())
+		);
+
+		let header =
+			MessageHeader { session: [7; 32], epoch: 3, round: 1, sequence: 0 };
+		let dealing = SignedDealing::<E>::sign(
+			header,
+			&decode_compact(&transcript).unwrap(),
+			&dealer,
+		)
+		.unwrap();
+		let signature = compressed(&dealing.signature.unwrap());
+		let dealer = compressed(&dealer.public);
+		let mut encoded = Vec::new();
+		header.write(&mut encoded);
+		assert_eq!(
+			verify_signed_transcript_bytes::<E, _>(
+				&encoded,
+				&transcript,
+				&signature,
+				&dealer,
+				&committee,
+				params
+			),
+			Ok(())
+		);
+		assert_eq!(
+			verify_signed_transcript_bytes::<E, _>(
+				&encoded,
+				&transcript,
+				&signature,
+				&committee[0],
+				&committee,
+				params
+			),
+			Err(AcssError::InvalidSignature)
+		);
+		let mut next_epoch = Vec::new();
+		MessageHeader { epoch: 4, ..header }.write(&mut next_epoch);
+		assert_eq!(
+			verify_signed_transcript_bytes::<E, _>(
+				&next_epoch,
+				&transcript,
+				&signature,
+				&dealer,
+				&committee,
+				params
+			),
+			Err(AcssError::InvalidSignature)
+		);
+		assert_eq!(
+			verify_signed_transcript_bytes::<E, _>(
+				&encoded[1..],
+				&transcript,
+				&signature,
+				&dealer,
+				&committee,
+				params
+			),
+			Err(AcssError::InvalidTranscript)
+		);
+
+		for (position, member) in committee.iter().enumerate() {
+			let index = position as u8 + 1;
+			assert_eq!(
+				verify_share_bytes::<E>(&transcript, member, index),
+				Ok(())
+			);
+		}
+		assert_eq!(
+			verify_share_bytes::<E>(&transcript, &committee[0], 0),
+			Err(AcssError::ShareIndexOutOfRange)
+		);
+	}
+
+	#[test]
+	fn invalid_inputs_are_rejected() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (_, committee, transcript, params) = dealing(&mut rng);
+
+		let mut swapped = committee.clone();
+		swapped.swap(0, 1);
+		assert_eq!(
+			verify_transcript_bytes::<E, _>(&transcript, &swapped, params),
+			Err(AcssError::InvalidProof)
+		);
+		assert_eq!(
+			verify_share_bytes::<E>(&transcript, &committee[1], 1),
+			Err(AcssError::InvalidProof)
+		);
+
+		assert_eq!(
+			verify_transcript_bytes::<E, _>(
+				&transcript,
+				&committee[..3],
+				params
+			),
+			Err(AcssError::InvalidCommittee)
+		);
+		let mut garbled = committee.clone();
+		garbled[2] = vec![0xff; garbled[2].len()];
+		assert_eq!(
+			verify_transcript_bytes::<E, _>(&transcript, &garbled, params),
+			Err(AcssError::InvalidCommittee)
+		);
+
+		assert_eq!(
+			verify_transcript_bytes::<E, _>(
+				&transcript,
+				&committee,
+				ThresholdParams::new(4, 2).unwrap()
+			),
+			Err(AcssError::InvalidCommitment)
+		);
+		assert_eq!(
+			verify_transcript_bytes::<E, _>(
+				&transcript,
+				&committee,
+				ThresholdParams::new(5, 3).unwrap()
+			),
+			Err(AcssError::InvalidParams)
+		);
+		assert_eq!(
+			verify_transcript_bytes::<E, _>(
+				&transcript[..transcript.len() - 1],
+				&committee,
+				params
+			),
+			Err(AcssError::InvalidTranscript)
+		);
+	}
+}
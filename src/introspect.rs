@@ -0,0 +1,346 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Transcript and Proof Introspection
+//!
+//! Gossip layers and fee models need to bound and price a dealing before
+//! verifying it. The methods here answer how large a transcript or proof is,
+//! how many recipients it deals to, which scheme produced it and roughly how
+//! much work verifying it takes. On a [`SignedDealing`] they only read the
+//! header of the compact encoding, so no point is decoded.
+//!
+//! [`VerifyCost`] counts the operations dominating verification. It is an
+//! estimate: group additions and the decoding of scalars are not counted.
+
+use crate::proofs::hashed_el_gamal_sigma::BatchPoK;
+#[cfg(feature = "verify")]
+use crate::{
+	error::AcssError,
+	messages::MessageHeader,
+	resharing::Resharing,
+	transcript::{read_compact_shape, SignedDealing},
+};
+use ark_ec::CurveGroup;
+use ark_serialize::CanonicalSerialize;
+use core::ops::{Add, Mul};
+#[cfg(feature = "verify")]
+use w3f_bls::EngineBLS;
+
+/// the scheme a transcript or proof was produced by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SchemeId {
+	/// a batched PoK of hashed el gamal ciphertexts
+	BatchPoK = 1,
+	/// a resharing in the canonical encoding
+	Resharing = 2,
+	/// a resharing in the compact encoding
+	CompactTranscript = 3,
+	/// a resharing in the compact encoding, signed by its dealer
+	SignedCompactTranscript = 4,
+}
+
+impl SchemeId {
+	/// the stable numeric identifier of the scheme
+	pub fn code(self) -> u8 {
+		self as u8
+	}
+}
+
+/// the operations dominating the verification of a transcript or proof
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerifyCost {
+	/// the points decoded and checked to be in the prime order subgroup
+	pub points: usize,
+	/// the scalar multiplications
+	pub scalar_muls: usize,
+	/// the hashes to a challenge or to the curve
+	pub hashes: usize,
+	/// the pairings
+	pub pairings: usize,
+}
+
+impl VerifyCost {
+	/// the weighted sum of the operations, e.g. a fee
+	///
+	/// * `weights`: The weight of a point check, scalar multiplication, hash
+	///   and pairing, in that order
+	pub fn weight(&self, weights: [u64; 4]) -> u64 {
+		[self.points, self.scalar_muls, self.hashes, self.pairings]
+			.into_iter()
+			.zip(weights)
+			.fold(0u64, |acc, (count, weight)| {
+				acc.saturating_add((count as u64).saturating_mul(weight))
+			})
+	}
+}
+
+impl Add for VerifyCost {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self {
+			points: self.points + other.points,
+			scalar_muls: self.scalar_muls + other.scalar_muls,
+			hashes: self.hashes + other.hashes,
+			pairings: self.pairings + other.pairings,
+		}
+	}
+}
+
+impl Mul<usize> for VerifyCost {
+	type Output = Self;
+
+	fn mul(self, n: usize) -> Self {
+		Self {
+			points: self.points * n,
+			scalar_muls: self.scalar_muls * n,
+			hashes: self.hashes * n,
+			pairings: self.pairings * n,
+		}
+	}
+}
+
+/// the cost of verifying a PoK, excluding the decoding of its c1s: s, t and
+/// the commitment are decoded, zG, zH and e * commitment computed and the
+/// challenge hashed
+const POK_COST: VerifyCost =
+	VerifyCost { points: 3, scalar_muls: 3, hashes: 1, pairings: 0 };
+
+impl<C: CurveGroup> BatchPoK<C> {
+	/// the length of the compressed encoding
+	pub fn size_in_bytes(&self) -> usize {
+		self.compressed_size()
+	}
+
+	/// the number of recipients the proof is for, which is always one
+	pub fn num_recipients(&self) -> usize {
+		1
+	}
+
+	/// the scheme of the proof
+	pub fn scheme_id(&self) -> SchemeId {
+		SchemeId::BatchPoK
+	}
+
+	/// the estimated cost of `verify`
+	pub fn estimated_verify_cost(&self) -> VerifyCost {
+		POK_COST +
+			VerifyCost {
+				points: self.ciphertexts.len(),
+				..Default::default()
+			}
+	}
+}
+
+#[cfg(feature = "verify")]
+impl<E: EngineBLS> Resharing<E> {
+	/// the length of the compressed canonical encoding
+	pub fn size_in_bytes(&self) -> usize {
+		self.compressed_size()
+	}
+
+	/// the number of recipients
+	pub fn num_recipients(&self) -> usize {
+		self.len()
+	}
+
+	/// the scheme of the resharing
+	pub fn scheme_id(&self) -> SchemeId {
+		SchemeId::Resharing
+	}
+
+	/// the estimated cost of decoding the resharing and verifying every PoK
+	pub fn estimated_verify_cost(&self) -> VerifyCost {
+		// the recipient and both halves of the share public key
+		let keys = VerifyCost { points: 3, ..Default::default() };
+		self.poks()
+			.map(|pok| keys + pok.estimated_verify_cost())
+			.fold(VerifyCost::default(), Add::add)
+	}
+}
+
+#[cfg(feature = "verify")]
+impl<E: EngineBLS> SignedDealing<E> {
+	/// the length of the header, transcript and signature, as sent over the
+	/// wire
+	pub fn size_in_bytes(&self) -> usize {
+		MessageHeader::ENCODED_LEN +
+			self.transcript.len() +
+			self.signature.as_ref().map_or(0, |s| s.0.compressed_size())
+	}
+
+	/// the number of recipients, read from the header of the transcript
+	/// fails with `InvalidTranscript` if the header is malformed or does not
+	/// match the length of the transcript
+	pub fn num_recipients(&self) -> Result<usize, AcssError> {
+		read_compact_shape::<E>(&self.transcript).map(|(n, _)| n)
+	}
+
+	/// the scheme of the dealing
+	pub fn scheme_id(&self) -> SchemeId {
+		match self.signature {
+			Some(_) => SchemeId::SignedCompactTranscript,
+			None => SchemeId::CompactTranscript,
+		}
+	}
+
+	/// the estimated cost of checking the signature (if any), decoding the
+	/// transcript and verifying every PoK
+	/// fails as `num_recipients` does
+	pub fn estimated_verify_cost(&self) -> Result<VerifyCost, AcssError> {
+		let (n, shared_c1) = read_compact_shape::<E>(&self.transcript)?;
+		// both halves of the share public key, and the c1s unless shared
+		let entry = VerifyCost {
+			points: if shared_c1 { 2 } else { 4 },
+			..Default::default()
+		} + POK_COST;
+		let header = VerifyCost {
+			points: if shared_c1 { 2 } else { 0 },
+			..Default::default()
+		};
+		// the signature is hashed to the curve and checked with two pairings
+		let signature = match self.signature {
+			Some(_) =>
+				VerifyCost { points: 1, scalar_muls: 0, hashes: 1, pairings: 2 },
+			None => VerifyCost::default(),
+		};
+		Ok(entry * n + header + signature)
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::{DealingMode, HighThresholdACSS},
+		params::ThresholdParams,
+	};
+	use ark_ff::UniformRand;
+	use ark_std::{rand::SeedableRng, vec::Vec};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, PublicKey, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	fn resharing(mode: DealingMode) -> (Resharing<E>, KeypairVT<E>) {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let committee: Vec<PublicKey<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng).public).collect();
+		let resharing = HighThresholdACSS::<E>::reshare_with_mode(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			&committee,
+			ThresholdParams::for_committee(&committee, 2).unwrap(),
+			mode,
+			&mut rng,
+		)
+		.unwrap();
+		(resharing, KeypairVT::<E>::generate(&mut rng))
+	}
+
+	#[test]
+	fn transcripts_report_their_shape_without_decoding() {
+		let (resharing, dealer) = resharing(DealingMode::PerRecipient);
+		let pok = resharing.poks().next().unwrap();
+		assert_eq!(pok.size_in_bytes(), pok.compressed_size());
+		assert_eq!(pok.scheme_id(), SchemeId::BatchPoK);
+		assert_eq!(resharing.num_recipients(), 3);
+		assert_eq!(resharing.scheme_id().code(), 2);
+
+		let unsigned = SignedDealing::unsigned(
+			MessageHeader::default(),
+			resharing.dealings(),
+		)
+		.unwrap();
+		let signed = SignedDealing::sign(
+			MessageHeader::default(),
+			resharing.dealings(),
+			&dealer,
+		)
+		.unwrap();
+		assert_eq!(unsigned.num_recipients(), Ok(3));
+		assert_eq!(
+			unsigned.size_in_bytes(),
+			MessageHeader::ENCODED_LEN + unsigned.transcript.len()
+		);
+		assert!(signed.size_in_bytes() > unsigned.size_in_bytes());
+		assert_eq!(unsigned.scheme_id(), SchemeId::CompactTranscript);
+		assert_eq!(signed.scheme_id(), SchemeId::SignedCompactTranscript);
+
+		let mut truncated = unsigned;
+		truncated.transcript.pop();
+		assert_eq!(
+			truncated.num_recipients(),
+			Err(AcssError::InvalidTranscript)
+		);
+		assert_eq!(
+			truncated.estimated_verify_cost(),
+			Err(AcssError::InvalidTranscript)
+		);
+	}
+
+	#[test]
+	fn verify_costs_scale_with_the_transcript() {
+		let (resharing, dealer) = resharing(DealingMode::PerRecipient);
+		let pok = resharing.poks().next().unwrap();
+		assert_eq!(
+			pok.estimated_verify_cost(),
+			VerifyCost { points: 5, scalar_muls: 3, hashes: 1, pairings: 0 }
+		);
+		// the canonical encoding also carries the recipients
+		assert_eq!(
+			resharing.estimated_verify_cost(),
+			VerifyCost { points: 24, scalar_muls: 9, hashes: 3, pairings: 0 }
+		);
+
+		let unsigned = SignedDealing::unsigned(
+			MessageHeader::default(),
+			resharing.dealings(),
+		)
+		.unwrap();
+		let signed = SignedDealing::sign(
+			MessageHeader::default(),
+			resharing.dealings(),
+			&dealer,
+		)
+		.unwrap();
+		let unsigned_cost = unsigned.estimated_verify_cost().unwrap();
+		assert_eq!(
+			unsigned_cost,
+			VerifyCost { points: 21, scalar_muls: 9, hashes: 3, pairings: 0 }
+		);
+		assert_eq!(
+			signed.estimated_verify_cost().unwrap(),
+			unsigned_cost +
+				VerifyCost {
+					points: 1,
+					scalar_muls: 0,
+					hashes: 1,
+					pairings: 2
+				}
+		);
+		assert_eq!(unsigned_cost.weight([1, 10, 100, 1000]), 21 + 90 + 300);
+
+		// shared c1s are decoded once
+		let (shared, _) = self::resharing(DealingMode::SharedEphemeral);
+		let shared = SignedDealing::unsigned(
+			MessageHeader::default(),
+			shared.dealings(),
+		)
+		.unwrap();
+		assert_eq!(shared.estimated_verify_cost().unwrap().points, 15 + 2);
+	}
+}
@@ -0,0 +1,312 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Late Joining
+//!
+//! A member joining mid-epoch is issued a share at a fresh index without
+//! re-dealing to the rest of the committee. Any t members (the helpers) each
+//! send the joiner a [`JoinContribution`]: their share weighted by its
+//! Lagrange coefficient at the new index, encrypted to the joiner with a PoK.
+//! The contributions sum to the polynomial evaluated at the new index, which
+//! is the joiner's share.
+//!
+//! A weighted share alone would reveal the helper's share to the joiner, so
+//! every contribution is blinded by a mask. Helpers i and j derive a common
+//! value from their Diffie-Hellman key `sk_i * pk_j`; the lower index adds it
+//! and the higher index subtracts it, so the masks cancel in the sum and no
+//! interaction between helpers is needed. The joiner only learns its own
+//! share, unless it colludes with a helper.
+
+use crate::{
+	acss::{decrypt_pok, lagrange_at_zero, DoubleSecret, Keypair},
+	error::AcssError,
+	keys::{PublicKey as EncryptionKey, SecretKey, ShareIndex},
+	proofs::{hashed_el_gamal_sigma::BatchPoK, ser::HashWriter},
+	rng::SecureRng,
+	wipe,
+};
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{collections::BTreeSet, vec::Vec};
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+use w3f_bls::{EngineBLS, PublicKey};
+
+/// the domain separation label of contribution masks
+pub const MASK_LABEL: &[u8] = b"acss/late-join/mask";
+
+/// a helper's blinded contribution to the share of a joining member
+pub struct JoinContribution<E: EngineBLS> {
+	/// the index of the helper's share
+	pub helper: ShareIndex,
+	/// the blinded, weighted share and blinding share, encrypted to the joiner
+	pub pok: BatchPoK<E::PublicKeyGroup>,
+}
+
+impl<E: EngineBLS> Clone for JoinContribution<E> {
+	fn clone(&self) -> Self {
+		Self { helper: self.helper, pok: self.pok.clone() }
+	}
+}
+
+impl<E: EngineBLS> PartialEq for JoinContribution<E> {
+	fn eq(&self, other: &Self) -> bool {
+		self.helper == other.helper && self.pok == other.pok
+	}
+}
+
+/// contribute to the share at index `joiner` for the member with public key
+/// `joiner_pk`, as the helper at index `helper`
+/// fails with `InvalidCommittee` if `helper` is not one of `helpers`, and with
+/// `DuplicateShareIndex` if the helpers repeat an index or include `joiner`
+///
+/// * `keypair`: The helper's keypair
+/// * `share`: The helper's share
+/// * `helper`: The index of the helper's share
+/// * `helpers`: The index and public key of every helper, including this one
+/// * `joiner`: The fresh index to issue a share at
+/// * `joiner_pk`: The public key of the joining member
+/// * `rng`: A CSPRNG
+pub fn contribute<E: EngineBLS, R: SecureRng>(
+	keypair: &Keypair<E>,
+	share: &DoubleSecret<E>,
+	helper: ShareIndex,
+	helpers: &[(ShareIndex, PublicKey<E>)],
+	joiner: ShareIndex,
+	joiner_pk: &PublicKey<E>,
+	rng: R,
+) -> Result<JoinContribution<E>, AcssError> {
+	let mut indices = BTreeSet::from([joiner]);
+	if !helpers.iter().all(|(index, _)| indices.insert(*index)) {
+		return Err(AcssError::DuplicateShareIndex);
+	}
+	let position = helpers
+		.iter()
+		.position(|(index, _)| *index == helper)
+		.ok_or(AcssError::InvalidCommittee)?;
+
+	// the Lagrange basis at the joiner's index, i.e. at zero after shifting
+	let k = joiner.to_scalar::<E::Scalar>();
+	let xs: Vec<E::Scalar> = helpers
+		.iter()
+		.map(|(index, _)| index.to_scalar::<E::Scalar>() - k)
+		.collect();
+	let lambda =
+		lagrange_at_zero(&xs).ok_or(AcssError::DuplicateShareIndex)?[position];
+
+	let sk = SecretKey::<E::PublicKeyGroup>::from(&keypair.0.secret);
+	let mut messages = [lambda * share.0, lambda * share.1];
+	for (other, pk) in helpers.iter().filter(|(index, _)| *index != helper) {
+		let shared = pk.0 * sk.expose_secret();
+		for (slot, message) in messages.iter_mut().enumerate() {
+			let mut mask = mask::<E>(&shared, helper, *other, joiner, slot)?;
+			if helper < *other {
+				*message += mask;
+			} else {
+				*message -= mask;
+			}
+			wipe(&mut mask);
+		}
+	}
+
+	let pok = BatchPoK::prove(&messages, EncryptionKey(joiner_pk.0), rng);
+	wipe(&mut messages);
+	Ok(JoinContribution { helper, pok: pok? })
+}
+
+/// combine the contributions of t helpers into the joiner's share
+/// fails with `InvalidCommittee` if there are no contributions, with
+/// `DuplicateShareIndex` if a helper contributed twice, and with
+/// `InvalidProof` if a contribution was not encrypted to the joiner
+///
+/// every helper must have contributed for the masks to cancel
+///
+/// * `keypair`: The joiner's keypair
+/// * `contributions`: The contribution of every helper
+pub fn combine<E: EngineBLS>(
+	keypair: &Keypair<E>,
+	contributions: &[JoinContribution<E>],
+) -> Result<DoubleSecret<E>, AcssError> {
+	if contributions.is_empty() {
+		return Err(AcssError::InvalidCommittee);
+	}
+	let mut helpers = BTreeSet::new();
+	if !contributions.iter().all(|c| helpers.insert(c.helper)) {
+		return Err(AcssError::DuplicateShareIndex);
+	}
+
+	let sk = SecretKey::<E::PublicKeyGroup>::from(&keypair.0.secret);
+	let pk = sk.public_key();
+	let mut share = DoubleSecret::<E>(E::Scalar::zero(), E::Scalar::zero());
+	for contribution in contributions {
+		if !contribution.pok.verify(pk) {
+			return Err(AcssError::InvalidProof);
+		}
+		let (mut r, mut r_hat) = decrypt_pok::<E>(&sk, &pk, &contribution.pok)?;
+		share.0 += r;
+		share.1 += r_hat;
+		wipe(&mut r);
+		wipe(&mut r_hat);
+	}
+	Ok(share)
+}
+
+/// the mask shared by two helpers for one slot (share or blinding share)
+fn mask<E: EngineBLS>(
+	shared: &E::PublicKeyGroup,
+	helper: ShareIndex,
+	other: ShareIndex,
+	joiner: ShareIndex,
+	slot: usize,
+) -> Result<E::Scalar, AcssError> {
+	let (low, high) =
+		if helper < other { (helper, other) } else { (other, helper) };
+	let mut h = Shake256::default();
+	h.update(MASK_LABEL);
+	h.update(&[low.get(), high.get(), joiner.get(), slot as u8]);
+	shared
+		.serialize_compressed(HashWriter(&mut h))
+		.map_err(|_| AcssError::InvalidCommittee)?;
+
+	let mut bytes = [0u8; 64];
+	h.finalize_xof().read(&mut bytes);
+	let mask = E::Scalar::from_le_bytes_mod_order(&bytes);
+	wipe(&mut bytes);
+	Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{acss::interpolate, params::ThresholdParams};
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	#[test]
+	fn helpers_issue_a_share_at_a_fresh_index() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<Keypair<E>> = (0..5)
+			.map(|_| Keypair(KeypairVT::<E>::generate(&mut rng)))
+			.collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|m| *m.public()).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 3).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut rng).unwrap();
+		let single_dealer = ThresholdParams::new(1, 1).unwrap();
+		let shares: Vec<DoubleSecret<E>> = members
+			.iter()
+			.zip(resharing.poks())
+			.map(|(m, pok)| {
+				m.recover(core::slice::from_ref(pok), single_dealer).unwrap()
+			})
+			.collect();
+
+		let joiner = Keypair(KeypairVT::<E>::generate(&mut rng));
+		let index = ShareIndex::new(6).unwrap();
+		let helpers: Vec<(ShareIndex, PublicKey<E>)> = [0, 2, 3]
+			.into_iter()
+			.map(|p| (ShareIndex::from_position(p).unwrap(), committee[p]))
+			.collect();
+		let contributions: Vec<JoinContribution<E>> = [0, 2, 3]
+			.into_iter()
+			.zip(&helpers)
+			.map(|(p, (helper, _))| {
+				contribute(
+					&members[p],
+					&shares[p],
+					*helper,
+					&helpers,
+					index,
+					joiner.public(),
+					&mut rng,
+				)
+				.unwrap()
+			})
+			.collect();
+		let share = combine(&joiner, &contributions).unwrap();
+
+		// the new share completes any two existing shares
+		let points = [
+			(ShareIndex::new(2).unwrap().to_scalar(), shares[1].0),
+			(ShareIndex::new(5).unwrap().to_scalar(), shares[4].0),
+			(index.to_scalar(), share.0),
+		];
+		assert_eq!(
+			interpolate::<<E as EngineBLS>::SignatureGroup>(&points),
+			secret.0
+		);
+
+		// the masks only cancel once every helper has contributed
+		let partial = combine(&joiner, &contributions[..2]).unwrap();
+		assert_ne!(partial.0, share.0);
+		assert_eq!(
+			combine(&members[0], &contributions).err(),
+			Some(AcssError::InvalidProof)
+		);
+		let repeated = [contributions[0].clone(), contributions[0].clone()];
+		assert_eq!(
+			combine(&joiner, &repeated).err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+	}
+
+	#[test]
+	fn contributions_reject_malformed_helper_sets() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keypair = Keypair(KeypairVT::<E>::generate(&mut rng));
+		let share = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let one = ShareIndex::new(1).unwrap();
+		let two = ShareIndex::new(2).unwrap();
+		let helpers = [(one, *keypair.public()), (two, *keypair.public())];
+		let mut contribute_at = |helper, joiner| {
+			contribute(
+				&keypair,
+				&share,
+				helper,
+				&helpers,
+				joiner,
+				keypair.public(),
+				&mut rng,
+			)
+			.err()
+		};
+
+		assert_eq!(
+			contribute_at(one, two),
+			Some(AcssError::DuplicateShareIndex)
+		);
+		assert_eq!(
+			contribute_at(
+				ShareIndex::new(3).unwrap(),
+				ShareIndex::new(4).unwrap()
+			),
+			Some(AcssError::InvalidCommittee)
+		);
+		assert_eq!(contribute_at(one, ShareIndex::new(4).unwrap()), None);
+	}
+}
@@ -0,0 +1,307 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Keys and Indices
+//!
+//! Newtypes for the values that are otherwise all field elements, points or
+//! small integers, so that e.g. a recipient's secret key cannot be passed
+//! where a share or a blinding share is expected.
+//!
+//! - [`SecretKey`]: a decryption key, its `Debug` output is redacted
+//! - [`PublicKey`]: an encryption key, `sk * G` for the default generator
+//! - [`ShareIndex`]: the (1-based) x coordinate of a share
+//!
+//! Committee members are identified by BLS public keys. [`CommitteeKey`]
+//! also admits `w3f_bls` double public keys, which carry the same key in the
+//! signature group as well. Shares are encrypted to, and acknowledgments
+//! verified against, the half in the public key group.
+
+#[cfg(feature = "serde")]
+use crate::proofs::ser::{ark_de_point, ark_se};
+use crate::{
+	error::SerializationError,
+	proofs::ser::{fmt_hex, parse_hex},
+};
+use alloc::vec::Vec;
+use ark_ec::{CurveGroup, Group};
+use ark_ff::PrimeField;
+use core::{fmt, num::NonZeroU8, str::FromStr};
+#[cfg(feature = "participant")]
+use w3f_bls::SecretKeyVT;
+use w3f_bls::{DoublePublicKey, EngineBLS};
+#[cfg(feature = "participant")]
+use zeroize::Zeroize;
+#[cfg(all(feature = "participant", feature = "zeroize"))]
+use zeroize::ZeroizeOnDrop;
+
+#[cfg(feature = "participant")]
+/// a secret (decryption) key
+/// with the `zeroize` feature, it is wiped when dropped
+#[derive(Clone)]
+pub struct SecretKey<C: CurveGroup>(C::ScalarField);
+
+#[cfg(feature = "participant")]
+impl<C: CurveGroup> SecretKey<C> {
+	/// wrap a scalar as a secret key
+	pub fn new(sk: C::ScalarField) -> Self {
+		Self(sk)
+	}
+
+	/// the public key sk * G for the default generator G
+	pub fn public_key(&self) -> PublicKey<C> {
+		PublicKey(C::generator() * self.0)
+	}
+
+	/// the secret scalar
+	pub fn expose_secret(&self) -> &C::ScalarField {
+		&self.0
+	}
+}
+
+#[cfg(feature = "participant")]
+impl<C: CurveGroup> fmt::Debug for SecretKey<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("SecretKey(<redacted>)")
+	}
+}
+
+#[cfg(feature = "participant")]
+impl<C: CurveGroup> Zeroize for SecretKey<C> {
+	fn zeroize(&mut self) {
+		self.0.zeroize();
+	}
+}
+
+#[cfg(all(feature = "participant", feature = "zeroize"))]
+impl<C: CurveGroup> Drop for SecretKey<C> {
+	fn drop(&mut self) {
+		self.zeroize();
+	}
+}
+
+#[cfg(all(feature = "participant", feature = "zeroize"))]
+impl<C: CurveGroup> ZeroizeOnDrop for SecretKey<C> {}
+
+#[cfg(feature = "participant")]
+impl<E: EngineBLS> From<&SecretKeyVT<E>> for SecretKey<E::PublicKeyGroup> {
+	fn from(sk: &SecretKeyVT<E>) -> Self {
+		Self(sk.0)
+	}
+}
+
+/// a public (encryption) key
+/// with the `serde` feature, it is (de)serialized as its compressed encoding
+/// and the identity is rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PublicKey<C: CurveGroup>(
+	#[cfg_attr(
+		feature = "serde",
+		serde(serialize_with = "ark_se", deserialize_with = "ark_de_point")
+	)]
+	pub C,
+);
+
+impl<C: CurveGroup> PublicKey<C> {
+	/// the compressed encoding of the point
+	/// fails with `Encoding` if the point cannot be encoded
+	pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+		let mut bytes = Vec::with_capacity(self.0.compressed_size());
+		self.0
+			.serialize_compressed(&mut bytes)
+			.map_err(|_| SerializationError::Encoding)?;
+		Ok(bytes)
+	}
+
+	/// decode a public key written by `to_bytes`
+	/// fails with `Decoding` if the bytes are not exactly the encoding of a
+	/// point in the prime order subgroup, or encode the identity
+	///
+	/// * `bytes`: The compressed encoding of the point
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+		let mut reader = bytes;
+		let pk = C::deserialize_compressed(&mut reader)
+			.map_err(|_| SerializationError::Decoding)?;
+		if !reader.is_empty() || pk.is_zero() {
+			return Err(SerializationError::Decoding);
+		}
+		Ok(Self(pk))
+	}
+}
+
+impl<C: CurveGroup> From<C> for PublicKey<C> {
+	fn from(pk: C) -> Self {
+		Self(pk)
+	}
+}
+
+impl<C: CurveGroup> fmt::Display for PublicKey<C> {
+	/// 0x-prefixed hex of the compressed point
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt_hex(&self.0, f)
+	}
+}
+
+impl<C: CurveGroup> FromStr for PublicKey<C> {
+	type Err = SerializationError;
+
+	/// the identity is rejected, nothing can be encrypted to it
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let pk: C = parse_hex(s)?;
+		if pk.is_zero() {
+			return Err(SerializationError::Decoding);
+		}
+		Ok(Self(pk))
+	}
+}
+
+/// the public key of a committee member
+pub trait CommitteeKey<E: EngineBLS> {
+	/// the key in the public key group, which shares are encrypted to and
+	/// signatures are verified against
+	fn public_key(&self) -> w3f_bls::PublicKey<E>;
+
+	/// true if the parts of the key agree with each other
+	fn is_consistent(&self) -> bool {
+		true
+	}
+}
+
+impl<E: EngineBLS> CommitteeKey<E> for w3f_bls::PublicKey<E> {
+	fn public_key(&self) -> w3f_bls::PublicKey<E> {
+		*self
+	}
+}
+
+impl<E: EngineBLS> CommitteeKey<E> for DoublePublicKey<E> {
+	fn public_key(&self) -> w3f_bls::PublicKey<E> {
+		w3f_bls::PublicKey(self.1)
+	}
+
+	/// true if both halves have the same discrete log, i.e.
+	/// e(pk_2, g_1) = e(g_2, pk_1)
+	fn is_consistent(&self) -> bool {
+		E::pairing(self.1, E::generator_of_signature_group()) ==
+			E::pairing(E::PublicKeyGroup::generator(), self.0)
+	}
+}
+
+/// the keys of a committee in the public key group, in share order
+///
+/// * `committee`: The public keys of the committee
+pub fn committee_keys<E: EngineBLS, K: CommitteeKey<E>>(
+	committee: &[K],
+) -> Vec<w3f_bls::PublicKey<E>> {
+	committee.iter().map(CommitteeKey::public_key).collect()
+}
+
+/// the index of a share, i.e. the x coordinate its polynomial is evaluated at
+/// indices start at 1, since the secret itself is the evaluation at 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShareIndex(NonZeroU8);
+
+impl ShareIndex {
+	/// the index of the first share
+	pub const FIRST: Self = Self(NonZeroU8::MIN);
+
+	/// the share index `index`, none if it is 0
+	pub fn new(index: u8) -> Option<Self> {
+		NonZeroU8::new(index).map(Self)
+	}
+
+	/// the index of the share held at (0-based) `position` in a committee,
+	/// i.e. position + 1, none if it does not fit in a u8
+	pub fn from_position(position: usize) -> Option<Self> {
+		u8::try_from(position + 1).ok().and_then(Self::new)
+	}
+
+	/// the index as an integer
+	pub fn get(self) -> u8 {
+		self.0.get()
+	}
+
+	/// the (0-based) position of the share in its committee
+	pub fn position(self) -> usize {
+		self.get() as usize - 1
+	}
+
+	/// the index as a field element, the x coordinate of the share
+	pub fn to_scalar<F: PrimeField>(self) -> F {
+		F::from(self.get())
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use alloc::format;
+	use ark_bls12_381::{Fr, G1Projective as G1};
+	use ark_ec::Group;
+	use ark_std::Zero;
+
+	#[test]
+	fn secret_keys_are_redacted() {
+		let sk = SecretKey::<G1>::new(Fr::from(42u64));
+		assert_eq!(format!("{:?}", sk), "SecretKey(<redacted>)");
+		assert_eq!(
+			sk.public_key(),
+			PublicKey(G1::generator() * Fr::from(42u64))
+		);
+	}
+
+	#[test]
+	fn public_keys_round_trip_through_hex() {
+		let pk = SecretKey::<G1>::new(Fr::from(42u64)).public_key();
+		let hex = format!("{pk}");
+		assert!(hex.starts_with("0x"));
+		assert_eq!(hex.parse::<PublicKey<G1>>(), Ok(pk));
+
+		let identity = format!("{}", PublicKey(G1::zero()));
+		assert_eq!(
+			identity.parse::<PublicKey<G1>>(),
+			Err(SerializationError::Decoding)
+		);
+	}
+
+	#[test]
+	fn public_keys_round_trip_through_bytes() {
+		let pk = SecretKey::<G1>::new(Fr::from(42u64)).public_key();
+		let bytes = pk.to_bytes().unwrap();
+		assert_eq!(PublicKey::<G1>::from_bytes(&bytes), Ok(pk));
+
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		let identity = PublicKey(G1::zero()).to_bytes().unwrap();
+		for bad in [&bytes[1..], &trailing[..], &identity[..]] {
+			assert_eq!(
+				PublicKey::<G1>::from_bytes(bad),
+				Err(SerializationError::Decoding)
+			);
+		}
+	}
+
+	#[test]
+	fn share_indices_start_at_one() {
+		assert!(ShareIndex::new(0).is_none());
+		let index = ShareIndex::from_position(0).unwrap();
+		assert_eq!((index.get(), index.position()), (1, 0));
+		assert_eq!(index.to_scalar::<Fr>(), Fr::from(1u64));
+		assert_eq!(
+			ShareIndex::from_position(254).map(ShareIndex::get),
+			Some(255)
+		);
+		assert!(ShareIndex::from_position(255).is_none());
+	}
+}
@@ -0,0 +1,70 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Lagrange Interpolation
+//!
+//! Interpolation at zero over a prime field, shared by recovery (of scalars)
+//! and by the verification paths that interpolate in the exponent.
+
+use ark_ec::CurveGroup;
+use ark_ff::Field;
+use ark_std::{vec::Vec, Zero};
+
+/// interpolate a polynomial from the input and evaluate it at 0
+/// P(X) = sum_{i = 0} ^n y_i * (\prod_{j=0}^n [j != i] (x-xj/xi - xj))hegs
+///
+/// * `evalulation`: a vec of (x, f(x)) pairs
+pub fn interpolate<C: CurveGroup>(
+	points: &[(C::ScalarField, C::ScalarField)],
+) -> C::ScalarField {
+	let xs: Vec<C::ScalarField> = points.iter().map(|p| p.0).collect();
+	// Handle the case when a denominator is zero (i.e. repeated x values)
+	let Some(lagrange_at_zero) = lagrange_at_zero::<C::ScalarField>(&xs) else {
+		return C::ScalarField::zero();
+	};
+
+	// Interpolate the value at 0
+	// compute  sum_{i = 0} ^n (y_i * sum... )
+	let mut interpolated_value = C::ScalarField::zero();
+	for (point, basis_value) in points.iter().zip(lagrange_at_zero.iter()) {
+		interpolated_value += point.1 * basis_value;
+	}
+
+	interpolated_value
+}
+
+/// calculate the Lagrange basis polynomials evaluated at 0 for the points
+/// `xs`, returns none if the points are not distinct
+///
+/// * `xs`: the x-coordinates of the points to interpolate
+pub fn lagrange_at_zero<F: Field>(xs: &[F]) -> Option<Vec<F>> {
+	let n = xs.len();
+	let mut lagrange_at_zero: Vec<F> = Vec::with_capacity(n);
+	for i in 0..n {
+		// build \prod_{j=0}^n [j != i] (x-xj/xi - xj)
+		let mut basis_value = F::one();
+		for j in 0..n {
+			if j != i {
+				let denominator = xs[i] - xs[j];
+				// Check if the denominator is zero before taking the inverse
+				let denominator_inv = denominator.inverse()?;
+				let numerator = F::zero() - xs[j];
+				basis_value *= numerator * denominator_inv;
+			}
+		}
+		lagrange_at_zero.push(basis_value);
+	}
+	Some(lagrange_at_zero)
+}
@@ -32,6 +32,113 @@
 
 #[macro_use]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+#[cfg(all(feature = "mlock", not(unix)))]
+compile_error!("the `mlock` feature is only supported on unix targets");
+
+#[cfg(feature = "participant")]
 pub mod acss;
+#[cfg(feature = "verify")]
+pub mod beacon;
+#[cfg(feature = "participant")]
+pub mod builder;
+#[cfg(feature = "verify")]
+pub mod certificate;
+#[cfg(feature = "participant")]
+pub mod coin;
+#[cfg(feature = "verify")]
+pub mod commitment;
+#[cfg(feature = "verify")]
+pub mod context;
+#[cfg(feature = "participant")]
+pub mod custody;
+#[cfg(feature = "differential")]
+pub mod differential;
+pub mod error;
+#[cfg(feature = "verify")]
+pub mod evidence;
+#[cfg(feature = "participant")]
+pub mod export;
+#[cfg(feature = "foreign")]
+pub mod foreign;
+#[cfg(feature = "participant")]
+pub mod guardians;
+#[cfg(feature = "verify")]
+pub mod host;
+pub mod introspect;
+#[cfg(feature = "participant")]
+pub mod join;
+pub mod keys;
+pub mod lagrange;
+#[cfg(feature = "verify")]
+pub mod manifest;
+#[cfg(feature = "verify")]
+pub mod merkle;
+#[cfg(feature = "verify")]
+pub mod messages;
+#[cfg(feature = "verify")]
+pub mod migration;
+#[cfg(all(feature = "mlock", unix))]
+pub mod mlock;
+pub mod msm;
+#[cfg(feature = "participant")]
+pub mod nonblocking;
+#[cfg(feature = "verify")]
+pub mod observer;
+#[cfg(feature = "offchain")]
+pub mod offchain;
+#[cfg(feature = "verify")]
+pub mod onchain;
+pub mod params;
+#[cfg(feature = "verify")]
+pub mod pending;
+pub mod prelude;
+#[cfg(feature = "verify")]
+pub mod progress;
 pub mod proofs;
+#[cfg(all(test, feature = "std", feature = "participant"))]
+mod properties;
+#[cfg(feature = "verify")]
+pub mod quorum;
+pub mod redact;
+#[cfg(feature = "verify")]
+pub mod replay;
+#[cfg(feature = "verify")]
+pub mod resharing;
+#[cfg(feature = "verify")]
+pub mod ring;
+pub mod rng;
+#[cfg(feature = "participant")]
+pub mod roles;
+#[cfg(feature = "verify")]
+pub mod rotation;
+#[cfg(feature = "participant")]
+pub mod selftest;
+#[cfg(feature = "participant")]
+pub mod session;
+#[cfg(feature = "participant")]
+pub mod shamir;
+#[cfg(feature = "participant")]
+pub mod share;
+#[cfg(feature = "store")]
+pub mod store;
+#[cfg(feature = "verify")]
+pub mod threshold;
+mod trace;
+#[cfg(feature = "verify")]
+pub mod transcript;
+#[cfg(feature = "participant")]
+pub mod vault;
+
+#[cfg(feature = "participant")]
+pub use selftest::self_test;
+
+/// wipe secret material from memory when the `zeroize` feature is enabled,
+/// otherwise a no-op
+#[inline(always)]
+pub(crate) fn wipe<Z: zeroize::Zeroize + ?Sized>(_secret: &mut Z) {
+	#[cfg(feature = "zeroize")]
+	_secret.zeroize();
+}
@@ -0,0 +1,291 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Committee Manifests
+//!
+//! A [`CommitteeManifest`] is the canonical descriptor of a committee: the
+//! public keys of its members sorted by their compressed encoding, so that
+//! the share index of a member is its position, and a digest committing to
+//! them. Test networks and key ceremonies publish the manifest so that every
+//! node agrees on the committee and its share order.
+//!
+//! [`Keypair::generate_committee`] derives a whole committee from one seed,
+//! recording the share index of each keypair and the counter it was derived
+//! with.
+//!
+//! Layout: `n || pk_1 || ... || pk_n`, with n a single byte.
+
+#[cfg(feature = "participant")]
+use crate::{acss::Keypair, wipe};
+use crate::{
+	error::AcssError,
+	keys::{committee_keys, CommitteeKey, ShareIndex},
+	transcript::{read_array, read_point, write},
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{vec::Vec, Zero};
+use sha2::{Digest, Sha256};
+use w3f_bls::{EngineBLS, PublicKey};
+
+/// the domain separation label of manifest digests
+pub const MANIFEST_DIGEST_LABEL: &[u8] = b"acss/committee-manifest";
+/// the domain separation label of the seeds of generated keypairs
+#[cfg(feature = "participant")]
+pub const KEYPAIR_DERIVATION_LABEL: &[u8] = b"acss/committee-keypair";
+
+/// the canonical descriptor of a committee
+pub struct CommitteeManifest<E: EngineBLS> {
+	/// the member keys, sorted by their compressed encoding
+	keys: Vec<PublicKey<E>>,
+	/// the digest of the encoding
+	digest: [u8; 32],
+}
+
+impl<E: EngineBLS> Clone for CommitteeManifest<E> {
+	fn clone(&self) -> Self {
+		Self { keys: self.keys.clone(), digest: self.digest }
+	}
+}
+
+impl<E: EngineBLS> CommitteeManifest<E> {
+	/// describe the committee of `members`, in any order
+	/// fails with `InvalidCommittee` if there are no members or more than
+	/// 255, if a key is the identity, or if a key appears twice
+	///
+	/// * `members`: The public keys of the members
+	pub fn new<K: CommitteeKey<E>>(members: &[K]) -> Result<Self, AcssError> {
+		let mut keys: Vec<(Vec<u8>, PublicKey<E>)> = committee_keys(members)
+			.into_iter()
+			.map(|pk| {
+				let mut bytes = Vec::with_capacity(pk.0.compressed_size());
+				write(&pk.0, &mut bytes)?;
+				Ok((bytes, pk))
+			})
+			.collect::<Result<_, AcssError>>()?;
+		keys.sort_by(|a, b| a.0.cmp(&b.0));
+		if keys.is_empty() ||
+			keys.len() > u8::MAX as usize ||
+			keys.iter().any(|(_, pk)| pk.0.is_zero()) ||
+			keys.windows(2).any(|pair| pair[0].0 == pair[1].0)
+		{
+			return Err(AcssError::InvalidCommittee);
+		}
+		let keys: Vec<PublicKey<E>> =
+			keys.into_iter().map(|(_, pk)| pk).collect();
+		let digest = Self::hash(&keys)?;
+		Ok(Self { keys, digest })
+	}
+
+	/// the member keys, in share order
+	pub fn keys(&self) -> &[PublicKey<E>] {
+		&self.keys
+	}
+
+	/// the number of members
+	pub fn len(&self) -> usize {
+		self.keys.len()
+	}
+
+	/// true if the committee has no members, which a manifest never does
+	pub fn is_empty(&self) -> bool {
+		self.keys.is_empty()
+	}
+
+	/// the digest of the manifest, which identifies the committee
+	pub fn digest(&self) -> [u8; 32] {
+		self.digest
+	}
+
+	/// the share index of the member with key `pk`, none if it is not a
+	/// member
+	///
+	/// * `pk`: The public key of the member
+	pub fn index_of(&self, pk: &PublicKey<E>) -> Option<ShareIndex> {
+		self.keys
+			.iter()
+			.position(|member| member.0 == pk.0)
+			.and_then(ShareIndex::from_position)
+	}
+
+	/// encode the manifest
+	pub fn encode(&self) -> Result<Vec<u8>, AcssError> {
+		let mut out = Vec::new();
+		out.push(self.keys.len() as u8);
+		self.keys.iter().try_for_each(|pk| write(&pk.0, &mut out))?;
+		Ok(out)
+	}
+
+	/// decode a manifest written by `encode`
+	/// fails with `InvalidTranscript` if the bytes cannot be decoded, have
+	/// trailing bytes, or do not list the keys in canonical order
+	///
+	/// * `bytes`: The encoded manifest
+	pub fn decode(bytes: &[u8]) -> Result<Self, AcssError> {
+		let mut reader = bytes;
+		let [n] = read_array::<1>(&mut reader)?;
+		let keys = (0..n)
+			.map(|_| Ok(PublicKey(read_point(&mut reader)?)))
+			.collect::<Result<Vec<PublicKey<E>>, AcssError>>()?;
+		if !reader.is_empty() {
+			return Err(AcssError::InvalidTranscript);
+		}
+		let manifest =
+			Self::new(&keys).map_err(|_| AcssError::InvalidTranscript)?;
+		if manifest.keys.iter().zip(keys.iter()).any(|(a, b)| a.0 != b.0) {
+			return Err(AcssError::InvalidTranscript);
+		}
+		Ok(manifest)
+	}
+
+	/// the digest of the sorted keys
+	fn hash(keys: &[PublicKey<E>]) -> Result<[u8; 32], AcssError> {
+		let mut encoded = Vec::new();
+		encoded.push(keys.len() as u8);
+		keys.iter().try_for_each(|pk| write(&pk.0, &mut encoded))?;
+		Ok(Sha256::new()
+			.chain_update(MANIFEST_DIGEST_LABEL)
+			.chain_update(&encoded)
+			.finalize()
+			.into())
+	}
+}
+
+/// a keypair generated as a member of a committee
+#[cfg(feature = "participant")]
+pub struct CommitteeKeypair<E: EngineBLS> {
+	/// the keypair
+	pub keypair: Keypair<E>,
+	/// the member's share index in the committee manifest
+	pub index: ShareIndex,
+	/// the counter the keypair's seed was derived with
+	pub derivation: u8,
+}
+
+#[cfg(feature = "participant")]
+impl<E: EngineBLS> Keypair<E> {
+	/// derive the keypairs of a committee of `n` members from one seed,
+	/// together with its manifest
+	/// the i-th keypair (counting from 0) is derived from the seed
+	/// H(label || seed || i), and the keypairs are output in share order
+	/// fails with `InvalidCommittee` if `n` is zero
+	///
+	/// * `n`: The number of members
+	/// * `seed`: The seed, wiped once the keys are derived
+	pub fn generate_committee(
+		n: u8,
+		mut seed: [u8; 32],
+	) -> Result<(Vec<CommitteeKeypair<E>>, CommitteeManifest<E>), AcssError> {
+		let keypairs: Vec<(u8, Keypair<E>)> = (0..n)
+			.map(|derivation| {
+				let member_seed: [u8; 32] = Sha256::new()
+					.chain_update(KEYPAIR_DERIVATION_LABEL)
+					.chain_update(seed)
+					.chain_update([derivation])
+					.finalize()
+					.into();
+				(derivation, Keypair::from_seed(member_seed))
+			})
+			.collect();
+		wipe(&mut seed);
+
+		let public: Vec<PublicKey<E>> =
+			keypairs.iter().map(|(_, kp)| *kp.public()).collect();
+		let manifest = CommitteeManifest::new(&public)?;
+		let mut members: Vec<CommitteeKeypair<E>> = keypairs
+			.into_iter()
+			.map(|(derivation, keypair)| {
+				let index = manifest
+					.index_of(keypair.public())
+					.ok_or(AcssError::InvalidCommittee)?;
+				Ok(CommitteeKeypair { keypair, index, derivation })
+			})
+			.collect::<Result<_, AcssError>>()?;
+		members.sort_by_key(|member| member.index);
+		Ok((members, manifest))
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	#[test]
+	fn committees_are_generated_in_share_order() {
+		let (members, manifest) =
+			Keypair::<E>::generate_committee(4, [1u8; 32]).unwrap();
+		assert_eq!(manifest.len(), 4);
+		for (position, member) in members.iter().enumerate() {
+			assert_eq!(member.index.position(), position);
+			assert_eq!(manifest.keys()[position].0, member.keypair.public().0);
+		}
+		let mut derivations: Vec<u8> =
+			members.iter().map(|member| member.derivation).collect();
+		derivations.sort();
+		assert_eq!(derivations, [0, 1, 2, 3]);
+
+		// the same seed gives the same committee, another seed does not
+		let (_, again) =
+			Keypair::<E>::generate_committee(4, [1u8; 32]).unwrap();
+		let (_, other) =
+			Keypair::<E>::generate_committee(4, [2u8; 32]).unwrap();
+		assert_eq!(again.digest(), manifest.digest());
+		assert_ne!(other.digest(), manifest.digest());
+
+		assert_eq!(
+			Keypair::<E>::generate_committee(0, [1u8; 32]).err(),
+			Some(AcssError::InvalidCommittee)
+		);
+	}
+
+	#[test]
+	fn manifests_are_canonical() {
+		let (members, manifest) =
+			Keypair::<E>::generate_committee(3, [1u8; 32]).unwrap();
+		let mut shuffled: Vec<PublicKey<E>> =
+			members.iter().map(|member| *member.keypair.public()).collect();
+		shuffled.reverse();
+		let reordered = CommitteeManifest::new(&shuffled).unwrap();
+		assert_eq!(reordered.digest(), manifest.digest());
+
+		let bytes = manifest.encode().unwrap();
+		let decoded = CommitteeManifest::<E>::decode(&bytes).unwrap();
+		assert_eq!(decoded.digest(), manifest.digest());
+
+		// keys out of order, repeated or followed by trailing bytes
+		let key_len = (bytes.len() - 1) / 3;
+		let mut swapped = vec![3u8];
+		swapped.extend_from_slice(&bytes[1 + key_len..1 + 2 * key_len]);
+		swapped.extend_from_slice(&bytes[1..1 + key_len]);
+		swapped.extend_from_slice(&bytes[1 + 2 * key_len..]);
+		let mut repeated = bytes.clone();
+		repeated[1 + key_len..1 + 2 * key_len]
+			.copy_from_slice(&bytes[1..1 + key_len]);
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		for bad in [&swapped[..], &repeated[..], &trailing[..], &bytes[..5]] {
+			assert_eq!(
+				CommitteeManifest::<E>::decode(bad).err(),
+				Some(AcssError::InvalidTranscript)
+			);
+		}
+		assert_eq!(
+			CommitteeManifest::<E>::new::<PublicKey<E>>(&[]).err(),
+			Some(AcssError::InvalidCommittee)
+		);
+	}
+}
@@ -0,0 +1,278 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Merkle Commitments to Resharings
+//!
+//! A dealer may publish the Merkle root of a resharing's per-recipient
+//! entries next to (or instead of) the full transcript. A server storing the
+//! transcript then hands each recipient its own entry with an
+//! [`InclusionProof`], and the recipient checks it against the root without
+//! downloading or trusting the rest of the transcript.
+//!
+//! A leaf hashes the share index, the recipient's public key, the share
+//! public key and the PoK of an entry. Leaves and nodes are hashed with
+//! distinct prefixes, an odd node is promoted to the next level as is, and
+//! the root commits to the number of leaves, so an entry only verifies at the
+//! position it was dealt to.
+
+use crate::{
+	error::AcssError,
+	keys::ShareIndex,
+	proofs::{hashed_el_gamal_sigma::BatchPoK, ser::HashWriter},
+	resharing::Resharing,
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+use w3f_bls::{DoublePublicKey, EngineBLS, PublicKey};
+
+/// the prefix of leaf hashes
+const LEAF_PREFIX: u8 = 0;
+/// the prefix of inner node hashes
+const NODE_PREFIX: u8 = 1;
+/// the prefix of the root, which binds the number of leaves
+const ROOT_PREFIX: u8 = 2;
+
+/// a proof that an entry is included in a resharing at its index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+	/// the index of the entry
+	pub index: ShareIndex,
+	/// the number of entries in the resharing
+	pub leaves: u8,
+	/// the sibling hashes on the path to the root, from the leaf up, skipping
+	/// the levels where the path is promoted
+	pub siblings: Vec<[u8; 32]>,
+}
+
+impl InclusionProof {
+	/// true if the entry dealt to `recipient` is included at `self.index` in
+	/// the resharing with Merkle root `root`
+	///
+	/// * `root`: The Merkle root of the resharing
+	/// * `recipient`: The public key of the recipient
+	/// * `share_pk`: The share public key of the entry
+	/// * `pok`: The PoK of the entry
+	pub fn verify<E: EngineBLS>(
+		&self,
+		root: &[u8; 32],
+		recipient: &PublicKey<E>,
+		share_pk: &DoublePublicKey<E>,
+		pok: &BatchPoK<E::PublicKeyGroup>,
+	) -> bool {
+		if self.index.get() > self.leaves {
+			return false;
+		}
+		let Ok(mut hash) = leaf_hash(self.index, recipient, share_pk, pok)
+		else {
+			return false;
+		};
+		let mut siblings = self.siblings.iter();
+		let (mut position, mut len) =
+			(self.index.position(), self.leaves as usize);
+		while len > 1 {
+			if position ^ 1 < len {
+				let Some(sibling) = siblings.next() else {
+					return false;
+				};
+				hash = if position % 2 == 0 {
+					node_hash(&hash, sibling)
+				} else {
+					node_hash(sibling, &hash)
+				};
+			}
+			position /= 2;
+			len = len.div_ceil(2);
+		}
+		siblings.next().is_none() && root_hash(self.leaves, &hash) == *root
+	}
+}
+
+/// the Merkle root of the entries of a resharing
+/// fails with `InvalidCommittee` if the resharing is empty or has more than
+/// 255 entries, and with `InvalidTranscript` if an entry cannot be encoded
+///
+/// * `resharing`: The resharing
+pub fn merkle_root<E: EngineBLS>(
+	resharing: &Resharing<E>,
+) -> Result<[u8; 32], AcssError> {
+	let levels = levels(resharing)?;
+	let leaves = resharing.len() as u8;
+	Ok(root_hash(leaves, &levels[levels.len() - 1][0]))
+}
+
+/// prove that the entry at `index` is included in a resharing
+/// fails as `merkle_root` does, and with `ShareIndexOutOfRange` if the
+/// resharing has fewer than `index` entries
+///
+/// * `resharing`: The resharing
+/// * `index`: The index of the entry
+pub fn prove_inclusion<E: EngineBLS>(
+	resharing: &Resharing<E>,
+	index: ShareIndex,
+) -> Result<InclusionProof, AcssError> {
+	if index.position() >= resharing.len() {
+		return Err(AcssError::ShareIndexOutOfRange);
+	}
+	let levels = levels(resharing)?;
+	let mut position = index.position();
+	let mut siblings = Vec::new();
+	for level in &levels[..levels.len() - 1] {
+		if let Some(sibling) = level.get(position ^ 1) {
+			siblings.push(*sibling);
+		}
+		position /= 2;
+	}
+	Ok(InclusionProof { index, leaves: resharing.len() as u8, siblings })
+}
+
+/// every level of the tree, from the leaves to the root
+fn levels<E: EngineBLS>(
+	resharing: &Resharing<E>,
+) -> Result<Vec<Vec<[u8; 32]>>, AcssError> {
+	if resharing.is_empty() || u8::try_from(resharing.len()).is_err() {
+		return Err(AcssError::InvalidCommittee);
+	}
+	let leaves = resharing
+		.iter()
+		.enumerate()
+		.map(|(position, (recipient, share_pk, pok))| {
+			let index = ShareIndex::from_position(position)
+				.ok_or(AcssError::InvalidCommittee)?;
+			leaf_hash(index, recipient, share_pk, pok)
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut levels = vec![leaves];
+	while levels[levels.len() - 1].len() > 1 {
+		let next = levels[levels.len() - 1]
+			.chunks(2)
+			.map(|pair| match pair {
+				[left, right] => node_hash(left, right),
+				[single] => *single,
+				_ => unreachable!("chunks of two are never empty"),
+			})
+			.collect();
+		levels.push(next);
+	}
+	Ok(levels)
+}
+
+fn leaf_hash<E: EngineBLS>(
+	index: ShareIndex,
+	recipient: &PublicKey<E>,
+	share_pk: &DoublePublicKey<E>,
+	pok: &BatchPoK<E::PublicKeyGroup>,
+) -> Result<[u8; 32], AcssError> {
+	let mut hasher = Sha256::new().chain_update([LEAF_PREFIX, index.get()]);
+	recipient
+		.0
+		.serialize_compressed(HashWriter(&mut hasher))
+		.and_then(|_| share_pk.0.serialize_compressed(HashWriter(&mut hasher)))
+		.and_then(|_| share_pk.1.serialize_compressed(HashWriter(&mut hasher)))
+		.and_then(|_| pok.serialize_compressed(HashWriter(&mut hasher)))
+		.map_err(|_| AcssError::InvalidTranscript)?;
+	Ok(hasher.finalize().into())
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+	Sha256::new()
+		.chain_update([NODE_PREFIX])
+		.chain_update(left)
+		.chain_update(right)
+		.finalize()
+		.into()
+}
+
+fn root_hash(leaves: u8, top: &[u8; 32]) -> [u8; 32] {
+	Sha256::new()
+		.chain_update([ROOT_PREFIX, leaves])
+		.chain_update(top)
+		.finalize()
+		.into()
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::{DoubleSecret, Keypair},
+		params::ThresholdParams,
+	};
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	fn resharing(n: usize) -> Resharing<E> {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let committee: Vec<PublicKey<E>> = (0..n)
+			.map(|_| *Keypair(KeypairVT::<E>::generate(&mut rng)).public())
+			.collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 1).unwrap();
+		secret.reshare(&committee, params, &mut rng).unwrap()
+	}
+
+	#[test]
+	fn every_entry_verifies_against_the_root() {
+		for n in [1, 2, 5] {
+			let resharing = resharing(n);
+			let root = merkle_root(&resharing).unwrap();
+			for (position, (recipient, share_pk, pok)) in
+				resharing.iter().enumerate()
+			{
+				let index = ShareIndex::from_position(position).unwrap();
+				let proof = prove_inclusion(&resharing, index).unwrap();
+				assert!(proof.verify(&root, recipient, share_pk, pok));
+			}
+			let out_of_range = ShareIndex::from_position(n).unwrap();
+			assert_eq!(
+				prove_inclusion(&resharing, out_of_range).err(),
+				Some(AcssError::ShareIndexOutOfRange)
+			);
+		}
+	}
+
+	#[test]
+	fn entries_only_verify_at_their_own_position() {
+		let resharing = resharing(5);
+		let root = merkle_root(&resharing).unwrap();
+		let entries: Vec<_> = resharing.iter().collect();
+		let (recipient, share_pk, pok) = entries[4];
+		let proof =
+			prove_inclusion(&resharing, ShareIndex::new(5).unwrap()).unwrap();
+
+		let (other_recipient, other_pk, other_pok) = entries[3];
+		assert!(!proof.verify(&root, other_recipient, other_pk, other_pok));
+		assert!(!proof.verify(&root, other_recipient, share_pk, pok));
+		assert!(!proof.verify(&[0u8; 32], recipient, share_pk, pok));
+
+		let mut moved = proof.clone();
+		moved.index = ShareIndex::new(4).unwrap();
+		assert!(!moved.verify(&root, recipient, share_pk, pok));
+		let mut resized = proof.clone();
+		resized.leaves = 6;
+		assert!(!resized.verify(&root, recipient, share_pk, pok));
+		let mut extended = proof;
+		extended.siblings.push([0u8; 32]);
+		assert!(!extended.verify(&root, recipient, share_pk, pok));
+	}
+}
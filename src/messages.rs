@@ -0,0 +1,310 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Message Authentication
+//!
+//! Dealings and acknowledgments carry their own signatures, but a relay
+//! between members could still forge what is not signed (complaints), or
+//! move a signed message to another session, epoch or round. Every protocol
+//! message is therefore sent in a [`SignedMessage`]: the sender signs the
+//! [`MessageHeader`] together with the encoded message under
+//! [`MESSAGE_SIGNATURE_CONTEXT`], and the receiver opens it against the
+//! header it expects before looking at the message. Whether the sender may
+//! send that message (e.g. is the dealer of a dealing) is up to the layer
+//! above.
+//!
+//! The header also carries the sender's sequence number, which increases with
+//! every message it sends. The receiver keeps a [`ReplayWindow`] per sender
+//! and opening a message records its sequence number there, so a network
+//! adversary cannot deliver the same message twice.
+//!
+//! Layout: `session || epoch || round || sequence || sender || signature ||
+//! payload` where the payload is the rest of the bytes.
+
+use crate::{
+	error::AcssError,
+	replay::ReplayWindow,
+	transcript::{read, read_array, read_point, write},
+};
+use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "participant")]
+use w3f_bls::KeypairVT;
+use w3f_bls::{EngineBLS, Message, PublicKey, Signature};
+
+/// the signing context of protocol messages
+pub const MESSAGE_SIGNATURE_CONTEXT: &[u8] = b"acss/message";
+
+/// where a message belongs, signed along with it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MessageHeader {
+	/// the id of the session, e.g. the digest of its committee manifest
+	pub session: [u8; 32],
+	/// the epoch of the committee
+	pub epoch: u64,
+	/// the round of the protocol within the epoch
+	pub round: u32,
+	/// the sender's sequence number of the message
+	pub sequence: u64,
+}
+
+impl MessageHeader {
+	/// the length of the encoding of a header
+	pub const ENCODED_LEN: usize = 32 + 8 + 4 + 8;
+
+	/// true if both headers are for the same session, epoch and round,
+	/// whatever their sequence numbers
+	///
+	/// * `other`: The other header
+	pub fn same_round(&self, other: &Self) -> bool {
+		self.session == other.session &&
+			self.epoch == other.epoch &&
+			self.round == other.round
+	}
+
+	/// the header of the next message sent, whose sequence number is one
+	/// higher
+	pub fn next(&self) -> Self {
+		Self { sequence: self.sequence.wrapping_add(1), ..*self }
+	}
+
+	/// append the encoding `session || epoch || round || sequence` of the
+	/// header
+	pub(crate) fn write(&self, out: &mut Vec<u8>) {
+		out.extend_from_slice(&self.session);
+		out.extend_from_slice(&self.epoch.to_le_bytes());
+		out.extend_from_slice(&self.round.to_le_bytes());
+		out.extend_from_slice(&self.sequence.to_le_bytes());
+	}
+
+	/// read a header written by `write`
+	/// fails with `InvalidTranscript` if the bytes run out
+	pub(crate) fn read(reader: &mut &[u8]) -> Result<Self, AcssError> {
+		Ok(Self {
+			session: read_array::<32>(reader)?,
+			epoch: u64::from_le_bytes(read_array::<8>(reader)?),
+			round: u32::from_le_bytes(read_array::<4>(reader)?),
+			sequence: u64::from_le_bytes(read_array::<8>(reader)?),
+		})
+	}
+}
+
+/// a message signed by its sender, bound to a header
+pub struct SignedMessage<E: EngineBLS> {
+	/// where the message belongs
+	pub header: MessageHeader,
+	/// the public key of the sender
+	pub sender: PublicKey<E>,
+	/// the encoded message
+	pub payload: Vec<u8>,
+	/// the sender's signature over the header and the payload
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for SignedMessage<E> {
+	fn clone(&self) -> Self {
+		Self {
+			header: self.header,
+			sender: self.sender,
+			payload: self.payload.clone(),
+			signature: self.signature,
+		}
+	}
+}
+
+impl<E: EngineBLS> SignedMessage<E> {
+	/// sign `payload` as the sender `keypair`
+	/// fails with `InvalidTranscript` if the sender's key cannot be encoded
+	///
+	/// * `header`: Where the message belongs
+	/// * `payload`: The encoded message
+	/// * `keypair`: The sender's committee keypair
+	#[cfg(feature = "participant")]
+	pub fn sign(
+		header: MessageHeader,
+		payload: Vec<u8>,
+		keypair: &KeypairVT<E>,
+	) -> Result<Self, AcssError> {
+		let signature =
+			keypair.sign(&message(&header, &keypair.public, &payload)?);
+		Ok(Self { header, sender: keypair.public, payload, signature })
+	}
+
+	/// the payload, if the message is signed by its sender, belongs where
+	/// `header` says and was not opened before
+	/// the sequence number of `header` is ignored, the message's own is
+	/// recorded in `window` once the signature verifies
+	/// fails with `HeaderMismatch` if the session, epoch or round differ from
+	/// those of `header`, with `InvalidSignature` if the signature does not
+	/// verify, with `ReplayedMessage` if `window` already saw the sequence
+	/// number or it is too old to tell, and with `InvalidTranscript` if the
+	/// sender's key cannot be encoded
+	///
+	/// * `header`: The header the receiver expects
+	/// * `window`: The window of the sequence numbers received from the sender
+	pub fn open(
+		&self,
+		header: &MessageHeader,
+		window: &mut ReplayWindow,
+	) -> Result<&[u8], AcssError> {
+		if !self.header.same_round(header) {
+			return Err(AcssError::HeaderMismatch);
+		}
+		let message = message(&self.header, &self.sender, &self.payload)?;
+		if !self.signature.verify(&message, &self.sender) {
+			return Err(AcssError::InvalidSignature);
+		}
+		window.accept(self.header.sequence)?;
+		Ok(&self.payload)
+	}
+
+	/// encode the message
+	/// fails with `InvalidSignature` if the signature cannot be encoded
+	pub fn encode(&self) -> Result<Vec<u8>, AcssError> {
+		let mut out = Vec::new();
+		self.header.write(&mut out);
+		write(&self.sender.0, &mut out)?;
+		write(&self.signature.0, &mut out)?;
+		out.extend_from_slice(&self.payload);
+		Ok(out)
+	}
+
+	/// decode a message
+	/// the signature is not checked, see [`Self::open`]
+	/// fails with `InvalidTranscript` if the bytes cannot be decoded
+	///
+	/// * `bytes`: The encoded message
+	pub fn decode(mut bytes: &[u8]) -> Result<Self, AcssError> {
+		let reader = &mut bytes;
+		let header = MessageHeader::read(reader)?;
+		let sender = PublicKey(read_point(reader)?);
+		let signature = Signature(read(reader)?);
+		Ok(Self { header, sender, payload: reader.to_vec(), signature })
+	}
+}
+
+/// the message the sender signs
+/// fails with `InvalidTranscript` if the sender's key cannot be encoded
+fn message<E: EngineBLS>(
+	header: &MessageHeader,
+	sender: &PublicKey<E>,
+	payload: &[u8],
+) -> Result<Message, AcssError> {
+	let mut bytes = Vec::new();
+	header.write(&mut bytes);
+	write(&sender.0, &mut bytes)?;
+	bytes.extend_from_slice(payload);
+	Ok(Message::new(MESSAGE_SIGNATURE_CONTEXT, &Sha256::digest(&bytes)))
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	#[test]
+	fn messages_open_only_where_they_belong() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let sender = KeypairVT::<E>::generate(&mut rng);
+		let header =
+			MessageHeader { session: [7; 32], epoch: 3, round: 1, sequence: 0 };
+		let signed =
+			SignedMessage::sign(header, b"complaint".to_vec(), &sender)
+				.unwrap();
+
+		let decoded =
+			SignedMessage::<E>::decode(&signed.encode().unwrap()).unwrap();
+		assert_eq!(
+			decoded.open(&header, &mut ReplayWindow::new()).unwrap(),
+			b"complaint"
+		);
+		assert_eq!(decoded.sender.0, sender.public.0);
+
+		// a relay can neither move the message nor change it
+		for other in [
+			MessageHeader { session: [8; 32], ..header },
+			MessageHeader { epoch: 4, ..header },
+			MessageHeader { round: 2, ..header },
+		] {
+			assert_eq!(
+				decoded.open(&other, &mut ReplayWindow::new()).err(),
+				Some(AcssError::HeaderMismatch)
+			);
+			let moved = SignedMessage { header: other, ..decoded.clone() };
+			assert_eq!(
+				moved.open(&other, &mut ReplayWindow::new()).err(),
+				Some(AcssError::InvalidSignature)
+			);
+		}
+		let renumbered = SignedMessage {
+			header: MessageHeader { sequence: 1, ..header },
+			..decoded.clone()
+		};
+		assert_eq!(
+			renumbered.open(&header, &mut ReplayWindow::new()).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		let forged =
+			SignedMessage { payload: b"forged".to_vec(), ..decoded.clone() };
+		assert_eq!(
+			forged.open(&header, &mut ReplayWindow::new()).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		let impersonated = SignedMessage {
+			sender: KeypairVT::<E>::generate(&mut rng).public,
+			..decoded
+		};
+		assert_eq!(
+			impersonated.open(&header, &mut ReplayWindow::new()).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		assert!(SignedMessage::<E>::decode(&[0; 40]).is_err());
+	}
+
+	#[test]
+	fn replayed_messages_are_rejected() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let sender = KeypairVT::<E>::generate(&mut rng);
+		let first =
+			MessageHeader { session: [7; 32], epoch: 3, round: 1, sequence: 0 };
+		let messages: Vec<Vec<u8>> = [first, first.next()]
+			.into_iter()
+			.map(|header| {
+				SignedMessage::sign(header, b"dealing".to_vec(), &sender)
+					.unwrap()
+					.encode()
+					.unwrap()
+			})
+			.collect();
+
+		let mut window = ReplayWindow::new();
+		let mut open = |bytes: &[u8]| {
+			SignedMessage::<E>::decode(bytes)
+				.unwrap()
+				.open(&first, &mut window)
+				.map(|payload| payload.to_vec())
+		};
+		// messages may arrive out of order, but only once
+		assert!(open(&messages[1]).is_ok());
+		assert!(open(&messages[0]).is_ok());
+		for bytes in &messages {
+			assert_eq!(open(bytes).err(), Some(AcssError::ReplayedMessage));
+		}
+	}
+}
@@ -0,0 +1,396 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Cross-Group Migration
+//!
+//! Moves a single secret, known in full to the caller, from the scalar field
+//! of one curve to that of another (e.g. from BLS12-377 to BLS12-381) without
+//! exposing it. [`migrate`] outputs x in the new scalar field together with a
+//! [`CrossGroupProof`] that xG in the old group and xG' in the new group hide
+//! the same integer, which anyone holding the old public key can check before
+//! accepting xG' as its replacement. A dealer that knows the secret (e.g. the
+//! initial dealer of a committee) migrates it and then deals it to the
+//! committee in the new group.
+//!
+//! Secrets held in shares cannot be migrated share by share. Shamir shares
+//! interpolate to the secret with Lagrange coefficients of the old field, and
+//! the integers of the shares interpolated in the new field give an unrelated
+//! value. Moving a shared secret needs a distributed protocol (e.g. a joint
+//! bit decomposition) that this module does not provide.
+//!
+//! The proof follows the bit decomposition approach: x is written in binary,
+//! every bit is committed to in both groups with a Pedersen commitment, and an
+//! OR proof shows that both commitments of a bit open to 0 or both open to 1.
+//! The blinding factors are chosen to sum to zero once the bits are weighted,
+//! so the weighted commitments sum to xG and xG'. The OR proofs share a
+//! 128-bit challenge, which is a valid scalar in either field.
+//!
+//! The secret must fit in fewer bits than both moduli, see [`max_bits`], so
+//! that the weighted sum wraps around in neither field. Not every scalar
+//! does: from BLS12-377 the bound is 2^252, while its scalars reach about
+//! 1.17 * 2^252, so roughly one uniformly random secret in seven cannot be
+//! migrated. A secret meant to be migrated must be generated below the bound.
+
+use crate::{
+	error::AcssError,
+	proofs::{nums_generator, ser::HashWriter},
+};
+#[cfg(feature = "participant")]
+use crate::{rng::SecureRng, wipe};
+use ark_ec::CurveGroup;
+#[cfg(feature = "participant")]
+use ark_ff::{BigInteger, UniformRand, Zero};
+use ark_ff::{Field, PrimeField};
+use ark_std::vec::Vec;
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+
+/// the domain separation label of the second generator of each group
+pub const GENERATOR_LABEL: &[u8] = b"acss/migration/generator";
+/// the domain separation label of bit proof challenges
+pub const CHALLENGE_LABEL: &[u8] = b"acss/migration/challenge";
+
+/// the proof that a bit is committed to in both groups
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitProof<C1: CurveGroup, C2: CurveGroup> {
+	/// the commitment to the bit in the first group
+	pub c1: C1,
+	/// the commitment to the bit in the second group
+	pub c2: C2,
+	/// the challenges of the 0 and 1 branches
+	pub e: [u128; 2],
+	/// the responses of the 0 and 1 branches in the first group
+	pub z1: [C1::ScalarField; 2],
+	/// the responses of the 0 and 1 branches in the second group
+	pub z2: [C2::ScalarField; 2],
+}
+
+/// a proof that xG1 and xG2 hide the same integer x, bit by bit from the
+/// least significant
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrossGroupProof<C1: CurveGroup, C2: CurveGroup> {
+	/// the proof of each bit of x
+	pub bits: Vec<BitProof<C1, C2>>,
+}
+
+#[cfg(feature = "participant")]
+/// a secret moved to the scalar field of C2
+pub struct Migration<C1: CurveGroup, C2: CurveGroup> {
+	/// the secret in the scalar field of C2
+	pub secret: C2::ScalarField,
+	/// the public key of the secret in C2
+	pub public: C2,
+	/// the proof that `public` hides the secret behind the old public key
+	pub proof: CrossGroupProof<C1, C2>,
+}
+
+#[cfg(feature = "participant")]
+impl<C1: CurveGroup, C2: CurveGroup> Drop for Migration<C1, C2> {
+	fn drop(&mut self) {
+		wipe(&mut self.secret);
+	}
+}
+
+/// the largest number of bits a secret can be migrated with, one less than
+/// the smaller modulus
+pub fn max_bits<C1: CurveGroup, C2: CurveGroup>() -> usize {
+	let size = C1::ScalarField::MODULUS_BIT_SIZE
+		.min(C2::ScalarField::MODULUS_BIT_SIZE);
+	size as usize - 1
+}
+
+#[cfg(feature = "participant")]
+/// move a secret from the scalar field of C1 to that of C2, proving that the
+/// public keys in both groups hide the same secret
+/// the secret must be known in full, see the module docs for shared secrets
+/// fails with `InvalidParams` if `bits` exceeds `max_bits` or the secret does
+/// not fit in `bits` bits
+///
+/// * `secret`: The secret in the scalar field of C1
+/// * `bits`: The number of bits of the secret
+/// * `rng`: A CSPRNG
+pub fn migrate<C1: CurveGroup, C2: CurveGroup, R: SecureRng>(
+	secret: &C1::ScalarField,
+	bits: usize,
+	mut rng: R,
+) -> Result<Migration<C1, C2>, AcssError> {
+	let mut repr = secret.into_bigint();
+	let mut decomposed = repr.to_bits_le();
+	wipe(&mut repr);
+	let fits = decomposed.iter().skip(bits).all(|bit| !bit);
+	decomposed.truncate(bits);
+	if bits == 0 || bits > max_bits::<C1, C2>() || !fits {
+		wipe(&mut decomposed);
+		return Err(AcssError::InvalidParams);
+	}
+
+	let mut bytes = secret.into_bigint().to_bytes_le();
+	let migrated = C2::ScalarField::from_le_bytes_mod_order(&bytes);
+	wipe(&mut bytes);
+	let (x1, x2) = (C1::generator() * secret, C2::generator() * migrated);
+
+	// the weighted blinding factors must sum to zero, which fixes the last
+	let mut r1: Vec<C1::ScalarField> =
+		(1..bits).map(|_| C1::ScalarField::rand(&mut rng)).collect();
+	let mut r2: Vec<C2::ScalarField> =
+		(1..bits).map(|_| C2::ScalarField::rand(&mut rng)).collect();
+	r1.push(balance(&r1));
+	r2.push(balance(&r2));
+
+	let (h1, h2) = (
+		nums_generator::<C1>(GENERATOR_LABEL),
+		nums_generator::<C2>(GENERATOR_LABEL),
+	);
+	let proof = decomposed
+		.iter()
+		.enumerate()
+		.map(|(i, bit)| {
+			let witness = BitWitness { bit: *bit, r1: r1[i], r2: r2[i] };
+			prove_bit(&witness, i, (&x1, &x2), (&h1, &h2), &mut rng)
+		})
+		.collect::<Result<Vec<_>, _>>();
+	wipe(&mut decomposed);
+	wipe(&mut r1);
+	wipe(&mut r2);
+
+	Ok(Migration {
+		secret: migrated,
+		public: x2,
+		proof: CrossGroupProof { bits: proof? },
+	})
+}
+
+impl<C1: CurveGroup, C2: CurveGroup> CrossGroupProof<C1, C2> {
+	/// true if `x1` and `x2` are the public keys of the same secret in C1 and
+	/// C2
+	///
+	/// * `x1`: The public key of the secret in C1
+	/// * `x2`: The public key of the secret in C2
+	pub fn verify(&self, x1: &C1, x2: &C2) -> bool {
+		let bits = self.bits.len();
+		if bits == 0 || bits > max_bits::<C1, C2>() {
+			return false;
+		}
+		let (g1, g2) = (C1::generator(), C2::generator());
+		let (h1, h2) = (
+			nums_generator::<C1>(GENERATOR_LABEL),
+			nums_generator::<C2>(GENERATOR_LABEL),
+		);
+
+		let (mut sum1, mut sum2) = (C1::zero(), C2::zero());
+		let (mut weight1, mut weight2) =
+			(C1::ScalarField::from(1u64), C2::ScalarField::from(1u64));
+		for (i, proof) in self.bits.iter().enumerate() {
+			// recompute the commitments of both branches from the responses
+			let a = [0u64, 1].map(|branch| {
+				let e1 = C1::ScalarField::from(proof.e[branch as usize]);
+				let e2 = C2::ScalarField::from(proof.e[branch as usize]);
+				let opened1 = proof.c1 - g1 * C1::ScalarField::from(branch);
+				let opened2 = proof.c2 - g2 * C2::ScalarField::from(branch);
+				(
+					h1 * proof.z1[branch as usize] - opened1 * e1,
+					h2 * proof.z2[branch as usize] - opened2 * e2,
+				)
+			});
+			let Ok(e) = challenge(i, (x1, x2), (&proof.c1, &proof.c2), &a)
+			else {
+				return false;
+			};
+			if proof.e[0].wrapping_add(proof.e[1]) != e {
+				return false;
+			}
+			sum1 += proof.c1 * weight1;
+			sum2 += proof.c2 * weight2;
+			weight1.double_in_place();
+			weight2.double_in_place();
+		}
+		sum1 == *x1 && sum2 == *x2
+	}
+}
+
+#[cfg(feature = "participant")]
+/// the secret opening of a bit commitment
+struct BitWitness<F1, F2> {
+	bit: bool,
+	r1: F1,
+	r2: F2,
+}
+
+#[cfg(feature = "participant")]
+/// prove that the commitments of a bit open to the same bit in both groups
+fn prove_bit<C1: CurveGroup, C2: CurveGroup, R: SecureRng>(
+	witness: &BitWitness<C1::ScalarField, C2::ScalarField>,
+	i: usize,
+	public: (&C1, &C2),
+	(h1, h2): (&C1, &C2),
+	mut rng: R,
+) -> Result<BitProof<C1, C2>, AcssError> {
+	let (g1, g2) = (C1::generator(), C2::generator());
+	let real = witness.bit as usize;
+	let simulated = 1 - real;
+	let c1 = *h1 * witness.r1 + if witness.bit { g1 } else { C1::zero() };
+	let c2 = *h2 * witness.r2 + if witness.bit { g2 } else { C2::zero() };
+
+	// simulate the branch of the other bit
+	let mut e = [0u128; 2];
+	let mut z1 = [C1::ScalarField::zero(); 2];
+	let mut z2 = [C2::ScalarField::zero(); 2];
+	let mut a = [(C1::zero(), C2::zero()); 2];
+	e[simulated] = u128::rand(&mut rng);
+	z1[simulated] = C1::ScalarField::rand(&mut rng);
+	z2[simulated] = C2::ScalarField::rand(&mut rng);
+	let branch = simulated as u64;
+	a[simulated] = (
+		*h1 * z1[simulated] -
+			(c1 - g1 * C1::ScalarField::from(branch)) *
+				C1::ScalarField::from(e[simulated]),
+		*h2 * z2[simulated] -
+			(c2 - g2 * C2::ScalarField::from(branch)) *
+				C2::ScalarField::from(e[simulated]),
+	);
+
+	// and commit honestly to the branch of the actual bit
+	let mut k1 = C1::ScalarField::rand(&mut rng);
+	let mut k2 = C2::ScalarField::rand(&mut rng);
+	a[real] = (*h1 * k1, *h2 * k2);
+	let challenge = challenge(i, public, (&c1, &c2), &a)?;
+	e[real] = challenge.wrapping_sub(e[simulated]);
+	z1[real] = k1 + C1::ScalarField::from(e[real]) * witness.r1;
+	z2[real] = k2 + C2::ScalarField::from(e[real]) * witness.r2;
+	wipe(&mut k1);
+	wipe(&mut k2);
+
+	Ok(BitProof { c1, c2, e, z1, z2 })
+}
+
+#[cfg(feature = "participant")]
+/// the blinding factor of the last bit, such that the blinding factors
+/// weighted by 2^i sum to zero
+fn balance<F: PrimeField>(blinds: &[F]) -> F {
+	let (mut sum, mut weight) = (F::zero(), F::one());
+	for blind in blinds {
+		sum += weight * blind;
+		weight.double_in_place();
+	}
+	// 2^i is invertible, since the modulus is odd
+	-sum * weight.inverse().unwrap_or_default()
+}
+
+/// the challenge of a bit proof, truncated to 128 bits so that it is a
+/// scalar in both fields
+fn challenge<C1: CurveGroup, C2: CurveGroup>(
+	i: usize,
+	(x1, x2): (&C1, &C2),
+	(c1, c2): (&C1, &C2),
+	a: &[(C1, C2); 2],
+) -> Result<u128, AcssError> {
+	let mut h = Shake256::default();
+	h.update(CHALLENGE_LABEL);
+	h.update(&(i as u64).to_be_bytes());
+	let encode = |h: &mut Shake256| {
+		x1.serialize_compressed(HashWriter(&mut *h))?;
+		x2.serialize_compressed(HashWriter(&mut *h))?;
+		c1.serialize_compressed(HashWriter(&mut *h))?;
+		c2.serialize_compressed(HashWriter(&mut *h))?;
+		for (a1, a2) in a {
+			a1.serialize_compressed(HashWriter(&mut *h))?;
+			a2.serialize_compressed(HashWriter(&mut *h))?;
+		}
+		Ok::<_, ark_serialize::SerializationError>(())
+	};
+	encode(&mut h).map_err(|_| AcssError::InvalidProof)?;
+	let mut bytes = [0u8; 16];
+	h.finalize_xof().read(&mut bytes);
+	Ok(u128::from_le_bytes(bytes))
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use ark_ec::Group;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{EngineBLS, TinyBLS377};
+
+	type C1 = <TinyBLS377 as EngineBLS>::SignatureGroup;
+	type C2 = ark_bls12_381::G1Projective;
+	type F1 = <C1 as Group>::ScalarField;
+	type F2 = <C2 as Group>::ScalarField;
+
+	#[test]
+	fn secrets_migrate_between_scalar_fields() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = F1::from(0xbeefu64);
+		let migration = migrate::<C1, C2, _>(&secret, 16, &mut rng).unwrap();
+		assert_eq!(migration.secret, F2::from(0xbeefu64));
+		assert_eq!(migration.public, C2::generator() * migration.secret);
+
+		let x1 = C1::generator() * secret;
+		let proof = &migration.proof;
+		assert!(proof.verify(&x1, &migration.public));
+		assert!(!proof.verify(&(x1 + x1), &migration.public));
+		assert!(!proof.verify(&x1, &(C2::generator() * F2::from(0xbeeeu64))));
+
+		let mut swapped = proof.clone();
+		swapped.bits.swap(0, 1);
+		assert!(!swapped.verify(&x1, &migration.public));
+		let mut truncated = proof.clone();
+		truncated.bits.pop();
+		assert!(!truncated.verify(&x1, &migration.public));
+	}
+
+	#[test]
+	fn full_width_secrets_migrate() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let bits = max_bits::<C1, C2>();
+		// a uniformly random secret below 2^bits
+		let mut repr = F1::rand(&mut rng).into_bigint();
+		repr.as_mut()[3] &= (1 << (bits - 192)) - 1;
+		let secret = F1::from_bigint(repr).unwrap();
+
+		let migration = migrate::<C1, C2, _>(&secret, bits, &mut rng).unwrap();
+		assert_eq!(
+			migration.secret,
+			F2::from_le_bytes_mod_order(&secret.into_bigint().to_bytes_le())
+		);
+		let x1 = C1::generator() * secret;
+		assert!(migration.proof.verify(&x1, &migration.public));
+
+		// while the scalars from 2^bits up do not fit
+		let bound = F1::from(2u64).pow([bits as u64]);
+		assert_eq!(
+			migrate::<C1, C2, _>(&bound, bits, &mut rng).err(),
+			Some(AcssError::InvalidParams)
+		);
+	}
+
+	#[test]
+	fn migrate_rejects_secrets_that_do_not_fit() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let mut migrate_with = |secret: u64, bits: usize| {
+			migrate::<C1, C2, _>(&F1::from(secret), bits, &mut rng).err()
+		};
+		assert_eq!(migrate_with(1 << 16, 16), Some(AcssError::InvalidParams));
+		assert_eq!(migrate_with(1, 0), Some(AcssError::InvalidParams));
+		assert_eq!(
+			migrate_with(1, max_bits::<C1, C2>() + 1),
+			Some(AcssError::InvalidParams)
+		);
+		assert_eq!(max_bits::<C1, C2>(), 252);
+	}
+}
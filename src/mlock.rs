@@ -0,0 +1,148 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Page-Locked Secrets
+//!
+//! [`Locked`] keeps a value in its own page-aligned allocation which is locked
+//! with `mlock`, so it is never written to swap. The pages are wiped and
+//! unlocked when the value is dropped. Since locks do not nest, each value
+//! gets whole pages to itself rather than sharing them with other allocations.
+//!
+//! Only the inline bytes of the value are locked, so it should not own heap
+//! allocations (field elements and `DoubleSecret` do not).
+#![allow(unsafe_code)]
+
+use crate::error::AcssError;
+use alloc::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use core::{
+	fmt,
+	mem::{align_of, size_of},
+	ops::{Deref, DerefMut},
+	ptr::NonNull,
+};
+use zeroize::Zeroize;
+
+/// a value stored in page-locked memory
+pub struct Locked<T: Zeroize> {
+	ptr: NonNull<T>,
+	layout: Layout,
+}
+
+// SAFETY: `Locked` owns its value exclusively, as a `Box` would
+unsafe impl<T: Zeroize + Send> Send for Locked<T> {}
+// SAFETY: shared access only hands out `&T`
+unsafe impl<T: Zeroize + Sync> Sync for Locked<T> {}
+
+impl<T: Zeroize> Locked<T> {
+	/// move a value into freshly locked pages
+	/// fails with `MemoryLockFailed` if the pages cannot be locked, e.g. when
+	/// `RLIMIT_MEMLOCK` is exhausted
+	///
+	/// the value is moved, so callers should wipe the original if it is still
+	/// reachable
+	///
+	/// * `value`: The value to lock
+	pub fn new(value: T) -> Result<Self, AcssError> {
+		let page = page_size();
+		let size = size_of::<T>().max(1).next_multiple_of(page);
+		let layout = Layout::from_size_align(size, page.max(align_of::<T>()))
+			.map_err(|_| AcssError::MemoryLockFailed)?;
+
+		// SAFETY: the layout has a non-zero size
+		let raw = unsafe { alloc_zeroed(layout) };
+		let Some(ptr) = NonNull::new(raw) else { handle_alloc_error(layout) };
+		// SAFETY: the allocation is valid for `size` bytes
+		if unsafe { libc::mlock(raw.cast(), size) } != 0 {
+			// SAFETY: allocated above with the same layout
+			unsafe { dealloc(raw, layout) };
+			return Err(AcssError::MemoryLockFailed);
+		}
+
+		let ptr = ptr.cast::<T>();
+		// SAFETY: the allocation is large enough and aligned for a `T`
+		unsafe { ptr.as_ptr().write(value) };
+		Ok(Self { ptr, layout })
+	}
+}
+
+impl<T: Zeroize> fmt::Debug for Locked<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("Locked(<redacted>)")
+	}
+}
+
+impl<T: Zeroize> Deref for Locked<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		// SAFETY: the pointer is valid and initialized for the lifetime of self
+		unsafe { self.ptr.as_ref() }
+	}
+}
+
+impl<T: Zeroize> DerefMut for Locked<T> {
+	fn deref_mut(&mut self) -> &mut T {
+		// SAFETY: as above, and `&mut self` guarantees exclusive access
+		unsafe { self.ptr.as_mut() }
+	}
+}
+
+impl<T: Zeroize> Drop for Locked<T> {
+	fn drop(&mut self) {
+		let raw = self.ptr.as_ptr().cast::<u8>();
+		let size = self.layout.size();
+		// SAFETY: the value is initialized and never used again, and the
+		// allocation (valid for `size` bytes) is wiped, unlocked and freed with
+		// the layout it was allocated with
+		unsafe {
+			self.ptr.as_mut().zeroize();
+			core::ptr::drop_in_place(self.ptr.as_ptr());
+			core::slice::from_raw_parts_mut(raw, size).zeroize();
+			libc::munlock(raw.cast(), size);
+			dealloc(raw, self.layout);
+		}
+	}
+}
+
+/// the size of a memory page, used as the granularity of locks
+fn page_size() -> usize {
+	// SAFETY: sysconf has no preconditions
+	let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+	usize::try_from(size).ok().filter(|s| s.is_power_of_two()).unwrap_or(4096)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn locked_values_are_page_aligned_and_usable() {
+		let mut locked = Locked::new([7u8; 32]).unwrap();
+		assert_eq!(locked.ptr.as_ptr() as usize % page_size(), 0);
+		assert_eq!(*locked, [7u8; 32]);
+
+		locked[0] = 1;
+		assert_eq!(locked[0], 1);
+		assert_eq!(locked.layout.size(), page_size());
+	}
+
+	#[test]
+	fn values_larger_than_a_page_are_locked_whole() {
+		let page = page_size();
+		let big = Locked::new([1u64; 1024]).unwrap();
+		assert_eq!(big.layout.size(), (8 * 1024usize).next_multiple_of(page));
+		assert!(big.iter().all(|x| *x == 1));
+	}
+}
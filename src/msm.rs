@@ -0,0 +1,104 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Multi-Scalar Multiplication Backends
+//!
+//! Batch verification reduces to a single large multi-scalar multiplication
+//! (MSM). The backend computing it is pluggable so that nodes verifying many
+//! dealings can swap in an accelerated implementation.
+//!
+//! - [`ArkworksMsm`]: arkworks' Pippenger implementation (the default)
+//! - [`ParallelMsm`]: splits the input across threads with rayon and runs
+//!   Pippenger on each chunk (requires the `parallel` feature)
+
+use ark_ec::CurveGroup;
+
+/// computes sum_i scalars[i] * bases[i]
+pub trait MsmBackend<C: CurveGroup> {
+	/// the multi-scalar multiplication of `bases` by `scalars`
+	/// the inputs are truncated to the length of the shorter one
+	fn msm(bases: &[C::Affine], scalars: &[C::ScalarField]) -> C;
+}
+
+/// arkworks' Pippenger implementation
+pub struct ArkworksMsm;
+
+impl<C: CurveGroup> MsmBackend<C> for ArkworksMsm {
+	fn msm(bases: &[C::Affine], scalars: &[C::ScalarField]) -> C {
+		let len = bases.len().min(scalars.len());
+		C::msm_unchecked(&bases[..len], &scalars[..len])
+	}
+}
+
+/// the backend used when none is specified
+#[cfg(not(feature = "parallel"))]
+pub type DefaultMsm = ArkworksMsm;
+/// the backend used when none is specified
+#[cfg(feature = "parallel")]
+pub type DefaultMsm = ParallelMsm;
+
+/// a multithreaded Pippenger, splitting the input into one chunk per thread
+#[cfg(feature = "parallel")]
+pub struct ParallelMsm;
+
+#[cfg(feature = "parallel")]
+impl<C: CurveGroup> MsmBackend<C> for ParallelMsm {
+	fn msm(bases: &[C::Affine], scalars: &[C::ScalarField]) -> C {
+		use rayon::prelude::*;
+
+		let len = bases.len().min(scalars.len());
+		let chunk_size = len.div_ceil(rayon::current_num_threads()).max(1);
+		bases[..len]
+			.par_chunks(chunk_size)
+			.zip(scalars[..len].par_chunks(chunk_size))
+			.map(|(b, s)| C::msm_unchecked(b, s))
+			.sum()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_bls12_381::{Fr, G1Projective as G1};
+	use ark_ec::Group;
+	use ark_ff::UniformRand;
+	use ark_std::{test_rng, vec::Vec, Zero};
+
+	fn naive(bases: &[G1], scalars: &[Fr]) -> G1 {
+		bases.iter().zip(scalars).fold(G1::zero(), |acc, (b, s)| acc + *b * s)
+	}
+
+	fn check_backend<M: MsmBackend<G1>>() {
+		let mut rng = test_rng();
+		for n in [0, 1, 7, 64] {
+			let bases: Vec<G1> =
+				(0..n).map(|_| G1::generator() * Fr::rand(&mut rng)).collect();
+			let scalars: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+			let affine = G1::normalize_batch(&bases);
+			assert_eq!(M::msm(&affine, &scalars), naive(&bases, &scalars));
+		}
+	}
+
+	#[test]
+	fn arkworks_backend_matches_naive_msm() {
+		check_backend::<ArkworksMsm>();
+	}
+
+	#[cfg(feature = "parallel")]
+	#[test]
+	fn parallel_backend_matches_naive_msm() {
+		check_backend::<ParallelMsm>();
+	}
+}
@@ -0,0 +1,587 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Async Variants
+//!
+//! Dealing to a large committee and batch verifying many proofs each take
+//! long enough to starve an async executor if run inside a task. The variants
+//! here do the same work in chunks, yielding back to the executor between
+//! chunks. They are executor agnostic: a yield wakes the task and returns
+//! `Pending` once, so they can be awaited from tokio, async-std or a runtime's
+//! own executor alike.
+//!
+//! The futures are `Send` whenever the rng is, so they can be spawned onto
+//! multithreaded executors.
+//!
+//! Drivers that need to spawn tasks or wait on a clock do so through a
+//! [`Runtime`], so they are not tied to one executor either. The `tokio` and
+//! `async-std` features provide `Tokio` and `AsyncStd`. An adapter for
+//! another executor is a few lines: smol's, for example, sleeps on
+//! `smol::Timer::after(duration).map(drop)` and spawns with
+//! `smol::spawn(future).detach()`, boxing the sleep future as `AsyncStd`
+//! does.
+
+use crate::{
+	acss::{DealingMode, DoubleSecret},
+	error::AcssError,
+	keys::{committee_keys, CommitteeKey, PublicKey as EncryptionKey},
+	observer::{Observer, Phase},
+	params::ThresholdParams,
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+	resharing::Resharing,
+	rng::SecureRng,
+	session::AcssSession,
+};
+use ark_ec::CurveGroup;
+use ark_std::vec::Vec;
+use core::{
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll},
+};
+use w3f_bls::{EngineBLS, PublicKey};
+
+/// a future that returns `Pending` once, waking its task immediately, so
+/// that the executor can run other tasks before polling it again
+#[derive(Debug, Default)]
+pub struct YieldNow(bool);
+
+impl Future for YieldNow {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+		if self.0 {
+			return Poll::Ready(());
+		}
+		self.0 = true;
+		cx.waker().wake_by_ref();
+		Poll::Pending
+	}
+}
+
+/// yield to the executor once
+pub fn yield_now() -> YieldNow {
+	YieldNow::default()
+}
+
+/// the executor a driver runs on: how it spawns tasks and tells the time
+pub trait Runtime {
+	/// the future output by `sleep`
+	type Sleep: Future<Output = ()>;
+
+	/// the current time, in the unit of the session timeouts
+	fn now(&self) -> u64;
+
+	/// a future that completes once `duration` has passed
+	///
+	/// * `duration`: How long to sleep, in the unit of `now`
+	fn sleep(&self, duration: u64) -> Self::Sleep;
+
+	/// run `future` to completion in the background
+	///
+	/// * `future`: The task to spawn
+	fn spawn<F: Future<Output = ()> + Send + 'static>(&self, future: F);
+}
+
+/// a [`Runtime`] on a tokio runtime, telling the time in milliseconds since
+/// the adapter was created
+#[cfg(feature = "tokio")]
+#[derive(Clone, Debug)]
+pub struct Tokio {
+	handle: tokio::runtime::Handle,
+	start: tokio::time::Instant,
+}
+
+#[cfg(feature = "tokio")]
+impl Tokio {
+	/// an adapter spawning onto the runtime of `handle`
+	///
+	/// * `handle`: The handle of a runtime with its time driver enabled
+	pub fn new(handle: tokio::runtime::Handle) -> Self {
+		Self { handle, start: tokio::time::Instant::now() }
+	}
+}
+
+#[cfg(feature = "tokio")]
+impl Runtime for Tokio {
+	type Sleep = tokio::time::Sleep;
+
+	fn now(&self) -> u64 {
+		millis(self.start.elapsed())
+	}
+
+	fn sleep(&self, duration: u64) -> Self::Sleep {
+		// the timer is registered with the runtime the sleep is created on
+		let _context = self.handle.enter();
+		tokio::time::sleep(core::time::Duration::from_millis(duration))
+	}
+
+	fn spawn<F: Future<Output = ()> + Send + 'static>(&self, future: F) {
+		self.handle.spawn(future);
+	}
+}
+
+/// a [`Runtime`] on the global async-std executor, telling the time in
+/// milliseconds since the adapter was created
+#[cfg(feature = "async-std")]
+#[derive(Clone, Debug)]
+pub struct AsyncStd(std::time::Instant);
+
+#[cfg(feature = "async-std")]
+impl AsyncStd {
+	/// an adapter whose clock starts now
+	pub fn new() -> Self {
+		Self(std::time::Instant::now())
+	}
+}
+
+#[cfg(feature = "async-std")]
+impl Default for AsyncStd {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStd {
+	// async-std's sleep future cannot be named
+	type Sleep = Pin<alloc::boxed::Box<dyn Future<Output = ()> + Send>>;
+
+	fn now(&self) -> u64 {
+		millis(self.0.elapsed())
+	}
+
+	fn sleep(&self, duration: u64) -> Self::Sleep {
+		let duration = core::time::Duration::from_millis(duration);
+		alloc::boxed::Box::pin(async_std::task::sleep(duration))
+	}
+
+	fn spawn<F: Future<Output = ()> + Send + 'static>(&self, future: F) {
+		async_std::task::spawn(future);
+	}
+}
+
+/// a duration in whole milliseconds, saturating at `u64::MAX`
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+fn millis(duration: core::time::Duration) -> u64 {
+	u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// sleep on `runtime` until the next deadline of `session`, then report the
+/// time to it as with `AcssSession::tick`
+/// outputs the phases that passed their deadline, none immediately if no
+/// running phase has a deadline
+///
+/// * `session`: The session to wait on
+/// * `runtime`: The runtime to sleep on
+pub async fn wait_deadline<E: EngineBLS, O: Observer, RT: Runtime>(
+	session: &mut AcssSession<E, O>,
+	runtime: &RT,
+) -> Vec<Phase> {
+	let Some(deadline) = session.next_deadline() else {
+		return Vec::new();
+	};
+	let now = runtime.now();
+	if deadline > now {
+		runtime.sleep(deadline - now).await;
+	}
+	session.tick(runtime.now())
+}
+
+/// reshare `secret` to a committee as with `DoubleSecret::reshare_with_mode`,
+/// yielding to the executor after every `chunk` recipients
+///
+/// * `secret`: The secret to reshare
+/// * `committee`: The next committee to generate shares for
+/// * `params`: The threshold parameters, where n is the committee size
+/// * `mode`: how to sample ephemeral keys
+/// * `chunk`: The number of recipients to deal to between yields, at least 1
+/// * `rng`: A CSPRNG
+pub async fn reshare<E: EngineBLS, R: SecureRng, K: CommitteeKey<E>>(
+	secret: &DoubleSecret<E>,
+	committee: &[K],
+	params: ThresholdParams,
+	mode: DealingMode,
+	chunk: usize,
+	mut rng: R,
+) -> Result<Resharing<E>, AcssError> {
+	let DoubleSecret(msk, msk_hat) = secret;
+	let committee = committee_keys(committee);
+	let mut dealing = crate::acss::Dealing::new(
+		*msk, *msk_hat, &committee, params, mode, &mut rng,
+	)?;
+	'dealing: loop {
+		for _ in 0..chunk.max(1) {
+			if dealing.deal_next(&mut rng)? {
+				break 'dealing;
+			}
+		}
+		yield_now().await;
+	}
+	dealing.finish()
+}
+
+/// reshare `secret` as with `reshare` in a task spawned on `runtime`,
+/// yielding between recipients, and hand the result to `deliver`
+///
+/// * `runtime`: The runtime to deal on
+/// * `secret`: The secret to reshare
+/// * `committee`: The next committee to generate shares for
+/// * `params`: The threshold parameters, where n is the committee size
+/// * `mode`: how to sample ephemeral keys
+/// * `rng`: A CSPRNG
+/// * `deliver`: Called with the resharing, or the error dealing failed with
+pub fn spawn_reshare<E, R, RT, F>(
+	runtime: &RT,
+	secret: DoubleSecret<E>,
+	committee: Vec<PublicKey<E>>,
+	params: ThresholdParams,
+	mode: DealingMode,
+	rng: R,
+	deliver: F,
+) where
+	E: EngineBLS + 'static,
+	R: SecureRng + Send + 'static,
+	RT: Runtime,
+	F: FnOnce(Result<Resharing<E>, AcssError>) + Send + 'static,
+{
+	runtime.spawn(async move {
+		deliver(reshare(&secret, &committee, params, mode, 1, rng).await);
+	});
+}
+
+/// verify many proofs as with `BatchPoK::verify_batch`, checking `chunk`
+/// proofs per multi-scalar multiplication and yielding to the executor
+/// between them
+/// outputs true if every proof is valid, false if any proof is invalid
+/// (except with negligible probability)
+///
+/// * `proofs`: (expected public key, proof) pairs
+/// * `chunk`: The number of proofs to verify between yields, at least 1
+/// * `rng`: used to sample the combination coefficients
+pub async fn verify_batch<C: CurveGroup, R: SecureRng>(
+	proofs: &[(EncryptionKey<C>, &BatchPoK<C>)],
+	chunk: usize,
+	mut rng: R,
+) -> bool {
+	let mut chunks = proofs.chunks(chunk.max(1)).peekable();
+	while let Some(proofs) = chunks.next() {
+		if !BatchPoK::verify_batch(proofs, &mut rng) {
+			return false;
+		}
+		if chunks.peek().is_some() {
+			yield_now().await;
+		}
+	}
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::{HighThresholdACSS, Keypair},
+		builder::ReshareBuilder,
+		context::VerificationContext,
+		keys::{SecretKey, ShareIndex},
+		pending::PendingVerifier,
+		progress::{CancellationToken, Monitor},
+		proofs::hashed_el_gamal::{Ciphertext, HashedElGamal},
+		replay::ReplayWindow,
+		session::{AcssSession, PhaseTimeouts},
+		transcript::SignedDealing,
+	};
+	use alloc::{boxed::Box, sync::Arc, task::Wake};
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use core::{
+		cell::{Cell, RefCell},
+		future::{ready, Ready},
+		pin::pin,
+	};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, PublicKey, TinyBLS377};
+
+	type E = TinyBLS377;
+	type G = <E as EngineBLS>::PublicKeyGroup;
+
+	struct Noop;
+
+	impl Wake for Noop {
+		fn wake(self: Arc<Self>) {}
+	}
+
+	/// poll `future` to completion, outputting its value and the number of
+	/// times it yielded
+	fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+		let waker = Arc::new(Noop).into();
+		let mut cx = Context::from_waker(&waker);
+		let mut future = pin!(future);
+		let mut yields = 0;
+		loop {
+			match future.as_mut().poll(&mut cx) {
+				Poll::Ready(output) => return (output, yields),
+				Poll::Pending => yields += 1,
+			}
+		}
+	}
+
+	/// a runtime on a manual clock, whose sleeps advance the clock and
+	/// whose spawned tasks are queued to be polled by the test
+	#[derive(Default)]
+	struct Manual {
+		clock: Cell<u64>,
+		tasks: RefCell<Vec<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+	}
+
+	impl Runtime for Manual {
+		type Sleep = Ready<()>;
+
+		fn now(&self) -> u64 {
+			self.clock.get()
+		}
+
+		fn sleep(&self, duration: u64) -> Self::Sleep {
+			self.clock.set(self.clock.get() + duration);
+			ready(())
+		}
+
+		fn spawn<F: Future<Output = ()> + Send + 'static>(&self, future: F) {
+			self.tasks.borrow_mut().push(Box::pin(future));
+		}
+	}
+
+	fn assert_send_sync<T: Send + Sync>() {}
+	fn assert_send<T: Send>(_: &T) {}
+
+	#[test]
+	fn public_types_are_send_and_sync() {
+		assert_send_sync::<DoubleSecret<E>>();
+		assert_send_sync::<Keypair<E>>();
+		assert_send_sync::<HighThresholdACSS<E>>();
+		assert_send_sync::<ReshareBuilder<'_, E>>();
+		assert_send_sync::<Resharing<E>>();
+		assert_send_sync::<SignedDealing<E>>();
+		assert_send_sync::<AcssSession<E>>();
+		assert_send_sync::<VerificationContext<E>>();
+		assert_send_sync::<ThresholdParams>();
+		assert_send_sync::<ShareIndex>();
+		assert_send_sync::<SecretKey<G>>();
+		assert_send_sync::<EncryptionKey<G>>();
+		assert_send_sync::<Ciphertext<G>>();
+		assert_send_sync::<HashedElGamal<G>>();
+		assert_send_sync::<BatchPoK<G>>();
+		assert_send_sync::<PendingVerifier<G>>();
+		assert_send_sync::<ReplayWindow>();
+		assert_send_sync::<Monitor<'_>>();
+		assert_send_sync::<CancellationToken>();
+		assert_send_sync::<AcssError>();
+	}
+
+	#[test]
+	fn async_reshare_matches_reshare_and_yields_between_chunks() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let keys: Vec<KeypairVT<E>> =
+			(0..5).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|kp| kp.public).collect();
+		let params = ThresholdParams::for_committee(&committee, 3).unwrap();
+		let mode = DealingMode::default();
+
+		let future = reshare(
+			&secret,
+			&committee,
+			params,
+			mode,
+			2,
+			ChaCha20Rng::seed_from_u64(1),
+		);
+		assert_send(&future);
+		let (resharing, yields) = block_on(future);
+		let resharing = resharing.unwrap();
+		let expected = secret
+			.reshare_with_mode(
+				&committee,
+				params,
+				mode,
+				ChaCha20Rng::seed_from_u64(1),
+			)
+			.unwrap();
+		assert_eq!(yields, 2);
+		assert!(resharing.poks().eq(expected.poks()));
+
+		let proofs: Vec<_> = keys
+			.iter()
+			.zip(resharing.poks())
+			.map(|(kp, pok)| (EncryptionKey(kp.public.0), pok))
+			.collect();
+		let (valid, yields) = block_on(verify_batch(&proofs, 2, &mut rng));
+		assert!(valid);
+		assert_eq!(yields, 2);
+		assert!(block_on(verify_batch(&proofs, 0, &mut rng)).0);
+
+		let mut wrong_keys = proofs.clone();
+		wrong_keys[2].0 = wrong_keys[3].0;
+		let (valid, yields) = block_on(verify_batch(&wrong_keys, 2, &mut rng));
+		assert!(!valid);
+		assert_eq!(yields, 1);
+
+		let empty: Vec<PublicKey<E>> = Vec::new();
+		assert!(block_on(reshare(&secret, &empty, params, mode, 2, &mut rng))
+			.0
+			.is_err());
+	}
+
+	/// a secret, a committee of 3 and the parameters of a 2 of 3 dealing
+	#[cfg(feature = "std")]
+	fn dealing_fixture() -> (DoubleSecret<E>, Vec<PublicKey<E>>, ThresholdParams)
+	{
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let committee: Vec<PublicKey<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng).public).collect();
+		let params = ThresholdParams::for_committee(&committee, 2).unwrap();
+		(secret, committee, params)
+	}
+
+	/// deal in a task spawned on `runtime`, sleeping on it until the
+	/// resharing is delivered, and check it matches a synchronous dealing
+	#[cfg(any(feature = "tokio", feature = "async-std"))]
+	async fn spawned_dealing_is_delivered<RT: Runtime>(runtime: &RT) {
+		let start = runtime.now();
+		runtime.sleep(5).await;
+		assert!(runtime.now() >= start + 5);
+
+		let (secret, committee, params) = dealing_fixture();
+		let expected = secret
+			.reshare(&committee, params, ChaCha20Rng::seed_from_u64(1))
+			.unwrap();
+		let delivered = Arc::new(std::sync::Mutex::new(None));
+		let slot = delivered.clone();
+		spawn_reshare(
+			runtime,
+			secret,
+			committee,
+			params,
+			DealingMode::default(),
+			ChaCha20Rng::seed_from_u64(1),
+			move |resharing| *slot.lock().unwrap() = Some(resharing),
+		);
+		let resharing = loop {
+			if let Some(resharing) = delivered.lock().unwrap().take() {
+				break resharing.unwrap();
+			}
+			runtime.sleep(1).await;
+		};
+		assert!(resharing.poks().eq(expected.poks()));
+	}
+
+	#[test]
+	#[cfg(feature = "tokio")]
+	fn tokio_runs_spawned_dealings() {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.enable_time()
+			.build()
+			.unwrap();
+		runtime.block_on(async {
+			let tokio = Tokio::new(tokio::runtime::Handle::current());
+			spawned_dealing_is_delivered(&tokio).await;
+		});
+	}
+
+	#[test]
+	#[cfg(feature = "async-std")]
+	fn async_std_runs_spawned_dealings() {
+		async_std::task::block_on(spawned_dealing_is_delivered(
+			&AsyncStd::new(),
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "std")]
+	fn spawned_dealings_are_delivered() {
+		let (secret, committee, params) = dealing_fixture();
+		let expected = secret
+			.reshare(&committee, params, ChaCha20Rng::seed_from_u64(1))
+			.unwrap();
+		let runtime = Manual::default();
+		let delivered = Arc::new(std::sync::Mutex::new(None));
+		let slot = delivered.clone();
+		spawn_reshare(
+			&runtime,
+			secret,
+			committee,
+			params,
+			DealingMode::default(),
+			ChaCha20Rng::seed_from_u64(1),
+			move |resharing| *slot.lock().unwrap() = Some(resharing),
+		);
+		assert!(delivered.lock().unwrap().is_none());
+		let tasks = runtime.tasks.take();
+		assert_eq!(tasks.len(), 1);
+		for task in tasks {
+			// a yield between each of the 3 recipients
+			assert_eq!(block_on(task).1, 2);
+		}
+		let resharing = delivered.lock().unwrap().take().unwrap().unwrap();
+		assert!(resharing.poks().eq(expected.poks()));
+	}
+
+	#[test]
+	fn wait_deadline_sleeps_on_the_runtime() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let runtime = Manual::default();
+		runtime.clock.set(100);
+		let timeouts =
+			PhaseTimeouts { deal: Some(10), ack: None, complaint: Some(20) };
+		let mut session =
+			AcssSession::new(Keypair(members[0].clone()), &committee, 2)
+				.unwrap()
+				.with_timeouts(timeouts, runtime.now());
+
+		let (expired, _) = block_on(wait_deadline(&mut session, &runtime));
+		assert_eq!(expired, [Phase::Deal]);
+		assert_eq!(runtime.now(), 110);
+		let (expired, _) = block_on(wait_deadline(&mut session, &runtime));
+		assert_eq!(expired, [Phase::Complaint]);
+		assert_eq!(runtime.now(), 130);
+		// no running phase has a deadline, so there is nothing to wait for
+		let (expired, _) = block_on(wait_deadline(&mut session, &runtime));
+		assert!(expired.is_empty());
+		assert_eq!(runtime.now(), 130);
+
+		let done = Arc::new(core::sync::atomic::AtomicBool::new(false));
+		let flag = done.clone();
+		runtime.spawn(async move {
+			yield_now().await;
+			flag.store(true, core::sync::atomic::Ordering::Relaxed);
+		});
+		for task in runtime.tasks.take() {
+			assert_eq!(block_on(task).1, 1);
+		}
+		assert!(done.load(core::sync::atomic::Ordering::Relaxed));
+	}
+}
@@ -0,0 +1,95 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Observers
+//!
+//! An [`Observer`] is told about the progress of an [`AcssSession`]: dealings
+//! started, shares verified or rejected, recoveries completed and phases
+//! timed out. Node
+//! software implements it to feed its metrics (e.g. Prometheus counters)
+//! without wrapping every call into the session. Every callback does nothing
+//! by default, and `()` is the observer of a session that has none.
+//!
+//! [`AcssSession`]: crate::session::AcssSession
+
+use crate::keys::ShareIndex;
+use alloc::{boxed::Box, sync::Arc};
+
+/// callbacks invoked by a session as the resharing progresses
+///
+/// the callbacks take `&self`, so an observer shared between sessions keeps
+/// its state in atomics or behind a lock
+pub trait Observer {
+	/// this node started dealing to a committee
+	///
+	/// * `n`: The size of the committee
+	/// * `t`: The threshold of the dealing
+	fn deal_started(&self, _n: u8, _t: u8) {}
+
+	/// the share dealt to this node by `dealer` was verified
+	fn share_verified(&self, _dealer: ShareIndex) {}
+
+	/// the dealing from `dealer` did not carry a valid proof for this node
+	fn proof_failed(&self, _dealer: ShareIndex) {}
+
+	/// this node recovered its share from the dealings of `dealers` dealers
+	fn recovery_completed(&self, _dealers: usize) {}
+
+	/// `phase` passed its deadline (see `AcssSession::tick`)
+	fn timed_out(&self, _phase: Phase) {}
+}
+
+/// the phases of a session that can time out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+	/// waiting for enough valid dealings to recover this node's share
+	Deal,
+	/// waiting for a quorum to acknowledge enough dealings, once the share
+	/// is recovered
+	Ack,
+	/// the window in which complaints about dealings are raised, once
+	/// dealings are no longer waited for
+	Complaint,
+}
+
+impl Observer for () {}
+
+macro_rules! forward_observer {
+	($($ty:ty),*) => {$(
+		impl<O: Observer + ?Sized> Observer for $ty {
+			fn deal_started(&self, n: u8, t: u8) {
+				(**self).deal_started(n, t)
+			}
+
+			fn share_verified(&self, dealer: ShareIndex) {
+				(**self).share_verified(dealer)
+			}
+
+			fn proof_failed(&self, dealer: ShareIndex) {
+				(**self).proof_failed(dealer)
+			}
+
+			fn recovery_completed(&self, dealers: usize) {
+				(**self).recovery_completed(dealers)
+			}
+
+			fn timed_out(&self, phase: Phase) {
+				(**self).timed_out(phase)
+			}
+		}
+	)*};
+}
+
+forward_observer!(&O, Box<O>, Arc<O>);
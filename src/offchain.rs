@@ -0,0 +1,1125 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Offchain Workers
+//!
+//! A Substrate offchain worker runs briefly after each block, with access to
+//! the node's persistent local storage, a way to reach the other members
+//! (HTTP or gossip) and the current timestamp. [`OffchainEnv`] captures
+//! exactly that, so that a node embeds the protocol by implementing it with
+//! a few `sp_io::offchain` calls, and this crate does not depend on Substrate.
+//!
+//! [`OffchainDriver`] runs an [`AcssSession`] against the environment. Each
+//! run ticks the session with the worker's timestamp, processes the messages
+//! received since the last run, broadcasts this node's acknowledgments, and
+//! checkpoints the session to local storage, so that the next run (possibly
+//! after a restart) resumes it with [`OffchainDriver::resume`].
+//!
+//! Outgoing messages go through a bounded [`OutgoingQueue`]. A message the
+//! network does not take stays queued and is retried on the next run, and
+//! [`OffchainDriver::backpressure`] tells the caller to stop generating
+//! dealings while the queue is over half full, so a slow network does not
+//! let unsent dealings pile up in memory. The queue is not checkpointed.
+//!
+//! Messages that are not acknowledged are resent as a [`ResendSchedule`]
+//! decides, by default never. [`ResendPolicies`] sets an exponential backoff
+//! and a maximum number of attempts per [`MessageClass`]; integrators who
+//! schedule resends themselves implement the trait instead. A dealing is
+//! acknowledged once it is certified, as is an acknowledgment of it, while
+//! complaints are resent until the schedule gives up.
+//!
+//! Every message is sent as a [`SignedMessage`] under the driver's
+//! [`MessageHeader`], which is also the header the session signs and accepts
+//! dealings for, so that a relay can neither forge a message, complaints
+//! included, nor move one to another session, epoch or round. A received
+//! message must also come from the member it speaks for: the dealer of a
+//! dealing, the member of an acknowledgment, a committee member for a
+//! complaint.
+//!
+//! The driver numbers the messages it sends and keeps a [`ReplayWindow`] per
+//! dealer and committee member, so a relay cannot deliver a message twice
+//! either (a resent message keeps its number, so only its first delivery is
+//! processed). Both are checkpointed along with the session.
+//!
+//! Checkpoint layout: `sequence || windows || snapshot` where `sequence` is
+//! the number of the next message sent, `windows` is a little endian u32 count
+//! followed by `sender || window` for every sender heard from, and `snapshot`
+//! is the rest of the bytes.
+//!
+//! Layout of the payload of a message: `kind || body` where the body is
+//! - `dealer || header || signature || len || transcript` for a dealing, with
+//!   `header` the one the dealing was signed for
+//! - `digest || member || signature` for an acknowledgment
+//! - the encoded evidence for a complaint
+
+use crate::{
+	acss::{DoubleSecret, Keypair},
+	certificate::{dealing_digest, ReadyAck},
+	error::AcssError,
+	evidence::{read_dealing, write_dealing, MisbehaviorEvidence},
+	keys::ShareIndex,
+	messages::{MessageHeader, SignedMessage},
+	observer::Phase,
+	replay::ReplayWindow,
+	rng::SecureRng,
+	session::AcssSession,
+	transcript::{read, read_array, read_point, write, SignedDealing},
+};
+use ark_std::{collections::VecDeque, vec, vec::Vec};
+use w3f_bls::{EngineBLS, PublicKey, Signature};
+
+const KIND_DEALING: u8 = 0;
+const KIND_ACK: u8 = 1;
+const KIND_COMPLAINT: u8 = 2;
+
+/// the default capacity of the outgoing queue of a driver, in bytes
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1 << 20;
+
+/// what the protocol needs from an offchain worker
+pub trait OffchainEnv {
+	/// the value stored under `key` in persistent local storage, if any
+	fn local_get(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+	/// store `value` under `key` in persistent local storage
+	fn local_set(&mut self, key: &[u8], value: &[u8]);
+
+	/// send `message` to every member of the committee
+	/// fails with `SendFailed` if the message could not be handed to the
+	/// network
+	fn send(&mut self, message: &[u8]) -> Result<(), AcssError>;
+
+	/// the current time, in the unit of the session's phase timeouts
+	fn timestamp(&self) -> u64;
+}
+
+/// a message exchanged by offchain workers
+pub enum OffchainMessage<E: EngineBLS> {
+	/// a dealing, with the key of the dealer who signed it
+	Dealing {
+		/// the public key of the dealer
+		dealer: PublicKey<E>,
+		/// the dealing
+		dealing: SignedDealing<E>,
+	},
+	/// an acknowledgment of a dealing
+	Ack(ReadyAck<E>),
+	/// evidence against a dealer
+	Complaint(MisbehaviorEvidence<E>),
+}
+
+impl<E: EngineBLS> OffchainMessage<E> {
+	/// encode the message
+	/// fails with `InvalidSignature` if a dealing is unsigned
+	pub fn encode(&self) -> Result<Vec<u8>, AcssError> {
+		let mut out = Vec::new();
+		match self {
+			Self::Dealing { dealer, dealing } => {
+				out.push(KIND_DEALING);
+				write(&dealer.0, &mut out)?;
+				write_dealing(dealing, &mut out)?;
+			},
+			Self::Ack(ack) => {
+				out.push(KIND_ACK);
+				out.extend_from_slice(&ack.digest);
+				out.push(ack.member.get());
+				write(&ack.signature.0, &mut out)?;
+			},
+			Self::Complaint(evidence) => return complaint(evidence),
+		}
+		Ok(out)
+	}
+
+	/// decode a message, rejecting trailing bytes
+	/// fails with `InvalidTranscript` if the bytes cannot be decoded
+	///
+	/// * `bytes`: The encoded message
+	pub fn decode(mut bytes: &[u8]) -> Result<Self, AcssError> {
+		let reader = &mut bytes;
+		let message = match read_array::<1>(reader)? {
+			[KIND_DEALING] => Self::Dealing {
+				dealer: PublicKey(read_point(reader)?),
+				dealing: read_dealing(reader)?,
+			},
+			[KIND_ACK] => {
+				let digest = read_array::<32>(reader)?;
+				let [member] = read_array::<1>(reader)?;
+				let member = ShareIndex::new(member)
+					.ok_or(AcssError::InvalidTranscript)?;
+				let signature = Signature(read(reader)?);
+				Self::Ack(ReadyAck { digest, member, signature })
+			},
+			[KIND_COMPLAINT] =>
+				return Ok(Self::Complaint(MisbehaviorEvidence::decode(reader)?)),
+			_ => return Err(AcssError::InvalidTranscript),
+		};
+		if !reader.is_empty() {
+			return Err(AcssError::InvalidTranscript);
+		}
+		Ok(message)
+	}
+}
+
+/// whether the producer of outgoing messages should keep going
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backpressure {
+	/// there is room for more messages
+	Ready,
+	/// the queue is over half full, stop generating dealings until it drains
+	Pause,
+}
+
+/// a bounded queue of messages waiting to be sent
+#[derive(Debug)]
+pub struct OutgoingQueue {
+	messages: VecDeque<Vec<u8>>,
+	bytes: usize,
+	capacity: usize,
+}
+
+impl OutgoingQueue {
+	/// an empty queue holding at most `capacity` bytes of messages
+	///
+	/// * `capacity`: The maximum number of bytes queued at once
+	pub fn new(capacity: usize) -> Self {
+		Self { messages: VecDeque::new(), bytes: 0, capacity }
+	}
+
+	/// the number of messages queued
+	pub fn len(&self) -> usize {
+		self.messages.len()
+	}
+
+	/// true if no message is queued
+	pub fn is_empty(&self) -> bool {
+		self.messages.is_empty()
+	}
+
+	/// the number of bytes queued
+	pub fn bytes(&self) -> usize {
+		self.bytes
+	}
+
+	/// whether to keep generating messages
+	pub fn backpressure(&self) -> Backpressure {
+		if self.bytes * 2 > self.capacity {
+			Backpressure::Pause
+		} else {
+			Backpressure::Ready
+		}
+	}
+
+	/// queue `message` behind the messages already queued
+	/// a message larger than the capacity is accepted into an empty queue,
+	/// so that it can still be sent
+	/// fails with `QueueFull` if the message does not fit
+	///
+	/// * `message`: The encoded message
+	pub fn push(
+		&mut self,
+		message: Vec<u8>,
+	) -> Result<Backpressure, AcssError> {
+		if !self.is_empty() && self.bytes + message.len() > self.capacity {
+			return Err(AcssError::QueueFull);
+		}
+		self.bytes += message.len();
+		self.messages.push_back(message);
+		Ok(self.backpressure())
+	}
+
+	/// send queued messages in order, stopping at the first one `send` fails
+	/// on, which stays queued
+	/// outputs the number of messages sent
+	///
+	/// * `send`: Hands a message to the network
+	pub fn flush<F>(&mut self, mut send: F) -> usize
+	where
+		F: FnMut(&[u8]) -> Result<(), AcssError>,
+	{
+		let mut sent = 0;
+		while let Some(message) = self.messages.front() {
+			if send(message).is_err() {
+				break;
+			}
+			self.bytes -= message.len();
+			self.messages.pop_front();
+			sent += 1;
+		}
+		sent
+	}
+}
+
+/// encode a complaint message
+fn complaint<E: EngineBLS>(
+	evidence: &MisbehaviorEvidence<E>,
+) -> Result<Vec<u8>, AcssError> {
+	let mut out = vec![KIND_COMPLAINT];
+	out.extend_from_slice(&evidence.encode()?);
+	Ok(out)
+}
+
+/// the kinds of message the driver resends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageClass {
+	/// this node's dealing
+	Dealing,
+	/// this node's acknowledgment of a dealing
+	Ack,
+	/// this node's complaint against a dealer
+	Complaint,
+}
+
+/// decides when unacknowledged messages are resent
+pub trait ResendSchedule {
+	/// when to send a message again, none to stop resending it
+	///
+	/// * `class`: The kind of message
+	/// * `attempts`: The number of times the message was sent so far
+	/// * `now`: The time of the last attempt
+	fn next_resend(
+		&self,
+		class: MessageClass,
+		attempts: u32,
+		now: u64,
+	) -> Option<u64>;
+}
+
+/// resend with exponential backoff, up to a number of attempts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResendPolicy {
+	/// the delay before the first resend, in the unit of the timestamp
+	pub interval: u64,
+	/// the factor each later delay grows by
+	pub factor: u64,
+	/// the maximum number of times a message is sent, including the first
+	pub max_attempts: u32,
+}
+
+impl ResendPolicy {
+	/// a policy doubling the delay after every attempt
+	///
+	/// * `interval`: The delay before the first resend
+	/// * `max_attempts`: The maximum number of sends, including the first
+	pub fn exponential(interval: u64, max_attempts: u32) -> Self {
+		Self { interval, factor: 2, max_attempts }
+	}
+
+	/// the delay after the `attempts`-th send, none once there are no
+	/// attempts left
+	///
+	/// * `attempts`: The number of times the message was sent so far
+	pub fn delay(&self, attempts: u32) -> Option<u64> {
+		if attempts >= self.max_attempts {
+			return None;
+		}
+		let backoff = self.factor.saturating_pow(attempts.saturating_sub(1));
+		Some(self.interval.saturating_mul(backoff))
+	}
+}
+
+/// a resend policy per class of message, none for no resends
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResendPolicies {
+	/// the policy for this node's dealing
+	pub dealing: Option<ResendPolicy>,
+	/// the policy for this node's acknowledgments
+	pub ack: Option<ResendPolicy>,
+	/// the policy for this node's complaints
+	pub complaint: Option<ResendPolicy>,
+}
+
+impl ResendSchedule for ResendPolicies {
+	fn next_resend(
+		&self,
+		class: MessageClass,
+		attempts: u32,
+		now: u64,
+	) -> Option<u64> {
+		let policy = match class {
+			MessageClass::Dealing => self.dealing,
+			MessageClass::Ack => self.ack,
+			MessageClass::Complaint => self.complaint,
+		}?;
+		Some(now.saturating_add(policy.delay(attempts)?))
+	}
+}
+
+/// a sent message waiting to be acknowledged
+struct Unacknowledged {
+	class: MessageClass,
+	/// the digest of the dealing whose certificate acknowledges the message
+	digest: Option<[u8; 32]>,
+	message: Vec<u8>,
+	attempts: u32,
+	due: u64,
+}
+
+/// what happened during a run of the driver
+pub struct OffchainReport<E: EngineBLS> {
+	/// the phases that passed their deadline
+	pub timed_out: Vec<Phase>,
+	/// evidence against dealers whose dealing carried no valid share for
+	/// this node
+	pub evidence: Vec<MisbehaviorEvidence<E>>,
+	/// valid complaints received from other members
+	pub complaints: Vec<MisbehaviorEvidence<E>>,
+	/// the digests of the dealings certified during the run
+	pub certified: Vec<[u8; 32]>,
+	/// the number of messages that could not be decoded or were rejected by
+	/// the session
+	pub rejected: usize,
+	/// the number of messages resent because they were not acknowledged
+	pub resent: usize,
+	/// the number of messages still queued after the run, for lack of a
+	/// network to send them to
+	pub queued: usize,
+	/// whether to keep generating dealings
+	pub backpressure: Backpressure,
+}
+
+/// runs a session in an offchain worker, resending messages as `S` decides
+pub struct OffchainDriver<
+	E: EngineBLS,
+	V: OffchainEnv,
+	S: ResendSchedule = ResendPolicies,
+> {
+	env: V,
+	storage_key: Vec<u8>,
+	/// the header of the next message sent
+	header: MessageHeader,
+	/// the sequence numbers received from each sender heard from
+	windows: Vec<(PublicKey<E>, ReplayWindow)>,
+	session: AcssSession<E>,
+	queue: OutgoingQueue,
+	schedule: S,
+	unacknowledged: Vec<Unacknowledged>,
+}
+
+impl<E: EngineBLS, V: OffchainEnv> OffchainDriver<E, V> {
+	/// drive a new session, checkpointing it under `storage_key`
+	/// the session signs and accepts dealings for `header`, and the messages
+	/// sent are numbered from its sequence number
+	/// fails as `AcssSession::snapshot` does
+	///
+	/// * `env`: The worker's environment
+	/// * `storage_key`: The local storage key of the session
+	/// * `header`: The header of the first message of the session
+	/// * `session`: The session
+	pub fn start(
+		env: V,
+		storage_key: &[u8],
+		header: MessageHeader,
+		session: AcssSession<E>,
+	) -> Result<Self, AcssError> {
+		let mut driver = Self {
+			env,
+			storage_key: storage_key.to_vec(),
+			header,
+			windows: Vec::new(),
+			session: session.with_header(header),
+			queue: OutgoingQueue::new(DEFAULT_QUEUE_CAPACITY),
+			schedule: ResendPolicies::default(),
+			unacknowledged: Vec::new(),
+		};
+		driver.checkpoint()?;
+		Ok(driver)
+	}
+
+	/// resume the session checkpointed under `storage_key`, none if there is
+	/// none
+	/// messages are numbered on from the checkpoint, whatever the sequence
+	/// number of `header`
+	/// fails with `InvalidTranscript` if the checkpoint cannot be decoded,
+	/// and otherwise as `AcssSession::restore` does
+	///
+	/// * `env`: The worker's environment
+	/// * `storage_key`: The local storage key of the session
+	/// * `header`: The header of the messages of the session
+	/// * `keypair`: This node's keypair
+	pub fn resume(
+		env: V,
+		storage_key: &[u8],
+		header: MessageHeader,
+		keypair: Keypair<E>,
+	) -> Result<Option<Self>, AcssError> {
+		let Some(checkpoint) = env.local_get(storage_key) else {
+			return Ok(None);
+		};
+		let reader = &mut &checkpoint[..];
+		let header = MessageHeader { sequence: read(reader)?, ..header };
+		let senders: u32 = read(reader)?;
+		let windows = (0..senders)
+			.map(|_| {
+				Ok((
+					PublicKey(read_point(reader)?),
+					ReplayWindow::read(reader)?,
+				))
+			})
+			.collect::<Result<Vec<_>, AcssError>>()?;
+		let session =
+			AcssSession::restore(keypair, reader)?.with_header(header);
+		Ok(Some(Self {
+			env,
+			storage_key: storage_key.to_vec(),
+			header,
+			windows,
+			session,
+			queue: OutgoingQueue::new(DEFAULT_QUEUE_CAPACITY),
+			schedule: ResendPolicies::default(),
+			unacknowledged: Vec::new(),
+		}))
+	}
+}
+
+impl<E: EngineBLS, V: OffchainEnv, S: ResendSchedule> OffchainDriver<E, V, S> {
+	/// resend unacknowledged messages as `schedule` decides, instead of
+	/// never
+	/// messages sent so far are rescheduled by it on their next resend
+	///
+	/// * `schedule`: The resend schedule, e.g. `ResendPolicies`
+	pub fn with_resend<T: ResendSchedule>(
+		self,
+		schedule: T,
+	) -> OffchainDriver<E, V, T> {
+		OffchainDriver {
+			env: self.env,
+			storage_key: self.storage_key,
+			header: self.header,
+			windows: self.windows,
+			session: self.session,
+			queue: self.queue,
+			schedule,
+			unacknowledged: self.unacknowledged,
+		}
+	}
+
+	/// bound the outgoing queue to `capacity` bytes instead of
+	/// `DEFAULT_QUEUE_CAPACITY`, keeping the messages already queued
+	///
+	/// * `capacity`: The maximum number of bytes queued at once
+	pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+		self.queue.capacity = capacity;
+		self
+	}
+
+	/// the session
+	pub fn session(&self) -> &AcssSession<E> {
+		&self.session
+	}
+
+	/// the worker's environment
+	pub fn env(&self) -> &V {
+		&self.env
+	}
+
+	/// the messages waiting to be sent
+	pub fn queue(&self) -> &OutgoingQueue {
+		&self.queue
+	}
+
+	/// whether to keep generating dealings, `Pause` while the network is not
+	/// keeping up with the outgoing queue
+	pub fn backpressure(&self) -> Backpressure {
+		self.queue.backpressure()
+	}
+
+	/// deal `secret` to the committee and broadcast the dealing
+	/// the dealing is also processed by this node's own session
+	/// fails as `AcssSession::deal` does, and with `QueueFull` if the
+	/// outgoing queue has no room for the dealing
+	///
+	/// * `secret`: The secret to deal
+	/// * `rng`: A CSPRNG
+	pub fn deal<R: SecureRng>(
+		&mut self,
+		secret: &DoubleSecret<E>,
+		rng: R,
+	) -> Result<OffchainReport<E>, AcssError> {
+		let dealing = self.session.deal(secret, rng)?;
+		let digest = dealing_digest(&dealing.transcript);
+		let message = self.seal(
+			OffchainMessage::Dealing {
+				dealer: *self.session.public_key(),
+				dealing,
+			}
+			.encode()?,
+		)?;
+		self.broadcast(MessageClass::Dealing, Some(digest), message.clone())?;
+		self.run([message])
+	}
+
+	/// process the messages received since the last run
+	/// ticks the session with the worker's timestamp first, then queues the
+	/// unacknowledged messages that are due for a resend, sends as much of
+	/// the outgoing queue as the network takes, and checkpoints the session
+	/// last. A message that cannot be decoded or is rejected by the
+	/// session is counted and skipped.
+	/// fails with `QueueFull` if the outgoing queue has no room for an
+	/// acknowledgment, and as `AcssSession::snapshot` does
+	///
+	/// * `messages`: The encoded messages received
+	pub fn run<I, B>(
+		&mut self,
+		messages: I,
+	) -> Result<OffchainReport<E>, AcssError>
+	where
+		I: IntoIterator<Item = B>,
+		B: AsRef<[u8]>,
+	{
+		let now = self.env.timestamp();
+		let mut report = OffchainReport {
+			timed_out: self.session.tick(now),
+			evidence: Vec::new(),
+			complaints: Vec::new(),
+			certified: Vec::new(),
+			rejected: 0,
+			resent: 0,
+			queued: 0,
+			backpressure: Backpressure::Ready,
+		};
+		for message in messages {
+			match self.handle(message.as_ref(), &mut report) {
+				Err(AcssError::QueueFull) => return Err(AcssError::QueueFull),
+				Err(_) => report.rejected += 1,
+				Ok(()) => {},
+			}
+		}
+		report.resent = self.resend(now);
+		let env = &mut self.env;
+		self.queue.flush(|message| env.send(message));
+		report.queued = self.queue.len();
+		report.backpressure = self.queue.backpressure();
+		self.checkpoint()?;
+		Ok(report)
+	}
+
+	/// process one message, once it is opened and its sender may send it
+	fn handle(
+		&mut self,
+		bytes: &[u8],
+		report: &mut OffchainReport<E>,
+	) -> Result<(), AcssError> {
+		let signed = SignedMessage::<E>::decode(bytes)?;
+		let sender = signed.sender.0;
+		let header = self.header;
+		let payload = signed.open(&header, self.window(&signed.sender)?)?;
+		let committee = self.session.committee();
+		match OffchainMessage::<E>::decode(payload)? {
+			OffchainMessage::Dealing { dealer, .. } if dealer.0 != sender =>
+				Err(AcssError::InvalidSignature),
+			OffchainMessage::Ack(ack)
+				if !committee
+					.get(ack.member.position())
+					.is_some_and(|member| member.0 == sender) =>
+				Err(AcssError::InvalidSignature),
+			OffchainMessage::Complaint(_)
+				if !committee.iter().any(|member| member.0 == sender) =>
+				Err(AcssError::InvalidCommittee),
+			message => self.process(message, report),
+		}
+	}
+
+	/// process one authenticated message
+	fn process(
+		&mut self,
+		message: OffchainMessage<E>,
+		report: &mut OffchainReport<E>,
+	) -> Result<(), AcssError> {
+		match message {
+			OffchainMessage::Dealing { dealer, dealing } => {
+				if let Some(evidence) =
+					self.session.on_message(&dealer, dealing)?
+				{
+					let message = self.seal(complaint(&evidence)?)?;
+					self.broadcast(MessageClass::Complaint, None, message)?;
+					report.evidence.push(evidence);
+				} else if let Some(ack) = self.session.ack(&dealer) {
+					let message =
+						self.seal(OffchainMessage::Ack(ack).encode()?)?;
+					self.broadcast(
+						MessageClass::Ack,
+						Some(ack.digest),
+						message,
+					)?;
+					self.on_ack(ack, report)?;
+				}
+			},
+			OffchainMessage::Ack(ack) => self.on_ack(ack, report)?,
+			OffchainMessage::Complaint(evidence) => {
+				if !evidence.verify() {
+					return Err(AcssError::InvalidProof);
+				}
+				report.complaints.push(evidence);
+			},
+		}
+		Ok(())
+	}
+
+	/// the replay window of `sender`
+	/// fails with `InvalidCommittee` if the sender is neither a dealer nor a
+	/// member of the committee, so outsiders cannot grow the windows
+	fn window(
+		&mut self,
+		sender: &PublicKey<E>,
+	) -> Result<&mut ReplayWindow, AcssError> {
+		let position =
+			match self.windows.iter().position(|(pk, _)| pk.0 == sender.0) {
+				Some(position) => position,
+				None => {
+					let known = self
+						.session
+						.dealers()
+						.iter()
+						.chain(self.session.committee())
+						.any(|pk| pk.0 == sender.0);
+					if !known {
+						return Err(AcssError::InvalidCommittee);
+					}
+					self.windows.push((*sender, ReplayWindow::new()));
+					self.windows.len() - 1
+				},
+			};
+		Ok(&mut self.windows[position].1)
+	}
+
+	/// sign `payload` under the header of the session, numbering it with the
+	/// next sequence number
+	fn seal(&mut self, payload: Vec<u8>) -> Result<Vec<u8>, AcssError> {
+		let header = self.header;
+		self.header = header.next();
+		SignedMessage::sign(header, payload, &self.session.keypair().0)?
+			.encode()
+	}
+
+	/// queue `message` and schedule its resend until the dealing with digest
+	/// `digest` is certified
+	fn broadcast(
+		&mut self,
+		class: MessageClass,
+		digest: Option<[u8; 32]>,
+		message: Vec<u8>,
+	) -> Result<(), AcssError> {
+		self.queue.push(message.clone())?;
+		let now = self.env.timestamp();
+		if let Some(due) = self.schedule.next_resend(class, 1, now) {
+			self.unacknowledged.push(Unacknowledged {
+				class,
+				digest,
+				message,
+				attempts: 1,
+				due,
+			});
+		}
+		Ok(())
+	}
+
+	/// queue the unacknowledged messages due at `now`, dropping those that
+	/// were acknowledged or that the schedule gave up on
+	/// outputs the number of messages queued
+	fn resend(&mut self, now: u64) -> usize {
+		let certificates = self.session.certificates();
+		self.unacknowledged.retain(|message| {
+			!message.digest.is_some_and(|digest| {
+				certificates.iter().any(|c| c.digest == digest)
+			})
+		});
+		let mut resent = 0;
+		let mut index = 0;
+		while let Some(message) = self.unacknowledged.get_mut(index) {
+			if message.due > now {
+				index += 1;
+				continue;
+			}
+			// a full queue is retried on the next run
+			if self.queue.push(message.message.clone()).is_err() {
+				break;
+			}
+			resent += 1;
+			message.attempts += 1;
+			match self.schedule.next_resend(
+				message.class,
+				message.attempts,
+				now,
+			) {
+				Some(due) => {
+					message.due = due;
+					index += 1;
+				},
+				None => {
+					self.unacknowledged.remove(index);
+				},
+			}
+		}
+		resent
+	}
+
+	/// process an acknowledgment, recording the dealing it certifies
+	fn on_ack(
+		&mut self,
+		ack: ReadyAck<E>,
+		report: &mut OffchainReport<E>,
+	) -> Result<(), AcssError> {
+		if let Some(certificate) = self.session.on_ack(ack)? {
+			report.certified.push(certificate.digest);
+		}
+		Ok(())
+	}
+
+	/// write the sequence number, the replay windows and the session to local
+	/// storage
+	fn checkpoint(&mut self) -> Result<(), AcssError> {
+		let mut checkpoint = Vec::new();
+		write(&self.header.sequence, &mut checkpoint)?;
+		write(&(self.windows.len() as u32), &mut checkpoint)?;
+		for (sender, window) in &self.windows {
+			write(&sender.0, &mut checkpoint)?;
+			window.write(&mut checkpoint);
+		}
+		checkpoint.extend_from_slice(&self.session.snapshot()?);
+		self.env.local_set(&self.storage_key, &checkpoint);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_ff::UniformRand;
+	use ark_std::{collections::BTreeMap, rand::SeedableRng};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	const KEY: &[u8] = b"acss/session";
+	const HEADER: MessageHeader =
+		MessageHeader { session: [1; 32], epoch: 0, round: 0, sequence: 0 };
+
+	#[derive(Default)]
+	struct MemoryEnv {
+		storage: BTreeMap<Vec<u8>, Vec<u8>>,
+		outbox: Vec<Vec<u8>>,
+		offline: bool,
+		clock: u64,
+	}
+
+	impl OffchainEnv for MemoryEnv {
+		fn local_get(&self, key: &[u8]) -> Option<Vec<u8>> {
+			self.storage.get(key).cloned()
+		}
+
+		fn local_set(&mut self, key: &[u8], value: &[u8]) {
+			self.storage.insert(key.to_vec(), value.to_vec());
+		}
+
+		fn send(&mut self, message: &[u8]) -> Result<(), AcssError> {
+			if self.offline {
+				return Err(AcssError::SendFailed);
+			}
+			self.outbox.push(message.to_vec());
+			Ok(())
+		}
+
+		fn timestamp(&self) -> u64 {
+			self.clock
+		}
+	}
+
+	fn secret(rng: &mut ChaCha20Rng) -> DoubleSecret<E> {
+		DoubleSecret(
+			<E as EngineBLS>::Scalar::rand(rng),
+			<E as EngineBLS>::Scalar::rand(rng),
+		)
+	}
+
+	#[test]
+	fn drivers_reshare_through_the_environment() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let mut drivers: Vec<OffchainDriver<E, MemoryEnv>> = members
+			.iter()
+			.map(|kp| {
+				let session =
+					AcssSession::new(Keypair(kp.clone()), &committee, 2)
+						.unwrap();
+				OffchainDriver::start(
+					MemoryEnv::default(),
+					KEY,
+					HEADER,
+					session,
+				)
+				.unwrap()
+			})
+			.collect();
+
+		// with a quorum of one, a dealer certifies its own dealing right away
+		for driver in drivers.iter_mut().take(2) {
+			let report = driver.deal(&secret(&mut rng), &mut rng).unwrap();
+			assert_eq!(report.rejected, 0);
+			assert_eq!(report.certified.len(), 1);
+		}
+		// deliver every message to the other members until none are left
+		let mut certified = 0;
+		loop {
+			let sent: Vec<(usize, Vec<u8>)> = drivers
+				.iter_mut()
+				.enumerate()
+				.flat_map(|(from, driver)| {
+					driver.env.outbox.drain(..).map(move |m| (from, m))
+				})
+				.collect();
+			if sent.is_empty() {
+				break;
+			}
+			for (to, driver) in drivers.iter_mut().enumerate() {
+				let inbox = sent
+					.iter()
+					.filter(|(from, _)| *from != to)
+					.map(|(_, message)| message);
+				let report = driver.run(inbox).unwrap();
+				assert_eq!(report.rejected, 0);
+				assert!(report.evidence.is_empty());
+				certified += report.certified.len();
+			}
+		}
+		assert_eq!(certified, 4);
+
+		// a restarted worker resumes from its checkpoint
+		let shares: Vec<_> = drivers
+			.into_iter()
+			.zip(members.iter())
+			.map(|(driver, kp)| {
+				let share = driver.session().my_share().unwrap().0;
+				let resumed = OffchainDriver::<E, _>::resume(
+					driver.env,
+					KEY,
+					HEADER,
+					Keypair(kp.clone()),
+				)
+				.unwrap()
+				.unwrap();
+				assert_eq!(resumed.session().my_share().unwrap().0, share);
+				assert_eq!(resumed.session().certificates().len(), 2);
+				share
+			})
+			.collect();
+		assert_eq!(shares.len(), 3);
+		assert!(OffchainDriver::<E, _>::resume(
+			MemoryEnv::default(),
+			KEY,
+			HEADER,
+			Keypair(members[0].clone())
+		)
+		.unwrap()
+		.is_none());
+	}
+
+	#[test]
+	fn drivers_skip_malformed_messages_and_queue_unsent_messages() {
+		let mut rng = ChaCha20Rng::seed_from_u64(1);
+		let members: Vec<KeypairVT<E>> =
+			(0..2).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let start = |kp: &KeypairVT<E>, env: MemoryEnv| {
+			let session =
+				AcssSession::new(Keypair(kp.clone()), &committee, 2).unwrap();
+			OffchainDriver::start(env, KEY, HEADER, session).unwrap()
+		};
+
+		let mut dealer = start(&members[0], MemoryEnv::default());
+		dealer.deal(&secret(&mut rng), &mut rng).unwrap();
+		let dealing = dealer.env.outbox[0].clone();
+		let ack = dealer.env.outbox[1].clone();
+		let open = |bytes: &[u8]| SignedMessage::<E>::decode(bytes).unwrap();
+		for message in [&dealing, &ack] {
+			let payload = open(message)
+				.open(&HEADER, &mut ReplayWindow::new())
+				.unwrap()
+				.to_vec();
+			let decoded = OffchainMessage::<E>::decode(&payload).unwrap();
+			assert_eq!(decoded.encode().unwrap(), payload);
+		}
+
+		let mut trailing = ack.clone();
+		trailing.push(0);
+		let report = dealer.run([&[2u8][..], &trailing, &ack[..10]]).unwrap();
+		assert_eq!(report.rejected, 3);
+
+		// a relay can neither move a message to another round nor send one
+		// on behalf of a member
+		let outsider = KeypairVT::<E>::generate(&mut rng);
+		let next_round = MessageHeader { round: 1, ..HEADER };
+		let forged = [
+			SignedMessage::sign(
+				next_round,
+				open(&dealing).payload,
+				&members[0],
+			),
+			SignedMessage::sign(HEADER, open(&dealing).payload, &outsider),
+			SignedMessage::sign(HEADER, open(&ack).payload, &members[1]),
+		]
+		.map(|message| message.unwrap().encode().unwrap());
+		let mut recipient = start(&members[1], MemoryEnv::default());
+		let report = recipient.run(&forged).unwrap();
+		assert_eq!(report.rejected, 3);
+		assert!(recipient.env.outbox.is_empty());
+		assert_eq!(recipient.run([&dealing]).unwrap().rejected, 0);
+		assert_eq!(recipient.env.outbox.len(), 1);
+
+		// nor deliver a message twice, even across a restart
+		assert_eq!(recipient.run([&ack]).unwrap().rejected, 0);
+		let mut recipient = OffchainDriver::<E, _>::resume(
+			recipient.env,
+			KEY,
+			HEADER,
+			Keypair(members[1].clone()),
+		)
+		.unwrap()
+		.unwrap();
+		// and its own acknowledgment keeps its number
+		assert_eq!(recipient.header.sequence, 1);
+		assert_eq!(recipient.run([&dealing, &ack]).unwrap().rejected, 2);
+
+		// an acknowledgment the network does not take is sent on a later run
+		let mut recipient = start(
+			&members[1],
+			MemoryEnv { offline: true, ..Default::default() },
+		);
+		let report = recipient.run([&dealing]).unwrap();
+		assert_eq!(report.queued, 1);
+		assert!(recipient.env.outbox.is_empty());
+		recipient.env.offline = false;
+		let report = recipient.run(Vec::<Vec<u8>>::new()).unwrap();
+		assert_eq!(report.queued, 0);
+		assert_eq!(recipient.env.outbox.len(), 1);
+
+		// the caller is told to stop dealing before the queue fills up
+		let mut dealer = start(
+			&members[0],
+			MemoryEnv { offline: true, ..Default::default() },
+		)
+		.with_queue_capacity(dealing.len() * 3);
+		let report = dealer.deal(&secret(&mut rng), &mut rng).unwrap();
+		assert_eq!(report.queued, 2);
+		assert_eq!(report.backpressure, Backpressure::Ready);
+		// the session rejects a second dealing from itself, but it is queued
+		let report = dealer.deal(&secret(&mut rng), &mut rng).unwrap();
+		assert_eq!(report.rejected, 1);
+		assert_eq!(report.queued, 3);
+		assert_eq!(report.backpressure, Backpressure::Pause);
+		assert_eq!(dealer.backpressure(), Backpressure::Pause);
+		assert_eq!(
+			dealer.deal(&secret(&mut rng), &mut rng).err(),
+			Some(AcssError::QueueFull)
+		);
+		dealer.env.offline = false;
+		let report = dealer.run(Vec::<Vec<u8>>::new()).unwrap();
+		assert_eq!(report.backpressure, Backpressure::Ready);
+		assert!(dealer.queue().is_empty());
+		assert_eq!(dealer.queue().bytes(), 0);
+	}
+
+	/// resends acknowledgments forever, at a fixed interval
+	struct Every(u64);
+
+	impl ResendSchedule for Every {
+		fn next_resend(
+			&self,
+			class: MessageClass,
+			_: u32,
+			now: u64,
+		) -> Option<u64> {
+			(class == MessageClass::Ack).then_some(now + self.0)
+		}
+	}
+
+	#[test]
+	fn drivers_resend_until_acknowledged() {
+		let policy = ResendPolicy::exponential(10, 3);
+		assert_eq!(policy.delay(1), Some(10));
+		assert_eq!(policy.delay(2), Some(20));
+		assert_eq!(policy.delay(3), None);
+		let policy =
+			ResendPolicy { interval: u64::MAX, factor: 3, max_attempts: 9 };
+		assert_eq!(policy.delay(5), Some(u64::MAX));
+
+		let mut rng = ChaCha20Rng::seed_from_u64(2);
+		let members: Vec<KeypairVT<E>> =
+			(0..4).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let session = |kp: &KeypairVT<E>| {
+			AcssSession::new(Keypair(kp.clone()), &committee, 2).unwrap()
+		};
+		let policies = ResendPolicies {
+			dealing: Some(ResendPolicy::exponential(10, 3)),
+			..Default::default()
+		};
+		let none = Vec::<Vec<u8>>::new;
+
+		// an unacknowledged dealing is resent with backoff, then given up on
+		let mut dealer = OffchainDriver::start(
+			MemoryEnv::default(),
+			KEY,
+			HEADER,
+			session(&members[0]),
+		)
+		.unwrap()
+		.with_resend(policies);
+		dealer.deal(&secret(&mut rng), &mut rng).unwrap();
+		let dealing = dealer.env.outbox[0].clone();
+		for (clock, resent) in [(5, 0), (10, 1), (29, 0), (30, 1), (100, 0)] {
+			dealer.env.clock = clock;
+			assert_eq!(dealer.run(none()).unwrap().resent, resent);
+		}
+		assert_eq!(dealer.env.outbox[2..], [dealing.clone(), dealing.clone()]);
+
+		// a certified dealing is not resent
+		let mut dealer = OffchainDriver::start(
+			MemoryEnv::default(),
+			KEY,
+			HEADER,
+			session(&members[0]),
+		)
+		.unwrap()
+		.with_resend(policies);
+		dealer.deal(&secret(&mut rng), &mut rng).unwrap();
+		let dealing = dealer.env.outbox[0].clone();
+		let acks: Vec<Vec<u8>> = members[1..3]
+			.iter()
+			.map(|kp| {
+				let mut member = OffchainDriver::start(
+					MemoryEnv::default(),
+					KEY,
+					HEADER,
+					session(kp),
+				)
+				.unwrap()
+				// integrators can schedule resends themselves
+				.with_resend(Every(5));
+				member.run([&dealing]).unwrap();
+				member.env.clock = 5;
+				assert_eq!(member.run(none()).unwrap().resent, 1);
+				member.env.outbox.swap_remove(0)
+			})
+			.collect();
+		assert_eq!(dealer.run(acks).unwrap().certified.len(), 1);
+		dealer.env.clock = 10;
+		assert_eq!(dealer.run(none()).unwrap().resent, 0);
+	}
+}
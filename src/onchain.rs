@@ -0,0 +1,367 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! On-chain Dealings
+//!
+//! The least a runtime needs to verify a dealing. A PoK only depends on its
+//! ciphertexts through their aggregate, so [`OnchainDealing`] keeps the
+//! aggregated ciphertext of each PoK in place of the ciphertexts themselves,
+//! which are delivered to the recipients off chain. Together with the
+//! recipients' keys and the share public keys this is enough to check every
+//! PoK and that the shares lie on a polynomial of degree t - 1, without any
+//! state.
+//!
+//! Layout: `version || session || t || n || entry_1 || ... || entry_n` where
+//! each entry is `recipient || pk.0 || pk.1 || s || t || z || commitment ||
+//! c1 || c2`, every point is compressed and every entry has the same length.
+
+use crate::{
+	error::AcssError,
+	keys::PublicKey as EncryptionKey,
+	params::ThresholdParams,
+	proofs::{hashed_el_gamal::Ciphertext, hashed_el_gamal_sigma::BatchPoK},
+	resharing::Resharing,
+	threshold::group_key,
+	transcript::{read, read_array, read_point, write},
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{vec, vec::Vec, Zero};
+use w3f_bls::{DoublePublicKey, EngineBLS, PublicKey};
+
+/// the version of the on-chain encoding
+pub const ONCHAIN_VERSION: u8 = 1;
+
+/// a recipient's part of an on-chain dealing
+pub struct OnchainEntry<E: EngineBLS> {
+	/// the recipient's public key
+	pub recipient: PublicKey<E>,
+	/// the public key of the recipient's share
+	pub share_pk: DoublePublicKey<E>,
+	/// the recipient's PoK, carrying the aggregate of its ciphertexts as its
+	/// only ciphertext
+	pub proof: BatchPoK<E::PublicKeyGroup>,
+}
+
+impl<E: EngineBLS> Clone for OnchainEntry<E> {
+	fn clone(&self) -> Self {
+		Self {
+			recipient: PublicKey(self.recipient.0),
+			share_pk: DoublePublicKey(self.share_pk.0, self.share_pk.1),
+			proof: self.proof.clone(),
+		}
+	}
+}
+
+/// the data a runtime verifies a dealing with
+pub struct OnchainDealing<E: EngineBLS> {
+	/// the session the dealing belongs to, e.g. the digest of its transcript
+	pub session: [u8; 32],
+	/// the threshold of the dealing
+	pub threshold: u8,
+	/// one entry per recipient, in committee order
+	pub entries: Vec<OnchainEntry<E>>,
+}
+
+impl<E: EngineBLS> Clone for OnchainDealing<E> {
+	fn clone(&self) -> Self {
+		Self {
+			session: self.session,
+			threshold: self.threshold,
+			entries: self.entries.clone(),
+		}
+	}
+}
+
+impl<E: EngineBLS> OnchainDealing<E> {
+	/// the on-chain form of a resharing
+	/// fails with `InvalidParams` if `params` do not match the resharing
+	///
+	/// * `session`: The session the resharing belongs to
+	/// * `resharing`: The resharing
+	/// * `params`: The threshold parameters of the resharing
+	pub fn from_resharing(
+		session: [u8; 32],
+		resharing: &Resharing<E>,
+		params: ThresholdParams,
+	) -> Result<Self, AcssError> {
+		params.check_len(resharing.len())?;
+		let entries = resharing
+			.iter()
+			.map(|(recipient, share_pk, pok)| {
+				let aggregate = Ciphertext::aggregate(pok.ciphertexts.iter());
+				OnchainEntry {
+					recipient: PublicKey(recipient.0),
+					share_pk: DoublePublicKey(share_pk.0, share_pk.1),
+					proof: BatchPoK {
+						ciphertexts: vec![aggregate],
+						..pok.clone()
+					},
+				}
+			})
+			.collect();
+		Ok(Self { session, threshold: params.t(), entries })
+	}
+
+	/// the on-chain encoding
+	/// fails with `InvalidCommittee` if there are more than 255 entries, and
+	/// with `InvalidCiphertext` if a PoK does not carry exactly one ciphertext
+	pub fn encode(&self) -> Result<Vec<u8>, AcssError> {
+		let n: u8 = self
+			.entries
+			.len()
+			.try_into()
+			.map_err(|_| AcssError::InvalidCommittee)?;
+		let mut out = Vec::with_capacity(35 + n as usize * entry_len::<E>());
+		out.push(ONCHAIN_VERSION);
+		out.extend_from_slice(&self.session);
+		out.extend_from_slice(&[self.threshold, n]);
+		for entry in &self.entries {
+			let [aggregate] = &entry.proof.ciphertexts[..] else {
+				return Err(AcssError::InvalidCiphertext);
+			};
+			write(&entry.recipient.0, &mut out)?;
+			write(&entry.share_pk.0, &mut out)?;
+			write(&entry.share_pk.1, &mut out)?;
+			write(&entry.proof.s, &mut out)?;
+			write(&entry.proof.t, &mut out)?;
+			write(&entry.proof.z, &mut out)?;
+			write(&entry.proof.commitment, &mut out)?;
+			write(&aggregate.c1, &mut out)?;
+			out.extend_from_slice(&aggregate.c2);
+		}
+		Ok(out)
+	}
+
+	/// decode an on-chain dealing without verifying it
+	/// fails with `InvalidTranscript` if the bytes are not exactly an
+	/// encoding of the current version with valid points
+	///
+	/// * `bytes`: The on-chain encoding
+	pub fn decode(mut bytes: &[u8]) -> Result<Self, AcssError> {
+		let reader = &mut bytes;
+		let [version] = read_array::<1>(reader)?;
+		if version != ONCHAIN_VERSION {
+			return Err(AcssError::InvalidTranscript);
+		}
+		let session = read_array::<32>(reader)?;
+		let [threshold, n] = read_array::<2>(reader)?;
+		if reader.len() != n as usize * entry_len::<E>() {
+			return Err(AcssError::InvalidTranscript);
+		}
+
+		let mut entries = Vec::with_capacity(n as usize);
+		for _ in 0..n {
+			let recipient = PublicKey(read_point(reader)?);
+			let share_pk =
+				DoublePublicKey(read_point(reader)?, read_point(reader)?);
+			let (s, t, z, commitment) =
+				(read(reader)?, read(reader)?, read(reader)?, read(reader)?);
+			let aggregate = Ciphertext {
+				c1: read_point(reader)?,
+				c2: read_array::<32>(reader)?,
+				tag: [0u8; 16],
+			};
+			let proof =
+				BatchPoK { s, t, z, commitment, ciphertexts: vec![aggregate] };
+			entries.push(OnchainEntry { recipient, share_pk, proof });
+		}
+		Ok(Self { session, threshold, entries })
+	}
+
+	/// verify every PoK against its recipient's key and that the share
+	/// public keys lie on a polynomial of degree t - 1
+	/// outputs the encryption key of the committee
+	/// fails with `InvalidParams` if the threshold does not fit the number of
+	/// entries, with `InvalidProof` if a PoK does not verify and with
+	/// `InvalidCommitment` if the shares do not fit the threshold
+	pub fn verify(
+		&self,
+	) -> Result<EncryptionKey<E::PublicKeyGroup>, AcssError> {
+		let params =
+			ThresholdParams::for_committee(&self.entries, self.threshold)?;
+		if !self
+			.entries
+			.iter()
+			.all(|entry| entry.proof.verify(EncryptionKey(entry.recipient.0)))
+		{
+			return Err(AcssError::InvalidProof);
+		}
+		let (recipients, dealings): (Vec<_>, Vec<_>) = self
+			.entries
+			.iter()
+			.cloned()
+			.map(|entry| (entry.recipient, (entry.share_pk, entry.proof)))
+			.unzip();
+		group_key(&Resharing::from_dealings(&recipients, dealings)?, params)
+	}
+}
+
+/// decode and verify an on-chain dealing, with no other input
+/// outputs the dealing, whose session and recipients the caller matches
+/// against its own state
+/// fails as `OnchainDealing::decode` and `OnchainDealing::verify` do
+///
+/// * `bytes`: The on-chain encoding
+pub fn verify_onchain_payload<E: EngineBLS>(
+	bytes: &[u8],
+) -> Result<OnchainDealing<E>, AcssError> {
+	let dealing = OnchainDealing::decode(bytes)?;
+	dealing.verify()?;
+	Ok(dealing)
+}
+
+/// the length of an encoded entry
+fn entry_len<E: EngineBLS>() -> usize {
+	let point = E::PublicKeyGroup::zero().compressed_size();
+	E::SignatureGroup::zero().compressed_size() +
+		6 * point +
+		E::Scalar::zero().compressed_size() +
+		32
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::acss::{DoubleSecret, Keypair};
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	fn dealing(rng: &mut ChaCha20Rng) -> (Resharing<E>, OnchainDealing<E>) {
+		let committee: Vec<PublicKey<E>> = (0..4)
+			.map(|_| *Keypair(KeypairVT::<E>::generate(&mut *rng)).public())
+			.collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(rng),
+			<E as EngineBLS>::Scalar::rand(rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 3).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut *rng).unwrap();
+		let onchain =
+			OnchainDealing::from_resharing([7; 32], &resharing, params)
+				.unwrap();
+		(resharing, onchain)
+	}
+
+	#[test]
+	fn payloads_round_trip_and_verify() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (resharing, onchain) = dealing(&mut rng);
+		let bytes = onchain.encode().unwrap();
+		assert_eq!(bytes.len(), 35 + 4 * entry_len::<E>());
+
+		let decoded = verify_onchain_payload::<E>(&bytes).unwrap();
+		assert_eq!(decoded.session, [7; 32]);
+		assert_eq!(decoded.threshold, 3);
+		assert_eq!(decoded.encode().unwrap(), bytes);
+		let params = ThresholdParams::new(4, 3).unwrap();
+		assert_eq!(decoded.verify(), group_key(&resharing, params));
+	}
+
+	#[test]
+	fn invalid_payloads_are_rejected() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (_, onchain) = dealing(&mut rng);
+
+		let mut forged = onchain.clone();
+		forged.entries[1].proof.z += <E as EngineBLS>::Scalar::from(1u64);
+		let bytes = forged.encode().unwrap();
+		assert_eq!(
+			verify_onchain_payload::<E>(&bytes).err(),
+			Some(AcssError::InvalidProof)
+		);
+
+		// shares of degree 2 claimed to be of degree 1
+		let mut forged = onchain.clone();
+		forged.threshold = 2;
+		let bytes = forged.encode().unwrap();
+		assert_eq!(
+			verify_onchain_payload::<E>(&bytes).err(),
+			Some(AcssError::InvalidCommitment)
+		);
+
+		let mut forged = onchain.clone();
+		forged.threshold = 5;
+		let bytes = forged.encode().unwrap();
+		assert_eq!(
+			verify_onchain_payload::<E>(&bytes).err(),
+			Some(AcssError::InvalidParams)
+		);
+
+		let bytes = onchain.encode().unwrap();
+		for bad in
+			[&bytes[..bytes.len() - 1], &[bytes.clone(), vec![0]].concat()]
+		{
+			assert_eq!(
+				verify_onchain_payload::<E>(bad).err(),
+				Some(AcssError::InvalidTranscript)
+			);
+		}
+		let mut version = bytes.clone();
+		version[0] = ONCHAIN_VERSION + 1;
+		assert_eq!(
+			OnchainDealing::<E>::decode(&version).err(),
+			Some(AcssError::InvalidTranscript)
+		);
+	}
+}
+
+/// checks a dealing made by a participant build, so that a `verify` build,
+/// which cannot deal, still runs a whole verification
+#[cfg(test)]
+mod fixture_tests {
+	use super::*;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	/// a dealing to 3 recipients with threshold 2, in session [9; 32]
+	const DEALING: &str = include_str!("../fixtures/onchain_dealing.hex");
+	/// the committee's encryption key, compressed
+	const GROUP_KEY: &str = "3c90bd68c7ef8c5c5d29bb557713dbc93a52add083a2f99f4f9b4b917abe55cddcb1516f1e69c52a4809d858ea301600c78be4f9d40cb602385fe93d5c73831e48811bacb51308d161cc77340eadf1a69a461d7438ba85cdb0e40a52b8308900";
+
+	fn fixture() -> Vec<u8> {
+		let hex: Vec<u8> =
+			DEALING.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+		hex::decode(hex).unwrap()
+	}
+
+	#[test]
+	fn fixture_dealings_verify() {
+		let bytes = fixture();
+		let dealing = verify_onchain_payload::<E>(&bytes).unwrap();
+		assert_eq!(dealing.session, [9; 32]);
+		assert_eq!((dealing.threshold, dealing.entries.len()), (2, 3));
+		assert_eq!(dealing.encode().unwrap(), bytes);
+
+		let mut key = Vec::new();
+		dealing.verify().unwrap().0.serialize_compressed(&mut key).unwrap();
+		assert_eq!(hex::encode(key), GROUP_KEY);
+	}
+
+	#[test]
+	fn tampered_fixture_dealings_are_rejected() {
+		// the last byte is in the aggregated c2 of the last entry
+		let mut bytes = fixture();
+		*bytes.last_mut().unwrap() ^= 1;
+		assert_eq!(
+			verify_onchain_payload::<E>(&bytes).err(),
+			Some(AcssError::InvalidProof)
+		);
+	}
+}
@@ -0,0 +1,267 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Threshold Parameters
+//!
+//! A dealing splits a secret into `n` shares such that any `t` of them
+//! recover it. [`ThresholdParams`] can only be built when `0 < t <= n`, so
+//! `reshare` and `recover` never see a threshold that cannot be met.
+//!
+//! Note that `t` is the number of shares required to recover, not the number
+//! of faulty members tolerated: a committee tolerating f faults uses
+//! t = f + 1 (see [`ThresholdParams::for_byzantine`]).
+
+use crate::{error::AcssError, keys::ShareIndex};
+
+/// validated (t, n) threshold parameters, where 0 < t <= n
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ThresholdParams {
+	n: u8,
+	t: u8,
+}
+
+impl ThresholdParams {
+	/// build parameters for `n` shares with threshold `t`
+	/// fails with `InvalidParams` if t = 0 or t > n
+	///
+	/// * `n`: The number of shares
+	/// * `t`: The number of shares required to recover
+	pub fn new(n: u8, t: u8) -> Result<Self, AcssError> {
+		if t == 0 || t > n {
+			return Err(AcssError::InvalidParams);
+		}
+		Ok(Self { n, t })
+	}
+
+	/// build parameters for a dealing to `committee` with threshold `t`
+	/// fails with `InvalidCommittee` if the committee is empty or has more
+	/// than 255 members, and with `InvalidParams` if t = 0 or t > n
+	///
+	/// * `committee`: The committee the shares are dealt to
+	/// * `t`: The number of shares required to recover
+	pub fn for_committee<T>(committee: &[T], t: u8) -> Result<Self, AcssError> {
+		let n: u8 = committee
+			.len()
+			.try_into()
+			.ok()
+			.filter(|n| *n > 0)
+			.ok_or(AcssError::InvalidCommittee)?;
+		Self::new(n, t)
+	}
+
+	/// build parameters for `n` shares that tolerate the most Byzantine
+	/// members, f = (n - 1) / 3 rounded down: t = f + 1, so the f faulty
+	/// members learn nothing and any f + 1 shares recover
+	/// fails with `InvalidParams` if n = 0
+	///
+	/// * `n`: The number of shares
+	pub fn for_byzantine(n: u8) -> Result<Self, AcssError> {
+		Self::new(n, max_byzantine_faults(n) + 1)
+	}
+
+	/// build parameters for `n` shares with an honest majority, i.e.
+	/// t = (n - 1) / 2 rounded down, plus one
+	/// fails with `InvalidParams` if n = 0
+	///
+	/// * `n`: The number of shares
+	pub fn for_honest_majority(n: u8) -> Result<Self, AcssError> {
+		Self::new(n, n.saturating_sub(1) / 2 + 1)
+	}
+
+	/// build high threshold parameters for `n` shares that tolerate
+	/// f = (n - 1) / 3 Byzantine members: t = n - f, so recovery needs every
+	/// honest member but the f faulty members cannot block it
+	/// fails with `InvalidParams` if n = 0
+	///
+	/// * `n`: The number of shares
+	pub fn for_high_threshold(n: u8) -> Result<Self, AcssError> {
+		Self::new(n, n - max_byzantine_faults(n))
+	}
+
+	/// check that the parameters tolerate `f` Byzantine members: n >= 3f + 1,
+	/// f colluding members learn nothing (t > f) and f withheld shares do not
+	/// block recovery (n - t >= f)
+	/// fails with `InvalidParams` otherwise
+	///
+	/// * `f`: The number of Byzantine members
+	pub fn check_byzantine(&self, f: u8) -> Result<(), AcssError> {
+		if 3 * f as u16 + 1 > self.n as u16 ||
+			self.privacy() < f ||
+			self.liveness() < f
+		{
+			return Err(AcssError::InvalidParams);
+		}
+		Ok(())
+	}
+
+	/// the most shares that can be combined without learning anything, t - 1
+	pub fn privacy(&self) -> u8 {
+		self.t - 1
+	}
+
+	/// the most shares that can be missing without blocking recovery, n - t
+	pub fn liveness(&self) -> u8 {
+		self.n - self.t
+	}
+
+	/// the number of shares
+	pub fn n(&self) -> u8 {
+		self.n
+	}
+
+	/// the number of shares required to recover
+	pub fn t(&self) -> u8 {
+		self.t
+	}
+
+	/// the share indices 1..=n
+	pub fn indices(&self) -> impl Iterator<Item = ShareIndex> {
+		(1..=self.n).filter_map(ShareIndex::new)
+	}
+
+	/// check that share indices are usable for interpolation, i.e. that each
+	/// lies in 1..=n and none is repeated
+	/// fails with `ShareIndexOutOfRange` or `DuplicateShareIndex` otherwise
+	///
+	/// * `indices`: The share indices
+	pub fn check_indices<I: IntoIterator<Item = ShareIndex>>(
+		&self,
+		indices: I,
+	) -> Result<(), AcssError> {
+		let mut seen = [false; 256];
+		for index in indices {
+			if index.get() > self.n {
+				return Err(AcssError::ShareIndexOutOfRange);
+			}
+			if core::mem::replace(&mut seen[index.get() as usize], true) {
+				return Err(AcssError::DuplicateShareIndex);
+			}
+		}
+		Ok(())
+	}
+
+	/// check that `len` items (committee members or poks) match n
+	#[cfg(feature = "verify")]
+	pub(crate) fn check_len(&self, len: usize) -> Result<(), AcssError> {
+		if len != self.n as usize {
+			return Err(AcssError::InvalidParams);
+		}
+		Ok(())
+	}
+}
+
+/// the most Byzantine members a committee of `n` tolerates, (n - 1) / 3
+/// rounded down
+pub fn max_byzantine_faults(n: u8) -> u8 {
+	n.saturating_sub(1) / 3
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn params_reject_invalid_thresholds() {
+		assert_eq!(ThresholdParams::new(3, 0), Err(AcssError::InvalidParams));
+		assert_eq!(ThresholdParams::new(3, 4), Err(AcssError::InvalidParams));
+		assert_eq!(ThresholdParams::new(0, 0), Err(AcssError::InvalidParams));
+
+		let params = ThresholdParams::new(3, 3).unwrap();
+		assert_eq!((params.n(), params.t()), (3, 3));
+	}
+
+	#[cfg(feature = "verify")]
+	#[test]
+	fn params_reject_mismatched_lengths() {
+		let params = ThresholdParams::new(3, 3).unwrap();
+		assert!(params.check_len(3).is_ok());
+		assert_eq!(params.check_len(2), Err(AcssError::InvalidParams));
+	}
+
+	fn indices<const N: usize>(indices: [u8; N]) -> [ShareIndex; N] {
+		indices.map(|i| ShareIndex::new(i).unwrap())
+	}
+
+	#[test]
+	fn params_reject_bad_share_indices() {
+		let params = ThresholdParams::new(3, 2).unwrap();
+		assert!(params.check_indices(indices([3, 1])).is_ok());
+		assert!(params.check_indices(params.indices()).is_ok());
+		assert!(params.check_indices([]).is_ok());
+		assert_eq!(
+			params.check_indices(indices([1, 4])),
+			Err(AcssError::ShareIndexOutOfRange)
+		);
+		assert_eq!(
+			params.check_indices(indices([2, 1, 2])),
+			Err(AcssError::DuplicateShareIndex)
+		);
+		assert_eq!(params.indices().map(ShareIndex::get).last(), Some(3));
+	}
+
+	#[test]
+	fn params_are_bounded_by_the_committee() {
+		assert_eq!(
+			ThresholdParams::for_committee::<u8>(&[], 1),
+			Err(AcssError::InvalidCommittee)
+		);
+		assert_eq!(
+			ThresholdParams::for_committee(&[0u8; 256], 1),
+			Err(AcssError::InvalidCommittee)
+		);
+		assert_eq!(
+			ThresholdParams::for_committee(&[0u8; 3], 4),
+			Err(AcssError::InvalidParams)
+		);
+		assert_eq!(
+			ThresholdParams::for_committee(&[0u8; 255], 2).map(|p| p.n()),
+			Ok(255)
+		);
+	}
+
+	#[test]
+	fn params_for_fault_models() {
+		for (n, byzantine, majority, high) in [
+			(1, 1, 1, 1),
+			(3, 1, 2, 3),
+			(4, 2, 2, 3),
+			(7, 3, 4, 5),
+			(10, 4, 5, 7),
+		] {
+			let t = |params: Result<ThresholdParams, AcssError>| {
+				params.unwrap().t()
+			};
+			assert_eq!(t(ThresholdParams::for_byzantine(n)), byzantine);
+			assert_eq!(t(ThresholdParams::for_honest_majority(n)), majority);
+			assert_eq!(t(ThresholdParams::for_high_threshold(n)), high);
+		}
+		assert_eq!(max_byzantine_faults(255), 84);
+		assert_eq!(
+			ThresholdParams::for_byzantine(0),
+			Err(AcssError::InvalidParams)
+		);
+
+		let params = ThresholdParams::for_byzantine(4).unwrap();
+		assert_eq!((params.privacy(), params.liveness()), (1, 2));
+		assert!(params.check_byzantine(1).is_ok());
+		assert_eq!(params.check_byzantine(2), Err(AcssError::InvalidParams));
+		// three members cannot tolerate a Byzantine one, whatever t is
+		let params = ThresholdParams::new(3, 2).unwrap();
+		assert_eq!(params.check_byzantine(1), Err(AcssError::InvalidParams));
+		// t = f lets the faulty members recover the secret
+		let params = ThresholdParams::new(4, 1).unwrap();
+		assert_eq!(params.check_byzantine(1), Err(AcssError::InvalidParams));
+	}
+}
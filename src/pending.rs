@@ -0,0 +1,352 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Deferred Verification
+//!
+//! High throughput nodes can accept proofs optimistically and verify them
+//! later in a batch. Each submitted proof gets a handle which resolves to
+//! accepted or rejected once `verify_pending` has run. A batch is checked
+//! with a single multi-scalar multiplication, and is only split up (to find
+//! the culprits) when it fails.
+
+use crate::{
+	keys::PublicKey,
+	msm::{DefaultMsm, MsmBackend},
+	proofs::{
+		challenge::{
+			bound_challenge, Binding, ChallengeHash, DefaultChallenge,
+		},
+		hashed_el_gamal_sigma::BatchPoK,
+	},
+	rng::SecureRng,
+};
+use ark_ec::CurveGroup;
+use ark_std::{collections::BTreeMap, marker::PhantomData, vec::Vec};
+
+/// a handle to a proof submitted for deferred verification
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VerificationHandle(u64);
+
+/// the verification status of a submitted proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+	/// the proof has not been verified yet
+	Pending,
+	/// the proof is valid
+	Accepted,
+	/// the proof is invalid
+	Rejected,
+	/// the handle is unknown (or its verdict was already taken)
+	Unknown,
+}
+
+/// a proof awaiting verification, with the binding it was proven under
+type Submission<C> =
+	(VerificationHandle, PublicKey<C>, BatchPoK<C>, Option<Binding>);
+
+/// the number of verdicts kept when none is specified
+pub const DEFAULT_VERDICT_LIMIT: usize = 1 << 16;
+
+/// a queue of proofs awaiting verification, batch verified with the MSM
+/// backend `M`
+///
+/// verdicts are kept until they are drained with `take`, up to a limit past
+/// which the verdicts of the oldest submissions are forgotten (and report
+/// `Unknown`), so callers should take every verdict they need
+pub struct PendingVerifier<C: CurveGroup, M: MsmBackend<C> = DefaultMsm> {
+	next: u64,
+	pending: Vec<Submission<C>>,
+	verdicts: BTreeMap<VerificationHandle, bool>,
+	limit: usize,
+	_backend: PhantomData<M>,
+}
+
+impl<C: CurveGroup, M: MsmBackend<C>> Default for PendingVerifier<C, M> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<C: CurveGroup, M: MsmBackend<C>> PendingVerifier<C, M> {
+	/// create an empty queue keeping up to `DEFAULT_VERDICT_LIMIT` verdicts
+	pub fn new() -> Self {
+		Self::with_verdict_limit(DEFAULT_VERDICT_LIMIT)
+	}
+
+	/// create an empty queue keeping up to `limit` verdicts that have not
+	/// been taken
+	///
+	/// * `limit`: The number of verdicts kept before the oldest are forgotten
+	pub fn with_verdict_limit(limit: usize) -> Self {
+		Self {
+			next: 0,
+			pending: Vec::new(),
+			verdicts: BTreeMap::new(),
+			limit,
+			_backend: PhantomData,
+		}
+	}
+
+	/// accept a proof optimistically, deferring its verification
+	///
+	/// * `pk`: The public key the proof should verify against
+	/// * `pok`: The proof
+	pub fn submit(
+		&mut self,
+		pk: PublicKey<C>,
+		pok: BatchPoK<C>,
+	) -> VerificationHandle {
+		self.enqueue(pk, pok, None)
+	}
+
+	/// accept a proof made with `BatchPoK::prove_bound` optimistically,
+	/// deferring its verification
+	/// the proof is rejected if it is bound to other data
+	///
+	/// * `pk`: The public key the proof should verify against
+	/// * `pok`: The proof
+	/// * `binding`: The digest of the associated data the proof is bound to
+	pub fn submit_bound(
+		&mut self,
+		pk: PublicKey<C>,
+		pok: BatchPoK<C>,
+		binding: Binding,
+	) -> VerificationHandle {
+		self.enqueue(pk, pok, Some(binding))
+	}
+
+	fn enqueue(
+		&mut self,
+		pk: PublicKey<C>,
+		pok: BatchPoK<C>,
+		binding: Option<Binding>,
+	) -> VerificationHandle {
+		let handle = VerificationHandle(self.next);
+		self.next += 1;
+		self.pending.push((handle, pk, pok, binding));
+		handle
+	}
+
+	/// the number of proofs awaiting verification
+	pub fn pending_len(&self) -> usize {
+		self.pending.len()
+	}
+
+	/// verify every pending proof as a batch
+	/// outputs the number of proofs that were rejected
+	///
+	/// * `rng`: A CSPRNG used to sample the batching coefficients
+	pub fn verify_pending<R: SecureRng>(&mut self, mut rng: R) -> usize {
+		let pending = core::mem::take(&mut self.pending);
+		let mut rejected = 0;
+		self.resolve(&pending, &mut rng, &mut rejected);
+		// handles are issued in order, so the first are the oldest
+		while self.verdicts.len() > self.limit {
+			self.verdicts.pop_first();
+		}
+		rejected
+	}
+
+	/// the current status of a submitted proof
+	pub fn status(&self, handle: VerificationHandle) -> Verdict {
+		match self.verdicts.get(&handle) {
+			Some(true) => Verdict::Accepted,
+			Some(false) => Verdict::Rejected,
+			None if self.pending.iter().any(|p| p.0 == handle) =>
+				Verdict::Pending,
+			None => Verdict::Unknown,
+		}
+	}
+
+	/// take the verdict of a resolved proof, forgetting it afterwards
+	/// outputs `Pending` (and keeps the proof queued) if it is not yet verified
+	pub fn take(&mut self, handle: VerificationHandle) -> Verdict {
+		match self.verdicts.remove(&handle) {
+			Some(true) => Verdict::Accepted,
+			Some(false) => Verdict::Rejected,
+			None => self.status(handle),
+		}
+	}
+
+	/// batch verify a set of proofs, bisecting on failure to find the invalid
+	/// ones
+	fn resolve<R: SecureRng>(
+		&mut self,
+		batch: &[Submission<C>],
+		rng: &mut R,
+		rejected: &mut usize,
+	) {
+		let valid = match batch {
+			[] => return,
+			[(_, pk, pok, None)] => pok.verify(*pk),
+			[(_, pk, pok, Some(binding))] => pok.verify_bound(*pk, binding),
+			_ => {
+				let proofs: Vec<(PublicKey<C>, &BatchPoK<C>)> =
+					batch.iter().map(|(_, pk, pok, _)| (*pk, pok)).collect();
+				BatchPoK::verify_batch_with_challenge::<M, _>(
+					&proofs,
+					&mut *rng,
+					|i, s, t, ct| match &batch[i].3 {
+						Some(binding) => bound_challenge(binding, s, t, ct),
+						None => DefaultChallenge::challenge(s, t, ct),
+					},
+				)
+			},
+		};
+
+		if valid {
+			batch.iter().for_each(|(handle, ..)| {
+				self.verdicts.insert(*handle, true);
+			});
+		} else if let [(handle, ..)] = batch {
+			self.verdicts.insert(*handle, false);
+			*rejected += 1;
+		} else {
+			let (left, right) = batch.split_at(batch.len() / 2);
+			self.resolve(left, rng, rejected);
+			self.resolve(right, rng, rejected);
+		}
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{proofs::hashed_el_gamal::Ephemeral, rng::test_rng};
+	use ark_ec::Group;
+	use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+	use ark_ff::UniformRand;
+	use ark_std::{ops::Mul, rand::SeedableRng};
+	use rand_chacha::ChaCha20Rng;
+
+	type Scalar = <JubJub as Group>::ScalarField;
+
+	fn proof_for(h: PublicKey<JubJub>) -> BatchPoK<JubJub> {
+		let m = Scalar::rand(&mut test_rng());
+		BatchPoK::prove(&[m, m], h, test_rng()).unwrap()
+	}
+
+	#[test]
+	fn pending_proofs_resolve_after_verification() {
+		let mut rng = test_rng();
+		let g = JubJub::generator();
+		let keys: Vec<PublicKey<JubJub>> =
+			(0..5).map(|_| PublicKey(g.mul(Scalar::rand(&mut rng)))).collect();
+
+		let mut verifier = PendingVerifier::<JubJub>::new();
+		let handles: Vec<VerificationHandle> = keys
+			.iter()
+			.enumerate()
+			.map(|(i, h)| {
+				// the proofs at index 1 and 3 are checked against a wrong key
+				let pk = if i % 2 == 1 { PublicKey(g) } else { *h };
+				verifier.submit(pk, proof_for(*h))
+			})
+			.collect();
+
+		assert_eq!(verifier.pending_len(), 5);
+		assert!(handles
+			.iter()
+			.all(|h| verifier.status(*h) == Verdict::Pending));
+
+		assert_eq!(verifier.verify_pending(ChaCha20Rng::seed_from_u64(0)), 2);
+		assert_eq!(verifier.pending_len(), 0);
+		for (i, handle) in handles.iter().enumerate() {
+			let expected =
+				if i % 2 == 1 { Verdict::Rejected } else { Verdict::Accepted };
+			assert_eq!(verifier.status(*handle), expected);
+		}
+
+		assert_eq!(verifier.take(handles[0]), Verdict::Accepted);
+		assert_eq!(verifier.take(handles[0]), Verdict::Unknown);
+	}
+
+	#[test]
+	fn take_keeps_unverified_proofs_queued() {
+		let h =
+			PublicKey(JubJub::generator().mul(Scalar::rand(&mut test_rng())));
+		let mut verifier = PendingVerifier::<JubJub>::new();
+		let handle = verifier.submit(h, proof_for(h));
+
+		assert_eq!(verifier.take(handle), Verdict::Pending);
+		assert_eq!(verifier.verify_pending(ChaCha20Rng::seed_from_u64(0)), 0);
+		assert_eq!(verifier.take(handle), Verdict::Accepted);
+	}
+
+	#[test]
+	fn the_oldest_verdicts_are_forgotten_past_the_limit() {
+		let h =
+			PublicKey(JubJub::generator().mul(Scalar::rand(&mut test_rng())));
+		let mut verifier = PendingVerifier::<JubJub>::with_verdict_limit(2);
+		let handles: Vec<VerificationHandle> =
+			(0..3).map(|_| verifier.submit(h, proof_for(h))).collect();
+
+		assert_eq!(verifier.verify_pending(ChaCha20Rng::seed_from_u64(0)), 0);
+		assert_eq!(verifier.status(handles[0]), Verdict::Unknown);
+		assert_eq!(verifier.take(handles[1]), Verdict::Accepted);
+
+		// taken verdicts make room for newer ones
+		let handle = verifier.submit(h, proof_for(h));
+		assert_eq!(verifier.verify_pending(ChaCha20Rng::seed_from_u64(0)), 0);
+		assert_eq!(verifier.status(handles[2]), Verdict::Accepted);
+		assert_eq!(verifier.status(handle), Verdict::Accepted);
+	}
+
+	#[test]
+	fn bound_proofs_are_checked_against_their_binding() {
+		let mut rng = test_rng();
+		let binding = Binding::of(b"session");
+		let mut verifier = PendingVerifier::<JubJub>::new();
+		let handles: Vec<VerificationHandle> = (0..4)
+			.map(|i| {
+				let h =
+					PublicKey(JubJub::generator().mul(Scalar::rand(&mut rng)));
+				if i == 3 {
+					return verifier.submit(h, proof_for(h));
+				}
+				let m = Scalar::rand(&mut rng);
+				let ephemerals = [Ephemeral::new(&mut rng)];
+				let pok = BatchPoK::prove_bound(
+					&[m],
+					h,
+					&ephemerals,
+					&binding,
+					&mut rng,
+				)
+				.unwrap();
+				// the proof at index 1 is submitted under another binding
+				let submitted = if i == 1 {
+					Binding::of(b"another session")
+				} else {
+					binding
+				};
+				verifier.submit_bound(h, pok, submitted)
+			})
+			.collect();
+
+		assert_eq!(verifier.verify_pending(ChaCha20Rng::seed_from_u64(0)), 1);
+		let verdicts: Vec<Verdict> =
+			handles.iter().map(|h| verifier.take(*h)).collect();
+		assert_eq!(
+			verdicts,
+			[
+				Verdict::Accepted,
+				Verdict::Rejected,
+				Verdict::Accepted,
+				Verdict::Accepted
+			]
+		);
+	}
+}
@@ -0,0 +1,63 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Prelude
+//!
+//! The types and traits most downstream code needs, so that a single
+//! `use acss::prelude::*;` keeps working as modules move between releases.
+//!
+//! The encryption key newtype is exported as `EncryptionKey`, leaving
+//! `PublicKey` free for the BLS public keys that identify committee members.
+
+#[cfg(feature = "foreign")]
+pub use crate::foreign::{Ed25519Keypair, X25519Keypair};
+#[cfg(feature = "participant")]
+pub use crate::{
+	acss::{DealingMode, DealtShare, DoubleSecret, HighThresholdACSS, Keypair},
+	builder::ReshareBuilder,
+	custody::{Custodian, DeviceKind, DevicePiece},
+	export::ExportedKey,
+	keys::SecretKey,
+	roles::{Dealer, Participant},
+	session::{AcssSession, PhaseTimeouts, SessionOutput},
+	share::{ExpiryPolicy, Share},
+	vault::{ShareVault, VaultEntry},
+};
+#[cfg(feature = "verify")]
+pub use crate::{
+	certificate::{OutputCertificate, ReadyAck},
+	commitment::{Feldman, Pedersen, PolynomialCommitment},
+	context::VerificationContext,
+	manifest::CommitteeManifest,
+	messages::{MessageHeader, SignedMessage},
+	observer::{Observer, Phase},
+	progress::{CancellationToken, Monitor},
+	resharing::Resharing,
+	ring::{Ring, RingSignature},
+	transcript::{BlindedDealing, SignedDealing},
+};
+pub use crate::{
+	error::{AcssError, ProofError, SerializationError},
+	introspect::{SchemeId, VerifyCost},
+	keys::{CommitteeKey, PublicKey as EncryptionKey, ShareIndex},
+	msm::MsmBackend,
+	params::ThresholdParams,
+	proofs::{
+		challenge::{Binding, ChallengeHash, TranscriptBinder},
+		hashed_el_gamal::{Ciphertext, HashedElGamal},
+		hashed_el_gamal_sigma::BatchPoK,
+	},
+	rng::SecureRng,
+};
@@ -0,0 +1,214 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Progress and Cancellation
+//!
+//! Dealing to a large committee or batch verifying many proofs can take
+//! seconds. A [`Monitor`] reports the progress of such an operation to a
+//! callback and checks a [`CancellationToken`] between steps (recipients when
+//! dealing, chunks of proofs when verifying), so that interactive tools and
+//! services can show progress and abort cleanly. A cancelled operation fails
+//! with `Cancelled`, and any secrets it held are wiped as usual.
+//!
+//! Dealings are monitored through [`ReshareBuilder::monitor`].
+//!
+//! [`ReshareBuilder::monitor`]: crate::builder::ReshareBuilder::monitor
+
+use crate::{
+	error::AcssError, keys::PublicKey, proofs::hashed_el_gamal_sigma::BatchPoK,
+	rng::SecureRng,
+};
+use alloc::sync::Arc;
+use ark_ec::CurveGroup;
+use core::{
+	fmt,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+/// a flag shared between an operation and whoever may cancel it
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+	/// a token that has not been cancelled
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// cancel every operation checking this token (or a clone of it)
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	/// true once the token has been cancelled
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// the progress callback of a monitor, called with (done, total)
+pub type ProgressFn<'a> = dyn FnMut(usize, usize) + Send + Sync + 'a;
+
+/// reports the progress of an operation and checks for its cancellation
+#[derive(Default)]
+pub struct Monitor<'a> {
+	progress: Option<&'a mut ProgressFn<'a>>,
+	token: Option<CancellationToken>,
+}
+
+impl fmt::Debug for Monitor<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Monitor")
+			.field("progress", &self.progress.is_some())
+			.field("token", &self.token)
+			.finish()
+	}
+}
+
+impl<'a> Monitor<'a> {
+	/// a monitor with no callback that is never cancelled
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// call `progress` with (done, total) after every step
+	pub fn progress(mut self, progress: &'a mut ProgressFn<'a>) -> Self {
+		self.progress = Some(progress);
+		self
+	}
+
+	/// fail with `Cancelled` before the next step once `token` is cancelled
+	pub fn cancellation(mut self, token: CancellationToken) -> Self {
+		self.token = Some(token);
+		self
+	}
+
+	/// fails with `Cancelled` if the token has been cancelled
+	pub(crate) fn check(&self) -> Result<(), AcssError> {
+		match &self.token {
+			Some(token) if token.is_cancelled() => Err(AcssError::Cancelled),
+			_ => Ok(()),
+		}
+	}
+
+	/// report that `done` of `total` steps are complete
+	pub(crate) fn report(&mut self, done: usize, total: usize) {
+		if let Some(progress) = self.progress.as_mut() {
+			progress(done, total);
+		}
+	}
+}
+
+/// verify many proofs as with `BatchPoK::verify_batch`, `chunk` proofs per
+/// multi-scalar multiplication, reporting the number of proofs verified after
+/// each chunk
+/// outputs true if every proof is valid, false if any proof is invalid
+/// (except with negligible probability)
+///
+/// fails with `Cancelled` if the token is cancelled before the last chunk
+///
+/// * `proofs`: (expected public key, proof) pairs
+/// * `chunk`: The number of proofs per chunk, at least 1
+/// * `monitor`: The progress callback and cancellation token
+/// * `rng`: used to sample the combination coefficients
+pub fn verify_batch<C: CurveGroup, R: SecureRng>(
+	proofs: &[(PublicKey<C>, &BatchPoK<C>)],
+	chunk: usize,
+	mut monitor: Monitor<'_>,
+	mut rng: R,
+) -> Result<bool, AcssError> {
+	let mut done = 0;
+	for batch in proofs.chunks(chunk.max(1)) {
+		monitor.check()?;
+		if !BatchPoK::verify_batch(batch, &mut rng) {
+			return Ok(false);
+		}
+		done += batch.len();
+		monitor.report(done, proofs.len());
+	}
+	Ok(true)
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::acss::DoubleSecret;
+	use ark_ff::UniformRand;
+	use ark_std::{rand::SeedableRng, vec::Vec};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{EngineBLS, KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	#[test]
+	fn dealings_report_progress_and_can_be_cancelled() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let keys: Vec<KeypairVT<E>> =
+			(0..4).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<_> = keys.iter().map(|kp| kp.public).collect();
+
+		let mut reports = Vec::new();
+		let mut progress = |done, total| reports.push((done, total));
+		let resharing = secret
+			.builder()
+			.committee(&committee)
+			.threshold(3)
+			.monitor(Monitor::new().progress(&mut progress))
+			.build(&mut rng)
+			.unwrap();
+		assert_eq!(reports, [(1, 4), (2, 4), (3, 4), (4, 4)]);
+
+		let token = CancellationToken::new();
+		let canceller = token.clone();
+		let mut cancel_at_two = |done, _| {
+			if done == 2 {
+				canceller.cancel();
+			}
+		};
+		let cancelled = secret
+			.builder()
+			.committee(&committee)
+			.threshold(3)
+			.monitor(
+				Monitor::new()
+					.progress(&mut cancel_at_two)
+					.cancellation(token.clone()),
+			)
+			.build(&mut rng);
+		assert_eq!(cancelled.err(), Some(AcssError::Cancelled));
+		assert!(token.is_cancelled());
+
+		let proofs: Vec<_> = keys
+			.iter()
+			.zip(resharing.poks())
+			.map(|(kp, pok)| (PublicKey(kp.public.0), pok))
+			.collect();
+		let mut reports = Vec::new();
+		let mut progress = |done, total| reports.push((done, total));
+		let monitor = Monitor::new().progress(&mut progress);
+		assert_eq!(verify_batch(&proofs, 3, monitor, &mut rng), Ok(true));
+		assert_eq!(reports, [(3, 4), (4, 4)]);
+
+		let monitor = Monitor::new().cancellation(token);
+		assert_eq!(
+			verify_batch(&proofs, 3, monitor, &mut rng),
+			Err(AcssError::Cancelled)
+		);
+	}
+}
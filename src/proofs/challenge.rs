@@ -0,0 +1,354 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Fiat-Shamir Challenge Hashes
+//!
+//! The hash used to derive the sigma protocol challenge from (s, t, c), where
+//! c is the aggregated ciphertext.
+//!
+//! - [`Shake128Challenge`]: shake128 over the compressed encoding (the default)
+//! - [`PoseidonChallenge`]: the standard x^5 Poseidon instance over the scalar
+//!   field, so that proofs can be verified inside a SNARK without emulating a
+//!   bit oriented hash (requires the `poseidon` feature)
+//!
+//! Provers and verifiers must agree on the hash. [`DefaultChallenge`] is always
+//! shake128, enabling a feature never changes it: Poseidon is chosen explicitly
+//! with `prove_with_hash::<PoseidonChallenge, _>` and
+//! `verify_with_hash::<PoseidonChallenge>`.
+//!
+//! A single run of the protocol has soundness error 2^-b for a challenge of b
+//! bits ([`ChallengeHash::challenge_bits`]), so the proof is not repeated:
+//! applications needing a different margin set the challenge size instead,
+//! e.g. `Shake128Challenge<64>` reads 64 bytes of output so that the reduced
+//! challenge is statistically uniform over the whole scalar field.
+//!
+//! Proofs can also be bound to associated data (e.g. the block headers a
+//! dealing is made for) with [`TranscriptBinder`], which absorbs the data
+//! incrementally so that it never has to be buffered contiguously. A bound
+//! proof only verifies against the same data. Bound challenges are always
+//! derived with shake128.
+
+use crate::{
+	error::{ProofError, SerializationError},
+	proofs::{hashed_el_gamal::Ciphertext, ser::HashWriter},
+};
+use ark_ec::CurveGroup;
+use ark_ff::PrimeField;
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake128,
+};
+
+/// derives a sigma protocol challenge
+pub trait ChallengeHash {
+	/// derive the challenge for the commitments s and t and the aggregated
+	/// ciphertext
+	fn challenge<C: CurveGroup>(
+		s: &C,
+		t: &C,
+		ciphertext: &Ciphertext<C>,
+	) -> Result<C::ScalarField, ProofError>;
+
+	/// the number of bits of the challenge space over the scalar field `F`
+	fn challenge_bits<F: PrimeField>() -> u32 {
+		F::MODULUS_BIT_SIZE - 1
+	}
+}
+
+/// the domain separation label of associated data
+const BINDER_LABEL: &[u8] = b"acss/challenge/aad";
+/// the domain separation label of challenges bound to associated data
+const BOUND_CHALLENGE_LABEL: &[u8] = b"acss/challenge/bound";
+
+/// incrementally absorbs the associated data a proof is bound to
+///
+/// absorbing a sequence of slices is equivalent to absorbing their
+/// concatenation, so the data can be streamed in pieces of any size
+#[derive(Clone)]
+pub struct TranscriptBinder(Shake128);
+
+impl Default for TranscriptBinder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl TranscriptBinder {
+	/// a binder that has absorbed nothing
+	pub fn new() -> Self {
+		let mut h = Shake128::default();
+		h.update(BINDER_LABEL);
+		Self(h)
+	}
+
+	/// absorb the next piece of the associated data
+	///
+	/// * `bytes`: The piece of the data
+	pub fn absorb(&mut self, bytes: &[u8]) -> &mut Self {
+		self.0.update(bytes);
+		self
+	}
+
+	/// the digest of everything absorbed, which proofs are bound to
+	pub fn finalize(self) -> Binding {
+		let mut digest = [0u8; 32];
+		self.0.finalize_xof().read(&mut digest);
+		Binding(digest)
+	}
+}
+
+/// the digest of the associated data a proof is bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding(pub [u8; 32]);
+
+impl Binding {
+	/// the binding of data available in one piece
+	///
+	/// * `bytes`: The associated data
+	pub fn of(bytes: &[u8]) -> Self {
+		let mut binder = TranscriptBinder::new();
+		binder.absorb(bytes);
+		binder.finalize()
+	}
+
+	/// the binding of a dealing made for a session, with associated data
+	/// as `ReshareBuilder` derives it
+	///
+	/// the session is fixed width, so the digest of the data that follows it
+	/// cannot be shifted into it
+	///
+	/// * `session`: The session the dealing is made for
+	/// * `aad`: The binding of the associated data
+	pub fn session(session: &[u8; 32], aad: &Binding) -> Self {
+		let mut binder = TranscriptBinder::new();
+		binder.absorb(session).absorb(&aad.0);
+		binder.finalize()
+	}
+}
+
+/// the challenge for s, t and the aggregated ciphertext, bound to associated
+/// data: shake128 over the binding followed by the inputs of
+/// [`Shake128Challenge`]
+pub(crate) fn bound_challenge<C: CurveGroup>(
+	binding: &Binding,
+	s: &C,
+	t: &C,
+	ciphertext: &Ciphertext<C>,
+) -> Result<C::ScalarField, ProofError> {
+	let mut h = Shake128::default();
+	h.update(BOUND_CHALLENGE_LABEL);
+	h.update(&binding.0);
+	for point in [s, t, &ciphertext.c1] {
+		point
+			.serialize_compressed(HashWriter(&mut h))
+			.map_err(|_| SerializationError::Encoding)?;
+	}
+	h.update(&ciphertext.c2);
+
+	let mut o = [0u8; DEFAULT_CHALLENGE_BYTES];
+	h.finalize_xof().read(&mut o);
+	Ok(C::ScalarField::from_be_bytes_mod_order(&o))
+}
+
+/// the challenge hash used when none is specified
+pub type DefaultChallenge = Shake128Challenge;
+
+/// the number of bytes read from shake128 when none is specified
+pub const DEFAULT_CHALLENGE_BYTES: usize = 32;
+
+/// absorbs s, t, and the aggregated ciphertext into shake128 and reduces
+/// `BYTES` bytes of output modulo the order of the scalar field
+///
+/// reading more bytes than the field is wide brings the challenge closer to
+/// uniform, reading fewer shrinks the challenge space to `8 * BYTES` bits
+pub struct Shake128Challenge<const BYTES: usize = DEFAULT_CHALLENGE_BYTES>;
+
+impl<const BYTES: usize> Shake128Challenge<BYTES> {
+	/// rejects empty challenges when the hash is instantiated
+	const NON_EMPTY: () = assert!(BYTES > 0, "the challenge cannot be empty");
+}
+
+impl<const BYTES: usize> ChallengeHash for Shake128Challenge<BYTES> {
+	/// elements are serialized directly into the hasher, so no intermediate
+	/// buffers are allocated
+	fn challenge<C: CurveGroup>(
+		s: &C,
+		t: &C,
+		ciphertext: &Ciphertext<C>,
+	) -> Result<C::ScalarField, ProofError> {
+		let mut h = Shake128::default();
+		for point in [s, t, &ciphertext.c1] {
+			point
+				.serialize_compressed(HashWriter(&mut h))
+				.map_err(|_| SerializationError::Encoding)?;
+		}
+		h.update(&ciphertext.c2);
+
+		let () = Self::NON_EMPTY;
+		let mut o = [0u8; BYTES];
+		// get challenge from hasher
+		h.finalize_xof().read(&mut o);
+		Ok(C::ScalarField::from_be_bytes_mod_order(&o))
+	}
+
+	fn challenge_bits<F: PrimeField>() -> u32 {
+		let bits = u32::try_from(8 * BYTES).unwrap_or(u32::MAX);
+		bits.min(F::MODULUS_BIT_SIZE - 1)
+	}
+}
+
+/// packs the compressed encoding of s, t, and the aggregated ciphertext into
+/// scalar field elements and absorbs them into a Poseidon sponge
+///
+/// fails with [`ProofError::UnsupportedField`] over scalar fields with no
+/// standard x^5 instance, such as that of BLS12-377
+#[cfg(feature = "poseidon")]
+pub struct PoseidonChallenge;
+
+#[cfg(feature = "poseidon")]
+impl ChallengeHash for PoseidonChallenge {
+	fn challenge<C: CurveGroup>(
+		s: &C,
+		t: &C,
+		ciphertext: &Ciphertext<C>,
+	) -> Result<C::ScalarField, ProofError> {
+		use crate::proofs::poseidon::{pack_bytes, Poseidon};
+		use ark_std::vec::Vec;
+
+		let poseidon = Poseidon::<C::ScalarField>::new()
+			.ok_or(ProofError::UnsupportedField)?;
+		let mut bytes = Vec::new();
+		for point in [s, t, &ciphertext.c1] {
+			point
+				.serialize_compressed(&mut bytes)
+				.map_err(|_| SerializationError::Encoding)?;
+		}
+		bytes.extend_from_slice(&ciphertext.c2);
+
+		Ok(poseidon.hash(&pack_bytes(&bytes)))
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{
+		keys::PublicKey,
+		proofs::{hashed_el_gamal::Ephemeral, hashed_el_gamal_sigma::BatchPoK},
+		rng::test_rng,
+	};
+	use ark_ec::Group;
+	use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+	use ark_ff::UniformRand;
+	use ark_std::{ops::Mul, vec::Vec};
+
+	type Scalar = <JubJub as Group>::ScalarField;
+
+	fn prove_and_verify<P: ChallengeHash, V: ChallengeHash>() -> bool {
+		let mut rng = test_rng();
+		let x = Scalar::rand(&mut rng);
+		let g = JubJub::generator();
+		let h = PublicKey(g.mul(x));
+
+		let messages = [Scalar::rand(&mut rng), Scalar::rand(&mut rng)];
+		let ephemerals: Vec<Ephemeral<JubJub>> =
+			messages.iter().map(|_| Ephemeral::new(&mut rng)).collect();
+		let proof =
+			BatchPoK::prove_with_hash::<P, _>(&messages, h, &ephemerals, rng)
+				.unwrap();
+		proof.verify_with_hash::<V>(h)
+	}
+
+	#[test]
+	fn shake128_challenge_round_trips() {
+		assert!(prove_and_verify::<Shake128Challenge, Shake128Challenge>());
+		assert!(prove_and_verify::<DefaultChallenge, DefaultChallenge>());
+	}
+
+	#[test]
+	fn streamed_data_binds_like_contiguous_data() {
+		let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+		let mut binder = TranscriptBinder::new();
+		for piece in data.chunks(777) {
+			binder.absorb(piece);
+		}
+		assert_eq!(binder.finalize(), Binding::of(&data));
+		assert_ne!(Binding::of(&data[1..]), Binding::of(&data));
+	}
+
+	#[test]
+	fn bound_proofs_only_verify_against_their_data() {
+		let mut rng = test_rng();
+		let g = JubJub::generator();
+		let h = PublicKey(g.mul(Scalar::rand(&mut rng)));
+		let messages = [Scalar::rand(&mut rng)];
+		let ephemerals = [Ephemeral::new(&mut rng)];
+		let binding = Binding::of(b"headers 1..=1000");
+
+		let proof =
+			BatchPoK::prove_bound(&messages, h, &ephemerals, &binding, rng)
+				.unwrap();
+		assert!(proof.verify_bound(h, &binding));
+		assert!(!proof.verify_bound(h, &Binding::of(b"headers 1..=999")));
+		assert!(!proof.verify(h));
+	}
+
+	#[test]
+	fn challenge_sizes_are_configurable() {
+		assert!(
+			prove_and_verify::<Shake128Challenge<16>, Shake128Challenge<16>>()
+		);
+		assert!(
+			prove_and_verify::<Shake128Challenge<64>, Shake128Challenge<64>>()
+		);
+		assert!(!prove_and_verify::<Shake128Challenge<64>, Shake128Challenge>());
+
+		assert_eq!(Shake128Challenge::<16>::challenge_bits::<Scalar>(), 128);
+		assert_eq!(
+			Shake128Challenge::<64>::challenge_bits::<Scalar>(),
+			Scalar::MODULUS_BIT_SIZE - 1
+		);
+		assert_eq!(
+			<Shake128Challenge>::challenge_bits::<Scalar>(),
+			DefaultChallenge::challenge_bits::<Scalar>()
+		);
+	}
+
+	#[cfg(feature = "poseidon")]
+	#[test]
+	fn poseidon_challenge_round_trips() {
+		assert!(prove_and_verify::<PoseidonChallenge, PoseidonChallenge>());
+	}
+
+	#[cfg(feature = "poseidon")]
+	#[test]
+	fn mixed_challenge_hashes_fail_to_verify() {
+		assert!(!prove_and_verify::<PoseidonChallenge, Shake128Challenge>());
+		assert!(!prove_and_verify::<Shake128Challenge, PoseidonChallenge>());
+	}
+
+	#[cfg(feature = "poseidon")]
+	#[test]
+	fn poseidon_challenge_rejects_unsupported_fields() {
+		use w3f_bls::{EngineBLS, TinyBLS377};
+
+		let g = <TinyBLS377 as EngineBLS>::PublicKeyGroup::generator();
+		let ciphertext = Ciphertext { c1: g, c2: [0u8; 32], tag: [0u8; 16] };
+		assert!(matches!(
+			PoseidonChallenge::challenge(&g, &g, &ciphertext),
+			Err(ProofError::UnsupportedField)
+		));
+	}
+}
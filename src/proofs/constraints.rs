@@ -0,0 +1,803 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! R1CS Constraints
+//!
+//! Gadgets that check ciphertexts and batch proofs of knowledge inside a
+//! circuit over F, so that a dealing can be shown correct by one succinct
+//! proof rather than by verifying every proof on chain. Points are allocated
+//! through a [`CurveVar`] of the ciphertext group over F (e.g. the twisted
+//! Edwards curve embedded in the scalar field of a pairing curve), and
+//! scalars, which are foreign to F, as little-endian bits.
+//!
+//! [`BatchPoKVar::verify`] takes the challenge as bits, e.g. an input the
+//! verifier of the circuit recomputes with [`challenge_bits`].
+//!
+//! [`DealingCircuit`] puts the proofs of a whole dealing in one circuit, for
+//! a Groth16 or PLONK backend to prove once (see the `groth16` feature). It
+//! derives each challenge itself, with [`circuit_challenge`]: Poseidon over F
+//! of the coordinates of s, t and the aggregated ciphertext, which is cheap
+//! in a circuit where shake128 is not. Proofs put in it must be made with
+//! `BatchPoK::prove_for_circuit`. Its one public input is the
+//! [`dealing_digest`], a Poseidon hash of the recipients' keys, the
+//! commitments and the ciphertexts, so the verifier of the circuit checks a
+//! constant size instance whatever the size of the committee.
+
+use crate::{
+	error::{ProofError, SerializationError},
+	keys::PublicKey,
+	proofs::{
+		challenge::ChallengeHash,
+		hashed_el_gamal::Ciphertext,
+		hashed_el_gamal_sigma::BatchPoK,
+		poseidon::{pack_bytes, Poseidon},
+	},
+};
+#[cfg(feature = "participant")]
+use crate::{proofs::hashed_el_gamal::Ephemeral, rng::SecureRng};
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+	alloc::{AllocVar, AllocationMode},
+	boolean::Boolean,
+	eq::EqGadget,
+	fields::{fp::FpVar, FieldVar},
+	groups::CurveVar,
+	uint8::UInt8,
+	ToBitsGadget, ToConstraintFieldGadget,
+};
+use ark_relations::r1cs::{
+	ConstraintSynthesizer, ConstraintSystemRef, Namespace, SynthesisError,
+};
+use ark_std::{borrow::Borrow, marker::PhantomData, vec, vec::Vec};
+
+/// the little-endian bits of a scalar, as allocated for the gadgets here
+pub fn scalar_bits<F: PrimeField>(scalar: &F) -> Vec<bool> {
+	let mut bits = scalar.into_bigint().to_bits_le();
+	bits.truncate(F::MODULUS_BIT_SIZE as usize);
+	bits
+}
+
+/// the bits of the challenge of `proof` under the hash `H`, to assign to the
+/// challenge of [`BatchPoKVar::verify`]
+/// fails if the aggregated ciphertext cannot be encoded
+pub fn challenge_bits<H: ChallengeHash, C: CurveGroup>(
+	proof: &BatchPoK<C>,
+) -> Result<Vec<bool>, ProofError> {
+	let ciphertext = Ciphertext::aggregate(proof.ciphertexts.iter());
+	H::challenge(&proof.s, &proof.t, &ciphertext).map(|e| scalar_bits(&e))
+}
+
+/// the number of bits of a challenge derived by [`circuit_challenge`],
+/// fewer than either field has, so that the low bits of a hash over F are a
+/// scalar of C without reduction
+fn circuit_challenge_bits<C: CurveGroup, F: PrimeField>() -> usize {
+	(C::ScalarField::MODULUS_BIT_SIZE.min(F::MODULUS_BIT_SIZE) - 1) as usize
+}
+
+/// the elements over F of a point, as `ToConstraintFieldGadget` allocates
+/// them
+fn point_elements<C: ToConstraintField<F>, F: PrimeField>(
+	point: &C,
+) -> Result<Vec<F>, ProofError> {
+	point.to_field_elements().ok_or_else(|| SerializationError::Encoding.into())
+}
+
+/// the challenge a [`DealingCircuit`] derives for the commitments s and t
+/// and the aggregated ciphertext: Poseidon over F of the coordinates of s, t
+/// and c1 and the packed bytes of c2, truncated to fewer bits than either
+/// field has
+/// fails with `UnsupportedField` if F has no Poseidon instance
+///
+/// * `s`: The commitment kG
+/// * `t`: The commitment kH
+/// * `ciphertext`: The aggregate of the proof's ciphertexts
+pub fn circuit_challenge<C, F>(
+	s: &C,
+	t: &C,
+	ciphertext: &Ciphertext<C>,
+) -> Result<C::ScalarField, ProofError>
+where
+	C: CurveGroup + ToConstraintField<F>,
+	F: PrimeField,
+{
+	let poseidon = Poseidon::<F>::new().ok_or(ProofError::UnsupportedField)?;
+	challenge_with(&poseidon, s, t, ciphertext)
+}
+
+/// `circuit_challenge` with the Poseidon parameters already generated
+fn challenge_with<C, F>(
+	poseidon: &Poseidon<F>,
+	s: &C,
+	t: &C,
+	ciphertext: &Ciphertext<C>,
+) -> Result<C::ScalarField, ProofError>
+where
+	C: CurveGroup + ToConstraintField<F>,
+	F: PrimeField,
+{
+	let mut inputs = Vec::new();
+	for point in [s, t, &ciphertext.c1] {
+		inputs.extend(point_elements(point)?);
+	}
+	inputs.extend(pack_bytes::<F>(&ciphertext.c2));
+	let mut bits = scalar_bits(&poseidon.hash(&inputs));
+	bits.truncate(circuit_challenge_bits::<C, F>());
+	let bigint = <C::ScalarField as PrimeField>::BigInt::from_bits_le(&bits);
+	C::ScalarField::from_bigint(bigint).ok_or(ProofError::UnsupportedField)
+}
+
+/// the public input of a [`DealingCircuit`]: Poseidon over F of the number
+/// of recipients and of ciphertexts per recipient, then of the coordinates
+/// of each recipient's key, s, t and commitment and of the c1 and packed c2
+/// of each of its ciphertexts
+/// fails with `UnsupportedField` if F has no Poseidon instance
+///
+/// * `proofs`: (recipient public key, proof) pairs
+pub fn dealing_digest<C, F>(
+	proofs: &[(PublicKey<C>, BatchPoK<C>)],
+) -> Result<F, ProofError>
+where
+	C: CurveGroup + ToConstraintField<F>,
+	F: PrimeField,
+{
+	let poseidon = Poseidon::<F>::new().ok_or(ProofError::UnsupportedField)?;
+	let k = proofs.first().map_or(0, |(_, proof)| proof.ciphertexts.len());
+	let mut inputs = vec![F::from(proofs.len() as u64), F::from(k as u64)];
+	for (pk, proof) in proofs {
+		for point in [&pk.0, &proof.s, &proof.t, &proof.commitment] {
+			inputs.extend(point_elements(point)?);
+		}
+		for ct in &proof.ciphertexts {
+			inputs.extend(point_elements(&ct.c1)?);
+			inputs.extend(pack_bytes::<F>(&ct.c2));
+		}
+	}
+	Ok(poseidon.hash(&inputs))
+}
+
+/// pack bytes allocated in a constraint system into field elements, as
+/// `pack_bytes` does
+fn pack_bytes_var<F: PrimeField>(
+	bytes: &[UInt8<F>],
+) -> Result<Vec<FpVar<F>>, SynthesisError> {
+	let chunk_size = ((F::MODULUS_BIT_SIZE - 1) / 8) as usize;
+	bytes
+		.chunks(chunk_size)
+		.map(|chunk| Boolean::le_bits_to_fp_var(&chunk.to_bits_le()?))
+		.collect()
+}
+
+/// the bits of the challenge [`circuit_challenge`] derives, computed in the
+/// circuit
+fn challenge_var<C, F, GG>(
+	poseidon: &Poseidon<F>,
+	s: &GG,
+	t: &GG,
+	ciphertext: &CiphertextVar<C, F, GG>,
+) -> Result<Vec<Boolean<F>>, SynthesisError>
+where
+	C: CurveGroup,
+	F: PrimeField,
+	GG: CurveVar<C, F> + ToConstraintFieldGadget<F>,
+{
+	let mut inputs = Vec::new();
+	for point in [s, t, &ciphertext.c1] {
+		inputs.extend(point.to_constraint_field()?);
+	}
+	inputs.extend(pack_bytes_var(&ciphertext.c2)?);
+	let mut bits = poseidon.hash_var(&inputs)?.to_bits_le()?;
+	bits.truncate(circuit_challenge_bits::<C, F>());
+	Ok(bits)
+}
+
+impl<C: CurveGroup> BatchPoK<C> {
+	#[cfg(feature = "participant")]
+	/// batch prove, as with `prove_with_ephemerals`, deriving the challenge
+	/// with [`circuit_challenge`] over F so that the proof can be put in a
+	/// [`DealingCircuit`]
+	/// the proof must be verified with `verify_for_circuit::<F>`
+	///
+	/// * `messages`: The messages to encrypt and prove knowledge of
+	/// * `pk`: The public key of the recipient
+	/// * `ephemerals`: One ephemeral key per message
+	/// * `rng`: Used to sample the commitment randomness
+	pub fn prove_for_circuit<F: PrimeField, R: SecureRng>(
+		messages: &[C::ScalarField],
+		pk: PublicKey<C>,
+		ephemerals: &[Ephemeral<C>],
+		rng: R,
+	) -> Result<Self, ProofError>
+	where
+		C: ToConstraintField<F>,
+	{
+		let poseidon =
+			Poseidon::<F>::new().ok_or(ProofError::UnsupportedField)?;
+		Self::prove_with_challenge(messages, pk, ephemerals, rng, |s, t, ct| {
+			challenge_with(&poseidon, s, t, ct)
+		})
+	}
+
+	/// verify a proof made with `prove_for_circuit::<F>`
+	/// outputs true if the proof is valid, false otherwise
+	///
+	/// * `pk`: The expected public key
+	pub fn verify_for_circuit<F: PrimeField>(&self, pk: PublicKey<C>) -> bool
+	where
+		C: ToConstraintField<F>,
+	{
+		self.verify_with_challenge(pk, circuit_challenge::<C, F>)
+	}
+}
+
+/// allocate the little-endian bits of a scalar of C
+fn alloc_scalar<C: CurveGroup, F: PrimeField>(
+	cs: impl Into<Namespace<F>>,
+	f: impl FnOnce() -> Result<C::ScalarField, SynthesisError>,
+	mode: AllocationMode,
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+	let cs = cs.into().cs();
+	let bits = f().map(|scalar| scalar_bits(&scalar));
+	(0..C::ScalarField::MODULUS_BIT_SIZE as usize)
+		.map(|i| {
+			Boolean::new_variable(
+				cs.clone(),
+				|| bits.as_ref().map(|bits| bits[i]).map_err(|e| *e),
+				mode,
+			)
+		})
+		.collect()
+}
+
+/// a ciphertext allocated in a constraint system over F
+/// the tag is not allocated, as the proof of knowledge does not cover it
+pub struct CiphertextVar<
+	C: CurveGroup,
+	F: PrimeField,
+	GG: CurveVar<C, F>,
+	const N: usize = 32,
+> {
+	/// the ephemeral public key
+	pub c1: GG,
+	/// the masked message
+	pub c2: Vec<UInt8<F>>,
+	_curve: PhantomData<C>,
+}
+
+impl<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>, const N: usize>
+	CiphertextVar<C, F, GG, N>
+{
+	/// true if c1 is not the identity, since a ciphertext with c1 = 0 carries
+	/// its message under a pad anyone can derive
+	pub fn is_well_formed(&self) -> Result<Boolean<F>, SynthesisError> {
+		Ok(self.c1.is_zero()?.not())
+	}
+
+	/// aggregate ciphertexts as [`Ciphertext::aggregate`] does, summing c1 and
+	/// xoring c2
+	/// an empty slice aggregates to the identity <0, 0^N>
+	pub fn aggregate(cts: &[Self]) -> Result<Self, SynthesisError> {
+		let mut c1 = GG::zero();
+		let mut c2 = UInt8::constant_vec(&[0u8; N]);
+		for ct in cts {
+			c1 += &ct.c1;
+			for (acc, byte) in c2.iter_mut().zip(&ct.c2) {
+				*acc = acc.xor(byte)?;
+			}
+		}
+		Ok(Self { c1, c2, _curve: PhantomData })
+	}
+}
+
+impl<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>, const N: usize>
+	AllocVar<Ciphertext<C, N>, F> for CiphertextVar<C, F, GG, N>
+{
+	fn new_variable<T: Borrow<Ciphertext<C, N>>>(
+		cs: impl Into<Namespace<F>>,
+		f: impl FnOnce() -> Result<T, SynthesisError>,
+		mode: AllocationMode,
+	) -> Result<Self, SynthesisError> {
+		let cs = cs.into().cs();
+		let ct = f().map(|ct| ct.borrow().clone());
+		let c1 = GG::new_variable(
+			cs.clone(),
+			|| ct.as_ref().map(|ct| ct.c1).map_err(|e| *e),
+			mode,
+		)?;
+		let c2 = (0..N)
+			.map(|i| {
+				UInt8::new_variable(
+					cs.clone(),
+					|| ct.as_ref().map(|ct| ct.c2[i]).map_err(|e| *e),
+					mode,
+				)
+			})
+			.collect::<Result<_, _>>()?;
+		Ok(Self { c1, c2, _curve: PhantomData })
+	}
+}
+
+/// a batch proof of knowledge allocated in a constraint system over F
+pub struct BatchPoKVar<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>> {
+	/// the commitment kG
+	pub s: GG,
+	/// the commitment kH
+	pub t: GG,
+	/// the bits of the response
+	pub z: Vec<Boolean<F>>,
+	/// the commitment to the secret input
+	pub commitment: GG,
+	/// the ciphertexts
+	pub ciphertexts: Vec<CiphertextVar<C, F, GG>>,
+}
+
+impl<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>> BatchPoKVar<C, F, GG> {
+	/// allocate a proof of `len` ciphertexts
+	/// unlike `new_variable`, which takes the number of ciphertexts from the
+	/// proof, this fixes the shape of the circuit without a proof, e.g. for a
+	/// trusted setup
+	///
+	/// * `cs`: The constraint system
+	/// * `len`: The number of ciphertexts
+	/// * `f`: The proof, if it is known
+	/// * `mode`: Whether the proof is a constant, input or witness
+	pub fn new_variable_with_len<T: Borrow<BatchPoK<C>>>(
+		cs: impl Into<Namespace<F>>,
+		len: usize,
+		f: impl FnOnce() -> Result<T, SynthesisError>,
+		mode: AllocationMode,
+	) -> Result<Self, SynthesisError> {
+		let cs = cs.into().cs();
+		let proof = f().map(|proof| proof.borrow().clone());
+		let proof = proof.as_ref().map_err(|e| *e);
+		let point = |p: fn(&BatchPoK<C>) -> C| {
+			GG::new_variable(cs.clone(), || proof.map(p), mode)
+		};
+		let (s, t, commitment) =
+			(point(|p| p.s)?, point(|p| p.t)?, point(|p| p.commitment)?);
+		let z = alloc_scalar::<C, F>(cs.clone(), || proof.map(|p| p.z), mode)?;
+		let ciphertexts = (0..len)
+			.map(|i| {
+				CiphertextVar::new_variable(
+					cs.clone(),
+					|| {
+						proof.and_then(|p| {
+							p.ciphertexts
+								.get(i)
+								.ok_or(SynthesisError::AssignmentMissing)
+						})
+					},
+					mode,
+				)
+			})
+			.collect::<Result<_, _>>()?;
+		Ok(Self { s, t, z, commitment, ciphertexts })
+	}
+
+	/// true if every ciphertext is well formed and
+	/// z(G + pk) = s + t + e * commitment for the challenge e
+	///
+	/// * `pk`: The expected public key
+	/// * `challenge`: The little-endian bits of the challenge, as output by
+	///   [`challenge_bits`]
+	pub fn verify(
+		&self,
+		pk: &GG,
+		challenge: &[Boolean<F>],
+	) -> Result<Boolean<F>, SynthesisError> {
+		let lhs = (pk.clone() + C::generator()).scalar_mul_le(self.z.iter())?;
+		let rhs = self.s.clone() +
+			&self.t + self.commitment.scalar_mul_le(challenge.iter())?;
+		let mut checks = Vec::with_capacity(self.ciphertexts.len() + 1);
+		checks.push(lhs.is_eq(&rhs)?);
+		for ct in &self.ciphertexts {
+			checks.push(ct.is_well_formed()?);
+		}
+		Boolean::kary_and(&checks)
+	}
+
+	/// the aggregate of the ciphertexts, which the challenge is derived from
+	pub fn aggregate(&self) -> Result<CiphertextVar<C, F, GG>, SynthesisError> {
+		CiphertextVar::aggregate(&self.ciphertexts)
+	}
+}
+
+impl<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>> AllocVar<BatchPoK<C>, F>
+	for BatchPoKVar<C, F, GG>
+{
+	/// the number of ciphertexts is taken from the proof, so allocation fails
+	/// with `AssignmentMissing` without one, see `new_variable_with_len`
+	fn new_variable<T: Borrow<BatchPoK<C>>>(
+		cs: impl Into<Namespace<F>>,
+		f: impl FnOnce() -> Result<T, SynthesisError>,
+		mode: AllocationMode,
+	) -> Result<Self, SynthesisError> {
+		let proof = f()?.borrow().clone();
+		let len = proof.ciphertexts.len();
+		Self::new_variable_with_len(cs, len, || Ok(proof), mode)
+	}
+}
+
+/// the circuit of a dealing, the proofs of knowledge of `n` recipients'
+/// shares of `k` ciphertexts each, with challenges derived by
+/// [`circuit_challenge`]
+///
+/// the only public input is the [`dealing_digest`], while the recipients'
+/// keys and the proofs are witnesses the circuit hashes to it
+pub struct DealingCircuit<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>> {
+	n: usize,
+	k: usize,
+	proofs: Option<Vec<(PublicKey<C>, BatchPoK<C>)>>,
+	_vars: PhantomData<(F, GG)>,
+}
+
+impl<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>> Clone
+	for DealingCircuit<C, F, GG>
+{
+	fn clone(&self) -> Self {
+		Self {
+			n: self.n,
+			k: self.k,
+			proofs: self.proofs.clone(),
+			_vars: PhantomData,
+		}
+	}
+}
+
+impl<C: CurveGroup, F: PrimeField, GG: CurveVar<C, F>>
+	DealingCircuit<C, F, GG>
+{
+	/// the circuit of a dealing to `n` recipients of `k` ciphertexts each,
+	/// without a dealing, e.g. for a trusted setup
+	pub fn setup(n: usize, k: usize) -> Self {
+		Self { n, k, proofs: None, _vars: PhantomData }
+	}
+
+	/// the circuit of a dealing, with as many ciphertexts per recipient as
+	/// the first proof has
+	/// the circuit is unsatisfied if any other proof has more or fewer
+	///
+	/// * `proofs`: (recipient public key, proof) pairs, made with
+	///   `BatchPoK::prove_for_circuit`
+	pub fn new(proofs: Vec<(PublicKey<C>, BatchPoK<C>)>) -> Self {
+		let k = proofs.first().map_or(0, |(_, proof)| proof.ciphertexts.len());
+		Self { n: proofs.len(), k, proofs: Some(proofs), _vars: PhantomData }
+	}
+}
+
+impl<C, F, GG> DealingCircuit<C, F, GG>
+where
+	C: CurveGroup + ToConstraintField<F>,
+	F: PrimeField,
+	GG: CurveVar<C, F>,
+{
+	/// the public inputs of the circuit, in the order a verifier of a
+	/// succinct proof of it expects them: the digest of the dealing
+	/// fails with `AssignmentMissing` for a circuit without a dealing, and
+	/// with `Unsatisfiable` if the digest cannot be derived
+	pub fn public_inputs(&self) -> Result<Vec<F>, SynthesisError> {
+		let proofs =
+			self.proofs.as_ref().ok_or(SynthesisError::AssignmentMissing)?;
+		let digest = dealing_digest(proofs)
+			.map_err(|_| SynthesisError::Unsatisfiable)?;
+		Ok(vec![digest])
+	}
+}
+
+impl<C, F, GG> ConstraintSynthesizer<F> for DealingCircuit<C, F, GG>
+where
+	C: CurveGroup + ToConstraintField<F>,
+	F: PrimeField,
+	GG: CurveVar<C, F> + ToConstraintFieldGadget<F>,
+{
+	fn generate_constraints(
+		self,
+		cs: ConstraintSystemRef<F>,
+	) -> Result<(), SynthesisError> {
+		let poseidon =
+			Poseidon::<F>::new().ok_or(SynthesisError::Unsatisfiable)?;
+		let digest = FpVar::new_input(cs.clone(), || {
+			self.public_inputs().map(|inputs| inputs[0])
+		})?;
+
+		let mut elements = vec![
+			FpVar::constant(F::from(self.n as u64)),
+			FpVar::constant(F::from(self.k as u64)),
+		];
+		for i in 0..self.n {
+			let entry = self
+				.proofs
+				.as_ref()
+				.and_then(|proofs| proofs.get(i))
+				.ok_or(SynthesisError::AssignmentMissing);
+			let proof = entry.map(|(_, proof)| proof);
+
+			let pk = GG::new_witness(cs.clone(), || entry.map(|(pk, _)| pk.0))?;
+			let var = BatchPoKVar::<C, F, GG>::new_variable_with_len(
+				cs.clone(),
+				self.k,
+				|| proof,
+				AllocationMode::Witness,
+			)?;
+			for point in [&pk, &var.s, &var.t, &var.commitment] {
+				elements.extend(point.to_constraint_field()?);
+			}
+			for ct in &var.ciphertexts {
+				elements.extend(ct.c1.to_constraint_field()?);
+				elements.extend(pack_bytes_var(&ct.c2)?);
+			}
+
+			let challenge =
+				challenge_var(&poseidon, &var.s, &var.t, &var.aggregate()?)?;
+			var.verify(&pk, &challenge)?.enforce_equal(&Boolean::TRUE)?;
+		}
+		poseidon.hash_var(&elements)?.enforce_equal(&digest)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		keys::PublicKey,
+		proofs::{challenge::DefaultChallenge, hashed_el_gamal::Ephemeral},
+	};
+	use ark_ec::Group;
+	use ark_ed_on_bls12_381::{
+		constraints::EdwardsVar, EdwardsProjective as C, Fq, Fr,
+	};
+	use ark_ff::UniformRand;
+	use ark_r1cs_std::R1CSVar;
+	use ark_relations::r1cs::{ConstraintSystem, SynthesisMode};
+	use ark_std::{rand::SeedableRng, Zero};
+	use rand_chacha::ChaCha20Rng;
+
+	/// allocate `proof` as a witness and the key and challenge as inputs,
+	/// outputting whether the proof verifies and the constraint system
+	fn check(
+		proof: &BatchPoK<C>,
+		pk: C,
+	) -> (bool, ark_relations::r1cs::ConstraintSystemRef<Fq>) {
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		let pk = EdwardsVar::new_input(cs.clone(), || Ok(pk)).unwrap();
+		let challenge = Vec::<Boolean<Fq>>::new_input(cs.clone(), || {
+			Ok(challenge_bits::<DefaultChallenge, C>(proof).unwrap())
+		})
+		.unwrap();
+		let proof =
+			BatchPoKVar::<C, Fq, EdwardsVar>::new_witness(cs.clone(), || {
+				Ok(proof)
+			})
+			.unwrap();
+		let valid = proof.verify(&pk, &challenge).unwrap();
+		(valid.value().unwrap(), cs)
+	}
+
+	fn prove(rng: &mut ChaCha20Rng) -> (BatchPoK<C>, C) {
+		let pk = C::generator() * Fr::rand(rng);
+		let messages = [Fr::rand(rng), Fr::rand(rng)];
+		(BatchPoK::prove(&messages, PublicKey(pk), rng).unwrap(), pk)
+	}
+
+	#[test]
+	fn valid_proofs_satisfy_the_circuit() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (proof, pk) = prove(&mut rng);
+		assert!(proof.verify(PublicKey(pk)));
+		let (valid, cs) = check(&proof, pk);
+		assert!(valid);
+		assert!(cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn invalid_proofs_do_not_verify_in_the_circuit() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (proof, pk) = prove(&mut rng);
+
+		let mut forged = proof.clone();
+		forged.z += Fr::from(1u64);
+		assert!(!check(&forged, pk).0);
+
+		let mut forged = proof.clone();
+		forged.ciphertexts[1].c2[0] ^= 1;
+		assert!(!check(&forged, pk).0);
+
+		let other = pk + pk;
+		assert!(!check(&proof, other).0);
+	}
+
+	#[test]
+	fn identity_ephemerals_are_not_well_formed() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (mut proof, _) = prove(&mut rng);
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		proof.ciphertexts[0].c1 = C::zero();
+		let proof =
+			BatchPoKVar::<C, Fq, EdwardsVar>::new_witness(cs.clone(), || {
+				Ok(&proof)
+			})
+			.unwrap();
+		assert!(!proof.ciphertexts[0]
+			.is_well_formed()
+			.unwrap()
+			.value()
+			.unwrap());
+		assert!(proof.ciphertexts[1]
+			.is_well_formed()
+			.unwrap()
+			.value()
+			.unwrap());
+	}
+
+	#[test]
+	fn aggregation_matches_the_native_aggregate() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (proof, _) = prove(&mut rng);
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		let var =
+			BatchPoKVar::<C, Fq, EdwardsVar>::new_witness(cs.clone(), || {
+				Ok(&proof)
+			})
+			.unwrap();
+		let aggregate = var.aggregate().unwrap();
+		let expected = Ciphertext::aggregate(proof.ciphertexts.iter());
+		assert_eq!(aggregate.c1.value().unwrap(), expected.c1);
+		assert_eq!(aggregate.c2.value().unwrap(), expected.c2.to_vec());
+	}
+
+	fn dealing(rng: &mut ChaCha20Rng) -> Vec<(PublicKey<C>, BatchPoK<C>)> {
+		(0..3)
+			.map(|_| {
+				let pk = PublicKey(C::generator() * Fr::rand(rng));
+				let messages = [Fr::rand(rng), Fr::rand(rng)];
+				let ephemerals =
+					[Ephemeral::new(&mut *rng), Ephemeral::new(&mut *rng)];
+				let proof = BatchPoK::prove_for_circuit::<Fq, _>(
+					&messages,
+					pk,
+					&ephemerals,
+					&mut *rng,
+				)
+				.unwrap();
+				(pk, proof)
+			})
+			.collect()
+	}
+
+	fn satisfies(proofs: Vec<(PublicKey<C>, BatchPoK<C>)>) -> bool {
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		DealingCircuit::<C, Fq, EdwardsVar>::new(proofs)
+			.generate_constraints(cs.clone())
+			.unwrap();
+		cs.is_satisfied().unwrap()
+	}
+
+	#[test]
+	fn circuit_challenges_verify_natively() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		for (pk, proof) in dealing(&mut rng) {
+			assert!(proof.verify_for_circuit::<Fq>(pk));
+			assert!(!proof.verify(pk));
+		}
+		let (proof, pk) = prove(&mut rng);
+		assert!(!proof.verify_for_circuit::<Fq>(PublicKey(pk)));
+	}
+
+	#[test]
+	fn dealings_satisfy_the_dealing_circuit() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let proofs = dealing(&mut rng);
+		let circuit = DealingCircuit::<C, Fq, EdwardsVar>::new(proofs.clone());
+		let inputs = circuit.public_inputs().unwrap();
+		assert_eq!(inputs, vec![dealing_digest::<C, Fq>(&proofs).unwrap()]);
+
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		circuit.generate_constraints(cs.clone()).unwrap();
+		assert!(cs.is_satisfied().unwrap());
+		assert_eq!(cs.borrow().unwrap().instance_assignment[1..], inputs[..]);
+	}
+
+	#[test]
+	fn forged_dealings_do_not_satisfy_the_dealing_circuit() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let mut proofs = dealing(&mut rng);
+		proofs[2].1.z += Fr::from(1u64);
+		assert!(!satisfies(proofs));
+
+		let mut proofs = dealing(&mut rng);
+		proofs[0].0 = proofs[1].0;
+		assert!(!satisfies(proofs));
+
+		// proofs under another challenge hash
+		let proofs = (0..3)
+			.map(|_| {
+				let (proof, pk) = prove(&mut rng);
+				(PublicKey(pk), proof)
+			})
+			.collect();
+		assert!(!satisfies(proofs));
+	}
+
+	#[test]
+	fn forged_challenges_do_not_satisfy_the_dealing_circuit() {
+		// without the messages, a forger picks the challenge and the response
+		// and solves the equation for t, which only holds under a challenge
+		// it chose rather than derived
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let mut proofs = dealing(&mut rng);
+		let (pk, proof) = &mut proofs[1];
+		let (e, z) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+		proof.z = z;
+		proof.t = (C::generator() + pk.0) * z - proof.s - proof.commitment * e;
+		assert_eq!(
+			(C::generator() + pk.0) * proof.z,
+			proof.s + proof.t + proof.commitment * e
+		);
+		assert!(!proof.verify_for_circuit::<Fq>(*pk));
+		assert!(!satisfies(proofs));
+	}
+
+	#[test]
+	fn the_digest_binds_the_dealing() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let proofs = dealing(&mut rng);
+		let digest = dealing_digest::<C, Fq>(&proofs).unwrap();
+
+		let mut tampered = proofs.clone();
+		tampered[2].1.ciphertexts[1].c2[0] ^= 1;
+		assert_ne!(dealing_digest::<C, Fq>(&tampered).unwrap(), digest);
+		let mut tampered = proofs.clone();
+		tampered.swap(0, 1);
+		assert_ne!(dealing_digest::<C, Fq>(&tampered).unwrap(), digest);
+		assert_ne!(dealing_digest::<C, Fq>(&proofs[..2]).unwrap(), digest);
+	}
+
+	#[test]
+	fn the_setup_circuit_has_the_shape_of_a_dealing() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let proving = ConstraintSystem::<Fq>::new_ref();
+		DealingCircuit::<C, Fq, EdwardsVar>::new(dealing(&mut rng))
+			.generate_constraints(proving.clone())
+			.unwrap();
+
+		let setup = ConstraintSystem::<Fq>::new_ref();
+		setup.set_mode(SynthesisMode::Setup);
+		let circuit = DealingCircuit::<C, Fq, EdwardsVar>::setup(3, 2);
+		assert_eq!(
+			circuit.public_inputs(),
+			Err(SynthesisError::AssignmentMissing)
+		);
+		circuit.generate_constraints(setup.clone()).unwrap();
+		assert_eq!(setup.num_constraints(), proving.num_constraints());
+		assert_eq!(
+			setup.num_instance_variables(),
+			proving.num_instance_variables()
+		);
+	}
+
+	#[test]
+	fn setup_allocates_without_a_proof() {
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		cs.set_mode(SynthesisMode::Setup);
+		let var = BatchPoKVar::<C, Fq, EdwardsVar>::new_variable_with_len(
+			cs.clone(),
+			2,
+			|| Err::<BatchPoK<C>, _>(SynthesisError::AssignmentMissing),
+			AllocationMode::Witness,
+		)
+		.unwrap();
+		assert_eq!(var.ciphertexts.len(), 2);
+	}
+}
@@ -0,0 +1,181 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Groth16 Proofs of Dealing
+//!
+//! A [`DealingCircuit`] proven with Groth16 over a pairing whose scalar field
+//! embeds the ciphertext group (e.g. JubJub in BLS12-381), so that a chain
+//! checks a whole dealing with one pairing equation over a single public
+//! input, the [`dealing_digest`], rather than verifying n proofs of
+//! knowledge. Computing the digest hashes the dealing once but needs no
+//! group operations.
+//!
+//! The proofs of the dealing must be made with `BatchPoK::prove_for_circuit`.
+//! The keys are specific to the shape of a dealing (n recipients of k
+//! ciphertexts each) and come from a trusted setup, whose randomness must be
+//! discarded. Anyone holding a dealing can prove it, since the circuit's
+//! witnesses are the public proofs.
+
+use crate::{
+	keys::PublicKey,
+	proofs::{
+		constraints::{dealing_digest, DealingCircuit},
+		hashed_el_gamal_sigma::BatchPoK,
+	},
+	rng::SecureRng,
+};
+use ark_ec::{pairing::Pairing, CurveGroup};
+use ark_ff::ToConstraintField;
+use ark_groth16::{prepare_verifying_key, Groth16};
+pub use ark_groth16::{Proof, ProvingKey, VerifyingKey};
+use ark_r1cs_std::{groups::CurveVar, ToConstraintFieldGadget};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::vec::Vec;
+
+/// generate the proving key of dealings to `n` recipients of `k` ciphertexts
+/// each, which holds the verifying key as `vk`
+///
+/// * `n`: The number of recipients
+/// * `k`: The number of ciphertexts per recipient
+/// * `rng`: The randomness of the setup, to be discarded
+pub fn setup_dealing<E, C, GG, R>(
+	n: usize,
+	k: usize,
+	mut rng: R,
+) -> Result<ProvingKey<E>, SynthesisError>
+where
+	E: Pairing,
+	C: CurveGroup + ToConstraintField<E::ScalarField>,
+	GG: CurveVar<C, E::ScalarField> + ToConstraintFieldGadget<E::ScalarField>,
+	R: SecureRng,
+{
+	let circuit = DealingCircuit::<C, E::ScalarField, GG>::setup(n, k);
+	Groth16::<E>::generate_random_parameters_with_reduction(circuit, &mut rng)
+}
+
+/// prove a dealing correct
+/// fails with `Unsatisfiable` if a proof is invalid, was not made with
+/// `BatchPoK::prove_for_circuit` or has more or fewer ciphertexts than the
+/// first
+///
+/// * `pk`: The proving key of the dealing's shape, see `setup_dealing`
+/// * `proofs`: (recipient public key, proof) pairs
+/// * `rng`: Used to randomize the proof
+pub fn prove_dealing<E, C, GG, R>(
+	pk: &ProvingKey<E>,
+	proofs: Vec<(PublicKey<C>, BatchPoK<C>)>,
+	mut rng: R,
+) -> Result<Proof<E>, SynthesisError>
+where
+	E: Pairing,
+	C: CurveGroup + ToConstraintField<E::ScalarField>,
+	GG: CurveVar<C, E::ScalarField> + ToConstraintFieldGadget<E::ScalarField>,
+	R: SecureRng,
+{
+	// groth16 proves unsatisfied circuits without complaint, so the dealing
+	// is checked natively first
+	let k = proofs.first().map_or(0, |(_, proof)| proof.ciphertexts.len());
+	let valid = proofs.iter().all(|(pk, proof)| {
+		proof.ciphertexts.len() == k &&
+			proof.verify_for_circuit::<E::ScalarField>(*pk)
+	});
+	if !valid {
+		return Err(SynthesisError::Unsatisfiable);
+	}
+	let circuit = DealingCircuit::<C, E::ScalarField, GG>::new(proofs);
+	Groth16::<E>::create_random_proof_with_reduction(circuit, pk, &mut rng)
+}
+
+/// verify a proof of a dealing
+/// outputs true if the dealing is correct, false otherwise
+///
+/// * `vk`: The verifying key of the dealing's shape
+/// * `proofs`: (recipient public key, proof) pairs, whose responses are not
+///   read
+/// * `proof`: The proof output by `prove_dealing`
+pub fn verify_dealing_snark<E, C>(
+	vk: &VerifyingKey<E>,
+	proofs: &[(PublicKey<C>, BatchPoK<C>)],
+	proof: &Proof<E>,
+) -> bool
+where
+	E: Pairing,
+	C: CurveGroup + ToConstraintField<E::ScalarField>,
+{
+	let Ok(digest) = dealing_digest::<C, E::ScalarField>(proofs) else {
+		return false;
+	};
+	let pvk = prepare_verifying_key(vk);
+	Groth16::<E>::verify_proof(&pvk, proof, &[digest]).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::proofs::hashed_el_gamal::Ephemeral;
+	use ark_bls12_381::Bls12_381;
+	use ark_ec::Group;
+	use ark_ed_on_bls12_381::{
+		constraints::EdwardsVar, EdwardsProjective as C, Fq, Fr,
+	};
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+
+	fn dealing(
+		n: usize,
+		rng: &mut ChaCha20Rng,
+	) -> Vec<(PublicKey<C>, BatchPoK<C>)> {
+		(0..n)
+			.map(|_| {
+				let pk = PublicKey(C::generator() * Fr::rand(rng));
+				let proof = BatchPoK::prove_for_circuit::<Fq, _>(
+					&[Fr::rand(rng)],
+					pk,
+					&[Ephemeral::new(&mut *rng)],
+					&mut *rng,
+				)
+				.unwrap();
+				(pk, proof)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn dealings_are_proven_and_verified_succinctly() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let pk = setup_dealing::<Bls12_381, C, EdwardsVar, _>(2, 1, &mut rng)
+			.unwrap();
+		let proofs = dealing(2, &mut rng);
+		let proof =
+			prove_dealing::<_, _, EdwardsVar, _>(&pk, proofs.clone(), &mut rng)
+				.unwrap();
+		assert!(verify_dealing_snark(&pk.vk, &proofs, &proof));
+
+		// another dealing of the same shape
+		assert!(!verify_dealing_snark(&pk.vk, &dealing(2, &mut rng), &proof));
+		let mut tampered = proofs.clone();
+		tampered[1].1.ciphertexts[0].c2[0] ^= 1;
+		assert!(!verify_dealing_snark(&pk.vk, &tampered, &proof));
+
+		// a dealing whose proofs do not verify is not proven
+		let mut forged = proofs;
+		forged[0].1.z += Fr::from(1u64);
+		assert_eq!(
+			prove_dealing::<_, _, EdwardsVar, _>(&pk, forged, &mut rng).err(),
+			Some(SynthesisError::Unsatisfiable)
+		);
+	}
+}
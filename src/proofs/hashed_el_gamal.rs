@@ -23,60 +23,309 @@
 //!       want this? would be better if only the recipient could verify this
 //!       aspect... let's consider that later0)
 //!
+//! Decryption and ciphertext comparison do not branch on secret data: pads are
+//! applied with xor, equality is checked with `subtle`, and decrypted scalars
+//! are decoded with [`decode_scalar`]. The scalar multiplication sk * c1 is
+//! still variable time, see the Security section of the README.
 
-use crate::proofs::ser::{ark_de, ark_se};
+#[cfg(feature = "serde")]
+use crate::proofs::ser::{ark_de_point, ark_se, array_de, array_se};
+#[cfg(feature = "verify")]
+use crate::{error::ProofError, keys::PublicKey, proofs::ser::HashWriter};
+use crate::{
+	error::SerializationError,
+	proofs::ser::{fmt_hex, parse_hex},
+	wipe,
+};
+#[cfg(feature = "participant")]
+use crate::{keys::SecretKey, rng::SecureRng};
 use alloc::borrow::ToOwned;
 use ark_ec::CurveGroup;
-use ark_ff::UniformRand;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{rand::Rng, vec::Vec};
-use core::marker::PhantomData;
-use serde::{Deserialize, Serialize};
+use ark_ff::PrimeField;
+#[cfg(feature = "participant")]
+use ark_ff::{UniformRand, Zero};
+use ark_serialize::{
+	CanonicalDeserialize, CanonicalSerialize, Compress, Read, Valid, Validate,
+};
+use ark_std::vec::Vec;
+use core::{fmt, marker::PhantomData, str::FromStr};
+#[cfg(feature = "verify")]
 use sha2::Digest;
+#[cfg(feature = "verify")]
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use subtle::{Choice, ConstantTimeEq};
+#[cfg(feature = "zeroize")]
+use zeroize::ZeroizeOnDrop;
 
 pub fn cross_product<const N: usize>(a: &[u8; N], b: &[u8; N]) -> [u8; N] {
 	let mut o = a.to_owned();
-	for (i, ri) in o.iter_mut().enumerate().take(N) {
-		*ri ^= b[i];
-	}
+	xor_in_place(&mut o, b);
 	o
 }
 
-/// the message type required for the hashed el gamal variant
-pub type Message = [u8; 32];
-
-/// the ciphertext type
-#[derive(
-	Clone,
-	PartialEq,
-	Debug,
-	Serialize,
-	Deserialize,
-	CanonicalDeserialize,
-	CanonicalSerialize,
-)]
-pub struct Ciphertext<C: CurveGroup> {
-	#[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+/// xor `src` into `dst` in place, over the length of the shorter slice
+///
+/// the bulk of the input is processed as u64 words (which the compiler is free
+/// to vectorize), with a byte-by-byte fallback for the remaining tail
+pub fn xor_in_place(dst: &mut [u8], src: &[u8]) {
+	const WORD: usize = core::mem::size_of::<u64>();
+	let len = dst.len().min(src.len());
+	let (dst, src) = (&mut dst[..len], &src[..len]);
+
+	let mut dst_words = dst.chunks_exact_mut(WORD);
+	let mut src_words = src.chunks_exact(WORD);
+	for (d, s) in (&mut dst_words).zip(&mut src_words) {
+		let x = u64::from_ne_bytes((&*d).try_into().expect("chunk is a word")) ^
+			u64::from_ne_bytes(s.try_into().expect("chunk is a word"));
+		d.copy_from_slice(&x.to_ne_bytes());
+	}
+	for (d, s) in
+		dst_words.into_remainder().iter_mut().zip(src_words.remainder())
+	{
+		*d ^= s;
+	}
+}
+
+/// the message type required for the hashed el gamal variant, 32 bytes by
+/// default
+pub type Message<const N: usize = 32> = [u8; N];
+
+/// a message of two 32 byte blocks, e.g. a 64 byte seed
+/// its pad is squeezed from the XOF in one pass (see `kdf`), so the pads of
+/// the two blocks are independent
+pub type Message64 = Message<64>;
+
+/// an integrity tag over a ciphertext, keyed by the shared point
+pub type Tag = [u8; 16];
+
+/// a short tag that lets the recipient of a ciphertext recognise it with one
+/// hash once it knows sk * c1, and tells anyone else nothing about the
+/// recipient
+pub type DetectionTag = [u8; 8];
+
+/// domain separator for deriving the tag key from the shared point
+#[cfg(feature = "verify")]
+const TAG_KEY_LABEL: &[u8] = b"acss/hashed-el-gamal/tag-key";
+/// domain separator for deriving the pad from the shared point
+#[cfg(feature = "verify")]
+pub(crate) const PAD_LABEL: &[u8] = b"acss/hashed-el-gamal/pad";
+/// domain separator for deriving pads of other than 32 bytes with an XOF
+#[cfg(feature = "verify")]
+const PAD_XOF_LABEL: &[u8] = b"acss/hashed-el-gamal/pad-xof";
+#[cfg(feature = "participant")]
+/// domain separator for deriving the keys of one chunk of a byte string
+const CHUNK_LABEL: &[u8] = b"acss/hashed-el-gamal/chunk";
+#[cfg(feature = "participant")]
+/// domain separator for deriving detection tags from the shared point
+const DETECTION_LABEL: &[u8] = b"acss/hashed-el-gamal/detection";
+#[cfg(feature = "participant")]
+/// the length prefix of a byte string encrypted in chunks
+const LENGTH_PREFIX: usize = core::mem::size_of::<u64>();
+
+/// decode a scalar from a decrypted message without branching on its value
+/// outputs the decoded scalar and a choice that is set iff the message is the
+/// canonical (compressed) encoding of that scalar
+pub fn decode_scalar<F: PrimeField>(message: &Message) -> (F, Choice) {
+	let scalar = F::from_le_bytes_mod_order(message);
+	let mut canonical = Vec::with_capacity(message.len());
+	let encoded =
+		Choice::from(scalar.serialize_compressed(&mut canonical).is_ok() as u8);
+	let is_canonical = encoded & canonical[..].ct_eq(&message[..]);
+	wipe(&mut canonical);
+	(scalar, is_canonical)
+}
+
+/// the ciphertext type, for messages of N bytes (32 by default)
+#[derive(Clone, Debug, CanonicalSerialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ciphertext<C: CurveGroup, const N: usize = 32> {
+	#[cfg_attr(
+		feature = "serde",
+		serde(serialize_with = "ark_se", deserialize_with = "ark_de_point")
+	)]
 	pub c1: C,
-	pub c2: [u8; 32],
+	#[cfg_attr(
+		feature = "serde",
+		serde(serialize_with = "array_se", deserialize_with = "array_de")
+	)]
+	pub c2: [u8; N],
+	/// authenticates c1 and c2 to the holder of the secret key
+	pub tag: Tag,
 }
 
-impl<C: CurveGroup> Ciphertext<C> {
+/// the ciphertext of a `Message64`
+pub type Ciphertext64<C> = Ciphertext<C, 64>;
+
+impl<C: CurveGroup, const N: usize> Ciphertext<C, N> {
 	/// aggregate two ciphertexts C = <u, v> and C' = <u', v'> by
 	/// calculating C'' = (u + u', v (+) v')
 	///
-	/// This is useful in the hashed el gamal sigma protocol
-	pub fn add(self, ct: Ciphertext<C>) -> Self {
-		Ciphertext {
-			c1: self.c1 + ct.c1,
-			c2: cross_product::<32>(&self.c2, &ct.c2),
+	/// This is useful in the hashed el gamal sigma protocol. The tags are
+	/// xored as well, but the tag of an aggregate does not authenticate it
+	pub fn add(mut self, ct: Ciphertext<C, N>) -> Self {
+		self.add_assign(&ct);
+		self
+	}
+
+	/// aggregate a ciphertext C' = <u', v'> into this one in place, i.e.
+	/// C <- (u + u', v (+) v')
+	pub fn add_assign(&mut self, ct: &Ciphertext<C, N>) {
+		self.c1 += ct.c1;
+		xor_in_place(&mut self.c2, &ct.c2);
+		xor_in_place(&mut self.tag, &ct.tag);
+	}
+
+	/// aggregate a collection of ciphertexts without cloning them
+	/// an empty collection aggregates to the identity <0, 0^N> (with a zero
+	/// tag)
+	pub fn aggregate<'a>(
+		cts: impl Iterator<Item = &'a Ciphertext<C, N>>,
+	) -> Self
+	where
+		C: 'a,
+	{
+		let identity =
+			Ciphertext { c1: C::zero(), c2: [0u8; N], tag: [0u8; 16] };
+		cts.fold(identity, |mut acc, ct| {
+			acc.add_assign(ct);
+			acc
+		})
+	}
+}
+
+impl<C: CurveGroup, const N: usize> Valid for Ciphertext<C, N> {
+	/// c1 must be in the prime order subgroup and must not be the identity
+	fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+		self.c1.check()?;
+		if self.c1.is_zero() {
+			return Err(ark_serialize::SerializationError::InvalidData);
 		}
+		Ok(())
 	}
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Error {
-	InvalidBufferSize,
+impl<C: CurveGroup, const N: usize> CanonicalDeserialize for Ciphertext<C, N> {
+	fn deserialize_with_mode<R: Read>(
+		mut reader: R,
+		compress: Compress,
+		validate: Validate,
+	) -> Result<Self, ark_serialize::SerializationError> {
+		let c1 = C::deserialize_with_mode(&mut reader, compress, validate)?;
+		let c2 =
+			<[u8; N]>::deserialize_with_mode(&mut reader, compress, validate)?;
+		let tag = Tag::deserialize_with_mode(reader, compress, validate)?;
+		let ciphertext = Ciphertext { c1, c2, tag };
+		if let Validate::Yes = validate {
+			ciphertext.check()?;
+		}
+		Ok(ciphertext)
+	}
+}
+
+impl<C: CurveGroup, const N: usize> fmt::Display for Ciphertext<C, N> {
+	/// 0x-prefixed hex of the compressed encoding
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt_hex(self, f)
+	}
+}
+
+impl<C: CurveGroup, const N: usize> FromStr for Ciphertext<C, N> {
+	type Err = SerializationError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse_hex(s)
+	}
+}
+
+/// SCALE encoded as the byte vector of the compressed encoding
+#[cfg(feature = "scale")]
+impl<C: CurveGroup, const N: usize> codec::Encode for Ciphertext<C, N> {
+	fn encode_to<O: codec::Output + ?Sized>(&self, dest: &mut O) {
+		crate::proofs::ser::scale_encode(self, dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl<C: CurveGroup, const N: usize> codec::Decode for Ciphertext<C, N> {
+	fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+		crate::proofs::ser::scale_decode(input)
+	}
+}
+
+impl<C: CurveGroup, const N: usize> ConstantTimeEq for Ciphertext<C, N> {
+	/// compare the compressed encodings of c1, the c2 bytes, and the tags
+	/// without short-circuiting
+	fn ct_eq(&self, other: &Self) -> Choice {
+		let mut lhs = Vec::new();
+		let mut rhs = Vec::new();
+		let encoded = self.c1.serialize_compressed(&mut lhs).is_ok() &
+			other.c1.serialize_compressed(&mut rhs).is_ok();
+		Choice::from(encoded as u8) &
+			lhs.ct_eq(&rhs) &
+			self.c2[..].ct_eq(&other.c2[..]) &
+			self.tag.ct_eq(&other.tag)
+	}
+}
+
+impl<C: CurveGroup, const N: usize> PartialEq for Ciphertext<C, N> {
+	fn eq(&self, other: &Self) -> bool {
+		self.ct_eq(other).into()
+	}
+}
+
+/// an ephemeral key (r, rP) used to encrypt a message
+///
+/// An ephemeral can be shared by ciphertexts addressed to *different*
+/// recipients (i.e. multi-recipient El Gamal), so that c1 = rP is computed once
+/// rather than once per recipient. It must never be used twice for the same
+/// recipient, since both messages would then be padded with the same H(r * pk).
+#[cfg(feature = "participant")]
+#[derive(Clone)]
+pub struct Ephemeral<C: CurveGroup> {
+	r: C::ScalarField,
+	c1: C,
+}
+
+#[cfg(feature = "participant")]
+impl<C: CurveGroup> Ephemeral<C> {
+	/// sample a fresh ephemeral key r <- Zp* and compute rP for the default
+	/// generator P
+	/// r = 0 would expose the message (the pad would be H(0)), so it is
+	/// resampled
+	pub fn new<R: SecureRng>(mut rng: R) -> Self {
+		let r = loop {
+			let r = C::ScalarField::rand(&mut rng);
+			if !r.is_zero() {
+				break r;
+			}
+		};
+		Self { r, c1: C::generator().mul(r) }
+	}
+
+	/// the public part of the ephemeral key, i.e. the c1 of its ciphertexts
+	pub fn c1(&self) -> C {
+		self.c1
+	}
+}
+
+#[cfg(all(feature = "participant", feature = "zeroize"))]
+impl<C: CurveGroup> Drop for Ephemeral<C> {
+	fn drop(&mut self) {
+		wipe(&mut self.r);
+	}
+}
+
+#[cfg(all(feature = "participant", feature = "zeroize"))]
+impl<C: CurveGroup> ZeroizeOnDrop for Ephemeral<C> {}
+
+#[cfg(feature = "participant")]
+impl<C: CurveGroup> fmt::Debug for Ephemeral<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Ephemeral")
+			.field("r", &format_args!("<redacted>"))
+			.field("c1", &self.c1)
+			.finish()
+	}
 }
 
 /// the hashed el gamal encryption scheme
@@ -89,90 +338,624 @@ pub struct HashedElGamal<C: CurveGroup> {
 // the HashedElGAmal type but this is fine for now
 
 impl<C: CurveGroup> HashedElGamal<C> {
+	#[cfg(feature = "participant")]
 	/// Encrypt the hash of a message
 	/// r <- Zp
+	/// <c1, c2, tag> = <rP, pk (+) H(message), MAC(c1, c2)>
+	/// where the MAC is keyed by a key derived from the shared point r * pk
+	/// and P is the default generator, which decryption assumes pk is over
+	pub fn encrypt<R: SecureRng, const N: usize>(
+		message: Message<N>,
+		pk: PublicKey<C>,
+		rng: R,
+	) -> Result<Ciphertext<C, N>, ProofError> {
+		Self::encrypt_with_ephemeral(message, pk, &Ephemeral::new(rng))
+	}
+
+	/// Encrypt the hash of a message, drawing r from the operating system
+	#[cfg(all(feature = "participant", feature = "getrandom"))]
+	pub fn encrypt_with_os_rng<const N: usize>(
+		message: Message<N>,
+		pk: PublicKey<C>,
+	) -> Result<Ciphertext<C, N>, ProofError> {
+		Self::encrypt(message, pk, crate::rng::OsRng)
+	}
+
+	#[cfg(feature = "participant")]
+	/// Encrypt the hash of a message under a given ephemeral key (r, rP)
 	/// <c1, c2> = <rP, pk (+) H(message)>
-	/// note that there is no MAC here, we produce that in the hashed el gamal
-	/// sigma protocl impl
-	pub fn encrypt<R: Rng + Sized>(
-		message: Message,
-		pk: C,
-		generator: C,
-		mut rng: R,
-	) -> Result<Ciphertext<C>, Error> {
-		let r = C::ScalarField::rand(&mut rng);
-		let c1 = generator.mul(r);
-		let inner = pk.mul(r);
-
-		let c2: [u8; 32] = cross_product::<32>(
-			&hash(inner).try_into().map_err(|_| Error::InvalidBufferSize)?, /*  but how can I test this? need to revist h2 impl */
-			&message,
-		);
+	/// this lets a dealer reuse one ephemeral across many recipients, see
+	/// [`Ephemeral`]
+	pub fn encrypt_with_ephemeral<const N: usize>(
+		message: Message<N>,
+		pk: PublicKey<C>,
+		ephemeral: &Ephemeral<C>,
+	) -> Result<Ciphertext<C, N>, ProofError> {
+		let PublicKey(pk) = pk;
+		// r * 0 = 0 for every r, so the pad would be public
+		if pk.is_zero() {
+			return Err(ProofError::InvalidPublicKey);
+		}
+		let mut inner = pk.mul(ephemeral.r);
+		let keys = kdf(inner, &ephemeral.c1, &pk);
+		wipe(&mut inner);
+		let (mut pad, mut tag_key) = keys?;
 
-		Ok(Ciphertext { c1, c2 })
+		let c2: [u8; N] = cross_product::<N>(&pad, &message);
+		let tag = tag(&tag_key, &ephemeral.c1, &c2);
+
+		wipe(&mut pad);
+		wipe(&mut tag_key);
+		Ok(Ciphertext { c1: ephemeral.c1, c2, tag: tag? })
 	}
 
+	#[cfg(feature = "participant")]
 	/// decrypt a ciphertext using a secret key, recovered a scalar field
-	/// element TODO: error handling
-	pub fn decrypt(
-		sk: C::ScalarField,
-		ciphertext: Ciphertext<C>,
-	) -> Result<Message, Error> {
+	/// element
+	/// fails with `AuthenticationFailed` if the tag does not match, e.g. under
+	/// the wrong key or if c1 or c2 were modified
+	///
+	/// the pad is bound to the recipient key, taken to be sk * G for the
+	/// default generator G
+	pub fn decrypt<const N: usize>(
+		sk: &SecretKey<C>,
+		ciphertext: &Ciphertext<C, N>,
+	) -> Result<Message<N>, ProofError> {
+		let pk = sk.public_key();
 		// s = sk * c1
-		let s = ciphertext.c1.mul(sk);
+		let mut s = ciphertext.c1.mul(sk.expose_secret());
+		let keys = kdf(s, &ciphertext.c1, &pk.0);
+		wipe(&mut s);
+		let (pad, tag_key) = keys?;
+		open(pad, tag_key, ciphertext)
+	}
+
+	/// decrypt a ciphertext given the shared point sk * c1 rather than sk,
+	/// e.g. as combined from threshold decryption shares, failing with
+	/// `AuthenticationFailed` if the tag does not match
+	#[cfg(feature = "verify")]
+	pub(crate) fn decrypt_with_shared<const N: usize>(
+		mut shared: C,
+		pk: &PublicKey<C>,
+		ciphertext: &Ciphertext<C, N>,
+	) -> Result<Message<N>, ProofError> {
+		let keys = kdf(shared, &ciphertext.c1, &pk.0);
+		wipe(&mut shared);
+		let (pad, tag_key) = keys?;
+		open(pad, tag_key, ciphertext)
+	}
+
+	#[cfg(feature = "participant")]
+	/// decrypt a ciphertext without checking its tag
+	///
+	/// this is only safe when c1 and c2 are authenticated some other way, as
+	/// in the sigma protocol where the proof of knowledge binds them. A
+	/// tampered ciphertext decrypts to garbage rather than failing.
+	pub fn decrypt_unauthenticated<const N: usize>(
+		sk: &SecretKey<C>,
+		ciphertext: &Ciphertext<C, N>,
+	) -> Result<Message<N>, ProofError> {
+		Self::decrypt_unauthenticated_with_pk(sk, &sk.public_key(), ciphertext)
+	}
+
+	#[cfg(feature = "participant")]
+	/// decrypt a ciphertext without checking its tag, as with
+	/// `decrypt_unauthenticated`, for callers that already hold pk = sk * G
+	pub(crate) fn decrypt_unauthenticated_with_pk<const N: usize>(
+		sk: &SecretKey<C>,
+		pk: &PublicKey<C>,
+		ciphertext: &Ciphertext<C, N>,
+	) -> Result<Message<N>, ProofError> {
+		// s = sk * c1
+		let mut s = ciphertext.c1.mul(sk.expose_secret());
+		let keys = kdf(s, &ciphertext.c1, &pk.0);
+		wipe(&mut s);
+		let (mut pad, mut tag_key) = keys?;
 		// m = s (+) c2
-		Ok(cross_product::<32>(
-			&hash(s).try_into().map_err(|_| Error::InvalidBufferSize)?,
-			&ciphertext.c2,
-		))
+		let message = cross_product::<N>(&pad, &ciphertext.c2);
+
+		wipe(&mut pad);
+		wipe(&mut tag_key);
+		Ok(message)
+	}
+
+	#[cfg(feature = "participant")]
+	/// decrypt a ciphertext produced before the pad was bound to c1 and the
+	/// recipient key and before ciphertexts carried a tag
+	/// fails with `Decoding` if `bytes` is not such a ciphertext
+	///
+	/// `bytes` is the compressed c1 followed by the 32 byte c2, and the pad is
+	/// H(sk * c1). Nothing authenticates these ciphertexts, so a tampered one
+	/// decrypts to garbage rather than failing.
+	pub fn decrypt_legacy(
+		sk: &SecretKey<C>,
+		mut bytes: &[u8],
+	) -> Result<Message, ProofError> {
+		let c1 = C::deserialize_compressed(&mut bytes)
+			.map_err(|_| SerializationError::Decoding)?;
+		let c2: Message =
+			bytes.try_into().map_err(|_| SerializationError::Decoding)?;
+
+		let mut shared = c1.mul(sk.expose_secret());
+		let mut hasher = sha2::Sha256::new();
+		let written = shared.serialize_compressed(HashWriter(&mut hasher));
+		wipe(&mut shared);
+		written.map_err(|_| SerializationError::Encoding)?;
+		let mut pad: [u8; 32] = hasher.finalize().into();
+		let message = cross_product::<32>(&pad, &c2);
+		wipe(&mut pad);
+		Ok(message)
+	}
+
+	#[cfg(feature = "participant")]
+	/// decrypt many ciphertexts under the same secret key, failing with
+	/// `AuthenticationFailed` if any tag does not match
+	/// the shared points sk * c1 are normalized together, so the batch pays
+	/// for one field inversion rather than one per ciphertext. With the
+	/// `parallel` feature the batch is split across threads.
+	pub fn decrypt_batch<const N: usize>(
+		sk: &SecretKey<C>,
+		ciphertexts: &[Ciphertext<C, N>],
+	) -> Result<Vec<Message<N>>, ProofError> {
+		let (sk, pk) = (sk.expose_secret(), sk.public_key().0);
+		#[cfg(not(feature = "parallel"))]
+		{
+			decrypt_chunk(sk, &pk, ciphertexts)
+		}
+		#[cfg(feature = "parallel")]
+		{
+			use rayon::prelude::*;
+
+			let chunk_size =
+				ciphertexts.len().div_ceil(rayon::current_num_threads()).max(1);
+			let chunks = ciphertexts
+				.par_chunks(chunk_size)
+				.map(|chunk| decrypt_chunk(sk, &pk, chunk))
+				.collect::<Result<Vec<_>, _>>()?;
+			Ok(chunks.into_iter().flatten().collect())
+		}
+	}
+
+	#[cfg(feature = "participant")]
+	/// the detection tag of ciphertexts encrypted to `pk` under `ephemeral`
+	/// tag = H(label || r * pk || c1 || pk), truncated
+	///
+	/// * `pk`: The recipient's public key
+	/// * `ephemeral`: The ephemeral key the ciphertexts are encrypted under
+	pub fn detection_tag(
+		pk: PublicKey<C>,
+		ephemeral: &Ephemeral<C>,
+	) -> Result<DetectionTag, ProofError> {
+		let PublicKey(pk) = pk;
+		if pk.is_zero() {
+			return Err(ProofError::InvalidPublicKey);
+		}
+		let mut shared = pk.mul(ephemeral.r);
+		let tag = detection_tag(&shared, &ephemeral.c1, &pk);
+		wipe(&mut shared);
+		tag
+	}
+
+	#[cfg(feature = "participant")]
+	/// true if the detection tag of ciphertexts with `c1` encrypted to the
+	/// holder of `sk` is `tag`
+	///
+	/// * `sk`: The recipient's secret key
+	/// * `c1`: The ephemeral public key of the ciphertexts
+	/// * `tag`: The detection tag
+	pub fn detect(sk: &SecretKey<C>, c1: &C, tag: &DetectionTag) -> bool {
+		let mut shared = c1.mul(sk.expose_secret());
+		let expected = detection_tag(&shared, c1, &sk.public_key().0);
+		wipe(&mut shared);
+		expected.is_ok_and(|expected| bool::from(expected.ct_eq(tag)))
+	}
+
+	#[cfg(feature = "participant")]
+	/// encrypt a byte string of any length as a sequence of 32 byte chunks
+	/// under one ephemeral key r
+	///
+	/// the plaintext is prefixed with its length as a big endian u64 and
+	/// zero padded to a whole number of chunks. The pad and tag key of each
+	/// chunk are derived from its position and the number of chunks, so
+	/// chunks cannot be reordered, dropped or spliced between strings
+	/// without failing authentication.
+	///
+	/// * `plaintext`: The byte string
+	/// * `pk`: The recipient's public key
+	/// * `rng`: Used to sample r
+	pub fn encrypt_bytes<R: SecureRng>(
+		plaintext: &[u8],
+		pk: PublicKey<C>,
+		rng: R,
+	) -> Result<Vec<Ciphertext<C>>, ProofError> {
+		let PublicKey(pk) = pk;
+		if pk.is_zero() {
+			return Err(ProofError::InvalidPublicKey);
+		}
+		let count = (LENGTH_PREFIX + plaintext.len()).div_ceil(32);
+		let mut padded = vec![0u8; count * 32];
+		padded[..LENGTH_PREFIX]
+			.copy_from_slice(&(plaintext.len() as u64).to_be_bytes());
+		padded[LENGTH_PREFIX..LENGTH_PREFIX + plaintext.len()]
+			.copy_from_slice(plaintext);
+
+		let ephemeral = Ephemeral::new(rng);
+		let mut shared = pk.mul(ephemeral.r);
+		let ciphertexts = padded
+			.chunks_exact(32)
+			.enumerate()
+			.map(|(index, chunk)| {
+				let (mut pad, mut tag_key) =
+					chunk_kdf(&shared, &ephemeral.c1, &pk, index, count)?;
+				let mut c2 = [0u8; 32];
+				c2.copy_from_slice(chunk);
+				xor_in_place(&mut c2, &pad);
+				let tag = tag(&tag_key, &ephemeral.c1, &c2);
+				wipe(&mut pad);
+				wipe(&mut tag_key);
+				Ok(Ciphertext { c1: ephemeral.c1, c2, tag: tag? })
+			})
+			.collect();
+		wipe(&mut shared);
+		wipe(&mut padded);
+		ciphertexts
 	}
+
+	#[cfg(feature = "participant")]
+	/// decrypt a byte string encrypted with `encrypt_bytes`
+	/// fails with `AuthenticationFailed` if the chunks do not share their c1
+	/// or any tag does not match, and with `Decoding` if the length prefix or
+	/// the padding is malformed
+	///
+	/// the pad is bound to the recipient key, taken to be sk * G for the
+	/// default generator G
+	pub fn decrypt_bytes(
+		sk: &SecretKey<C>,
+		ciphertexts: &[Ciphertext<C>],
+	) -> Result<Vec<u8>, ProofError> {
+		let Some(first) = ciphertexts.first() else {
+			return Err(SerializationError::Decoding.into());
+		};
+		if ciphertexts.iter().any(|ct| ct.c1 != first.c1) {
+			return Err(ProofError::AuthenticationFailed);
+		}
+		let pk = sk.public_key();
+		let mut shared = first.c1.mul(sk.expose_secret());
+		let mut padded = Vec::with_capacity(ciphertexts.len() * 32);
+		for (index, ciphertext) in ciphertexts.iter().enumerate() {
+			let chunk =
+				chunk_kdf(&shared, &first.c1, &pk.0, index, ciphertexts.len())
+					.and_then(|(pad, tag_key)| open(pad, tag_key, ciphertext));
+			match chunk {
+				Ok(mut chunk) => {
+					padded.extend_from_slice(&chunk);
+					wipe(&mut chunk);
+				},
+				Err(e) => {
+					wipe(&mut shared);
+					wipe(&mut padded);
+					return Err(e);
+				},
+			}
+		}
+		wipe(&mut shared);
+
+		let mut prefix = [0u8; LENGTH_PREFIX];
+		prefix.copy_from_slice(&padded[..LENGTH_PREFIX]);
+		// the prefix is chosen by the sender, so its end is only trusted once
+		// it is known not to overflow and to fall in the last chunk
+		let len =
+			usize::try_from(u64::from_be_bytes(prefix)).ok().filter(|len| {
+				LENGTH_PREFIX.checked_add(*len).is_some_and(|end| {
+					end.div_ceil(32) == ciphertexts.len() &&
+						padded[end..].iter().all(|b| *b == 0)
+				})
+			});
+		let Some(len) = len else {
+			wipe(&mut padded);
+			return Err(SerializationError::Decoding.into());
+		};
+		padded.copy_within(LENGTH_PREFIX..LENGTH_PREFIX + len, 0);
+		padded.truncate(len);
+		Ok(padded)
+	}
+}
+
+#[cfg(feature = "participant")]
+fn decrypt_chunk<C: CurveGroup, const N: usize>(
+	sk: &C::ScalarField,
+	pk: &C,
+	ciphertexts: &[Ciphertext<C, N>],
+) -> Result<Vec<Message<N>>, ProofError> {
+	let mut shared: Vec<C> =
+		ciphertexts.iter().map(|ct| ct.c1.mul(sk)).collect();
+	let mut shared_affine = C::normalize_batch(&shared);
+	let messages = shared_affine
+		.iter()
+		.zip(ciphertexts.iter())
+		.map(|(s, ct)| {
+			let (pad, tag_key) = kdf(*s, &ct.c1, pk)?;
+			open(pad, tag_key, ct)
+		})
+		.collect();
+
+	wipe(&mut shared);
+	wipe(&mut shared_affine);
+	messages
+}
+
+/// check the tag of a ciphertext and remove its pad
+/// the message is computed and the keys wiped whether or not the tag matches,
+/// so the only branch is on the (public) outcome of the check
+#[cfg(feature = "verify")]
+fn open<C: CurveGroup, const N: usize>(
+	mut pad: [u8; N],
+	mut tag_key: [u8; 32],
+	ciphertext: &Ciphertext<C, N>,
+) -> Result<Message<N>, ProofError> {
+	let expected = tag(&tag_key, &ciphertext.c1, &ciphertext.c2);
+	let mut message = cross_product::<N>(&pad, &ciphertext.c2);
+	wipe(&mut pad);
+	wipe(&mut tag_key);
+
+	let authentic = expected?.ct_eq(&ciphertext.tag);
+	if !bool::from(authentic) {
+		wipe(&mut message);
+		return Err(ProofError::AuthenticationFailed);
+	}
+	Ok(message)
+}
+
+/// derive an N byte pad and the tag key from the shared point, c1, and the
+/// recipient key
+/// 32 byte pads are hashed with SHA256 (see `kdf32`), so ciphertexts of the
+/// default size are unchanged. Pads of any other size are squeezed from
+/// SHAKE256(label || N || shared || c1 || pk).
+#[cfg(feature = "verify")]
+pub(crate) fn kdf<C: CurveGroup, G: CanonicalSerialize, const N: usize>(
+	shared: G,
+	c1: &C,
+	pk: &C,
+) -> Result<([u8; N], [u8; 32]), ProofError> {
+	let (mut pad32, tag_key) = kdf32(&shared, c1, pk)?;
+	let mut pad = [0u8; N];
+	if N == 32 {
+		pad.copy_from_slice(&pad32);
+	} else {
+		let mut xof = sha3::Shake256::default();
+		xof.update(PAD_XOF_LABEL);
+		xof.update(&(N as u64).to_be_bytes());
+		shared
+			.serialize_compressed(HashWriter(&mut xof))
+			.and_then(|_| c1.serialize_compressed(HashWriter(&mut xof)))
+			.and_then(|_| pk.serialize_compressed(HashWriter(&mut xof)))
+			.map_err(|_| SerializationError::Encoding)?;
+		XofReader::read(&mut xof.finalize_xof(), &mut pad);
+	}
+	wipe(&mut pad32);
+	Ok((pad, tag_key))
+}
+
+#[cfg(feature = "participant")]
+/// the detection tag for the shared point, c1, and the recipient key
+pub(crate) fn detection_tag<C: CurveGroup>(
+	shared: &C,
+	c1: &C,
+	pk: &C,
+) -> Result<DetectionTag, ProofError> {
+	let mut hasher = sha2::Sha256::new();
+	Digest::update(&mut hasher, DETECTION_LABEL);
+	shared
+		.serialize_compressed(HashWriter(&mut hasher))
+		.and_then(|_| c1.serialize_compressed(HashWriter(&mut hasher)))
+		.and_then(|_| pk.serialize_compressed(HashWriter(&mut hasher)))
+		.map_err(|_| SerializationError::Encoding)?;
+	let digest: [u8; 32] = hasher.finalize().into();
+	let mut tag = [0u8; 8];
+	tag.copy_from_slice(&digest[..8]);
+	Ok(tag)
 }
 
-/// a map from G -> {0, 1}^{32}
-fn hash<G: CanonicalSerialize>(g: G) -> Vec<u8> {
-	// let mut out = Vec::with_capacity(g.compressed_size());
-	let mut out = Vec::new();
-	g.serialize_compressed(&mut out)
-		.expect("Enough space has been allocated in the buffer");
+/// derive the pad and tag key of the chunk at `index` of a byte string of
+/// `count` chunks from the shared point, c1, and the recipient key
+/// (pad || tag key) = SHAKE256(label || index || count || shared || c1 || pk)
+#[cfg(feature = "participant")]
+fn chunk_kdf<C: CurveGroup>(
+	shared: &C,
+	c1: &C,
+	pk: &C,
+	index: usize,
+	count: usize,
+) -> Result<([u8; 32], [u8; 32]), ProofError> {
+	let mut xof = sha3::Shake256::default();
+	xof.update(CHUNK_LABEL);
+	xof.update(&(index as u64).to_be_bytes());
+	xof.update(&(count as u64).to_be_bytes());
+	shared
+		.serialize_compressed(HashWriter(&mut xof))
+		.and_then(|_| c1.serialize_compressed(HashWriter(&mut xof)))
+		.and_then(|_| pk.serialize_compressed(HashWriter(&mut xof)))
+		.map_err(|_| SerializationError::Encoding)?;
+	let mut reader = xof.finalize_xof();
+	let (mut pad, mut tag_key) = ([0u8; 32], [0u8; 32]);
+	XofReader::read(&mut reader, &mut pad);
+	XofReader::read(&mut reader, &mut tag_key);
+	Ok((pad, tag_key))
+}
+
+/// derive the 32 byte pad and the tag key from the shared point, c1, and the
+/// recipient key
+/// pad = H(label || shared || c1 || pk), so a pad is never reused across
+/// recipients or ciphertexts even if the shared point is
+#[cfg(feature = "verify")]
+fn kdf32<C: CurveGroup, G: CanonicalSerialize>(
+	shared: &G,
+	c1: &C,
+	pk: &C,
+) -> Result<([u8; 32], [u8; 32]), ProofError> {
+	let derive = |label: &[u8]| -> Result<[u8; 32], ProofError> {
+		let mut hasher = sha2::Sha256::new();
+		Digest::update(&mut hasher, label);
+		shared
+			.serialize_compressed(HashWriter(&mut hasher))
+			.and_then(|_| c1.serialize_compressed(HashWriter(&mut hasher)))
+			.and_then(|_| pk.serialize_compressed(HashWriter(&mut hasher)))
+			.map_err(|_| SerializationError::Encoding)?;
+		Ok(hasher.finalize().into())
+	};
+	Ok((derive(PAD_LABEL)?, derive(TAG_KEY_LABEL)?))
+}
 
+/// the tag over (c1, c2) under `tag_key`
+#[cfg(feature = "verify")]
+fn tag<C: CurveGroup>(
+	tag_key: &[u8; 32],
+	c1: &C,
+	c2: &[u8],
+) -> Result<Tag, ProofError> {
 	let mut hasher = sha2::Sha256::new();
-	hasher.update(&out);
-	hasher.finalize().to_vec()
+	Digest::update(&mut hasher, tag_key);
+	c1.serialize_compressed(HashWriter(&mut hasher))
+		.map_err(|_| SerializationError::Encoding)?;
+	Digest::update(&mut hasher, c2);
+	let digest: [u8; 32] = hasher.finalize().into();
+	let mut tag = [0u8; 16];
+	tag.copy_from_slice(&digest[..16]);
+	Ok(tag)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "participant"))]
 mod test {
 
 	use super::*;
+	use crate::rng::test_rng;
 	use ark_bls12_381::{Fr, G1Projective as G1};
 	use ark_ec::Group;
-	use ark_ff::{One, UniformRand};
-	use ark_std::{ops::Mul, test_rng};
+	use ark_ff::{One, UniformRand, Zero};
+	use ark_std::vec::Vec;
 
 	#[test]
 	fn basic_encrypt_decrypt_works() {
-		let sk = Fr::rand(&mut test_rng());
-		let pk = G1::generator().mul(sk);
+		let sk = SecretKey::new(Fr::rand(&mut test_rng()));
+		let pk = sk.public_key();
 
 		let secret = Fr::rand(&mut test_rng());
 		let mut secret_bytes = Vec::new();
 		secret.serialize_compressed(&mut secret_bytes).unwrap();
 
-		let ct = HashedElGamal::encrypt(
+		let ct: Ciphertext<G1> = HashedElGamal::encrypt(
 			secret_bytes.clone().try_into().unwrap(),
 			pk,
-			G1::generator(),
 			&mut test_rng(),
 		)
 		.unwrap();
-		let recovered_bytes = HashedElGamal::decrypt(sk, ct).unwrap();
+		let recovered_bytes = HashedElGamal::decrypt(&sk, &ct).unwrap();
 		assert_eq!(recovered_bytes.to_vec(), secret_bytes);
 	}
 
+	#[test]
+	fn byte_strings_round_trip_in_chunks() {
+		let sk = SecretKey::new(Fr::rand(&mut test_rng()));
+		for len in [0usize, 1, 23, 24, 25, 100] {
+			let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+			let cts: Vec<Ciphertext<G1>> = HashedElGamal::encrypt_bytes(
+				&plaintext,
+				sk.public_key(),
+				&mut test_rng(),
+			)
+			.unwrap();
+			// the 8 byte length prefix is carried with the plaintext
+			assert_eq!(cts.len(), (8 + len).div_ceil(32));
+			assert_eq!(
+				HashedElGamal::decrypt_bytes(&sk, &cts).unwrap(),
+				plaintext
+			);
+		}
+	}
+
+	#[test]
+	fn chunks_cannot_be_reordered_or_dropped() {
+		let mut rng = test_rng();
+		let sk = SecretKey::new(Fr::rand(&mut rng));
+		let plaintext = [7u8; 100];
+		let cts: Vec<Ciphertext<G1>> =
+			HashedElGamal::encrypt_bytes(&plaintext, sk.public_key(), &mut rng)
+				.unwrap();
+
+		let mut swapped = cts.clone();
+		swapped.swap(1, 2);
+		let mut tampered = cts.clone();
+		tampered[3].c2[0] ^= 1;
+		let other = SecretKey::new(Fr::rand(&mut rng));
+		for (sk, cts) in [
+			(&sk, &swapped[..]),
+			(&sk, &cts[..3]),
+			(&sk, &tampered[..]),
+			(&other, &cts[..]),
+		] {
+			assert_eq!(
+				HashedElGamal::decrypt_bytes(sk, cts),
+				Err(ProofError::AuthenticationFailed)
+			);
+		}
+		assert_eq!(
+			HashedElGamal::<G1>::decrypt_bytes(&sk, &[]),
+			Err(SerializationError::Decoding.into())
+		);
+	}
+
+	#[test]
+	fn forged_length_prefixes_are_rejected() {
+		let mut rng = test_rng();
+		let sk = SecretKey::<G1>::new(Fr::rand(&mut rng));
+		let pk = sk.public_key().0;
+
+		// a sender encrypts a chunk whose prefix claims a length that
+		// overflows once the prefix itself is added
+		for len in [u64::MAX, u64::MAX - 3, u64::MAX - 7] {
+			let ephemeral = Ephemeral::<G1>::new(&mut rng);
+			let shared = pk * ephemeral.r;
+			let (pad, tag_key) =
+				chunk_kdf(&shared, &ephemeral.c1, &pk, 0, 1).unwrap();
+			let mut c2 = [0u8; 32];
+			c2[..LENGTH_PREFIX].copy_from_slice(&len.to_be_bytes());
+			xor_in_place(&mut c2, &pad);
+			let tag = tag(&tag_key, &ephemeral.c1, &c2).unwrap();
+			let forged = [Ciphertext { c1: ephemeral.c1, c2, tag }];
+			assert_eq!(
+				HashedElGamal::decrypt_bytes(&sk, &forged),
+				Err(SerializationError::Decoding.into())
+			);
+		}
+	}
+
+	#[test]
+	fn xor_in_place_matches_bytewise_xor() {
+		for len in 0..=67usize {
+			let a: Vec<u8> = (0..len).map(|i| i as u8).collect();
+			let b: Vec<u8> =
+				(0..len).map(|i| (i as u8).wrapping_mul(31)).collect();
+			let expected: Vec<u8> =
+				a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect();
+
+			let mut out = a.clone();
+			xor_in_place(&mut out, &b);
+			assert_eq!(out, expected);
+		}
+	}
+
+	#[test]
+	fn xor_in_place_stops_at_shorter_slice() {
+		let mut dst = [0xffu8; 12];
+		xor_in_place(&mut dst, &[0xffu8; 9]);
+		assert_eq!(dst[..9], [0u8; 9]);
+		assert_eq!(dst[9..], [0xffu8; 3]);
+	}
+
 	#[test]
 	fn can_add_ciphertexts() {
-		let sk = Fr::rand(&mut test_rng());
-		let pk = G1::generator().mul(sk);
+		let sk = SecretKey::new(Fr::rand(&mut test_rng()));
+		let pk = sk.public_key();
 
 		let secret = Fr::rand(&mut test_rng());
 		let mut secret_bytes = Vec::new();
@@ -186,53 +969,102 @@ mod test {
 		let mut combined_bytes = Vec::new();
 		combined.serialize_compressed(&mut combined_bytes).unwrap();
 
-		let ct = HashedElGamal::encrypt(
+		let ct: Ciphertext<G1> = HashedElGamal::encrypt(
 			secret_bytes.clone().try_into().unwrap(),
 			pk,
-			G1::generator(),
 			&mut test_rng(),
 		)
 		.unwrap();
 		let other_ct = HashedElGamal::encrypt(
 			other_secret_bytes.clone().try_into().unwrap(),
 			pk,
-			G1::generator(),
 			&mut test_rng(),
 		)
 		.unwrap();
 
 		let expected = Ciphertext {
 			c1: ct.c1 + other_ct.c1,
-			c2: cross_product::<32>(&ct.c2, &other_ct.c2).try_into().unwrap(),
+			c2: cross_product::<32>(&ct.c2, &other_ct.c2),
+			tag: cross_product::<16>(&ct.tag, &other_ct.tag),
 		};
 		assert_eq!(ct.add(other_ct), expected);
 	}
 
+	#[test]
+	fn aggregate_matches_pairwise_addition() {
+		let sk = SecretKey::new(Fr::rand(&mut test_rng()));
+		let pk = sk.public_key();
+
+		let cts: Vec<Ciphertext<G1>> = (0..3u8)
+			.map(|i| HashedElGamal::encrypt([i; 32], pk, test_rng()).unwrap())
+			.collect();
+
+		let expected = cts[0].clone().add(cts[1].clone()).add(cts[2].clone());
+		assert_eq!(Ciphertext::aggregate(cts.iter()), expected);
+
+		let mut acc = cts[0].clone();
+		acc.add_assign(&cts[1]);
+		acc.add_assign(&cts[2]);
+		assert_eq!(acc, expected);
+	}
+
+	#[test]
+	fn aggregate_of_nothing_is_the_identity() {
+		let identity = Ciphertext::<G1>::aggregate(core::iter::empty());
+		assert_eq!(
+			identity,
+			Ciphertext { c1: G1::zero(), c2: [0u8; 32], tag: [0u8; 16] }
+		);
+	}
+
+	#[test]
+	fn shared_ephemeral_encrypts_to_many_recipients() {
+		let sks: Vec<SecretKey<G1>> =
+			(0..3).map(|_| SecretKey::new(Fr::rand(&mut test_rng()))).collect();
+		let ephemeral = Ephemeral::new(test_rng());
+
+		for (i, sk) in sks.iter().enumerate() {
+			let message = [i as u8; 32];
+			let ct = HashedElGamal::encrypt_with_ephemeral(
+				message,
+				sk.public_key(),
+				&ephemeral,
+			)
+			.unwrap();
+			assert_eq!(ct.c1, ephemeral.c1());
+			assert_eq!(HashedElGamal::decrypt(sk, &ct).unwrap(), message);
+		}
+	}
+
 	#[test]
 	fn decryption_fails_with_bad_key() {
-		let sk = Fr::rand(&mut test_rng());
-		let bad_sk = Fr::one() + sk;
-		let pk = G1::generator().mul(sk);
+		let sk = SecretKey::new(Fr::rand(&mut test_rng()));
+		let bad_sk = SecretKey::new(Fr::one() + sk.expose_secret());
+		let pk = sk.public_key();
 
 		let secret = Fr::rand(&mut test_rng());
 		let mut secret_bytes = Vec::new();
 		secret.serialize_compressed(&mut secret_bytes).unwrap();
 
-		let ct = HashedElGamal::encrypt(
+		let ct: Ciphertext<G1> = HashedElGamal::encrypt(
 			secret_bytes.clone().try_into().unwrap(),
 			pk,
-			G1::generator(),
 			&mut test_rng(),
 		)
 		.unwrap();
-		let recovered_bytes = HashedElGamal::decrypt(bad_sk, ct).unwrap();
+		assert_eq!(
+			HashedElGamal::decrypt(&bad_sk, &ct),
+			Err(ProofError::AuthenticationFailed)
+		);
+		let recovered_bytes =
+			HashedElGamal::decrypt_unauthenticated(&bad_sk, &ct).unwrap();
 		assert!(recovered_bytes.to_vec() != secret_bytes);
 	}
 
 	#[test]
 	fn decryption_fails_with_bad_ciphertext() {
-		let sk = Fr::rand(&mut test_rng());
-		let pk = G1::generator().mul(sk);
+		let sk = SecretKey::<G1>::new(Fr::rand(&mut test_rng()));
+		let pk = sk.public_key();
 
 		let secret = Fr::rand(&mut test_rng());
 		let mut secret_bytes = Vec::new();
@@ -241,18 +1073,263 @@ mod test {
 		let mut ct = HashedElGamal::encrypt(
 			secret_bytes.clone().try_into().unwrap(),
 			pk,
-			G1::generator(),
 			&mut test_rng(),
 		)
 		.unwrap();
 		ct.c2 = [1; 32];
-		match HashedElGamal::decrypt(sk, ct) {
-			Ok(recovered_bytes) => {
-				assert!(recovered_bytes.to_vec() != secret_bytes);
-			},
-			Err(_) => {
-				// assert_eq!(e, );
-			},
+		assert_eq!(
+			HashedElGamal::decrypt(&sk, &ct),
+			Err(ProofError::AuthenticationFailed)
+		);
+		let recovered_bytes =
+			HashedElGamal::decrypt_unauthenticated(&sk, &ct).unwrap();
+		assert!(recovered_bytes.to_vec() != secret_bytes);
+	}
+
+	#[test]
+	fn decryption_fails_with_bad_tag_or_c1() {
+		let mut rng = test_rng();
+		let sk = SecretKey::<G1>::new(Fr::rand(&mut rng));
+		let pk = sk.public_key();
+		let ct = HashedElGamal::encrypt([7; 32], pk, &mut rng).unwrap();
+
+		let mut bad_tag = ct.clone();
+		bad_tag.tag[0] ^= 1;
+		assert_eq!(
+			HashedElGamal::decrypt(&sk, &bad_tag),
+			Err(ProofError::AuthenticationFailed)
+		);
+		// the unauthenticated path ignores the tag
+		assert_eq!(
+			HashedElGamal::decrypt_unauthenticated(&sk, &bad_tag).unwrap(),
+			[7; 32]
+		);
+
+		let mut bad_c1 = ct.clone();
+		bad_c1.c1 += G1::generator();
+		assert_eq!(
+			HashedElGamal::decrypt(&sk, &bad_c1),
+			Err(ProofError::AuthenticationFailed)
+		);
+
+		assert_eq!(
+			HashedElGamal::decrypt_batch(&sk, &[ct, bad_tag]),
+			Err(ProofError::AuthenticationFailed)
+		);
+	}
+
+	#[test]
+	fn decrypt_batch_matches_decrypt() {
+		let mut rng = test_rng();
+		let sk = SecretKey::new(Fr::rand(&mut rng));
+		let pk = sk.public_key();
+
+		let ciphertexts: Vec<Ciphertext<G1>> = (0..9u8)
+			.map(|i| HashedElGamal::encrypt([i; 32], pk, &mut rng).unwrap())
+			.collect();
+
+		let batch = HashedElGamal::decrypt_batch(&sk, &ciphertexts).unwrap();
+		assert_eq!(batch.len(), ciphertexts.len());
+		for (i, (m, ct)) in batch.iter().zip(ciphertexts.iter()).enumerate() {
+			assert_eq!(*m, [i as u8; 32]);
+			assert_eq!(*m, HashedElGamal::decrypt(&sk, ct).unwrap());
+		}
+		assert!(HashedElGamal::<G1>::decrypt_batch::<32>(&sk, &[])
+			.unwrap()
+			.is_empty());
+	}
+
+	#[test]
+	fn messages_of_any_length_round_trip() {
+		let mut rng = test_rng();
+		let sk = SecretKey::new(Fr::rand(&mut rng));
+		let pk = sk.public_key();
+
+		let short = [5u8; 48];
+		let ct = HashedElGamal::encrypt(short, pk, &mut rng).unwrap();
+		assert_eq!(HashedElGamal::decrypt(&sk, &ct), Ok(short));
+
+		let message = [7u8; 64];
+		let ct = HashedElGamal::encrypt(message, pk, &mut rng).unwrap();
+		assert_eq!(HashedElGamal::decrypt(&sk, &ct), Ok(message));
+		assert_eq!(
+			HashedElGamal::decrypt_batch(&sk, core::slice::from_ref(&ct)),
+			Ok(vec![message])
+		);
+
+		let mut bytes = Vec::new();
+		ct.serialize_compressed(&mut bytes).unwrap();
+		let decoded =
+			Ciphertext::<G1, 64>::deserialize_compressed(&bytes[..]).unwrap();
+		assert_eq!(decoded, ct);
+
+		let mut tampered = ct;
+		tampered.c2[63] ^= 1;
+		assert_eq!(
+			HashedElGamal::decrypt(&sk, &tampered),
+			Err(ProofError::AuthenticationFailed)
+		);
+
+		// the pads of different lengths are independent
+		let shared = G1::rand(&mut rng);
+		let c1 = G1::rand(&mut rng);
+		let (short, _) = kdf::<_, _, 32>(shared, &c1, &pk.0).unwrap();
+		let (long, _) = kdf::<_, _, 64>(shared, &c1, &pk.0).unwrap();
+		let (longer, _) = kdf::<_, _, 96>(shared, &c1, &pk.0).unwrap();
+		assert_ne!(short[..], long[..32]);
+		assert_ne!(long[..], longer[..64]);
+	}
+
+	#[test]
+	fn two_block_messages_round_trip_with_independent_pads() {
+		let mut rng = test_rng();
+		let sk = SecretKey::new(Fr::rand(&mut rng));
+		let pk = sk.public_key();
+
+		let seed: Message64 = core::array::from_fn(|_| u8::rand(&mut rng));
+		let ct: Ciphertext64<G1> =
+			HashedElGamal::encrypt(seed, pk, &mut rng).unwrap();
+		assert_eq!(HashedElGamal::decrypt(&sk, &ct), Ok(seed));
+
+		// a message with two equal blocks is not encrypted to two equal blocks
+		let ct: Ciphertext64<G1> =
+			HashedElGamal::encrypt([9u8; 64], pk, &mut rng).unwrap();
+		assert_ne!(ct.c2[..32], ct.c2[32..]);
+	}
+
+	#[test]
+	fn decode_scalar_rejects_non_canonical_encodings() {
+		let x = Fr::rand(&mut test_rng());
+		let mut bytes = [0u8; 32];
+		x.serialize_compressed(&mut bytes[..]).unwrap();
+
+		let (decoded, ok) = decode_scalar::<Fr>(&bytes);
+		assert!(bool::from(ok));
+		assert_eq!(decoded, x);
+
+		// the all-ones string is larger than the modulus
+		let (_, ok) = decode_scalar::<Fr>(&[0xff; 32]);
+		assert!(!bool::from(ok));
+	}
+
+	#[test]
+	fn ciphertext_equality_is_constant_time_eq() {
+		let sk = SecretKey::<G1>::new(Fr::rand(&mut test_rng()));
+		let pk = sk.public_key();
+		let ct = HashedElGamal::encrypt([1; 32], pk, &mut test_rng()).unwrap();
+
+		assert!(bool::from(ct.ct_eq(&ct.clone())));
+		let mut other = ct.clone();
+		other.c2[31] ^= 1;
+		assert!(!bool::from(ct.ct_eq(&other)));
+		assert_ne!(ct, other);
+		let mut other = ct.clone();
+		other.c1 += G1::generator();
+		assert_ne!(ct, other);
+	}
+
+	/// outputs zeros for the first `zeros` words, then defers to `rng`
+	struct ZerosThen<R> {
+		zeros: usize,
+		rng: R,
+	}
+
+	impl<R: ark_std::rand::RngCore> ark_std::rand::RngCore for ZerosThen<R> {
+		fn next_u32(&mut self) -> u32 {
+			self.next_u64() as u32
+		}
+
+		fn next_u64(&mut self) -> u64 {
+			if self.zeros > 0 {
+				self.zeros -= 1;
+				return 0;
+			}
+			self.rng.next_u64()
+		}
+
+		fn fill_bytes(&mut self, dest: &mut [u8]) {
+			for chunk in dest.chunks_mut(8) {
+				let word = self.next_u64().to_le_bytes();
+				chunk.copy_from_slice(&word[..chunk.len()]);
+			}
+		}
+
+		fn try_fill_bytes(
+			&mut self,
+			dest: &mut [u8],
+		) -> Result<(), ark_std::rand::Error> {
+			self.fill_bytes(dest);
+			Ok(())
+		}
+	}
+
+	impl<R: ark_std::rand::CryptoRng> ark_std::rand::CryptoRng for ZerosThen<R> {}
+
+	#[test]
+	fn ephemeral_resamples_zero_scalars() {
+		let mut rng = ZerosThen { zeros: 4, rng: test_rng() };
+		assert!(Fr::rand(&mut rng).is_zero());
+
+		// a zero scalar is sampled first, twice over
+		let rng = ZerosThen { zeros: 8, rng: test_rng() };
+		let ephemeral = Ephemeral::<G1>::new(rng);
+		assert!(!ephemeral.r.is_zero());
+		assert!(!ephemeral.c1().is_zero());
+	}
+
+	#[test]
+	fn encrypt_rejects_identity_public_key() {
+		let result = HashedElGamal::encrypt(
+			[1; 32],
+			PublicKey(G1::zero()),
+			&mut test_rng(),
+		);
+		assert_eq!(result, Err(ProofError::InvalidPublicKey));
+	}
+
+	#[test]
+	fn decrypts_legacy_ciphertexts() {
+		// produced by the untagged hashed El Gamal of the initial release,
+		// encrypting the bytes 0..32 to 0x5eed * G
+		let legacy = hex::decode(
+			"b80c23d4aa5989f8932c141cf9796036e6e6d61ac125301c9f76cd8dc62d2f58\
+			 2fcafb249ef4ade115e975f06d7b1af93b70bf54bf808000d56a70e537e2ff0b\
+			 b220e4a6ac0be60f26ad60d0d37a525f",
+		)
+		.unwrap();
+		let sk = SecretKey::<G1>::new(Fr::from(0x5eed_u64));
+		let expected: Message = core::array::from_fn(|i| i as u8);
+		assert_eq!(HashedElGamal::decrypt_legacy(&sk, &legacy), Ok(expected));
+
+		let other = SecretKey::<G1>::new(Fr::from(0xbeef_u64));
+		assert_ne!(
+			HashedElGamal::decrypt_legacy(&other, &legacy),
+			Ok(expected)
+		);
+		for bytes in
+			[&legacy[..legacy.len() - 1], &[legacy.clone(), vec![0]].concat()]
+		{
+			assert_eq!(
+				HashedElGamal::decrypt_legacy(&sk, bytes),
+				Err(SerializationError::Decoding.into())
+			);
+		}
+	}
+
+	#[test]
+	fn kdf_binds_c1_and_recipient_key() {
+		let mut rng = test_rng();
+		let shared = G1::rand(&mut rng);
+		let c1 = G1::rand(&mut rng);
+		let pk = G1::rand(&mut rng);
+		let other = G1::rand(&mut rng);
+
+		let (pad, tag_key) = kdf::<_, _, 32>(shared, &c1, &pk).unwrap();
+		assert_ne!(pad, tag_key);
+		for keys in [kdf(shared, &other, &pk), kdf(shared, &c1, &other)] {
+			let (other_pad, other_tag_key) = keys.unwrap();
+			assert_ne!(pad, other_pad);
+			assert_ne!(tag_key, other_tag_key);
 		}
 	}
 }
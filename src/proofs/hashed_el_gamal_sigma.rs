@@ -14,63 +14,104 @@
  * limitations under the License.
  */
 
-use crate::proofs::{
-	hashed_el_gamal::{Ciphertext, HashedElGamal},
-	ser::{ark_de, ark_se},
+#[cfg(feature = "serde")]
+use crate::proofs::ser::{ark_de, ark_se};
+use crate::{
+	error::{ProofError, SerializationError},
+	keys::PublicKey,
+	msm::{DefaultMsm, MsmBackend},
+	proofs::{
+		challenge::{
+			bound_challenge, Binding, ChallengeHash, DefaultChallenge,
+		},
+		hashed_el_gamal::Ciphertext,
+		ser::{fmt_hex, parse_hex},
+	},
+	rng::SecureRng,
+	trace::timed_span,
+};
+#[cfg(feature = "participant")]
+use crate::{
+	proofs::hashed_el_gamal::{Ephemeral, HashedElGamal, Message},
+	wipe,
 };
 use ark_ec::CurveGroup;
-use ark_ff::{fields::PrimeField, UniformRand, Zero};
+use ark_ff::{UniformRand, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{rand::Rng, vec::Vec};
-use serde::{Deserialize, Serialize};
-use sha3::{
-	digest::{ExtendableOutput, Update, XofReader},
-	Shake128,
-};
+use ark_std::vec::Vec;
+use core::{fmt, str::FromStr};
 
 // a public commitment for a point in the curve group's scalar field
 pub type Commitment<C> = C;
 
-/// Error types for the protocol
-#[derive(Debug)]
-pub enum Error {
-	SerializationError,
-	InvalidBufferAllocation,
-	EncryptionFailed,
-}
-
 // To investigate: How can I get these values from arkworks instead?
 pub const SERIALIZED_SCALAR_BUFFER_SIZE: usize = 32;
 pub const SERIALIZED_SIG_BUFFER_SIZE: usize = 48;
 
 /// the NIZK PoK with support for batched ciphertexts
-#[derive(
-	Clone,
-	PartialEq,
-	Debug,
-	Serialize,
-	Deserialize,
-	CanonicalSerialize,
-	CanonicalDeserialize,
-)]
+#[derive(Clone, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BatchPoK<C: CurveGroup> {
 	/// the commitment to the random value (e.g. rG)
-	#[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+	#[cfg_attr(
+		feature = "serde",
+		serde(serialize_with = "ark_se", deserialize_with = "ark_de")
+	)]
 	pub s: C,
 	/// the 'blinding' commitment to the random value (e.g. rH)
-	#[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+	#[cfg_attr(
+		feature = "serde",
+		serde(serialize_with = "ark_se", deserialize_with = "ark_de")
+	)]
 	pub t: C,
 	/// the challenge (e.g. z = k + es)
-	#[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+	#[cfg_attr(
+		feature = "serde",
+		serde(serialize_with = "ark_se", deserialize_with = "ark_de")
+	)]
 	pub z: C::ScalarField,
 	/// the commitment to the secret input
-	#[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+	#[cfg_attr(
+		feature = "serde",
+		serde(serialize_with = "ark_se", deserialize_with = "ark_de")
+	)]
 	pub commitment: C,
 	/// the (hashed el gamal) ciphertexts
 	pub ciphertexts: Vec<Ciphertext<C>>,
 }
 
+impl<C: CurveGroup> fmt::Display for BatchPoK<C> {
+	/// 0x-prefixed hex of the compressed encoding
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt_hex(self, f)
+	}
+}
+
+impl<C: CurveGroup> FromStr for BatchPoK<C> {
+	type Err = SerializationError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse_hex(s)
+	}
+}
+
+/// SCALE encoded as the byte vector of the compressed encoding
+#[cfg(feature = "scale")]
+impl<C: CurveGroup> codec::Encode for BatchPoK<C> {
+	fn encode_to<O: codec::Output + ?Sized>(&self, dest: &mut O) {
+		crate::proofs::ser::scale_encode(self, dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl<C: CurveGroup> codec::Decode for BatchPoK<C> {
+	fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+		crate::proofs::ser::scale_decode(input)
+	}
+}
+
 impl<C: CurveGroup> BatchPoK<C> {
+	#[cfg(feature = "participant")]
 	/// batch prove
 	/// works by aggregating the messages and then calling the prove function on
 	/// the result returns a vec of ciphertexts for decryption later on but
@@ -83,56 +124,129 @@ impl<C: CurveGroup> BatchPoK<C> {
 	/// TODO: this functions as is, but we should ideally bound the size of the
 	/// vectors and properly handle the error. For all of our cases, this
 	/// failing would be a critical fail.
-	pub fn prove<R: Rng + Sized>(
+	pub fn prove<R: SecureRng>(
 		messages: &[C::ScalarField],
-		pk: C,
+		pk: PublicKey<C>,
 		mut rng: R,
-	) -> Result<BatchPoK<C>, Error> {
-		let g = C::generator();
+	) -> Result<BatchPoK<C>, ProofError> {
+		let ephemerals: Vec<Ephemeral<C>> =
+			messages.iter().map(|_| Ephemeral::new(&mut rng)).collect();
+		Self::prove_with_ephemerals(messages, pk, &ephemerals, rng)
+	}
 
-		let aggregated_messages = (0..messages.len())
-			.fold(C::ScalarField::zero(), |acc, val| acc + messages[val]);
+	#[cfg(feature = "participant")]
+	/// batch prove, drawing randomness from the operating system
+	///
+	/// `messages`: the messages to encrypt and prove knowledge of
+	/// `pk`: the public key of the recipient
+	#[cfg(feature = "getrandom")]
+	pub fn prove_with_os_rng(
+		messages: &[C::ScalarField],
+		pk: PublicKey<C>,
+	) -> Result<BatchPoK<C>, ProofError> {
+		Self::prove(messages, pk, crate::rng::OsRng)
+	}
 
-		let batch_data = process_batch_data(messages, pk, g, &mut rng)?;
-		let ciphertexts =
-			batch_data.iter().map(|b| b.0.clone()).collect::<Vec<_>>();
+	#[cfg(feature = "participant")]
+	/// batch prove, encrypting the i-th message under the i-th ephemeral key
+	///
+	/// the ephemerals can be shared between proofs for *different* recipients
+	/// but each one must only be used once per recipient
+	///
+	/// `messages`: the messages to encrypt and prove knowledge of
+	/// `pk`: the public key of the recipient
+	/// `ephemerals`: one ephemeral key per message
+	/// `rng`: used to sample the commitment randomness
+	pub fn prove_with_ephemerals<R: SecureRng>(
+		messages: &[C::ScalarField],
+		pk: PublicKey<C>,
+		ephemerals: &[Ephemeral<C>],
+		rng: R,
+	) -> Result<BatchPoK<C>, ProofError> {
+		Self::prove_with_hash::<DefaultChallenge, R>(
+			messages, pk, ephemerals, rng,
+		)
+	}
+
+	#[cfg(feature = "participant")]
+	/// batch prove, as with `prove_with_ephemerals`, deriving the challenge
+	/// with the hash `H`
+	/// the proof must be verified with `verify_with_hash::<H>`
+	///
+	/// `messages`: the messages to encrypt and prove knowledge of
+	/// `pk`: the public key of the recipient
+	/// `ephemerals`: one ephemeral key per message
+	/// `rng`: used to sample the commitment randomness
+	pub fn prove_with_hash<H: ChallengeHash, R: SecureRng>(
+		messages: &[C::ScalarField],
+		pk: PublicKey<C>,
+		ephemerals: &[Ephemeral<C>],
+		rng: R,
+	) -> Result<BatchPoK<C>, ProofError> {
+		Self::prove_with_challenge(messages, pk, ephemerals, rng, H::challenge)
+	}
 
-		let mut batch_ciphertext: Ciphertext<C> = ciphertexts[0].clone();
-		for ct in ciphertexts.iter().skip(1) {
-			batch_ciphertext = batch_ciphertext.add(ct.clone());
+	#[cfg(feature = "participant")]
+	/// batch prove, as with `prove_with_ephemerals`, binding the proof to
+	/// associated data
+	/// the proof must be verified with `verify_bound` (or `verify_batch_bound`)
+	/// and the same binding
+	///
+	/// `messages`: the messages to encrypt and prove knowledge of
+	/// `pk`: the public key of the recipient
+	/// `ephemerals`: one ephemeral key per message
+	/// `binding`: the digest of the associated data, see `TranscriptBinder`
+	/// `rng`: used to sample the commitment randomness
+	pub fn prove_bound<R: SecureRng>(
+		messages: &[C::ScalarField],
+		pk: PublicKey<C>,
+		ephemerals: &[Ephemeral<C>],
+		binding: &Binding,
+		rng: R,
+	) -> Result<BatchPoK<C>, ProofError> {
+		Self::prove_with_challenge(messages, pk, ephemerals, rng, |s, t, ct| {
+			bound_challenge(binding, s, t, ct)
+		})
+	}
+
+	#[cfg(feature = "participant")]
+	pub(crate) fn prove_with_challenge<R: SecureRng>(
+		messages: &[C::ScalarField],
+		pk: PublicKey<C>,
+		ephemerals: &[Ephemeral<C>],
+		mut rng: R,
+		challenge: impl FnOnce(
+			&C,
+			&C,
+			&Ciphertext<C>,
+		) -> Result<C::ScalarField, ProofError>,
+	) -> Result<BatchPoK<C>, ProofError> {
+		if messages.len() != ephemerals.len() {
+			return Err(ProofError::EncryptionFailed);
 		}
+		let g = C::generator();
+
+		let batch_data = process_batch_data(messages, pk, g, ephemerals)?;
+		let batch_ciphertext: Ciphertext<C> =
+			Ciphertext::aggregate(batch_data.iter().map(|b| &b.0));
 
 		let batch_commitment = batch_data
 			.iter()
 			.map(|c| c.1)
 			.fold(C::zero(), |acc, val| acc + val);
+		let ciphertexts =
+			batch_data.into_iter().map(|b| b.0).collect::<Vec<_>>();
 
-		let k = C::ScalarField::rand(&mut rng);
+		let mut k = C::ScalarField::rand(&mut rng);
 		let s = g * k;
-		let t = pk * k;
-
-		let mut s_bytes = Vec::new();
-		let mut t_bytes = Vec::new();
-		s.serialize_compressed(&mut s_bytes)
-			.expect("The buffer must have sufficient space allocated");
-		t.serialize_compressed(&mut t_bytes)
-			.expect("The buffer must have sufficient space allocated");
-
-		// prepare input for shake128
-		let mut inputs = Vec::new();
-		inputs.push(s_bytes);
-		inputs.push(t_bytes);
-		let mut c1_bytes = Vec::new();
-		batch_ciphertext
-			.c1
-			.serialize_compressed(&mut c1_bytes)
-			.expect("The buffer must have sufficient space allocated");
-		inputs.push(c1_bytes);
-		inputs.push(batch_ciphertext.c2.to_vec());
-
-		let challenge: C::ScalarField =
-			C::ScalarField::from_be_bytes_mod_order(&shake128(inputs.as_ref()));
+		let t = pk.0 * k;
+
+		let challenge = challenge(&s, &t, &batch_ciphertext)?;
+		let mut aggregated_messages = (0..messages.len())
+			.fold(C::ScalarField::zero(), |acc, val| acc + messages[val]);
 		let z = k + challenge * aggregated_messages;
+		wipe(&mut k);
+		wipe(&mut aggregated_messages);
 		Ok(BatchPoK { s, t, z, commitment: batch_commitment, ciphertexts })
 	}
 
@@ -140,93 +254,191 @@ impl<C: CurveGroup> BatchPoK<C> {
 	/// ciphertext outputs true if the proof is valid, false otherwise
 	///
 	/// * `pk`: the expected public key
-	pub fn verify(&self, pk: C) -> bool {
+	pub fn verify(&self, pk: PublicKey<C>) -> bool {
+		self.verify_with_hash::<DefaultChallenge>(pk)
+	}
+
+	/// verify a proof whose challenge was derived with the hash `H`
+	///
+	/// * `pk`: the expected public key
+	pub fn verify_with_hash<H: ChallengeHash>(&self, pk: PublicKey<C>) -> bool {
+		self.verify_with_challenge(pk, H::challenge)
+	}
+
+	/// verify a proof bound to associated data with `prove_bound`
+	/// outputs false if the proof is invalid or bound to other data
+	///
+	/// * `pk`: the expected public key
+	/// * `binding`: the digest of the associated data, see `TranscriptBinder`
+	pub fn verify_bound(&self, pk: PublicKey<C>, binding: &Binding) -> bool {
+		self.verify_with_challenge(pk, |s, t, ct| {
+			bound_challenge(binding, s, t, ct)
+		})
+	}
+
+	pub(crate) fn verify_with_challenge(
+		&self,
+		pk: PublicKey<C>,
+		challenge: impl FnOnce(
+			&C,
+			&C,
+			&Ciphertext<C>,
+		) -> Result<C::ScalarField, ProofError>,
+	) -> bool {
 		// first we need to combine the ciphertexts
-		let mut ciphertext: Ciphertext<C> = self.ciphertexts[0].clone();
-		for ct in self.ciphertexts.iter().skip(1) {
-			ciphertext = ciphertext.add(ct.clone());
-		}
+		let ciphertext: Ciphertext<C> =
+			Ciphertext::aggregate(self.ciphertexts.iter());
 
-		let mut s_bytes = Vec::new();
-		let mut t_bytes = Vec::new();
-		self.s
-			.serialize_compressed(&mut s_bytes)
-			.expect("group element should exist");
-		self.t
-			.serialize_compressed(&mut t_bytes)
-			.expect("group element should exist");
-
-		let mut inputs = Vec::new();
-		inputs.push(s_bytes);
-		inputs.push(t_bytes);
-		let mut c1_bytes = Vec::new();
-		ciphertext
-			.c1
-			.serialize_compressed(&mut c1_bytes)
-			.expect("group elements should be serializable");
-		inputs.push(c1_bytes);
-		inputs.push(ciphertext.c2.to_vec());
-
-		let challenge: C::ScalarField =
-			C::ScalarField::from_be_bytes_mod_order(&shake128(inputs.as_ref()));
+		let Ok(challenge) = challenge(&self.s, &self.t, &ciphertext) else {
+			return false;
+		};
 
 		let zg = C::generator() * self.z;
-		let zh = pk * self.z;
+		let zh = pk.0 * self.z;
 
 		zg + zh == self.s + self.t + self.commitment * challenge
 	}
+
+	/// verify many proofs at once by checking a random linear combination of
+	/// their verification equations with a single multi-scalar multiplication
+	/// outputs true if every proof is valid, false if any proof is invalid
+	/// (except with negligible probability)
+	///
+	/// * `proofs`: (expected public key, proof) pairs
+	/// * `rng`: used to sample the combination coefficients
+	pub fn verify_batch<R: SecureRng>(
+		proofs: &[(PublicKey<C>, &BatchPoK<C>)],
+		rng: R,
+	) -> bool {
+		Self::verify_batch_with::<DefaultMsm, R>(proofs, rng)
+	}
+
+	/// verify many proofs at once, as with `verify_batch`, computing the
+	/// multi-scalar multiplication with the backend `M`
+	///
+	/// * `proofs`: (expected public key, proof) pairs
+	/// * `rng`: used to sample the combination coefficients
+	pub fn verify_batch_with<M: MsmBackend<C>, R: SecureRng>(
+		proofs: &[(PublicKey<C>, &BatchPoK<C>)],
+		rng: R,
+	) -> bool {
+		Self::verify_batch_with_challenge::<M, R>(proofs, rng, |_, s, t, ct| {
+			DefaultChallenge::challenge(s, t, ct)
+		})
+	}
+
+	/// verify many proofs bound to the same associated data with
+	/// `prove_bound`, as with `verify_batch`
+	/// outputs false if any proof is invalid or bound to other data
+	///
+	/// * `proofs`: (expected public key, proof) pairs
+	/// * `binding`: the digest of the associated data, see `TranscriptBinder`
+	/// * `rng`: used to sample the combination coefficients
+	pub fn verify_batch_bound<R: SecureRng>(
+		proofs: &[(PublicKey<C>, &BatchPoK<C>)],
+		binding: &Binding,
+		rng: R,
+	) -> bool {
+		Self::verify_batch_bound_with::<DefaultMsm, R>(proofs, binding, rng)
+	}
+
+	/// verify many bound proofs at once, as with `verify_batch_bound`,
+	/// computing the multi-scalar multiplication with the backend `M`
+	///
+	/// * `proofs`: (expected public key, proof) pairs
+	/// * `binding`: the digest of the associated data, see `TranscriptBinder`
+	/// * `rng`: used to sample the combination coefficients
+	pub fn verify_batch_bound_with<M: MsmBackend<C>, R: SecureRng>(
+		proofs: &[(PublicKey<C>, &BatchPoK<C>)],
+		binding: &Binding,
+		rng: R,
+	) -> bool {
+		Self::verify_batch_with_challenge::<M, R>(proofs, rng, |_, s, t, ct| {
+			bound_challenge(binding, s, t, ct)
+		})
+	}
+
+	/// verify many proofs at once, deriving the challenge of the i-th proof
+	/// with `challenge(i, s, t, ciphertext)`
+	pub(crate) fn verify_batch_with_challenge<
+		M: MsmBackend<C>,
+		R: SecureRng,
+	>(
+		proofs: &[(PublicKey<C>, &BatchPoK<C>)],
+		mut rng: R,
+		challenge: impl Fn(
+			usize,
+			&C,
+			&C,
+			&Ciphertext<C>,
+		) -> Result<C::ScalarField, ProofError>,
+	) -> bool {
+		timed_span!("acss::verify_batch", proofs = proofs.len());
+		let mut bases = Vec::with_capacity(4 * proofs.len() + 1);
+		let mut scalars = Vec::with_capacity(4 * proofs.len() + 1);
+		let mut zg = C::ScalarField::zero();
+
+		for (i, (pk, proof)) in proofs.iter().enumerate() {
+			let ciphertext = Ciphertext::aggregate(proof.ciphertexts.iter());
+			let Ok(challenge) = challenge(i, &proof.s, &proof.t, &ciphertext)
+			else {
+				return false;
+			};
+			let rho = C::ScalarField::rand(&mut rng);
+
+			// rho * (z(G + H) - s - t - e * commitment) = 0
+			zg += rho * proof.z;
+			bases.extend([pk.0, proof.s, proof.t, proof.commitment]);
+			scalars.extend([rho * proof.z, -rho, -rho, -(rho * challenge)]);
+		}
+		bases.push(C::generator());
+		scalars.push(zg);
+
+		let bases = C::normalize_batch(&bases);
+		M::msm(&bases, &scalars).is_zero()
+	}
 }
 
-fn process_batch_data<C: CurveGroup, R: Sized + Rng>(
+#[cfg(feature = "participant")]
+fn process_batch_data<C: CurveGroup>(
 	messages: &[C::ScalarField],
-	pk: C,
+	pk: PublicKey<C>,
 	g: C,
-	mut rng: R,
-) -> Result<Vec<(Ciphertext<C>, Commitment<C>)>, Error> {
+	ephemerals: &[Ephemeral<C>],
+) -> Result<Vec<(Ciphertext<C>, Commitment<C>)>, ProofError> {
 	let mut output = Vec::new();
 
-	for m in messages {
-		let mut message_bytes =
-			Vec::with_capacity(SERIALIZED_SCALAR_BUFFER_SIZE);
-		m.serialize_compressed(&mut message_bytes)
-			.map_err(|_| Error::InvalidBufferAllocation)?;
-		let ciphertext: Ciphertext<C> = HashedElGamal::encrypt(
-			message_bytes
-				.try_into()
-				.expect("The buffer must have sufficient space allocated"),
-			pk,
-			g,
-			&mut rng,
-		)
-		.map_err(|_| Error::InvalidBufferAllocation)?;
-		let commitment: Commitment<C> = g * m + pk * m;
+	for (m, ephemeral) in messages.iter().zip(ephemerals.iter()) {
+		// scalars that do not encode to exactly one message are rejected
+		if m.compressed_size() != SERIALIZED_SCALAR_BUFFER_SIZE {
+			return Err(SerializationError::InvalidBufferSize.into());
+		}
+		// the share is serialized on the stack, so no copy of it outlives
+		// the wipe below
+		let mut message: Message = [0u8; SERIALIZED_SCALAR_BUFFER_SIZE];
+		if m.serialize_compressed(&mut message[..]).is_err() {
+			wipe(&mut message);
+			return Err(SerializationError::Encoding.into());
+		}
+		let ciphertext =
+			HashedElGamal::encrypt_with_ephemeral(message, pk, ephemeral);
+		wipe(&mut message);
+		let ciphertext: Ciphertext<C> = ciphertext?;
+		let commitment: Commitment<C> = g * m + pk.0 * m;
 		output.push((ciphertext, commitment));
 	}
 
 	Ok(output)
 }
 
-/// shake128 hash some input
-fn shake128(input: &[Vec<u8>]) -> [u8; 32] {
-	let mut h = Shake128::default();
-
-	for item in input.iter() {
-		h.update(item);
-	}
-
-	let mut o = [0u8; 32];
-	// get challenge from hasher
-	h.finalize_xof().read(&mut o);
-	o
-}
-
-#[cfg(test)]
+#[cfg(all(test, feature = "participant"))]
 mod test {
 
 	use super::*;
+	use crate::{keys::SecretKey, rng::test_rng};
 	use ark_ec::Group;
 	use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
-	use ark_std::{ops::Mul, test_rng};
+	use ark_std::ops::Mul;
 
 	#[test]
 	pub fn hegs_batch_prove_and_verify_single_secret() {
@@ -239,16 +451,17 @@ mod test {
 		m.serialize_compressed(&mut m_bytes).unwrap();
 
 		// the public key
-		let g: JubJub = JubJub::generator().into();
-		let h: JubJub = g.mul(x).into();
+		let g: JubJub = JubJub::generator();
+		let h = PublicKey(g.mul(x));
 
-		let proof = BatchPoK::prove(&vec![m], h, test_rng()).unwrap();
+		let proof = BatchPoK::prove(&[m], h, test_rng()).unwrap();
 		let result = proof.verify(h);
-		assert_eq!(result, true);
+		assert!(result);
 
-		assert_eq!(1, proof.ciphertexts.clone().len());
+		assert_eq!(1, proof.ciphertexts.len());
 		let n =
-			HashedElGamal::decrypt(x, proof.ciphertexts[0].clone()).unwrap();
+			HashedElGamal::decrypt(&SecretKey::new(x), &proof.ciphertexts[0])
+				.unwrap();
 		assert_eq!(m_bytes, n);
 	}
 
@@ -267,43 +480,108 @@ mod test {
 		m2.serialize_compressed(&mut m2_bytes).unwrap();
 
 		// the public key
-		let g: JubJub = JubJub::generator().into();
-		let h: JubJub = g.mul(x).into();
+		let g: JubJub = JubJub::generator();
+		let h = PublicKey(g.mul(x));
 
-		let proof = BatchPoK::prove(&vec![m1, m2], h, test_rng()).unwrap();
+		let proof = BatchPoK::prove(&[m1, m2], h, test_rng()).unwrap();
 		let result = proof.verify(h);
-		assert_eq!(result, true);
+		assert!(result);
 
-		assert_eq!(2, proof.ciphertexts.clone().len());
+		assert_eq!(2, proof.ciphertexts.len());
 		let n1 =
-			HashedElGamal::decrypt(x, proof.ciphertexts[0].clone()).unwrap();
+			HashedElGamal::decrypt(&SecretKey::new(x), &proof.ciphertexts[0])
+				.unwrap();
 		assert_eq!(m1_bytes, n1);
 		let n2 =
-			HashedElGamal::decrypt(x, proof.ciphertexts[1].clone()).unwrap();
+			HashedElGamal::decrypt(&SecretKey::new(x), &proof.ciphertexts[1])
+				.unwrap();
 		assert_eq!(m2_bytes, n2);
 	}
 
+	#[test]
+	pub fn hegs_batch_verify_accepts_valid_and_rejects_invalid_proofs() {
+		let mut rng = test_rng();
+		let g: JubJub = JubJub::generator();
+		let keys: Vec<PublicKey<JubJub>> = (0..3)
+			.map(|_| {
+				PublicKey(g.mul(<JubJub as Group>::ScalarField::rand(&mut rng)))
+			})
+			.collect();
+		let proofs: Vec<BatchPoK<JubJub>> = keys
+			.iter()
+			.map(|h| {
+				let m = <JubJub as Group>::ScalarField::rand(&mut rng);
+				BatchPoK::prove(&[m, m], *h, &mut rng).unwrap()
+			})
+			.collect();
+
+		let batch: Vec<(PublicKey<JubJub>, &BatchPoK<JubJub>)> =
+			keys.iter().copied().zip(proofs.iter()).collect();
+		assert!(BatchPoK::verify_batch(&batch, &mut rng));
+		assert!(BatchPoK::<JubJub>::verify_batch(&[], &mut rng));
+
+		// the proof for the second key checked against the first key
+		let mut bad_batch = batch.clone();
+		bad_batch[1].0 = keys[0];
+		assert!(!BatchPoK::verify_batch(&bad_batch, &mut rng));
+	}
+
+	#[test]
+	pub fn hegs_batch_verify_bound_checks_the_binding() {
+		let mut rng = test_rng();
+		let g: JubJub = JubJub::generator();
+		let binding = Binding::of(b"session");
+		let keys: Vec<PublicKey<JubJub>> = (0..3)
+			.map(|_| {
+				PublicKey(g.mul(<JubJub as Group>::ScalarField::rand(&mut rng)))
+			})
+			.collect();
+		let proofs: Vec<BatchPoK<JubJub>> = keys
+			.iter()
+			.map(|h| {
+				let m = <JubJub as Group>::ScalarField::rand(&mut rng);
+				let ephemerals = [Ephemeral::new(&mut rng)];
+				BatchPoK::prove_bound(&[m], *h, &ephemerals, &binding, &mut rng)
+					.unwrap()
+			})
+			.collect();
+
+		let batch: Vec<(PublicKey<JubJub>, &BatchPoK<JubJub>)> =
+			keys.iter().copied().zip(proofs.iter()).collect();
+		assert!(BatchPoK::verify_batch_bound(&batch, &binding, &mut rng));
+		assert!(!BatchPoK::verify_batch(&batch, &mut rng));
+		assert!(!BatchPoK::verify_batch_bound(
+			&batch,
+			&Binding::of(b"another session"),
+			&mut rng
+		));
+	}
+
 	#[test]
 	pub fn hegs_verify_fails_with_invalid_challenge() {
 		let mut rng = test_rng();
 		// the secret key
 		let x = <JubJub as Group>::ScalarField::rand(&mut rng);
-		let g: JubJub = JubJub::generator().into();
+		let g: JubJub = JubJub::generator();
 		// the public key
-		let h: JubJub = g.mul(x).into();
+		let h = PublicKey(g.mul(x));
 
 		let j = <JubJub as Group>::ScalarField::rand(&mut rng);
 		let bad_proof = BatchPoK::<JubJub> {
-			s: g.mul(j).into(),
-			t: g.mul(j).into(),
+			s: g.mul(j),
+			t: g.mul(j),
 			z: j,
-			commitment: g.mul(j).into(),
-			ciphertexts: vec![Ciphertext { c1: g.mul(j).into(), c2: [1; 32] }],
+			commitment: g.mul(j),
+			ciphertexts: vec![Ciphertext {
+				c1: g.mul(j),
+				c2: [1; 32],
+				tag: [0; 16],
+			}],
 		};
 
 		// let params = Params { g, h };
 		let result = bad_proof.verify(h);
-		assert_eq!(result, false);
+		assert!(!result);
 	}
 
 	#[test]
@@ -312,20 +590,20 @@ mod test {
 		// the secret key
 		let x = <JubJub as Group>::ScalarField::rand(&mut rng);
 		let x_prime = <JubJub as Group>::ScalarField::rand(&mut rng);
-		let g: JubJub = JubJub::generator().into();
+		let g: JubJub = JubJub::generator();
 		// the public key
-		let h: JubJub = g.mul(x).into();
+		let h = PublicKey(g.mul(x));
 
 		let j = <JubJub as Group>::ScalarField::rand(&mut rng);
-		let bad_commitment = g.mul(j).into();
+		let bad_commitment = g.mul(j);
 
 		// let params = Params { g, h };
 
 		let mut proof =
-			BatchPoK::prove(&vec![x, x_prime], g.clone(), test_rng()).unwrap();
+			BatchPoK::prove(&[x, x_prime], PublicKey(g), test_rng()).unwrap();
 		proof.commitment = bad_commitment;
 		let result = proof.verify(h);
-		assert_eq!(result, false);
+		assert!(!result);
 	}
 
 	#[test]
@@ -333,20 +611,62 @@ mod test {
 		let mut rng = test_rng();
 		// the secret key
 		let x = <JubJub as Group>::ScalarField::rand(&mut rng);
-		let g: JubJub = JubJub::generator().into();
+		let g: JubJub = JubJub::generator();
 		// the public key
-		let h: JubJub = g.mul(x).into();
+		let h = PublicKey(g.mul(x));
 
 		let j = <JubJub as Group>::ScalarField::rand(&mut rng);
 		let bad_ciphertext =
-			vec![Ciphertext { c1: g.mul(j).into(), c2: [1; 32] }];
+			vec![Ciphertext { c1: g.mul(j), c2: [1; 32], tag: [0; 16] }];
 
 		// let params = Params { g, h };
 
 		let mut proof =
-			BatchPoK::prove(&vec![x], g.clone(), test_rng()).unwrap();
+			BatchPoK::prove(&[x], PublicKey(g), test_rng()).unwrap();
 		proof.ciphertexts = bad_ciphertext;
 		let result = proof.verify(h);
-		assert_eq!(result, false);
+		assert!(!result);
+	}
+
+	#[test]
+	pub fn hegs_proofs_round_trip_through_hex() {
+		let mut rng = test_rng();
+		let x = <JubJub as Group>::ScalarField::rand(&mut rng);
+		let h = PublicKey(JubJub::generator().mul(x));
+		let proof = BatchPoK::prove(&[x, x], h, &mut rng).unwrap();
+
+		let parsed: BatchPoK<JubJub> = format!("{proof}").parse().unwrap();
+		assert_eq!(parsed, proof);
+		assert!(parsed.verify(h));
+
+		let ciphertext = &proof.ciphertexts[0];
+		assert_eq!(format!("{ciphertext}").parse(), Ok(ciphertext.clone()));
+		assert_eq!(
+			"0x00".parse::<BatchPoK<JubJub>>(),
+			Err(SerializationError::Decoding)
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "scale")]
+	pub fn hegs_proofs_round_trip_through_scale() {
+		use codec::{Decode, Encode};
+
+		let mut rng = test_rng();
+		let x = <JubJub as Group>::ScalarField::rand(&mut rng);
+		let h = PublicKey(JubJub::generator().mul(x));
+		let proof = BatchPoK::prove(&[x, x], h, &mut rng).unwrap();
+
+		let bytes = proof.encode();
+		let decoded = BatchPoK::<JubJub>::decode(&mut &bytes[..]).unwrap();
+		assert_eq!(decoded, proof);
+		assert!(decoded.verify(h));
+
+		// the compressed encoding must fill the byte vector exactly
+		let mut padded = Vec::new();
+		proof.ciphertexts[0].serialize_compressed(&mut padded).unwrap();
+		padded.push(0);
+		let bytes = padded.encode();
+		assert!(Ciphertext::<JubJub>::decode(&mut &bytes[..]).is_err());
 	}
 }
@@ -0,0 +1,498 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Inner-Product Aggregation
+//!
+//! Each batched PoK of a resharing is checked by one equation
+//! z (G + pk) = s + t + e * commitment, and a verifier handed n proofs needs
+//! the n responses z to check them (see `BatchPoK::verify_batch`, the
+//! linear aggregate). An [`AggregateProof`] replaces those n responses with
+//! a logarithmic-size argument. The dealer first commits to the responses,
+//! C = sum_i z_i H_i, over generators H_i hashed to the curve. The verifier
+//! then folds the equations with random weights rho_i into one,
+//! sum_i z_i rho_i (G + pk_i) = P, and the dealer proves knowledge of the
+//! vector z that opens C and satisfies it, with the inner-product argument
+//! of Bulletproofs over the bases H_i + gamma rho_i (G + pk_i). The argument
+//! has one vector only, as in compressed Sigma protocols. It carries
+//! 2 log2(n) + 1 points and one scalar. Verification is still linear in n
+//! (one MSM) but no longer needs the responses.
+//!
+//! The responses are public in a batched PoK, so the argument need not hide
+//! them, but it must fix them before the weights are drawn. Every base
+//! G + pk_i is a multiple of G, and a dealer who knows the discrete log of
+//! one of them (e.g. a dealer resharing to a committee it belongs to) could
+//! otherwise fold the error of every other equation into that one response.
+//! The weights are derived from C, and no discrete log relation between the
+//! H_i and G is known, so z is bound before rho and every equation must
+//! hold on its own.
+//!
+//! Layout: `C || L_1..L_k || R_1..R_k || a`, k = log2 of n rounded up to a
+//! power of two.
+
+use crate::{
+	error::{ProofError, SerializationError},
+	keys::PublicKey,
+	msm::{DefaultMsm, MsmBackend},
+	proofs::{
+		challenge::{ChallengeHash, DefaultChallenge, DEFAULT_CHALLENGE_BYTES},
+		hashed_el_gamal::Ciphertext,
+		hashed_el_gamal_sigma::BatchPoK,
+		ser::HashWriter,
+	},
+};
+use ark_ec::{AffineRepr, CurveGroup};
+#[cfg(feature = "participant")]
+use ark_ff::Zero;
+use ark_ff::{Field, One, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::vec::Vec;
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake128,
+};
+
+/// domain separator for the weights and round challenges
+const IPA_LABEL: &[u8] = b"acss/ipa";
+
+/// domain separator for the generators the responses are committed over
+const IPA_GENERATORS_LABEL: &[u8] = b"acss/ipa/generators";
+
+/// the equation z * base = target satisfied by the response z of one proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerificationEquation<C: CurveGroup> {
+	/// G + pk for the recipient's key pk
+	pub base: C,
+	/// s + t + e * commitment for the proof's challenge e
+	pub target: C,
+}
+
+impl<C: CurveGroup> VerificationEquation<C> {
+	/// the equation `BatchPoK::verify` checks for `pok` dealt to `pk`
+	/// outputs none if the challenge cannot be derived
+	///
+	/// * `pk`: The recipient's public key
+	/// * `pok`: The proof, whose response is not read
+	pub fn of(pk: PublicKey<C>, pok: &BatchPoK<C>) -> Option<Self> {
+		let ciphertext = Ciphertext::aggregate(pok.ciphertexts.iter());
+		let challenge =
+			DefaultChallenge::challenge(&pok.s, &pok.t, &ciphertext).ok()?;
+		Some(Self {
+			base: C::generator() + pk.0,
+			target: pok.s + pok.t + pok.commitment * challenge,
+		})
+	}
+
+	/// true if `response` satisfies the equation
+	///
+	/// * `response`: The response z of the proof
+	pub fn holds(&self, response: C::ScalarField) -> bool {
+		self.base * response == self.target
+	}
+}
+
+/// a logarithmic-size argument that the responses of n proofs satisfy
+/// their equations
+#[derive(
+	Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize,
+)]
+pub struct AggregateProof<C: CurveGroup> {
+	/// the commitment sum_i z_i H_i to the responses, which the weights are
+	/// derived from
+	pub commitment: C,
+	/// the cross term <z_L, B_R> of each round
+	pub ls: Vec<C>,
+	/// the cross term <z_R, B_L> of each round
+	pub rs: Vec<C>,
+	/// the folded response
+	pub a: C::ScalarField,
+}
+
+impl<C: CurveGroup> AggregateProof<C> {
+	#[cfg(feature = "participant")]
+	/// prove that `responses` satisfy `equations`, the i-th the i-th
+	/// fails with `EncryptionFailed` if there are no equations or the number
+	/// of responses does not match
+	///
+	/// * `equations`: The equations, e.g. of the proofs of a resharing
+	/// * `responses`: The response z of each proof
+	pub fn prove(
+		equations: &[VerificationEquation<C>],
+		responses: &[C::ScalarField],
+	) -> Result<Self, ProofError> {
+		if equations.is_empty() || equations.len() != responses.len() {
+			return Err(ProofError::EncryptionFailed);
+		}
+		let generators = generators::<C>(equations.len());
+		let commitment =
+			DefaultMsm::msm(&C::normalize_batch(&generators), responses);
+		Self::argue(equations, &generators, responses, commitment)
+	}
+
+	#[cfg(feature = "participant")]
+	/// the argument for `responses` against `commitment`, which `prove` has
+	/// checked they open
+	fn argue(
+		equations: &[VerificationEquation<C>],
+		generators: &[C],
+		responses: &[C::ScalarField],
+		commitment: C,
+	) -> Result<Self, ProofError> {
+		let size = equations.len().next_power_of_two();
+		let (mut state, weights, gamma) = weights(equations, &commitment)?;
+		let mut bases: Vec<C> = equations
+			.iter()
+			.zip(generators)
+			.zip(&weights)
+			.map(|((equation, h), weight)| {
+				*h + equation.base * (gamma * weight)
+			})
+			.collect();
+		bases.resize(size, C::zero());
+		let mut z = responses.to_vec();
+		z.resize(size, C::ScalarField::zero());
+
+		let (mut ls, mut rs) = (Vec::new(), Vec::new());
+		while z.len() > 1 {
+			let half = z.len() / 2;
+			let (z_l, z_r) = z.split_at(half);
+			let (b_l, b_r) = bases.split_at(half);
+			let l = DefaultMsm::msm(&C::normalize_batch(b_r), z_l);
+			let r = DefaultMsm::msm(&C::normalize_batch(b_l), z_r);
+			let (x, x_inv) = round_challenge(&mut state, &l, &r)?;
+			z = z_l.iter().zip(z_r).map(|(l, r)| x * l + x_inv * r).collect();
+			bases =
+				b_l.iter().zip(b_r).map(|(l, r)| *l * x_inv + *r * x).collect();
+			ls.push(l);
+			rs.push(r);
+		}
+		Ok(Self { commitment, ls, rs, a: z[0] })
+	}
+
+	/// verify the argument against `equations`
+	/// outputs true if it proves knowledge of responses satisfying every
+	/// equation, false otherwise
+	///
+	/// * `equations`: The equations, in the order they were proven
+	pub fn verify(&self, equations: &[VerificationEquation<C>]) -> bool {
+		self.verify_with::<DefaultMsm>(equations)
+	}
+
+	/// verify the argument, as with `verify`, computing the multi-scalar
+	/// multiplication with the backend `M`
+	///
+	/// * `equations`: The equations, in the order they were proven
+	pub fn verify_with<M: MsmBackend<C>>(
+		&self,
+		equations: &[VerificationEquation<C>],
+	) -> bool {
+		let rounds = equations.len().next_power_of_two().trailing_zeros();
+		if equations.is_empty() ||
+			self.ls.len() != rounds as usize ||
+			self.rs.len() != rounds as usize
+		{
+			return false;
+		}
+		let generators = generators::<C>(equations.len());
+		let Ok((mut state, weights, gamma)) =
+			weights(equations, &self.commitment)
+		else {
+			return false;
+		};
+		let mut challenges = Vec::with_capacity(self.ls.len());
+		for (l, r) in self.ls.iter().zip(&self.rs) {
+			let Ok(challenge) = round_challenge(&mut state, l, r) else {
+				return false;
+			};
+			challenges.push(challenge);
+		}
+
+		// a * sum_i s_i (H_i + gamma rho_i base_i)
+		//     = C + gamma sum_i rho_i target_i
+		//       + sum_j (x_j^2 L_j + x_j^-2 R_j)
+		// where s_i is the product over the rounds of x_j or x_j^-1, as the
+		// j-th bit of i (from the top) is set or not
+		let mut bases =
+			Vec::with_capacity(3 * equations.len() + 2 * self.ls.len() + 1);
+		let mut scalars = Vec::with_capacity(bases.capacity());
+		for (i, ((equation, h), weight)) in
+			equations.iter().zip(&generators).zip(&weights).enumerate()
+		{
+			let s = challenges.iter().enumerate().fold(
+				C::ScalarField::one(),
+				|s, (j, (x, x_inv))| {
+					let bit = (i >> (challenges.len() - 1 - j)) & 1;
+					s * if bit == 1 { x } else { x_inv }
+				},
+			);
+			let weight = gamma * weight;
+			bases.extend([*h, equation.base, equation.target]);
+			scalars.extend([self.a * s, self.a * s * weight, -weight]);
+		}
+		bases.push(self.commitment);
+		scalars.push(-C::ScalarField::one());
+		for ((l, r), (x, x_inv)) in
+			self.ls.iter().zip(&self.rs).zip(&challenges)
+		{
+			bases.extend([*l, *r]);
+			scalars.extend([-x.square(), -x_inv.square()]);
+		}
+		M::msm(&C::normalize_batch(&bases), &scalars).is_zero()
+	}
+}
+
+impl<C: CurveGroup> BatchPoK<C> {
+	#[cfg(feature = "participant")]
+	/// aggregate the proofs dealt to a committee, as an `AggregateProof` over
+	/// their equations
+	/// fails with `EncryptionFailed` if there are no proofs or a challenge
+	/// cannot be derived
+	///
+	/// * `proofs`: (recipient public key, proof) pairs
+	pub fn aggregate(
+		proofs: &[(PublicKey<C>, &BatchPoK<C>)],
+	) -> Result<(Vec<VerificationEquation<C>>, AggregateProof<C>), ProofError>
+	{
+		let equations = proofs
+			.iter()
+			.map(|(pk, pok)| VerificationEquation::of(*pk, pok))
+			.collect::<Option<Vec<_>>>()
+			.ok_or(ProofError::EncryptionFailed)?;
+		let responses: Vec<C::ScalarField> =
+			proofs.iter().map(|(_, pok)| pok.z).collect();
+		let proof = AggregateProof::prove(&equations, &responses)?;
+		Ok((equations, proof))
+	}
+}
+
+/// the generators H_1..H_n the responses are committed over, hashed to the
+/// curve by try-and-increment so that no discrete log relation between them
+/// or to G is known
+fn generators<C: CurveGroup>(n: usize) -> Vec<C> {
+	let size = C::Affine::generator().compressed_size();
+	let mut bytes = ark_std::vec![0u8; size];
+	let mut generators = Vec::with_capacity(n);
+	for i in 0..n as u64 {
+		let mut h = Shake128::default();
+		h.update(IPA_GENERATORS_LABEL);
+		h.update(&i.to_le_bytes());
+		let mut reader = h.finalize_xof();
+		let generator = loop {
+			reader.read(&mut bytes);
+			if let Some(point) = C::Affine::from_random_bytes(&bytes) {
+				let point = point.clear_cofactor();
+				if !point.is_zero() {
+					break point;
+				}
+			}
+		};
+		generators.push(generator.into_group());
+	}
+	generators
+}
+
+/// the weight of each equation and the challenge gamma that separates them
+/// from the commitment, and the transcript state they leave behind
+#[allow(clippy::type_complexity)]
+fn weights<C: CurveGroup>(
+	equations: &[VerificationEquation<C>],
+	commitment: &C,
+) -> Result<
+	([u8; DEFAULT_CHALLENGE_BYTES], Vec<C::ScalarField>, C::ScalarField),
+	ProofError,
+> {
+	let mut h = Shake128::default();
+	h.update(IPA_LABEL);
+	h.update(&(equations.len() as u64).to_le_bytes());
+	for equation in equations {
+		equation
+			.base
+			.serialize_compressed(HashWriter(&mut h))
+			.and_then(|_| {
+				equation.target.serialize_compressed(HashWriter(&mut h))
+			})
+			.map_err(|_| SerializationError::Encoding)?;
+	}
+	commitment
+		.serialize_compressed(HashWriter(&mut h))
+		.map_err(|_| SerializationError::Encoding)?;
+	let mut reader = h.finalize_xof();
+	let mut draw = || {
+		let mut o = [0u8; DEFAULT_CHALLENGE_BYTES];
+		reader.read(&mut o);
+		o
+	};
+	let state = draw();
+	let gamma = C::ScalarField::from_be_bytes_mod_order(&draw());
+	let weights = equations
+		.iter()
+		.map(|_| C::ScalarField::from_be_bytes_mod_order(&draw()))
+		.collect();
+	Ok((state, weights, gamma))
+}
+
+/// the challenge x of a round and its inverse, chained from `state`
+/// a zero challenge, which occurs with negligible probability, is replaced
+/// by one
+fn round_challenge<C: CurveGroup>(
+	state: &mut [u8; DEFAULT_CHALLENGE_BYTES],
+	l: &C,
+	r: &C,
+) -> Result<(C::ScalarField, C::ScalarField), ProofError> {
+	let mut h = Shake128::default();
+	h.update(IPA_LABEL);
+	h.update(state);
+	l.serialize_compressed(HashWriter(&mut h))
+		.and_then(|_| r.serialize_compressed(HashWriter(&mut h)))
+		.map_err(|_| SerializationError::Encoding)?;
+	h.finalize_xof().read(state);
+	let x = C::ScalarField::from_be_bytes_mod_order(state);
+	Ok(match x.inverse() {
+		Some(x_inv) => (x, x_inv),
+		None => (C::ScalarField::one(), C::ScalarField::one()),
+	})
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::rng::test_rng;
+	use ark_ec::Group;
+	use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+	use ark_ff::UniformRand;
+
+	type Fr = <JubJub as Group>::ScalarField;
+
+	fn proofs(n: usize) -> Vec<(PublicKey<JubJub>, BatchPoK<JubJub>)> {
+		let mut rng = test_rng();
+		(0..n)
+			.map(|_| {
+				let pk = PublicKey(JubJub::generator() * Fr::rand(&mut rng));
+				let messages = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+				(pk, BatchPoK::prove(&messages, pk, &mut rng).unwrap())
+			})
+			.collect()
+	}
+
+	#[test]
+	fn aggregate_proofs_verify_at_logarithmic_size() {
+		for n in [1, 2, 5, 8] {
+			let proofs = proofs(n);
+			let pairs: Vec<_> =
+				proofs.iter().map(|(pk, pok)| (*pk, pok)).collect();
+			let (equations, proof) = BatchPoK::aggregate(&pairs).unwrap();
+			assert!(proof.verify(&equations), "n = {n}");
+			let rounds = n.next_power_of_two().trailing_zeros() as usize;
+			assert_eq!((proof.ls.len(), proof.rs.len()), (rounds, rounds));
+
+			let encoded = {
+				let mut bytes = Vec::new();
+				proof.serialize_compressed(&mut bytes).unwrap();
+				bytes
+			};
+			let decoded =
+				AggregateProof::<JubJub>::deserialize_compressed(&encoded[..])
+					.unwrap();
+			assert!(decoded.verify(&equations));
+		}
+	}
+
+	#[test]
+	fn aggregate_proofs_reject_an_invalid_equation() {
+		let proofs = proofs(5);
+		let pairs: Vec<_> = proofs.iter().map(|(pk, pok)| (*pk, pok)).collect();
+		let (mut equations, proof) = BatchPoK::aggregate(&pairs).unwrap();
+
+		// a wrong response for one proof
+		let mut responses: Vec<Fr> =
+			proofs.iter().map(|(_, pok)| pok.z).collect();
+		responses[3] += Fr::one();
+		assert!(!equations[3].holds(responses[3]));
+		let forged = AggregateProof::prove(&equations, &responses).unwrap();
+		assert!(!forged.verify(&equations));
+
+		// the same argument against other equations, or too few of them
+		assert!(!proof.verify(&equations[..4]));
+		equations.swap(0, 1);
+		assert!(!proof.verify(&equations));
+		equations.swap(0, 1);
+		equations[2].target += JubJub::generator();
+		assert!(!proof.verify(&equations));
+		assert!(!proof.verify(&[]));
+		assert_eq!(
+			AggregateProof::<JubJub>::prove(&[], &[]).err(),
+			Some(ProofError::EncryptionFailed)
+		);
+	}
+
+	#[test]
+	fn aggregate_proofs_reject_a_dealer_folding_errors_into_its_own_key() {
+		// the dealer holds the secret key of recipient 0, e.g. as a member of
+		// the committee it reshares to
+		let mut rng = test_rng();
+		let sk = Fr::rand(&mut rng);
+		let mut proofs = proofs(5);
+		let messages = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+		let pk = PublicKey(JubJub::generator() * sk);
+		proofs[0] = (pk, BatchPoK::prove(&messages, pk, &mut rng).unwrap());
+		let pairs: Vec<_> = proofs.iter().map(|(pk, pok)| (*pk, pok)).collect();
+		let (mut equations, _) = BatchPoK::aggregate(&pairs).unwrap();
+		let mut responses: Vec<Fr> =
+			proofs.iter().map(|(_, pok)| pok.z).collect();
+
+		// every other proof is invalid, off by a multiple of G it knows
+		let offsets: Vec<Fr> = (1..5).map(|_| Fr::rand(&mut rng)).collect();
+		for (equation, offset) in equations[1..].iter_mut().zip(&offsets) {
+			equation.target += JubJub::generator() * offset;
+		}
+		assert!((1..5).all(|i| !equations[i].holds(responses[i])));
+
+		// it knows the discrete log 1 + sk of its own base, so it can fold
+		// the offsets into its own response under the weights of a commitment
+		let generators = generators::<JubJub>(5);
+		let commitment =
+			DefaultMsm::msm(&JubJub::normalize_batch(&generators), &responses);
+		let (_, weights, _) = weights(&equations, &commitment).unwrap();
+		let folded = weights[1..]
+			.iter()
+			.zip(&offsets)
+			.fold(Fr::zero(), |acc, (weight, offset)| acc + *weight * offset);
+		responses[0] += folded / (weights[0] * (Fr::one() + sk));
+		let lhs: JubJub = equations
+			.iter()
+			.zip(&weights)
+			.zip(&responses)
+			.map(|((equation, weight), z)| equation.base * (*weight * z))
+			.sum();
+		let rhs: JubJub = equations
+			.iter()
+			.zip(&weights)
+			.map(|(equation, weight)| equation.target * weight)
+			.sum();
+		assert_eq!(lhs, rhs);
+
+		// but the commitment no longer opens to the responses, and committing
+		// to them anew redraws the weights
+		let stale = AggregateProof::argue(
+			&equations,
+			&generators,
+			&responses,
+			commitment,
+		)
+		.unwrap();
+		assert!(!stale.verify(&equations));
+		let forged = AggregateProof::prove(&equations, &responses).unwrap();
+		assert!(!forged.verify(&equations));
+	}
+}
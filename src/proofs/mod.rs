@@ -14,7 +14,48 @@
  * limitations under the License.
  */
 
+pub mod challenge;
+#[cfg(feature = "r1cs")]
+pub mod constraints;
+#[cfg(feature = "groth16")]
+pub mod groth16;
 pub mod hashed_el_gamal;
 // pub mod el_gamal_sigma;
 pub mod hashed_el_gamal_sigma;
-mod ser;
+pub mod ipa;
+#[cfg(feature = "poseidon")]
+pub mod poseidon;
+pub(crate) mod ser;
+
+#[cfg(feature = "verify")]
+use ark_ec::{AffineRepr, CurveGroup};
+#[cfg(feature = "verify")]
+use ark_serialize::CanonicalDeserialize;
+#[cfg(feature = "verify")]
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+
+/// a generator of C with unknown discrete log to the standard generator,
+/// hashed to the curve from `label` by try-and-increment
+#[cfg(feature = "verify")]
+pub(crate) fn nums_generator<C: CurveGroup>(label: &[u8]) -> C {
+	let size = C::generator().compressed_size();
+	let mut bytes = vec![0u8; size];
+	for counter in 0u32.. {
+		let mut h = Shake256::default();
+		h.update(label);
+		h.update(&counter.to_be_bytes());
+		h.finalize_xof().read(&mut bytes);
+		let Ok(point) = C::Affine::deserialize_compressed_unchecked(&bytes[..])
+		else {
+			continue;
+		};
+		let point = point.clear_cofactor();
+		if !point.is_zero() {
+			return point.into_group();
+		}
+	}
+	unreachable!("a point is found long before the counter overflows")
+}
@@ -0,0 +1,393 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Poseidon
+//!
+//! The Poseidon permutation (https://eprint.iacr.org/2019/458.pdf) of width 3
+//! with the S-box x^5, 8 full rounds and 57 partial rounds: the instance the
+//! reference implementation recommends for 128 bit security over prime fields
+//! of about 255 bits, such as the BLS12-381 and BN254 scalar fields.
+//!
+//! The round constants and the Cauchy MDS matrix are drawn from the Grain LFSR
+//! of the reference parameter generator, seeded with the bit size of the
+//! field, so the permutation reproduces the published test vectors
+//! (`poseidonperm_x5_255_3` and `poseidonperm_x5_254_3`). Fields in which x^5
+//! is not a permutation (5 divides p - 1, e.g. the BLS12-377 scalar field) have
+//! no such instance.
+//!
+//! The sponge absorbs field elements at rate 2 into a state whose capacity
+//! element holds the input length, so inputs of different lengths are domain
+//! separated, and squeezes a single element.
+//!
+//! With the `r1cs` feature, `permute_var` and `hash_var` compute the same
+//! permutation and sponge over elements allocated in a constraint system.
+
+use ark_ff::{BigInteger, PrimeField};
+#[cfg(feature = "r1cs")]
+use ark_r1cs_std::fields::{fp::FpVar, FieldVar};
+#[cfg(feature = "r1cs")]
+use ark_relations::r1cs::SynthesisError;
+use ark_std::vec::Vec;
+
+/// the width of the permutation
+pub const WIDTH: usize = 3;
+/// the number of field elements absorbed per permutation
+pub const RATE: usize = 2;
+/// the exponent of the S-box
+pub const ALPHA: u64 = 5;
+/// the number of full rounds
+pub const FULL_ROUNDS: usize = 8;
+/// the number of partial rounds
+pub const PARTIAL_ROUNDS: usize = 57;
+
+/// the Poseidon permutation and sponge parameters for the field F
+pub struct Poseidon<F: PrimeField> {
+	round_constants: Vec<[F; WIDTH]>,
+	mds: [[F; WIDTH]; WIDTH],
+}
+
+impl<F: PrimeField> Poseidon<F> {
+	/// generate the parameters for the field F as the reference generator
+	/// does, outputs none if x^5 is not a permutation of F
+	pub fn new() -> Option<Self> {
+		if modulus_rem::<F>(ALPHA) == 1 {
+			return None;
+		}
+
+		let mut grain = Grain::new(F::MODULUS_BIT_SIZE);
+		let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+			.map(|_| core::array::from_fn(|_| grain.next_field_element()))
+			.collect();
+		let mds = loop {
+			if let Some(mds) = cauchy_matrix(&mut grain) {
+				break mds;
+			}
+		};
+		Some(Self { round_constants, mds })
+	}
+
+	/// apply the permutation to the state in place
+	pub fn permute(&self, state: &mut [F; WIDTH]) {
+		let half_full = FULL_ROUNDS / 2;
+		for (round, constants) in self.round_constants.iter().enumerate() {
+			for (x, c) in state.iter_mut().zip(constants.iter()) {
+				*x += c;
+			}
+
+			let is_full =
+				round < half_full || round >= half_full + PARTIAL_ROUNDS;
+			if is_full {
+				state.iter_mut().for_each(|x| *x = x.pow([ALPHA]));
+			} else {
+				state[0] = state[0].pow([ALPHA]);
+			}
+
+			let prev = *state;
+			for (x, row) in state.iter_mut().zip(self.mds.iter()) {
+				*x = row.iter().zip(prev.iter()).map(|(m, p)| *m * p).sum();
+			}
+		}
+	}
+
+	/// hash a sequence of field elements to a single field element
+	/// the capacity element is initialized with the input length, so inputs
+	/// of different lengths are domain separated
+	pub fn hash(&self, inputs: &[F]) -> F {
+		let mut state = [F::zero(); WIDTH];
+		state[RATE] = F::from(inputs.len() as u64);
+		for chunk in inputs.chunks(RATE) {
+			for (x, input) in state.iter_mut().zip(chunk.iter()) {
+				*x += input;
+			}
+			self.permute(&mut state);
+		}
+		if inputs.is_empty() {
+			self.permute(&mut state);
+		}
+		state[0]
+	}
+}
+
+#[cfg(feature = "r1cs")]
+impl<F: PrimeField> Poseidon<F> {
+	/// apply the permutation in place to a state allocated in a constraint
+	/// system, as `permute` does
+	/// each S-box costs three constraints and the linear layers none
+	pub fn permute_var(
+		&self,
+		state: &mut [FpVar<F>; WIDTH],
+	) -> Result<(), SynthesisError> {
+		let half_full = FULL_ROUNDS / 2;
+		for (round, constants) in self.round_constants.iter().enumerate() {
+			for (x, c) in state.iter_mut().zip(constants.iter()) {
+				*x += *c;
+			}
+
+			let is_full =
+				round < half_full || round >= half_full + PARTIAL_ROUNDS;
+			let sbox = |x: &FpVar<F>| -> Result<FpVar<F>, SynthesisError> {
+				Ok(x.square()?.square()? * x)
+			};
+			if is_full {
+				for x in state.iter_mut() {
+					*x = sbox(x)?;
+				}
+			} else {
+				state[0] = sbox(&state[0])?;
+			}
+
+			let prev = state.clone();
+			for (x, row) in state.iter_mut().zip(self.mds.iter()) {
+				*x = row
+					.iter()
+					.zip(prev.iter())
+					.fold(FpVar::zero(), |acc, (m, p)| acc + p * *m);
+			}
+		}
+		Ok(())
+	}
+
+	/// hash a sequence of field elements allocated in a constraint system, as
+	/// `hash` does
+	pub fn hash_var(
+		&self,
+		inputs: &[FpVar<F>],
+	) -> Result<FpVar<F>, SynthesisError> {
+		let mut state = [
+			FpVar::zero(),
+			FpVar::zero(),
+			FpVar::constant(F::from(inputs.len() as u64)),
+		];
+		for chunk in inputs.chunks(RATE) {
+			for (x, input) in state.iter_mut().zip(chunk.iter()) {
+				*x += input;
+			}
+			self.permute_var(&mut state)?;
+		}
+		if inputs.is_empty() {
+			self.permute_var(&mut state)?;
+		}
+		let [out, ..] = state;
+		Ok(out)
+	}
+}
+
+/// the self-shrinking Grain LFSR of the reference parameter generator
+struct Grain {
+	/// the last 80 bits of the sequence, the oldest in the lowest bit
+	state: u128,
+	/// the bit size of the field
+	bits: u32,
+}
+
+impl Grain {
+	/// the generator for a prime field of `bits` bits and the parameters of
+	/// this instance, with its first 160 bits discarded
+	fn new(bits: u32) -> Self {
+		// (width, value) of each field of the seed, most significant bit first:
+		// the field type (1 for a prime field), the S-box (0 for x^alpha), the
+		// field size, the width, the full rounds, the partial rounds, and a
+		// padding of ones
+		let seed: [(u32, u128); 7] = [
+			(2, 1),
+			(4, 0),
+			(12, bits as u128),
+			(12, WIDTH as u128),
+			(10, FULL_ROUNDS as u128),
+			(10, PARTIAL_ROUNDS as u128),
+			(30, (1 << 30) - 1),
+		];
+		let mut state = 0u128;
+		let mut position = 0;
+		for (width, value) in seed {
+			for i in (0..width).rev() {
+				state |= ((value >> i) & 1) << position;
+				position += 1;
+			}
+		}
+
+		let mut grain = Self { state, bits };
+		for _ in 0..160 {
+			grain.step();
+		}
+		grain
+	}
+
+	/// advance the LFSR, outputting the new bit
+	fn step(&mut self) -> bool {
+		let bit = |i: u32| (self.state >> i) & 1;
+		let new = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+		self.state = (self.state >> 1) | (new << 79);
+		new == 1
+	}
+
+	/// the next output bit: of each pair of LFSR bits, the second is output
+	/// if the first is set and discarded otherwise
+	fn next_bit(&mut self) -> bool {
+		loop {
+			let keep = self.step();
+			let bit = self.step();
+			if keep {
+				return bit;
+			}
+		}
+	}
+
+	/// the next field size bits, most significant first
+	fn next_bits(&mut self) -> Vec<bool> {
+		(0..self.bits).map(|_| self.next_bit()).collect()
+	}
+
+	/// the next field element, rejecting draws that are not below the modulus
+	fn next_field_element<F: PrimeField>(&mut self) -> F {
+		loop {
+			let draw = F::BigInt::from_bits_be(&self.next_bits());
+			if let Some(element) = F::from_bigint(draw) {
+				return element;
+			}
+		}
+	}
+
+	/// the next field element, reducing draws modulo the modulus
+	fn next_field_element_mod_order<F: PrimeField>(&mut self) -> F {
+		let draw = F::BigInt::from_bits_be(&self.next_bits());
+		F::from_be_bytes_mod_order(&draw.to_bytes_be())
+	}
+}
+
+/// the Cauchy matrix M_ij = 1 / (x_i + y_j) for x and y drawn from `grain`,
+/// or none if the draws are not distinct or some x_i + y_j is zero, in which
+/// case the reference generator draws again
+fn cauchy_matrix<F: PrimeField>(
+	grain: &mut Grain,
+) -> Option<[[F; WIDTH]; WIDTH]> {
+	let draws: [F; 2 * WIDTH] =
+		core::array::from_fn(|_| grain.next_field_element_mod_order());
+	let distinct = draws
+		.iter()
+		.enumerate()
+		.all(|(i, a)| draws[i + 1..].iter().all(|b| a != b));
+	if !distinct {
+		return None;
+	}
+
+	let (xs, ys) = draws.split_at(WIDTH);
+	let mut mds = [[F::zero(); WIDTH]; WIDTH];
+	for (row, x) in mds.iter_mut().zip(xs.iter()) {
+		for (entry, y) in row.iter_mut().zip(ys.iter()) {
+			*entry = (*x + y).inverse()?;
+		}
+	}
+	Some(mds)
+}
+
+/// pack bytes into field elements, using as many bytes per element as fit
+/// below the modulus
+pub fn pack_bytes<F: PrimeField>(bytes: &[u8]) -> Vec<F> {
+	let chunk_size = ((F::MODULUS_BIT_SIZE - 1) / 8) as usize;
+	bytes.chunks(chunk_size).map(F::from_le_bytes_mod_order).collect()
+}
+
+/// p mod a
+fn modulus_rem<F: PrimeField>(a: u64) -> u64 {
+	F::MODULUS
+		.as_ref()
+		.iter()
+		.rev()
+		.fold(0u128, |rem, limb| ((rem << 64) | *limb as u128) % a as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_bls12_381::Fr;
+	use ark_ff::{One, Zero};
+	use w3f_bls::{EngineBLS, TinyBLS377};
+
+	fn element(hex: &str) -> Fr {
+		Fr::from_be_bytes_mod_order(&hex::decode(hex).unwrap())
+	}
+
+	#[test]
+	fn permutation_matches_the_reference_test_vector() {
+		// poseidonperm_x5_255_3 of the reference implementation
+		let poseidon = Poseidon::<Fr>::new().unwrap();
+		let mut state = [Fr::zero(), Fr::one(), Fr::from(2u64)];
+		poseidon.permute(&mut state);
+		assert_eq!(
+			state,
+			[
+				element(
+					"28ce19420fc246a05553ad1e8c98f5c9d67166be2c18e9e4cb4b4e317dd2a78a"
+				),
+				element(
+					"51f3e312c95343a896cfd8945ea82ba956c1118ce9b9859b6ea56637b4b1ddc4"
+				),
+				element(
+					"3b2b69139b235626a0bfb56c9527ae66a7bf486ad8c11c14d1da0c69bbe0f79a"
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn fields_without_an_x5_permutation_have_no_instance() {
+		assert_eq!(modulus_rem::<<TinyBLS377 as EngineBLS>::Scalar>(ALPHA), 1);
+		assert!(Poseidon::<<TinyBLS377 as EngineBLS>::Scalar>::new().is_none());
+	}
+
+	#[test]
+	fn hash_is_deterministic_and_input_sensitive() {
+		let poseidon = Poseidon::<Fr>::new().unwrap();
+		let a = poseidon.hash(&[Fr::one(), Fr::from(2u64)]);
+		assert_eq!(a, poseidon.hash(&[Fr::one(), Fr::from(2u64)]));
+		assert_ne!(a, poseidon.hash(&[Fr::from(2u64), Fr::one()]));
+		// length is bound into the capacity
+		assert_ne!(
+			poseidon.hash(&[Fr::zero()]),
+			poseidon.hash(&[Fr::zero(), Fr::zero()])
+		);
+		assert_ne!(poseidon.hash(&[]), poseidon.hash(&[Fr::zero()]));
+	}
+
+	#[cfg(feature = "r1cs")]
+	#[test]
+	fn the_gadget_matches_the_native_hash() {
+		use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+		use ark_relations::r1cs::ConstraintSystem;
+
+		let poseidon = Poseidon::<Fr>::new().unwrap();
+		for len in [0, 1, 2, 5] {
+			let inputs: Vec<Fr> =
+				(0..len).map(|i| Fr::from(i as u64)).collect();
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			let vars = inputs
+				.iter()
+				.map(|x| FpVar::new_witness(cs.clone(), || Ok(*x)))
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap();
+			let hash = poseidon.hash_var(&vars).unwrap();
+			assert_eq!(hash.value().unwrap(), poseidon.hash(&inputs));
+			assert!(cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn pack_bytes_fits_chunks_below_the_modulus() {
+		let packed = pack_bytes::<Fr>(&[0xff; 63]);
+		assert_eq!(packed.len(), 3);
+		assert_eq!(packed[2], Fr::from(0xffu64));
+	}
+}
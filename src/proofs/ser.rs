@@ -14,11 +14,17 @@
  * limitations under the License.
  */
 // https://github.com/arkworks-rs/algebra/issues/178
+use crate::error::SerializationError;
 use alloc::vec::Vec;
-use ark_serialize::{
-	CanonicalDeserialize, CanonicalSerialize, Compress, Validate,
-};
+#[cfg(feature = "serde")]
+use ark_ec::CurveGroup;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Write};
+#[cfg(feature = "serde")]
+use ark_serialize::{Compress, Validate};
+use core::fmt;
+use sha3::digest::Update;
 
+#[cfg(feature = "serde")]
 pub fn ark_se<S, A: CanonicalSerialize>(a: &A, s: S) -> Result<S::Ok, S::Error>
 where
 	S: serde::Serializer,
@@ -29,14 +35,169 @@ where
 	s.serialize_bytes(&bytes)
 }
 
+#[cfg(feature = "serde")]
+/// deserialize a compressed, validated element
+/// points must be on the curve and in the prime order subgroup, field elements
+/// must be reduced, and trailing bytes are rejected so that every element has
+/// exactly one accepted encoding
 pub fn ark_de<'de, D, A: CanonicalDeserialize>(data: D) -> Result<A, D::Error>
 where
 	D: serde::de::Deserializer<'de>,
 {
 	let s: Vec<u8> = serde::de::Deserialize::deserialize(data)?;
-	let a =
-		A::deserialize_with_mode(s.as_slice(), Compress::Yes, Validate::Yes);
-	a.map_err(serde::de::Error::custom)
+	let mut reader = s.as_slice();
+	let a = A::deserialize_with_mode(&mut reader, Compress::Yes, Validate::Yes)
+		.map_err(serde::de::Error::custom)?;
+	if !reader.is_empty() {
+		return Err(serde::de::Error::custom("trailing bytes"));
+	}
+	Ok(a)
+}
+
+#[cfg(feature = "serde")]
+/// deserialize a point as with `ark_de`, additionally rejecting the identity
+pub fn ark_de_point<'de, D, C: CurveGroup>(data: D) -> Result<C, D::Error>
+where
+	D: serde::de::Deserializer<'de>,
+{
+	let point: C = ark_de(data)?;
+	if point.is_zero() {
+		return Err(serde::de::Error::custom("identity point"));
+	}
+	Ok(point)
+}
+
+/// serialize a byte array as a tuple, as serde does for arrays of at most 32
+/// elements
+#[cfg(feature = "serde")]
+pub fn array_se<S, const N: usize>(a: &[u8; N], s: S) -> Result<S::Ok, S::Error>
+where
+	S: serde::Serializer,
+{
+	use serde::ser::SerializeTuple;
+	let mut tuple = s.serialize_tuple(N)?;
+	a.iter().try_for_each(|b| tuple.serialize_element(b))?;
+	tuple.end()
+}
+
+/// deserialize a byte array written by `array_se`
+#[cfg(feature = "serde")]
+pub fn array_de<'de, D, const N: usize>(data: D) -> Result<[u8; N], D::Error>
+where
+	D: serde::de::Deserializer<'de>,
+{
+	struct ArrayVisitor<const N: usize>;
+
+	impl<'de, const N: usize> serde::de::Visitor<'de> for ArrayVisitor<N> {
+		type Value = [u8; N];
+
+		fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			write!(f, "an array of {N} bytes")
+		}
+
+		fn visit_seq<A>(self, mut seq: A) -> Result<[u8; N], A::Error>
+		where
+			A: serde::de::SeqAccess<'de>,
+		{
+			let mut out = [0u8; N];
+			for (i, b) in out.iter_mut().enumerate() {
+				*b = seq.next_element()?.ok_or_else(|| {
+					serde::de::Error::invalid_length(i, &self)
+				})?;
+			}
+			Ok(out)
+		}
+	}
+
+	data.deserialize_tuple(N, ArrayVisitor::<N>)
+}
+
+/// SCALE encode the compressed encoding of an element as a byte vector
+/// SCALE encoding cannot fail, so an element that cannot be encoded is
+/// written as the empty vector, which `scale_decode` rejects
+#[cfg(feature = "scale")]
+pub fn scale_encode<A: CanonicalSerialize, O: codec::Output + ?Sized>(
+	a: &A,
+	dest: &mut O,
+) {
+	let mut bytes = Vec::with_capacity(a.compressed_size());
+	if a.serialize_compressed(&mut bytes).is_err() {
+		bytes.clear();
+	}
+	codec::Encode::encode_to(&bytes, dest)
+}
+
+/// SCALE decode an element written by `scale_encode`
+/// the element is validated as with `ark_de` and trailing bytes are rejected
+#[cfg(feature = "scale")]
+pub fn scale_decode<A: CanonicalDeserialize, I: codec::Input>(
+	input: &mut I,
+) -> Result<A, codec::Error> {
+	let bytes: Vec<u8> = codec::Decode::decode(input)?;
+	let mut reader = bytes.as_slice();
+	let a = A::deserialize_compressed(&mut reader)
+		.map_err(|_| "an element could not be decoded")?;
+	if !reader.is_empty() {
+		return Err("trailing bytes".into());
+	}
+	Ok(a)
+}
+
+/// write the compressed encoding of an element as 0x-prefixed lowercase hex
+pub fn fmt_hex<A: CanonicalSerialize>(
+	a: &A,
+	f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+	let mut bytes = Vec::with_capacity(a.compressed_size());
+	a.serialize_compressed(&mut bytes).map_err(|_| fmt::Error)?;
+	f.write_str("0x")?;
+	bytes.iter().try_for_each(|b| write!(f, "{b:02x}"))
+}
+
+/// parse an element from 0x-prefixed hex of its compressed encoding, as
+/// written by `fmt_hex`
+/// the element is validated as with `ark_de` and trailing bytes are rejected
+pub fn parse_hex<A: CanonicalDeserialize>(
+	s: &str,
+) -> Result<A, SerializationError> {
+	let digits = s.strip_prefix("0x").ok_or(SerializationError::Decoding)?;
+	if digits.len() % 2 != 0 {
+		return Err(SerializationError::Decoding);
+	}
+	let nibble = |c: u8| match c {
+		b'0'..=b'9' => Ok(c - b'0'),
+		b'a'..=b'f' => Ok(c - b'a' + 10),
+		b'A'..=b'F' => Ok(c - b'A' + 10),
+		_ => Err(SerializationError::Decoding),
+	};
+	let bytes = digits
+		.as_bytes()
+		.chunks(2)
+		.map(|pair| Ok(nibble(pair[0])? << 4 | nibble(pair[1])?))
+		.collect::<Result<Vec<u8>, _>>()?;
+
+	let mut reader = bytes.as_slice();
+	let a = A::deserialize_compressed(&mut reader)
+		.map_err(|_| SerializationError::Decoding)?;
+	if !reader.is_empty() {
+		return Err(SerializationError::Decoding);
+	}
+	Ok(a)
+}
+
+/// a `Write` adapter that absorbs serialized bytes directly into a hasher
+/// so that elements can be hashed without an intermediate buffer
+pub(crate) struct HashWriter<'a, H: Update>(pub &'a mut H);
+
+impl<H: Update> Write for HashWriter<'_, H> {
+	fn write(&mut self, buf: &[u8]) -> ark_std::io::Result<usize> {
+		self.0.update(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> ark_std::io::Result<()> {
+		Ok(())
+	}
 }
 
 #[cfg(test)]
@@ -44,21 +205,15 @@ mod tests {
 	use super::*;
 	use alloc::vec::Vec;
 	use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-	use serde::{Deserialize, Serialize};
-
-	#[derive(
-		Debug,
-		PartialEq,
-		Serialize,
-		Deserialize,
-		CanonicalSerialize,
-		CanonicalDeserialize,
-	)]
+
+	#[derive(Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+	#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 	struct TestStruct {
 		field1: u32,
 	}
 
 	#[test]
+	#[cfg(feature = "serde")]
 	fn test_serialization() {
 		// Create an instance of TestStruct for testing
 		let test_struct = TestStruct { field1: 42 };
@@ -69,4 +224,85 @@ mod tests {
 		// Check if serialization was successful
 		assert!(ark_se_result.is_ok());
 	}
+
+	#[test]
+	fn hash_writer_matches_buffered_hashing() {
+		use sha2::Digest;
+
+		let test_struct = TestStruct { field1: 42 };
+		let mut bytes = Vec::new();
+		test_struct.serialize_compressed(&mut bytes).unwrap();
+		let expected = sha2::Sha256::digest(&bytes);
+
+		let mut hasher = sha2::Sha256::new();
+		test_struct.serialize_compressed(HashWriter(&mut hasher)).unwrap();
+		assert_eq!(hasher.finalize(), expected);
+	}
+
+	#[test]
+	#[cfg(feature = "serde")]
+	fn ark_de_rejects_trailing_bytes() {
+		let mut bytes = Vec::new();
+		42u32.serialize_compressed(&mut bytes).unwrap();
+		let json = serde_json::to_vec(&bytes).unwrap();
+		let mut de = serde_json::Deserializer::from_slice(&json);
+		assert_eq!(ark_de::<_, u32>(&mut de).unwrap(), 42);
+
+		bytes.push(0);
+		let json = serde_json::to_vec(&bytes).unwrap();
+		let mut de = serde_json::Deserializer::from_slice(&json);
+		assert!(ark_de::<_, u32>(&mut de).is_err());
+	}
+
+	#[test]
+	fn hex_round_trips_and_rejects_malformed_input() {
+		struct Hex(u32);
+		impl fmt::Display for Hex {
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				fmt_hex(&self.0, f)
+			}
+		}
+
+		assert_eq!(format!("{}", Hex(42)), "0x2a000000");
+		assert_eq!(parse_hex::<u32>("0x2a000000"), Ok(42));
+		assert_eq!(parse_hex::<u32>("0x2A000000"), Ok(42));
+		for bad in ["2a000000", "0x2a00000", "0x2a00000g", "0x2a00000000", "0x"]
+		{
+			assert_eq!(
+				parse_hex::<u32>(bad),
+				Err(SerializationError::Decoding)
+			);
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "serde")]
+	fn ark_de_rejects_points_outside_the_subgroup() {
+		use ark_bls12_381::{Fq, G1Affine, G1Projective};
+		use ark_ec::{AffineRepr, CurveGroup, Group};
+		use ark_ff::One;
+
+		let deserialize = |point: &G1Affine| {
+			let mut bytes = Vec::new();
+			point.serialize_compressed(&mut bytes).unwrap();
+			let json = serde_json::to_vec(&bytes).unwrap();
+			let mut de = serde_json::Deserializer::from_slice(&json);
+			ark_de_point::<_, G1Projective>(&mut de)
+		};
+
+		assert!(deserialize(&G1Projective::generator().into_affine()).is_ok());
+		assert!(deserialize(&G1Affine::zero()).is_err());
+
+		// find a point on the curve that is not in the prime order subgroup
+		let mut x = Fq::one();
+		let point = loop {
+			if let Some(p) = G1Affine::get_point_from_x_unchecked(x, false) {
+				if !p.is_in_correct_subgroup_assuming_on_curve() {
+					break p;
+				}
+			}
+			x += Fq::one();
+		};
+		assert!(deserialize(&point).is_err());
+	}
 }
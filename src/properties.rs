@@ -0,0 +1,176 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Property Tests
+//!
+//! Algebraic identities checked over many random cases, each drawn from a
+//! ChaCha20 stream seeded with its case number so that a failure names the
+//! case that reproduces it. The edge cases the unit tests skip (n = 1,
+//! t = 1, t = n) are always checked, on top of the random ones. Set
+//! `ACSS_PROPERTY_CASES` to run more cases.
+
+use crate::{
+	acss::{DealingMode, DoubleSecret, Keypair},
+	keys::SecretKey,
+	lagrange::interpolate,
+	params::ThresholdParams,
+	proofs::hashed_el_gamal::{
+		cross_product, xor_in_place, Ciphertext, HashedElGamal,
+	},
+};
+use ark_ec::Group;
+use ark_ff::UniformRand;
+use ark_std::{
+	rand::{seq::SliceRandom, Rng, SeedableRng},
+	vec::Vec,
+};
+use rand_chacha::ChaCha20Rng;
+use w3f_bls::{EngineBLS, KeypairVT, TinyBLS377};
+
+type E = TinyBLS377;
+type G = <E as EngineBLS>::PublicKeyGroup;
+type Scalar = <E as EngineBLS>::Scalar;
+
+/// the number of random cases per property, unless overridden
+const DEFAULT_CASES: u64 = 32;
+
+/// the largest committee drawn for the resharing properties
+const MAX_COMMITTEE: u8 = 6;
+
+/// run `property` once per case, with a rng seeded by the case number
+fn for_all(property: impl Fn(u64, &mut ChaCha20Rng)) {
+	let cases = std::env::var("ACSS_PROPERTY_CASES")
+		.ok()
+		.and_then(|cases| cases.parse().ok())
+		.unwrap_or(DEFAULT_CASES);
+	for case in 0..cases {
+		property(case, &mut ChaCha20Rng::seed_from_u64(case));
+	}
+}
+
+/// a ciphertext of a random message to a random key
+fn ciphertext(rng: &mut ChaCha20Rng) -> Ciphertext<G> {
+	let pk =
+		SecretKey::<G>::new(<G as Group>::ScalarField::rand(rng)).public_key();
+	HashedElGamal::encrypt(rng.gen(), pk, rng).unwrap()
+}
+
+#[test]
+fn ciphertext_addition_commutes_and_associates() {
+	for_all(|case, rng| {
+		let [a, b, c] = [ciphertext(rng), ciphertext(rng), ciphertext(rng)];
+		assert_eq!(
+			a.clone().add(b.clone()),
+			b.clone().add(a.clone()),
+			"case {case}"
+		);
+		assert_eq!(
+			a.clone().add(b.clone()).add(c.clone()),
+			a.clone().add(b.clone().add(c.clone())),
+			"case {case}"
+		);
+		// so the order in which a recipient aggregates does not matter
+		let mut shuffled = [a.clone(), b.clone(), c.clone()];
+		shuffled.shuffle(rng);
+		assert_eq!(
+			Ciphertext::aggregate([a, b, c].iter()),
+			Ciphertext::aggregate(shuffled.iter()),
+			"case {case}"
+		);
+	});
+}
+
+#[test]
+fn xor_pads_are_involutions() {
+	for_all(|case, rng| {
+		let len = rng.gen_range(0..=100);
+		let message: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+		let pad: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+		let mut out = message.clone();
+		xor_in_place(&mut out, &pad);
+		xor_in_place(&mut out, &pad);
+		assert_eq!(out, message, "case {case}");
+
+		let (message, pad): ([u8; 32], [u8; 32]) = (rng.gen(), rng.gen());
+		assert_eq!(
+			cross_product(&cross_product(&message, &pad), &pad),
+			message,
+			"case {case}"
+		);
+
+		// and so decryption undoes encryption under the right key
+		let sk = SecretKey::<G>::new(<G as Group>::ScalarField::rand(rng));
+		let ct = HashedElGamal::encrypt(message, sk.public_key(), &mut *rng)
+			.unwrap();
+		assert_eq!(
+			HashedElGamal::decrypt(&sk, &ct),
+			Ok(message),
+			"case {case}"
+		);
+	});
+}
+
+/// reshare a random secret to a committee of `n` with threshold `t`, then
+/// check that a random subset of `t` recovered shares interpolates to it
+fn reshare_then_recover(case: u64, rng: &mut ChaCha20Rng, n: u8, t: u8) {
+	let keys: Vec<KeypairVT<E>> =
+		(0..n).map(|_| KeypairVT::generate(&mut *rng)).collect();
+	let committee: Vec<_> = keys.iter().map(|kp| kp.public).collect();
+	let secret = DoubleSecret::<E>(Scalar::rand(rng), Scalar::rand(rng));
+	let mode = *[DealingMode::PerRecipient, DealingMode::SharedEphemeral]
+		.choose(rng)
+		.unwrap();
+	let params = ThresholdParams::new(n, t).unwrap();
+	let resharing =
+		secret.reshare_with_mode(&committee, params, mode, &mut *rng).unwrap();
+
+	let mut members: Vec<usize> = (0..n as usize).collect();
+	members.shuffle(rng);
+	let single_dealer = ThresholdParams::new(1, 1).unwrap();
+	let (shares, blinding_shares): (Vec<_>, Vec<_>) = members[..t as usize]
+		.iter()
+		.map(|&i| {
+			let pok = resharing.for_recipient(&keys[i].public).unwrap();
+			let share = Keypair(keys[i].clone())
+				.recover(core::slice::from_ref(pok), single_dealer)
+				.unwrap();
+			let x = Scalar::from(i as u64 + 1);
+			((x, share.0), (x, share.1))
+		})
+		.unzip();
+	assert_eq!(
+		interpolate::<<E as EngineBLS>::SignatureGroup>(&shares),
+		secret.0,
+		"case {case}: n = {n}, t = {t}"
+	);
+	assert_eq!(
+		interpolate::<<E as EngineBLS>::SignatureGroup>(&blinding_shares),
+		secret.1,
+		"case {case}: n = {n}, t = {t}"
+	);
+}
+
+#[test]
+fn recovering_any_t_shares_of_a_resharing_gives_the_secret() {
+	let mut rng = ChaCha20Rng::seed_from_u64(u64::MAX);
+	for (n, t) in [(1, 1), (MAX_COMMITTEE, 1), (MAX_COMMITTEE, MAX_COMMITTEE)] {
+		reshare_then_recover(u64::MAX, &mut rng, n, t);
+	}
+	for_all(|case, rng| {
+		let n = rng.gen_range(1..=MAX_COMMITTEE);
+		let t = rng.gen_range(1..=n);
+		reshare_then_recover(case, rng, n, t);
+	});
+}
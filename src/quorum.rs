@@ -0,0 +1,344 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Quorum Certificates
+//!
+//! Committee members sign [`Vote`]s on 32 byte digests of whatever the
+//! protocol needs agreement on: dealings (see [`crate::certificate`]),
+//! complaints (see [`crate::evidence`]) and so on. Each use signs under its
+//! own context, so that a vote for one purpose cannot be replayed for
+//! another. [`QuorumCollector`] verifies and deduplicates incoming votes and
+//! aggregates them into a [`QuorumCert`] once enough members voted for the
+//! same digest. Anyone who knows the committee can verify the certificate
+//! against a threshold.
+//!
+//! The signatures are aggregated by addition, which is only sound if every
+//! committee key was registered with a proof of possession, as it must be
+//! anyway to rule out rogue keys in the committee.
+
+use crate::{
+	error::AcssError,
+	keys::{CommitteeKey, ShareIndex},
+};
+use ark_std::{collections::BTreeMap, vec::Vec, Zero};
+#[cfg(feature = "participant")]
+use w3f_bls::KeypairVT;
+use w3f_bls::{EngineBLS, Message, PublicKey, Signature};
+
+/// a committee member's signature over a digest
+pub struct Vote<E: EngineBLS> {
+	/// the digest voted for
+	pub digest: [u8; 32],
+	/// the index of the voting member in the committee
+	pub member: ShareIndex,
+	/// the member's signature over the digest
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for Vote<E> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<E: EngineBLS> Copy for Vote<E> {}
+
+impl<E: EngineBLS> Vote<E> {
+	/// vote for `digest` under `context`, as the member at index `member`
+	///
+	/// * `context`: The signing context of the votes
+	/// * `digest`: The digest voted for
+	/// * `member`: The index of the voting member
+	/// * `keypair`: The voting member's keypair
+	#[cfg(feature = "participant")]
+	pub fn sign(
+		context: &[u8],
+		digest: [u8; 32],
+		member: ShareIndex,
+		keypair: &KeypairVT<E>,
+	) -> Self {
+		let signature = keypair.sign(&Message::new(context, &digest));
+		Self { digest, member, signature }
+	}
+
+	/// true if the vote is signed under `context` by the member of
+	/// `committee` at its index
+	///
+	/// * `context`: The signing context of the votes
+	/// * `committee`: The public keys of the committee, in share order
+	pub fn verify<K: CommitteeKey<E>>(
+		&self,
+		context: &[u8],
+		committee: &[K],
+	) -> bool {
+		committee.get(self.member.position()).is_some_and(|member| {
+			self.signature.verify(
+				&Message::new(context, &self.digest),
+				&member.public_key(),
+			)
+		})
+	}
+}
+
+/// the aggregated votes of a quorum for a digest
+pub struct QuorumCert<E: EngineBLS> {
+	/// the digest voted for
+	pub digest: [u8; 32],
+	/// the indices of the voting members, in increasing order
+	pub signers: Vec<ShareIndex>,
+	/// the sum of their signatures
+	pub signature: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for QuorumCert<E> {
+	fn clone(&self) -> Self {
+		Self {
+			digest: self.digest,
+			signers: self.signers.clone(),
+			signature: self.signature,
+		}
+	}
+}
+
+impl<E: EngineBLS> QuorumCert<E> {
+	/// aggregate votes for the same digest into one signature
+	/// the votes are not checked, see `QuorumCert::verify`
+	///
+	/// fails with `InvalidCommittee` if there are no votes, with
+	/// `InvalidSignature` if they are for different digests, and with
+	/// `DuplicateShareIndex` if a member voted twice
+	///
+	/// * `votes`: The votes
+	pub fn aggregate(votes: &[Vote<E>]) -> Result<Self, AcssError> {
+		let digest = votes.first().ok_or(AcssError::InvalidCommittee)?.digest;
+		if votes.iter().any(|vote| vote.digest != digest) {
+			return Err(AcssError::InvalidSignature);
+		}
+		let mut signers: Vec<ShareIndex> =
+			votes.iter().map(|vote| vote.member).collect();
+		signers.sort();
+		if signers.windows(2).any(|pair| pair[0] == pair[1]) {
+			return Err(AcssError::DuplicateShareIndex);
+		}
+		let signature =
+			votes.iter().fold(E::SignatureGroup::zero(), |sum, vote| {
+				sum + vote.signature.0
+			});
+		Ok(Self { digest, signers, signature: Signature(signature) })
+	}
+
+	/// true if at least `threshold` distinct members of `committee` voted
+	/// for the digest under `context`
+	///
+	/// * `context`: The signing context of the votes
+	/// * `committee`: The public keys of the committee, in share order
+	/// * `threshold`: The number of votes required
+	pub fn verify<K: CommitteeKey<E>>(
+		&self,
+		context: &[u8],
+		committee: &[K],
+		threshold: usize,
+	) -> bool {
+		verify_aggregate(
+			context,
+			&self.digest,
+			&self.signers,
+			&self.signature,
+			committee,
+			threshold,
+		)
+	}
+}
+
+/// true if `signature` is the sum of the signatures under `context` over
+/// `digest` of at least `threshold` distinct members of `committee`, listed
+/// in increasing order in `signers`
+pub(crate) fn verify_aggregate<E: EngineBLS, K: CommitteeKey<E>>(
+	context: &[u8],
+	digest: &[u8; 32],
+	signers: &[ShareIndex],
+	signature: &Signature<E>,
+	committee: &[K],
+	threshold: usize,
+) -> bool {
+	if signers.len() < threshold ||
+		signers.windows(2).any(|pair| pair[0] >= pair[1])
+	{
+		return false;
+	}
+	let mut aggregated = E::PublicKeyGroup::zero();
+	for index in signers {
+		let Some(member) = committee.get(index.position()) else {
+			return false;
+		};
+		aggregated += member.public_key().0;
+	}
+	signature.verify(&Message::new(context, digest), &PublicKey(aggregated))
+}
+
+/// collects the votes of a committee until a quorum voted for a digest
+pub struct QuorumCollector<E: EngineBLS> {
+	context: &'static [u8],
+	threshold: usize,
+	/// the votes received for each digest not yet certified
+	votes: BTreeMap<[u8; 32], BTreeMap<ShareIndex, Vote<E>>>,
+}
+
+impl<E: EngineBLS> QuorumCollector<E> {
+	/// collect votes signed under `context` until `threshold` members voted
+	/// for the same digest
+	///
+	/// * `context`: The signing context of the votes
+	/// * `threshold`: The number of votes required
+	pub fn new(context: &'static [u8], threshold: usize) -> Self {
+		Self { context, threshold, votes: BTreeMap::new() }
+	}
+
+	/// the number of votes received for `digest` so far, zero once it is
+	/// certified
+	pub fn votes(&self, digest: &[u8; 32]) -> usize {
+		self.votes.get(digest).map_or(0, BTreeMap::len)
+	}
+
+	/// the votes received for digests not yet certified
+	#[cfg(feature = "participant")]
+	pub(crate) fn pending(&self) -> impl Iterator<Item = &Vote<E>> {
+		self.votes.values().flat_map(BTreeMap::values)
+	}
+
+	/// receive a vote
+	/// outputs the certificate of the digest once a quorum voted for it
+	/// fails with `InvalidSignature` if the vote is not signed by the member
+	/// of the committee at its index, and with `ReplayedMessage` if the
+	/// member already voted for the digest
+	///
+	/// * `vote`: The vote
+	/// * `committee`: The public keys of the committee, in share order
+	pub fn add<K: CommitteeKey<E>>(
+		&mut self,
+		vote: Vote<E>,
+		committee: &[K],
+	) -> Result<Option<QuorumCert<E>>, AcssError> {
+		if !vote.verify(self.context, committee) {
+			return Err(AcssError::InvalidSignature);
+		}
+		let votes = self.votes.entry(vote.digest).or_default();
+		if votes.insert(vote.member, vote).is_some() {
+			return Err(AcssError::ReplayedMessage);
+		}
+		if votes.len() < self.threshold {
+			return Ok(None);
+		}
+
+		let votes: Vec<Vote<E>> = self
+			.votes
+			.remove(&vote.digest)
+			.unwrap_or_default()
+			.into_values()
+			.collect();
+		QuorumCert::aggregate(&votes).map(Some)
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	const CONTEXT: &[u8] = b"acss/test";
+
+	fn votes(
+		rng: &mut ChaCha20Rng,
+		digest: [u8; 32],
+	) -> (Vec<PublicKey<E>>, Vec<Vote<E>>) {
+		let members: Vec<KeypairVT<E>> =
+			(0..4).map(|_| KeypairVT::<E>::generate(&mut *rng)).collect();
+		let votes = members
+			.iter()
+			.enumerate()
+			.map(|(position, kp)| {
+				let index = ShareIndex::from_position(position).unwrap();
+				Vote::sign(CONTEXT, digest, index, kp)
+			})
+			.collect();
+		(members.iter().map(|kp| kp.public).collect(), votes)
+	}
+
+	#[test]
+	fn collectors_certify_a_quorum_once() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (committee, votes) = votes(&mut rng, [1; 32]);
+		let mut collector = QuorumCollector::new(CONTEXT, 3);
+
+		let mut misattributed = votes[0];
+		misattributed.member = votes[1].member;
+		assert_eq!(
+			collector.add(misattributed, &committee).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		assert_eq!(
+			QuorumCollector::new(b"acss/other", 3)
+				.add(votes[0], &committee)
+				.err(),
+			Some(AcssError::InvalidSignature)
+		);
+
+		assert!(collector.add(votes[2], &committee).unwrap().is_none());
+		assert_eq!(
+			collector.add(votes[2], &committee).err(),
+			Some(AcssError::ReplayedMessage)
+		);
+		assert!(collector.add(votes[0], &committee).unwrap().is_none());
+		assert_eq!(collector.votes(&[1; 32]), 2);
+		let cert = collector.add(votes[3], &committee).unwrap().unwrap();
+		assert_eq!(collector.votes(&[1; 32]), 0);
+		assert_eq!(
+			cert.signers,
+			[1, 3, 4].map(|i| ShareIndex::new(i).unwrap())
+		);
+		assert!(cert.verify(CONTEXT, &committee, 3));
+		assert!(!cert.verify(CONTEXT, &committee, 4));
+		assert!(!cert.verify(b"acss/other", &committee, 3));
+		assert!(!cert.verify(CONTEXT, &committee[..3], 3));
+	}
+
+	#[test]
+	fn aggregation_rejects_mixed_and_repeated_votes() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (committee, votes) = votes(&mut rng, [1; 32]);
+		let other = Vote { digest: [2; 32], ..votes[2] };
+		assert_eq!(
+			QuorumCert::aggregate(&[votes[0], votes[1], other]).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		assert_eq!(
+			QuorumCert::aggregate(&[votes[0], votes[0], votes[1]]).err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+		assert_eq!(
+			QuorumCert::<E>::aggregate(&[]).err(),
+			Some(AcssError::InvalidCommittee)
+		);
+
+		let mut forged = QuorumCert::aggregate(&votes[..3]).unwrap();
+		assert!(forged.verify(CONTEXT, &committee, 3));
+		forged.signature = votes[0].signature;
+		assert!(!forged.verify(CONTEXT, &committee, 3));
+	}
+}
@@ -0,0 +1,319 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Redacted Logging
+//!
+//! `to_redacted_json` renders a value as one line of JSON for logs and
+//! telemetry. It keeps the structure of the value (counts, indices, epochs,
+//! sizes) but none of its bytes. Public data such as points, ciphertexts,
+//! proofs and transcripts becomes its length and a short SHA-256
+//! fingerprint, which is enough to tell values apart and to match them
+//! across logs. Secret data such as keys, shares and exported keys becomes
+//! its length alone, since a fingerprint of a secret would let anyone who
+//! reads the log confirm a guess of it.
+//!
+//! Layout: every value is an object whose `type` names its Rust type, a
+//! public field is `{"len":<bytes>,"sha256":"<16 hex digits>"}` and a secret
+//! field is `{"len":<bytes>,"redacted":true}`.
+
+use crate::proofs::{
+	hashed_el_gamal::Ciphertext, hashed_el_gamal_sigma::BatchPoK,
+	ser::HashWriter,
+};
+#[cfg(feature = "participant")]
+use crate::{
+	acss::{DoubleSecret, Keypair},
+	export::ExportedKey,
+	keys::SecretKey,
+	share::Share,
+};
+#[cfg(feature = "verify")]
+use crate::{
+	messages::SignedMessage, resharing::Resharing, transcript::SignedDealing,
+};
+use alloc::string::String;
+use ark_ec::CurveGroup;
+#[cfg(feature = "participant")]
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use core::fmt::Write;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "verify")]
+use w3f_bls::EngineBLS;
+
+/// the number of bytes of the SHA-256 digest kept as a fingerprint
+pub const FINGERPRINT_LEN: usize = 8;
+
+/// a JSON object, written one field at a time
+struct JsonObject(String);
+
+impl JsonObject {
+	/// an object of type `name`
+	fn new(name: &str) -> Self {
+		Self(format!("{{\"type\":\"{name}\""))
+	}
+
+	/// start the field `name`
+	fn key(&mut self, name: &str) -> &mut String {
+		let _ = write!(self.0, ",\"{name}\":");
+		&mut self.0
+	}
+
+	/// a number
+	fn number(mut self, name: &str, value: u64) -> Self {
+		let _ = write!(self.key(name), "{value}");
+		self
+	}
+
+	/// a boolean
+	#[cfg(feature = "verify")]
+	fn flag(mut self, name: &str, value: bool) -> Self {
+		let _ = write!(self.key(name), "{value}");
+		self
+	}
+
+	/// an identifier, written as a string
+	#[cfg(feature = "participant")]
+	fn label(mut self, name: &str, value: &str) -> Self {
+		let _ = write!(self.key(name), "\"{value}\"");
+		self
+	}
+
+	/// public bytes, as their length and fingerprint
+	fn bytes(self, name: &str, value: &[u8]) -> Self {
+		self.fingerprint(name, value.len(), Sha256::new_with_prefix(value))
+	}
+
+	/// a public element, as the length and fingerprint of its compressed
+	/// encoding
+	fn element<T: CanonicalSerialize>(self, name: &str, value: &T) -> Self {
+		let mut hasher = Sha256::new();
+		// writing to a hasher cannot fail
+		let _ = value.serialize_compressed(HashWriter(&mut hasher));
+		self.fingerprint(name, value.compressed_size(), hasher)
+	}
+
+	/// secret data, as its length alone
+	#[cfg(feature = "participant")]
+	fn secret(mut self, name: &str, len: usize) -> Self {
+		let _ = write!(self.key(name), "{{\"len\":{len},\"redacted\":true}}");
+		self
+	}
+
+	fn fingerprint(mut self, name: &str, len: usize, hasher: Sha256) -> Self {
+		let digest = hasher.finalize();
+		let out = self.key(name);
+		let _ = write!(out, "{{\"len\":{len},\"sha256\":\"");
+		for byte in &digest[..FINGERPRINT_LEN] {
+			let _ = write!(out, "{byte:02x}");
+		}
+		out.push_str("\"}");
+		self
+	}
+
+	fn finish(mut self) -> String {
+		self.0.push('}');
+		self.0
+	}
+}
+
+/// the length of the encoding of a scalar
+#[cfg(feature = "participant")]
+fn scalar_len<F: PrimeField>() -> usize {
+	F::zero().compressed_size()
+}
+
+impl<C: CurveGroup, const N: usize> Ciphertext<C, N> {
+	/// the ciphertext as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("Ciphertext")
+			.element("c1", &self.c1)
+			.bytes("c2", &self.c2)
+			.bytes("tag", &self.tag)
+			.finish()
+	}
+}
+
+impl<C: CurveGroup> BatchPoK<C> {
+	/// the proof as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("BatchPoK")
+			.number("ciphertexts", self.ciphertexts.len() as u64)
+			.element("proof", self)
+			.finish()
+	}
+}
+
+#[cfg(feature = "verify")]
+impl<E: EngineBLS> Resharing<E> {
+	/// the resharing as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("Resharing")
+			.number("recipients", self.num_recipients() as u64)
+			.flag("detection_tags", self.detection_tags().is_some())
+			.element("transcript", self)
+			.finish()
+	}
+}
+
+#[cfg(feature = "verify")]
+impl<E: EngineBLS> SignedDealing<E> {
+	/// the dealing as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		let json = JsonObject::new("SignedDealing")
+			.number("scheme", self.scheme_id().code().into())
+			.bytes("session", &self.header.session)
+			.number("epoch", self.header.epoch)
+			.number("round", self.header.round.into())
+			.bytes("transcript", &self.transcript);
+		match &self.signature {
+			Some(signature) => json.element("signature", &signature.0),
+			None => json,
+		}
+		.finish()
+	}
+}
+
+#[cfg(feature = "verify")]
+impl<E: EngineBLS> SignedMessage<E> {
+	/// the message as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("SignedMessage")
+			.bytes("session", &self.header.session)
+			.number("epoch", self.header.epoch)
+			.number("round", self.header.round.into())
+			.number("sequence", self.header.sequence)
+			.element("sender", &self.sender.0)
+			.bytes("payload", &self.payload)
+			.element("signature", &self.signature.0)
+			.finish()
+	}
+}
+
+#[cfg(feature = "participant")]
+impl<E: EngineBLS> DoubleSecret<E> {
+	/// the secret as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("DoubleSecret")
+			.secret("secret", 2 * scalar_len::<E::Scalar>())
+			.finish()
+	}
+}
+
+#[cfg(feature = "participant")]
+impl<C: CurveGroup> SecretKey<C> {
+	/// the key as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("SecretKey")
+			.element("public", &self.public_key().0)
+			.secret("secret", scalar_len::<C::ScalarField>())
+			.finish()
+	}
+}
+
+#[cfg(feature = "participant")]
+impl<E: EngineBLS> Keypair<E> {
+	/// the keypair as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("Keypair")
+			.element("public", &self.0.public.0)
+			.secret("secret", scalar_len::<E::Scalar>())
+			.finish()
+	}
+}
+
+#[cfg(feature = "participant")]
+impl<E: EngineBLS> Share<E> {
+	/// the share as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		let json = JsonObject::new("Share")
+			.number("index", self.index.get().into())
+			.number("epoch", self.epoch)
+			.number("expires", self.expires)
+			.label("policy", &format!("{:?}", self.policy));
+		match &self.custodian {
+			Some(custodian) => json
+				.label("device", &format!("{:?}", custodian.kind))
+				.bytes("custodian", &custodian.id),
+			None => json,
+		}
+		.secret("secret", 2 * scalar_len::<E::Scalar>())
+		.finish()
+	}
+}
+
+#[cfg(feature = "participant")]
+impl ExportedKey {
+	/// the key as redacted JSON, see the module docs
+	pub fn to_redacted_json(&self) -> String {
+		JsonObject::new("ExportedKey")
+			.secret("key", self.as_bytes().len())
+			.finish()
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{keys::ShareIndex, proofs::hashed_el_gamal::HashedElGamal};
+	use ark_ec::Group;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use serde_json::Value;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+	type G = <E as EngineBLS>::PublicKeyGroup;
+
+	#[test]
+	fn redacted_json_keeps_structure_and_hides_bytes() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keypair = Keypair::<E>(KeypairVT::generate(&mut rng));
+		let mut secret = vec![];
+		keypair.0.secret.0.serialize_compressed(&mut secret).unwrap();
+		let json = keypair.to_redacted_json();
+		assert!(!json.contains(&hex::encode(&secret[..8])));
+		let value: Value = serde_json::from_str(&json).unwrap();
+		assert_eq!(value["type"], "Keypair");
+		assert_eq!(value["secret"]["redacted"], true);
+		assert_eq!(value["secret"]["len"], 32);
+		assert_eq!(value["public"]["sha256"].as_str().unwrap().len(), 16);
+
+		let sk = SecretKey::<G>::new(<G as Group>::ScalarField::rand(&mut rng));
+		let ct: Ciphertext<G, 64> =
+			HashedElGamal::encrypt([7; 64], sk.public_key(), &mut rng).unwrap();
+		let value: Value =
+			serde_json::from_str(&ct.to_redacted_json()).unwrap();
+		assert_eq!(value["c2"]["len"], 64);
+		// equal values have equal fingerprints, so logs can be correlated
+		assert_eq!(ct.to_redacted_json(), ct.clone().to_redacted_json());
+
+		let share = Share::new(
+			DoubleSecret::<E>(
+				<E as EngineBLS>::Scalar::rand(&mut rng),
+				<E as EngineBLS>::Scalar::rand(&mut rng),
+			),
+			ShareIndex::new(2).unwrap(),
+			4,
+		);
+		let value: Value =
+			serde_json::from_str(&share.to_redacted_json()).unwrap();
+		assert_eq!(value["index"], 2);
+		assert_eq!(value["expires"], 5);
+		assert_eq!(value["policy"], "Enforce");
+		assert_eq!(value["secret"]["len"], 64);
+	}
+}
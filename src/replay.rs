@@ -0,0 +1,152 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Replay Protection
+//!
+//! Messages exchanged between nodes carry a per-sender sequence number in
+//! their [`MessageHeader`]. A receiver keeps one [`ReplayWindow`] per sender
+//! and accepts each sequence number at most once (see
+//! [`SignedMessage::open`]), so a network adversary cannot re-deliver an old
+//! message. Messages may arrive out of order as long as they are no more than
+//! [`WINDOW_SIZE`] behind the newest one seen.
+//!
+//! [`MessageHeader`]: crate::messages::MessageHeader
+//! [`SignedMessage::open`]: crate::messages::SignedMessage::open
+
+use crate::error::AcssError;
+#[cfg(feature = "offchain")]
+use crate::transcript::read_array;
+#[cfg(feature = "offchain")]
+use ark_std::vec::Vec;
+
+/// how far behind the newest sequence number a message may arrive
+pub const WINDOW_SIZE: u64 = 64;
+
+/// a sliding window over the sequence numbers received from one sender
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplayWindow {
+	/// the newest sequence number accepted, if any
+	highest: Option<u64>,
+	/// bit i is set if `highest - i` was accepted
+	seen: u64,
+}
+
+impl ReplayWindow {
+	/// create a window that has not seen any message
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// the newest sequence number accepted so far
+	pub fn highest(&self) -> Option<u64> {
+		self.highest
+	}
+
+	/// accept a sequence number, recording it as seen
+	/// fails with `ReplayedMessage` if it was already accepted, or if it is
+	/// too old to tell
+	///
+	/// * `sequence`: The sequence number of the received message
+	pub fn accept(&mut self, sequence: u64) -> Result<(), AcssError> {
+		let Some(highest) = self.highest else {
+			self.highest = Some(sequence);
+			self.seen = 1;
+			return Ok(());
+		};
+
+		if sequence > highest {
+			let shift = sequence - highest;
+			self.seen =
+				if shift >= WINDOW_SIZE { 0 } else { self.seen << shift };
+			self.seen |= 1;
+			self.highest = Some(sequence);
+			return Ok(());
+		}
+
+		let age = highest - sequence;
+		if age >= WINDOW_SIZE || self.seen & (1 << age) != 0 {
+			return Err(AcssError::ReplayedMessage);
+		}
+		self.seen |= 1 << age;
+		Ok(())
+	}
+
+	/// append the encoding `highest || seen` of the window, with `highest`
+	/// prefixed by 1 if any message was accepted and 0 otherwise
+	#[cfg(feature = "offchain")]
+	pub(crate) fn write(&self, out: &mut Vec<u8>) {
+		match self.highest {
+			Some(highest) => {
+				out.push(1);
+				out.extend_from_slice(&highest.to_le_bytes());
+			},
+			None => out.push(0),
+		}
+		out.extend_from_slice(&self.seen.to_le_bytes());
+	}
+
+	/// read a window written by `write`
+	/// fails with `InvalidTranscript` if the bytes cannot be decoded
+	#[cfg(feature = "offchain")]
+	pub(crate) fn read(reader: &mut &[u8]) -> Result<Self, AcssError> {
+		let highest = match read_array::<1>(reader)? {
+			[0] => None,
+			[1] => Some(u64::from_le_bytes(read_array::<8>(reader)?)),
+			_ => return Err(AcssError::InvalidTranscript),
+		};
+		let seen = u64::from_le_bytes(read_array::<8>(reader)?);
+		Ok(Self { highest, seen })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn window_rejects_replays() {
+		let mut window = ReplayWindow::new();
+		assert_eq!(window.highest(), None);
+		for sequence in [0, 1, 2] {
+			assert!(window.accept(sequence).is_ok());
+		}
+		for sequence in [0, 1, 2] {
+			assert_eq!(
+				window.accept(sequence),
+				Err(AcssError::ReplayedMessage)
+			);
+		}
+		assert_eq!(window.highest(), Some(2));
+	}
+
+	#[test]
+	fn window_accepts_reordered_messages_within_its_size() {
+		let mut window = ReplayWindow::new();
+		assert!(window.accept(100).is_ok());
+		assert!(window.accept(100 - (WINDOW_SIZE - 1)).is_ok());
+		assert!(window.accept(99).is_ok());
+		assert_eq!(window.accept(99), Err(AcssError::ReplayedMessage));
+		// too old to tell whether it was already seen
+		assert_eq!(
+			window.accept(100 - WINDOW_SIZE),
+			Err(AcssError::ReplayedMessage)
+		);
+
+		// a large jump forgets everything before the window
+		assert!(window.accept(100 + 2 * WINDOW_SIZE).is_ok());
+		assert!(window.accept(100 + WINDOW_SIZE + 1).is_ok());
+		assert_eq!(window.accept(100), Err(AcssError::ReplayedMessage));
+	}
+}
@@ -0,0 +1,465 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Resharings
+//!
+//! [`Resharing`] is the output of a dealing: for each member of the committee,
+//! in committee order, the double public key of their new share and the
+//! batched PoK that delivers it. Members look up their PoK by public key or by
+//! share index rather than by position.
+//!
+//! The compact transcript encoding only carries the dealings, since every
+//! recipient already knows the committee. [`Resharing::from_dealings`] pairs a
+//! decoded transcript with its committee again.
+//!
+//! [`Resharing::refresh_in_place`] runs a whole refresh of an unchanged
+//! committee in one call, for a party holding every member's keypair (e.g. a
+//! simulation, or a committee run by a single operator).
+
+#[cfg(feature = "participant")]
+use crate::{
+	acss::{DoubleSecret, Keypair},
+	keys::PublicKey as EncryptionKey,
+	params::ThresholdParams,
+	rng::SecureRng,
+	wipe,
+};
+use crate::{
+	error::AcssError,
+	keys::{committee_keys, CommitteeKey, ShareIndex},
+	proofs::{hashed_el_gamal::DetectionTag, hashed_el_gamal_sigma::BatchPoK},
+};
+use ark_serialize::{
+	CanonicalDeserialize, CanonicalSerialize, Compress, Read,
+	SerializationError, Valid, Validate, Write,
+};
+use ark_std::vec::Vec;
+use core::{iter::Zip, slice};
+use w3f_bls::{DoublePublicKey, EngineBLS, PublicKey};
+#[cfg(feature = "participant")]
+use w3f_bls::{DoublePublicKeyScheme, SecretKeyVT};
+
+/// the output of a resharing to a committee
+pub struct Resharing<E: EngineBLS> {
+	recipients: Vec<PublicKey<E>>,
+	dealings: Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>,
+	detection_tags: Option<Vec<DetectionTag>>,
+}
+
+impl<E: EngineBLS> Resharing<E> {
+	/// pair the dealings of a resharing with the committee they were dealt to
+	/// fails with `InvalidCommittee` unless there is one dealing per member
+	///
+	/// * `committee`: The committee, in the order it was dealt to
+	/// * `dealings`: The dealings, e.g. decoded from a compact transcript
+	pub fn from_dealings<K: CommitteeKey<E>>(
+		committee: &[K],
+		dealings: Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>,
+	) -> Result<Self, AcssError> {
+		if committee.len() != dealings.len() {
+			return Err(AcssError::InvalidCommittee);
+		}
+		Ok(Self {
+			recipients: committee_keys(committee),
+			dealings,
+			detection_tags: None,
+		})
+	}
+
+	/// the number of recipients
+	pub fn len(&self) -> usize {
+		self.dealings.len()
+	}
+
+	/// true if there are no recipients
+	pub fn is_empty(&self) -> bool {
+		self.dealings.is_empty()
+	}
+
+	/// the committee, in the order it was dealt to
+	pub fn recipients(&self) -> &[PublicKey<E>] {
+		&self.recipients
+	}
+
+	/// the PoK dealt to the recipient with public key `pk`
+	pub fn for_recipient(
+		&self,
+		pk: &PublicKey<E>,
+	) -> Option<&BatchPoK<E::PublicKeyGroup>> {
+		let position = self.recipients.iter().position(|r| r.0 == pk.0)?;
+		Some(&self.dealings[position].1)
+	}
+
+	/// the PoK dealt to the recipient holding the share at `index`
+	pub fn by_index(
+		&self,
+		index: ShareIndex,
+	) -> Option<&BatchPoK<E::PublicKeyGroup>> {
+		self.dealings.get(index.position()).map(|(_, pok)| pok)
+	}
+
+	/// the double public key of the share at `index`
+	pub fn share_public_key(
+		&self,
+		index: ShareIndex,
+	) -> Option<&DoublePublicKey<E>> {
+		self.dealings.get(index.position()).map(|(pk, _)| pk)
+	}
+
+	/// (recipient, share public key, PoK) for each recipient, in committee
+	/// order
+	pub fn iter(&self) -> Iter<'_, E> {
+		Iter(self.recipients.iter().zip(self.dealings.iter()))
+	}
+
+	/// the PoKs, in committee order
+	pub fn poks(&self) -> impl Iterator<Item = &BatchPoK<E::PublicKeyGroup>> {
+		self.dealings.iter().map(|(_, pok)| pok)
+	}
+
+	/// the dealings without their recipients, as carried by the compact
+	/// transcript encoding
+	pub fn dealings(
+		&self,
+	) -> &[(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)] {
+		&self.dealings
+	}
+
+	/// the detection tag of each recipient's entry, in committee order, if
+	/// the dealing was made with them
+	///
+	/// the tags are carried by the compact encoding (see
+	/// [`crate::transcript::encode_compact_with_tags`]) but not by the
+	/// canonical one
+	pub fn detection_tags(&self) -> Option<&[DetectionTag]> {
+		self.detection_tags.as_deref()
+	}
+
+	/// attach one detection tag per recipient
+	#[cfg(feature = "participant")]
+	pub(crate) fn set_detection_tags(&mut self, tags: Vec<DetectionTag>) {
+		self.detection_tags = (tags.len() == self.len()).then_some(tags);
+	}
+
+	/// hand the share at `index` to a new recipient, who holds it under
+	/// `pok`, see [`crate::rotation`]
+	/// the detection tags are dropped, since the new entry has none
+	pub(crate) fn replace_recipient(
+		&mut self,
+		index: ShareIndex,
+		recipient: PublicKey<E>,
+		pok: BatchPoK<E::PublicKeyGroup>,
+	) {
+		self.recipients[index.position()] = recipient;
+		self.dealings[index.position()].1 = pok;
+		self.detection_tags = None;
+	}
+
+	/// take the dealings, dropping the recipients
+	pub fn into_dealings(
+		self,
+	) -> Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)> {
+		self.dealings
+	}
+
+	/// refresh the shares of an unchanged committee, keeping the secret
+	///
+	/// every member recovers its share from this resharing and deals it to
+	/// the committee again, then every member recovers its new share from
+	/// those sub-dealings. The resharing is replaced by one dealing the new
+	/// shares to the same members, so the old shares become useless.
+	///
+	/// fails with `InvalidCommittee` unless `committee` holds the keypairs of
+	/// the recipients, in order, and with `InvalidParams` if `t` does not fit
+	/// the committee
+	///
+	/// * `committee`: The keypairs of the recipients, in committee order
+	/// * `t`: The threshold of the new shares
+	/// * `rng`: A CSPRNG
+	#[cfg(feature = "participant")]
+	pub fn refresh_in_place<R: SecureRng>(
+		&mut self,
+		committee: &[Keypair<E>],
+		t: u8,
+		mut rng: R,
+	) -> Result<(), AcssError> {
+		if committee.len() != self.len() ||
+			committee
+				.iter()
+				.zip(self.recipients.iter())
+				.any(|(member, recipient)| member.public().0 != recipient.0)
+		{
+			return Err(AcssError::InvalidCommittee);
+		}
+		let params = ThresholdParams::for_committee(&self.recipients, t)?;
+		// each member's current share, recovered from a single dealer
+		let single_dealer = ThresholdParams::new(1, 1)?;
+
+		let mut sub_dealings = Vec::with_capacity(committee.len());
+		for (member, pok) in committee.iter().zip(self.poks()) {
+			let share = member.recover(slice::from_ref(pok), single_dealer)?;
+			sub_dealings.push(share.reshare(
+				&self.recipients,
+				params,
+				&mut rng,
+			)?);
+		}
+
+		// interpolating over every sub-dealing is correct whatever the
+		// threshold of the old shares was
+		let dealers = ThresholdParams::new(params.n(), params.n())?;
+		let mut dealings = Vec::with_capacity(committee.len());
+		for (position, member) in committee.iter().enumerate() {
+			let recipient = ShareIndex::from_position(position)
+				.ok_or(AcssError::InvalidCommittee)?;
+			let poks: Vec<(ShareIndex, BatchPoK<E::PublicKeyGroup>)> =
+				sub_dealings
+					.iter()
+					.zip(params.indices())
+					.filter_map(|(sub_dealing, dealer)| {
+						let pok = sub_dealing.by_index(recipient)?;
+						Some((dealer, pok.clone()))
+					})
+					.collect();
+			let share: DoubleSecret<E> =
+				member.recover_indexed(&poks, dealers)?;
+			let mut messages = [share.0, share.1];
+			let pok = BatchPoK::prove(
+				&messages,
+				EncryptionKey(member.public().0),
+				&mut rng,
+			);
+			wipe(&mut messages);
+			let pk = SecretKeyVT::<E>(share.0).into_double_public_key();
+			let pok = pok?;
+			dealings.push((pk, pok));
+		}
+		self.dealings = dealings;
+		self.detection_tags = None;
+		Ok(())
+	}
+}
+
+impl<E: EngineBLS> CanonicalSerialize for Resharing<E> {
+	fn serialize_with_mode<W: Write>(
+		&self,
+		mut writer: W,
+		compress: Compress,
+	) -> Result<(), SerializationError> {
+		self.recipients.serialize_with_mode(&mut writer, compress)?;
+		self.dealings.serialize_with_mode(writer, compress)
+	}
+
+	fn serialized_size(&self, compress: Compress) -> usize {
+		self.recipients.serialized_size(compress) +
+			self.dealings.serialized_size(compress)
+	}
+}
+
+impl<E: EngineBLS> Valid for Resharing<E> {
+	fn check(&self) -> Result<(), SerializationError> {
+		if self.recipients.len() != self.dealings.len() {
+			return Err(SerializationError::InvalidData);
+		}
+		self.recipients.check()?;
+		self.dealings.check()
+	}
+}
+
+impl<E: EngineBLS> CanonicalDeserialize for Resharing<E> {
+	/// the recipients and dealings must have the same length, whether or not
+	/// the elements are validated
+	fn deserialize_with_mode<R: Read>(
+		mut reader: R,
+		compress: Compress,
+		validate: Validate,
+	) -> Result<Self, SerializationError> {
+		let recipients =
+			Vec::deserialize_with_mode(&mut reader, compress, validate)?;
+		let dealings = Vec::deserialize_with_mode(reader, compress, validate)?;
+		if recipients.len() != dealings.len() {
+			return Err(SerializationError::InvalidData);
+		}
+		let resharing = Self { recipients, dealings, detection_tags: None };
+		if let Validate::Yes = validate {
+			resharing.check()?;
+		}
+		Ok(resharing)
+	}
+}
+
+/// iterates over the (recipient, share public key, PoK) of a resharing
+pub struct Iter<'a, E: EngineBLS>(
+	Zip<
+		slice::Iter<'a, PublicKey<E>>,
+		slice::Iter<'a, (DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>,
+	>,
+);
+
+impl<'a, E: EngineBLS> Iterator for Iter<'a, E> {
+	type Item = (
+		&'a PublicKey<E>,
+		&'a DoublePublicKey<E>,
+		&'a BatchPoK<E::PublicKeyGroup>,
+	);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(|(recipient, (pk, pok))| (recipient, pk, pok))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+
+impl<E: EngineBLS> ExactSizeIterator for Iter<'_, E> {}
+
+impl<'a, E: EngineBLS> IntoIterator for &'a Resharing<E> {
+	type Item = <Iter<'a, E> as Iterator>::Item;
+	type IntoIter = Iter<'a, E>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::acss::interpolate;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	fn committee_and_resharing() -> (Vec<KeypairVT<E>>, Resharing<E>) {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|kp| kp.public).collect();
+		let resharing = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		)
+		.reshare(
+			&committee,
+			ThresholdParams::for_committee(&committee, 2).unwrap(),
+			&mut rng,
+		)
+		.unwrap();
+		(keys, resharing)
+	}
+
+	#[test]
+	fn resharing_looks_up_poks_by_recipient_and_index() {
+		let (keys, resharing) = committee_and_resharing();
+		assert_eq!(resharing.len(), 3);
+		assert_eq!(resharing.iter().len(), 3);
+
+		for (position, kp) in keys.iter().enumerate() {
+			let index = ShareIndex::from_position(position).unwrap();
+			let pok = resharing.for_recipient(&kp.public).unwrap();
+			assert_eq!(resharing.by_index(index), Some(pok));
+			assert!(pok.verify(crate::keys::PublicKey(kp.public.0)));
+		}
+
+		let stranger =
+			KeypairVT::<E>::generate(&mut ChaCha20Rng::seed_from_u64(1));
+		assert!(resharing.for_recipient(&stranger.public).is_none());
+		let out_of_range = ShareIndex::new(4).unwrap();
+		assert!(resharing.by_index(out_of_range).is_none());
+		assert!(resharing.share_public_key(out_of_range).is_none());
+	}
+
+	#[test]
+	fn resharing_round_trips_and_rejects_mismatched_lengths() {
+		let (_, resharing) = committee_and_resharing();
+		let mut bytes = Vec::new();
+		resharing.serialize_compressed(&mut bytes).unwrap();
+		let decoded =
+			Resharing::<E>::deserialize_compressed(&bytes[..]).unwrap();
+		assert_eq!(decoded.len(), resharing.len());
+		for (a, b) in decoded.iter().zip(resharing.iter()) {
+			assert_eq!(a.0 .0, b.0 .0);
+			assert_eq!(a.2, b.2);
+		}
+
+		let recipients = resharing.recipients().to_vec();
+		let mut dealings = resharing.into_dealings();
+		dealings.pop();
+
+		let mut bytes = Vec::new();
+		recipients.serialize_compressed(&mut bytes).unwrap();
+		dealings.serialize_compressed(&mut bytes).unwrap();
+		assert!(Resharing::<E>::deserialize_compressed(&bytes[..]).is_err());
+		assert!(Resharing::<E>::deserialize_compressed_unchecked(&bytes[..])
+			.is_err());
+		assert_eq!(
+			Resharing::from_dealings(&recipients, dealings).err(),
+			Some(AcssError::InvalidCommittee)
+		);
+	}
+
+	#[test]
+	fn refreshing_in_place_keeps_the_secret_and_replaces_the_shares() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (keys, mut resharing) = committee_and_resharing();
+		let members: Vec<Keypair<E>> = keys.into_iter().map(Keypair).collect();
+		let single_dealer = ThresholdParams::new(1, 1).unwrap();
+		let shares = |resharing: &Resharing<E>| -> Vec<_> {
+			members
+				.iter()
+				.zip(resharing.poks())
+				.enumerate()
+				.map(|(position, (member, pok))| {
+					let share = member
+						.recover(slice::from_ref(pok), single_dealer)
+						.unwrap();
+					let x = ShareIndex::from_position(position).unwrap();
+					(x.to_scalar(), share.0)
+				})
+				.collect()
+		};
+		let before = shares(&resharing);
+		let secret =
+			interpolate::<<E as EngineBLS>::SignatureGroup>(&before[..2]);
+
+		resharing.refresh_in_place(&members, 2, &mut rng).unwrap();
+		let after = shares(&resharing);
+		assert!(before.iter().zip(&after).all(|(old, new)| old.1 != new.1));
+		for window in [&after[..2], &after[1..]] {
+			assert_eq!(
+				interpolate::<<E as EngineBLS>::SignatureGroup>(window),
+				secret
+			);
+		}
+		for ((_, share_pk, _), (_, share)) in resharing.iter().zip(&after) {
+			let expected = SecretKeyVT::<E>(*share).into_double_public_key();
+			assert_eq!((share_pk.0, share_pk.1), (expected.0, expected.1));
+		}
+
+		assert_eq!(
+			resharing.refresh_in_place(&members[1..], 2, &mut rng),
+			Err(AcssError::InvalidCommittee)
+		);
+		assert_eq!(
+			resharing.refresh_in_place(&members, 4, &mut rng),
+			Err(AcssError::InvalidParams)
+		);
+	}
+}
@@ -0,0 +1,383 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Linkable Ring Signatures
+//!
+//! A [`RingSignature`] proves that the signer holds the secret key of one of
+//! the keys in a ring, without revealing which (an LSAG signature, Liu, Wei
+//! and Wong 2004). Every signature by the same key carries the same key
+//! image x * H(pk), so two signatures by one member can be linked even though
+//! neither can be attributed. Blinded dealings (see
+//! [`crate::transcript::BlindedDealing`]) use them so that a dealer is known
+//! to be allowed, and to deal at most once, but not who it is.
+//!
+//! Hashing the members' keys to the curve dominates the cost of signing and
+//! verifying, so a [`Ring`] does it once and can be reused for every
+//! signature over the same members.
+//!
+//! Layout: `key image || c_0 || s_1 || ... || s_n` for a ring of n keys.
+
+use crate::{
+	error::AcssError,
+	keys::{committee_keys, CommitteeKey},
+	proofs::{nums_generator, ser::HashWriter},
+	transcript::{read, read_point, write},
+};
+#[cfg(feature = "participant")]
+use crate::{rng::SecureRng, wipe};
+use ark_ec::Group;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+#[cfg(feature = "participant")]
+use ark_std::UniformRand;
+use ark_std::{vec::Vec, Zero};
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+#[cfg(feature = "participant")]
+use w3f_bls::KeypairVT;
+use w3f_bls::{EngineBLS, PublicKey};
+
+/// the domain separation label of the ring challenges
+const CHALLENGE_LABEL: &[u8] = b"acss/ring/challenge";
+/// the domain separation label of the key image base points
+const KEY_IMAGE_LABEL: &[u8] = b"acss/ring/key-image";
+
+/// the keys of the members who may sign, with the base point of each
+/// member's key image
+pub struct Ring<E: EngineBLS> {
+	keys: Vec<PublicKey<E>>,
+	bases: Vec<E::PublicKeyGroup>,
+}
+
+impl<E: EngineBLS> Ring<E> {
+	/// build a ring, hashing each key to the base point H(pk) of its key
+	/// image
+	/// fails with `InvalidTranscript` if a key cannot be encoded
+	///
+	/// * `keys`: The keys of the members
+	pub fn new<K: CommitteeKey<E>>(keys: &[K]) -> Result<Self, AcssError> {
+		let keys = committee_keys(keys);
+		let bases = keys
+			.iter()
+			.map(|pk| {
+				let mut label = KEY_IMAGE_LABEL.to_vec();
+				write(&pk.0, &mut label)?;
+				Ok(nums_generator::<E::PublicKeyGroup>(&label))
+			})
+			.collect::<Result<_, AcssError>>()?;
+		Ok(Self { keys, bases })
+	}
+
+	/// the keys of the members
+	pub fn keys(&self) -> &[PublicKey<E>] {
+		&self.keys
+	}
+
+	/// the number of members
+	pub fn len(&self) -> usize {
+		self.keys.len()
+	}
+
+	/// true if the ring has no members
+	pub fn is_empty(&self) -> bool {
+		self.keys.is_empty()
+	}
+}
+
+/// a linkable signature by an unidentified member of a ring of keys
+pub struct RingSignature<E: EngineBLS> {
+	/// x * H(pk) for the signer's keypair (x, pk)
+	pub key_image: E::PublicKeyGroup,
+	/// the first challenge of the ring
+	pub challenge: E::Scalar,
+	/// one response per member of the ring
+	pub responses: Vec<E::Scalar>,
+}
+
+impl<E: EngineBLS> Clone for RingSignature<E> {
+	fn clone(&self) -> Self {
+		Self {
+			key_image: self.key_image,
+			challenge: self.challenge,
+			responses: self.responses.clone(),
+		}
+	}
+}
+
+impl<E: EngineBLS> RingSignature<E> {
+	/// sign `message` as an unidentified member of `ring`
+	/// fails with `InvalidCommittee` if the signer's key is not in the ring
+	///
+	/// * `context`: The signing context
+	/// * `message`: The message to sign
+	/// * `ring`: The members who may have signed
+	/// * `keypair`: The signer's keypair
+	/// * `rng`: A CSPRNG
+	#[cfg(feature = "participant")]
+	pub fn sign<R: SecureRng>(
+		context: &[u8],
+		message: &[u8],
+		ring: &Ring<E>,
+		keypair: &KeypairVT<E>,
+		mut rng: R,
+	) -> Result<Self, AcssError> {
+		let signer = ring
+			.keys
+			.iter()
+			.position(|pk| pk.0 == keypair.public.0)
+			.ok_or(AcssError::InvalidCommittee)?;
+		let n = ring.len();
+		let g = E::PublicKeyGroup::generator();
+		let bases = &ring.bases;
+		let mut x = keypair.secret.0;
+		let key_image = bases[signer] * x;
+
+		let mut alpha = E::Scalar::rand(&mut rng);
+		let mut challenges = vec![E::Scalar::zero(); n];
+		let mut responses: Vec<E::Scalar> =
+			(0..n).map(|_| E::Scalar::rand(&mut rng)).collect();
+		challenges[(signer + 1) % n] = challenge::<E>(
+			context,
+			message,
+			ring,
+			&key_image,
+			&(g * alpha),
+			&(bases[signer] * alpha),
+		)?;
+		for step in 1..n {
+			let i = (signer + step) % n;
+			let (l, r) = commitments::<E>(
+				&ring.keys[i],
+				&bases[i],
+				&key_image,
+				challenges[i],
+				responses[i],
+			);
+			challenges[(i + 1) % n] =
+				challenge::<E>(context, message, ring, &key_image, &l, &r)?;
+		}
+		responses[signer] = alpha - challenges[signer] * x;
+		wipe(&mut alpha);
+		wipe(&mut x);
+
+		Ok(Self { key_image, challenge: challenges[0], responses })
+	}
+
+	/// true if the signature over `message` was made by a member of `ring`
+	///
+	/// * `context`: The signing context
+	/// * `message`: The signed message
+	/// * `ring`: The members who may have signed
+	pub fn verify(
+		&self,
+		context: &[u8],
+		message: &[u8],
+		ring: &Ring<E>,
+	) -> bool {
+		if ring.is_empty() ||
+			self.responses.len() != ring.len() ||
+			self.key_image.is_zero()
+		{
+			return false;
+		}
+
+		let mut c = self.challenge;
+		for ((pk, base), response) in
+			ring.keys.iter().zip(ring.bases.iter()).zip(self.responses.iter())
+		{
+			let (l, r) =
+				commitments::<E>(pk, base, &self.key_image, c, *response);
+			let Ok(next) =
+				challenge::<E>(context, message, ring, &self.key_image, &l, &r)
+			else {
+				return false;
+			};
+			c = next;
+		}
+		c == self.challenge
+	}
+
+	/// true if both signatures were made with the same key
+	pub fn links(&self, other: &Self) -> bool {
+		self.key_image == other.key_image
+	}
+
+	/// encode the signature
+	/// fails with `InvalidTranscript` if an element cannot be encoded
+	pub fn encode(&self) -> Result<Vec<u8>, AcssError> {
+		let mut out = Vec::new();
+		write(&self.key_image, &mut out)?;
+		write(&self.challenge, &mut out)?;
+		for response in &self.responses {
+			write(response, &mut out)?;
+		}
+		Ok(out)
+	}
+
+	/// decode the signature of a ring of `ring_len` members
+	/// fails with `InvalidTranscript` if the bytes are malformed, have
+	/// trailing data or the key image is the identity
+	///
+	/// * `bytes`: The encoded signature
+	/// * `ring_len`: The number of members in the ring
+	pub fn decode(
+		mut bytes: &[u8],
+		ring_len: usize,
+	) -> Result<Self, AcssError> {
+		let reader = &mut bytes;
+		let key_image = read_point(reader)?;
+		let challenge = read(reader)?;
+		let responses =
+			(0..ring_len).map(|_| read(reader)).collect::<Result<_, _>>()?;
+		if !reader.is_empty() {
+			return Err(AcssError::InvalidTranscript);
+		}
+		Ok(Self { key_image, challenge, responses })
+	}
+}
+
+/// the commitments s * G + c * pk and s * H(pk) + c * I of a ring member
+fn commitments<E: EngineBLS>(
+	pk: &PublicKey<E>,
+	base: &E::PublicKeyGroup,
+	key_image: &E::PublicKeyGroup,
+	c: E::Scalar,
+	s: E::Scalar,
+) -> (E::PublicKeyGroup, E::PublicKeyGroup) {
+	(E::PublicKeyGroup::generator() * s + pk.0 * c, *base * s + *key_image * c)
+}
+
+/// the challenge H(label || context || message || ring || I || L || R)
+fn challenge<E: EngineBLS>(
+	context: &[u8],
+	message: &[u8],
+	ring: &Ring<E>,
+	key_image: &E::PublicKeyGroup,
+	l: &E::PublicKeyGroup,
+	r: &E::PublicKeyGroup,
+) -> Result<E::Scalar, AcssError> {
+	let mut h = Shake256::default();
+	h.update(CHALLENGE_LABEL);
+	for bytes in [context, message] {
+		h.update(&(bytes.len() as u64).to_be_bytes());
+		h.update(bytes);
+	}
+	ring.keys
+		.iter()
+		.map(|pk| &pk.0)
+		.chain([key_image, l, r])
+		.try_for_each(|point| point.serialize_compressed(HashWriter(&mut h)))
+		.map_err(|_| AcssError::InvalidTranscript)?;
+	let mut o = [0u8; 64];
+	h.finalize_xof().read(&mut o);
+	Ok(E::Scalar::from_be_bytes_mod_order(&o))
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	const CONTEXT: &[u8] = b"test";
+
+	fn ring(rng: &mut ChaCha20Rng, n: usize) -> Vec<KeypairVT<E>> {
+		(0..n).map(|_| KeypairVT::<E>::generate(&mut *rng)).collect()
+	}
+
+	#[test]
+	fn any_member_signs_for_the_ring() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		for n in [1, 3] {
+			let members = ring(&mut rng, n);
+			let keys: Vec<PublicKey<E>> =
+				members.iter().map(|kp| kp.public).collect();
+			let full = Ring::new(&keys).unwrap();
+			let partial = Ring::new(&keys[1..]).unwrap();
+			for member in &members {
+				let signature =
+					RingSignature::sign(CONTEXT, b"m", &full, member, &mut rng)
+						.unwrap();
+				assert!(signature.verify(CONTEXT, b"m", &full));
+				assert!(!signature.verify(CONTEXT, b"m'", &full));
+				assert!(!signature.verify(b"other", b"m", &full));
+				assert!(!signature.verify(CONTEXT, b"m", &partial));
+			}
+		}
+
+		let members = ring(&mut rng, 2);
+		let keys = Ring::new(&[members[0].public]).unwrap();
+		assert_eq!(
+			RingSignature::sign(CONTEXT, b"m", &keys, &members[1], &mut rng)
+				.err(),
+			Some(AcssError::InvalidCommittee)
+		);
+	}
+
+	#[test]
+	fn signatures_by_one_member_link() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = ring(&mut rng, 3);
+		let keys =
+			Ring::new(&members.iter().map(|kp| kp.public).collect::<Vec<_>>())
+				.unwrap();
+		let sign = |member: &KeypairVT<E>,
+		            message: &[u8],
+		            rng: &mut ChaCha20Rng| {
+			RingSignature::sign(CONTEXT, message, &keys, member, rng).unwrap()
+		};
+
+		let a = sign(&members[1], b"a", &mut rng);
+		let b = sign(&members[1], b"b", &mut rng);
+		let c = sign(&members[2], b"a", &mut rng);
+		assert!(a.links(&b));
+		assert!(!a.links(&c));
+
+		// a signature cannot be given another member's key image
+		let mut forged = c.clone();
+		forged.key_image = a.key_image;
+		assert!(!forged.verify(CONTEXT, b"a", &keys));
+	}
+
+	#[test]
+	fn signatures_round_trip_through_bytes() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = ring(&mut rng, 3);
+		let keys =
+			Ring::new(&members.iter().map(|kp| kp.public).collect::<Vec<_>>())
+				.unwrap();
+		let signature =
+			RingSignature::sign(CONTEXT, b"m", &keys, &members[0], &mut rng)
+				.unwrap();
+
+		let bytes = signature.encode().unwrap();
+		let decoded = RingSignature::<E>::decode(&bytes, 3).unwrap();
+		assert!(decoded.verify(CONTEXT, b"m", &keys));
+		assert!(decoded.links(&signature));
+		assert_eq!(
+			RingSignature::<E>::decode(&bytes, 2).err(),
+			Some(AcssError::InvalidTranscript)
+		);
+		assert_eq!(
+			RingSignature::<E>::decode(&bytes, 4).err(),
+			Some(AcssError::InvalidTranscript)
+		);
+	}
+}
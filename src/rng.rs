@@ -0,0 +1,119 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Randomness
+//!
+//! Every API that consumes randomness is bounded on [`SecureRng`], so a
+//! non-cryptographic rng (e.g. a small fast rng or a test rng) can't be passed
+//! to `reshare`, `encrypt`, or `prove` by accident. With the `getrandom`
+//! feature (implied by `std`), the `*_with_os_rng` conveniences draw from the
+//! operating system's CSPRNG. On wasm32-unknown-unknown, enable `wasm` to
+//! draw from the browser's CSPRNG instead.
+//!
+//! Multi-node tests and simulations draw every rng from one
+//! [`SimulationSeed`], forking a seed per node and per purpose (dealing,
+//! proving, sampling faults, ...), so that a whole run, including its
+//! complaint and justification branches, is reproduced from a single seed.
+
+use ark_std::rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+
+/// a cryptographically secure rng
+pub trait SecureRng: RngCore + CryptoRng {}
+
+impl<R: RngCore + CryptoRng + ?Sized> SecureRng for R {}
+
+/// the operating system's CSPRNG
+#[cfg(feature = "getrandom")]
+pub use rand_core::OsRng;
+
+/// the domain separation label of forked seeds
+const FORK_LABEL: &[u8] = b"acss/simulation-seed";
+
+/// a seed from which the rngs of a whole test or simulation are forked
+///
+/// anyone who learns the seed learns every secret drawn from its rngs, so it
+/// is for tests and simulations only
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SimulationSeed([u8; 32]);
+
+impl SimulationSeed {
+	/// the root seed of a run
+	pub fn new(seed: [u8; 32]) -> Self {
+		Self(seed)
+	}
+
+	/// the root seed of a run, from a short seed (e.g. a test case number)
+	pub fn from_u64(seed: u64) -> Self {
+		Self::new([0u8; 32]).fork(&seed.to_le_bytes())
+	}
+
+	/// an independent seed for `label`
+	/// forks with different labels yield unrelated rngs, and forking is
+	/// deterministic, so the same path of labels always yields the same rng
+	///
+	/// * `label`: The purpose of the fork, e.g. `b"deal"`
+	pub fn fork(&self, label: &[u8]) -> Self {
+		let mut h = Shake256::default();
+		h.update(FORK_LABEL);
+		h.update(&self.0);
+		h.update(&(label.len() as u64).to_le_bytes());
+		h.update(label);
+		let mut seed = [0u8; 32];
+		h.finalize_xof().read(&mut seed);
+		Self(seed)
+	}
+
+	/// an independent seed for the node at `position`
+	///
+	/// * `position`: The (0-based) position of the node in the simulation
+	pub fn node(&self, position: usize) -> Self {
+		self.fork(b"node").fork(&(position as u64).to_le_bytes())
+	}
+
+	/// the rng of this seed
+	pub fn rng(&self) -> ChaCha20Rng {
+		ChaCha20Rng::from_seed(self.0)
+	}
+}
+
+/// a CSPRNG with a fixed seed, for tests, so that every run draws the same
+/// values and a failure can be reproduced
+#[cfg(all(test, feature = "participant"))]
+pub(crate) fn test_rng() -> ChaCha20Rng {
+	ChaCha20Rng::seed_from_u64(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn forks_are_reproducible_and_independent() {
+		let seed = SimulationSeed::from_u64(7);
+		assert!(seed == SimulationSeed::from_u64(7));
+		assert!(seed != SimulationSeed::from_u64(8));
+
+		let draw = |seed: SimulationSeed| seed.rng().next_u64();
+		assert_eq!(draw(seed.fork(b"deal")), draw(seed.fork(b"deal")));
+		assert_ne!(draw(seed.fork(b"deal")), draw(seed.fork(b"prove")));
+		assert_ne!(draw(seed.node(0)), draw(seed.node(1)));
+		assert_ne!(draw(seed.node(0)), draw(seed));
+	}
+}
@@ -0,0 +1,228 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Roles
+//!
+//! [`Dealer`] and [`Participant`] name the operations each role performs in a
+//! resharing, with default implementations over the primitives in this crate.
+//! Node software can program against the traits and substitute mocks in its
+//! own tests.
+//!
+//! A participant that recovered its share becomes a dealer of the next epoch
+//! with [`Participant::refresh`].
+
+use crate::{
+	acss::{DoubleSecret, HighThresholdACSS, Keypair},
+	error::AcssError,
+	evidence::MisbehaviorEvidence,
+	keys::{CommitteeKey, PublicKey as EncryptionKey, SecretKey, ShareIndex},
+	params::ThresholdParams,
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+	resharing::Resharing,
+	rng::SecureRng,
+	transcript::SignedDealing,
+};
+use w3f_bls::{EngineBLS, KeypairVT, PublicKey};
+
+/// deals a secret to a committee
+pub trait Dealer<E: EngineBLS> {
+	/// the secret being dealt
+	fn secret(&self) -> &DoubleSecret<E>;
+
+	/// deal the secret to `committee`
+	///
+	/// * `committee`: The public keys of the recipients
+	/// * `params`: The threshold parameters of the resharing
+	/// * `rng`: A CSPRNG
+	fn deal<R: SecureRng, K: CommitteeKey<E>>(
+		&self,
+		committee: &[K],
+		params: ThresholdParams,
+		rng: R,
+	) -> Result<Resharing<E>, AcssError> {
+		self.secret().reshare(committee, params, rng)
+	}
+}
+
+impl<E: EngineBLS> Dealer<E> for DoubleSecret<E> {
+	fn secret(&self) -> &DoubleSecret<E> {
+		self
+	}
+}
+
+/// receives, checks and recovers shares as a member of a committee
+pub trait Participant<E: EngineBLS> {
+	/// the keypair the participant's shares are encrypted to
+	fn keypair(&self) -> &KeypairVT<E>;
+
+	/// true if `pok` proves a valid share dealt to this participant
+	fn verify(&self, pok: &BatchPoK<E::PublicKeyGroup>) -> bool {
+		pok.verify(EncryptionKey(self.keypair().public.0))
+	}
+
+	/// accuse `dealer` of dealing an invalid share at `index` in `dealing`
+	/// returns none if the dealing is not signed by the dealer or the share
+	/// is valid, since the evidence would not verify
+	///
+	/// * `dealer`: The public key of the accused dealer
+	/// * `dealing`: The dealing, signed by the dealer
+	/// * `index`: The index of this participant's share in the dealing
+	fn complain(
+		&self,
+		dealer: &PublicKey<E>,
+		dealing: SignedDealing<E>,
+		index: ShareIndex,
+	) -> Option<MisbehaviorEvidence<E>> {
+		let evidence = MisbehaviorEvidence::InvalidShare {
+			dealer: *dealer,
+			dealing,
+			index,
+			recipient: self.keypair().public,
+		};
+		evidence.verify().then_some(evidence)
+	}
+
+	/// recover this participant's share from the PoKs dealt to it
+	///
+	/// * `poks`: The batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers
+	fn recover(
+		&self,
+		poks: &[BatchPoK<E::PublicKeyGroup>],
+		params: ThresholdParams,
+	) -> Result<DoubleSecret<E>, AcssError> {
+		let secret = SecretKey::from(&self.keypair().secret);
+		HighThresholdACSS::<E>::recover(&secret, poks, params)
+	}
+
+	/// recover this participant's share and deal it to the next committee
+	///
+	/// * `poks`: The batched PoKs, the i-th from dealer i + 1
+	/// * `params`: The threshold parameters of the dealers
+	/// * `committee`: The public keys of the next committee
+	/// * `next`: The threshold parameters of the next committee
+	/// * `rng`: A CSPRNG
+	fn refresh<R: SecureRng, K: CommitteeKey<E>>(
+		&self,
+		poks: &[BatchPoK<E::PublicKeyGroup>],
+		params: ThresholdParams,
+		committee: &[K],
+		next: ThresholdParams,
+		rng: R,
+	) -> Result<Resharing<E>, AcssError> {
+		self.recover(poks, params)?.deal(committee, next, rng)
+	}
+}
+
+impl<E: EngineBLS> Participant<E> for Keypair<E> {
+	fn keypair(&self) -> &KeypairVT<E> {
+		&self.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::messages::MessageHeader;
+	use ark_ec::Group;
+	use ark_ff::{One, UniformRand};
+	use ark_std::{rand::SeedableRng, vec::Vec};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	fn committee(rng: &mut ChaCha20Rng, n: usize) -> Vec<Keypair<E>> {
+		(0..n).map(|_| Keypair(KeypairVT::<E>::generate(&mut *rng))).collect()
+	}
+
+	fn public_keys(members: &[Keypair<E>]) -> Vec<PublicKey<E>> {
+		members.iter().map(|m| m.keypair().public).collect()
+	}
+
+	#[test]
+	fn participants_recover_and_refresh_a_dealing() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = committee(&mut rng, 3);
+		let next = committee(&mut rng, 2);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let resharing =
+			secret.deal(&public_keys(&members), params, &mut rng).unwrap();
+		let single_dealer = ThresholdParams::new(1, 1).unwrap();
+		let next_params = ThresholdParams::new(2, 2).unwrap();
+		for member in &members {
+			let pok =
+				resharing.for_recipient(&member.keypair().public).unwrap();
+			assert!(Participant::verify(member, pok));
+
+			let refreshed = member
+				.refresh(
+					core::slice::from_ref(pok),
+					single_dealer,
+					&public_keys(&next),
+					next_params,
+					&mut rng,
+				)
+				.unwrap();
+			for (recipient, pok) in next.iter().zip(refreshed.poks()) {
+				assert!(Participant::verify(recipient, pok));
+			}
+		}
+	}
+
+	#[test]
+	fn participants_only_complain_about_invalid_shares() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let members = committee(&mut rng, 2);
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let mut dealings = secret
+			.deal(
+				&public_keys(&members),
+				ThresholdParams::new(2, 2).unwrap(),
+				&mut rng,
+			)
+			.unwrap()
+			.into_dealings();
+		// the proof for the second member is made for another key
+		dealings[1].1 = BatchPoK::prove(
+			&[<E as EngineBLS>::Scalar::one(); 2],
+			EncryptionKey(<E as EngineBLS>::PublicKeyGroup::generator()),
+			&mut rng,
+		)
+		.unwrap();
+
+		let complain = |position: usize| {
+			let dealing = SignedDealing::sign(
+				MessageHeader::default(),
+				&dealings,
+				&dealer,
+			)
+			.unwrap();
+			let index = ShareIndex::from_position(position).unwrap();
+			members[position].complain(&dealer.public, dealing, index)
+		};
+		assert!(complain(0).is_none());
+		assert!(complain(1).is_some_and(|evidence| evidence.verify()));
+	}
+}
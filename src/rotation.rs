@@ -0,0 +1,356 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Key Rotation
+//!
+//! A member replaces the keypair its share is encrypted to without a new
+//! resharing. It encrypts its share to the new key with a fresh PoK and
+//! proves that the fresh PoK commits to the same share as the one it was
+//! dealt: the PoK dealt to key pk commits to w(G + pk), where w is the sum of
+//! the share and blinding share, so a DLEQ proof that
+//! log_{G + old}(C_old) = log_{G + new}(C_new) links the two.
+//!
+//! The old key signs the rotation, authorizing it, and the new key signs it
+//! too, proving possession. The signed message binds the Merkle root of the
+//! resharing being rotated, so successive rotations of the same share form a
+//! chain, each signed by the key the previous one rotated to.
+
+#[cfg(feature = "participant")]
+use crate::{
+	acss::{DoubleSecret, Keypair},
+	rng::SecureRng,
+	wipe,
+};
+use crate::{
+	error::AcssError,
+	keys::{PublicKey as EncryptionKey, ShareIndex},
+	merkle::merkle_root,
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+	resharing::Resharing,
+	threshold::DleqProof,
+	transcript::write,
+};
+use ark_ec::{CurveGroup, Group};
+use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+use w3f_bls::{EngineBLS, Message, PublicKey, Signature};
+
+/// the signing context of key rotations
+pub const ROTATION_CONTEXT: &[u8] = b"acss/rotation";
+/// the domain separation label of continuity proofs
+pub const CONTINUITY_LABEL: &[u8] = b"acss/rotation/continuity";
+
+/// a member's move of its share to a new key
+pub struct KeyRotation<E: EngineBLS> {
+	/// the index of the share
+	pub index: ShareIndex,
+	/// the new key
+	pub key: PublicKey<E>,
+	/// the share, encrypted to the new key
+	pub pok: BatchPoK<E::PublicKeyGroup>,
+	/// the proof that `pok` commits to the share of the PoK it replaces
+	pub continuity: DleqProof<E::PublicKeyGroup>,
+	/// the signature of the old key
+	pub authorization: Signature<E>,
+	/// the signature of the new key
+	pub possession: Signature<E>,
+}
+
+impl<E: EngineBLS> Clone for KeyRotation<E> {
+	fn clone(&self) -> Self {
+		Self {
+			index: self.index,
+			key: PublicKey(self.key.0),
+			pok: self.pok.clone(),
+			continuity: self.continuity.clone(),
+			authorization: Signature(self.authorization.0),
+			possession: Signature(self.possession.0),
+		}
+	}
+}
+
+/// move the share at `index` of `resharing` from `old` to `new`
+/// fails with `ShareIndexOutOfRange` if there is no share at `index`, with
+/// `InvalidCommittee` if it was not dealt to `old`, and with
+/// `InvalidCommitment` if `share` is not the share dealt to `old`
+///
+/// * `old`: The member's current keypair
+/// * `new`: The member's new keypair
+/// * `resharing`: The resharing the share was dealt in, or last rotated in
+/// * `index`: The index of the share
+/// * `share`: The share
+/// * `rng`: A CSPRNG
+#[cfg(feature = "participant")]
+pub fn rotate<E: EngineBLS, R: SecureRng>(
+	old: &Keypair<E>,
+	new: &Keypair<E>,
+	resharing: &Resharing<E>,
+	index: ShareIndex,
+	share: &DoubleSecret<E>,
+	mut rng: R,
+) -> Result<KeyRotation<E>, AcssError> {
+	let recipient = resharing
+		.recipients()
+		.get(index.position())
+		.ok_or(AcssError::ShareIndexOutOfRange)?;
+	if recipient.0 != old.public().0 {
+		return Err(AcssError::InvalidCommittee);
+	}
+	let dealt =
+		resharing.by_index(index).ok_or(AcssError::ShareIndexOutOfRange)?;
+
+	let mut messages =
+		[*share.expose_secret(), *share.expose_blinding_secret()];
+	let mut w = messages[0] + messages[1];
+	let pok =
+		BatchPoK::prove(&messages, EncryptionKey(new.public().0), &mut rng);
+	wipe(&mut messages);
+	let pok = match pok {
+		Ok(pok) => pok,
+		Err(e) => {
+			wipe(&mut w);
+			return Err(AcssError::Proof(e));
+		},
+	};
+	let statement = statement::<E>(old.public(), dealt, new.public(), &pok);
+	if statement[0] * w != statement[1] {
+		wipe(&mut w);
+		return Err(AcssError::InvalidCommitment);
+	}
+	let continuity =
+		DleqProof::prove_with_bases(CONTINUITY_LABEL, &w, statement, rng);
+	wipe(&mut w);
+
+	let message = message(&merkle_root(resharing)?, index, new.public(), &pok)?;
+	Ok(KeyRotation {
+		index,
+		key: *new.public(),
+		pok,
+		continuity: continuity?,
+		authorization: old.0.sign(&message),
+		possession: new.0.sign(&message),
+	})
+}
+
+impl<E: EngineBLS> KeyRotation<E> {
+	/// true if the rotation is signed by the recipient of the share in
+	/// `resharing` and by the new key, and its PoK is of the same share
+	///
+	/// * `resharing`: The resharing the share was dealt in, or last rotated in
+	pub fn verify(&self, resharing: &Resharing<E>) -> bool {
+		let (Some(old), Some(dealt)) = (
+			resharing.recipients().get(self.index.position()),
+			resharing.by_index(self.index),
+		) else {
+			return false;
+		};
+		let Ok(message) = merkle_root(resharing)
+			.and_then(|root| message(&root, self.index, &self.key, &self.pok))
+		else {
+			return false;
+		};
+		let statement = statement::<E>(old, dealt, &self.key, &self.pok);
+		self.authorization.verify(&message, old) &&
+			self.possession.verify(&message, &self.key) &&
+			self.pok.verify(EncryptionKey(self.key.0)) &&
+			self.continuity.verify_with_bases(CONTINUITY_LABEL, statement)
+	}
+
+	/// hand the share to the new key in `resharing`
+	/// fails with `InvalidProof` if the rotation does not verify, leaving
+	/// `resharing` unchanged
+	///
+	/// * `resharing`: The resharing the share was dealt in, or last rotated in
+	pub fn apply(&self, resharing: &mut Resharing<E>) -> Result<(), AcssError> {
+		if !self.verify(resharing) {
+			return Err(AcssError::InvalidProof);
+		}
+		resharing.replace_recipient(
+			self.index,
+			PublicKey(self.key.0),
+			self.pok.clone(),
+		);
+		Ok(())
+	}
+}
+
+/// the continuity statement [G + old, C_old, G + new, C_new]
+fn statement<E: EngineBLS>(
+	old: &PublicKey<E>,
+	dealt: &BatchPoK<E::PublicKeyGroup>,
+	new: &PublicKey<E>,
+	pok: &BatchPoK<E::PublicKeyGroup>,
+) -> [E::PublicKeyGroup; 4] {
+	let g = E::PublicKeyGroup::generator();
+	[g + old.0, dealt.commitment, g + new.0, pok.commitment]
+}
+
+/// the message both keys sign
+/// fails with `InvalidTranscript` if a point cannot be encoded
+fn message<E: EngineBLS>(
+	root: &[u8; 32],
+	index: ShareIndex,
+	key: &PublicKey<E>,
+	pok: &BatchPoK<E::PublicKeyGroup>,
+) -> Result<Message, AcssError> {
+	let mut bytes = Vec::from(&root[..]);
+	bytes.push(index.get());
+	for point in [key.0, pok.commitment] {
+		write(&point.into_affine(), &mut bytes)?;
+	}
+	Ok(Message::new(ROTATION_CONTEXT, &Sha256::digest(&bytes)))
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::params::ThresholdParams;
+	use ark_ff::UniformRand;
+	use ark_std::{rand::SeedableRng, slice};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	fn fixture(
+		rng: &mut ChaCha20Rng,
+	) -> (Vec<Keypair<E>>, Resharing<E>, Vec<DoubleSecret<E>>) {
+		let members: Vec<Keypair<E>> =
+			(0..3).map(|_| Keypair(KeypairVT::generate(&mut *rng))).collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|m| *m.public()).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(rng),
+			<E as EngineBLS>::Scalar::rand(rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 2).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut *rng).unwrap();
+		let shares = members
+			.iter()
+			.zip(resharing.poks())
+			.map(|(m, pok)| {
+				m.recover(
+					slice::from_ref(pok),
+					ThresholdParams::new(1, 1).unwrap(),
+				)
+				.unwrap()
+			})
+			.collect();
+		(members, resharing, shares)
+	}
+
+	#[test]
+	fn rotations_chain_and_keep_the_share() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (members, mut resharing, shares) = fixture(&mut rng);
+		let index = ShareIndex::new(2).unwrap();
+		let share_pk = resharing.share_public_key(index).unwrap().1;
+
+		let mut current = &members[1];
+		let keys: Vec<Keypair<E>> =
+			(0..2).map(|_| Keypair(KeypairVT::generate(&mut rng))).collect();
+		for next in &keys {
+			let rotation =
+				rotate(current, next, &resharing, index, &shares[1], &mut rng)
+					.unwrap();
+			assert!(rotation.verify(&resharing));
+			rotation.apply(&mut resharing).unwrap();
+			// a rotation cannot be replayed against the rotated resharing
+			assert!(!rotation.verify(&resharing));
+			current = next;
+		}
+
+		assert_eq!(resharing.recipients()[1].0, keys[1].public().0);
+		assert_eq!(resharing.share_public_key(index).unwrap().1, share_pk);
+		let pok = resharing.by_index(index).unwrap();
+		let share = keys[1]
+			.recover(slice::from_ref(pok), ThresholdParams::new(1, 1).unwrap())
+			.unwrap();
+		assert_eq!(share.expose_secret(), shares[1].expose_secret());
+	}
+
+	#[test]
+	fn rotations_require_the_dealt_key_and_share() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (members, resharing, shares) = fixture(&mut rng);
+		let new = Keypair::<E>(KeypairVT::generate(&mut rng));
+		let index = ShareIndex::new(1).unwrap();
+
+		assert_eq!(
+			rotate(&members[1], &new, &resharing, index, &shares[0], &mut rng)
+				.err(),
+			Some(AcssError::InvalidCommittee)
+		);
+		assert_eq!(
+			rotate(&members[0], &new, &resharing, index, &shares[1], &mut rng)
+				.err(),
+			Some(AcssError::InvalidCommitment)
+		);
+		let outside = ShareIndex::new(4).unwrap();
+		assert_eq!(
+			rotate(
+				&members[0],
+				&new,
+				&resharing,
+				outside,
+				&shares[0],
+				&mut rng
+			)
+			.err(),
+			Some(AcssError::ShareIndexOutOfRange)
+		);
+	}
+
+	#[test]
+	fn forged_rotations_are_rejected() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (members, mut resharing, shares) = fixture(&mut rng);
+		let new = Keypair::<E>(KeypairVT::generate(&mut rng));
+		let index = ShareIndex::new(1).unwrap();
+		let rotation =
+			rotate(&members[0], &new, &resharing, index, &shares[0], &mut rng)
+				.unwrap();
+
+		// signed by the wrong old key
+		let thief = Keypair::<E>(KeypairVT::generate(&mut rng));
+		let mut forged = rotation.clone();
+		let message = message(
+			&merkle_root(&resharing).unwrap(),
+			index,
+			&forged.key,
+			&forged.pok,
+		)
+		.unwrap();
+		forged.authorization = thief.0.sign(&message);
+		assert!(!forged.verify(&resharing));
+
+		// a PoK of another share under the same signatures
+		let messages = [
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		];
+		let key = EncryptionKey(new.public().0);
+		let other = BatchPoK::prove(&messages, key, &mut rng).unwrap();
+		let mut forged = rotation.clone();
+		forged.pok = other;
+		assert!(!forged.verify(&resharing));
+
+		// moved to another index
+		let mut forged = rotation.clone();
+		forged.index = ShareIndex::new(2).unwrap();
+		assert_eq!(forged.apply(&mut resharing), Err(AcssError::InvalidProof));
+		assert_eq!(resharing.recipients()[1].0, members[1].public().0);
+	}
+}
@@ -0,0 +1,199 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Self Test
+//!
+//! [`self_test`] runs known-answer tests of the primitives a member relies
+//! on, for deployments that must check the library before it handles real
+//! keys (e.g. a FIPS 140 style power-on self test). It checks
+//!
+//! - SHA-256, SHAKE128 and SHAKE256 against their published test vectors
+//! - the hashed El Gamal key derivation, on fixed points
+//! - hashed El Gamal encryption, encrypting a fixed message to a fixed key with
+//!   a seeded RNG, and decrypting it again
+//! - a 3-of-4 resharing of a fixed secret, every member recovering its share
+//!   and every 3 of the shares interpolating to the secret
+//!
+//! on TinyBLS377, and fails with `SelfTestFailed` if any of them does not
+//! give the expected answer. The resharing dominates its cost, which is that
+//! of dealing to and recovering for a committee of 4.
+
+use crate::{
+	acss::{DoubleSecret, Keypair},
+	error::AcssError,
+	keys::{PublicKey as EncryptionKey, SecretKey},
+	lagrange::interpolate,
+	params::ThresholdParams,
+	proofs::hashed_el_gamal::{kdf, HashedElGamal},
+	trace::trace_event,
+};
+use ark_ec::Group;
+use ark_serialize::CanonicalSerialize;
+use ark_std::vec::Vec;
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use sha2::{Digest, Sha256};
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake128, Shake256,
+};
+use w3f_bls::{EngineBLS, KeypairVT, TinyBLS377};
+
+type E = TinyBLS377;
+type G = <E as EngineBLS>::PublicKeyGroup;
+type Scalar = <E as EngineBLS>::Scalar;
+
+/// the input of the hash test vectors
+const HASH_INPUT: &[u8] = b"abc";
+/// SHA-256("abc")
+const SHA256_ABC: [u8; 32] = [
+	0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde,
+	0x5d, 0xae, 0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c,
+	0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+];
+/// the first 32 bytes of SHAKE128("abc")
+const SHAKE128_ABC: [u8; 32] = [
+	0x58, 0x81, 0x09, 0x2d, 0xd8, 0x18, 0xbf, 0x5c, 0xf8, 0xa3, 0xdd, 0xb7,
+	0x93, 0xfb, 0xcb, 0xa7, 0x40, 0x97, 0xd5, 0xc5, 0x26, 0xa6, 0xd3, 0x5f,
+	0x97, 0xb8, 0x33, 0x51, 0x94, 0x0f, 0x2c, 0xc8,
+];
+/// the first 32 bytes of SHAKE256("abc")
+const SHAKE256_ABC: [u8; 32] = [
+	0x48, 0x33, 0x66, 0x60, 0x13, 0x60, 0xa8, 0x77, 0x1c, 0x68, 0x63, 0x08,
+	0x0c, 0xc4, 0x11, 0x4d, 0x8d, 0xb4, 0x45, 0x30, 0xf8, 0xf1, 0xe1, 0xee,
+	0x4f, 0x94, 0xea, 0x37, 0xe7, 0x8b, 0x57, 0x39,
+];
+/// the pad derived from shared = 2G, c1 = 3G and pk = 5G
+const KDF_PAD: [u8; 32] = [
+	0x8e, 0x6d, 0xde, 0xc9, 0xb9, 0xed, 0x5c, 0x4b, 0x80, 0xe9, 0x7b, 0x48,
+	0xca, 0x42, 0x2b, 0x9e, 0x4c, 0xef, 0xf6, 0x10, 0x9c, 0x20, 0xf0, 0x7c,
+	0xf6, 0x06, 0xff, 0x18, 0x07, 0x46, 0x6f, 0x43,
+];
+/// the SHA-256 digest of the compressed ciphertext of `[0x42; 32]` to 7G,
+/// with the RNG seeded with zeros
+const CIPHERTEXT_DIGEST: [u8; 32] = [
+	0x67, 0xc1, 0x82, 0x48, 0x5c, 0xe6, 0xbc, 0x5f, 0xed, 0x90, 0x4e, 0xa9,
+	0x6c, 0x35, 0x32, 0x19, 0x17, 0x5c, 0x50, 0xe7, 0x5e, 0xde, 0x4d, 0xeb,
+	0xf7, 0x8a, 0xc0, 0x3a, 0x5d, 0xa8, 0xe1, 0xc8,
+];
+
+/// run the known-answer tests
+/// fails with `SelfTestFailed` if any of them does not give the expected
+/// answer
+pub fn self_test() -> Result<(), AcssError> {
+	check("hash", hashes())?;
+	check("kdf", key_derivation())?;
+	check("encryption", encryption())?;
+	check("reshare", reshare_and_recover())
+}
+
+/// map a failed test to `SelfTestFailed`, naming it in a trace event
+fn check(_name: &str, passed: bool) -> Result<(), AcssError> {
+	if passed {
+		return Ok(());
+	}
+	trace_event!(test = _name, "self test failed");
+	Err(AcssError::SelfTestFailed)
+}
+
+fn hashes() -> bool {
+	let sha256: [u8; 32] = Sha256::digest(HASH_INPUT).into();
+	let mut shake128 = [0u8; 32];
+	let mut h = Shake128::default();
+	Update::update(&mut h, HASH_INPUT);
+	h.finalize_xof().read(&mut shake128);
+	let mut shake256 = [0u8; 32];
+	let mut h = Shake256::default();
+	Update::update(&mut h, HASH_INPUT);
+	h.finalize_xof().read(&mut shake256);
+	sha256 == SHA256_ABC && shake128 == SHAKE128_ABC && shake256 == SHAKE256_ABC
+}
+
+fn key_derivation() -> bool {
+	let g = G::generator();
+	let point = |k: u64| g * Scalar::from(k);
+	kdf::<G, G, 32>(point(2), &point(3), &point(5))
+		.is_ok_and(|(pad, _)| pad == KDF_PAD)
+}
+
+fn encryption() -> bool {
+	let message = [0x42u8; 32];
+	let sk = SecretKey::<G>::new(Scalar::from(7u64));
+	let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+	let Ok(ciphertext) =
+		HashedElGamal::encrypt(message, sk.public_key(), &mut rng)
+	else {
+		return false;
+	};
+	let mut encoded = Vec::new();
+	if ciphertext.serialize_compressed(&mut encoded).is_err() {
+		return false;
+	}
+	Sha256::digest(&encoded)[..] == CIPHERTEXT_DIGEST &&
+		HashedElGamal::decrypt(&sk, &ciphertext) == Ok(message)
+}
+
+fn reshare_and_recover() -> bool {
+	let mut rng = ChaCha20Rng::from_seed([1u8; 32]);
+	let keys: Vec<KeypairVT<E>> =
+		(0..4).map(|_| KeypairVT::generate(&mut rng)).collect();
+	let committee: Vec<_> = keys.iter().map(|kp| kp.public).collect();
+	let secret = DoubleSecret::<E>(Scalar::from(11u64), Scalar::from(13u64));
+	let (Ok(params), Ok(single_dealer)) =
+		(ThresholdParams::new(4, 3), ThresholdParams::new(1, 1))
+	else {
+		return false;
+	};
+	let Ok(resharing) = secret.reshare(&committee, params, &mut rng) else {
+		return false;
+	};
+
+	let mut shares = Vec::with_capacity(keys.len());
+	for (i, kp) in keys.iter().enumerate() {
+		let Some(pok) = resharing.for_recipient(&kp.public) else {
+			return false;
+		};
+		if !pok.verify(EncryptionKey(kp.public.0)) {
+			return false;
+		}
+		let Ok(share) = Keypair(kp.clone())
+			.recover(core::slice::from_ref(pok), single_dealer)
+		else {
+			return false;
+		};
+		shares.push((Scalar::from(i as u64 + 1), share));
+	}
+	(0..shares.len()).all(|left_out| {
+		let (evals, blinding_evals): (Vec<_>, Vec<_>) = shares
+			.iter()
+			.enumerate()
+			.filter(|(i, _)| *i != left_out)
+			.map(|(_, (x, share))| ((*x, share.0), (*x, share.1)))
+			.unzip();
+		interpolate::<<E as EngineBLS>::SignatureGroup>(&evals) == secret.0 &&
+			interpolate::<<E as EngineBLS>::SignatureGroup>(&blinding_evals) ==
+				secret.1
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn self_test_passes() {
+		assert_eq!(self_test(), Ok(()));
+		assert_eq!(check("hash", false), Err(AcssError::SelfTestFailed));
+	}
+}
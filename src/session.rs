@@ -0,0 +1,1185 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Sessions
+//!
+//! [`AcssSession`] runs one resharing from the point of view of a single node:
+//! it owns the committee, the threshold parameters and the dealings received
+//! so far, and recovers the node's share as soon as enough valid dealings
+//! have arrived. It covers the common case of dealings exchanged as
+//! [`SignedDealing`]s; the primitives it is built from remain available for
+//! anything else.
+//!
+//! By default the committee reshares to itself. A session receiving from a
+//! different set of dealers, e.g. a single initial dealer, is configured with
+//! [`AcssSession::with_dealers`]. Metrics are collected by attaching an
+//! [`Observer`] with [`AcssSession::with_observer`].
+//!
+//! After verifying its share of a dealing, a member broadcasts a
+//! [`ReadyAck`] ([`AcssSession::ack`]). Sessions collect the acknowledgments
+//! they receive and emit an [`OutputCertificate`] once a quorum of the
+//! committee acknowledged the same dealing.
+//!
+//! A session configured with [`PhaseTimeouts`] keeps a deadline for each
+//! phase (waiting for dealings, waiting for acknowledgments, the complaint
+//! window). The session has no clock of its own: the caller reports the time
+//! with [`AcssSession::tick`], which emits each phase that passed its
+//! deadline once, and schedules its next call at
+//! [`AcssSession::next_deadline`].
+//!
+//! Dealings are signed for the [`MessageHeader`] of the session (the
+//! default one unless set with [`AcssSession::with_header`]), and dealings
+//! signed for another session, epoch or round are rejected.
+//!
+//! [`AcssSession::snapshot`] checkpoints a session, so that a node restarted
+//! in the middle of a long ceremony picks up where it stopped with
+//! [`AcssSession::restore`] instead of restarting the round. A snapshot only
+//! holds public data (the PoKs received are encrypted to the node), and the
+//! node's share is recovered again on restore.
+//!
+//! Snapshot layout: `version || committee || t || dealers || dealer t ||
+//! header || received || digests || acks || certificates || timeouts || clock
+//! || started || timed out`, where every list is prefixed by its length.
+
+use crate::{
+	acss::{DoubleSecret, Keypair},
+	certificate::{self, OutputCertificate, ReadyAck, ACK_SIGNATURE_CONTEXT},
+	error::AcssError,
+	evidence::MisbehaviorEvidence,
+	keys::{
+		committee_keys, CommitteeKey, PublicKey as EncryptionKey, ShareIndex,
+	},
+	messages::MessageHeader,
+	observer::{Observer, Phase},
+	params::ThresholdParams,
+	proofs::hashed_el_gamal_sigma::BatchPoK,
+	quorum::{QuorumCert, QuorumCollector, Vote},
+	rng::SecureRng,
+	transcript::{
+		decode_compact, read, read_array, read_point, write, SignedDealing,
+	},
+};
+use ark_std::{collections::BTreeMap, vec, vec::Vec};
+use w3f_bls::{EngineBLS, PublicKey, Signature};
+
+/// the output of a completed session
+pub struct SessionOutput<E: EngineBLS> {
+	/// the index of this node's share in the committee
+	pub index: ShareIndex,
+	/// this node's share
+	pub share: DoubleSecret<E>,
+	/// the indices of the dealers whose dealings the share was recovered from
+	pub dealers: Vec<ShareIndex>,
+}
+
+/// the version of the snapshot encoding
+pub const SNAPSHOT_VERSION: u8 = 2;
+
+/// how long each phase of a session may last, in the caller's unit of time
+/// (e.g. milliseconds), none for no deadline
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhaseTimeouts {
+	/// how long to wait for enough valid dealings, from the start
+	pub deal: Option<u64>,
+	/// how long to wait for enough dealings to be certified, from the
+	/// recovery of this node's share
+	pub ack: Option<u64>,
+	/// how long complaints are raised for, from the end of the deal phase
+	pub complaint: Option<u64>,
+}
+
+impl PhaseTimeouts {
+	/// the timeout of `phase`
+	pub fn get(&self, phase: Phase) -> Option<u64> {
+		match phase {
+			Phase::Deal => self.deal,
+			Phase::Ack => self.ack,
+			Phase::Complaint => self.complaint,
+		}
+	}
+}
+
+/// a resharing, as seen by one node, reporting its progress to `O`
+pub struct AcssSession<E: EngineBLS, O: Observer = ()> {
+	keypair: Keypair<E>,
+	committee: Vec<PublicKey<E>>,
+	params: ThresholdParams,
+	dealers: Vec<PublicKey<E>>,
+	dealer_params: ThresholdParams,
+	/// the session, epoch and round dealings are signed for
+	header: MessageHeader,
+	/// this node's index in the committee, none if it only deals
+	index: Option<ShareIndex>,
+	/// the PoK dealt to this node by each dealer heard from, none if it was
+	/// invalid
+	received: BTreeMap<ShareIndex, Option<BatchPoK<E::PublicKeyGroup>>>,
+	output: Option<SessionOutput<E>>,
+	/// the digest of each valid dealing, by dealer
+	digests: BTreeMap<ShareIndex, [u8; 32]>,
+	/// the acknowledgments received for each digest not yet certified
+	acks: QuorumCollector<E>,
+	certificates: Vec<OutputCertificate<E>>,
+	timeouts: PhaseTimeouts,
+	/// the latest time reported by the caller
+	clock: u64,
+	/// the start time of each phase started so far
+	started: BTreeMap<Phase, u64>,
+	/// the phases that passed their deadline, in the order they did
+	timed_out: Vec<Phase>,
+	observer: O,
+}
+
+impl<E: EngineBLS> AcssSession<E> {
+	/// start a session in which `committee` reshares to itself
+	/// fails with `InvalidParams` if `t` does not fit the committee
+	///
+	/// * `keypair`: This node's keypair
+	/// * `committee`: The public keys of the committee, in share order
+	/// * `t`: The number of shares required to recover
+	pub fn new<K: CommitteeKey<E>>(
+		keypair: Keypair<E>,
+		committee: &[K],
+		t: u8,
+	) -> Result<Self, AcssError> {
+		let committee = committee_keys(committee);
+		let params = ThresholdParams::for_committee(&committee, t)?;
+		let index = committee
+			.iter()
+			.position(|pk| pk.0 == keypair.0.public.0)
+			.and_then(ShareIndex::from_position);
+		Ok(Self {
+			keypair,
+			dealers: committee.clone(),
+			committee,
+			params,
+			dealer_params: params,
+			header: MessageHeader::default(),
+			index,
+			received: BTreeMap::new(),
+			output: None,
+			digests: BTreeMap::new(),
+			acks: QuorumCollector::new(
+				ACK_SIGNATURE_CONTEXT,
+				certificate::quorum(params.n()) as usize,
+			),
+			certificates: Vec::new(),
+			timeouts: PhaseTimeouts::default(),
+			clock: 0,
+			started: BTreeMap::new(),
+			timed_out: Vec::new(),
+			observer: (),
+		})
+	}
+}
+
+impl<E: EngineBLS> AcssSession<E> {
+	/// restore a session from a snapshot taken with `snapshot`, recovering
+	/// this node's share again if the session had
+	/// fails with `InvalidTranscript` if the snapshot cannot be decoded, with
+	/// `InvalidParams` if its thresholds do not fit its committees, with
+	/// `InvalidSignature` if an acknowledgment does not verify, and as
+	/// `Keypair::recover_indexed` does if the share cannot be recovered (e.g.
+	/// the keypair is not the one the snapshot was taken with)
+	///
+	/// * `keypair`: This node's keypair
+	/// * `snapshot`: The snapshot
+	pub fn restore(
+		keypair: Keypair<E>,
+		mut snapshot: &[u8],
+	) -> Result<Self, AcssError> {
+		let reader = &mut snapshot;
+		let [version] = read_array::<1>(reader)?;
+		if version != SNAPSHOT_VERSION {
+			return Err(AcssError::InvalidTranscript);
+		}
+		let committee = read_keys::<E>(reader)?;
+		let [t] = read_array::<1>(reader)?;
+		let dealers = read_keys::<E>(reader)?;
+		let [dealer_t] = read_array::<1>(reader)?;
+		let mut session = Self::new(keypair, &committee, t)?
+			.with_dealers(&dealers, dealer_t)?
+			.with_header(MessageHeader::read(reader)?);
+
+		for _ in 0..read_len(reader)? {
+			let from = read_index(reader)?;
+			let pok = match read_array::<1>(reader)? {
+				[0] => None,
+				[1] => Some(read(reader)?),
+				_ => return Err(AcssError::InvalidTranscript),
+			};
+			session.received.insert(from, pok);
+		}
+		for _ in 0..read_len(reader)? {
+			let from = read_index(reader)?;
+			session.digests.insert(from, read_array::<32>(reader)?);
+		}
+		let acks: u32 = read(reader)?;
+		for _ in 0..acks {
+			let vote = Vote {
+				digest: read_array::<32>(reader)?,
+				member: read_index(reader)?,
+				signature: Signature(read_point(reader)?),
+			};
+			session.acks.add(vote, &session.committee)?;
+		}
+		for _ in 0..read_len(reader)? {
+			let digest = read_array::<32>(reader)?;
+			let signers = (0..read_len(reader)?)
+				.map(|_| read_index(reader))
+				.collect::<Result<Vec<_>, _>>()?;
+			let signature = Signature(read_point(reader)?);
+			let certificate = QuorumCert { digest, signers, signature };
+			session
+				.certificates
+				.push(OutputCertificate::from_quorum(certificate));
+		}
+
+		session.timeouts = PhaseTimeouts {
+			deal: read(reader)?,
+			ack: read(reader)?,
+			complaint: read(reader)?,
+		};
+		session.clock = read(reader)?;
+		for _ in 0..read_len(reader)? {
+			let phase = read_phase(reader)?;
+			session.started.insert(phase, read(reader)?);
+		}
+		for _ in 0..read_len(reader)? {
+			session.timed_out.push(read_phase(reader)?);
+		}
+		if !reader.is_empty() {
+			return Err(AcssError::InvalidTranscript);
+		}
+
+		session.try_recover()?;
+		Ok(session)
+	}
+}
+
+impl<E: EngineBLS, O: Observer> AcssSession<E, O> {
+	/// checkpoint the session, see `restore`
+	/// the snapshot holds no secret: neither the keypair nor the share
+	/// fails with `InvalidTranscript` if an element cannot be encoded
+	pub fn snapshot(&self) -> Result<Vec<u8>, AcssError> {
+		let mut out = vec![SNAPSHOT_VERSION];
+		write_keys(&self.committee, &mut out)?;
+		out.push(self.params.t());
+		write_keys(&self.dealers, &mut out)?;
+		out.push(self.dealer_params.t());
+		self.header.write(&mut out);
+
+		out.push(self.received.len() as u8);
+		for (from, pok) in &self.received {
+			out.push(from.get());
+			match pok {
+				Some(pok) => {
+					out.push(1);
+					write(pok, &mut out)?;
+				},
+				None => out.push(0),
+			}
+		}
+		out.push(self.digests.len() as u8);
+		for (from, digest) in &self.digests {
+			out.push(from.get());
+			out.extend_from_slice(digest);
+		}
+		let acks: Vec<&Vote<E>> = self.acks.pending().collect();
+		write(&(acks.len() as u32), &mut out)?;
+		for vote in acks {
+			out.extend_from_slice(&vote.digest);
+			out.push(vote.member.get());
+			write(&vote.signature.0, &mut out)?;
+		}
+		out.push(self.certificates.len() as u8);
+		for certificate in &self.certificates {
+			out.extend_from_slice(&certificate.digest);
+			out.push(certificate.signers.len() as u8);
+			out.extend(certificate.signers.iter().map(|index| index.get()));
+			write(&certificate.signature.0, &mut out)?;
+		}
+
+		write(&self.timeouts.deal, &mut out)?;
+		write(&self.timeouts.ack, &mut out)?;
+		write(&self.timeouts.complaint, &mut out)?;
+		write(&self.clock, &mut out)?;
+		out.push(self.started.len() as u8);
+		for (phase, start) in &self.started {
+			out.push(*phase as u8);
+			write(start, &mut out)?;
+		}
+		out.push(self.timed_out.len() as u8);
+		out.extend(self.timed_out.iter().map(|phase| *phase as u8));
+		Ok(out)
+	}
+
+	/// report the progress of the session to `observer`
+	pub fn with_observer<P: Observer>(self, observer: P) -> AcssSession<E, P> {
+		AcssSession {
+			keypair: self.keypair,
+			committee: self.committee,
+			params: self.params,
+			dealers: self.dealers,
+			dealer_params: self.dealer_params,
+			header: self.header,
+			index: self.index,
+			received: self.received,
+			output: self.output,
+			digests: self.digests,
+			acks: self.acks,
+			certificates: self.certificates,
+			timeouts: self.timeouts,
+			clock: self.clock,
+			started: self.started,
+			timed_out: self.timed_out,
+			observer,
+		}
+	}
+
+	/// receive dealings from `dealers` rather than from the committee
+	/// fails with `InvalidParams` if `t` does not fit the dealers
+	///
+	/// * `dealers`: The public keys of the dealers, in share order
+	/// * `t`: The number of valid dealings required to recover
+	pub fn with_dealers<K: CommitteeKey<E>>(
+		mut self,
+		dealers: &[K],
+		t: u8,
+	) -> Result<Self, AcssError> {
+		self.dealer_params = ThresholdParams::for_committee(dealers, t)?;
+		self.dealers = committee_keys(dealers);
+		Ok(self)
+	}
+
+	/// sign and accept dealings for `header` rather than the default header
+	///
+	/// * `header`: The session, epoch and round of the dealings
+	pub fn with_header(mut self, header: MessageHeader) -> Self {
+		self.header = header;
+		self
+	}
+
+	/// enforce deadlines on the phases of the session, starting the deal
+	/// phase at time `now`
+	///
+	/// * `timeouts`: The timeout of each phase
+	/// * `now`: The current time, in the unit of the timeouts
+	pub fn with_timeouts(mut self, timeouts: PhaseTimeouts, now: u64) -> Self {
+		self.timeouts = timeouts;
+		self.clock = now;
+		self.started = BTreeMap::from([(Phase::Deal, now)]);
+		self.timed_out.clear();
+		self.advance();
+		self
+	}
+
+	/// report the current time, emitting every phase that passed its
+	/// deadline since the last call, each only once
+	/// phases that end after this call start at the time it reported
+	///
+	/// * `now`: The current time, in the unit of the timeouts
+	pub fn tick(&mut self, now: u64) -> Vec<Phase> {
+		self.clock = self.clock.max(now);
+		self.advance();
+		let mut expired = Vec::new();
+		// a deal timeout opens the complaint window, which is then checked
+		for phase in [Phase::Deal, Phase::Ack, Phase::Complaint] {
+			if self.deadline(phase).is_some_and(|deadline| deadline <= now) {
+				self.timed_out.push(phase);
+				self.observer.timed_out(phase);
+				expired.push(phase);
+				self.advance();
+			}
+		}
+		expired
+	}
+
+	/// the earliest deadline of a running phase, none if no phase has one
+	pub fn next_deadline(&self) -> Option<u64> {
+		[Phase::Deal, Phase::Ack, Phase::Complaint]
+			.into_iter()
+			.filter_map(|phase| self.deadline(phase))
+			.min()
+	}
+
+	/// the phases that passed their deadline so far, in the order they did
+	pub fn timed_out(&self) -> &[Phase] {
+		&self.timed_out
+	}
+
+	/// the deadline of `phase`, none if it has not started, has ended, has
+	/// already timed out or has no timeout
+	fn deadline(&self, phase: Phase) -> Option<u64> {
+		if self.is_done(phase) || self.timed_out.contains(&phase) {
+			return None;
+		}
+		let start = self.started.get(&phase)?;
+		Some(start.saturating_add(self.timeouts.get(phase)?))
+	}
+
+	/// true once `phase` has completed, which the complaint window never does
+	fn is_done(&self, phase: Phase) -> bool {
+		match phase {
+			Phase::Deal => self.output.is_some(),
+			Phase::Ack =>
+				self.certificates.len() >= self.dealer_params.t() as usize,
+			Phase::Complaint => false,
+		}
+	}
+
+	/// start the phases whose predecessor ended, at the latest reported time
+	fn advance(&mut self) {
+		if !self.started.contains_key(&Phase::Deal) {
+			return;
+		}
+		let clock = self.clock;
+		if self.output.is_some() {
+			self.started.entry(Phase::Ack).or_insert(clock);
+		}
+		if self.output.is_some() || self.timed_out.contains(&Phase::Deal) {
+			self.started.entry(Phase::Complaint).or_insert(clock);
+		}
+	}
+
+	/// the index of this node's share, none if it is not in the committee
+	pub fn index(&self) -> Option<ShareIndex> {
+		self.index
+	}
+
+	/// this node's public key
+	pub fn public_key(&self) -> &PublicKey<E> {
+		&self.keypair.0.public
+	}
+
+	/// this node's keypair
+	#[cfg(feature = "offchain")]
+	pub(crate) fn keypair(&self) -> &Keypair<E> {
+		&self.keypair
+	}
+
+	/// the public keys of the committee, in share order
+	pub fn committee(&self) -> &[PublicKey<E>] {
+		&self.committee
+	}
+
+	/// the public keys of the dealers, in share order
+	pub fn dealers(&self) -> &[PublicKey<E>] {
+		&self.dealers
+	}
+
+	/// deal `secret` to the committee, signed by this node for the header of
+	/// the session
+	///
+	/// * `secret`: The secret to deal, e.g. this node's share when refreshing
+	/// * `rng`: A CSPRNG
+	pub fn deal<R: SecureRng>(
+		&self,
+		secret: &DoubleSecret<E>,
+		rng: R,
+	) -> Result<SignedDealing<E>, AcssError> {
+		self.observer.deal_started(self.params.n(), self.params.t());
+		let resharing = secret.reshare(&self.committee, self.params, rng)?;
+		SignedDealing::sign(self.header, resharing.dealings(), &self.keypair.0)
+	}
+
+	/// process a dealing received from `dealer`
+	/// outputs evidence against the dealer if the dealing does not carry a
+	/// valid share for this node, and recovers the share once enough valid
+	/// dealings have been received. Dealings received after that are ignored.
+	///
+	/// fails with `HeaderMismatch` if the dealing was made for another
+	/// session, epoch or round, with `InvalidSignature` if the dealing is not
+	/// signed by `dealer`, with `InvalidCommittee` if `dealer` is not a dealer
+	/// of the session or this node is not in the committee, and with
+	/// `ReplayedMessage` if the dealer was already heard from
+	///
+	/// * `dealer`: The public key of the dealer
+	/// * `dealing`: The dealing
+	pub fn on_message(
+		&mut self,
+		dealer: &PublicKey<E>,
+		dealing: SignedDealing<E>,
+	) -> Result<Option<MisbehaviorEvidence<E>>, AcssError> {
+		if self.output.is_some() {
+			return Ok(None);
+		}
+		if !dealing.header.same_round(&self.header) {
+			return Err(AcssError::HeaderMismatch);
+		}
+		if !dealing.verify(dealer) {
+			return Err(AcssError::InvalidSignature);
+		}
+		let from = self
+			.dealers
+			.iter()
+			.position(|pk| pk.0 == dealer.0)
+			.and_then(ShareIndex::from_position)
+			.ok_or(AcssError::InvalidCommittee)?;
+		let index = self.index.ok_or(AcssError::InvalidCommittee)?;
+		if self.received.contains_key(&from) {
+			return Err(AcssError::ReplayedMessage);
+		}
+
+		let n = self.params.n();
+		let digest = certificate::dealing_digest(&dealing.transcript);
+		let pok = match decode_compact::<E>(&dealing.transcript) {
+			Ok(dealings) if dealings.len() == n as usize =>
+				dealings.into_iter().nth(index.position()).map(|(_, pok)| pok),
+			_ => None,
+		};
+		let Some(pok) = pok else {
+			self.received.insert(from, None);
+			self.observer.proof_failed(from);
+			let evidence = MisbehaviorEvidence::InvalidJustification {
+				dealer: *dealer,
+				dealing,
+				n,
+			};
+			return Ok(Some(evidence));
+		};
+		if !pok.verify(EncryptionKey(self.keypair.0.public.0)) {
+			self.received.insert(from, None);
+			self.observer.proof_failed(from);
+			let evidence = MisbehaviorEvidence::InvalidShare {
+				dealer: *dealer,
+				dealing,
+				index,
+				recipient: self.keypair.0.public,
+			};
+			return Ok(Some(evidence));
+		}
+		self.received.insert(from, Some(pok));
+		self.digests.insert(from, digest);
+		self.observer.share_verified(from);
+		if let Some(dealers) = self.try_recover()? {
+			self.observer.recovery_completed(dealers);
+		}
+		Ok(None)
+	}
+
+	/// recover this node's share once enough valid dealings were received
+	/// outputs the number of dealings it was recovered from, if it was just
+	/// recovered
+	fn try_recover(&mut self) -> Result<Option<usize>, AcssError> {
+		let Some(index) = self.index else {
+			return Ok(None);
+		};
+		let valid: Vec<(ShareIndex, BatchPoK<E::PublicKeyGroup>)> = self
+			.received
+			.iter()
+			.filter_map(|(from, pok)| pok.clone().map(|pok| (*from, pok)))
+			.collect();
+		if self.output.is_some() ||
+			valid.len() < self.dealer_params.t() as usize
+		{
+			return Ok(None);
+		}
+		let share = self.keypair.recover_indexed(&valid, self.dealer_params)?;
+		let dealers: Vec<ShareIndex> =
+			valid.into_iter().map(|(from, _)| from).collect();
+		let recovered = dealers.len();
+		self.output = Some(SessionOutput { index, share, dealers });
+		self.advance();
+		Ok(Some(recovered))
+	}
+
+	/// this node's share, once recovered
+	pub fn my_share(&self) -> Option<&DoubleSecret<E>> {
+		self.output.as_ref().map(|output| &output.share)
+	}
+
+	/// the output of the session, once this node's share is recovered
+	pub fn output(&self) -> Option<&SessionOutput<E>> {
+		self.output.as_ref()
+	}
+
+	/// this node's acknowledgment of the dealing received from `dealer`, to
+	/// be broadcast to the committee
+	/// none unless the dealing carried a valid share for this node
+	///
+	/// * `dealer`: The public key of the dealer
+	pub fn ack(&self, dealer: &PublicKey<E>) -> Option<ReadyAck<E>> {
+		let from = self
+			.dealers
+			.iter()
+			.position(|pk| pk.0 == dealer.0)
+			.and_then(ShareIndex::from_position)?;
+		let digest = self.digests.get(&from)?;
+		Some(ReadyAck::sign(*digest, self.index?, &self.keypair.0))
+	}
+
+	/// process an acknowledgment of a dealing
+	/// outputs the certificate of the dealing once a quorum of the committee
+	/// acknowledged it. Acknowledgments of certified dealings are ignored.
+	///
+	/// fails with `InvalidSignature` if the acknowledgment is not signed by
+	/// the member of the committee at its index, and with `ReplayedMessage`
+	/// if the member already acknowledged the dealing
+	///
+	/// * `ack`: The acknowledgment
+	pub fn on_ack(
+		&mut self,
+		ack: ReadyAck<E>,
+	) -> Result<Option<&OutputCertificate<E>>, AcssError> {
+		if self.certificates.iter().any(|c| c.digest == ack.digest) {
+			return Ok(None);
+		}
+		let Some(certificate) = self.acks.add(ack.into(), &self.committee)?
+		else {
+			return Ok(None);
+		};
+		self.certificates.push(OutputCertificate::from_quorum(certificate));
+		Ok(self.certificates.last())
+	}
+
+	/// the certificates of the dealings acknowledged by a quorum so far
+	pub fn certificates(&self) -> &[OutputCertificate<E>] {
+		&self.certificates
+	}
+}
+
+/// write a list of at most 255 public keys
+fn write_keys<E: EngineBLS>(
+	keys: &[PublicKey<E>],
+	out: &mut Vec<u8>,
+) -> Result<(), AcssError> {
+	out.push(keys.len() as u8);
+	keys.iter().try_for_each(|pk| write(&pk.0, out))
+}
+
+/// read a list of public keys
+fn read_keys<E: EngineBLS>(
+	reader: &mut &[u8],
+) -> Result<Vec<PublicKey<E>>, AcssError> {
+	(0..read_len(reader)?).map(|_| Ok(PublicKey(read_point(reader)?))).collect()
+}
+
+/// read the length of a list
+fn read_len(reader: &mut &[u8]) -> Result<u8, AcssError> {
+	read_array::<1>(reader).map(|[len]| len)
+}
+
+/// read a share index, rejecting zero
+fn read_index(reader: &mut &[u8]) -> Result<ShareIndex, AcssError> {
+	ShareIndex::new(read_len(reader)?).ok_or(AcssError::InvalidTranscript)
+}
+
+/// read a phase
+fn read_phase(reader: &mut &[u8]) -> Result<Phase, AcssError> {
+	match read_array::<1>(reader)? {
+		[0] => Ok(Phase::Deal),
+		[1] => Ok(Phase::Ack),
+		[2] => Ok(Phase::Complaint),
+		_ => Err(AcssError::InvalidTranscript),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{acss::interpolate, rng::SimulationSeed};
+	use ark_ec::Group;
+	use ark_ff::{One, UniformRand};
+	use ark_std::rand::SeedableRng;
+	use core::cell::RefCell;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	#[derive(Debug, PartialEq)]
+	enum Event {
+		DealStarted(u8, u8),
+		ShareVerified(u8),
+		ProofFailed(u8),
+		RecoveryCompleted(usize),
+		TimedOut(Phase),
+	}
+
+	#[derive(Default)]
+	struct Recorder(RefCell<Vec<Event>>);
+
+	impl Observer for Recorder {
+		fn deal_started(&self, n: u8, t: u8) {
+			self.0.borrow_mut().push(Event::DealStarted(n, t));
+		}
+
+		fn share_verified(&self, dealer: ShareIndex) {
+			self.0.borrow_mut().push(Event::ShareVerified(dealer.get()));
+		}
+
+		fn proof_failed(&self, dealer: ShareIndex) {
+			self.0.borrow_mut().push(Event::ProofFailed(dealer.get()));
+		}
+
+		fn recovery_completed(&self, dealers: usize) {
+			self.0.borrow_mut().push(Event::RecoveryCompleted(dealers));
+		}
+
+		fn timed_out(&self, phase: Phase) {
+			self.0.borrow_mut().push(Event::TimedOut(phase));
+		}
+	}
+
+	fn keypairs(rng: &mut ChaCha20Rng, n: usize) -> Vec<KeypairVT<E>> {
+		(0..n).map(|_| KeypairVT::<E>::generate(&mut *rng)).collect()
+	}
+
+	#[test]
+	fn sessions_recover_a_dealt_secret() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let members = keypairs(&mut rng, 3);
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+
+		let dealing = AcssSession::new(Keypair(dealer.clone()), &committee, 2)
+			.unwrap()
+			.deal(&secret, &mut rng)
+			.unwrap();
+		let mut shares = Vec::new();
+		for kp in &members {
+			let mut session =
+				AcssSession::new(Keypair(kp.clone()), &committee, 2)
+					.unwrap()
+					.with_dealers(&[dealer.public], 1)
+					.unwrap();
+			assert!(session.my_share().is_none());
+			let dealing = SignedDealing::<E> {
+				header: dealing.header,
+				transcript: dealing.transcript.clone(),
+				signature: dealing.signature,
+			};
+			assert!(session
+				.on_message(&dealer.public, dealing)
+				.unwrap()
+				.is_none());
+
+			let output = session.output().unwrap();
+			assert_eq!(Some(output.index), session.index());
+			assert_eq!(output.dealers, [ShareIndex::new(1).unwrap()]);
+			shares.push((output.index.to_scalar(), output.share.0));
+		}
+		assert_eq!(
+			interpolate::<<E as EngineBLS>::SignatureGroup>(&shares[1..]),
+			secret.0
+		);
+	}
+
+	#[test]
+	fn sessions_reject_unexpected_dealings_and_accuse_invalid_ones() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = keypairs(&mut rng, 2);
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let mut session =
+			AcssSession::new(Keypair(members[0].clone()), &committee, 2)
+				.unwrap();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let mut dealings = secret
+			.reshare(&committee, ThresholdParams::new(2, 2).unwrap(), &mut rng)
+			.unwrap()
+			.into_dealings();
+		let sign = |dealings: &[_], dealer: &KeypairVT<E>| {
+			SignedDealing::<E>::sign(MessageHeader::default(), dealings, dealer)
+				.unwrap()
+		};
+
+		let outsider = KeypairVT::<E>::generate(&mut rng);
+		let unknown =
+			session.on_message(&outsider.public, sign(&dealings, &outsider));
+		assert_eq!(unknown.err(), Some(AcssError::InvalidCommittee));
+		let forged =
+			session.on_message(&members[1].public, sign(&dealings, &outsider));
+		assert_eq!(forged.err(), Some(AcssError::InvalidSignature));
+		let next_epoch = SignedDealing::<E>::sign(
+			MessageHeader { epoch: 1, ..MessageHeader::default() },
+			&dealings,
+			&members[1],
+		)
+		.unwrap();
+		let stale = session.on_message(&members[1].public, next_epoch);
+		assert_eq!(stale.err(), Some(AcssError::HeaderMismatch));
+
+		// the proof for this node is made for another key
+		dealings[0].1 = BatchPoK::prove(
+			&[<E as EngineBLS>::Scalar::one(); 2],
+			EncryptionKey(<E as EngineBLS>::PublicKeyGroup::generator()),
+			&mut rng,
+		)
+		.unwrap();
+		let evidence = session
+			.on_message(&members[1].public, sign(&dealings, &members[1]))
+			.unwrap()
+			.unwrap();
+		assert!(evidence.verify());
+		let replayed = session
+			.on_message(&members[1].public, sign(&dealings, &members[1]));
+		assert_eq!(replayed.err(), Some(AcssError::ReplayedMessage));
+		assert!(session.output().is_none());
+	}
+
+	#[test]
+	fn sessions_report_progress_to_their_observer() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = keypairs(&mut rng, 3);
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let recorder = Recorder::default();
+		let mut session =
+			AcssSession::new(Keypair(members[0].clone()), &committee, 2)
+				.unwrap()
+				.with_observer(&recorder);
+
+		let dealing = session.deal(&secret, &mut rng).unwrap();
+		let garbage = SignedDealing::<E>::sign(
+			MessageHeader::default(),
+			&[],
+			&members[1],
+		)
+		.unwrap();
+		assert!(session
+			.on_message(&members[1].public, garbage)
+			.unwrap()
+			.is_some());
+		session.on_message(&members[0].public, dealing).unwrap();
+		let dealing = SignedDealing::<E>::sign(
+			MessageHeader::default(),
+			secret
+				.reshare(&committee, session.params, &mut rng)
+				.unwrap()
+				.dealings(),
+			&members[2],
+		)
+		.unwrap();
+		session.on_message(&members[2].public, dealing).unwrap();
+		assert!(session.my_share().is_some());
+
+		assert_eq!(
+			*recorder.0.borrow(),
+			[
+				Event::DealStarted(3, 2),
+				Event::ProofFailed(2),
+				Event::ShareVerified(1),
+				Event::ShareVerified(3),
+				Event::RecoveryCompleted(2),
+			]
+		);
+	}
+
+	#[test]
+	fn sessions_certify_dealings_acknowledged_by_a_quorum() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = keypairs(&mut rng, 4);
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let mut sessions: Vec<AcssSession<E>> = members
+			.iter()
+			.map(|kp| {
+				AcssSession::new(Keypair(kp.clone()), &committee, 2).unwrap()
+			})
+			.collect();
+		let dealer = &members[0];
+		let dealing = sessions[0].deal(&secret, &mut rng).unwrap();
+
+		let mut acks = Vec::new();
+		for session in sessions.iter_mut() {
+			assert!(session.ack(&dealer.public).is_none());
+			let dealing = SignedDealing::<E> {
+				header: dealing.header,
+				transcript: dealing.transcript.clone(),
+				signature: dealing.signature,
+			};
+			session.on_message(&dealer.public, dealing).unwrap();
+			acks.push(session.ack(&dealer.public).unwrap());
+		}
+
+		let session = &mut sessions[1];
+		let mut misattributed = acks[0];
+		misattributed.member = acks[1].member;
+		assert_eq!(
+			session.on_ack(misattributed).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		assert!(session.on_ack(acks[0]).unwrap().is_none());
+		assert_eq!(
+			session.on_ack(acks[0]).err(),
+			Some(AcssError::ReplayedMessage)
+		);
+		assert!(session.on_ack(acks[1]).unwrap().is_none());
+		let certificate = session.on_ack(acks[2]).unwrap().unwrap();
+		assert!(certificate.verify(&committee));
+
+		assert!(session.on_ack(acks[3]).unwrap().is_none());
+		assert_eq!(session.certificates().len(), 1);
+	}
+
+	#[test]
+	fn sessions_emit_each_timeout_once() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = keypairs(&mut rng, 3);
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let timeouts =
+			PhaseTimeouts { deal: Some(10), ack: Some(5), complaint: Some(20) };
+		let recorder = Recorder::default();
+		let mut session =
+			AcssSession::new(Keypair(members[0].clone()), &committee, 2)
+				.unwrap()
+				.with_observer(&recorder)
+				.with_timeouts(timeouts, 100);
+
+		assert_eq!(session.next_deadline(), Some(110));
+		assert!(session.tick(109).is_empty());
+		assert_eq!(session.tick(110), [Phase::Deal]);
+		// the complaint window opens when dealings are no longer waited for
+		assert_eq!(session.next_deadline(), Some(130));
+		assert!(session.tick(111).is_empty());
+		assert_eq!(session.tick(200), [Phase::Complaint]);
+		assert!(session.tick(300).is_empty());
+		assert_eq!(session.next_deadline(), None);
+		assert_eq!(session.timed_out(), [Phase::Deal, Phase::Complaint]);
+		assert_eq!(
+			*recorder.0.borrow(),
+			[Event::TimedOut(Phase::Deal), Event::TimedOut(Phase::Complaint)]
+		);
+
+		let mut session =
+			AcssSession::new(Keypair(members[0].clone()), &committee, 2)
+				.unwrap();
+		assert!(session.tick(u64::MAX).is_empty());
+		assert_eq!(session.next_deadline(), None);
+	}
+
+	#[test]
+	fn recovery_starts_the_ack_phase_and_complaint_window() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let members = keypairs(&mut rng, 3);
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let dealing = AcssSession::new(Keypair(dealer.clone()), &committee, 2)
+			.unwrap()
+			.deal(&secret, &mut rng)
+			.unwrap();
+		let timeouts =
+			PhaseTimeouts { deal: Some(10), ack: Some(5), complaint: None };
+		let mut session =
+			AcssSession::new(Keypair(members[0].clone()), &committee, 2)
+				.unwrap()
+				.with_dealers(&[dealer.public], 1)
+				.unwrap()
+				.with_timeouts(timeouts, 0);
+
+		assert!(session.tick(3).is_empty());
+		session.on_message(&dealer.public, dealing).unwrap();
+		assert!(session.my_share().is_some());
+		// the deal phase ended, the ack phase started at the last tick
+		assert_eq!(session.next_deadline(), Some(8));
+		assert!(session.tick(7).is_empty());
+		assert_eq!(session.tick(8), [Phase::Ack]);
+		assert_eq!(session.next_deadline(), None);
+		assert_eq!(session.timed_out(), [Phase::Ack]);
+	}
+
+	/// run a resharing among 3 members from one seed, with the third dealer
+	/// corrupting the share of the first member
+	/// outputs the dealings, the evidence raised and the recovered shares
+	fn simulate(
+		seed: SimulationSeed,
+	) -> (Vec<Vec<u8>>, Vec<Vec<u8>>, Vec<<E as EngineBLS>::Scalar>) {
+		let members: Vec<KeypairVT<E>> = (0..3)
+			.map(|i| KeypairVT::<E>::generate(seed.node(i).fork(b"key").rng()))
+			.collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let mut sessions: Vec<AcssSession<E>> = members
+			.iter()
+			.map(|kp| {
+				AcssSession::new(Keypair(kp.clone()), &committee, 2).unwrap()
+			})
+			.collect();
+
+		let mut faults = seed.fork(b"faults").rng();
+		let dealings: Vec<SignedDealing<E>> = sessions
+			.iter()
+			.enumerate()
+			.map(|(i, session)| {
+				let mut rng = seed.node(i).fork(b"deal").rng();
+				let secret = DoubleSecret::<E>(
+					<E as EngineBLS>::Scalar::rand(&mut rng),
+					<E as EngineBLS>::Scalar::rand(&mut rng),
+				);
+				if i < 2 {
+					return session.deal(&secret, &mut rng).unwrap();
+				}
+				let mut dealings = secret
+					.reshare(&committee, session.params, &mut rng)
+					.unwrap()
+					.into_dealings();
+				dealings[0].1 = BatchPoK::prove(
+					&[<E as EngineBLS>::Scalar::rand(&mut faults); 2],
+					EncryptionKey(<E as EngineBLS>::PublicKeyGroup::generator()),
+					&mut faults,
+				)
+				.unwrap();
+				SignedDealing::sign(
+					MessageHeader::default(),
+					&dealings,
+					&members[i],
+				)
+				.unwrap()
+			})
+			.collect();
+
+		let mut evidence = Vec::new();
+		for session in sessions.iter_mut() {
+			for (dealer, dealing) in members.iter().zip(dealings.iter()).rev() {
+				let dealing = SignedDealing::<E> {
+					header: dealing.header,
+					transcript: dealing.transcript.clone(),
+					signature: dealing.signature,
+				};
+				if let Some(e) =
+					session.on_message(&dealer.public, dealing).unwrap()
+				{
+					evidence.push(e.encode().unwrap());
+				}
+			}
+		}
+		let shares = sessions.iter().map(|s| s.my_share().unwrap().0).collect();
+		let transcripts = dealings.into_iter().map(|d| d.transcript).collect();
+		(transcripts, evidence, shares)
+	}
+
+	#[test]
+	fn simulations_are_reproducible_from_one_seed() {
+		let run = simulate(SimulationSeed::from_u64(0));
+		// the first member complains about the third dealer
+		assert_eq!(run.1.len(), 1);
+		assert!(run == simulate(SimulationSeed::from_u64(0)));
+		assert!(run.0 != simulate(SimulationSeed::from_u64(1)).0);
+	}
+
+	#[test]
+	fn sessions_resume_from_snapshots() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members = keypairs(&mut rng, 4);
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|kp| kp.public).collect();
+		let session = |position: usize| {
+			AcssSession::new(Keypair(members[position].clone()), &committee, 2)
+				.unwrap()
+		};
+		let copy = |dealing: &SignedDealing<E>| SignedDealing::<E> {
+			header: dealing.header,
+			transcript: dealing.transcript.clone(),
+			signature: dealing.signature,
+		};
+		let dealings: Vec<SignedDealing<E>> = (0..2)
+			.map(|position| {
+				let secret = DoubleSecret::<E>(
+					<E as EngineBLS>::Scalar::rand(&mut rng),
+					<E as EngineBLS>::Scalar::rand(&mut rng),
+				);
+				session(position).deal(&secret, &mut rng).unwrap()
+			})
+			.collect();
+		let timeouts =
+			PhaseTimeouts { deal: Some(10), ack: Some(10), complaint: Some(5) };
+
+		// restart between the two dealings
+		let mut first = session(1).with_timeouts(timeouts, 0);
+		first.on_message(&members[0].public, copy(&dealings[0])).unwrap();
+		let snapshot = first.snapshot().unwrap();
+		let mut restored =
+			AcssSession::restore(Keypair(members[1].clone()), &snapshot)
+				.unwrap();
+		assert_eq!(restored.snapshot().unwrap(), snapshot);
+		assert_eq!(restored.next_deadline(), Some(10));
+		assert_eq!(
+			restored.on_message(&members[0].public, copy(&dealings[0])).err(),
+			Some(AcssError::ReplayedMessage)
+		);
+		restored.on_message(&members[1].public, copy(&dealings[1])).unwrap();
+		first.on_message(&members[1].public, copy(&dealings[1])).unwrap();
+		assert_eq!(restored.my_share().unwrap().0, first.my_share().unwrap().0);
+
+		// restart between acknowledgments, after the share was recovered
+		let acks: Vec<ReadyAck<E>> = (0..3)
+			.map(|position| {
+				let mut member = session(position);
+				for (dealer, dealing) in members.iter().zip(&dealings) {
+					member.on_message(&dealer.public, copy(dealing)).unwrap();
+				}
+				member.ack(&members[0].public).unwrap()
+			})
+			.collect();
+		first.on_ack(acks[0]).unwrap();
+		first.on_ack(acks[1]).unwrap();
+		first.tick(12);
+		let snapshot = first.snapshot().unwrap();
+		let mut restored =
+			AcssSession::restore(Keypair(members[1].clone()), &snapshot)
+				.unwrap();
+		assert_eq!(restored.my_share().unwrap().1, first.my_share().unwrap().1);
+		assert_eq!(restored.timed_out(), [Phase::Ack, Phase::Complaint]);
+		assert_eq!(
+			restored.on_ack(acks[1]).err(),
+			Some(AcssError::ReplayedMessage)
+		);
+		assert!(restored.on_ack(acks[2]).unwrap().unwrap().verify(&committee));
+
+		assert_eq!(
+			AcssSession::<E>::restore(
+				Keypair(members[1].clone()),
+				&snapshot[1..]
+			)
+			.err(),
+			Some(AcssError::InvalidTranscript)
+		);
+		let trailing = [snapshot.as_slice(), &[0]].concat();
+		assert_eq!(
+			AcssSession::<E>::restore(Keypair(members[1].clone()), &trailing)
+				.err(),
+			Some(AcssError::InvalidTranscript)
+		);
+		// the share cannot be recovered with another member's keypair
+		assert!(AcssSession::<E>::restore(
+			Keypair(members[2].clone()),
+			&snapshot
+		)
+		.is_err());
+	}
+}
@@ -0,0 +1,140 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Shamir Secret Sharing
+//!
+//! Classic, non-verifiable secret splitting over any prime field: [`split`]
+//! evaluates a random polynomial of degree t - 1 with the secret as its
+//! constant term at the share indices 1..=n, and [`reconstruct`]
+//! interpolates it at zero from any t shares.
+//!
+//! Nothing here proves that a share is consistent with the others, so a
+//! single corrupted share silently changes the reconstructed secret. Use
+//! [`crate::acss::HighThresholdACSS`] when the dealer or the shareholders are
+//! not trusted.
+
+use crate::{
+	error::AcssError, keys::ShareIndex, lagrange::lagrange_at_zero,
+	params::ThresholdParams, rng::SecureRng, wipe,
+};
+use ark_ff::PrimeField;
+use ark_poly::{
+	polynomial::univariate::DensePolynomial, DenseUVPolynomial, Polynomial,
+};
+use ark_std::vec::Vec;
+
+/// a share of a secret split with [`split`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ShamirShare<F: PrimeField> {
+	/// the index the polynomial was evaluated at
+	pub index: ShareIndex,
+	/// the evaluation of the polynomial at the index
+	pub value: F,
+}
+
+/// split a secret into `params.n()` shares, any `params.t()` of which
+/// reconstruct it
+///
+/// * `secret`: The secret to split
+/// * `params`: The number of shares and the threshold
+/// * `rng`: Used to sample the coefficients of the polynomial
+pub fn split<F: PrimeField, R: SecureRng>(
+	secret: F,
+	params: ThresholdParams,
+	mut rng: R,
+) -> Vec<ShamirShare<F>> {
+	let mut coeffs: Vec<F> =
+		(0..params.t()).map(|_| F::rand(&mut rng)).collect();
+	coeffs[0] = secret;
+	let mut f = DensePolynomial::from_coefficients_vec(coeffs);
+
+	let shares = params
+		.indices()
+		.map(|index| ShamirShare {
+			index,
+			value: f.evaluate(&index.to_scalar::<F>()),
+		})
+		.collect();
+	wipe(&mut f.coeffs);
+	shares
+}
+
+/// reconstruct a secret from at least `params.t()` of its shares
+/// fails with `InsufficientValidPoK` if there are fewer than t shares, and
+/// with `ShareIndexOutOfRange` or `DuplicateShareIndex` if an index is
+/// greater than n or repeated
+///
+/// * `shares`: The shares, in any order
+/// * `params`: The parameters the secret was split with
+pub fn reconstruct<F: PrimeField>(
+	shares: &[ShamirShare<F>],
+	params: ThresholdParams,
+) -> Result<F, AcssError> {
+	if shares.len() < params.t() as usize {
+		return Err(AcssError::InsufficientValidPoK);
+	}
+	params.check_indices(shares.iter().map(|share| share.index))?;
+
+	let xs: Vec<F> =
+		shares.iter().map(|share| share.index.to_scalar::<F>()).collect();
+	// the indices are distinct and non-zero, so every basis is defined
+	let basis = lagrange_at_zero(&xs).ok_or(AcssError::DuplicateShareIndex)?;
+	Ok(shares.iter().zip(basis).map(|(share, b)| share.value * b).sum())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_bls12_381::Fr;
+	use ark_std::{rand::SeedableRng, UniformRand};
+	use rand_chacha::ChaCha20Rng;
+
+	#[test]
+	fn any_t_shares_reconstruct_the_secret() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let secret = Fr::rand(&mut rng);
+		let params = ThresholdParams::new(5, 3).unwrap();
+		let shares = split(secret, params, &mut rng);
+		assert_eq!(shares.len(), 5);
+
+		for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+			let subset: Vec<_> = subset.iter().map(|&i| shares[i]).collect();
+			assert_eq!(reconstruct(&subset, params), Ok(secret));
+		}
+		assert_eq!(reconstruct(&shares, params), Ok(secret));
+		assert_eq!(
+			reconstruct(&shares[..2], params),
+			Err(AcssError::InsufficientValidPoK)
+		);
+	}
+
+	#[test]
+	fn reconstruction_rejects_unusable_indices() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let mut shares = split(Fr::rand(&mut rng), params, &mut rng);
+
+		shares[1].index = shares[0].index;
+		assert_eq!(
+			reconstruct(&shares, params),
+			Err(AcssError::DuplicateShareIndex)
+		);
+		shares[1].index = ShareIndex::new(4).unwrap();
+		assert_eq!(
+			reconstruct(&shares, params),
+			Err(AcssError::ShareIndexOutOfRange)
+		);
+	}
+}
@@ -0,0 +1,344 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Epoch-scoped Shares
+//!
+//! A [`Share`] is a member's share labelled with its index, the epoch it was
+//! dealt in and the first epoch in which it is expired. Signing, threshold
+//! decryption and recovery through a `Share` take the current epoch and
+//! fail with `ExpiredShare` once the share has expired, so a stale share
+//! leaked from an old backup cannot be replayed into current operations.
+//!
+//! By default a share expires as soon as the next epoch starts, since a
+//! resharing replaces it. Callers that must use an expired share on purpose
+//! (e.g. to finish the resharing that replaces it) opt in with
+//! [`Share::allow_expired`].
+
+use crate::{
+	acss::DoubleSecret,
+	beacon::{partial_sign, BeaconRound, PartialSignature},
+	custody::Custodian,
+	error::AcssError,
+	keys::ShareIndex,
+	lagrange::interpolate,
+	proofs::hashed_el_gamal::Ciphertext,
+	rng::SecureRng,
+	threshold::{partial_decrypt, DecryptionShare},
+	vault::VaultEntry,
+	wipe,
+};
+use ark_std::{collections::BTreeSet, vec::Vec};
+use w3f_bls::EngineBLS;
+
+/// whether operations refuse a share once it has expired
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExpiryPolicy {
+	/// fail with `ExpiredShare` once the share has expired
+	#[default]
+	Enforce,
+	/// use the share regardless of its expiry
+	AllowExpired,
+}
+
+/// a member's share, scoped to the epoch it was dealt in
+pub struct Share<E: EngineBLS> {
+	/// the index of the share in its committee
+	pub index: ShareIndex,
+	/// the epoch the share was dealt in
+	pub epoch: u64,
+	/// the first epoch in which the share is expired
+	pub expires: u64,
+	/// whether the expiry is enforced
+	pub policy: ExpiryPolicy,
+	/// the device holding the share, if recorded
+	pub custodian: Option<Custodian>,
+	/// the share
+	secret: DoubleSecret<E>,
+}
+
+impl<E: EngineBLS> Share<E> {
+	/// a share dealt in `epoch`, expiring when the next epoch starts
+	///
+	/// * `secret`: The share
+	/// * `index`: The index of the share in its committee
+	/// * `epoch`: The epoch the share was dealt in
+	pub fn new(secret: DoubleSecret<E>, index: ShareIndex, epoch: u64) -> Self {
+		Self {
+			index,
+			epoch,
+			expires: epoch.saturating_add(1),
+			policy: ExpiryPolicy::Enforce,
+			custodian: None,
+			secret,
+		}
+	}
+
+	/// the share, expiring at the start of epoch `expires` instead
+	///
+	/// * `expires`: The first epoch in which the share is expired
+	pub fn with_expiry(mut self, expires: u64) -> Self {
+		self.expires = expires;
+		self
+	}
+
+	/// the share, recorded as held by `custodian`
+	///
+	/// * `custodian`: The device holding the share
+	pub fn with_custodian(mut self, custodian: Custodian) -> Self {
+		self.custodian = Some(custodian);
+		self
+	}
+
+	/// the share, usable after it has expired
+	pub fn allow_expired(mut self) -> Self {
+		self.policy = ExpiryPolicy::AllowExpired;
+		self
+	}
+
+	/// true if the share has expired by epoch `now`
+	///
+	/// * `now`: The current epoch
+	pub fn is_expired(&self, now: u64) -> bool {
+		now >= self.expires
+	}
+
+	/// the share, unless it has expired by epoch `now` and the expiry is
+	/// enforced
+	/// fails with `ExpiredShare` if so
+	///
+	/// * `now`: The current epoch
+	pub fn expose(&self, now: u64) -> Result<&DoubleSecret<E>, AcssError> {
+		if self.is_expired(now) && self.policy == ExpiryPolicy::Enforce {
+			return Err(AcssError::ExpiredShare);
+		}
+		Ok(&self.secret)
+	}
+
+	/// sign a round of the beacon with the share, as with
+	/// `beacon::partial_sign`
+	/// fails with `ExpiredShare` if the share has expired by epoch `now`
+	///
+	/// * `now`: The current epoch
+	/// * `round`: The round number
+	/// * `previous`: The previous round, if any
+	pub fn partial_sign(
+		&self,
+		now: u64,
+		round: u64,
+		previous: Option<&BeaconRound<E>>,
+	) -> Result<PartialSignature<E>, AcssError> {
+		partial_sign(self.expose(now)?, self.index, round, previous)
+	}
+
+	/// compute this member's share of the decryption of `ciphertext`, as with
+	/// `threshold::partial_decrypt`
+	/// fails with `ExpiredShare` if the share has expired by epoch `now`
+	///
+	/// * `now`: The current epoch
+	/// * `ciphertext`: The ciphertext to decrypt
+	/// * `rng`: A CSPRNG
+	pub fn partial_decrypt<R: SecureRng, const N: usize>(
+		&self,
+		now: u64,
+		ciphertext: &Ciphertext<E::PublicKeyGroup, N>,
+		rng: R,
+	) -> Result<DecryptionShare<E>, AcssError> {
+		partial_decrypt(self.expose(now)?, self.index, ciphertext, rng)
+	}
+}
+
+impl<E: EngineBLS> From<VaultEntry<E>> for Share<E> {
+	fn from(entry: VaultEntry<E>) -> Self {
+		let VaultEntry { epoch, index, share } = entry;
+		Self::new(share, index, epoch)
+	}
+}
+
+/// recover the shared secret from the shares of t members
+/// fails with `InsufficientValidPoK` if there are no shares, with
+/// `DuplicateShareIndex` if a share appears twice and with `ExpiredShare` if
+/// a share has expired by epoch `now`
+///
+/// * `shares`: The shares of (at least) t members
+/// * `now`: The current epoch
+pub fn recover<E: EngineBLS>(
+	shares: &[Share<E>],
+	now: u64,
+) -> Result<DoubleSecret<E>, AcssError> {
+	if shares.is_empty() {
+		return Err(AcssError::InsufficientValidPoK);
+	}
+	let mut indices = BTreeSet::new();
+	if !shares.iter().all(|share| indices.insert(share.index)) {
+		return Err(AcssError::DuplicateShareIndex);
+	}
+
+	let mut secrets = Vec::with_capacity(shares.len());
+	let mut blinding_secrets = Vec::with_capacity(shares.len());
+	for share in shares {
+		let secret = share.expose(now)?;
+		let x = share.index.to_scalar::<E::Scalar>();
+		secrets.push((x, *secret.expose_secret()));
+		blinding_secrets.push((x, *secret.expose_blinding_secret()));
+	}
+	let secret = DoubleSecret(
+		interpolate::<E::SignatureGroup>(&secrets),
+		interpolate::<E::SignatureGroup>(&blinding_secrets),
+	);
+	secrets
+		.iter_mut()
+		.chain(blinding_secrets.iter_mut())
+		.for_each(|(_, y)| wipe(y));
+	Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::Keypair,
+		beacon::combine,
+		params::ThresholdParams,
+		resharing::Resharing,
+		threshold::{decrypt, encrypt_to_committee, group_key},
+	};
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, PublicKey, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	/// the secret, the resharing and its shares, dealt in epoch 5
+	fn shares(
+		rng: &mut ChaCha20Rng,
+	) -> (DoubleSecret<E>, Resharing<E>, Vec<Share<E>>) {
+		let keypairs: Vec<Keypair<E>> =
+			(0..3).map(|_| Keypair(KeypairVT::generate(&mut *rng))).collect();
+		let committee: Vec<PublicKey<E>> =
+			keypairs.iter().map(|k| *k.public()).collect();
+		let params = ThresholdParams::for_committee(&committee, 2).unwrap();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(rng),
+			<E as EngineBLS>::Scalar::rand(rng),
+		);
+		let resharing = secret.reshare(&committee, params, &mut *rng).unwrap();
+		let shares = keypairs
+			.iter()
+			.zip(resharing.poks())
+			.enumerate()
+			.map(|(position, (keypair, pok))| {
+				let share = keypair
+					.recover(
+						core::slice::from_ref(pok),
+						ThresholdParams::new(1, 1).unwrap(),
+					)
+					.unwrap();
+				Share::new(
+					share,
+					ShareIndex::from_position(position).unwrap(),
+					5,
+				)
+			})
+			.collect();
+		(secret, resharing, shares)
+	}
+
+	#[test]
+	fn live_shares_sign_decrypt_and_recover() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (secret, resharing, shares) = shares(&mut rng);
+		let params = ThresholdParams::new(3, 2).unwrap();
+
+		let recovered = recover(&shares[1..], 5).unwrap();
+		assert_eq!(recovered.0, secret.0);
+		assert_eq!(recovered.1, secret.1);
+
+		let partials: Vec<_> = shares
+			.iter()
+			.map(|s| s.partial_sign(5, 1, None).unwrap())
+			.collect();
+		assert!(combine(&resharing, params, 1, None, &partials).is_ok());
+
+		let group_pk = group_key(&resharing, params).unwrap();
+		let message = [9u8; 32];
+		let ciphertext =
+			encrypt_to_committee::<E, _, 32>(message, &group_pk, &mut rng)
+				.unwrap();
+		let decryption_shares: Vec<_> = shares
+			.iter()
+			.map(|s| s.partial_decrypt(5, &ciphertext, &mut rng).unwrap())
+			.collect();
+		assert_eq!(
+			decrypt(
+				&resharing,
+				params,
+				&group_pk,
+				&ciphertext,
+				&decryption_shares
+			),
+			Ok(message)
+		);
+	}
+
+	#[test]
+	fn expired_shares_are_refused_unless_allowed() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (secret, resharing, shares) = shares(&mut rng);
+		let params = ThresholdParams::new(3, 2).unwrap();
+		let ciphertext = encrypt_to_committee::<E, _, 32>(
+			[9u8; 32],
+			&group_key(&resharing, params).unwrap(),
+			&mut rng,
+		)
+		.unwrap();
+
+		assert!(!shares[0].is_expired(5));
+		assert!(shares[0].is_expired(6));
+		assert_eq!(recover(&shares, 6).err(), Some(AcssError::ExpiredShare));
+		assert_eq!(
+			shares[0].partial_sign(6, 1, None).err(),
+			Some(AcssError::ExpiredShare)
+		);
+		assert_eq!(
+			shares[0].partial_decrypt(6, &ciphertext, &mut rng).err(),
+			Some(AcssError::ExpiredShare)
+		);
+
+		let shares: Vec<Share<E>> = shares
+			.into_iter()
+			.map(|share| share.with_expiry(8).allow_expired())
+			.collect();
+		assert!(shares[0].is_expired(8));
+		let recovered = recover(&shares, 8).unwrap();
+		assert_eq!(recovered.0, secret.0);
+		assert!(shares[0].partial_sign(8, 1, None).is_ok());
+	}
+
+	#[test]
+	fn recovery_rejects_repeated_shares() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let (_, _, mut shares) = shares(&mut rng);
+		shares[1].index = shares[0].index;
+		assert_eq!(
+			recover(&shares, 5).err(),
+			Some(AcssError::DuplicateShareIndex)
+		);
+		assert_eq!(
+			recover::<E>(&[], 5).err(),
+			Some(AcssError::InsufficientValidPoK)
+		);
+	}
+}
@@ -0,0 +1,237 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Share Cache
+//!
+//! A [`ShareStore`] keeps the verified shares a member received, keyed by
+//! session, epoch and dealer, until they expire. A driver inserts a share
+//! once its dealing verifies, asks for the shares of a session and epoch
+//! when it has enough dealings to recover, and calls [`ShareStore::evict`]
+//! as epochs pass. Expired shares are never handed out, whether or not they
+//! have been evicted yet, and are wiped when they are evicted (with the
+//! `zeroize` feature).
+//!
+//! The store lives in memory. Back its shares up with a
+//! [`crate::vault::ShareVault`].
+
+use crate::{error::AcssError, keys::ShareIndex, share::Share};
+use ark_std::collections::BTreeMap;
+use w3f_bls::EngineBLS;
+
+/// where a share belongs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StoreKey {
+	/// the id of the session, e.g. the digest of its committee manifest
+	pub session: [u8; 32],
+	/// the epoch the share was dealt in
+	pub epoch: u64,
+	/// the index of the dealer that dealt it
+	pub dealer: ShareIndex,
+}
+
+/// verified shares, by session, epoch and dealer
+pub struct ShareStore<E: EngineBLS> {
+	shares: BTreeMap<StoreKey, Share<E>>,
+}
+
+impl<E: EngineBLS> Default for ShareStore<E> {
+	fn default() -> Self {
+		Self { shares: BTreeMap::new() }
+	}
+}
+
+impl<E: EngineBLS> ShareStore<E> {
+	/// an empty store
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// store a verified share, outputting the share it replaces, if any
+	/// fails with `ExpiredShare` if the share has expired by epoch `now`,
+	/// and with `InvalidParams` if it was dealt in another epoch than `key`
+	/// names
+	///
+	/// * `key`: The session, epoch and dealer of the share
+	/// * `share`: The share
+	/// * `now`: The current epoch
+	pub fn insert(
+		&mut self,
+		key: StoreKey,
+		share: Share<E>,
+		now: u64,
+	) -> Result<Option<Share<E>>, AcssError> {
+		if share.epoch != key.epoch {
+			return Err(AcssError::InvalidParams);
+		}
+		if share.is_expired(now) {
+			return Err(AcssError::ExpiredShare);
+		}
+		Ok(self.shares.insert(key, share))
+	}
+
+	/// the share stored under `key`, none if there is none or it has
+	/// expired by epoch `now`
+	///
+	/// * `key`: The session, epoch and dealer of the share
+	/// * `now`: The current epoch
+	pub fn get(&self, key: &StoreKey, now: u64) -> Option<&Share<E>> {
+		self.shares.get(key).filter(|share| !share.is_expired(now))
+	}
+
+	/// remove the share stored under `key`
+	pub fn remove(&mut self, key: &StoreKey) -> Option<Share<E>> {
+		self.shares.remove(key)
+	}
+
+	/// the unexpired shares of `session` in `epoch`, by dealer, in dealer
+	/// order
+	///
+	/// * `session`: The id of the session
+	/// * `epoch`: The epoch the shares were dealt in
+	/// * `now`: The current epoch
+	pub fn shares(
+		&self,
+		session: [u8; 32],
+		epoch: u64,
+		now: u64,
+	) -> impl Iterator<Item = (ShareIndex, &Share<E>)> {
+		let start = StoreKey { session, epoch, dealer: ShareIndex::FIRST };
+		self.shares
+			.range(start..)
+			.take_while(move |(key, _)| {
+				key.session == session && key.epoch == epoch
+			})
+			.filter(move |(_, share)| !share.is_expired(now))
+			.map(|(key, share)| (key.dealer, share))
+	}
+
+	/// the number of unexpired shares of `session` in `epoch`, e.g. to tell
+	/// whether enough dealings have been received to recover
+	///
+	/// * `session`: The id of the session
+	/// * `epoch`: The epoch the shares were dealt in
+	/// * `now`: The current epoch
+	pub fn count(&self, session: [u8; 32], epoch: u64, now: u64) -> usize {
+		self.shares(session, epoch, now).count()
+	}
+
+	/// remove every share that has expired by epoch `now`
+	/// outputs the number of shares removed
+	///
+	/// * `now`: The current epoch
+	pub fn evict(&mut self, now: u64) -> usize {
+		let before = self.shares.len();
+		self.shares.retain(|_, share| !share.is_expired(now));
+		before - self.shares.len()
+	}
+
+	/// the number of shares held, including expired ones not yet evicted
+	pub fn len(&self) -> usize {
+		self.shares.len()
+	}
+
+	/// true if the store holds no shares
+	pub fn is_empty(&self) -> bool {
+		self.shares.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::acss::DoubleSecret;
+	use ark_ff::UniformRand;
+	use ark_std::vec::Vec;
+	use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+	type Scalar = <E as EngineBLS>::Scalar;
+
+	fn share(rng: &mut ChaCha20Rng, epoch: u64) -> Share<E> {
+		let secret =
+			DoubleSecret::<E>(Scalar::rand(&mut *rng), Scalar::rand(&mut *rng));
+		Share::new(secret, ShareIndex::new(1).unwrap(), epoch)
+	}
+
+	fn key(session: u8, epoch: u64, dealer: u8) -> StoreKey {
+		StoreKey {
+			session: [session; 32],
+			epoch,
+			dealer: ShareIndex::new(dealer).unwrap(),
+		}
+	}
+
+	#[test]
+	fn store_answers_queries_by_session_and_epoch() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let mut store = ShareStore::<E>::new();
+		for (session, epoch, dealer) in
+			[(1, 5, 3), (1, 5, 1), (1, 6, 2), (2, 5, 1)]
+		{
+			let share = share(&mut rng, epoch);
+			assert!(store
+				.insert(key(session, epoch, dealer), share, epoch)
+				.unwrap()
+				.is_none());
+		}
+
+		let dealers: Vec<ShareIndex> =
+			store.shares([1; 32], 5, 5).map(|(dealer, _)| dealer).collect();
+		assert_eq!(
+			dealers,
+			[ShareIndex::new(1).unwrap(), ShareIndex::new(3).unwrap()]
+		);
+		assert_eq!(store.count([1; 32], 6, 6), 1);
+		assert_eq!(store.count([2; 32], 5, 5), 1);
+		assert_eq!(store.count([3; 32], 5, 5), 0);
+		assert!(store.get(&key(1, 5, 3), 5).is_some());
+		assert!(store.get(&key(1, 5, 2), 5).is_none());
+
+		// a share is stored under the epoch it was dealt in
+		let stale = share(&mut rng, 4);
+		assert_eq!(
+			store.insert(key(1, 5, 2), stale, 5).err(),
+			Some(AcssError::InvalidParams)
+		);
+		assert!(store.remove(&key(2, 5, 1)).is_some());
+		assert_eq!(store.len(), 3);
+	}
+
+	#[test]
+	fn store_evicts_expired_shares() {
+		let mut rng = ChaCha20Rng::seed_from_u64(1);
+		let mut store = ShareStore::<E>::new();
+		store.insert(key(1, 5, 1), share(&mut rng, 5), 5).unwrap();
+		store
+			.insert(key(1, 5, 2), share(&mut rng, 5).with_expiry(8), 5)
+			.unwrap();
+
+		// expired shares are not handed out before they are evicted
+		assert_eq!(store.count([1; 32], 5, 6), 1);
+		assert!(store.get(&key(1, 5, 1), 6).is_none());
+		assert_eq!(store.len(), 2);
+
+		assert_eq!(store.evict(6), 1);
+		assert_eq!(store.len(), 1);
+		assert_eq!(store.evict(8), 1);
+		assert!(store.is_empty());
+
+		assert_eq!(
+			store.insert(key(1, 5, 1), share(&mut rng, 5), 6).err(),
+			Some(AcssError::ExpiredShare)
+		);
+	}
+}
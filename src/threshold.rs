@@ -0,0 +1,419 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Threshold El Gamal
+//!
+//! A dealing doubles as a distributed key generation: the secret it shares is
+//! the decryption key of the committee, and the matching group encryption key
+//! is interpolated from the share public keys ([`group_key`]). Anyone can then
+//! encrypt to the committee ([`encrypt_to_committee`], with the `participant`
+//! feature) with the same hashed El Gamal scheme used for dealings, and any t
+//! members can decrypt together without reconstructing the key.
+//!
+//! Each member publishes a [`DecryptionShare`]: its share times c1, with a
+//! Chaum-Pedersen proof that it used the share committed to in the dealing.
+//! [`decrypt`] checks the proofs and interpolates the shared point from t of
+//! them, which recovers the pad and tag key exactly as a single holder of the
+//! key would.
+
+#[cfg(feature = "participant")]
+use crate::{acss::DoubleSecret, rng::SecureRng, wipe};
+use crate::{
+	error::AcssError,
+	keys::{PublicKey as EncryptionKey, ShareIndex},
+	lagrange::lagrange_at_zero,
+	params::ThresholdParams,
+	proofs::{
+		hashed_el_gamal::{Ciphertext, HashedElGamal, Message},
+		ser::HashWriter,
+	},
+	resharing::Resharing,
+};
+use ark_ec::CurveGroup;
+#[cfg(feature = "participant")]
+use ark_ec::Group;
+use ark_ff::PrimeField;
+#[cfg(feature = "participant")]
+use ark_ff::{UniformRand, Zero};
+use ark_std::{collections::BTreeSet, vec::Vec};
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+use w3f_bls::{DoublePublicKey, EngineBLS};
+
+/// the domain separation label of decryption share proofs
+pub const DLEQ_LABEL: &[u8] = b"acss/threshold/dleq";
+
+/// a proof that log_G(U) = log_c1(D), for the generator G, a share public key
+/// U and a decryption share D
+/// with other bases, a proof that log_g(x) = log_h(y)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DleqProof<C: CurveGroup> {
+	/// the commitment kG
+	pub a: C,
+	/// the commitment k * c1
+	pub b: C,
+	/// the response k + e * u
+	pub z: C::ScalarField,
+}
+
+impl<C: CurveGroup> DleqProof<C> {
+	/// prove that `shared` = `secret` * `c1` for the secret behind `public`
+	#[cfg(feature = "participant")]
+	fn prove<R: SecureRng>(
+		secret: &C::ScalarField,
+		public: &C,
+		c1: &C,
+		shared: &C,
+		rng: R,
+	) -> Result<Self, AcssError> {
+		let statement = [C::generator(), *public, *c1, *shared];
+		Self::prove_with_bases(DLEQ_LABEL, secret, statement, rng)
+	}
+
+	/// true if `shared` = u * `c1` for the u with `public` = uG
+	fn verify(&self, public: &C, c1: &C, shared: &C) -> bool {
+		let statement = [C::generator(), *public, *c1, *shared];
+		self.verify_with_bases(DLEQ_LABEL, statement)
+	}
+
+	/// prove that x = `secret` * g and y = `secret` * h for the statement
+	/// [g, x, h, y], with challenges domain separated by `label`
+	#[cfg(feature = "participant")]
+	pub(crate) fn prove_with_bases<R: SecureRng>(
+		label: &[u8],
+		secret: &C::ScalarField,
+		[g, x, h, y]: [C; 4],
+		mut rng: R,
+	) -> Result<Self, AcssError> {
+		let mut k = C::ScalarField::rand(&mut rng);
+		let (a, b) = (g * k, h * k);
+		let e = challenge(label, &[&g, &x, &h, &y, &a, &b]);
+		let z = k + e? * secret;
+		wipe(&mut k);
+		Ok(Self { a, b, z })
+	}
+
+	/// true if log_g(x) = log_h(y) for the statement [g, x, h, y]
+	pub(crate) fn verify_with_bases(
+		&self,
+		label: &[u8],
+		[g, x, h, y]: [C; 4],
+	) -> bool {
+		let points = [&g, &x, &h, &y, &self.a, &self.b];
+		let Ok(e) = challenge(label, &points) else {
+			return false;
+		};
+		g * self.z == self.a + x * e && h * self.z == self.b + y * e
+	}
+}
+
+/// a member's share of the decryption of a ciphertext
+pub struct DecryptionShare<E: EngineBLS> {
+	/// the index of the member's share
+	pub index: ShareIndex,
+	/// the share times c1
+	pub shared: E::PublicKeyGroup,
+	/// the proof that `shared` was computed with the share at `index`
+	pub proof: DleqProof<E::PublicKeyGroup>,
+}
+
+impl<E: EngineBLS> Clone for DecryptionShare<E> {
+	fn clone(&self) -> Self {
+		Self {
+			index: self.index,
+			shared: self.shared,
+			proof: self.proof.clone(),
+		}
+	}
+}
+
+impl<E: EngineBLS> PartialEq for DecryptionShare<E> {
+	fn eq(&self, other: &Self) -> bool {
+		self.index == other.index &&
+			self.shared == other.shared &&
+			self.proof == other.proof
+	}
+}
+
+impl<E: EngineBLS> DecryptionShare<E> {
+	/// true if the decryption share was computed for `ciphertext` with the
+	/// share committed to by `share_pk`
+	///
+	/// * `share_pk`: The double public key of the share at `self.index`
+	/// * `ciphertext`: The ciphertext being decrypted
+	pub fn verify<const N: usize>(
+		&self,
+		share_pk: &DoublePublicKey<E>,
+		ciphertext: &Ciphertext<E::PublicKeyGroup, N>,
+	) -> bool {
+		self.proof.verify(&share_pk.1, &ciphertext.c1, &self.shared)
+	}
+}
+
+/// the encryption key of the committee a resharing was dealt to, i.e. the
+/// shared secret times the generator
+/// fails with `InvalidParams` if `params` do not match the resharing, and
+/// with `InvalidCommitment` if the share public keys do not lie on a
+/// polynomial of degree t - 1
+///
+/// * `resharing`: The resharing, e.g. decoded from a verified transcript
+/// * `params`: The threshold parameters of the resharing
+pub fn group_key<E: EngineBLS>(
+	resharing: &Resharing<E>,
+	params: ThresholdParams,
+) -> Result<EncryptionKey<E::PublicKeyGroup>, AcssError> {
+	params.check_len(resharing.len())?;
+	let commitments: Vec<E::PublicKeyGroup> =
+		resharing.dealings().iter().map(|(pk, _)| pk.1).collect();
+	let t = params.t() as usize;
+	let xs: Vec<E::Scalar> = (1..=t as u64).map(E::Scalar::from).collect();
+
+	// every other share must lie on the polynomial through the first t
+	for (position, commitment) in commitments.iter().enumerate().skip(t) {
+		let x = E::Scalar::from(position as u64 + 1);
+		let shifted: Vec<E::Scalar> = xs.iter().map(|xi| *xi - x).collect();
+		if interpolate_in_exponent(&commitments[..t], &shifted)? != *commitment
+		{
+			return Err(AcssError::InvalidCommitment);
+		}
+	}
+	Ok(EncryptionKey(interpolate_in_exponent(&commitments[..t], &xs)?))
+}
+
+/// encrypt a message to the committee with encryption key `group_pk`
+/// fails with `Proof(InvalidPublicKey)` if the key is the identity
+///
+/// * `message`: The message
+/// * `group_pk`: The encryption key of the committee (see `group_key`)
+/// * `rng`: A CSPRNG
+#[cfg(feature = "participant")]
+pub fn encrypt_to_committee<E: EngineBLS, R: SecureRng, const N: usize>(
+	message: Message<N>,
+	group_pk: &EncryptionKey<E::PublicKeyGroup>,
+	rng: R,
+) -> Result<Ciphertext<E::PublicKeyGroup, N>, AcssError> {
+	Ok(HashedElGamal::encrypt(message, *group_pk, rng)?)
+}
+
+/// compute this member's share of the decryption of `ciphertext`
+/// fails with `InvalidCiphertext` if c1 is the identity
+///
+/// * `share`: The member's share
+/// * `index`: The index of the member's share
+/// * `ciphertext`: The ciphertext to decrypt
+/// * `rng`: A CSPRNG
+#[cfg(feature = "participant")]
+pub fn partial_decrypt<E: EngineBLS, R: SecureRng, const N: usize>(
+	share: &DoubleSecret<E>,
+	index: ShareIndex,
+	ciphertext: &Ciphertext<E::PublicKeyGroup, N>,
+	rng: R,
+) -> Result<DecryptionShare<E>, AcssError> {
+	if ciphertext.c1.is_zero() {
+		return Err(AcssError::InvalidCiphertext);
+	}
+	let u = share.expose_secret();
+	let public = E::PublicKeyGroup::generator() * u;
+	let shared = ciphertext.c1 * u;
+	let proof = DleqProof::prove(u, &public, &ciphertext.c1, &shared, rng)?;
+	Ok(DecryptionShare { index, shared, proof })
+}
+
+/// decrypt a ciphertext from the decryption shares of at least t members
+/// fails with `ShareIndexOutOfRange` if a share is not of the resharing, with
+/// `DuplicateShareIndex` if a member's share appears twice, with
+/// `InvalidProof` if a share does not verify, with `InsufficientValidPoK` if
+/// there are fewer than t shares, and with `InvalidCiphertext` if the
+/// ciphertext was not encrypted to `group_pk`
+///
+/// * `resharing`: The resharing that generated the committee key
+/// * `params`: The threshold parameters of the resharing
+/// * `group_pk`: The encryption key of the committee
+/// * `ciphertext`: The ciphertext
+/// * `shares`: The decryption shares
+pub fn decrypt<E: EngineBLS, const N: usize>(
+	resharing: &Resharing<E>,
+	params: ThresholdParams,
+	group_pk: &EncryptionKey<E::PublicKeyGroup>,
+	ciphertext: &Ciphertext<E::PublicKeyGroup, N>,
+	shares: &[DecryptionShare<E>],
+) -> Result<Message<N>, AcssError> {
+	params.check_len(resharing.len())?;
+	let mut indices = BTreeSet::new();
+	for share in shares {
+		let share_pk = resharing
+			.share_public_key(share.index)
+			.ok_or(AcssError::ShareIndexOutOfRange)?;
+		if !indices.insert(share.index) {
+			return Err(AcssError::DuplicateShareIndex);
+		}
+		if !share.verify(share_pk, ciphertext) {
+			return Err(AcssError::InvalidProof);
+		}
+	}
+	let t = params.t() as usize;
+	if shares.len() < t {
+		return Err(AcssError::InsufficientValidPoK);
+	}
+
+	let points: Vec<E::PublicKeyGroup> =
+		shares[..t].iter().map(|share| share.shared).collect();
+	let xs: Vec<E::Scalar> =
+		shares[..t].iter().map(|share| share.index.to_scalar()).collect();
+	let shared = interpolate_in_exponent(&points, &xs)?;
+	HashedElGamal::decrypt_with_shared(shared, group_pk, ciphertext)
+		.map_err(|_| AcssError::InvalidCiphertext)
+}
+
+/// interpolate points in the exponent and evaluate the polynomial at zero
+pub(crate) fn interpolate_in_exponent<C: CurveGroup>(
+	points: &[C],
+	xs: &[C::ScalarField],
+) -> Result<C, AcssError> {
+	let lagrange =
+		lagrange_at_zero(xs).ok_or(AcssError::DuplicateShareIndex)?;
+	Ok(points.iter().zip(lagrange).map(|(point, l)| *point * l).sum())
+}
+
+/// the Fiat-Shamir challenge of a DLEQ proof
+fn challenge<C: CurveGroup>(
+	label: &[u8],
+	points: &[&C],
+) -> Result<C::ScalarField, AcssError> {
+	let mut h = Shake256::default();
+	h.update(label);
+	for point in points {
+		point
+			.serialize_compressed(HashWriter(&mut h))
+			.map_err(|_| AcssError::InvalidCiphertext)?;
+	}
+	let mut bytes = [0u8; 64];
+	h.finalize_xof().read(&mut bytes);
+	Ok(C::ScalarField::from_le_bytes_mod_order(&bytes))
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::acss::Keypair;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::{KeypairVT, PublicKey, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	#[test]
+	fn any_t_members_decrypt_messages_to_the_committee() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<Keypair<E>> = (0..5)
+			.map(|_| Keypair(KeypairVT::<E>::generate(&mut rng)))
+			.collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|m| *m.public()).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 3).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut rng).unwrap();
+		let single_dealer = ThresholdParams::new(1, 1).unwrap();
+		let shares: Vec<DoubleSecret<E>> = members
+			.iter()
+			.zip(resharing.poks())
+			.map(|(m, pok)| {
+				m.recover(core::slice::from_ref(pok), single_dealer).unwrap()
+			})
+			.collect();
+
+		let group_pk = group_key(&resharing, params).unwrap();
+		assert_eq!(
+			group_pk.0,
+			<E as EngineBLS>::PublicKeyGroup::generator() * secret.0
+		);
+
+		let message = [7u8; 32];
+		let ciphertext =
+			encrypt_to_committee::<E, _, 32>(message, &group_pk, &mut rng)
+				.unwrap();
+		let decryption_shares: Vec<DecryptionShare<E>> = shares
+			.iter()
+			.enumerate()
+			.map(|(position, share)| {
+				let index = ShareIndex::from_position(position).unwrap();
+				partial_decrypt(share, index, &ciphertext, &mut rng).unwrap()
+			})
+			.collect();
+		let decrypt_with = |shares: &[DecryptionShare<E>]| {
+			decrypt(&resharing, params, &group_pk, &ciphertext, shares)
+		};
+
+		assert_eq!(decrypt_with(&decryption_shares[2..]), Ok(message));
+		assert_eq!(decrypt_with(&decryption_shares[..3]), Ok(message));
+		assert_eq!(
+			decrypt_with(&decryption_shares[..2]),
+			Err(AcssError::InsufficientValidPoK)
+		);
+
+		let mut forged = decryption_shares[..3].to_vec();
+		forged[1].shared = forged[0].shared;
+		assert_eq!(decrypt_with(&forged), Err(AcssError::InvalidProof));
+		let repeated = [0, 1, 1].map(|i| decryption_shares[i].clone());
+		assert_eq!(
+			decrypt_with(&repeated),
+			Err(AcssError::DuplicateShareIndex)
+		);
+
+		let other =
+			encrypt_to_committee::<E, _, 32>(message, &group_pk, &mut rng)
+				.unwrap();
+		assert!(!decryption_shares[0].verify(
+			resharing.share_public_key(decryption_shares[0].index).unwrap(),
+			&other
+		));
+	}
+
+	#[test]
+	fn group_keys_reject_inconsistent_share_public_keys() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let members: Vec<Keypair<E>> = (0..4)
+			.map(|_| Keypair(KeypairVT::<E>::generate(&mut rng)))
+			.collect();
+		let committee: Vec<PublicKey<E>> =
+			members.iter().map(|m| *m.public()).collect();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let params = ThresholdParams::for_committee(&committee, 2).unwrap();
+		let resharing = secret.reshare(&committee, params, &mut rng).unwrap();
+		assert!(group_key(&resharing, params).is_ok());
+
+		let mut dealings = resharing.into_dealings();
+		let (sig, pk) = (dealings[2].0 .0, dealings[2].0 .1);
+		dealings[3].0 = DoublePublicKey(sig, pk);
+		let tampered = Resharing::from_dealings(&committee, dealings).unwrap();
+		assert_eq!(
+			group_key(&tampered, params).err(),
+			Some(AcssError::InvalidCommitment)
+		);
+		let mismatched = ThresholdParams::new(3, 2).unwrap();
+		assert_eq!(
+			group_key(&tampered, mismatched).err(),
+			Some(AcssError::InvalidParams)
+		);
+	}
+}
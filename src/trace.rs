@@ -0,0 +1,174 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Tracing
+//!
+//! With the `tracing` feature, dealing, proof verification, recovery and the
+//! compact transcript encoding run inside `debug` spans carrying the
+//! committee size (and threshold, where there is one), and emit an event with
+//! the elapsed time in microseconds when they finish (with `std`). Per-proof
+//! verdicts are emitted at `trace` level. Without the feature the macros here
+//! expand to nothing.
+
+/// enter a `debug` span for the rest of the enclosing block, emitting the
+/// time spent in it when the block exits
+macro_rules! timed_span {
+	($name:literal $(, $($field:tt)*)?) => {
+		#[cfg(feature = "tracing")]
+		let _timed = $crate::trace::Timed::enter(
+			tracing::debug_span!($name $(, $($field)*)?)
+		);
+	};
+}
+
+/// emit a `trace` level event
+#[cfg(feature = "participant")]
+macro_rules! trace_event {
+	($($arg:tt)*) => {
+		#[cfg(feature = "tracing")]
+		tracing::trace!($($arg)*);
+	};
+}
+
+pub(crate) use timed_span;
+#[cfg(feature = "participant")]
+pub(crate) use trace_event;
+
+/// an entered span that emits its elapsed time when dropped
+#[cfg(feature = "tracing")]
+pub(crate) struct Timed {
+	span: tracing::span::EnteredSpan,
+	#[cfg(feature = "std")]
+	start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl Timed {
+	pub(crate) fn enter(span: tracing::Span) -> Self {
+		Self {
+			span: span.entered(),
+			#[cfg(feature = "std")]
+			start: std::time::Instant::now(),
+		}
+	}
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Timed {
+	fn drop(&mut self) {
+		#[cfg(feature = "std")]
+		tracing::debug!(
+			parent: &*self.span,
+			elapsed_us = self.start.elapsed().as_micros() as u64,
+			"done"
+		);
+		#[cfg(not(feature = "std"))]
+		let _ = &self.span;
+	}
+}
+
+#[cfg(all(test, feature = "tracing", feature = "std"))]
+mod tests {
+	use crate::{
+		acss::{DoubleSecret, HighThresholdACSS},
+		params::ThresholdParams,
+		transcript::{decode_compact, encode_compact},
+	};
+	use ark_ff::UniformRand;
+	use ark_std::{rand::SeedableRng, vec::Vec};
+	use rand_chacha::ChaCha20Rng;
+	use std::sync::{Arc, Mutex};
+	use tracing::{
+		span::{Attributes, Id, Record},
+		Event, Metadata, Subscriber,
+	};
+	use w3f_bls::{EngineBLS, KeypairVT, PublicKey, TinyBLS377};
+
+	type E = TinyBLS377;
+
+	/// records the names of new spans and counts events
+	#[derive(Clone, Default)]
+	struct Recorder {
+		spans: Arc<Mutex<Vec<&'static str>>>,
+		events: Arc<Mutex<usize>>,
+	}
+
+	impl Subscriber for Recorder {
+		fn enabled(&self, _: &Metadata<'_>) -> bool {
+			true
+		}
+
+		fn new_span(&self, span: &Attributes<'_>) -> Id {
+			let mut spans = self.spans.lock().unwrap();
+			spans.push(span.metadata().name());
+			Id::from_u64(spans.len() as u64)
+		}
+
+		fn record(&self, _: &Id, _: &Record<'_>) {}
+
+		fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+		fn event(&self, _: &Event<'_>) {
+			*self.events.lock().unwrap() += 1;
+		}
+
+		fn enter(&self, _: &Id) {}
+
+		fn exit(&self, _: &Id) {}
+	}
+
+	#[test]
+	fn dealing_recovery_and_serialization_are_traced() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|kp| kp.public).collect();
+		let params = ThresholdParams::for_committee(&committee, 2).unwrap();
+		let secret = DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+
+		let recorder = Recorder::default();
+		tracing::subscriber::with_default(recorder.clone(), || {
+			let resharing =
+				secret.reshare(&committee, params, &mut rng).unwrap();
+			let bytes = encode_compact(resharing.dealings()).unwrap();
+			decode_compact::<E>(&bytes).unwrap();
+			let poks: Vec<_> = resharing.poks().cloned().collect();
+			// the first member's pok stands in for three dealers
+			let sk = crate::keys::SecretKey::new(keys[0].secret.0);
+			HighThresholdACSS::<E>::recover(
+				&sk,
+				&[poks[0].clone(), poks[0].clone(), poks[0].clone()],
+				params,
+			)
+			.unwrap();
+		});
+
+		assert_eq!(
+			*recorder.spans.lock().unwrap(),
+			[
+				"acss::deal",
+				"acss::encode_compact",
+				"acss::decode_compact",
+				"acss::recover"
+			]
+		);
+		// a timing event per span, one per dealt share and two verified poks
+		assert_eq!(*recorder.events.lock().unwrap(), 4 + 3 + 2);
+	}
+}
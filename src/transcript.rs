@@ -0,0 +1,887 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Compact Transcript Encoding
+//!
+//! A bandwidth oriented encoding for resharings. Compared to the canonical
+//! (ark-serialize) encoding it:
+//!    1) compresses every point
+//!    2) drops the per-entry length prefixes, since every entry carries exactly
+//!       two ciphertexts
+//!    3) stores the ciphertext `c1` of each slot (share and blinding share)
+//!       once when it is shared by every entry in the transcript, as produced
+//!       by the shared ephemeral dealing mode
+//!
+//! Layout: `version || flags || n || [c1_1 || c1_2] || entry_1 || ... ||
+//! entry_n` where each entry is `pk.0 || pk.1 || s || t || z || commitment ||
+//! ct_1 || ct_2 || [detection tag]` and each ciphertext is `[c1] || c2 ||
+//! tag`.
+//!
+//! Entries of a dealing made with detection tags can carry them, so that
+//! each recipient finds its own entries with [`detect_entries`] by comparing
+//! tags instead of trial decrypting every entry.
+//!
+//! Every entry has the same length, so [`verify_recipient_share`] can seek to
+//! and verify a single recipient's entry without decoding the others.
+//!
+//! A [`SignedDealing`] pairs the encoding with an optional BLS signature by
+//! the dealer, so that a transcript relayed through untrusted gossip can be
+//! attributed to its dealer before it is processed. The signature also covers
+//! the [`MessageHeader`] (session, epoch and round) the dealing was made for,
+//! so a dealing cannot be replayed into another round, and two signed dealings
+//! only conflict when they were made for the same round.
+//!
+//! A [`BlindedDealing`] instead pairs it with a linkable ring signature over
+//! the allowed dealers, for deployments where who dealt must stay private:
+//! anyone can check that some allowed dealer made the dealing and that two
+//! dealings were made by the same dealer, but not which dealer it was. The
+//! encoding itself carries no dealer key.
+
+#[cfg(feature = "participant")]
+use crate::rng::SecureRng;
+use crate::{
+	error::AcssError,
+	keys::{CommitteeKey, PublicKey as EncryptionKey, ShareIndex},
+	messages::MessageHeader,
+	proofs::{
+		hashed_el_gamal::{Ciphertext, DetectionTag},
+		hashed_el_gamal_sigma::BatchPoK,
+	},
+	ring::{Ring, RingSignature},
+	trace::timed_span,
+};
+#[cfg(feature = "participant")]
+use crate::{keys::SecretKey, proofs::hashed_el_gamal::detection_tag};
+use ark_ec::CurveGroup;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{vec::Vec, Zero};
+#[cfg(feature = "participant")]
+use w3f_bls::KeypairVT;
+use w3f_bls::{DoublePublicKey, EngineBLS, Message, PublicKey, Signature};
+
+/// the version of the compact encoding
+pub const COMPACT_VERSION: u8 = 2;
+/// set when the ciphertexts in each slot share the same c1 across all entries
+const FLAG_SHARED_C1: u8 = 0b0000_0001;
+/// set when each entry is followed by the detection tag of its recipient
+const FLAG_DETECTION_TAGS: u8 = 0b0000_0010;
+/// the length of a detection tag
+const DETECTION_TAG_LEN: usize = core::mem::size_of::<DetectionTag>();
+/// the number of ciphertexts carried by each batched PoK
+const CIPHERTEXTS_PER_ENTRY: usize = 2;
+/// the signing context of dealer signatures
+pub const DEALER_SIGNATURE_CONTEXT: &[u8] = b"acss/dealing";
+/// the signing context of the ring signatures of blinded dealings
+pub const BLINDED_DEALER_CONTEXT: &[u8] = b"acss/blinded-dealing";
+
+/// a resharing in the compact encoding, optionally signed by its dealer
+pub struct SignedDealing<E: EngineBLS> {
+	/// the session, epoch and round the dealing was made for
+	pub header: MessageHeader,
+	/// the compact encoding of the resharing
+	pub transcript: Vec<u8>,
+	/// the dealer's signature over the header and the transcript
+	pub signature: Option<Signature<E>>,
+}
+
+impl<E: EngineBLS> SignedDealing<E> {
+	/// encode a resharing without signing it
+	///
+	/// * `header`: The session, epoch and round of the dealing
+	/// * `resharing`: The output of a resharing
+	pub fn unsigned(
+		header: MessageHeader,
+		resharing: &[(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)],
+	) -> Result<Self, AcssError> {
+		Ok(Self {
+			header,
+			transcript: encode_compact(resharing)?,
+			signature: None,
+		})
+	}
+
+	/// encode a resharing and sign the encoding, together with the header,
+	/// with the dealer's key
+	///
+	/// * `header`: The session, epoch and round of the dealing
+	/// * `resharing`: The output of a resharing
+	/// * `dealer`: The dealer's keypair
+	#[cfg(feature = "participant")]
+	pub fn sign(
+		header: MessageHeader,
+		resharing: &[(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)],
+		dealer: &KeypairVT<E>,
+	) -> Result<Self, AcssError> {
+		let transcript = encode_compact(resharing)?;
+		let signature = dealer.sign(&signing_message(&header, &transcript));
+		Ok(Self { header, transcript, signature: Some(signature) })
+	}
+
+	/// true if the dealing is signed by `dealer`
+	///
+	/// * `dealer`: The public key of the expected dealer
+	pub fn verify<K: CommitteeKey<E>>(&self, dealer: &K) -> bool {
+		self.signature.as_ref().is_some_and(|signature| {
+			signature.verify(
+				&signing_message(&self.header, &self.transcript),
+				&dealer.public_key(),
+			)
+		})
+	}
+
+	/// verify the dealer's signature and only then decode the resharing
+	/// fails with `InvalidSignature` if the dealing is unsigned or was not
+	/// signed by `dealer`
+	///
+	/// * `dealer`: The public key of the expected dealer
+	pub fn open<K: CommitteeKey<E>>(
+		&self,
+		dealer: &K,
+	) -> Result<Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>, AcssError>
+	{
+		if !self.verify(dealer) {
+			return Err(AcssError::InvalidSignature);
+		}
+		decode_compact(&self.transcript)
+	}
+
+	/// decode the resharing without checking who dealt it
+	pub fn open_unauthenticated(
+		&self,
+	) -> Result<Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>, AcssError>
+	{
+		decode_compact(&self.transcript)
+	}
+}
+
+/// a resharing in the compact encoding, signed by an unidentified member of
+/// the allowed dealers
+pub struct BlindedDealing<E: EngineBLS> {
+	/// the compact encoding of the resharing
+	pub transcript: Vec<u8>,
+	/// the ring signature over the transcript
+	pub signature: RingSignature<E>,
+}
+
+impl<E: EngineBLS> BlindedDealing<E> {
+	/// encode a resharing and ring sign the encoding over the allowed
+	/// dealers
+	/// fails with `InvalidCommittee` if the dealer is not allowed
+	///
+	/// * `resharing`: The output of a resharing
+	/// * `dealer`: The dealer's keypair
+	/// * `dealers`: The ring of allowed dealers
+	/// * `rng`: A CSPRNG
+	#[cfg(feature = "participant")]
+	pub fn sign<R: SecureRng>(
+		resharing: &[(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)],
+		dealer: &KeypairVT<E>,
+		dealers: &Ring<E>,
+		rng: R,
+	) -> Result<Self, AcssError> {
+		let transcript = encode_compact(resharing)?;
+		let signature = RingSignature::sign(
+			BLINDED_DEALER_CONTEXT,
+			&transcript,
+			dealers,
+			dealer,
+			rng,
+		)?;
+		Ok(Self { transcript, signature })
+	}
+
+	/// true if the dealing was signed by one of `dealers`
+	///
+	/// * `dealers`: The ring of allowed dealers
+	pub fn verify(&self, dealers: &Ring<E>) -> bool {
+		self.signature.verify(BLINDED_DEALER_CONTEXT, &self.transcript, dealers)
+	}
+
+	/// verify the ring signature and only then decode the resharing
+	/// fails with `InvalidSignature` if the dealing was not signed by one of
+	/// `dealers`
+	///
+	/// * `dealers`: The ring of allowed dealers
+	pub fn open(
+		&self,
+		dealers: &Ring<E>,
+	) -> Result<Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>, AcssError>
+	{
+		if !self.verify(dealers) {
+			return Err(AcssError::InvalidSignature);
+		}
+		decode_compact(&self.transcript)
+	}
+
+	/// true if both dealings were signed by the same dealer
+	pub fn links(&self, other: &Self) -> bool {
+		self.signature.links(&other.signature)
+	}
+}
+
+/// the message signed by a dealer for a transcript: the fixed length header
+/// followed by the transcript
+fn signing_message(header: &MessageHeader, transcript: &[u8]) -> Message {
+	let mut bytes = Vec::new();
+	header.write(&mut bytes);
+	bytes.extend_from_slice(transcript);
+	Message::new(DEALER_SIGNATURE_CONTEXT, &bytes)
+}
+
+/// encode a resharing using the compact encoding
+///
+/// * `resharing`: The output of a resharing
+pub fn encode_compact<E: EngineBLS>(
+	resharing: &[(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)],
+) -> Result<Vec<u8>, AcssError> {
+	encode(resharing, None)
+}
+
+/// encode a resharing using the compact encoding, following each entry with
+/// the detection tag of its recipient
+/// fails with `InvalidTranscript` unless there is one tag per entry
+///
+/// * `resharing`: The output of a resharing
+/// * `tags`: The detection tags, see `Resharing::detection_tags`
+pub fn encode_compact_with_tags<E: EngineBLS>(
+	resharing: &[(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)],
+	tags: &[DetectionTag],
+) -> Result<Vec<u8>, AcssError> {
+	if tags.len() != resharing.len() {
+		return Err(AcssError::InvalidTranscript);
+	}
+	encode(resharing, Some(tags))
+}
+
+fn encode<E: EngineBLS>(
+	resharing: &[(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)],
+	tags: Option<&[DetectionTag]>,
+) -> Result<Vec<u8>, AcssError> {
+	timed_span!("acss::encode_compact", n = resharing.len());
+	let n: u8 =
+		resharing.len().try_into().map_err(|_| AcssError::InvalidCommittee)?;
+	if resharing
+		.iter()
+		.any(|(_, pok)| pok.ciphertexts.len() != CIPHERTEXTS_PER_ENTRY)
+	{
+		return Err(AcssError::InvalidCiphertext);
+	}
+
+	let shared_c1 = resharing.first().and_then(|(_, first)| {
+		resharing
+			.iter()
+			.all(|(_, pok)| {
+				pok.ciphertexts
+					.iter()
+					.zip(first.ciphertexts.iter())
+					.all(|(a, b)| a.c1 == b.c1)
+			})
+			.then_some(&first.ciphertexts)
+	});
+	let mut flags = if shared_c1.is_some() { FLAG_SHARED_C1 } else { 0 };
+	if tags.is_some() {
+		flags |= FLAG_DETECTION_TAGS;
+	}
+
+	let mut out = vec![COMPACT_VERSION, flags, n];
+	for ct in shared_c1.into_iter().flatten() {
+		write(&ct.c1, &mut out)?;
+	}
+	for (position, (pk, pok)) in resharing.iter().enumerate() {
+		write(&pk.0, &mut out)?;
+		write(&pk.1, &mut out)?;
+		write(&pok.s, &mut out)?;
+		write(&pok.t, &mut out)?;
+		write(&pok.z, &mut out)?;
+		write(&pok.commitment, &mut out)?;
+		for ct in &pok.ciphertexts {
+			if shared_c1.is_none() {
+				write(&ct.c1, &mut out)?;
+			}
+			out.extend_from_slice(&ct.c2);
+			out.extend_from_slice(&ct.tag);
+		}
+		if let Some(tags) = tags {
+			out.extend_from_slice(&tags[position]);
+		}
+	}
+	Ok(out)
+}
+
+/// decode a resharing from the compact encoding
+/// all points are validated and trailing bytes are rejected
+///
+/// * `bytes`: The compact encoding of a resharing
+pub fn decode_compact<E: EngineBLS>(
+	mut bytes: &[u8],
+) -> Result<Vec<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>)>, AcssError> {
+	timed_span!("acss::decode_compact", bytes = bytes.len());
+	let reader = &mut bytes;
+	let (n, shared_c1, tagged) = read_header::<E>(reader)?;
+
+	let mut resharing = Vec::with_capacity(n as usize);
+	for _ in 0..n {
+		resharing.push(read_entry::<E>(reader, shared_c1, tagged)?);
+	}
+
+	if !reader.is_empty() {
+		return Err(AcssError::InvalidTranscript);
+	}
+	Ok(resharing)
+}
+
+/// decode only the entry dealt to the share at `index` from the compact
+/// encoding, skipping the other entries without decoding them
+/// fails with `ShareIndexOutOfRange` if the transcript has fewer than `index`
+/// entries, and with `InvalidTranscript` if its length does not match its
+/// header
+///
+/// * `bytes`: The compact encoding of a resharing
+/// * `index`: The index of the share
+pub fn decode_compact_entry<E: EngineBLS>(
+	mut bytes: &[u8],
+	index: ShareIndex,
+) -> Result<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>), AcssError> {
+	let reader = &mut bytes;
+	let (n, shared_c1, tagged) = read_header::<E>(reader)?;
+	let entry_len = compact_entry_len::<E>(shared_c1.is_some(), tagged);
+	if reader.len() != n as usize * entry_len {
+		return Err(AcssError::InvalidTranscript);
+	}
+	if index.get() > n {
+		return Err(AcssError::ShareIndexOutOfRange);
+	}
+
+	*reader = &reader[index.position() * entry_len..];
+	read_entry::<E>(reader, shared_c1, tagged)
+}
+
+/// verify the entry of a compact transcript dealt to a single recipient,
+/// without decoding the rest of the transcript
+/// outputs the share public key and PoK of the entry
+///
+/// fails as `decode_compact_entry` does, and with `InvalidProof` if the PoK
+/// does not verify against the recipient's key
+///
+/// * `transcript`: The compact encoding of a resharing
+/// * `recipient`: The public key of the recipient
+/// * `index`: The index of the recipient's share
+pub fn verify_recipient_share<E: EngineBLS>(
+	transcript: &[u8],
+	recipient: &PublicKey<E>,
+	index: ShareIndex,
+) -> Result<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>), AcssError> {
+	let (pk, pok) = decode_compact_entry::<E>(transcript, index)?;
+	if !pok.verify(EncryptionKey(recipient.0)) {
+		return Err(AcssError::InvalidProof);
+	}
+	Ok((pk, pok))
+}
+
+/// find the entries of a compact transcript dealt to the holder of `sk` by
+/// their detection tags rather than by trial decryption
+/// outputs the share indices of the entries, in order
+///
+/// a transcript whose c1s are shared costs one scalar multiplication and a
+/// hash per entry, any other one scalar multiplication per entry
+///
+/// fails with `InvalidTranscript` if the transcript is malformed or its
+/// entries are not tagged
+///
+/// * `bytes`: The compact encoding of a resharing, with detection tags
+/// * `sk`: The secret key of the recipient
+#[cfg(feature = "participant")]
+pub fn detect_entries<E: EngineBLS>(
+	mut bytes: &[u8],
+	sk: &SecretKey<E::PublicKeyGroup>,
+) -> Result<Vec<ShareIndex>, AcssError> {
+	let reader = &mut bytes;
+	let (n, shared_c1, tagged) = read_header::<E>(reader)?;
+	let entry_len = compact_entry_len::<E>(shared_c1.is_some(), tagged);
+	if !tagged || reader.len() != n as usize * entry_len {
+		return Err(AcssError::InvalidTranscript);
+	}
+
+	let pk = sk.public_key().0;
+	let expected = |c1: &E::PublicKeyGroup| {
+		let mut shared = *c1 * sk.expose_secret();
+		let tag = detection_tag(&shared, c1, &pk);
+		crate::wipe(&mut shared);
+		tag.map_err(AcssError::Proof)
+	};
+	let shared_tag = shared_c1.map(|[c1, _]| expected(&c1)).transpose()?;
+
+	let mut found = Vec::new();
+	for (position, entry) in reader.chunks_exact(entry_len).enumerate() {
+		let tag = &entry[entry_len - DETECTION_TAG_LEN..];
+		let matches = match shared_tag {
+			Some(shared_tag) => shared_tag[..] == *tag,
+			None => {
+				let c1 = read_point(&mut &entry[compact_c1_offset::<E>()..])?;
+				expected(&c1)?[..] == *tag
+			},
+		};
+		if matches {
+			found.push(
+				ShareIndex::from_position(position)
+					.ok_or(AcssError::InvalidTranscript)?,
+			);
+		}
+	}
+	Ok(found)
+}
+
+/// read the number of entries of a compact transcript and whether its c1s
+/// are shared, checking the length against the header without decoding any
+/// point
+pub(crate) fn read_compact_shape<E: EngineBLS>(
+	bytes: &[u8],
+) -> Result<(usize, bool), AcssError> {
+	let reader = &mut &bytes[..];
+	let [n, flags] = read_flags(reader)?;
+	let shared_c1 = flags & FLAG_SHARED_C1 != 0;
+	let tagged = flags & FLAG_DETECTION_TAGS != 0;
+	let point = E::PublicKeyGroup::zero().compressed_size();
+	let header = if shared_c1 { CIPHERTEXTS_PER_ENTRY * point } else { 0 };
+	if reader.len() !=
+		header + n as usize * compact_entry_len::<E>(shared_c1, tagged)
+	{
+		return Err(AcssError::InvalidTranscript);
+	}
+	Ok((n as usize, shared_c1))
+}
+
+/// read the version, checking it, and output the number of entries and the
+/// flags, rejecting unknown flags
+fn read_flags(reader: &mut &[u8]) -> Result<[u8; 2], AcssError> {
+	let [version, flags, n] = read_array::<3>(reader)?;
+	if version != COMPACT_VERSION ||
+		flags & !(FLAG_SHARED_C1 | FLAG_DETECTION_TAGS) != 0
+	{
+		return Err(AcssError::InvalidTranscript);
+	}
+	Ok([n, flags])
+}
+
+/// read the version, flags, number of entries and shared c1s (if any), and
+/// whether the entries carry detection tags
+fn read_header<E: EngineBLS>(
+	reader: &mut &[u8],
+) -> Result<(u8, Option<[E::PublicKeyGroup; 2]>, bool), AcssError> {
+	let [n, flags] = read_flags(reader)?;
+
+	let shared_c1 = if flags & FLAG_SHARED_C1 != 0 {
+		Some([read_point::<E::PublicKeyGroup>(reader)?, read_point(reader)?])
+	} else {
+		None
+	};
+	Ok((n, shared_c1, flags & FLAG_DETECTION_TAGS != 0))
+}
+
+/// read one entry, taking the c1s from `shared_c1` if they are shared and
+/// skipping its detection tag if `tagged`
+fn read_entry<E: EngineBLS>(
+	reader: &mut &[u8],
+	shared_c1: Option<[E::PublicKeyGroup; 2]>,
+	tagged: bool,
+) -> Result<(DoublePublicKey<E>, BatchPoK<E::PublicKeyGroup>), AcssError> {
+	let pk = DoublePublicKey::<E>(read_point(reader)?, read_point(reader)?);
+	let s = read(reader)?;
+	let t = read(reader)?;
+	let z = read(reader)?;
+	let commitment = read(reader)?;
+	let mut ciphertexts = Vec::with_capacity(CIPHERTEXTS_PER_ENTRY);
+	for slot in 0..CIPHERTEXTS_PER_ENTRY {
+		let c1 = match shared_c1 {
+			Some(c1s) => c1s[slot],
+			None => read_point(reader)?,
+		};
+		let c2 = read_array::<32>(reader)?;
+		let tag = read_array::<16>(reader)?;
+		ciphertexts.push(Ciphertext { c1, c2, tag });
+	}
+	if tagged {
+		read_array::<DETECTION_TAG_LEN>(reader)?;
+	}
+	Ok((pk, BatchPoK { s, t, z, commitment, ciphertexts }))
+}
+
+/// the length of an encoded entry, which only depends on the curves, on
+/// whether the c1s are shared and on whether entries are tagged
+fn compact_entry_len<E: EngineBLS>(shared_c1: bool, tagged: bool) -> usize {
+	let point = E::PublicKeyGroup::zero().compressed_size();
+	let ciphertext = if shared_c1 { 0 } else { point } + 32 + 16;
+	compact_c1_offset::<E>() +
+		CIPHERTEXTS_PER_ENTRY * ciphertext +
+		if tagged { DETECTION_TAG_LEN } else { 0 }
+}
+
+/// the offset of the first ciphertext within an entry
+fn compact_c1_offset<E: EngineBLS>() -> usize {
+	E::SignatureGroup::zero().compressed_size() +
+		4 * E::PublicKeyGroup::zero().compressed_size() +
+		E::Scalar::zero().compressed_size()
+}
+
+pub(crate) fn write<T: CanonicalSerialize>(
+	value: &T,
+	out: &mut Vec<u8>,
+) -> Result<(), AcssError> {
+	value.serialize_compressed(out).map_err(|_| AcssError::InvalidTranscript)
+}
+
+pub(crate) fn read<T: CanonicalDeserialize>(
+	reader: &mut &[u8],
+) -> Result<T, AcssError> {
+	T::deserialize_compressed(reader).map_err(|_| AcssError::InvalidTranscript)
+}
+
+/// read a point, rejecting the identity (public keys and c1s are never the
+/// identity in an honest transcript)
+pub(crate) fn read_point<C: CurveGroup>(
+	reader: &mut &[u8],
+) -> Result<C, AcssError> {
+	let point: C = read(reader)?;
+	if point.is_zero() {
+		return Err(AcssError::InvalidTranscript);
+	}
+	Ok(point)
+}
+
+pub(crate) fn read_array<const N: usize>(
+	reader: &mut &[u8],
+) -> Result<[u8; N], AcssError> {
+	if reader.len() < N {
+		return Err(AcssError::InvalidTranscript);
+	}
+	let (head, tail) = reader.split_at(N);
+	*reader = tail;
+	head.try_into().map_err(|_| AcssError::InvalidTranscript)
+}
+
+#[cfg(all(test, feature = "participant"))]
+mod tests {
+	use super::*;
+	use crate::{
+		acss::{DealingMode, HighThresholdACSS},
+		params::ThresholdParams,
+	};
+	use ark_ff::UniformRand;
+	use ark_std::{rand::SeedableRng, Zero};
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	fn resharing(
+		n: usize,
+		mode: DealingMode,
+	) -> Vec<(DoublePublicKey<E>, BatchPoK<<E as EngineBLS>::PublicKeyGroup>)>
+	{
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let committee: Vec<PublicKey<E>> =
+			(0..n).map(|_| KeypairVT::<E>::generate(&mut rng).public).collect();
+		HighThresholdACSS::<E>::reshare_with_mode(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			&committee,
+			ThresholdParams::for_committee(&committee, 2).unwrap(),
+			mode,
+			&mut rng,
+		)
+		.unwrap()
+		.into_dealings()
+	}
+
+	fn assert_same(
+		a: &[(
+			DoublePublicKey<E>,
+			BatchPoK<<E as EngineBLS>::PublicKeyGroup>,
+		)],
+		b: &[(
+			DoublePublicKey<E>,
+			BatchPoK<<E as EngineBLS>::PublicKeyGroup>,
+		)],
+	) {
+		assert_eq!(a.len(), b.len());
+		for (x, y) in a.iter().zip(b.iter()) {
+			assert_eq!(x.0 .0, y.0 .0);
+			assert_eq!(x.0 .1, y.0 .1);
+			assert_eq!(x.1, y.1);
+		}
+	}
+
+	#[test]
+	fn compact_encoding_round_trips() {
+		for mode in [DealingMode::PerRecipient, DealingMode::SharedEphemeral] {
+			let resharing = resharing(3, mode);
+			let bytes = encode_compact::<E>(&resharing).unwrap();
+			let decoded = decode_compact::<E>(&bytes).unwrap();
+			assert_same(&resharing, &decoded);
+		}
+	}
+
+	#[test]
+	fn compact_encoding_is_smaller_than_canonical() {
+		let resharing = resharing(3, DealingMode::PerRecipient);
+		let mut canonical = Vec::new();
+		for (pk, pok) in &resharing {
+			pk.serialize_compressed(&mut canonical).unwrap();
+			pok.serialize_compressed(&mut canonical).unwrap();
+		}
+		let compact = encode_compact::<E>(&resharing).unwrap();
+		assert!(compact.len() < canonical.len());
+	}
+
+	#[test]
+	fn compact_encoding_stores_shared_c1s_once() {
+		let per_recipient =
+			encode_compact::<E>(&resharing(3, DealingMode::PerRecipient))
+				.unwrap();
+		let resharing = resharing(3, DealingMode::SharedEphemeral);
+		let shared = encode_compact::<E>(&resharing).unwrap();
+
+		let c1_size = resharing[0].1.ciphertexts[0].c1.compressed_size();
+		assert_eq!(per_recipient[1], 0);
+		assert_eq!(shared[1], FLAG_SHARED_C1);
+		assert_eq!(per_recipient.len() - shared.len(), (6 - 2) * c1_size);
+	}
+
+	#[test]
+	fn compact_decoding_rejects_malformed_input() {
+		let bytes =
+			encode_compact::<E>(&resharing(2, DealingMode::PerRecipient))
+				.unwrap();
+
+		let truncated = &bytes[..bytes.len() - 1];
+		assert_eq!(
+			decode_compact::<E>(truncated).err(),
+			Some(AcssError::InvalidTranscript)
+		);
+
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		assert_eq!(
+			decode_compact::<E>(&trailing).err(),
+			Some(AcssError::InvalidTranscript)
+		);
+
+		let mut bad_version = bytes;
+		bad_version[0] = COMPACT_VERSION + 1;
+		assert_eq!(
+			decode_compact::<E>(&bad_version).err(),
+			Some(AcssError::InvalidTranscript)
+		);
+	}
+
+	#[test]
+	fn recipients_verify_their_own_entry() {
+		// the committee dealt to by `resharing`
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+
+		for mode in [DealingMode::SharedEphemeral, DealingMode::PerRecipient] {
+			let resharing = resharing(3, mode);
+			let bytes = encode_compact::<E>(&resharing).unwrap();
+			for (position, kp) in keys.iter().enumerate() {
+				let index = ShareIndex::from_position(position).unwrap();
+				let (pk, pok) =
+					verify_recipient_share::<E>(&bytes, &kp.public, index)
+						.unwrap();
+				assert_eq!(
+					(pk.0, pk.1),
+					(resharing[position].0 .0, resharing[position].0 .1)
+				);
+				assert_eq!(pok, resharing[position].1);
+			}
+
+			let first = ShareIndex::new(1).unwrap();
+			assert_eq!(
+				verify_recipient_share::<E>(&bytes, &keys[1].public, first)
+					.err(),
+				Some(AcssError::InvalidProof)
+			);
+			assert_eq!(
+				decode_compact_entry::<E>(&bytes, ShareIndex::new(4).unwrap())
+					.err(),
+				Some(AcssError::ShareIndexOutOfRange)
+			);
+			assert_eq!(
+				decode_compact_entry::<E>(&bytes[..bytes.len() - 1], first)
+					.err(),
+				Some(AcssError::InvalidTranscript)
+			);
+		}
+	}
+
+	#[test]
+	fn recipients_detect_their_tagged_entries() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let keys: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let committee: Vec<PublicKey<E>> =
+			keys.iter().map(|kp| kp.public).collect();
+		let secret = crate::acss::DoubleSecret::<E>(
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+			<E as EngineBLS>::Scalar::rand(&mut rng),
+		);
+		let outsider =
+			SecretKey::from(&KeypairVT::<E>::generate(&mut rng).secret);
+
+		for mode in [DealingMode::SharedEphemeral, DealingMode::PerRecipient] {
+			let deal = |tagged: bool| {
+				let builder = secret
+					.builder()
+					.committee(&committee)
+					.threshold(2)
+					.mode(mode)
+					.deterministic([1; 32]);
+				let builder =
+					if tagged { builder.detection_tags() } else { builder };
+				builder.build(ChaCha20Rng::seed_from_u64(0)).unwrap()
+			};
+			let (tagged, untagged) = (deal(true), deal(false));
+			// tagging does not change the dealing
+			assert_same(tagged.dealings(), untagged.dealings());
+			assert!(untagged.detection_tags().is_none());
+
+			let tags = tagged.detection_tags().unwrap();
+			let bytes =
+				encode_compact_with_tags(tagged.dealings(), tags).unwrap();
+			let plain = encode_compact::<E>(untagged.dealings()).unwrap();
+			assert_eq!(bytes.len(), plain.len() + 3 * DETECTION_TAG_LEN);
+			assert_same(
+				&decode_compact::<E>(&bytes).unwrap(),
+				tagged.dealings(),
+			);
+
+			for (position, kp) in keys.iter().enumerate() {
+				let index = ShareIndex::from_position(position).unwrap();
+				let sk = SecretKey::from(&kp.secret);
+				assert_eq!(detect_entries::<E>(&bytes, &sk).unwrap(), [index]);
+				assert!(verify_recipient_share::<E>(&bytes, &kp.public, index)
+					.is_ok());
+			}
+			assert!(detect_entries::<E>(&bytes, &outsider).unwrap().is_empty());
+			assert_eq!(
+				detect_entries::<E>(&plain, &outsider).err(),
+				Some(AcssError::InvalidTranscript)
+			);
+			assert_eq!(
+				encode_compact_with_tags(tagged.dealings(), &tags[1..]).err(),
+				Some(AcssError::InvalidTranscript)
+			);
+		}
+	}
+
+	#[test]
+	fn signed_dealings_are_bound_to_their_dealer() {
+		let mut rng = ChaCha20Rng::seed_from_u64(1);
+		let dealer = KeypairVT::<E>::generate(&mut rng);
+		let other = KeypairVT::<E>::generate(&mut rng);
+		let resharing = resharing(2, DealingMode::SharedEphemeral);
+
+		let header =
+			MessageHeader { session: [7; 32], epoch: 3, round: 1, sequence: 0 };
+		let signed = SignedDealing::sign(header, &resharing, &dealer).unwrap();
+		assert!(signed.verify(&dealer.public));
+		assert_same(&resharing, &signed.open(&dealer.public).unwrap());
+		assert_eq!(
+			signed.open(&other.public).err(),
+			Some(AcssError::InvalidSignature)
+		);
+
+		// the dealing can be moved to neither another round nor another epoch
+		for other in [
+			MessageHeader { round: 2, ..header },
+			MessageHeader { epoch: 4, ..header },
+		] {
+			let moved = SignedDealing::<E> {
+				header: other,
+				transcript: signed.transcript.clone(),
+				signature: signed.signature,
+			};
+			assert!(!moved.verify(&dealer.public));
+		}
+
+		let mut tampered = signed;
+		*tampered.transcript.last_mut().unwrap() ^= 1;
+		assert!(!tampered.verify(&dealer.public));
+
+		let unsigned =
+			SignedDealing::<E>::unsigned(header, &resharing).unwrap();
+		assert_eq!(
+			unsigned.open(&dealer.public).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		assert_same(&resharing, &unsigned.open_unauthenticated().unwrap());
+	}
+
+	#[test]
+	fn blinded_dealings_hide_their_dealer() {
+		let mut rng = ChaCha20Rng::seed_from_u64(1);
+		let dealers: Vec<KeypairVT<E>> =
+			(0..3).map(|_| KeypairVT::<E>::generate(&mut rng)).collect();
+		let keys: Vec<PublicKey<E>> =
+			dealers.iter().map(|kp| kp.public).collect();
+		let ring = Ring::new(&keys).unwrap();
+		let resharing = resharing(2, DealingMode::SharedEphemeral);
+
+		let blinded =
+			BlindedDealing::sign(&resharing, &dealers[1], &ring, &mut rng)
+				.unwrap();
+		assert_same(&resharing, &blinded.open(&ring).unwrap());
+		assert_eq!(
+			blinded.open(&Ring::new(&keys[..2]).unwrap()).err(),
+			Some(AcssError::InvalidSignature)
+		);
+		let mut dealer_key = Vec::new();
+		write(&keys[1].0, &mut dealer_key).unwrap();
+		assert!(!blinded
+			.transcript
+			.windows(dealer_key.len())
+			.any(|window| window == dealer_key));
+
+		// a second dealing by the same dealer is linked to the first
+		let again =
+			BlindedDealing::sign(&resharing, &dealers[1], &ring, &mut rng)
+				.unwrap();
+		let other =
+			BlindedDealing::sign(&resharing, &dealers[2], &ring, &mut rng)
+				.unwrap();
+		assert!(blinded.links(&again));
+		assert!(!blinded.links(&other));
+
+		let mut tampered = other;
+		*tampered.transcript.last_mut().unwrap() ^= 1;
+		assert!(!tampered.verify(&ring));
+	}
+
+	#[test]
+	fn compact_decoding_rejects_identity_c1() {
+		for mode in [DealingMode::SharedEphemeral, DealingMode::PerRecipient] {
+			let mut resharing = resharing(2, mode);
+			for (_, pok) in resharing.iter_mut() {
+				pok.ciphertexts[0].c1 = Zero::zero();
+			}
+			let bytes = encode_compact::<E>(&resharing).unwrap();
+			assert_eq!(
+				decode_compact::<E>(&bytes).err(),
+				Some(AcssError::InvalidTranscript)
+			);
+		}
+	}
+}
@@ -0,0 +1,380 @@
+/*
+ * Copyright 2024 by Ideal Labs, LLC
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Share Backups
+//!
+//! A [`ShareVault`] holds the shares a member recovered, one per session
+//! (identified by the digest of the dealing it was recovered from) and epoch.
+//! [`ShareVault::export`] seals the whole state to a recovery key in one
+//! blob, and [`ShareVault::import`] opens it again, so that every operator
+//! backs up and restores shares the same way.
+//!
+//! The blob is hybrid encrypted. A fresh 32 byte key is sealed to the
+//! recovery key with hashed El Gamal, and the encoded state is masked with a
+//! pad expanded from that key and authenticated by a tag over everything
+//! before it:
+//!
+//! `version || Ciphertext(key) || state (+) pad || tag`
+
+use crate::{
+	acss::DoubleSecret,
+	error::{AcssError, SerializationError},
+	keys::{PublicKey as EncryptionKey, SecretKey, ShareIndex},
+	proofs::hashed_el_gamal::{xor_in_place, Ciphertext, HashedElGamal},
+	rng::SecureRng,
+	wipe,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{collections::BTreeMap, vec::Vec};
+use sha3::{
+	digest::{ExtendableOutput, Update, XofReader},
+	Shake256,
+};
+use subtle::ConstantTimeEq;
+use w3f_bls::EngineBLS;
+
+/// the version of the backup format
+pub const VAULT_VERSION: u8 = 1;
+/// the domain separation label of the pad
+const PAD_LABEL: &[u8] = b"acss/vault/pad";
+/// the domain separation label of the tag
+const TAG_LABEL: &[u8] = b"acss/vault/tag";
+/// the length of the tag
+const TAG_LEN: usize = 32;
+
+/// a share recovered in a session
+pub struct VaultEntry<E: EngineBLS> {
+	/// the epoch the share belongs to
+	pub epoch: u64,
+	/// the index of the share in its committee
+	pub index: ShareIndex,
+	/// the share
+	pub share: DoubleSecret<E>,
+}
+
+/// the shares of a member, by session
+pub struct ShareVault<E: EngineBLS> {
+	entries: BTreeMap<[u8; 32], VaultEntry<E>>,
+}
+
+impl<E: EngineBLS> Default for ShareVault<E> {
+	fn default() -> Self {
+		Self { entries: BTreeMap::new() }
+	}
+}
+
+impl<E: EngineBLS> ShareVault<E> {
+	/// an empty vault
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// store the share recovered in `session`, outputting the share it
+	/// replaces, if any
+	///
+	/// * `session`: The digest of the dealing the share was recovered from
+	/// * `entry`: The share, its index and epoch
+	pub fn insert(
+		&mut self,
+		session: [u8; 32],
+		entry: VaultEntry<E>,
+	) -> Option<VaultEntry<E>> {
+		self.entries.insert(session, entry)
+	}
+
+	/// the share recovered in `session`
+	pub fn get(&self, session: &[u8; 32]) -> Option<&VaultEntry<E>> {
+		self.entries.get(session)
+	}
+
+	/// remove the share recovered in `session`, e.g. once its epoch is over
+	pub fn remove(&mut self, session: &[u8; 32]) -> Option<VaultEntry<E>> {
+		self.entries.remove(session)
+	}
+
+	/// the shares, ordered by session
+	pub fn iter(&self) -> impl Iterator<Item = (&[u8; 32], &VaultEntry<E>)> {
+		self.entries.iter()
+	}
+
+	/// the number of shares
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// true if the vault holds no shares
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// seal the vault to a recovery key
+	/// fails with `Proof(InvalidPublicKey)` if the recovery key is the
+	/// identity, and with `Proof(Serialization(Encoding))` if a share cannot
+	/// be encoded
+	///
+	/// * `recovery_pk`: The public key to restore the vault with
+	/// * `rng`: A CSPRNG
+	pub fn export<R: SecureRng>(
+		&self,
+		recovery_pk: &EncryptionKey<E::PublicKeyGroup>,
+		mut rng: R,
+	) -> Result<Vec<u8>, AcssError> {
+		let mut key = [0u8; 32];
+		rng.fill_bytes(&mut key);
+		let sealed = HashedElGamal::encrypt(key, *recovery_pk, &mut rng);
+		let sealed = match sealed {
+			Ok(sealed) => sealed,
+			Err(e) => {
+				wipe(&mut key);
+				return Err(AcssError::Proof(e));
+			},
+		};
+
+		let mut blob = Vec::from([VAULT_VERSION]);
+		let encoded = match sealed.serialize_compressed(&mut blob) {
+			Ok(()) => {
+				let start = blob.len();
+				self.encode(&mut blob).map(|_| start)
+			},
+			Err(_) => Err(SerializationError::Encoding),
+		};
+		let start = match encoded {
+			Ok(start) => start,
+			Err(e) => {
+				wipe(&mut key);
+				wipe(&mut blob);
+				return Err(AcssError::Proof(e.into()));
+			},
+		};
+		mask(&key, &mut blob[start..]);
+		let tag = tag(&key, &blob);
+		wipe(&mut key);
+		blob.extend_from_slice(&tag);
+		Ok(blob)
+	}
+
+	/// restore a vault sealed by `export`
+	/// fails with `InvalidCiphertext` if the blob is not a vault sealed to
+	/// the recovery key or was modified, and with `InvalidTranscript` if its
+	/// version is not supported
+	///
+	/// * `blob`: The sealed vault
+	/// * `recovery_sk`: The secret key of the recovery key it was sealed to
+	pub fn import(
+		blob: &[u8],
+		recovery_sk: &SecretKey<E::PublicKeyGroup>,
+	) -> Result<Self, AcssError> {
+		let (&version, mut reader) =
+			blob.split_first().ok_or(AcssError::InvalidCiphertext)?;
+		if version != VAULT_VERSION {
+			return Err(AcssError::InvalidTranscript);
+		}
+		let sealed = Ciphertext::<E::PublicKeyGroup>::deserialize_compressed(
+			&mut reader,
+		)
+		.map_err(|_| AcssError::InvalidCiphertext)?;
+		if reader.len() < TAG_LEN {
+			return Err(AcssError::InvalidCiphertext);
+		}
+		let (authenticated, expected) = blob.split_at(blob.len() - TAG_LEN);
+		let start = blob.len() - reader.len();
+
+		let mut key = HashedElGamal::decrypt(recovery_sk, &sealed)
+			.map_err(|_| AcssError::InvalidCiphertext)?;
+		if !bool::from(tag(&key, authenticated)[..].ct_eq(expected)) {
+			wipe(&mut key);
+			return Err(AcssError::InvalidCiphertext);
+		}
+		let mut state = authenticated[start..].to_vec();
+		mask(&key, &mut state);
+		wipe(&mut key);
+		let vault = Self::decode(&state);
+		wipe(&mut state);
+		vault
+	}
+
+	/// append the encoding of the entries to `out`
+	/// fails with `Encoding` if a share cannot be encoded
+	fn encode(&self, out: &mut Vec<u8>) -> Result<(), SerializationError> {
+		out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+		for (session, entry) in &self.entries {
+			out.extend_from_slice(session);
+			out.extend_from_slice(&entry.epoch.to_le_bytes());
+			out.push(entry.index.get());
+			for scalar in [
+				entry.share.expose_secret(),
+				entry.share.expose_blinding_secret(),
+			] {
+				scalar
+					.serialize_compressed(&mut *out)
+					.map_err(|_| SerializationError::Encoding)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// decode entries encoded by `encode`
+	fn decode(mut bytes: &[u8]) -> Result<Self, AcssError> {
+		let invalid = |_| AcssError::InvalidCiphertext;
+		let len = u32::deserialize_compressed(&mut bytes).map_err(invalid)?;
+		let mut vault = Self::new();
+		for _ in 0..len {
+			let session = <[u8; 32]>::deserialize_compressed(&mut bytes)
+				.map_err(invalid)?;
+			let epoch =
+				u64::deserialize_compressed(&mut bytes).map_err(invalid)?;
+			let index =
+				u8::deserialize_compressed(&mut bytes).map_err(invalid)?;
+			let index =
+				ShareIndex::new(index).ok_or(AcssError::InvalidCiphertext)?;
+			let (mut secret, mut blinding) =
+				<(E::Scalar, E::Scalar)>::deserialize_compressed(&mut bytes)
+					.map_err(invalid)?;
+			let share = DoubleSecret(secret, blinding);
+			wipe(&mut secret);
+			wipe(&mut blinding);
+			vault.insert(session, VaultEntry { epoch, index, share });
+		}
+		if !bytes.is_empty() || vault.len() != len as usize {
+			return Err(AcssError::InvalidCiphertext);
+		}
+		Ok(vault)
+	}
+}
+
+/// xor the pad expanded from `key` into `bytes`
+fn mask(key: &[u8; 32], bytes: &mut [u8]) {
+	let mut pad = ark_std::vec![0u8; bytes.len()];
+	let mut h = Shake256::default();
+	h.update(PAD_LABEL);
+	h.update(key);
+	h.finalize_xof().read(&mut pad);
+	xor_in_place(bytes, &pad);
+	wipe(&mut pad);
+}
+
+/// the tag over `authenticated`, keyed by `key`
+fn tag(key: &[u8; 32], authenticated: &[u8]) -> [u8; TAG_LEN] {
+	let mut h = Shake256::default();
+	h.update(TAG_LABEL);
+	h.update(key);
+	h.update(authenticated);
+	let mut tag = [0u8; TAG_LEN];
+	h.finalize_xof().read(&mut tag);
+	tag
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_ff::UniformRand;
+	use ark_std::rand::SeedableRng;
+	use rand_chacha::ChaCha20Rng;
+	use w3f_bls::TinyBLS377;
+
+	type E = TinyBLS377;
+
+	fn vault(rng: &mut ChaCha20Rng) -> ShareVault<E> {
+		let mut vault = ShareVault::new();
+		for (epoch, session) in [(3u64, [1u8; 32]), (4, [2; 32])] {
+			let share = DoubleSecret(
+				<E as EngineBLS>::Scalar::rand(rng),
+				<E as EngineBLS>::Scalar::rand(rng),
+			);
+			let index = ShareIndex::new(epoch as u8).unwrap();
+			vault.insert(session, VaultEntry { epoch, index, share });
+		}
+		vault
+	}
+
+	fn recovery_key(
+		rng: &mut ChaCha20Rng,
+	) -> SecretKey<<E as EngineBLS>::PublicKeyGroup> {
+		SecretKey::new(<E as EngineBLS>::Scalar::rand(rng))
+	}
+
+	#[test]
+	fn vaults_round_trip_through_a_recovery_key() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let vault = vault(&mut rng);
+		let sk = recovery_key(&mut rng);
+		let blob = vault.export(&sk.public_key(), &mut rng).unwrap();
+
+		let restored = ShareVault::<E>::import(&blob, &sk).unwrap();
+		assert_eq!(restored.len(), 2);
+		for ((session, entry), (other, restored)) in
+			vault.iter().zip(restored.iter())
+		{
+			assert_eq!(session, other);
+			assert_eq!(entry.epoch, restored.epoch);
+			assert_eq!(entry.index, restored.index);
+			assert_eq!(
+				entry.share.expose_secret(),
+				restored.share.expose_secret()
+			);
+			assert_eq!(
+				entry.share.expose_blinding_secret(),
+				restored.share.expose_blinding_secret()
+			);
+		}
+
+		let empty = ShareVault::<E>::new().export(&sk.public_key(), &mut rng);
+		assert!(ShareVault::<E>::import(&empty.unwrap(), &sk)
+			.unwrap()
+			.is_empty());
+	}
+
+	#[test]
+	fn import_rejects_other_keys_and_modified_blobs() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let sk = recovery_key(&mut rng);
+		let blob = vault(&mut rng).export(&sk.public_key(), &mut rng).unwrap();
+
+		let other = recovery_key(&mut rng);
+		assert_eq!(
+			ShareVault::<E>::import(&blob, &other).err(),
+			Some(AcssError::InvalidCiphertext)
+		);
+		for position in [1, blob.len() - 40, blob.len() - 1] {
+			let mut modified = blob.clone();
+			modified[position] ^= 1;
+			assert_eq!(
+				ShareVault::<E>::import(&modified, &sk).err(),
+				Some(AcssError::InvalidCiphertext)
+			);
+		}
+		assert_eq!(
+			ShareVault::<E>::import(&blob[..blob.len() - 1], &sk).err(),
+			Some(AcssError::InvalidCiphertext)
+		);
+
+		let mut version = blob.clone();
+		version[0] = VAULT_VERSION + 1;
+		assert_eq!(
+			ShareVault::<E>::import(&version, &sk).err(),
+			Some(AcssError::InvalidTranscript)
+		);
+	}
+
+	#[test]
+	fn export_rejects_the_identity_as_recovery_key() {
+		let mut rng = ChaCha20Rng::seed_from_u64(0);
+		let identity = EncryptionKey(ark_std::Zero::zero());
+		assert_eq!(
+			vault(&mut rng).export(&identity, &mut rng).err(),
+			Some(AcssError::Proof(crate::error::ProofError::InvalidPublicKey))
+		);
+	}
+}